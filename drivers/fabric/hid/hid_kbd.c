@@ -82,7 +82,8 @@ static volatile uint32_t scancode_queue_tail = 0;
 
 void hid_kbd_flush_queue(void)
 {
-    interrupts_disable();
+    /* May run inside a syscall: restore the caller's IRQL, not PASSIVE */
+    irql_t old_irql = set_irql(IRQL_HIGH);
 
     /* Drain any pending bytes from controller output buffer */
     while (kbd_read_status() & 0x01) {
@@ -93,7 +94,7 @@ void hid_kbd_flush_queue(void)
     scancode_queue_tail = 0;
     __asm__ volatile ("" ::: "memory");
 
-    interrupts_enable();
+    (void)set_irql(old_irql);
 }
 
 
@@ -11,7 +11,6 @@
 #include "../../../kernel/net/socket.h"
 #include "../../../kernel/common/heap.h"
 #include "../../../kernel/arch/config.h"
-#include "../../../kernel/arch/paging.h"
 #include "../../../kernel/arch/pmm.h"
 #include "../../../include/common.h"
 #include "../../../include/console.h"
@@ -26,8 +25,6 @@
 #define INTEL_VENDOR_ID   0x8086u
 
 #define E1000_IF_MAX 4
-#define E1000_MMIO_SIZE 0x20000u
-#define E1000_MMIO_VIRT_BASE 0xFFFFFFFFC0000000ULL
 #define E1000_TX_DESC_COUNT 64u
 #define E1000_RX_DESC_COUNT 64u
 #define E1000_RX_BUF_SIZE 2048u
@@ -62,25 +59,26 @@ typedef struct {
 static e1000_slot_t g_slots[E1000_IF_MAX];
 static uint32_t g_next_index = 0;
 
-static int e1000_map_mmio(e1000_slot_t* slot, uint32_t bar0)
+static int e1000_map_mmio(e1000_slot_t* slot, fabric_device_t* dev)
 {
-    if (!slot || (bar0 & 0x1u) != 0u) {
+    if (!slot || !dev) {
         return RDNX_E_INVALID;
     }
 
-    uint64_t phys = (uint64_t)(bar0 & ~0xFu);
-    uint64_t virt = E1000_MMIO_VIRT_BASE + (phys & 0x3FFFFFFFULL);
-    const uint64_t flags = (uint64_t)(PTE_PRESENT | PTE_RW | PTE_PCD);
-
-    for (uint64_t off = 0; off < E1000_MMIO_SIZE; off += 0x1000u) {
-        if (paging_map_page_4kb(virt + off, phys + off, flags) != 0) {
-            return RDNX_E_GENERIC;
-        }
+    /* BAR0 is the register window; Fabric sized and recorded it at enumeration */
+    fabric_resource_t res;
+    if (fabric_resource_get(dev, 0, &res) != RDNX_OK || res.type != FABRIC_RES_MEM) {
+        return RDNX_E_INVALID;
+    }
+    uint64_t size = 0;
+    void* virt = fabric_resource_map(dev, 0, &size);
+    if (!virt) {
+        return RDNX_E_GENERIC;
     }
 
-    slot->mmio_phys = phys;
-    slot->mmio_virt = virt;
-    slot->mmio_size = E1000_MMIO_SIZE;
+    slot->mmio_phys = res.start;
+    slot->mmio_virt = (uint64_t)(uintptr_t)virt;
+    slot->mmio_size = (uint32_t)size;
     return RDNX_OK;
 }
 
@@ -95,7 +93,7 @@ static int e1000_hw_init(e1000_slot_t* slot, fabric_device_t* dev)
         return RDNX_E_INVALID;
     }
 
-    if (e1000_map_mmio(slot, pci->bars[0]) != RDNX_OK) {
+    if (e1000_map_mmio(slot, dev) != RDNX_OK) {
         fabric_log("[E1000] mmio map failed bar0=%x\n", pci->bars[0]);
         return RDNX_E_GENERIC;
     }
@@ -193,11 +191,28 @@ static int e1000_alloc_dma_rings(e1000_slot_t* slot)
     const uint32_t tx_buf_pages = (tx_buf_bytes + 4095u) / 4096u;
     const uint32_t rx_buf_pages = (rx_buf_bytes + 4095u) / 4096u;
 
-    slot->tx_desc_phys = pmm_alloc_pages(tx_desc_pages);
-    slot->rx_desc_phys = pmm_alloc_pages(rx_desc_pages);
-    slot->tx_buf_phys = pmm_alloc_pages(tx_buf_pages);
-    slot->rx_buf_phys = pmm_alloc_pages(rx_buf_pages);
+    /* Physically contiguous DMA memory below 4 GiB */
+    slot->tx_desc_phys = pmm_alloc_pages_in_zone(PMM_ZONE_DMA32, tx_desc_pages);
+    slot->rx_desc_phys = pmm_alloc_pages_in_zone(PMM_ZONE_DMA32, rx_desc_pages);
+    slot->tx_buf_phys = pmm_alloc_pages_in_zone(PMM_ZONE_DMA32, tx_buf_pages);
+    slot->rx_buf_phys = pmm_alloc_pages_in_zone(PMM_ZONE_DMA32, rx_buf_pages);
     if (!slot->tx_desc_phys || !slot->rx_desc_phys || !slot->tx_buf_phys || !slot->rx_buf_phys) {
+        if (slot->tx_desc_phys) {
+            pmm_free_pages(slot->tx_desc_phys, tx_desc_pages);
+        }
+        if (slot->rx_desc_phys) {
+            pmm_free_pages(slot->rx_desc_phys, rx_desc_pages);
+        }
+        if (slot->tx_buf_phys) {
+            pmm_free_pages(slot->tx_buf_phys, tx_buf_pages);
+        }
+        if (slot->rx_buf_phys) {
+            pmm_free_pages(slot->rx_buf_phys, rx_buf_pages);
+        }
+        slot->tx_desc_phys = 0;
+        slot->rx_desc_phys = 0;
+        slot->tx_buf_phys = 0;
+        slot->rx_buf_phys = 0;
         return RDNX_E_NOMEM;
     }
 
@@ -1,6 +1,6 @@
 /**
  * @file ide_storage_stub.c
- * @brief Fabric IDE storage backend (MVP: detect + publish ata0/disk0)
+ * @brief Fabric IDE storage backend (MVP: detect + publish ata0/disk0, ata1/disk1)
  */
 
 #include "../../../kernel/fabric/fabric.h"
@@ -271,47 +271,48 @@ static int ide_storage_attach(fabric_device_t* dev)
     if (!dev) {
         return RDNX_E_INVALID;
     }
+    if (g_slots[0].used) {
+        return RDNX_E_BUSY;
+    }
+    /* Primary channel: master is disk0, the optional slave disk1 (e.g. swap) */
     for (uint32_t i = 0; i < IDE_SLOT_MAX; i++) {
-        if (!g_slots[i].used) {
-            memset(&g_slots[i], 0, sizeof(g_slots[i]));
-            g_slots[i].used = 1;
-            g_slots[i].dev = dev;
-            g_slots[i].ata_name = (i == 0) ? "ata0" : "ata1";
-            g_slots[i].disk_name = (i == 0) ? "disk0" : "disk1";
-            g_slots[i].io_base = (i == 0) ? 0x1F0 : 0x170;
-            g_slots[i].ctrl_base = (i == 0) ? 0x3F6 : 0x376;
-            g_slots[i].drive_head = 0xA0; /* master */
-            g_slots[i].blockops.hdr = RDNX_ABI_INIT(fabric_blockdev_ops_t);
-            g_slots[i].blockops.read_sectors = ide_block_read;
-            g_slots[i].blockops.write_sectors = ide_block_write;
-            g_slots[i].blockdev.hdr = RDNX_ABI_INIT(fabric_blockdev_t);
-            g_slots[i].blockdev.name = g_slots[i].disk_name;
-            g_slots[i].blockdev.sector_size = 512;
-            g_slots[i].blockdev.sector_count = 0;
-            g_slots[i].blockdev.flags = 0;
-            g_slots[i].blockdev.ops = &g_slots[i].blockops;
-            g_slots[i].blockdev.context = &g_slots[i];
-
-            uint64_t sectors = 0;
-            int irc = ide_identify(&g_slots[i], &sectors);
-            if (irc == RDNX_OK) {
-                g_slots[i].present = 1;
-                g_slots[i].blockdev.sector_count = sectors;
-                fabric_log("[IDE] %s: sectors=%llu (%llu MiB)\n",
-                           g_slots[i].disk_name,
-                           (unsigned long long)sectors,
-                           (unsigned long long)((sectors * 512ULL) / (1024ULL * 1024ULL)));
-            } else {
-                g_slots[i].present = 0;
-                fabric_log("[IDE] %s: identify failed rc=%d\n", g_slots[i].disk_name, irc);
-            }
-
-            fabric_log("[IDE] attached %s vendor=%x device=%x\n",
-                       g_slots[i].ata_name, dev->vendor_id, dev->device_id);
-            return RDNX_OK;
+        memset(&g_slots[i], 0, sizeof(g_slots[i]));
+        g_slots[i].used = 1;
+        g_slots[i].dev = dev;
+        g_slots[i].ata_name = (i == 0) ? "ata0" : "ata1";
+        g_slots[i].disk_name = (i == 0) ? "disk0" : "disk1";
+        g_slots[i].io_base = 0x1F0;
+        g_slots[i].ctrl_base = 0x3F6;
+        g_slots[i].drive_head = (i == 0) ? 0xA0 : 0xB0; /* master / slave */
+        g_slots[i].blockops.hdr = RDNX_ABI_INIT(fabric_blockdev_ops_t);
+        g_slots[i].blockops.read_sectors = ide_block_read;
+        g_slots[i].blockops.write_sectors = ide_block_write;
+        g_slots[i].blockdev.hdr = RDNX_ABI_INIT(fabric_blockdev_t);
+        g_slots[i].blockdev.name = g_slots[i].disk_name;
+        g_slots[i].blockdev.sector_size = 512;
+        g_slots[i].blockdev.sector_count = 0;
+        g_slots[i].blockdev.flags = 0;
+        g_slots[i].blockdev.ops = &g_slots[i].blockops;
+        g_slots[i].blockdev.context = &g_slots[i];
+
+        uint64_t sectors = 0;
+        int irc = ide_identify(&g_slots[i], &sectors);
+        if (irc == RDNX_OK) {
+            g_slots[i].present = 1;
+            g_slots[i].blockdev.sector_count = sectors;
+            fabric_log("[IDE] %s: sectors=%llu (%llu MiB)\n",
+                       g_slots[i].disk_name,
+                       (unsigned long long)sectors,
+                       (unsigned long long)((sectors * 512ULL) / (1024ULL * 1024ULL)));
+        } else if (i == 0) {
+            g_slots[i].present = 0;
+            fabric_log("[IDE] %s: identify failed rc=%d\n", g_slots[i].disk_name, irc);
         }
     }
-    return RDNX_E_BUSY;
+
+    fabric_log("[IDE] attached %s vendor=%x device=%x\n",
+               g_slots[0].ata_name, dev->vendor_id, dev->device_id);
+    return RDNX_OK;
 }
 
 static int ide_storage_publish(fabric_device_t* dev)
@@ -319,10 +320,14 @@ static int ide_storage_publish(fabric_device_t* dev)
     if (!dev) {
         return RDNX_E_INVALID;
     }
+    int found = 0;
     for (uint32_t i = 0; i < IDE_SLOT_MAX; i++) {
         if (!g_slots[i].used || g_slots[i].dev != dev) {
             continue;
         }
+        if (i > 0 && !g_slots[i].present) {
+            continue; /* No slave drive */
+        }
         if (fabric_publish_service_node(g_slots[i].ata_name, "storage", dev) != RDNX_OK) {
             return RDNX_E_GENERIC;
         }
@@ -332,9 +337,9 @@ static int ide_storage_publish(fabric_device_t* dev)
         if (g_slots[i].present) {
             (void)fabric_blockdev_register(&g_slots[i].blockdev);
         }
-        return RDNX_OK;
+        found = 1;
     }
-    return RDNX_E_NOTFOUND;
+    return found ? RDNX_OK : RDNX_E_NOTFOUND;
 }
 
 static void ide_storage_detach(fabric_device_t* dev)
@@ -52,6 +52,14 @@ void console_clear(void);
  */
 void console_set_vga_buffer(void* buffer);
 
+/**
+ * Switch output to the bootloader framebuffer (built-in 8x16 font).
+ * VGA text mode stays in use if this fails; serial mirroring is unaffected.
+ * Call after memory init: the framebuffer is mapped through the MMIO window.
+ * @return RDNX_OK or RDNX_E_* (RDNX_E_NOTFOUND: no RGB framebuffer)
+ */
+int console_attach_framebuffer(void);
+
 /* ============================================================================
  * Output functions
  * ============================================================================ */
@@ -128,9 +136,65 @@ void console_reset_color(void);
 /* Log prefix control */
 void console_set_log_prefix_enabled(bool enabled);
 
-/* Uptime (microseconds) */
+/* ============================================================================
+ * Log tail and raw serial (post-mortem)
+ * ============================================================================ */
+
+/**
+ * Total number of characters printed so far
+ */
+uint64_t console_log_position(void);
+
+/**
+ * Copy printed characters starting at an absolute position.
+ * Only the last 16 KiB are kept; older positions are clamped.
+ * @return Number of characters copied
+ */
+size_t console_log_read(uint64_t from, char* out, size_t max);
+
+/**
+ * Write to COM1 only, bypassing the screen and the log
+ */
+void console_serial_write(const char* buf, size_t len);
+
+/* ============================================================================
+ * Serial input (COM1)
+ * ============================================================================ */
+
+/**
+ * Switch COM1 input to the RX interrupt (IRQ4) and a receive ring.
+ * The handler also watches for Enter ~ Ctrl-B and enters the debugger.
+ * @return RDNX_OK, RDNX_E_NOTFOUND without a serial port
+ */
+int console_serial_rx_irq_init(void);
+
+/**
+ * Next received byte, or -1. Raw bytes: no CR/LF translation.
+ */
+int console_serial_getc(void);
+bool console_serial_has_char(void);
+
+/* ============================================================================
+ * COM2 (GDB remote stub)
+ * ============================================================================ */
+
+/* Program COM2 for 115200 8N1; RDNX_E_NOTFOUND if the port is absent */
+int console_serial2_init(void);
+/* Blocking byte write */
+void console_serial2_putc(uint8_t c);
+/* Polled byte read, -1 if none */
+int console_serial2_getc(void);
+/**
+ * Enable the COM2 RX interrupt (IRQ3): a byte arriving while the kernel
+ * runs is handed to gdbstub_break().
+ */
+int console_serial2_rx_irq_init(void);
+
+/* Uptime (microseconds; _ns variants keep the clocksource resolution) */
 uint64_t console_get_uptime_us(void);
+uint64_t console_get_uptime_ns(void);
 const char* console_get_uptime_source(void);
 uint64_t console_get_realtime_us(void);
+uint64_t console_get_realtime_ns(void);
 
 #endif /* _RODNIX_CONSOLE_H */
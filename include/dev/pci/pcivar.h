@@ -6,9 +6,6 @@
 #include "../../../kernel/fabric/bus/pci.h"
 #include "pcireg.h"
 
-#define RODNIX_PCI_CFG_ADDR 0xCF8u
-#define RODNIX_PCI_CFG_DATA 0xCFCu
-
 static inline const pci_device_info_t* pci_get_info(device_t dev)
 {
     if (!dev || !dev->bus_private) {
@@ -69,54 +66,15 @@ static inline uint32_t pci_read_bar(device_t dev, uint32_t bar)
     return p->bars[bar];
 }
 
-static inline uint32_t pci_cfg_addr(device_t dev, uint32_t reg)
-{
-    return (1u << 31) |
-           ((uint32_t)pci_get_bus(dev) << 16) |
-           ((uint32_t)pci_get_slot(dev) << 11) |
-           ((uint32_t)pci_get_function(dev) << 8) |
-           (reg & 0xFCu);
-}
-
+/* Go through the bus: ECAM when available, legacy 0xCF8/0xCFC otherwise */
 static inline uint32_t pci_read_config(device_t dev, uint32_t reg, uint32_t width)
 {
-    uint32_t addr = pci_cfg_addr(dev, reg);
-    uint32_t value = 0;
-    __asm__ volatile ("outl %%eax, %0" : : "Nd"((uint16_t)RODNIX_PCI_CFG_ADDR), "a"(addr));
-    __asm__ volatile ("inl %1, %%eax" : "=a"(value) : "Nd"((uint16_t)RODNIX_PCI_CFG_DATA));
-
-    uint32_t shift = (reg & 0x3u) * 8u;
-    if (width == 1u) {
-        return (value >> shift) & 0xFFu;
-    }
-    if (width == 2u) {
-        shift = (reg & 0x2u) * 8u;
-        return (value >> shift) & 0xFFFFu;
-    }
-    return value;
+    return pci_cfg_read(pci_get_info(dev), (uint16_t)reg, width);
 }
 
 static inline void pci_write_config(device_t dev, uint32_t reg, uint32_t value, uint32_t width)
 {
-    uint32_t addr = pci_cfg_addr(dev, reg);
-    uint32_t cur = 0;
-    __asm__ volatile ("outl %%eax, %0" : : "Nd"((uint16_t)RODNIX_PCI_CFG_ADDR), "a"(addr));
-    __asm__ volatile ("inl %1, %%eax" : "=a"(cur) : "Nd"((uint16_t)RODNIX_PCI_CFG_DATA));
-
-    if (width == 1u) {
-        uint32_t shift = (reg & 0x3u) * 8u;
-        cur &= ~(0xFFu << shift);
-        cur |= (value & 0xFFu) << shift;
-    } else if (width == 2u) {
-        uint32_t shift = (reg & 0x2u) * 8u;
-        cur &= ~(0xFFFFu << shift);
-        cur |= (value & 0xFFFFu) << shift;
-    } else {
-        cur = value;
-    }
-
-    __asm__ volatile ("outl %%eax, %0" : : "Nd"((uint16_t)RODNIX_PCI_CFG_ADDR), "a"(addr));
-    __asm__ volatile ("outl %%eax, %0" : : "Nd"((uint16_t)RODNIX_PCI_CFG_DATA), "a"(cur));
+    pci_cfg_write(pci_get_info(dev), (uint16_t)reg, value, width);
 }
 
 static inline int pci_find_cap(device_t dev, int capability, uint32_t* offset_out)
@@ -56,6 +56,19 @@ void interrupts_disable(void)
     __asm__ volatile ("msr daifset, #2");  /* Set I bit (IRQ) */
 }
 
+uint64_t interrupts_local_save(void)
+{
+    uint64_t daif;
+    __asm__ volatile ("mrs %0, daif" : "=r"(daif));
+    __asm__ volatile ("msr daifset, #2" ::: "memory");
+    return daif;
+}
+
+void interrupts_local_restore(uint64_t state)
+{
+    __asm__ volatile ("msr daif, %0" :: "r"(state) : "memory");
+}
+
 irql_t get_current_irql(void)
 {
     return current_irql;
@@ -15,6 +15,8 @@
 #define ARCH_PAGE_SIZE_1GB X86_64_PAGE_SIZE_1GB
 #define ARCH_PHYS_TO_VIRT(addr) X86_64_PHYS_TO_VIRT(addr)
 #define ARCH_VIRT_TO_PHYS(addr) X86_64_VIRT_TO_PHYS(addr)
+#define ARCH_MMIO_VIRT_BASE X86_64_MMIO_VIRT_BASE
+#define ARCH_MMIO_VIRT_END X86_64_MMIO_VIRT_END
 #define ARCH_USER_CANON_MAX 0x00007FFFFFFFFFFFULL
 #elif defined(__aarch64__) || defined(_M_ARM64)
 #include "arm64/config.h"
@@ -29,6 +31,9 @@
 #elif defined(__riscv) && (__riscv_xlen == 64)
 #include "riscv64/config.h"
 #define ARCH_MACHINE "riscv64"
+#define ARCH_KERNEL_VIRT_BASE RISCV64_KERNEL_VIRT_BASE
+#define ARCH_PHYS_TO_VIRT(addr) RISCV64_PHYS_TO_VIRT(addr)
+#define ARCH_VIRT_TO_PHYS(addr) RISCV64_VIRT_TO_PHYS(addr)
 #define ARCH_USER_CANON_MAX 0x0000FFFFFFFFFFFFULL
 #else
 #error "Unsupported architecture"
@@ -0,0 +1,15 @@
+/**
+ * @file arch/dbreg.h
+ * @brief Common entry point for hardware breakpoint registers.
+ */
+
+#ifndef _RODNIX_ARCH_DBREG_H
+#define _RODNIX_ARCH_DBREG_H
+
+#if defined(__x86_64__) || defined(_M_X64)
+#include "x86_64/dbreg.h"
+#else
+#error "Hardware breakpoints are not wired for this target yet"
+#endif
+
+#endif /* _RODNIX_ARCH_DBREG_H */
@@ -62,6 +62,20 @@ void interrupts_disable(void)
     __asm__ volatile ("csrw sstatus, %0" :: "r"(sstatus));
 }
 
+uint64_t interrupts_local_save(void)
+{
+    uint64_t sstatus;
+    __asm__ volatile ("csrrc %0, sstatus, %1" : "=r"(sstatus) : "r"(1UL << 1) : "memory");
+    return sstatus;
+}
+
+void interrupts_local_restore(uint64_t state)
+{
+    if (state & (1UL << 1)) {
+        __asm__ volatile ("csrs sstatus, %0" :: "r"(1UL << 1) : "memory");
+    }
+}
+
 irql_t get_current_irql(void)
 {
     return current_irql;
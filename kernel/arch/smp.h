@@ -0,0 +1,15 @@
+/**
+ * @file arch/smp.h
+ * @brief Common entry point for multiprocessor bring-up.
+ */
+
+#ifndef _RODNIX_ARCH_SMP_H
+#define _RODNIX_ARCH_SMP_H
+
+#if defined(__x86_64__) || defined(_M_X64)
+#include "x86_64/smp.h"
+#else
+#error "SMP bring-up is not wired for this target yet"
+#endif
+
+#endif /* _RODNIX_ARCH_SMP_H */
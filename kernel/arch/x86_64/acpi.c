@@ -7,6 +7,7 @@
 #include "config.h"
 #include "../../../include/common.h"
 #include "../../../include/console.h"
+#include <stddef.h>
 
 #define ACPI_RSDP_SIGNATURE "RSD PTR "
 #define ACPI_RSDT_SIGNATURE "RSDT"
@@ -29,6 +30,63 @@ struct acpi_root_state {
 
 static struct acpi_root_state g_acpi;
 
+#define ACPI_FADT_SIGNATURE "FACP"
+#define ACPI_DSDT_SIGNATURE "DSDT"
+
+/* Smallest FADT lengths that carry a given field */
+#define ACPI_FADT_LEN_RESET     129U
+#define ACPI_FADT_LEN_X_DSDT    148U
+#define ACPI_FADT_LEN_X_PM1     196U
+
+/* AML opcodes needed to decode the \_S5 package */
+#define AML_ZERO_OP             0x00
+#define AML_ONE_OP              0x01
+#define AML_NAME_OP             0x08
+#define AML_BYTE_PREFIX         0x0A
+#define AML_WORD_PREFIX         0x0B
+#define AML_DWORD_PREFIX        0x0C
+#define AML_PACKAGE_OP          0x12
+#define AML_ROOT_CHAR           0x5C
+
+#define ACPI_SCI_EN_SPIN        1000000U
+#define ACPI_PM_SETTLE_SPIN     10000000U
+
+#define PCI_CONFIG_ADDRESS      0xCF8
+#define PCI_CONFIG_DATA         0xCFC
+
+struct acpi_power_state {
+    const struct acpi_fadt* fadt;
+    uint16_t pm1a_cnt;
+    uint16_t pm1b_cnt;
+    uint16_t slp_typa;
+    uint16_t slp_typb;
+    bool s5_valid;
+};
+
+static struct acpi_power_state g_acpi_pm;
+
+static inline void acpi_outb(uint16_t port, uint8_t value)
+{
+    __asm__ volatile ("outb %0, %1" : : "a"(value), "Nd"(port));
+}
+
+static inline void acpi_outw(uint16_t port, uint16_t value)
+{
+    __asm__ volatile ("outw %0, %1" : : "a"(value), "Nd"(port));
+}
+
+static inline void acpi_outl(uint16_t port, uint32_t value)
+{
+    __asm__ volatile ("outl %0, %1" : : "a"(value), "Nd"(port));
+}
+
+static inline uint16_t acpi_inw(uint16_t port)
+{
+    uint16_t value;
+    __asm__ volatile ("inw %1, %0" : "=a"(value) : "Nd"(port));
+    return value;
+}
+
 static bool acpi_checksum_ok(const void* ptr, size_t len)
 {
     if (!ptr || len == 0) {
@@ -105,6 +163,141 @@ static bool acpi_sdt_header_valid(uint64_t phys,
     return true;
 }
 
+/* PM1 control block as an I/O port: X_ form when present, legacy otherwise */
+static uint16_t acpi_fadt_pm1_port(const struct acpi_fadt* fadt,
+                                   const struct acpi_gas* xblk,
+                                   uint32_t legacy)
+{
+    if (fadt->header.length >= ACPI_FADT_LEN_X_PM1 && xblk->address != 0) {
+        if (xblk->space_id != ACPI_GAS_SPACE_IO || xblk->address > 0xFFFFULL) {
+            return 0;
+        }
+        return (uint16_t)xblk->address;
+    }
+    return legacy <= 0xFFFFU ? (uint16_t)legacy : 0;
+}
+
+/* ZeroOp, OneOp or Byte/Word/DWord constant; returns bytes consumed, 0 on error */
+static uint32_t acpi_aml_read_int(const uint8_t* p, const uint8_t* end, uint32_t* out)
+{
+    if (p >= end) {
+        return 0;
+    }
+    switch (p[0]) {
+    case AML_ZERO_OP:
+        *out = 0;
+        return 1;
+    case AML_ONE_OP:
+        *out = 1;
+        return 1;
+    case AML_BYTE_PREFIX:
+        if (end - p < 2) {
+            return 0;
+        }
+        *out = p[1];
+        return 2;
+    case AML_WORD_PREFIX:
+        if (end - p < 3) {
+            return 0;
+        }
+        *out = (uint32_t)p[1] | ((uint32_t)p[2] << 8);
+        return 3;
+    case AML_DWORD_PREFIX:
+        if (end - p < 5) {
+            return 0;
+        }
+        *out = (uint32_t)p[1] | ((uint32_t)p[2] << 8) |
+               ((uint32_t)p[3] << 16) | ((uint32_t)p[4] << 24);
+        return 5;
+    default:
+        return 0;
+    }
+}
+
+/*
+ * Find "Name(\_S5, Package() { SLP_TYPa, SLP_TYPb, ... })" in the DSDT.
+ * No interpreter: the package is matched by its encoding, which is what
+ * every firmware emits for \_S5 in practice.
+ */
+static bool acpi_dsdt_find_s5(const struct acpi_sdt_header* dsdt,
+                              uint16_t* out_typa,
+                              uint16_t* out_typb)
+{
+    const uint8_t* aml = (const uint8_t*)dsdt + sizeof(*dsdt);
+    const uint8_t* end = (const uint8_t*)dsdt + dsdt->length;
+
+    for (const uint8_t* p = aml + 1; p + 4 < end; p++) {
+        if (memcmp(p, "_S5_", 4) != 0) {
+            continue;
+        }
+        bool named = (p[-1] == AML_NAME_OP) ||
+                     (p[-1] == AML_ROOT_CHAR && p - aml >= 2 && p[-2] == AML_NAME_OP);
+        if (!named || p[4] != AML_PACKAGE_OP) {
+            continue;
+        }
+
+        /* PkgLength: bits 7:6 of the lead byte count the extra bytes */
+        const uint8_t* q = p + 5;
+        if (q >= end) {
+            return false;
+        }
+        q += 1u + (q[0] >> 6);
+        /* NumElements */
+        q += 1;
+
+        uint32_t typa = 0;
+        uint32_t typb = 0;
+        uint32_t n = acpi_aml_read_int(q, end, &typa);
+        if (n == 0) {
+            return false;
+        }
+        q += n;
+        /* Some tables carry only SLP_TYPa */
+        if (acpi_aml_read_int(q, end, &typb) == 0) {
+            typb = typa;
+        }
+        *out_typa = (uint16_t)(typa & 7u);
+        *out_typb = (uint16_t)(typb & 7u);
+        return true;
+    }
+    return false;
+}
+
+static void acpi_power_parse(void)
+{
+    memset(&g_acpi_pm, 0, sizeof(g_acpi_pm));
+
+    const struct acpi_fadt* fadt = (const struct acpi_fadt*)acpi_find_table(ACPI_FADT_SIGNATURE);
+    if (!fadt || fadt->header.length < offsetof(struct acpi_fadt, reset_reg)) {
+        kputs("[ACPI] FADT not found, no ACPI power control\n");
+        return;
+    }
+    g_acpi_pm.fadt = fadt;
+    g_acpi_pm.pm1a_cnt = acpi_fadt_pm1_port(fadt, &fadt->x_pm1a_cnt_blk, fadt->pm1a_cnt_blk);
+    g_acpi_pm.pm1b_cnt = acpi_fadt_pm1_port(fadt, &fadt->x_pm1b_cnt_blk, fadt->pm1b_cnt_blk);
+
+    uint64_t dsdt_phys = fadt->dsdt;
+    if (fadt->header.length >= ACPI_FADT_LEN_X_DSDT && fadt->x_dsdt != 0) {
+        dsdt_phys = fadt->x_dsdt;
+    }
+    struct acpi_sdt_header* dsdt = NULL;
+    if (dsdt_phys == 0 || !acpi_sdt_header_valid(dsdt_phys, ACPI_DSDT_SIGNATURE, &dsdt)) {
+        kputs("[ACPI] DSDT invalid\n");
+    } else if (g_acpi_pm.pm1a_cnt != 0 &&
+               acpi_dsdt_find_s5(dsdt, &g_acpi_pm.slp_typa, &g_acpi_pm.slp_typb)) {
+        g_acpi_pm.s5_valid = true;
+    }
+
+    kprintf("[ACPI] FADT rev=%u pm1a_cnt=%x pm1b_cnt=%x s5=%s slp_typ=%u/%u reset=%s\n",
+            (unsigned)fadt->header.revision,
+            (unsigned)g_acpi_pm.pm1a_cnt,
+            (unsigned)g_acpi_pm.pm1b_cnt,
+            g_acpi_pm.s5_valid ? "yes" : "no",
+            (unsigned)g_acpi_pm.slp_typa,
+            (unsigned)g_acpi_pm.slp_typb,
+            acpi_reset_available() ? "yes" : "no");
+}
+
 int acpi_init(void)
 {
     if (g_acpi.initialized) {
@@ -167,6 +360,8 @@ int acpi_init(void)
         return -1;
     }
 
+    acpi_power_parse();
+
     if (g_acpi.xsdt_phys != 0) {
         kprintf("[ACPI] XSDT ready rev=%u rsdp=%llx xsdt=%llx\n",
                 (unsigned)g_acpi.revision,
@@ -406,3 +601,121 @@ int acpi_madt_get_iso_for_source(uint8_t source, struct acpi_madt_iso_info* out_
 
     return -1;
 }
+
+int acpi_mcfg_get(uint32_t index, struct acpi_mcfg_allocation* out)
+{
+    if (!out) {
+        return -1;
+    }
+
+    const struct acpi_mcfg* mcfg = (const struct acpi_mcfg*)acpi_find_table("MCFG");
+    if (!mcfg || mcfg->header.length < sizeof(*mcfg)) {
+        return -1;
+    }
+
+    uint32_t count = (mcfg->header.length - (uint32_t)sizeof(*mcfg)) /
+                     (uint32_t)sizeof(struct acpi_mcfg_allocation);
+    if (index >= count) {
+        return -1;
+    }
+    const struct acpi_mcfg_allocation* alloc =
+        (const struct acpi_mcfg_allocation*)((const uint8_t*)mcfg + sizeof(*mcfg));
+    *out = alloc[index];
+    return 0;
+}
+
+const struct acpi_fadt* acpi_get_fadt(void)
+{
+    return g_acpi_pm.fadt;
+}
+
+bool acpi_s5_available(void)
+{
+    return g_acpi_pm.s5_valid;
+}
+
+bool acpi_reset_available(void)
+{
+    const struct acpi_fadt* fadt = g_acpi_pm.fadt;
+    if (!fadt || fadt->header.length < ACPI_FADT_LEN_RESET) {
+        return false;
+    }
+    if ((fadt->flags & ACPI_FADT_RESET_REG_SUP) == 0 || fadt->reset_reg.address == 0) {
+        return false;
+    }
+    return fadt->reset_reg.space_id == ACPI_GAS_SPACE_IO ||
+           fadt->reset_reg.space_id == ACPI_GAS_SPACE_PCI_CONFIG;
+}
+
+/* Firmware may boot in legacy mode; SLP_EN is ignored until SCI_EN is set */
+static void acpi_enable_sci(void)
+{
+    const struct acpi_fadt* fadt = g_acpi_pm.fadt;
+    if ((acpi_inw(g_acpi_pm.pm1a_cnt) & ACPI_PM1_CNT_SCI_EN) != 0) {
+        return;
+    }
+    if ((fadt->flags & ACPI_FADT_HW_REDUCED) != 0 ||
+        fadt->smi_cmd == 0 || fadt->smi_cmd > 0xFFFFU || fadt->acpi_enable == 0) {
+        return;
+    }
+    acpi_outb((uint16_t)fadt->smi_cmd, fadt->acpi_enable);
+    for (uint32_t i = 0; i < ACPI_SCI_EN_SPIN; i++) {
+        if ((acpi_inw(g_acpi_pm.pm1a_cnt) & ACPI_PM1_CNT_SCI_EN) != 0) {
+            return;
+        }
+        __asm__ volatile ("pause");
+    }
+    kputs("[ACPI] SCI_EN did not come up\n");
+}
+
+static void acpi_write_slp(uint16_t port, uint16_t slp_typ)
+{
+    uint16_t v = acpi_inw(port);
+    v &= (uint16_t)~(ACPI_PM1_CNT_SLP_TYP_MASK | ACPI_PM1_CNT_SLP_EN);
+    v |= (uint16_t)(slp_typ << ACPI_PM1_CNT_SLP_TYP_SHIFT);
+    acpi_outw(port, v);
+    acpi_outw(port, (uint16_t)(v | ACPI_PM1_CNT_SLP_EN));
+}
+
+int acpi_enter_s5(void)
+{
+    if (!g_acpi_pm.s5_valid) {
+        return -1;
+    }
+
+    acpi_enable_sci();
+    acpi_write_slp(g_acpi_pm.pm1a_cnt, g_acpi_pm.slp_typa);
+    if (g_acpi_pm.pm1b_cnt != 0) {
+        acpi_write_slp(g_acpi_pm.pm1b_cnt, g_acpi_pm.slp_typb);
+    }
+
+    for (uint32_t i = 0; i < ACPI_PM_SETTLE_SPIN; i++) {
+        __asm__ volatile ("pause");
+    }
+    return -1;
+}
+
+int acpi_reset(void)
+{
+    if (!acpi_reset_available()) {
+        return -1;
+    }
+
+    const struct acpi_fadt* fadt = g_acpi_pm.fadt;
+    uint64_t addr = fadt->reset_reg.address;
+    if (fadt->reset_reg.space_id == ACPI_GAS_SPACE_IO) {
+        acpi_outb((uint16_t)addr, fadt->reset_value);
+    } else {
+        /* PCI config on segment 0, bus 0: device[47:32] function[31:16] offset[15:0] */
+        uint32_t dev = (uint32_t)((addr >> 32) & 0x1FU);
+        uint32_t fn = (uint32_t)((addr >> 16) & 0x7U);
+        uint32_t off = (uint32_t)(addr & 0xFFU);
+        acpi_outl(PCI_CONFIG_ADDRESS, (1U << 31) | (dev << 11) | (fn << 8) | (off & 0xFCU));
+        acpi_outb((uint16_t)(PCI_CONFIG_DATA + (off & 3U)), fadt->reset_value);
+    }
+
+    for (uint32_t i = 0; i < ACPI_PM_SETTLE_SPIN; i++) {
+        __asm__ volatile ("pause");
+    }
+    return -1;
+}
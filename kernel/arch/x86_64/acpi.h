@@ -48,6 +48,31 @@ struct acpi_madt_entry_header {
     uint8_t length;
 } __attribute__((packed));
 
+#define ACPI_MADT_TYPE_LAPIC        0
+#define ACPI_MADT_TYPE_IOAPIC       1
+#define ACPI_MADT_TYPE_ISO          2
+#define ACPI_MADT_TYPE_X2APIC       9
+
+#define ACPI_MADT_LAPIC_ENABLED        (1u << 0)
+#define ACPI_MADT_LAPIC_ONLINE_CAPABLE (1u << 1)
+
+struct acpi_madt_lapic {
+    uint8_t type;
+    uint8_t length;
+    uint8_t acpi_processor_id;
+    uint8_t apic_id;
+    uint32_t flags;
+} __attribute__((packed));
+
+struct acpi_madt_x2apic {
+    uint8_t type;
+    uint8_t length;
+    uint16_t reserved;
+    uint32_t x2apic_id;
+    uint32_t flags;
+    uint32_t acpi_processor_uid;
+} __attribute__((packed));
+
 struct acpi_madt_ioapic {
     uint8_t type;
     uint8_t length;
@@ -79,6 +104,105 @@ struct acpi_madt_iso_info {
     uint16_t flags;
 };
 
+/* Generic Address Structure */
+struct acpi_gas {
+    uint8_t space_id;
+    uint8_t bit_width;
+    uint8_t bit_offset;
+    uint8_t access_size;
+    uint64_t address;
+} __attribute__((packed));
+
+#define ACPI_GAS_SPACE_MEMORY       0
+#define ACPI_GAS_SPACE_IO           1
+
+struct acpi_hpet {
+    struct acpi_sdt_header header;
+    uint32_t event_timer_block_id;
+    struct acpi_gas base_address;
+    uint8_t hpet_number;
+    uint16_t min_clock_tick;
+    uint8_t page_protection;
+} __attribute__((packed));
+
+#define ACPI_GAS_SPACE_PCI_CONFIG   2
+
+/* MCFG: PCIe enhanced configuration (ECAM) windows */
+struct acpi_mcfg {
+    struct acpi_sdt_header header;
+    uint64_t reserved;
+} __attribute__((packed));
+
+struct acpi_mcfg_allocation {
+    uint64_t base_address;      /* ECAM base for bus 0 of the segment */
+    uint16_t segment;
+    uint8_t start_bus;
+    uint8_t end_bus;
+    uint32_t reserved;
+} __attribute__((packed));
+
+/* FADT ("FACP") up to the extended PM1 control blocks */
+struct acpi_fadt {
+    struct acpi_sdt_header header;
+    uint32_t firmware_ctrl;
+    uint32_t dsdt;
+    uint8_t reserved0;
+    uint8_t preferred_pm_profile;
+    uint16_t sci_int;
+    uint32_t smi_cmd;
+    uint8_t acpi_enable;
+    uint8_t acpi_disable;
+    uint8_t s4bios_req;
+    uint8_t pstate_cnt;
+    uint32_t pm1a_evt_blk;
+    uint32_t pm1b_evt_blk;
+    uint32_t pm1a_cnt_blk;
+    uint32_t pm1b_cnt_blk;
+    uint32_t pm2_cnt_blk;
+    uint32_t pm_tmr_blk;
+    uint32_t gpe0_blk;
+    uint32_t gpe1_blk;
+    uint8_t pm1_evt_len;
+    uint8_t pm1_cnt_len;
+    uint8_t pm2_cnt_len;
+    uint8_t pm_tmr_len;
+    uint8_t gpe0_blk_len;
+    uint8_t gpe1_blk_len;
+    uint8_t gpe1_base;
+    uint8_t cst_cnt;
+    uint16_t p_lvl2_lat;
+    uint16_t p_lvl3_lat;
+    uint16_t flush_size;
+    uint16_t flush_stride;
+    uint8_t duty_offset;
+    uint8_t duty_width;
+    uint8_t day_alrm;
+    uint8_t mon_alrm;
+    uint8_t century;
+    uint16_t iapc_boot_arch;
+    uint8_t reserved1;
+    uint32_t flags;
+    struct acpi_gas reset_reg;
+    uint8_t reset_value;
+    uint16_t arm_boot_arch;
+    uint8_t fadt_minor_version;
+    uint64_t x_firmware_ctrl;
+    uint64_t x_dsdt;
+    struct acpi_gas x_pm1a_evt_blk;
+    struct acpi_gas x_pm1b_evt_blk;
+    struct acpi_gas x_pm1a_cnt_blk;
+    struct acpi_gas x_pm1b_cnt_blk;
+} __attribute__((packed));
+
+#define ACPI_FADT_RESET_REG_SUP     (1u << 10)
+#define ACPI_FADT_HW_REDUCED        (1u << 20)
+
+/* PM1 control register */
+#define ACPI_PM1_CNT_SCI_EN         (1u << 0)
+#define ACPI_PM1_CNT_SLP_TYP_SHIFT  10
+#define ACPI_PM1_CNT_SLP_TYP_MASK   (7u << ACPI_PM1_CNT_SLP_TYP_SHIFT)
+#define ACPI_PM1_CNT_SLP_EN         (1u << 13)
+
 typedef int (*acpi_madt_iter_fn)(const struct acpi_madt_entry_header* entry, void* ctx);
 
 int acpi_init(void);
@@ -91,5 +215,13 @@ int acpi_madt_foreach(acpi_madt_iter_fn fn, void* ctx);
 int acpi_madt_get_lapic_addr(uint32_t* out_addr);
 int acpi_madt_get_ioapic(uint32_t index, struct acpi_madt_ioapic_info* out_info);
 int acpi_madt_get_iso_for_source(uint8_t source, struct acpi_madt_iso_info* out_info);
+int acpi_mcfg_get(uint32_t index, struct acpi_mcfg_allocation* out);
+
+/* FADT power control; parsed by acpi_init() */
+const struct acpi_fadt* acpi_get_fadt(void);
+bool acpi_s5_available(void);
+bool acpi_reset_available(void);
+int acpi_enter_s5(void);
+int acpi_reset(void);
 
 #endif /* _RODNIX_ARCH_X86_64_ACPI_H */
@@ -13,14 +13,20 @@
 
 #include "types.h"
 #include "config.h"
+#include "apic.h"
 #include "paging.h"
 #include "pic.h"
 #include "lapic_regs.h"
 #include "lapic_access.h"
 #include "acpi.h"
+#include "percpu.h"
+#include "clocksource.h"
 #include "../../../include/debug.h"
 #include "../../core/interrupts.h"
 #include "../../common/scheduler.h"
+#include "../../core/cpu.h"
+#include "../../core/clock.h"
+#include "../../fabric/spin.h"
 #include <stddef.h>
 #include <stdbool.h>
 #include <stdint.h>
@@ -92,6 +98,33 @@ static uint64_t ioapic_base_addr = IOAPIC_BASE_ADDR_DEFAULT;  /* Will be set fro
 static uint32_t apic_timer_ticks_per_ms = 0;  /* Calibrated ticks per millisecond */
 static uint32_t apic_timer_frequency = 0;     /* Target frequency */
 static volatile uint32_t apic_timer_ticks = 0; /* System tick counter */
+static bool apic_timer_tsc_deadline = false;  /* LVT runs in TSC-deadline mode */
+static uint64_t apic_timer_tsc_period = 0;    /* TSC cycles per tick (deadline mode) */
+static bool apic_timer_oneshot = false;       /* deadlines come from clockevent_program() */
+
+/*
+ * Cross-CPU calls.
+ * LOCKING: ipi_call_lock serializes senders, so a single descriptor is
+ *   enough. A target is marked in x86_percpu_t.ipi_call_pending and
+ *   decrements ipi_call.remaining after running the callback; the sender
+ *   spins until the counter drops to zero. CPUs that spin with interrupts
+ *   off (IRQL giant, ipi_call_lock) serve calls posted to them meanwhile.
+ */
+static spinlock_t ipi_call_lock;
+static struct {
+    apic_ipi_func_t fn;
+    void* arg;
+    volatile uint64_t remaining;
+} ipi_call;
+
+/*
+ * CPU stop for halt/power-off/reboot.
+ * LOCKING: atomics only. apic_stop_requested is set once and never
+ *   cleared; every CPU that takes the stop NMI bumps apic_cpus_stopped.
+ */
+static volatile uint64_t apic_stop_requested;
+static volatile uint64_t apic_cpus_stopped;
+#define APIC_STOP_SPIN_LIMIT 10000000U
 
 /* ============================================================================
  * Internal Helper Functions
@@ -231,6 +264,14 @@ static uint64_t apic_read_msr(uint32_t msr)
     return result;
 }
 
+static void apic_write_msr(uint32_t msr, uint64_t value)
+{
+    __asm__ volatile ("wrmsr"
+                      :
+                      : "c"(msr), "a"((uint32_t)value), "d"((uint32_t)(value >> 32))
+                      : "memory");
+}
+
 /**
  * @function apic_read_register
  * @brief Read APIC register
@@ -472,6 +513,7 @@ int apic_init(void)
     
     kputs("[APIC-10] Set initialized\n");
     __asm__ volatile ("" ::: "memory");
+    spinlock_init(&ipi_call_lock);
     apic_initialized = true;
     __asm__ volatile ("" ::: "memory");
     
@@ -565,6 +607,276 @@ void apic_send_eoi(void)
     apic_write_register(APIC_EOI, 0);
 }
 
+/**
+ * @function apic_init_ap
+ * @brief Enable the Local APIC of an application processor
+ *
+ * The LAPIC mode (xAPIC/x2APIC) follows the BSP. Legacy LINT0/LINT1 stay
+ * masked: ExtINT and NMI routing belong to the BSP only.
+ *
+ * @return 0 on success, -1 on failure
+ */
+int apic_init_ap(void)
+{
+    if (!apic_initialized) {
+        return -1;
+    }
+    if (lapic_access_init_ap() != 0) {
+        return -1;
+    }
+    apic_reset_local();
+
+    uint32_t svr = apic_read_register(APIC_SVR);
+    svr |= APIC_SVR_ENABLE;
+    svr &= ~0xFF;
+    svr |= APIC_SVR_SPURIOUS_VECTOR;
+    apic_write_register(APIC_SVR, svr);
+    return 0;
+}
+
+static void apic_wait_icr_idle(void)
+{
+    uint32_t spin = 0;
+    while (lapic_access_icr_busy()) {
+        __asm__ volatile ("pause");
+        if (++spin > 1000000U) {
+            break;
+        }
+    }
+}
+
+/**
+ * @function apic_send_init_ipi
+ * @brief Send INIT IPI (assert) to a CPU
+ *
+ * @param apic_id Destination LAPIC ID
+ */
+void apic_send_init_ipi(uint32_t apic_id)
+{
+    if (!apic_initialized) {
+        return;
+    }
+    apic_write_register(APIC_ESR, 0);
+    lapic_access_write_icr(apic_id, APIC_ICR_DM_INIT |
+                                    APIC_ICR_LEVEL_ASSERT |
+                                    APIC_ICR_TRIGGER_LEVEL);
+    apic_wait_icr_idle();
+}
+
+/**
+ * @function apic_send_startup_ipi
+ * @brief Send STARTUP IPI to a CPU
+ *
+ * @param apic_id Destination LAPIC ID
+ * @param vector_page Physical page number of the real-mode entry (addr >> 12)
+ */
+void apic_send_startup_ipi(uint32_t apic_id, uint8_t vector_page)
+{
+    if (!apic_initialized) {
+        return;
+    }
+    apic_write_register(APIC_ESR, 0);
+    lapic_access_write_icr(apic_id, APIC_ICR_DM_STARTUP | (uint32_t)vector_page);
+    apic_wait_icr_idle();
+}
+
+/* ============================================================================
+ * Inter-Processor Interrupts
+ * ============================================================================ */
+
+_Static_assert(CPU_MAX_COUNT <= 32, "IPI cpu mask is 32 bits wide");
+
+/**
+ * @function apic_send_ipi
+ * @brief Send a fixed-vector IPI to one CPU
+ *
+ * @param apic_id Destination LAPIC ID
+ * @param vector Interrupt vector
+ */
+void apic_send_ipi(uint32_t apic_id, uint8_t vector)
+{
+    if (!apic_initialized) {
+        return;
+    }
+    lapic_access_write_icr(apic_id, APIC_ICR_DM_FIXED |
+                                    APIC_ICR_LEVEL_ASSERT |
+                                    (uint32_t)vector);
+    apic_wait_icr_idle();
+}
+
+/**
+ * @function apic_send_ipi_all
+ * @brief Send a fixed-vector IPI to every CPU except the caller
+ *
+ * @param vector Interrupt vector
+ */
+void apic_send_ipi_all(uint8_t vector)
+{
+    if (!apic_initialized) {
+        return;
+    }
+    lapic_access_write_icr(0, APIC_ICR_DM_FIXED |
+                              APIC_ICR_LEVEL_ASSERT |
+                              APIC_ICR_DEST_OTHERS |
+                              (uint32_t)vector);
+    apic_wait_icr_idle();
+}
+
+/**
+ * @function apic_send_ipi_mask
+ * @brief Send a fixed-vector IPI to a set of logical CPUs
+ *
+ * Offline CPUs in the mask are skipped.
+ *
+ * @param cpu_mask Bit N selects logical CPU N
+ * @param vector Interrupt vector
+ */
+void apic_send_ipi_mask(uint32_t cpu_mask, uint8_t vector)
+{
+    for (uint32_t cpu = 0; cpu < CPU_MAX_COUNT && cpu_mask; cpu++) {
+        if (!(cpu_mask & (1u << cpu))) {
+            continue;
+        }
+        cpu_mask &= ~(1u << cpu);
+        x86_percpu_t* pc = percpu_get(cpu);
+        if (pc && pc->online) {
+            apic_send_ipi(pc->apic_id, vector);
+        }
+    }
+}
+
+/**
+ * @function apic_ipi_stop_others
+ * @brief Park every other online CPU with an NMI
+ *
+ * NMI gets through even to a CPU spinning with interrupts off on the IRQL
+ * giant the caller holds. The wait is bounded: a CPU that never answers
+ * is left alone rather than hanging the halt path.
+ *
+ * @return Number of CPUs that reported stopped
+ */
+int apic_ipi_stop_others(void)
+{
+    if (!apic_initialized) {
+        return 0;
+    }
+    if (cpu_atomic_swap(&apic_stop_requested, 1) != 0) {
+        return (int)apic_cpus_stopped;
+    }
+    __asm__ volatile ("mfence" ::: "memory");
+
+    x86_percpu_t* self = percpu_self();
+    uint64_t expected = 0;
+    for (uint32_t cpu = 0; cpu < CPU_MAX_COUNT; cpu++) {
+        x86_percpu_t* pc = percpu_get(cpu);
+        if (!pc || !pc->online || pc == self) {
+            continue;
+        }
+        lapic_access_write_icr(pc->apic_id, APIC_ICR_DM_NMI |
+                                            APIC_ICR_LEVEL_ASSERT);
+        apic_wait_icr_idle();
+        expected++;
+    }
+    for (uint32_t spin = 0; spin < APIC_STOP_SPIN_LIMIT; spin++) {
+        if (apic_cpus_stopped >= expected) {
+            break;
+        }
+        __asm__ volatile ("pause");
+    }
+    return (int)apic_cpus_stopped;
+}
+
+void apic_ipi_stop_nmi(void)
+{
+    if (!apic_stop_requested) {
+        return;
+    }
+    x86_percpu_t* self = percpu_self();
+    if (self) {
+        self->online = 0;
+    }
+    (void)cpu_atomic_add(&apic_cpus_stopped, 1);
+    for (;;) {
+        __asm__ volatile ("cli; hlt");
+    }
+}
+
+void apic_ipi_poll(void)
+{
+    x86_percpu_t* pc = percpu_self();
+    if (!pc->ipi_call_pending || !cpu_atomic_swap(&pc->ipi_call_pending, 0)) {
+        return;
+    }
+    ipi_call.fn(ipi_call.arg);
+    (void)cpu_atomic_sub(&ipi_call.remaining, 1);
+}
+
+void apic_ipi_call_interrupt(void)
+{
+    apic_ipi_poll();
+    apic_send_eoi();
+}
+
+/**
+ * @function apic_ipi_call
+ * @brief Synchronous cross-CPU function call
+ *
+ * The callback runs from the APIC_IPI_VECTOR_CALL handler (or from a spin
+ * loop of the target) with interrupts disabled and IRQL untouched.
+ *
+ * @param cpu_mask Bit N selects logical CPU N
+ * @param fn Callback
+ * @param arg Callback argument (must stay valid until return)
+ * @return Number of CPUs that ran the call, or -1 if the LAPIC is not up
+ */
+int apic_ipi_call(uint32_t cpu_mask, apic_ipi_func_t fn, void* arg)
+{
+    if (!apic_initialized || !fn) {
+        return -1;
+    }
+
+    uint64_t rflags;
+    __asm__ volatile ("pushfq; pop %0; cli" : "=r"(rflags) : : "memory");
+
+    uint32_t self = percpu_self()->cpu_id;
+    uint32_t targets = 0;
+    int count = 0;
+    for (uint32_t cpu = 0; cpu < CPU_MAX_COUNT; cpu++) {
+        x86_percpu_t* pc = percpu_get(cpu);
+        if (cpu != self && (cpu_mask & (1u << cpu)) && pc->online) {
+            targets |= 1u << cpu;
+            count++;
+        }
+    }
+
+    if (count > 0) {
+        /* The current holder may be waiting for us: keep serving calls. */
+        while (!spinlock_trylock(&ipi_call_lock)) {
+            apic_ipi_poll();
+            __asm__ volatile ("pause");
+        }
+        ipi_call.fn = fn;
+        ipi_call.arg = arg;
+        ipi_call.remaining = (uint64_t)count;
+        for (uint32_t cpu = 0; cpu < CPU_MAX_COUNT; cpu++) {
+            if (targets & (1u << cpu)) {
+                percpu_get(cpu)->ipi_call_pending = 1;
+            }
+        }
+        __asm__ volatile ("mfence" ::: "memory");
+        apic_send_ipi_mask(targets, APIC_IPI_VECTOR_CALL);
+        while (ipi_call.remaining != 0) {
+            __asm__ volatile ("pause");
+        }
+        spinlock_unlock(&ipi_call_lock);
+    }
+
+    if (rflags & (1ULL << 9)) {
+        __asm__ volatile ("sti" ::: "memory");
+    }
+    return count;
+}
+
 /* ============================================================================
  * I/O APIC Helper Functions
  * ============================================================================ */
@@ -639,7 +951,7 @@ int ioapic_init(void)
     /* Use address found from MADT or default */
     uint64_t ioapic_phys = ioapic_base_addr;
     uint64_t ioapic_virt = IOAPIC_MMIO_VIRT;
-    uint64_t mmio_flags = PTE_PRESENT | PTE_RW | PTE_PCD; /* PRESENT | RW | PCD (uncached) */
+    uint64_t mmio_flags = PTE_PRESENT | PTE_RW | PTE_PCD | PTE_NX; /* uncached, no execute */
     
     #if APIC_DEBUG
     kprintf("[IOAPIC-1.1] Attempting to map I/O APIC at phys=%llX, virt=%llX\n", 
@@ -815,10 +1127,8 @@ uint8_t apic_get_lapic_id(void)
         return 0;
     }
     
-    /* Read APIC ID register */
-    uint32_t apic_id_reg = apic_read_register(APIC_ID);
-    /* APIC ID is in bits 24-31 */
-    return (uint8_t)((apic_id_reg >> 24) & 0xFF);
+    /* xAPIC keeps the ID in bits 24-31, x2APIC returns the full value */
+    return (uint8_t)(lapic_access_id() & 0xFFu);
 }
 
 /**
@@ -921,9 +1231,29 @@ void apic_disable_irq(uint8_t irq)
  * 
  * @note Minimal work in interrupt handler
  */
+static void apic_timer_rearm_deadline(void);
+
 void apic_timer_handler(interrupt_context_t* ctx)
 {
     (void)ctx;
+    if (apic_timer_tsc_deadline) {
+        /* Vector 32 also arrives from "int $32" and kicks: not a timer expiry */
+        x86_percpu_t* pc = percpu_self();
+        if (pc->tsc_deadline == 0 || clocksource_rdtsc() < pc->tsc_deadline) {
+            return;
+        }
+        if (apic_timer_oneshot) {
+            /* The scheduler arms the next event after dispatch */
+            pc->tsc_deadline = 0;
+        } else {
+            /* TSC-deadline is one-shot: every CPU re-arms its own timer */
+            apic_timer_rearm_deadline();
+        }
+    }
+    /* Every CPU has its own LAPIC timer; system ticks come from the BSP only */
+    if (cpu_get_id() != 0) {
+        return;
+    }
     /* Increment tick counter */
     apic_timer_ticks++;
 }
@@ -1074,27 +1404,30 @@ int apic_timer_init(uint32_t frequency)
         }
     }
 
-    kprintf("[APIC-TIMER-INIT] hz=%u ticks_per_ms=%u div=%x lvt=%x\n",
+    /* Calibrated TSC and CPU support: ticks come from TSC deadlines instead */
+    if (clocksource_tsc_deadline_ok() && frequency != 0) {
+        apic_timer_tsc_period = clocksource_tsc_hz() / frequency;
+        apic_timer_tsc_deadline = apic_timer_tsc_period != 0;
+    }
+
+    kprintf("[APIC-TIMER-INIT] hz=%u ticks_per_ms=%u div=%x lvt=%x mode=%s\n",
             apic_timer_frequency,
             apic_timer_ticks_per_ms,
             apic_read_register(APIC_TIMER_DIV),
-            apic_read_register(APIC_LVT_TIMER));
+            apic_read_register(APIC_LVT_TIMER),
+            apic_timer_mode_name());
     
     return 0;
 }
 
 /**
- * @function apic_timer_start
- * @brief Start LAPIC timer in periodic mode
- * 
- * @note Uses calibrated frequency for accurate timing
+ * @function apic_timer_program_periodic
+ * @brief Program the calling CPU's LAPIC timer in periodic mode
+ *
+ * @return Initial count written to the timer
  */
-void apic_timer_start(void)
+static uint32_t apic_timer_program_periodic(void)
 {
-    if (!apic_initialized || apic_timer_ticks_per_ms == 0) {
-        return;
-    }
-    
     /* Calculate initial count for desired frequency */
     /* ticks_per_ms * 1000 / frequency = ticks per period */
     /* For 100Hz: period = 10ms, so initial_count = ticks_per_ms * 10 */
@@ -1109,17 +1442,86 @@ void apic_timer_start(void)
         /* TODO: Implement proper division-free calculation for other frequencies */
         initial_count = (apic_timer_ticks_per_ms << 3) + (apic_timer_ticks_per_ms << 1); /* * 10 */
     }
+
+    /* Divider is per-CPU state: APs never ran the calibration path */
+    apic_write_register(APIC_TIMER_DIV, 0b0011);
     
     /* Set timer to periodic mode */
     uint32_t lvt_timer = apic_read_register(APIC_LVT_TIMER);
     lvt_timer |= APIC_LVT_TIMER_PERIODIC; /* Periodic mode */
     lvt_timer &= ~APIC_LVT_MASKED; /* Unmask timer */
+    lvt_timer &= ~0xFFu;
     lvt_timer |= 32; /* Timer interrupt vector */
     apic_write_register(APIC_LVT_TIMER, lvt_timer);
     
     /* Set initial count to start timer */
     apic_write_register(APIC_TIMER_INITCNT, initial_count);
     __asm__ volatile ("" ::: "memory");
+    return initial_count;
+}
+
+/**
+ * @function apic_timer_program_deadline
+ * @brief Program the calling CPU's LAPIC timer in TSC-deadline mode
+ *
+ * The first deadline is one period from now; apic_timer_handler() keeps
+ * re-arming it.
+ */
+static void apic_timer_program_deadline(void)
+{
+    uint32_t lvt_timer = apic_read_register(APIC_LVT_TIMER);
+    lvt_timer &= ~(APIC_LVT_TIMER_MODE_MASK | APIC_LVT_MASKED | 0xFFu);
+    lvt_timer |= APIC_LVT_TIMER_TSC_DEADLINE | 32u;
+    apic_write_register(APIC_LVT_TIMER, lvt_timer);
+
+    /* SDM: the LVT mode switch must be ordered before the deadline write */
+    __asm__ volatile ("mfence" ::: "memory");
+
+    x86_percpu_t* pc = percpu_self();
+    pc->tsc_deadline = clocksource_rdtsc() + apic_timer_tsc_period;
+    apic_write_msr(IA32_TSC_DEADLINE_MSR, pc->tsc_deadline);
+}
+
+/**
+ * @function apic_timer_rearm_deadline
+ * @brief Arm the next TSC deadline of the calling CPU
+ *
+ * Deadlines advance by whole periods so ticks do not drift; ticks missed
+ * while interrupts were off are dropped rather than replayed.
+ */
+static void apic_timer_rearm_deadline(void)
+{
+    x86_percpu_t* pc = percpu_self();
+    uint64_t now = clocksource_rdtsc();
+    uint64_t next = pc->tsc_deadline + apic_timer_tsc_period;
+    if (next <= now) {
+        next = now + apic_timer_tsc_period;
+    }
+    pc->tsc_deadline = next;
+    apic_write_msr(IA32_TSC_DEADLINE_MSR, next);
+}
+
+/**
+ * @function apic_timer_start
+ * @brief Start LAPIC timer (TSC-deadline when available, periodic otherwise)
+ * 
+ * @note Uses calibrated frequency for accurate timing
+ */
+void apic_timer_start(void)
+{
+    if (!apic_initialized || apic_timer_ticks_per_ms == 0) {
+        return;
+    }
+
+    if (apic_timer_tsc_deadline) {
+        apic_timer_program_deadline();
+        kprintf("[APIC-TIMER-START] tsc-deadline period=%llu lvt=%x\n",
+                (unsigned long long)apic_timer_tsc_period,
+                apic_read_register(APIC_LVT_TIMER));
+        return;
+    }
+    
+    uint32_t initial_count = apic_timer_program_periodic();
 
     kprintf("[APIC-TIMER-START] init=%u lvt=%x div=%x cur=%u\n",
             initial_count,
@@ -1128,6 +1530,24 @@ void apic_timer_start(void)
             apic_read_register(APIC_TIMER_CURRCNT));
 }
 
+/**
+ * @function apic_timer_start_ap
+ * @brief Start the LAPIC timer of an application processor
+ *
+ * Reuses the BSP calibration: all LAPIC timers share the bus clock.
+ */
+void apic_timer_start_ap(void)
+{
+    if (!apic_initialized || apic_timer_ticks_per_ms == 0) {
+        return;
+    }
+    if (apic_timer_tsc_deadline) {
+        apic_timer_program_deadline();
+        return;
+    }
+    (void)apic_timer_program_periodic();
+}
+
 /**
  * @function apic_timer_stop
  * @brief Stop LAPIC timer
@@ -1142,6 +1562,9 @@ void apic_timer_stop(void)
     uint32_t lvt_timer = apic_read_register(APIC_LVT_TIMER);
     lvt_timer |= APIC_LVT_MASKED;
     apic_write_register(APIC_LVT_TIMER, lvt_timer);
+    if (apic_timer_tsc_deadline) {
+        apic_write_msr(IA32_TSC_DEADLINE_MSR, 0);
+    }
 }
 
 /**
@@ -1168,6 +1591,58 @@ uint32_t apic_timer_get_frequency(void)
     return apic_timer_frequency;
 }
 
+/**
+ * @function apic_timer_mode_name
+ * @brief Name of the LAPIC timer mode ("tickless", "tsc-deadline" or "periodic")
+ */
+const char* apic_timer_mode_name(void)
+{
+    if (apic_timer_oneshot) {
+        return "tickless";
+    }
+    return apic_timer_tsc_deadline ? "tsc-deadline" : "periodic";
+}
+
+/* ============================================================================
+ * Clockevent (core/clock.h) on top of the TSC-deadline timer
+ * ============================================================================ */
+
+bool clockevent_oneshot_capable(void)
+{
+    return apic_timer_tsc_deadline && clocksource_available();
+}
+
+int clockevent_enable_oneshot(void)
+{
+    if (!clockevent_oneshot_capable()) {
+        return -1;
+    }
+    /* The deadline armed now still fires; the scheduler takes over from there */
+    apic_timer_oneshot = true;
+    return 0;
+}
+
+void clockevent_program(uint64_t deadline_ns)
+{
+    if (!apic_timer_oneshot) {
+        return;
+    }
+    x86_percpu_t* pc = percpu_self();
+    if (deadline_ns == 0) {
+        pc->tsc_deadline = 0;
+        apic_write_msr(IA32_TSC_DEADLINE_MSR, 0);
+        return;
+    }
+
+    uint64_t now_ns = clocksource_monotonic_ns();
+    uint64_t now_tsc = clocksource_rdtsc();
+    uint64_t delta_ns = deadline_ns > now_ns ? deadline_ns - now_ns : 0;
+    /* A deadline in the past fires at once */
+    uint64_t deadline = now_tsc + clock_ns_to_cycles(delta_ns, clocksource_tsc_hz()) + 1;
+    pc->tsc_deadline = deadline;
+    apic_write_msr(IA32_TSC_DEADLINE_MSR, deadline);
+}
+
 uint32_t apic_timer_get_lvt_raw(void)
 {
     if (!apic_initialized) {
@@ -26,12 +26,57 @@ void apic_disable(void);
 void apic_send_eoi(void);
 uint8_t apic_get_lapic_id(void);
 
+/* Application processors (SMP bring-up) */
+int apic_init_ap(void);
+void apic_send_init_ipi(uint32_t apic_id);
+void apic_send_startup_ipi(uint32_t apic_id, uint8_t vector_page);
+
+/*
+ * Inter-processor interrupts.
+ *
+ * APIC_IPI_VECTOR_CALL carries synchronous cross-CPU calls. It bypasses IRQL
+ * bookkeeping like NMI does: the target may be spinning on the IRQL giant
+ * that the caller holds, so the callback must not raise IRQL, take locks or
+ * sleep (TLB invalidation and similar per-CPU work only).
+ */
+#define APIC_IPI_VECTOR_CALL 0xF0
+
+typedef void (*apic_ipi_func_t)(void* arg);
+
+void apic_send_ipi(uint32_t apic_id, uint8_t vector);
+void apic_send_ipi_all(uint8_t vector);
+void apic_send_ipi_mask(uint32_t cpu_mask, uint8_t vector);
+
+/**
+ * Run @p fn(@p arg) on every online CPU in @p cpu_mask (logical CPU bits,
+ * the calling CPU is skipped) and wait until all of them have returned.
+ * @return Number of CPUs that ran the call, or -1 if the LAPIC is not up
+ */
+int apic_ipi_call(uint32_t cpu_mask, apic_ipi_func_t fn, void* arg);
+
+/* Run a cross-CPU call posted to this CPU, if any (IRQL-free spin loops) */
+void apic_ipi_poll(void);
+
+/* APIC_IPI_VECTOR_CALL handler: poll + EOI */
+void apic_ipi_call_interrupt(void);
+
+/**
+ * Stop every other online CPU with an NMI before halt, power-off or reset.
+ * @return Number of CPUs that reported stopped (0 if the LAPIC is not up)
+ */
+int apic_ipi_stop_others(void);
+
+/* NMI hook: park this CPU for good if a stop was requested, else return */
+void apic_ipi_stop_nmi(void);
+
 /* APIC timer (LAPIC timer) */
 int apic_timer_init(uint32_t frequency);
 void apic_timer_start(void);
+void apic_timer_start_ap(void);
 void apic_timer_stop(void);
 uint32_t apic_timer_get_ticks(void);
 uint32_t apic_timer_get_frequency(void);
+const char* apic_timer_mode_name(void);
 uint32_t apic_timer_get_lvt_raw(void);
 uint32_t apic_timer_get_initial_count(void);
 uint32_t apic_timer_get_current_count(void);
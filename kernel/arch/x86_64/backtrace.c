@@ -0,0 +1,222 @@
+/**
+ * @file x86_64/backtrace.c
+ * @brief Frame-pointer stack unwinder for x86_64
+ *
+ * Every frame starts with [saved rbp][return address]. When a return
+ * address points into the common interrupt stubs, the C handler was
+ * called with the interrupt_frame_t right above its frame: the walk
+ * continues from the saved rip/rbp of the interrupted context.
+ *
+ * Output is formatted by hand so the exception path can send it to the
+ * serial port without going through kprintf.
+ */
+
+#include "../../core/backtrace.h"
+#include "../../common/ksyms.h"
+#include "../../../include/console.h"
+#include "interrupt_frame.h"
+#include "../config.h"
+#include <stdbool.h>
+#include <stddef.h>
+
+#define BT_MAX_DEPTH 32
+/* Largest believable distance between two frames of one stack */
+#define BT_MAX_FRAME_SPAN (64u * 1024u)
+
+extern char intr_entry_text_start[];
+extern char intr_entry_text_end[];
+
+static void bt_default_out(const char* str)
+{
+    kputs(str);
+}
+
+static size_t bt_put_str(char* buf, size_t pos, size_t cap, const char* s)
+{
+    while (*s && pos + 1 < cap) {
+        buf[pos++] = *s++;
+    }
+    buf[pos] = '\0';
+    return pos;
+}
+
+static size_t bt_put_hex(char* buf, size_t pos, size_t cap, uint64_t v)
+{
+    static const char hex[] = "0123456789abcdef";
+    char tmp[16];
+    int n = 0;
+    do {
+        tmp[n++] = hex[v & 0xFu];
+        v >>= 4;
+    } while (v && n < (int)sizeof(tmp));
+    pos = bt_put_str(buf, pos, cap, "0x");
+    while (n > 0 && pos + 1 < cap) {
+        buf[pos++] = tmp[--n];
+    }
+    buf[pos] = '\0';
+    return pos;
+}
+
+static size_t bt_put_dec(char* buf, size_t pos, size_t cap, uint64_t v)
+{
+    char tmp[20];
+    int n = 0;
+    do {
+        tmp[n++] = (char)('0' + (v % 10u));
+        v /= 10u;
+    } while (v && n < (int)sizeof(tmp));
+    while (n > 0 && pos + 1 < cap) {
+        buf[pos++] = tmp[--n];
+    }
+    buf[pos] = '\0';
+    return pos;
+}
+
+/*
+ * Where the walk goes: printed lines (out) and/or a list of PCs (pcs).
+ */
+typedef struct {
+    backtrace_out_t out;
+    uint64_t* pcs;
+    uint32_t max;
+    uint32_t count;
+} bt_sink_t;
+
+/*
+ * One line per frame: "  #3 0xffffffff80012345 vm_fault_handle+0x4c".
+ * Return addresses are resolved at addr - 1 so a call at the very end of
+ * a function is not attributed to the next one.
+ */
+static void bt_emit_pc(bt_sink_t* sink, uint32_t depth, uint64_t pc, bool is_return)
+{
+    if (sink->pcs && sink->count < sink->max) {
+        sink->pcs[sink->count++] = pc;
+    }
+    if (!sink->out) {
+        return;
+    }
+
+    char line[160];
+    size_t pos = 0;
+    uint64_t off = 0;
+    const char* name = ksyms_lookup(is_return ? pc - 1 : pc, &off);
+
+    pos = bt_put_str(line, pos, sizeof(line), "  #");
+    pos = bt_put_dec(line, pos, sizeof(line), depth);
+    pos = bt_put_str(line, pos, sizeof(line), " ");
+    pos = bt_put_hex(line, pos, sizeof(line), pc);
+    pos = bt_put_str(line, pos, sizeof(line), " ");
+    if (name) {
+        pos = bt_put_str(line, pos, sizeof(line), name);
+        pos = bt_put_str(line, pos, sizeof(line), "+");
+        pos = bt_put_hex(line, pos, sizeof(line), is_return ? off + 1 : off);
+    } else {
+        pos = bt_put_str(line, pos, sizeof(line), "?");
+    }
+    (void)bt_put_str(line, pos, sizeof(line), "\n");
+    sink->out(line);
+}
+
+static void bt_emit_intr(bt_sink_t* sink, const interrupt_frame_t* regs)
+{
+    if (!sink->out) {
+        return;
+    }
+    char line[96];
+    size_t pos = 0;
+    pos = bt_put_str(line, pos, sizeof(line), "  --- interrupt vector ");
+    pos = bt_put_dec(line, pos, sizeof(line), regs->int_no);
+    pos = bt_put_str(line, pos, sizeof(line), " err=");
+    pos = bt_put_hex(line, pos, sizeof(line), regs->err_code);
+    pos = bt_put_str(line, pos, sizeof(line), (regs->cs & 3u) ? " from user" : "");
+    (void)bt_put_str(line, pos, sizeof(line), " ---\n");
+    sink->out(line);
+}
+
+static void bt_emit_note(bt_sink_t* sink, const char* note)
+{
+    if (sink->out) {
+        sink->out(note);
+    }
+}
+
+static bool bt_kernel_addr(uint64_t addr)
+{
+    return addr > ARCH_USER_CANON_MAX;
+}
+
+static bool bt_in_intr_entry(uint64_t pc)
+{
+    return pc >= (uint64_t)(uintptr_t)intr_entry_text_start &&
+           pc < (uint64_t)(uintptr_t)intr_entry_text_end;
+}
+
+static void bt_walk(uint64_t fp, uint32_t depth, bt_sink_t* sink)
+{
+    uint64_t prev_fp = 0;
+
+    while (depth < BT_MAX_DEPTH) {
+        if (fp == 0 || (fp & 7u) != 0 || !bt_kernel_addr(fp)) {
+            return;
+        }
+        /* Frames of one stack only move up; a wild rbp must not be followed */
+        if (prev_fp && (fp <= prev_fp || fp - prev_fp > BT_MAX_FRAME_SPAN)) {
+            bt_emit_note(sink, "  (frame chain broken)\n");
+            return;
+        }
+
+        const uint64_t* frame = (const uint64_t*)(uintptr_t)fp;
+        uint64_t ret = frame[1];
+
+        if (bt_in_intr_entry(ret)) {
+            const interrupt_frame_t* regs = (const interrupt_frame_t*)(frame + 2);
+            bt_emit_intr(sink, regs);
+            if (regs->cs & 3u) {
+                return;
+            }
+            bt_emit_pc(sink, depth++, regs->rip, false);
+            /* The interrupted code may have run on another (IST) stack */
+            prev_fp = 0;
+            fp = regs->rbp;
+            continue;
+        }
+
+        if (!bt_kernel_addr(ret)) {
+            return;
+        }
+        bt_emit_pc(sink, depth++, ret, true);
+        prev_fp = fp;
+        fp = frame[0];
+    }
+    bt_emit_note(sink, "  ...\n");
+}
+
+__attribute__((noinline)) void backtrace_print(backtrace_out_t out)
+{
+    bt_sink_t sink = { out ? out : bt_default_out, NULL, 0, 0 };
+    sink.out("Backtrace:\n");
+    bt_walk((uint64_t)(uintptr_t)__builtin_frame_address(0), 0, &sink);
+}
+
+void backtrace_print_from(uint64_t pc, uint64_t fp, backtrace_out_t out)
+{
+    bt_sink_t sink = { out ? out : bt_default_out, NULL, 0, 0 };
+    sink.out("Backtrace:\n");
+    bt_emit_pc(&sink, 0, pc, false);
+    bt_walk(fp, 1, &sink);
+}
+
+__attribute__((noinline)) uint32_t backtrace_capture(uint64_t* pcs, uint32_t max)
+{
+    bt_sink_t sink = { NULL, pcs, pcs ? max : 0, 0 };
+    bt_walk((uint64_t)(uintptr_t)__builtin_frame_address(0), 0, &sink);
+    return sink.count;
+}
+
+uint32_t backtrace_capture_from(uint64_t pc, uint64_t fp, uint64_t* pcs, uint32_t max)
+{
+    bt_sink_t sink = { NULL, pcs, pcs ? max : 0, 0 };
+    bt_emit_pc(&sink, 0, pc, false);
+    bt_walk(fp, 1, &sink);
+    return sink.count;
+}
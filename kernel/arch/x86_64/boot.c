@@ -30,6 +30,7 @@ struct multiboot2_tag {
 #define MB2_TAG_MODULE        3
 #define MB2_TAG_BASIC_MEMINFO 4
 #define MB2_TAG_MMAP          6
+#define MB2_TAG_FRAMEBUFFER   8
 
 /* Multiboot2 command line tag (type 1) */
 struct multiboot2_tag_string {
@@ -55,6 +56,26 @@ struct multiboot2_tag_module {
     char cmdline[];
 } __attribute__((packed));
 
+/* Multiboot2 framebuffer info tag (type 8) */
+struct multiboot2_tag_framebuffer {
+    uint32_t type;
+    uint32_t size;
+    uint64_t addr;
+    uint32_t pitch;
+    uint32_t width;
+    uint32_t height;
+    uint8_t bpp;
+    uint8_t fb_type;
+    uint16_t reserved;
+    /* fb_type 1 (RGB): color layout follows */
+    uint8_t red_pos;
+    uint8_t red_size;
+    uint8_t green_pos;
+    uint8_t green_size;
+    uint8_t blue_pos;
+    uint8_t blue_size;
+} __attribute__((packed));
+
 /* Multiboot2 memory map tag (type 6) */
 struct multiboot2_tag_mmap {
     uint32_t type;
@@ -166,6 +187,36 @@ static void mb2_parse_mmap(const struct multiboot2_tag_mmap* tag)
     boot_info_storage.mem_lower = total_usable;
 }
 
+static void mb2_parse_framebuffer(const struct multiboot2_tag_framebuffer* tag)
+{
+    if (!tag || tag->size < offsetof(struct multiboot2_tag_framebuffer, red_pos)) {
+        return;
+    }
+    boot_info_storage.fb_addr = tag->addr;
+    boot_info_storage.fb_pitch = tag->pitch;
+    boot_info_storage.fb_width = tag->width;
+    boot_info_storage.fb_height = tag->height;
+    boot_info_storage.fb_bpp = tag->bpp;
+    boot_info_storage.fb_type = tag->fb_type;
+    if (tag->fb_type == BOOT_FB_TYPE_RGB) {
+        if (tag->size < sizeof(*tag)) {
+            /* RGB without a color layout is unusable */
+            boot_info_storage.fb_addr = 0;
+            return;
+        }
+        boot_info_storage.fb_red_pos = tag->red_pos;
+        boot_info_storage.fb_red_size = tag->red_size;
+        boot_info_storage.fb_green_pos = tag->green_pos;
+        boot_info_storage.fb_green_size = tag->green_size;
+        boot_info_storage.fb_blue_pos = tag->blue_pos;
+        boot_info_storage.fb_blue_size = tag->blue_size;
+    }
+    kprintf("[BOOT] framebuffer: addr=%llx %ux%u bpp=%u pitch=%u type=%u\n",
+            (unsigned long long)tag->addr,
+            (unsigned)tag->width, (unsigned)tag->height,
+            (unsigned)tag->bpp, (unsigned)tag->pitch, (unsigned)tag->fb_type);
+}
+
 int boot_early_init(boot_info_t* info)
 {
     if (!info) {
@@ -197,6 +248,10 @@ int boot_early_init(boot_info_t* info)
     boot_info_storage.mmap_size = 0;
     boot_info_storage.mmap_entry_size = 0;
     __asm__ volatile ("" ::: "memory");
+
+    boot_info_storage.fb_addr = 0;
+    boot_info_storage.fb_type = 0;
+    __asm__ volatile ("" ::: "memory");
     
     /* Initialize cmdline buffer to empty string (fixed buffer) */
     boot_info_storage.cmdline[0] = '\0';
@@ -242,6 +297,9 @@ int boot_early_init(boot_info_t* info)
                 case MB2_TAG_MMAP:
                     mb2_parse_mmap((const struct multiboot2_tag_mmap*)tag);
                     break;
+                case MB2_TAG_FRAMEBUFFER:
+                    mb2_parse_framebuffer((const struct multiboot2_tag_framebuffer*)tag);
+                    break;
                 default:
                     break;
             }
@@ -0,0 +1,250 @@
+/**
+ * @file clocksource.c
+ * @brief x86_64 clocksource selection: invariant TSC, HPET
+ *
+ * The HPET has a frequency stated by hardware, so it is the reference: the
+ * TSC is calibrated against it (or taken from CPUID.15H when there is no
+ * HPET). An invariant TSC is preferred because RDTSC is cheap; otherwise a
+ * 64-bit HPET main counter is read directly. The calibrated TSC frequency is
+ * also what the LAPIC timer needs for TSC-deadline mode.
+ */
+
+#include "clocksource.h"
+#include "hpet.h"
+#include "../../core/clock.h"
+#include "../../../include/console.h"
+#include <stddef.h>
+
+#define CPUID1_ECX_TSC_DEADLINE     (1u << 24)
+#define CPUIDX7_EDX_INVARIANT_TSC   (1u << 8)
+
+/* 20 ms calibration windows, median of three */
+#define TSC_CALIBRATE_DIV           50u
+#define TSC_CALIBRATE_ROUNDS        3u
+#define TSC_CALIBRATE_SPIN_LIMIT    100000000ULL
+
+typedef enum {
+    CLOCKSOURCE_NONE = 0,
+    CLOCKSOURCE_TSC,
+    CLOCKSOURCE_HPET
+} clocksource_kind_t;
+
+static clocksource_kind_t cs_kind = CLOCKSOURCE_NONE;
+static uint64_t cs_hz = 0;
+static uint64_t cs_base = 0;
+static volatile uint64_t cs_last_ns = 0;
+
+static uint64_t tsc_hz = 0;
+static bool tsc_invariant = false;
+static bool tsc_deadline = false;
+
+static inline void cs_cpuid(uint32_t leaf, uint32_t subleaf,
+                            uint32_t* eax, uint32_t* ebx, uint32_t* ecx, uint32_t* edx)
+{
+    uint32_t a, b, c, d;
+    __asm__ volatile ("cpuid"
+                      : "=a"(a), "=b"(b), "=c"(c), "=d"(d)
+                      : "a"(leaf), "c"(subleaf));
+    if (eax) *eax = a;
+    if (ebx) *ebx = b;
+    if (ecx) *ecx = c;
+    if (edx) *edx = d;
+}
+
+static void tsc_probe_features(void)
+{
+    uint32_t max_basic = 0, max_ext = 0;
+    cs_cpuid(0, 0, &max_basic, NULL, NULL, NULL);
+    cs_cpuid(0x80000000u, 0, &max_ext, NULL, NULL, NULL);
+
+    if (max_basic >= 1) {
+        uint32_t ecx1 = 0;
+        cs_cpuid(1, 0, NULL, NULL, &ecx1, NULL);
+        tsc_deadline = (ecx1 & CPUID1_ECX_TSC_DEADLINE) != 0;
+    }
+    if (max_ext >= 0x80000007u) {
+        uint32_t edx = 0;
+        cs_cpuid(0x80000007u, 0, NULL, NULL, NULL, &edx);
+        tsc_invariant = (edx & CPUIDX7_EDX_INVARIANT_TSC) != 0;
+    }
+}
+
+/* CPUID.15H: TSC/crystal ratio, only usable when the crystal rate is given */
+static uint64_t tsc_hz_from_cpuid(void)
+{
+    uint32_t max_basic = 0;
+    cs_cpuid(0, 0, &max_basic, NULL, NULL, NULL);
+    if (max_basic < 0x15u) {
+        return 0;
+    }
+    uint32_t den = 0, num = 0, crystal = 0;
+    cs_cpuid(0x15u, 0, &den, &num, &crystal, NULL);
+    if (den == 0 || num == 0 || crystal == 0) {
+        return 0;
+    }
+    return ((uint64_t)crystal * (uint64_t)num) / (uint64_t)den;
+}
+
+static inline uint64_t hpet_delta(uint64_t from, uint64_t to)
+{
+    if (!hpet_counter_is_64bit()) {
+        return (uint64_t)(uint32_t)((uint32_t)to - (uint32_t)from);
+    }
+    return to - from;
+}
+
+/* Interrupts are still off during bring-up, so the windows are not stretched */
+static uint64_t tsc_calibrate_hpet(void)
+{
+    uint64_t ref_hz = hpet_frequency();
+    uint64_t window = ref_hz / TSC_CALIBRATE_DIV;
+    uint64_t samples[TSC_CALIBRATE_ROUNDS];
+
+    if (window == 0) {
+        return 0;
+    }
+
+    for (uint32_t round = 0; round < TSC_CALIBRATE_ROUNDS; round++) {
+        uint64_t h0 = hpet_read_counter();
+        uint64_t t0 = clocksource_rdtsc();
+        uint64_t h1 = h0;
+        uint64_t spin = 0;
+        while (hpet_delta(h0, h1) < window) {
+            __asm__ volatile ("pause");
+            if (++spin > TSC_CALIBRATE_SPIN_LIMIT) {
+                kputs("[CLOCK] HPET counter does not advance\n");
+                return 0;
+            }
+            h1 = hpet_read_counter();
+        }
+        uint64_t t1 = clocksource_rdtsc();
+
+        uint64_t ns = clock_cycles_to_ns(hpet_delta(h0, h1), ref_hz);
+        if (ns == 0) {
+            return 0;
+        }
+        samples[round] = ((t1 - t0) * CLOCK_NSEC_PER_SEC) / ns;
+    }
+
+    /* Median of three */
+    for (uint32_t i = 1; i < TSC_CALIBRATE_ROUNDS; i++) {
+        uint64_t v = samples[i];
+        uint32_t j = i;
+        while (j > 0 && samples[j - 1] > v) {
+            samples[j] = samples[j - 1];
+            j--;
+        }
+        samples[j] = v;
+    }
+    return samples[TSC_CALIBRATE_ROUNDS / 2];
+}
+
+static uint64_t cs_read(void)
+{
+    switch (cs_kind) {
+    case CLOCKSOURCE_TSC:
+        return clocksource_rdtsc();
+    case CLOCKSOURCE_HPET:
+        return hpet_read_counter();
+    default:
+        return 0;
+    }
+}
+
+int clocksource_init(void)
+{
+    if (cs_kind != CLOCKSOURCE_NONE) {
+        return 0;
+    }
+
+    tsc_probe_features();
+
+    const char* tsc_ref = "none";
+    if (hpet_init() == 0) {
+        tsc_hz = tsc_calibrate_hpet();
+        tsc_ref = "hpet";
+    }
+    if (tsc_hz == 0) {
+        tsc_hz = tsc_hz_from_cpuid();
+        tsc_ref = tsc_hz ? "cpuid" : "none";
+    }
+
+    if (tsc_invariant && tsc_hz != 0) {
+        cs_kind = CLOCKSOURCE_TSC;
+        cs_hz = tsc_hz;
+    } else if (hpet_is_available() && hpet_counter_is_64bit()) {
+        cs_kind = CLOCKSOURCE_HPET;
+        cs_hz = hpet_frequency();
+    }
+
+    kprintf("[CLOCK] tsc=%llu Hz (ref=%s invariant=%u deadline=%u)\n",
+            (unsigned long long)tsc_hz,
+            tsc_ref,
+            tsc_invariant ? 1u : 0u,
+            clocksource_tsc_deadline_ok() ? 1u : 0u);
+
+    if (cs_kind == CLOCKSOURCE_NONE) {
+        kputs("[CLOCK] no clocksource, uptime stays on timer ticks\n");
+        return -1;
+    }
+    cs_base = cs_read();
+    cs_last_ns = 0;
+    kprintf("[CLOCK] clocksource %s %llu Hz\n",
+            clocksource_name(), (unsigned long long)cs_hz);
+    return 0;
+}
+
+bool clocksource_available(void)
+{
+    return cs_kind != CLOCKSOURCE_NONE;
+}
+
+uint64_t clocksource_monotonic_ns(void)
+{
+    if (cs_kind == CLOCKSOURCE_NONE) {
+        return 0;
+    }
+    uint64_t ns = clock_cycles_to_ns(cs_read() - cs_base, cs_hz);
+
+    /* TSCs of different CPUs may be a few cycles apart: never step back */
+    uint64_t last = __atomic_load_n(&cs_last_ns, __ATOMIC_RELAXED);
+    while (ns > last) {
+        if (__atomic_compare_exchange_n(&cs_last_ns, &last, ns, false,
+                                        __ATOMIC_RELAXED, __ATOMIC_RELAXED)) {
+            return ns;
+        }
+    }
+    return last;
+}
+
+const char* clocksource_name(void)
+{
+    switch (cs_kind) {
+    case CLOCKSOURCE_TSC:
+        return "tsc";
+    case CLOCKSOURCE_HPET:
+        return "hpet";
+    default:
+        return "none";
+    }
+}
+
+uint64_t clocksource_frequency(void)
+{
+    return cs_hz;
+}
+
+uint64_t clocksource_tsc_hz(void)
+{
+    return tsc_hz;
+}
+
+bool clocksource_tsc_invariant(void)
+{
+    return tsc_invariant;
+}
+
+bool clocksource_tsc_deadline_ok(void)
+{
+    return tsc_deadline && tsc_hz != 0;
+}
@@ -0,0 +1,30 @@
+/**
+ * @file clocksource.h
+ * @brief x86_64 clocksource internals shared with the LAPIC timer
+ *
+ * The generic interface lives in core/clock.h.
+ */
+
+#ifndef _RODNIX_ARCH_X86_64_CLOCKSOURCE_H
+#define _RODNIX_ARCH_X86_64_CLOCKSOURCE_H
+
+#include <stdbool.h>
+#include <stdint.h>
+
+/* Calibrated TSC frequency in Hz, 0 if the TSC was not calibrated */
+uint64_t clocksource_tsc_hz(void);
+
+/* CPUID.80000007H:EDX[8] — TSC rate does not depend on P/C-states */
+bool clocksource_tsc_invariant(void);
+
+/* CPUID.01H:ECX[24] and a calibrated TSC: LAPIC can run in TSC-deadline mode */
+bool clocksource_tsc_deadline_ok(void);
+
+static inline uint64_t clocksource_rdtsc(void)
+{
+    uint32_t lo, hi;
+    __asm__ volatile ("rdtsc" : "=a"(lo), "=d"(hi));
+    return ((uint64_t)hi << 32) | lo;
+}
+
+#endif /* _RODNIX_ARCH_X86_64_CLOCKSOURCE_H */
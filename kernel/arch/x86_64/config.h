@@ -17,6 +17,14 @@
 #define X86_64_PAGE_SIZE_2MB  2097152
 #define X86_64_PAGE_SIZE_1GB  1073741824
 
+/* Kernel window for device MMIO mappings (below the IOAPIC/HPET/LAPIC pages) */
+#define X86_64_MMIO_VIRT_BASE 0xFFFFFFFFC0000000ULL
+#define X86_64_MMIO_VIRT_END  0xFFFFFFFFFEC00000ULL
+
+/* Kernel window for loadable module images, the only executable memory besides .text */
+#define X86_64_KMOD_VIRT_BASE 0xFFFFFFFF7C000000ULL
+#define X86_64_KMOD_VIRT_END  0xFFFFFFFF80000000ULL
+
 /* Macros for address conversion */
 #define X86_64_VIRT_TO_PHYS(addr) ((uintptr_t)(addr) - X86_64_KERNEL_VIRT_BASE)
 #define X86_64_PHYS_TO_VIRT(addr) ((void*)((uintptr_t)(addr) + X86_64_KERNEL_VIRT_BASE))
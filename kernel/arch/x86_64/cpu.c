@@ -6,6 +6,12 @@
 #include "../../core/cpu.h"
 #include "types.h"
 #include "gdt.h"
+#include "fpu.h"
+#include "cpu_prot.h"
+#include "idt.h"
+#include "percpu.h"
+#include "apic.h"
+#include "syscall_fast.h"
 #include "../../../include/common.h"
 #include <stddef.h>
 
@@ -61,17 +67,11 @@ int cpu_init(void)
         return 0;
     }
 
-    /* Enable SSE/SSE2 for compiler-generated XMM instructions */
-    uint64_t cr0, cr4;
-    __asm__ volatile ("mov %%cr0, %0" : "=r"(cr0));
-    cr0 &= ~(1ULL << 2);  /* Clear EM (x87 emulation) */
-    cr0 |= (1ULL << 1);   /* Set MP (monitor co-processor) */
-    __asm__ volatile ("mov %0, %%cr0" : : "r"(cr0));
+    /* x87/SSE/AVX for user mode; the kernel itself is built without SIMD */
+    fpu_init_cpu();
 
-    __asm__ volatile ("mov %%cr4, %0" : "=r"(cr4));
-    cr4 |= (1ULL << 9);   /* OSFXSR */
-    cr4 |= (1ULL << 10);  /* OSXMMEXCPT */
-    __asm__ volatile ("mov %0, %%cr4" : : "r"(cr4));
+    /* SMEP/SMAP/UMIP + NX: user pages are off limits outside uaccess windows */
+    cpu_prot_init_cpu();
 
     /* Initialize GDT/TSS (user segments + RSP0) */
     gdt_init();
@@ -187,6 +187,8 @@ int cpu_init(void)
     __asm__ volatile ("" ::: "memory");
     cpu_info_cache.apic_id = (ebx1 >> 24) & 0xFFu;
     __asm__ volatile ("" ::: "memory");
+    /* BSP per-CPU block: GS base is valid from here on */
+    percpu_init_cpu(0, cpu_info_cache.apic_id);
     cpu_info_cache.family = cpu_extract_display_family(eax1);
     __asm__ volatile ("" ::: "memory");
     cpu_info_cache.model_id = cpu_extract_display_model(eax1);
@@ -241,10 +243,32 @@ int cpu_get_info(cpu_info_t* info)
     return 0;
 }
 
+/**
+ * Arch bring-up of an application processor, called from smp_ap_entry()
+ * on the AP itself. The AP arrives from the trampoline with paging on the
+ * kernel PML4 and a private stack, but with the trampoline GDT and no IDT.
+ */
+void cpu_init_ap(uint32_t cpu, uint32_t apic_id)
+{
+    fpu_init_cpu();
+    cpu_prot_init_cpu();
+    gdt_init_cpu(cpu);
+    percpu_init_cpu(cpu, apic_id);
+    idt_load();
+    (void)x86_64_syscall_fast_init();
+}
+
+void x86_64_cpu_set_count(uint32_t count)
+{
+    if (count == 0 || count > CPU_MAX_COUNT) {
+        return;
+    }
+    cpu_count = count;
+}
+
 uint32_t cpu_get_id(void)
 {
-    /* TODO: Get real CPU ID via APIC */
-    return 0;
+    return percpu_self()->cpu_id;
 }
 
 uint32_t cpu_get_count(void)
@@ -252,6 +276,19 @@ uint32_t cpu_get_count(void)
     return cpu_count;
 }
 
+void cpu_kick(uint32_t cpu_id)
+{
+    if (cpu_id >= CPU_MAX_COUNT || cpu_id == cpu_get_id()) {
+        return;
+    }
+    x86_percpu_t* pc = percpu_get(cpu_id);
+    if (!pc || !pc->online) {
+        return;
+    }
+    /* Timer vector: the target goes through scheduler_tick() and dispatch */
+    apic_send_ipi(pc->apic_id, 32);
+}
+
 void cpu_save_context(thread_context_t* ctx)
 {
     if (!ctx) {
@@ -0,0 +1,150 @@
+/**
+ * @file cpu_prot.c
+ * @brief x86_64 supervisor protections: SMEP, SMAP, UMIP and NX
+ *
+ * SMEP stops the kernel from executing user pages, SMAP from touching them.
+ * Kernel code that has to read or write user memory opens an explicit window
+ * with cpu_user_access_begin() (STAC) and closes it with
+ * cpu_user_access_end() (CLAC). Windows are short and never span a sleep:
+ * the thread switch does not carry RFLAGS.AC. Interrupt and exception entry
+ * close the window for the handler; IRETQ reopens it for the interrupted
+ * code. SYSCALL entry clears AC through SFMASK.
+ *
+ * The decision is taken on the BSP from CPUID.7.0; every AP enables the
+ * same set so the window state means the same thing on all CPUs.
+ */
+
+#include "cpu_prot.h"
+#include "../../core/cpu.h"
+#include <stddef.h>
+
+#define CPUID7_EBX_SMEP     (1u << 7)
+#define CPUID7_EBX_SMAP     (1u << 20)
+#define CPUID7_ECX_UMIP     (1u << 2)
+#define CPUIDX1_EDX_NX      (1u << 20)
+
+#define MSR_EFER            0xC0000080u
+#define EFER_NXE            (1ULL << 11)
+
+static bool prot_configured = false;
+static bool prot_smep = false;
+static bool prot_smap = false;
+static bool prot_umip = false;
+static bool prot_nx = false;
+
+static inline void prot_cpuid(uint32_t leaf, uint32_t subleaf,
+                              uint32_t* eax, uint32_t* ebx, uint32_t* ecx, uint32_t* edx)
+{
+    uint32_t a, b, c, d;
+    __asm__ volatile ("cpuid"
+                      : "=a"(a), "=b"(b), "=c"(c), "=d"(d)
+                      : "a"(leaf), "c"(subleaf));
+    if (eax) *eax = a;
+    if (ebx) *ebx = b;
+    if (ecx) *ecx = c;
+    if (edx) *edx = d;
+}
+
+static inline uint64_t prot_rdmsr(uint32_t msr)
+{
+    uint32_t lo, hi;
+    __asm__ volatile ("rdmsr" : "=a"(lo), "=d"(hi) : "c"(msr));
+    return ((uint64_t)hi << 32) | lo;
+}
+
+static inline void prot_wrmsr(uint32_t msr, uint64_t value)
+{
+    __asm__ volatile ("wrmsr"
+                      :
+                      : "c"(msr), "a"((uint32_t)value), "d"((uint32_t)(value >> 32)));
+}
+
+/* BSP: pick the feature set */
+static void prot_configure(void)
+{
+    uint32_t max_basic = 0;
+    uint32_t max_ext = 0;
+    prot_cpuid(0, 0, &max_basic, NULL, NULL, NULL);
+    prot_cpuid(0x80000000u, 0, &max_ext, NULL, NULL, NULL);
+
+    if (max_basic >= 7) {
+        uint32_t ebx7 = 0, ecx7 = 0;
+        prot_cpuid(7, 0, NULL, &ebx7, &ecx7, NULL);
+        prot_smep = (ebx7 & CPUID7_EBX_SMEP) != 0;
+        prot_smap = (ebx7 & CPUID7_EBX_SMAP) != 0;
+        prot_umip = (ecx7 & CPUID7_ECX_UMIP) != 0;
+    }
+    if (max_ext >= 0x80000001u) {
+        uint32_t edx = 0;
+        prot_cpuid(0x80000001u, 0, NULL, NULL, NULL, &edx);
+        prot_nx = (edx & CPUIDX1_EDX_NX) != 0;
+    }
+    prot_configured = true;
+}
+
+void cpu_prot_init_cpu(void)
+{
+    if (!prot_configured) {
+        prot_configure();
+    }
+
+    /* boot.S and the AP trampoline set NXE already; keep it explicit */
+    if (prot_nx) {
+        uint64_t efer = prot_rdmsr(MSR_EFER);
+        if (!(efer & EFER_NXE)) {
+            prot_wrmsr(MSR_EFER, efer | EFER_NXE);
+        }
+    }
+
+    /* Close any window before SMAP starts checking */
+    if (prot_smap) {
+        __asm__ volatile ("clac" ::: "memory");
+    }
+
+    uint64_t cr4;
+    __asm__ volatile ("mov %%cr4, %0" : "=r"(cr4));
+    if (prot_smep) {
+        cr4 |= CR4_SMEP;
+    }
+    if (prot_smap) {
+        cr4 |= CR4_SMAP;
+    }
+    if (prot_umip) {
+        cr4 |= CR4_UMIP;
+    }
+    __asm__ volatile ("mov %0, %%cr4" : : "r"(cr4) : "memory");
+}
+
+bool cpu_prot_smep(void)
+{
+    return prot_smep;
+}
+
+bool cpu_prot_smap(void)
+{
+    return prot_smap;
+}
+
+bool cpu_prot_umip(void)
+{
+    return prot_umip;
+}
+
+bool cpu_prot_nx(void)
+{
+    return prot_nx;
+}
+
+void cpu_user_access_begin(void)
+{
+    if (prot_smap) {
+        __asm__ volatile ("stac" ::: "memory", "cc");
+    }
+}
+
+void cpu_user_access_end(void)
+{
+    if (prot_smap) {
+        __asm__ volatile ("clac" ::: "memory", "cc");
+    }
+}
@@ -0,0 +1,33 @@
+/**
+ * @file cpu_prot.h
+ * @brief x86_64 supervisor protections: SMEP, SMAP, UMIP
+ *
+ * The generic user-access window (cpu_user_access_begin/end) is declared in
+ * core/cpu.h. This header carries the per-CPU enable hook and the state
+ * the page fault handler needs to report violations.
+ */
+
+#ifndef _RODNIX_ARCH_X86_64_CPU_PROT_H
+#define _RODNIX_ARCH_X86_64_CPU_PROT_H
+
+#include <stdbool.h>
+#include <stdint.h>
+
+#define CR4_UMIP            (1ULL << 11)
+#define CR4_SMEP            (1ULL << 20)
+#define CR4_SMAP            (1ULL << 21)
+
+#define RFLAGS_AC           (1ULL << 18)
+
+/**
+ * Enable SMEP/SMAP/UMIP on the calling CPU when CPUID reports them and
+ * make sure EFER.NXE is set. The BSP decides; APs enable the same set.
+ */
+void cpu_prot_init_cpu(void);
+
+bool cpu_prot_smep(void);
+bool cpu_prot_smap(void);
+bool cpu_prot_umip(void);
+bool cpu_prot_nx(void);
+
+#endif /* _RODNIX_ARCH_X86_64_CPU_PROT_H */
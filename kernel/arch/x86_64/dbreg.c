@@ -0,0 +1,138 @@
+/**
+ * @file dbreg.c
+ * @brief x86_64 debug registers (DR0-DR3, DR6, DR7)
+ */
+
+#include "dbreg.h"
+#include "percpu.h"
+#include "../../../include/error.h"
+
+/* DR7: L0..L3 enable bits, then R/W and LEN nibbles from bit 16 */
+#define DR7_LOCAL(slot)     (1ULL << ((slot) * 2u))
+#define DR7_RW_SHIFT(slot)  (16u + (slot) * 4u)
+#define DR7_LEN_SHIFT(slot) (18u + (slot) * 4u)
+#define DR6_RESERVED_ONES   0xFFFF0FF0ULL
+
+typedef struct {
+    uint64_t addr;
+    uint32_t type;
+    uint32_t len;
+    bool armed;
+} dbreg_slot_t;
+
+/*
+ * LOCKING: none
+ *   Written only by the kernel debugger, which runs alone with interrupts
+ *   off; other CPUs read it from their timer tick once dbreg_gen moves.
+ */
+static dbreg_slot_t dbreg_table[DBREG_COUNT];
+static volatile uint32_t dbreg_gen = 1;
+
+static uint64_t dbreg_len_bits(uint32_t len)
+{
+    switch (len) {
+        case 2: return 1;
+        case 8: return 2;
+        case 4: return 3;
+        default: return 0;
+    }
+}
+
+static void dbreg_load(void)
+{
+    uint64_t dr7 = 0;
+    uint64_t addr[DBREG_COUNT] = { 0, 0, 0, 0 };
+
+    for (uint32_t i = 0; i < DBREG_COUNT; i++) {
+        if (!dbreg_table[i].armed) {
+            continue;
+        }
+        addr[i] = dbreg_table[i].addr;
+        dr7 |= DR7_LOCAL(i);
+        dr7 |= (uint64_t)dbreg_table[i].type << DR7_RW_SHIFT(i);
+        dr7 |= dbreg_len_bits(dbreg_table[i].len) << DR7_LEN_SHIFT(i);
+    }
+
+    /* Disable first so a half-written slot never fires */
+    __asm__ volatile ("mov %0, %%dr7" : : "r"(0ULL));
+    __asm__ volatile ("mov %0, %%dr0" : : "r"(addr[0]));
+    __asm__ volatile ("mov %0, %%dr1" : : "r"(addr[1]));
+    __asm__ volatile ("mov %0, %%dr2" : : "r"(addr[2]));
+    __asm__ volatile ("mov %0, %%dr3" : : "r"(addr[3]));
+    __asm__ volatile ("mov %0, %%dr7" : : "r"(dr7));
+}
+
+static void dbreg_publish(void)
+{
+    uint32_t gen = __atomic_add_fetch(&dbreg_gen, 1u, __ATOMIC_RELEASE);
+    dbreg_load();
+    percpu_self()->dbreg_gen = gen;
+}
+
+int dbreg_set(uint32_t slot, uint64_t addr, uint32_t type, uint32_t len)
+{
+    if (slot >= DBREG_COUNT) {
+        return RDNX_E_INVALID;
+    }
+    if (type != DBREG_EXEC && type != DBREG_WRITE && type != DBREG_RW) {
+        return RDNX_E_INVALID;
+    }
+    if (type == DBREG_EXEC) {
+        len = 1;
+    } else if ((len != 1 && len != 2 && len != 4 && len != 8) || (addr & (len - 1u)) != 0) {
+        return RDNX_E_INVALID;
+    }
+
+    dbreg_table[slot].addr = addr;
+    dbreg_table[slot].type = type;
+    dbreg_table[slot].len = len;
+    dbreg_table[slot].armed = true;
+    dbreg_publish();
+    return RDNX_OK;
+}
+
+int dbreg_clear(uint32_t slot)
+{
+    if (slot >= DBREG_COUNT) {
+        return RDNX_E_INVALID;
+    }
+    dbreg_table[slot].armed = false;
+    dbreg_publish();
+    return RDNX_OK;
+}
+
+bool dbreg_get(uint32_t slot, uint64_t* addr, uint32_t* type, uint32_t* len)
+{
+    if (slot >= DBREG_COUNT || !dbreg_table[slot].armed) {
+        return false;
+    }
+    if (addr) {
+        *addr = dbreg_table[slot].addr;
+    }
+    if (type) {
+        *type = dbreg_table[slot].type;
+    }
+    if (len) {
+        *len = dbreg_table[slot].len;
+    }
+    return true;
+}
+
+void dbreg_sync(void)
+{
+    x86_percpu_t* pc = percpu_self();
+    uint32_t gen = __atomic_load_n(&dbreg_gen, __ATOMIC_ACQUIRE);
+    if (pc->dbreg_gen == gen) {
+        return;
+    }
+    dbreg_load();
+    pc->dbreg_gen = gen;
+}
+
+uint64_t dbreg_take_status(void)
+{
+    uint64_t dr6;
+    __asm__ volatile ("mov %%dr6, %0" : "=r"(dr6));
+    __asm__ volatile ("mov %0, %%dr6" : : "r"(DR6_RESERVED_ONES));
+    return dr6;
+}
@@ -0,0 +1,57 @@
+/**
+ * @file dbreg.h
+ * @brief x86_64 debug registers (DR0-DR3, DR6, DR7)
+ *
+ * One global breakpoint table shared by all CPUs. The CPU that changes it
+ * loads it at once; every other CPU picks the new generation up on its
+ * next timer tick (dbreg_sync), so no cross-CPU call is needed while the
+ * debugger has the machine stopped.
+ */
+
+#ifndef _RODNIX_ARCH_X86_64_DBREG_H
+#define _RODNIX_ARCH_X86_64_DBREG_H
+
+#include <stdint.h>
+#include <stdbool.h>
+
+#define DBREG_COUNT 4
+
+/* DR7 R/W field encodings */
+#define DBREG_EXEC  0u
+#define DBREG_WRITE 1u
+#define DBREG_RW    3u
+
+/* DR6 status bits */
+#define DBREG_DR6_B0 0x1ULL     /* B0..B3: slot N matched */
+#define DBREG_DR6_BS (1ULL << 14) /* single step */
+
+/* RFLAGS bits used for stepping and resuming past an execute breakpoint */
+#define DBREG_RFLAGS_TF (1ULL << 8)
+#define DBREG_RFLAGS_RF (1ULL << 16)
+
+/**
+ * Arm a breakpoint slot on all CPUs.
+ * @param slot 0..DBREG_COUNT-1
+ * @param addr Linear address (aligned to @p len for data breakpoints)
+ * @param type DBREG_EXEC, DBREG_WRITE or DBREG_RW
+ * @param len 1, 2, 4 or 8 bytes (must be 1 for DBREG_EXEC)
+ * @return RDNX_OK or RDNX_E_INVALID
+ */
+int dbreg_set(uint32_t slot, uint64_t addr, uint32_t type, uint32_t len);
+
+/* Disarm a slot on all CPUs; RDNX_E_INVALID for a bad slot */
+int dbreg_clear(uint32_t slot);
+
+/**
+ * Read back a slot.
+ * @return true if the slot is armed
+ */
+bool dbreg_get(uint32_t slot, uint64_t* addr, uint32_t* type, uint32_t* len);
+
+/* Load the table into this CPU if it changed since the last load */
+void dbreg_sync(void);
+
+/* Read DR6 and reset it for the next #DB */
+uint64_t dbreg_take_status(void);
+
+#endif /* _RODNIX_ARCH_X86_64_DBREG_H */
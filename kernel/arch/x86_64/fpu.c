@@ -0,0 +1,228 @@
+/**
+ * @file fpu.c
+ * @brief x86_64 per-thread x87/SSE/AVX state (FXSAVE/XSAVE)
+ *
+ * Switching is eager. The kernel is built without FP/SIMD code generation,
+ * so the live x87/SSE/AVX registers always hold the user context of the
+ * thread running on the CPU: the scheduler saves them when it switches away
+ * from a thread and reloads them when it switches back in.
+ *
+ * XSAVE/XRSTOR (standard format) is used when the CPU supports it, with x87,
+ * SSE, AVX and AVX-512 components enabled in XCR0 as available. Otherwise the
+ * 512-byte FXSAVE area is used. All CPUs run with the XCR0 chosen on the BSP.
+ */
+
+#include "fpu.h"
+#include "../../core/cpu.h"
+#include "../../common/heap.h"
+#include "../../../include/common.h"
+#include <stddef.h>
+
+#define CR0_MP              (1ULL << 1)
+#define CR0_EM              (1ULL << 2)
+#define CR0_TS              (1ULL << 3)
+#define CR4_OSFXSR          (1ULL << 9)
+#define CR4_OSXMMEXCPT      (1ULL << 10)
+#define CR4_OSXSAVE         (1ULL << 18)
+
+#define CPUID1_ECX_XSAVE    (1u << 26)
+#define CPUID1_ECX_AVX      (1u << 28)
+
+#define FPU_AREA_ALIGN      64u
+#define FPU_AREA_MAX        4096u
+
+static bool fpu_configured = false;
+static bool fpu_xsave = false;
+static uint64_t fpu_xcr0_mask = 0;
+static size_t fpu_area_size = FPU_FXSAVE_SIZE;
+
+/* Initial state: FNINIT + default MXCSR, XSTATE_BV = 0 (all components init) */
+static uint8_t fpu_init_area[FPU_AREA_MAX] __attribute__((aligned(FPU_AREA_ALIGN)));
+
+static inline void fpu_cpuid(uint32_t leaf, uint32_t subleaf,
+                             uint32_t* eax, uint32_t* ebx, uint32_t* ecx, uint32_t* edx)
+{
+    uint32_t a, b, c, d;
+    __asm__ volatile ("cpuid"
+                      : "=a"(a), "=b"(b), "=c"(c), "=d"(d)
+                      : "a"(leaf), "c"(subleaf));
+    if (eax) *eax = a;
+    if (ebx) *ebx = b;
+    if (ecx) *ecx = c;
+    if (edx) *edx = d;
+}
+
+static inline void fpu_xsetbv(uint32_t reg, uint64_t value)
+{
+    __asm__ volatile ("xsetbv"
+                      :
+                      : "c"(reg), "a"((uint32_t)value), "d"((uint32_t)(value >> 32))
+                      : "memory");
+}
+
+static inline void fpu_xsave_to(void* area)
+{
+    __asm__ volatile ("xsave64 (%0)" : : "r"(area), "a"(0xFFFFFFFFu), "d"(0xFFFFFFFFu) : "memory");
+}
+
+static inline void fpu_xrstor_from(const void* area)
+{
+    __asm__ volatile ("xrstor64 (%0)" : : "r"(area), "a"(0xFFFFFFFFu), "d"(0xFFFFFFFFu) : "memory");
+}
+
+/* Area size for the XCR0 currently programmed on this CPU */
+static size_t fpu_xsave_size(void)
+{
+    uint32_t ebx = 0;
+    fpu_cpuid(0xD, 0, NULL, &ebx, NULL, NULL);
+    return (size_t)ebx;
+}
+
+/* BSP: pick XCR0 and the area size */
+static void fpu_configure(void)
+{
+    uint32_t ecx1 = 0;
+    fpu_cpuid(1, 0, NULL, NULL, &ecx1, NULL);
+    if (!(ecx1 & CPUID1_ECX_XSAVE)) {
+        return;
+    }
+
+    uint32_t lo = 0, hi = 0;
+    fpu_cpuid(0xD, 0, &lo, NULL, NULL, &hi);
+    uint64_t supported = ((uint64_t)hi << 32) | lo;
+
+    uint64_t xcr0 = XCR0_X87 | XCR0_SSE;
+    if ((ecx1 & CPUID1_ECX_AVX) && (supported & XCR0_AVX)) {
+        xcr0 |= XCR0_AVX;
+        if ((supported & XCR0_AVX512) == XCR0_AVX512) {
+            xcr0 |= XCR0_AVX512;
+        }
+    }
+    fpu_xcr0_mask = xcr0;
+    fpu_xsave = true;
+}
+
+static void fpu_build_init_area(void)
+{
+    memset(fpu_init_area, 0, sizeof(fpu_init_area));
+    *(uint16_t*)(fpu_init_area + 0) = FPU_DEFAULT_FCW;
+    *(uint32_t*)(fpu_init_area + 24) = FPU_DEFAULT_MXCSR;
+}
+
+void fpu_init_cpu(void)
+{
+    uint64_t cr0, cr4;
+    __asm__ volatile ("mov %%cr0, %0" : "=r"(cr0));
+    cr0 &= ~(CR0_EM | CR0_TS);
+    cr0 |= CR0_MP;
+    __asm__ volatile ("mov %0, %%cr0" : : "r"(cr0));
+
+    if (!fpu_configured) {
+        fpu_configure();
+    }
+
+    __asm__ volatile ("mov %%cr4, %0" : "=r"(cr4));
+    cr4 |= CR4_OSFXSR | CR4_OSXMMEXCPT;
+    if (fpu_xsave) {
+        cr4 |= CR4_OSXSAVE;
+    }
+    __asm__ volatile ("mov %0, %%cr4" : : "r"(cr4));
+
+    if (fpu_xsave) {
+        fpu_xsetbv(0, fpu_xcr0_mask);
+    }
+
+    if (!fpu_configured) {
+        if (fpu_xsave) {
+            fpu_area_size = fpu_xsave_size();
+            if (fpu_area_size > FPU_AREA_MAX && (fpu_xcr0_mask & XCR0_AVX512)) {
+                /* Keep the area within the static template */
+                fpu_xcr0_mask &= ~XCR0_AVX512;
+                fpu_xsetbv(0, fpu_xcr0_mask);
+                fpu_area_size = fpu_xsave_size();
+            }
+        }
+        fpu_build_init_area();
+        fpu_configured = true;
+    }
+
+    cpu_fpu_reset();
+}
+
+bool fpu_uses_xsave(void)
+{
+    return fpu_xsave;
+}
+
+uint64_t fpu_xcr0(void)
+{
+    return fpu_xsave ? fpu_xcr0_mask : 0;
+}
+
+size_t cpu_fpu_state_size(void)
+{
+    return fpu_area_size;
+}
+
+void* cpu_fpu_state_alloc(void)
+{
+    /* kmalloc gives no 64-byte alignment: keep the raw pointer in front */
+    uint8_t* raw = (uint8_t*)kmalloc(fpu_area_size + FPU_AREA_ALIGN + sizeof(void*));
+    if (!raw) {
+        return NULL;
+    }
+    uintptr_t p = ((uintptr_t)raw + sizeof(void*) + FPU_AREA_ALIGN - 1u) &
+                  ~(uintptr_t)(FPU_AREA_ALIGN - 1u);
+    ((void**)p)[-1] = raw;
+    memcpy((void*)p, fpu_init_area, fpu_area_size);
+    return (void*)p;
+}
+
+void cpu_fpu_state_free(void* state)
+{
+    if (!state) {
+        return;
+    }
+    kfree(((void**)state)[-1]);
+}
+
+void cpu_fpu_state_copy(void* dst, const void* src)
+{
+    if (!dst || !src) {
+        return;
+    }
+    memcpy(dst, src, fpu_area_size);
+}
+
+void cpu_fpu_save(void* state)
+{
+    if (!state) {
+        return;
+    }
+    if (fpu_xsave) {
+        fpu_xsave_to(state);
+    } else {
+        __asm__ volatile ("fxsave64 (%0)" : : "r"(state) : "memory");
+    }
+}
+
+void cpu_fpu_restore(const void* state)
+{
+    if (!state) {
+        return;
+    }
+    if (fpu_xsave) {
+        fpu_xrstor_from(state);
+    } else {
+        __asm__ volatile ("fxrstor64 (%0)" : : "r"(state) : "memory");
+    }
+}
+
+void cpu_fpu_reset(void)
+{
+    if (!fpu_configured) {
+        __asm__ volatile ("fninit" ::: "memory");
+        return;
+    }
+    cpu_fpu_restore(fpu_init_area);
+}
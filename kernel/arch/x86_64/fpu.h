@@ -0,0 +1,39 @@
+/**
+ * @file fpu.h
+ * @brief x86_64 extended register state (x87/SSE/AVX)
+ *
+ * The generic per-thread interface (cpu_fpu_*) is declared in core/cpu.h.
+ * This header only carries the per-CPU enable hook and diagnostics.
+ */
+
+#ifndef _RODNIX_ARCH_X86_64_FPU_H
+#define _RODNIX_ARCH_X86_64_FPU_H
+
+#include <stdbool.h>
+#include <stdint.h>
+
+/* Legacy FXSAVE area: FCW at +0, MXCSR at +24 */
+#define FPU_FXSAVE_SIZE     512u
+#define FPU_XSAVE_HDR_SIZE  64u
+#define FPU_DEFAULT_FCW     0x037Fu
+#define FPU_DEFAULT_MXCSR   0x1F80u
+
+/* XCR0 state components */
+#define XCR0_X87            (1ULL << 0)
+#define XCR0_SSE            (1ULL << 1)
+#define XCR0_AVX            (1ULL << 2)
+#define XCR0_AVX512         (7ULL << 5)  /* opmask, ZMM_Hi256, Hi16_ZMM */
+
+/**
+ * Enable x87/SSE (and XSAVE/AVX when present) on the calling CPU.
+ * The BSP picks the XCR0 mask; APs program the same one.
+ */
+void fpu_init_cpu(void);
+
+/* XSAVE is in use (false: FXSAVE, 512-byte area) */
+bool fpu_uses_xsave(void);
+
+/* XCR0 programmed on every CPU (0 without XSAVE) */
+uint64_t fpu_xcr0(void);
+
+#endif /* _RODNIX_ARCH_X86_64_FPU_H */
@@ -1,9 +1,13 @@
 /**
  * @file gdt.c
  * @brief Minimal GDT/TSS setup for ring3 support
+ *
+ * Every logical CPU gets its own GDT copy and TSS: the TSS descriptor is
+ * marked busy by LTR and RSP0 differs per CPU.
  */
 
 #include "gdt.h"
+#include "percpu.h"
 #include <stdint.h>
 #include "../../include/common.h"
 
@@ -59,9 +63,8 @@ typedef struct {
     uint16_t iomap_base;
 } __attribute__((packed)) tss64_t;
 
-static gdt_table_t gdt;
-static tss64_t tss;
-uint64_t g_tss_rsp0_shadow = 0;
+static gdt_table_t gdt[CPU_MAX_COUNT];
+static tss64_t tss[CPU_MAX_COUNT];
 
 static void gdt_set_entry(gdt_entry_t* e, uint32_t base, uint32_t limit, uint8_t access, uint8_t gran)
 {
@@ -85,24 +88,41 @@ static void gdt_set_tss(gdt_tss_entry_t* e, uint64_t base, uint32_t limit)
     e->reserved = 0;
 }
 
-void gdt_init(void)
+void gdt_init_cpu(uint32_t cpu)
 {
-    memset(&gdt, 0, sizeof(gdt));
-    memset(&tss, 0, sizeof(tss));
+    if (cpu >= CPU_MAX_COUNT) {
+        return;
+    }
+    gdt_table_t* g = &gdt[cpu];
+    tss64_t* t = &tss[cpu];
+
+    memset(g, 0, sizeof(*g));
+    memset(t, 0, sizeof(*t));
 
-    gdt_set_entry(&gdt.kcode, 0, 0, 0x9A, 0xA0);
-    gdt_set_entry(&gdt.kdata, 0, 0, 0x92, 0xC0);
-    gdt_set_entry(&gdt.udata, 0, 0, 0xF2, 0xC0);
-    gdt_set_entry(&gdt.ucode, 0, 0, 0xFA, 0xA0);
+    gdt_set_entry(&g->kcode, 0, 0, 0x9A, 0xA0);
+    gdt_set_entry(&g->kdata, 0, 0, 0x92, 0xC0);
+    gdt_set_entry(&g->udata, 0, 0, 0xF2, 0xC0);
+    gdt_set_entry(&g->ucode, 0, 0, 0xFA, 0xA0);
 
-    tss.iomap_base = (uint16_t)sizeof(tss);
-    gdt_set_tss(&gdt.tss, (uint64_t)(uintptr_t)&tss, sizeof(tss) - 1);
+    t->iomap_base = (uint16_t)sizeof(*t);
+    gdt_set_tss(&g->tss, (uint64_t)(uintptr_t)t, sizeof(*t) - 1);
 
     gdt_ptr_t gdt_ptr;
-    gdt_ptr.limit = (uint16_t)(sizeof(gdt) - 1);
-    gdt_ptr.base = (uint64_t)(uintptr_t)&gdt;
+    gdt_ptr.limit = (uint16_t)(sizeof(*g) - 1);
+    gdt_ptr.base = (uint64_t)(uintptr_t)g;
 
     __asm__ volatile ("lgdt %0" : : "m"(gdt_ptr));
+    /* Reload CS through a far return; APs arrive here on the trampoline GDT. */
+    __asm__ volatile (
+        "pushq %0\n\t"
+        "leaq 1f(%%rip), %%rax\n\t"
+        "pushq %%rax\n\t"
+        "lretq\n\t"
+        "1:\n\t"
+        :
+        : "i"((uint64_t)GDT_KERNEL_CS)
+        : "memory", "rax"
+    );
     __asm__ volatile (
         "movw %0, %%ax\n\t"
         "mov %%ax, %%ds\n\t"
@@ -116,8 +136,18 @@ void gdt_init(void)
     __asm__ volatile ("ltr %0" : : "r"((uint16_t)GDT_TSS_SEL));
 }
 
+void gdt_init(void)
+{
+    gdt_init_cpu(0);
+}
+
 void tss_set_rsp0(uint64_t rsp0)
 {
-    tss.rsp0 = rsp0;
-    g_tss_rsp0_shadow = rsp0;
+    x86_percpu_t* pc = percpu_self();
+    uint32_t cpu = pc->cpu_id;
+    if (cpu >= CPU_MAX_COUNT) {
+        return;
+    }
+    tss[cpu].rsp0 = rsp0;
+    pc->kernel_rsp = rsp0;
 }
@@ -10,7 +10,7 @@
 #define GDT_TSS_SEL   0x28
 
 void gdt_init(void);
+void gdt_init_cpu(uint32_t cpu);
 void tss_set_rsp0(uint64_t rsp0);
-extern uint64_t g_tss_rsp0_shadow;
 
 #endif /* _RODNIX_ARCH_X86_64_GDT_H */
@@ -0,0 +1,122 @@
+/**
+ * @file hpet.c
+ * @brief HPET main counter for x86_64
+ *
+ * The HPET is discovered through the ACPI "HPET" table and mapped as a single
+ * uncached page. Only the free-running main counter is used; comparators are
+ * left alone and the legacy replacement route stays off so the PIT keeps
+ * IRQ 0.
+ */
+
+#include "hpet.h"
+#include "acpi.h"
+#include "paging.h"
+#include "../../../include/console.h"
+#include <stddef.h>
+
+#define HPET_MMIO_VIRT          0xFFFFFFFFFED00000ULL
+
+/* Register offsets */
+#define HPET_REG_CAP_ID         0x000
+#define HPET_REG_CONFIG         0x010
+#define HPET_REG_MAIN_COUNTER   0x0F0
+
+#define HPET_CAP_COUNT_SIZE_64  (1ULL << 13)
+#define HPET_CAP_PERIOD_SHIFT   32
+#define HPET_CONFIG_ENABLE      (1ULL << 0)
+#define HPET_CONFIG_LEGACY_RT   (1ULL << 1)
+
+/* The specification caps the tick period at 100 ns */
+#define HPET_MAX_PERIOD_FS      100000000ULL
+#define HPET_FS_PER_SEC         1000000000000000ULL
+
+static volatile uint8_t* hpet_regs = NULL;
+static bool hpet_available = false;
+static bool hpet_wide = false;
+static uint64_t hpet_hz = 0;
+
+static inline uint64_t hpet_read64(uint32_t off)
+{
+    return *(volatile uint64_t*)(hpet_regs + off);
+}
+
+static inline void hpet_write64(uint32_t off, uint64_t value)
+{
+    *(volatile uint64_t*)(hpet_regs + off) = value;
+}
+
+int hpet_init(void)
+{
+    if (hpet_available) {
+        return 0;
+    }
+
+    const struct acpi_hpet* tbl = (const struct acpi_hpet*)acpi_find_table("HPET");
+    if (!tbl || tbl->header.length < sizeof(*tbl)) {
+        kputs("[HPET] no ACPI HPET table\n");
+        return -1;
+    }
+    if (tbl->base_address.space_id != ACPI_GAS_SPACE_MEMORY ||
+        tbl->base_address.address == 0) {
+        kprintf("[HPET] unsupported address space %u\n",
+                (unsigned)tbl->base_address.space_id);
+        return -1;
+    }
+
+    uint64_t phys = tbl->base_address.address;
+    uint64_t page = phys & ~0xFFFULL;
+    if (paging_map_page_4kb(HPET_MMIO_VIRT, page, PTE_PRESENT | PTE_RW | PTE_PCD | PTE_NX) != 0) {
+        kputs("[HPET] failed to map registers\n");
+        return -1;
+    }
+    hpet_regs = (volatile uint8_t*)(uintptr_t)(HPET_MMIO_VIRT + (phys - page));
+
+    uint64_t cap = hpet_read64(HPET_REG_CAP_ID);
+    uint64_t period_fs = cap >> HPET_CAP_PERIOD_SHIFT;
+    if (period_fs == 0 || period_fs > HPET_MAX_PERIOD_FS) {
+        kprintf("[HPET] bogus period %llu fs\n", (unsigned long long)period_fs);
+        hpet_regs = NULL;
+        return -1;
+    }
+    hpet_hz = HPET_FS_PER_SEC / period_fs;
+    hpet_wide = (cap & HPET_CAP_COUNT_SIZE_64) != 0;
+
+    /* Counter runs free from here on; legacy routing stays off */
+    uint64_t cfg = hpet_read64(HPET_REG_CONFIG);
+    cfg &= ~HPET_CONFIG_LEGACY_RT;
+    cfg |= HPET_CONFIG_ENABLE;
+    hpet_write64(HPET_REG_CONFIG, cfg);
+
+    hpet_available = true;
+    kprintf("[HPET] phys=%llx freq=%llu Hz counter=%s\n",
+            (unsigned long long)phys,
+            (unsigned long long)hpet_hz,
+            hpet_wide ? "64-bit" : "32-bit");
+    return 0;
+}
+
+bool hpet_is_available(void)
+{
+    return hpet_available;
+}
+
+uint64_t hpet_read_counter(void)
+{
+    if (!hpet_available) {
+        return 0;
+    }
+    if (!hpet_wide) {
+        return (uint64_t)*(volatile uint32_t*)(hpet_regs + HPET_REG_MAIN_COUNTER);
+    }
+    return hpet_read64(HPET_REG_MAIN_COUNTER);
+}
+
+uint64_t hpet_frequency(void)
+{
+    return hpet_hz;
+}
+
+bool hpet_counter_is_64bit(void)
+{
+    return hpet_wide;
+}
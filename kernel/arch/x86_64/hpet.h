@@ -0,0 +1,33 @@
+/**
+ * @file hpet.h
+ * @brief HPET (High Precision Event Timer) main counter for x86_64
+ *
+ * Only the main counter is used: as a clocksource and as the reference for
+ * TSC calibration. Comparators stay disabled, legacy replacement is off.
+ */
+
+#ifndef _RODNIX_ARCH_X86_64_HPET_H
+#define _RODNIX_ARCH_X86_64_HPET_H
+
+#include <stdbool.h>
+#include <stdint.h>
+
+/**
+ * Find the HPET through ACPI ("HPET" table), map its registers and start
+ * the main counter.
+ * @return 0 on success, -1 if there is no usable HPET
+ */
+int hpet_init(void);
+
+bool hpet_is_available(void);
+
+/* Main counter value (zero-extended on 32-bit counters) */
+uint64_t hpet_read_counter(void);
+
+/* Main counter frequency in Hz */
+uint64_t hpet_frequency(void);
+
+/* True when the main counter is 64 bits wide (no wrap handling needed) */
+bool hpet_counter_is_64bit(void);
+
+#endif /* _RODNIX_ARCH_X86_64_HPET_H */
@@ -10,6 +10,8 @@
 
 #include "types.h"
 #include "config.h"
+#include "idt.h"
+#include "../../fabric/spin.h"
 #include "../../../include/debug.h"
 #include <stddef.h>
 #include <stdbool.h>
@@ -109,6 +111,22 @@ extern void irq13(void);
 extern void irq14(void);
 extern void irq15(void);
 extern void isr128(void);
+extern void isr240(void);
+
+/* Stubs for IDT_DYN_VECTOR_FIRST..IDT_DYN_VECTOR_LAST (0 for vector 128) */
+extern const uint64_t irq_dyn_stubs[];
+
+/* ============================================================================
+ * Dynamic Vector Allocator State
+ * ============================================================================ */
+
+/*
+ * LOCKING: idt_vector_lock
+ *   Protects: idt_vector_map.
+ *   Bit set = vector handed out by idt_vector_alloc().
+ */
+static uint32_t idt_vector_map[256 / 32];
+static spinlock_t idt_vector_lock;
 
 /* ============================================================================
  * Internal Helper Functions
@@ -142,11 +160,8 @@ static void idt_set_entry(uint8_t num, uint64_t base, uint16_t selector, uint8_t
  * IDT Entry Type Attributes
  * ============================================================================ */
 
-/* IDT entry type and attributes flags */
-#define IDT_TYPE_INTERRUPT_GATE  0x8E  /* 64-bit interrupt gate (IF cleared on entry) */
-#define IDT_TYPE_TRAP_GATE       0x8F  /* 64-bit trap gate (IF not cleared) */
+/* IDT entry type and attributes flags (gate types shared via idt.h) */
 #define IDT_TYPE_TASK_GATE       0x85  /* Task gate (not used in 64-bit mode) */
-#define IDT_TYPE_TRAP_GATE_USER  (IDT_TYPE_TRAP_GATE | 0x60) /* DPL=3 */
 
 /* Bit fields for type_attr byte:
  *   Bit 7: Present (1 = present, 0 = not present)
@@ -254,11 +269,27 @@ int idt_init(void)
     idt_set_entry(47, (uint64_t)irq15, 0x08, IDT_TYPE_INTERRUPT_GATE, 0);
     __asm__ volatile ("" ::: "memory");
 
+    /* Step 4.0: Dynamic vectors (MSI/MSI-X), stubs are always present */
+    for (uint32_t v = IDT_DYN_VECTOR_FIRST; v <= IDT_DYN_VECTOR_LAST; v++) {
+        uint64_t stub = irq_dyn_stubs[v - IDT_DYN_VECTOR_FIRST];
+        if (stub) {
+            idt_set_entry((uint8_t)v, stub, 0x08, IDT_TYPE_INTERRUPT_GATE, 0);
+        }
+    }
+    spinlock_init(&idt_vector_lock);
+    for (uint32_t i = 0; i < 256 / 32; i++) {
+        idt_vector_map[i] = 0;
+    }
+
     /* Step 4.1: Setup syscall handler (vector 128 / 0x80, DPL=3) */
     kputs("[IDT-4.1] Setup syscall 0x80\n");
     __asm__ volatile ("" ::: "memory");
     idt_set_entry(128, (uint64_t)isr128, 0x08, IDT_TYPE_TRAP_GATE_USER, 0);
     __asm__ volatile ("" ::: "memory");
+
+    /* Step 4.2: Cross-CPU call IPI (vector 0xF0) */
+    idt_set_entry(240, (uint64_t)isr240, 0x08, IDT_TYPE_INTERRUPT_GATE, 0);
+    __asm__ volatile ("" ::: "memory");
     
     /* Step 5: Load IDT */
     kputs("[IDT-5] Load IDT\n");
@@ -270,6 +301,18 @@ int idt_init(void)
     return 0;
 }
 
+/**
+ * @function idt_load
+ * @brief Load the shared IDT on the calling CPU
+ *
+ * Used by application processors: the table is built once by idt_init()
+ * on the BSP and every CPU points its IDTR at it.
+ */
+void idt_load(void)
+{
+    __asm__ volatile ("lidt %0" : : "m"(idt_pointer));
+}
+
 /**
  * @function idt_get_handler
  * @brief Get the handler address for a given interrupt vector
@@ -310,3 +353,84 @@ int idt_set_handler(uint16_t vector, void* handler, uint8_t type_attr, uint8_t i
     idt_set_entry(vector, (uint64_t)handler, 0x08, type_attr, ist);
     return 0;
 }
+
+static inline bool idt_vector_used(uint32_t v)
+{
+    return (idt_vector_map[v / 32] & (1u << (v % 32))) != 0;
+}
+
+static inline bool idt_vector_allocatable(uint32_t v)
+{
+    return v >= IDT_DYN_VECTOR_FIRST && v <= IDT_DYN_VECTOR_LAST &&
+           v != 128 && !idt_vector_used(v);
+}
+
+/**
+ * @function idt_vector_alloc
+ * @brief Allocate a block of dynamic interrupt vectors
+ *
+ * The search runs from the top of the range down: a higher vector has a
+ * higher LAPIC priority class, and the legacy IRQs stay the lowest.
+ *
+ * @param count Number of consecutive vectors (1-32)
+ * @param align Alignment of the first vector (power of two, 0 = 1)
+ * @return First vector, or -1 if no such block is free
+ */
+int idt_vector_alloc(uint32_t count, uint32_t align)
+{
+    if (count == 0 || count > 32) {
+        return -1;
+    }
+    if (align == 0) {
+        align = 1;
+    }
+    if ((align & (align - 1)) != 0) {
+        return -1;
+    }
+
+    int result = -1;
+    spinlock_lock(&idt_vector_lock);
+    uint32_t first = (IDT_DYN_VECTOR_LAST + 1 - count) & ~(align - 1);
+    while (first >= IDT_DYN_VECTOR_FIRST) {
+        uint32_t n = 0;
+        while (n < count && idt_vector_allocatable(first + n)) {
+            n++;
+        }
+        if (n == count) {
+            for (uint32_t i = 0; i < count; i++) {
+                idt_vector_map[(first + i) / 32] |= 1u << ((first + i) % 32);
+            }
+            result = (int)first;
+            break;
+        }
+        if (first < align) {
+            break;
+        }
+        first -= align;
+    }
+    spinlock_unlock(&idt_vector_lock);
+    return result;
+}
+
+/**
+ * @function idt_vector_free
+ * @brief Release vectors obtained from idt_vector_alloc()
+ */
+void idt_vector_free(uint32_t first, uint32_t count)
+{
+    spinlock_lock(&idt_vector_lock);
+    for (uint32_t v = first; v < first + count && v <= IDT_DYN_VECTOR_LAST; v++) {
+        if (v >= IDT_DYN_VECTOR_FIRST) {
+            idt_vector_map[v / 32] &= ~(1u << (v % 32));
+        }
+    }
+    spinlock_unlock(&idt_vector_lock);
+}
+
+bool idt_vector_is_allocated(uint32_t vector)
+{
+    if (vector < IDT_DYN_VECTOR_FIRST || vector > IDT_DYN_VECTOR_LAST) {
+        return false;
+    }
+    return idt_vector_used(vector);
+}
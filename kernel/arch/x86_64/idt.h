@@ -8,6 +8,7 @@
 #ifndef _RODNIX_ARCH_X86_64_IDT_H
 #define _RODNIX_ARCH_X86_64_IDT_H
 
+#include <stdbool.h>
 #include <stdint.h>
 
 /* IDT entry type and attributes flags */
@@ -15,9 +16,33 @@
 #define IDT_TYPE_TRAP_GATE       0x8F  /* 64-bit trap gate (IF not cleared) */
 #define IDT_TYPE_TRAP_GATE_USER  (IDT_TYPE_TRAP_GATE | 0x60) /* DPL=3 */
 
+/*
+ * Vectors handed out at runtime (MSI/MSI-X). Below is the legacy IRQ range
+ * 32-47, above are the IPI vectors (0xF0) and the spurious vector (0xFF).
+ * Vector 128 (int 0x80) is never allocated.
+ */
+#define IDT_DYN_VECTOR_FIRST  48
+#define IDT_DYN_VECTOR_LAST   239
+
 /* Initialize IDT */
 int idt_init(void);
 
+/**
+ * Allocate @p count consecutive free vectors whose first vector is a
+ * multiple of @p align (power of two; MSI multi-message needs count-aligned
+ * blocks). @return First vector, or -1 if no block is free
+ */
+int idt_vector_alloc(uint32_t count, uint32_t align);
+
+/* Return vectors obtained from idt_vector_alloc() */
+void idt_vector_free(uint32_t first, uint32_t count);
+
+/* Is @p vector currently allocated by idt_vector_alloc() */
+bool idt_vector_is_allocated(uint32_t vector);
+
+/* Load IDT on the calling CPU (application processors) */
+void idt_load(void);
+
 /* Get handler address */
 void* idt_get_handler(uint16_t vector);
 
@@ -14,7 +14,9 @@
 #include "idt.h"
 #include "pic.h"
 #include "apic.h"
+#include "percpu.h"
 #include "interrupt_frame.h"
+#include "../../fabric/spin.h"
 #include <stddef.h>
 #include <stdbool.h>
 
@@ -25,8 +27,17 @@
 /* Array of registered interrupt handlers (one per vector, 0-255) */
 interrupt_handler_t interrupt_handlers[256];
 
-/* Current Interrupt Request Level (IRQL) */
-volatile irql_t current_irql = IRQL_PASSIVE;
+/*
+ * LOCKING: IRQL is per-CPU (x86_percpu_t.irql).
+ *   On UP raising IRQL above PASSIVE only masks interrupts. With several CPUs
+ *   online the same transition also takes irql_giant, so every section that
+ *   relied on "cli == exclusive" keeps that meaning across CPUs.
+ *   Invariant: a CPU owns irql_giant iff its IRQL > PASSIVE.
+ *   Interrupt dispatch raises IRQL to DEVICE for the duration of the handler.
+ *   System calls run at APC: giant held, interrupts stay enabled.
+ */
+static spinlock_t irql_giant;
+static volatile uint32_t irql_giant_owner = 0xFFFFFFFFu;
 
 /* ============================================================================
  * External References
@@ -127,7 +138,8 @@ int interrupts_init(void)
     kputs("[INT-2] Set IRQL\n");
     __asm__ volatile ("" ::: "memory");
     /* Set initial IRQL to PASSIVE (lowest level, interrupts allowed) */
-    current_irql = IRQL_PASSIVE;
+    spinlock_init(&irql_giant);
+    percpu_self()->irql = (uint32_t)IRQL_PASSIVE;
     __asm__ volatile ("" ::: "memory");
     
     kputs("[INT-4] Init PIC (early, will disable if APIC works)\n");
@@ -188,13 +200,7 @@ int interrupt_unregister(uint32_t vector)
  */
 void interrupts_enable(void)
 {
-    /* Set IRQL to PASSIVE and enable interrupts */
-    /* Use volatile to prevent optimization issues */
-    volatile irql_t* irql_ptr = &current_irql;
-    *irql_ptr = IRQL_PASSIVE;
-    
-    /* Enable interrupts - execute sti directly without barriers */
-    __asm__ volatile ("sti");
+    (void)set_irql(IRQL_PASSIVE);
 }
 
 /**
@@ -208,16 +214,51 @@ void interrupts_enable(void)
  */
 void interrupts_disable(void)
 {
-    /* Disable interrupts and set IRQL to HIGH */
-    __asm__ volatile ("cli");
-    __asm__ volatile ("" ::: "memory"); /* Memory barrier */
-    current_irql = IRQL_HIGH;
-    __asm__ volatile ("" ::: "memory"); /* Memory barrier */
+    (void)set_irql(IRQL_HIGH);
+}
+
+uint64_t interrupts_local_save(void)
+{
+    uint64_t rflags;
+    __asm__ volatile ("pushfq; pop %0; cli" : "=r"(rflags) :: "memory");
+    return rflags;
+}
+
+void interrupts_local_restore(uint64_t state)
+{
+    if (state & (1ULL << 9)) { /* RFLAGS.IF */
+        __asm__ volatile ("sti" ::: "memory");
+    }
 }
 
 irql_t get_current_irql(void)
 {
-    return current_irql;
+    return (irql_t)percpu_self()->irql;
+}
+
+static void irql_giant_acquire(x86_percpu_t* pc)
+{
+    if (irql_giant_owner == pc->cpu_id) {
+        return;
+    }
+    /*
+     * The owner may be waiting in apic_ipi_call() for this CPU, which spins
+     * here with interrupts off: serve posted cross-CPU calls meanwhile.
+     */
+    while (!spinlock_trylock(&irql_giant)) {
+        apic_ipi_poll();
+        __asm__ volatile ("pause");
+    }
+    irql_giant_owner = pc->cpu_id;
+}
+
+static void irql_giant_release(x86_percpu_t* pc)
+{
+    if (irql_giant_owner != pc->cpu_id) {
+        return;
+    }
+    irql_giant_owner = 0xFFFFFFFFu;
+    spinlock_unlock(&irql_giant);
 }
 
 /**
@@ -225,6 +266,7 @@ irql_t get_current_irql(void)
  * @brief Set interrupt request level
  * 
  * This function sets the IRQL and enables/disables interrupts accordingly.
+ * On SMP the PASSIVE <-> non-PASSIVE transitions also take/drop irql_giant.
  * 
  * @param new_level New IRQL level
  * @return Previous IRQL level
@@ -233,24 +275,78 @@ irql_t get_current_irql(void)
  */
 irql_t set_irql(irql_t new_level)
 {
-    irql_t old_level = current_irql;
-    __asm__ volatile ("" ::: "memory"); /* Memory barrier */
-    
-    current_irql = new_level;
+    __asm__ volatile ("cli" ::: "memory");
+    x86_percpu_t* pc = percpu_self();
+    irql_t old_level = (irql_t)pc->irql;
+
+    if (old_level == IRQL_PASSIVE && new_level != IRQL_PASSIVE) {
+        irql_giant_acquire(pc);
+    }
+    pc->irql = (uint32_t)new_level;
     __asm__ volatile ("" ::: "memory"); /* Memory barrier */
     
-    /* Enable interrupts only at PASSIVE level */
-    if (new_level == IRQL_PASSIVE) {
+    /* Enable interrupts only at PASSIVE/APC level */
+    if (new_level <= IRQL_APC) {
+        if (new_level == IRQL_PASSIVE && old_level != IRQL_PASSIVE) {
+            irql_giant_release(pc);
+        }
         __asm__ volatile ("sti");
         __asm__ volatile ("" ::: "memory"); /* Memory barrier */
-    } else {
-        __asm__ volatile ("cli");
-        __asm__ volatile ("" ::: "memory"); /* Memory barrier */
     }
     
     return old_level;
 }
 
+/**
+ * @function interrupt_enter_irql
+ * @brief Raise IRQL on kernel entry (interrupt, exception, syscall)
+ *
+ * Entry from PASSIVE takes irql_giant. IRQL is raised to @p level unless the
+ * interrupted context already runs higher. The interrupt flag is left as the
+ * gate set it (int 0x80 is a trap gate), but the bookkeeping itself runs with
+ * interrupts off so a nested IRQ never sees a half-taken giant.
+ *
+ * @param level IRQL_DEVICE for interrupts/exceptions, IRQL_APC for syscalls
+ * @return IRQL of the interrupted context
+ */
+irql_t interrupt_enter_irql(irql_t level)
+{
+    uint64_t rflags;
+    __asm__ volatile ("pushfq; pop %0; cli" : "=r"(rflags) : : "memory");
+    x86_percpu_t* pc = percpu_self();
+    irql_t old_level = (irql_t)pc->irql;
+    if (old_level == IRQL_PASSIVE) {
+        irql_giant_acquire(pc);
+    }
+    if (old_level < level) {
+        pc->irql = (uint32_t)level;
+    }
+    if (rflags & (1ULL << 9)) {
+        __asm__ volatile ("sti" ::: "memory");
+    }
+    return old_level;
+}
+
+/**
+ * @function interrupt_leave_irql
+ * @brief Restore IRQL before returning from a kernel entry
+ *
+ * Called with interrupts disabled right before IRET/SYSRET. @p level is the
+ * IRQL of the context being resumed, which differs from the interrupted one
+ * after a thread switch. Dropping to PASSIVE releases irql_giant.
+ */
+void interrupt_leave_irql(irql_t level)
+{
+    x86_percpu_t* pc = percpu_self();
+    if (level == IRQL_PASSIVE) {
+        pc->irql = (uint32_t)IRQL_PASSIVE;
+        irql_giant_release(pc);
+        return;
+    }
+    irql_giant_acquire(pc);
+    pc->irql = (uint32_t)level;
+}
+
 void interrupt_wait(void)
 {
     __asm__ volatile ("hlt");
@@ -258,8 +354,55 @@ void interrupt_wait(void)
 
 int interrupt_send_ipi(uint32_t cpu_id, uint32_t vector)
 {
-    /* TODO: Implement IPI sending via APIC */
-    (void)cpu_id;
-    (void)vector;
-    return -1;
+    x86_percpu_t* pc = percpu_get(cpu_id);
+    if (!pc || !pc->online || vector >= 256 || !apic_is_available()) {
+        return -1;
+    }
+    apic_send_ipi(pc->apic_id, (uint8_t)vector);
+    return 0;
+}
+
+/* ============================================================================
+ * Dynamic Vectors (MSI/MSI-X)
+ * ============================================================================ */
+
+/* MSI address: 0xFEExxxxx, destination APIC ID in bits 19:12 (physical mode) */
+#define X86_MSI_ADDR_BASE        0xFEE00000ULL
+#define X86_MSI_ADDR_DEST_SHIFT  12
+
+int interrupt_alloc_vectors(uint32_t count, uint32_t align)
+{
+    return idt_vector_alloc(count, align);
+}
+
+void interrupt_free_vectors(uint32_t first, uint32_t count)
+{
+    for (uint32_t i = 0; i < count; i++) {
+        if (idt_vector_is_allocated(first + i)) {
+            interrupt_handlers[first + i] = NULL;
+        }
+    }
+    idt_vector_free(first, count);
+}
+
+int interrupt_msi_message(uint32_t vector, uint32_t cpu_id,
+                          uint64_t* addr, uint32_t* data)
+{
+    if (!addr || !data || !idt_vector_is_allocated(vector)) {
+        return -1;
+    }
+    uint32_t apic_id = 0;
+    x86_percpu_t* pc = percpu_get(cpu_id);
+    if (pc && pc->online) {
+        apic_id = pc->apic_id;
+    } else if (apic_is_available()) {
+        apic_id = apic_get_lapic_id();
+    }
+    /* No interrupt remapping: an 8-bit destination is all MSI can address */
+    if (apic_id > 0xFF) {
+        return -1;
+    }
+    *addr = X86_MSI_ADDR_BASE | ((uint64_t)apic_id << X86_MSI_ADDR_DEST_SHIFT);
+    *data = vector & 0xFF;  /* Fixed delivery, edge trigger */
+    return 0;
 }
@@ -18,13 +18,25 @@
 #include "../../common/tracev2.h"
 #include "../../linux/linux_compat.h"
 #include "../../core/task.h"
+#include "../../core/backtrace.h"
+#include "../../common/crashdump.h"
+#include "../../common/ddb.h"
+#include "../../common/gdbstub.h"
+#include "../../common/memprobe.h"
 #include "../../vm/vm_fault.h"
+#include "../../vm/vm_oom.h"
+#include "../../unix/unix_layer.h"
 #include "interrupt_frame.h"
 #include "types.h"
 #include "config.h"
 #include "pic.h"
 #include "apic.h"
+#include "idt.h"
 #include "syscall_fast.h"
+#include "dbreg.h"
+#include "percpu.h"
+#include "cpu_prot.h"
+#include "../config.h"
 #include <stddef.h>
 
 
@@ -174,6 +186,63 @@ static void safe_vga_hex(uint8_t row, uint8_t col, uint64_t value, uint8_t color
     }
 }
 
+/* #PF error code bits */
+#define PF_ERR_PRESENT  (1u << 0)
+#define PF_ERR_WRITE    (1u << 1)
+#define PF_ERR_USER     (1u << 2)
+#define PF_ERR_FETCH    (1u << 4)
+
+static inline bool pf_user_addr(uint64_t addr)
+{
+    return addr <= ARCH_USER_CANON_MAX && addr < ARCH_KERNEL_VIRT_BASE;
+}
+
+/* Kernel-mode data access to a user page inside a STAC/CLAC window */
+static bool pf_in_uaccess_window(const interrupt_frame_t* regs, uint64_t cr2)
+{
+    return (regs->cs & 3u) == 0 &&
+           cpu_prot_smap() &&
+           (regs->rflags & RFLAGS_AC) != 0 &&
+           (regs->err_code & PF_ERR_FETCH) == 0 &&
+           pf_user_addr(cr2);
+}
+
+/*
+ * Unresolved kernel-mode fault on a user address: say what went wrong
+ * before the generic exception dump.
+ * @return Short message for the VGA panic line, NULL if not a user access
+ */
+static const char* pf_report_user_access(const interrupt_frame_t* regs, uint64_t cr2)
+{
+    if ((regs->cs & 3u) != 0 || !pf_user_addr(cr2)) {
+        return NULL;
+    }
+
+    const char* msg;
+    if (regs->err_code & PF_ERR_FETCH) {
+        msg = cpu_prot_smep() ? "SMEP violation: kernel executed a user page"
+                              : "kernel executed a user address";
+    } else if ((regs->rflags & RFLAGS_AC) && cpu_prot_smap()) {
+        msg = "user access fault inside a uaccess window";
+    } else if ((regs->err_code & PF_ERR_PRESENT) && cpu_prot_smap()) {
+        msg = "SMAP violation: kernel touched a user page outside a uaccess window";
+    } else {
+        msg = "kernel dereferenced a user pointer outside a uaccess window";
+    }
+
+    serial_write_str("\n[UACCESS] ");
+    serial_write_str(msg);
+    serial_write_str("\n[UACCESS] cr2=");
+    serial_write_hex64(cr2);
+    serial_write_str(" rip=");
+    serial_write_hex64(regs->rip);
+    serial_write_str(" err=");
+    serial_write_hex64(regs->err_code);
+    serial_write_str(regs->err_code & PF_ERR_WRITE ? " (write)" : " (read)");
+    serial_write_str("\n");
+    return msg;
+}
+
 static interrupt_frame_t* handle_syscall(interrupt_frame_t* regs)
 {
     (void)x86_64_syscall_dispatch_frame(regs, 0);
@@ -221,7 +290,8 @@ static const char* exception_names[] = {
  * @brief Unified interrupt dispatcher
  * 
  * This is the main interrupt handler that routes interrupts to their
- * appropriate handlers. It handles both exceptions (0-31) and IRQs (32-47).
+ * appropriate handlers. It handles exceptions (0-31), legacy IRQs (32-47)
+ * and dynamically allocated MSI/MSI-X vectors (48-239).
  * 
  * @param regs Pointer to saved CPU registers
  * 
@@ -272,25 +342,62 @@ static interrupt_frame_t* interrupt_dispatch(interrupt_frame_t* regs)
         
         irq_send_eoi(irq);
         if (vector == 32) {
+            /* Pick up breakpoints the debugger set on another CPU */
+            dbreg_sync();
             /* Timer tick drives preemption */
             scheduler_tick();
             regs = scheduler_switch_from_irq(regs);
+            /* Dynamic tick: program the next one-shot for whoever runs now */
+            scheduler_tick_rearm();
         }
         return regs;
     }
     
     /* Handle exception (0-31) */
     if (vector < 32) {
+        const char* panic_msg = NULL;
         if (vector == 14) {
             uint64_t cr2 = 0;
             __asm__ volatile ("mov %%cr2, %0" : "=r"(cr2));
             task_t* task = task_get_current();
-            if (vm_fault_handle(task, cr2, regs->err_code, regs->rip) == RDNX_OK) {
+            uint64_t err = regs->err_code;
+            if (pf_in_uaccess_window(regs, cr2)) {
+                /* Copy to/from user memory: demand-fault and COW like the task would */
+                err |= PF_ERR_USER;
+            }
+            int rc = (task && task->oom_killed) ? RDNX_E_NOMEM
+                                                : vm_fault_handle(task, cr2, err, regs->rip);
+            if (rc == RDNX_OK) {
+                return regs;
+            }
+            if (rc == RDNX_E_NOMEM && (err & PF_ERR_USER)) {
+                /* Out of memory: retry once the OOM victim is gone, or be it */
+                if (!task->oom_killed && vm_oom_kill() != task) {
+                    scheduler_yield();
+                    return regs;
+                }
+                if ((regs->cs & 3u) != 0) {
+                    /* The victim exits in interrupt_return_to_user() */
+                    return regs;
+                }
+                /* A uaccess copy by the victim fails below; its syscall then returns */
+            }
+            if ((regs->cs & 3u) == 0 && pf_user_addr(cr2) && cpu_user_fault_fixup(&regs->rip)) {
+                /* Bad pointer handed to a uaccess copy: the copy returns an error */
                 return regs;
             }
             if (task && task_get_abi(task) == TASK_ABI_LINUX) {
                 linux_compat_trace_dump_recent();
             }
+            panic_msg = pf_report_user_access(regs, cr2);
+        }
+        if (vector == 13 || vector == 14) {
+            /* A debugger memory probe that faulted unwinds back to the debugger */
+            memprobe_fault();
+        }
+        if ((vector == 1 || vector == 3) && gdbstub_trap(regs)) {
+            /* Remote gdb owns #DB/#BP while it is enabled */
+            return regs;
         }
         tracev2_emit(TR2_CAT_FAULT, TR2_EV_FAULT_EXCEPTION, vector, regs->err_code);
         /* Call registered handler if available */
@@ -404,6 +511,7 @@ static interrupt_frame_t* interrupt_dispatch(interrupt_frame_t* regs)
                 serial_write_str("\n");
             }
         }
+        backtrace_print_from(regs->rip, regs->rbp, serial_write_str);
 
         /* Extra minimal dump at top-left to avoid being scrolled out */
         safe_vga_puts(0, 0, "EXC v=", 0x0C);
@@ -444,7 +552,21 @@ static interrupt_frame_t* interrupt_dispatch(interrupt_frame_t* regs)
         }
         
         safe_vga_puts(23, 0, "*** KERNEL PANIC ***", 0x0C); /* Red */
-        safe_vga_puts(24, 0, "Message: Unhandled exception", 0x0C); /* Red */
+        if (panic_msg) {
+            safe_vga_puts(24, 0, panic_msg, 0x0C);
+        } else {
+            safe_vga_puts(24, 0, "Message: Unhandled exception", 0x0C); /* Red */
+        }
+
+        crashdump_write(panic_msg ? panic_msg :
+                        (exception_names[vector] ? exception_names[vector] : "Unknown exception"),
+                        regs);
+        gdbstub_fatal(panic_msg ? panic_msg :
+                      (exception_names[vector] ? exception_names[vector] : "Unknown exception"),
+                      regs);
+        ddb_panic(panic_msg ? panic_msg :
+                  (exception_names[vector] ? exception_names[vector] : "Unknown exception"),
+                  regs);
         
         /* Halt system */
         __asm__ volatile ("cli; hlt");
@@ -454,19 +576,94 @@ static interrupt_frame_t* interrupt_dispatch(interrupt_frame_t* regs)
         return regs;
     }
     
-    /* Unknown interrupt vector (48-255) - ignore silently */
+    /* Dynamic vectors (MSI/MSI-X), always delivered through the LAPIC */
+    if (vector >= IDT_DYN_VECTOR_FIRST && vector <= IDT_DYN_VECTOR_LAST) {
+        if (interrupt_handlers[vector]) {
+            interrupt_context_t ctx;
+            ctx.pc = regs->rip;
+            ctx.sp = 0;
+            ctx.flags = regs->rflags;
+            ctx.error_code = regs->err_code;
+            ctx.vector = vector;
+            ctx.type = INTERRUPT_TYPE_IRQ;
+            ctx.arch_specific = (void*)regs;
+            interrupt_handlers[vector](&ctx);
+        }
+        apic_send_eoi();
+        return regs;
+    }
+
+    /* Unknown interrupt vector - ignore silently */
     /* These are typically spurious interrupts or reserved vectors */
     return regs;
 }
 
+/**
+ * @function interrupt_dispatch_irql
+ * @brief interrupt_dispatch() wrapped into IRQL entry/exit
+ *
+ * Handlers run at IRQL_DEVICE (holding the IRQL giant when entered from
+ * PASSIVE); int 0x80 system calls run at IRQL_APC with interrupts enabled.
+ * If the handler switched threads, the IRQL of the interrupted context is
+ * parked in the old thread and the resumed thread gets its own level back.
+ */
+static interrupt_frame_t* interrupt_dispatch_irql(interrupt_frame_t* regs)
+{
+    /* Handlers never run inside the interrupted uaccess window; IRETQ restores AC. */
+    cpu_user_access_end();
+
+    /* NMI may land inside giant acquisition: never touch IRQL state there. */
+    if (regs->int_no == 2) {
+        apic_ipi_stop_nmi();
+        return interrupt_dispatch(regs);
+    }
+    /* Cross-CPU calls are served while the sender holds the giant. */
+    if (regs->int_no == APIC_IPI_VECTOR_CALL) {
+        apic_ipi_call_interrupt();
+        return regs;
+    }
+
+    thread_t* prev = thread_get_current();
+    irql_t level = interrupt_enter_irql(regs->int_no == SYSCALL_VECTOR ? IRQL_APC : IRQL_DEVICE);
+    regs = interrupt_dispatch(regs);
+    __asm__ volatile ("cli" ::: "memory");
+    thread_t* cur = thread_get_current();
+    if (cur != prev) {
+        if (prev) {
+            prev->saved_irql = (uint8_t)level;
+        }
+        level = cur ? (irql_t)cur->saved_irql : IRQL_PASSIVE;
+    }
+    interrupt_leave_irql(level);
+    if ((regs->cs & 3u) != 0) {
+        interrupt_return_to_user();
+    }
+    return regs;
+}
+
+void interrupt_return_to_user(void)
+{
+    if (!task_kill_pending(task_get_current())) {
+        return;
+    }
+    /*
+     * An OOM victim, possibly spinning in user mode without syscalls. Its
+     * exit closes files (which may sleep or poll a disk), so it runs here at
+     * PASSIVE with interrupts on rather than inside the handler.
+     */
+    __asm__ volatile ("sti" ::: "memory");
+    unix_proc_kill_checkpoint();
+    __asm__ volatile ("cli" ::: "memory");
+}
+
 /* ISR handler (called from assembly for exceptions 0-31) */
 interrupt_frame_t* isr_handler(interrupt_frame_t* regs)
 {
-    return interrupt_dispatch(regs);
+    return interrupt_dispatch_irql(regs);
 }
 
 /* IRQ handler (called from assembly for IRQ 32-47) */
 interrupt_frame_t* irq_handler(interrupt_frame_t* regs)
 {
-    return interrupt_dispatch(regs);
+    return interrupt_dispatch_irql(regs);
 }
@@ -55,7 +55,7 @@ int lapic_access_init(uint64_t apic_phys, bool prefer_x2apic)
 
     {
         uint64_t apic_virt = LAPIC_MMIO_VIRT;
-        uint64_t mmio_flags = 0x001 | 0x002 | 0x010; /* PRESENT | RW | PCD */
+        uint64_t mmio_flags = PTE_PRESENT | PTE_RW | PTE_PCD | PTE_NX;
         if (paging_map_page_4kb(apic_virt, apic_phys, mmio_flags) != 0) {
             g_lapic_mode = LAPIC_MODE_NONE;
             g_lapic_mmio = NULL;
@@ -68,6 +68,25 @@ int lapic_access_init(uint64_t apic_phys, bool prefer_x2apic)
     return 0;
 }
 
+int lapic_access_init_ap(void)
+{
+    uint64_t apic_base = lapic_rdmsr(APIC_BASE_MSR);
+
+    /* The enable bits in APIC_BASE_MSR are per CPU: mirror the BSP mode. */
+    if (g_lapic_mode == LAPIC_MODE_X2APIC) {
+        apic_base |= APIC_BASE_ENABLE | APIC_BASE_X2APIC;
+        lapic_wrmsr(APIC_BASE_MSR, apic_base);
+        return 0;
+    }
+    if (g_lapic_mode == LAPIC_MODE_XAPIC && g_lapic_mmio) {
+        apic_base |= APIC_BASE_ENABLE;
+        apic_base &= ~APIC_BASE_X2APIC;
+        lapic_wrmsr(APIC_BASE_MSR, apic_base);
+        return 0;
+    }
+    return -1;
+}
+
 bool lapic_access_ready(void)
 {
     if (g_lapic_mode == LAPIC_MODE_X2APIC) {
@@ -120,3 +139,38 @@ void lapic_access_write(uint32_t reg_off, uint32_t value)
         __asm__ volatile ("" ::: "memory");
     }
 }
+
+uint32_t lapic_access_id(void)
+{
+    uint32_t id = lapic_access_read(APIC_ID);
+    if (g_lapic_mode == LAPIC_MODE_X2APIC) {
+        return id;
+    }
+    return (id >> 24) & 0xFFu;
+}
+
+void lapic_access_write_icr(uint32_t dest, uint32_t icr_low)
+{
+    if (g_lapic_mode == LAPIC_MODE_X2APIC) {
+        /* x2APIC ICR is a single 64-bit MSR; the write sends the IPI. */
+        uint32_t msr = X2APIC_MSR_BASE + (APIC_ICR_LOW >> 4);
+        __asm__ volatile ("mfence\n\tlfence" ::: "memory");
+        lapic_wrmsr(msr, ((uint64_t)dest << 32) | (uint64_t)icr_low);
+        return;
+    }
+    if (g_lapic_mode == LAPIC_MODE_XAPIC && g_lapic_mmio) {
+        /* Writing ICR_LOW sends the IPI, so the destination goes first. */
+        g_lapic_mmio[APIC_ICR_HIGH >> 2] = (dest & 0xFFu) << 24;
+        __asm__ volatile ("" ::: "memory");
+        g_lapic_mmio[APIC_ICR_LOW >> 2] = icr_low;
+        __asm__ volatile ("" ::: "memory");
+    }
+}
+
+bool lapic_access_icr_busy(void)
+{
+    if (g_lapic_mode != LAPIC_MODE_XAPIC || !g_lapic_mmio) {
+        return false;
+    }
+    return (g_lapic_mmio[APIC_ICR_LOW >> 2] & APIC_ICR_DELIVERY_PENDING) != 0;
+}
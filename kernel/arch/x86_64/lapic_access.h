@@ -11,6 +11,8 @@ typedef enum lapic_mode {
 } lapic_mode_t;
 
 int lapic_access_init(uint64_t apic_phys, bool prefer_x2apic);
+/* Enable the LAPIC of an application processor in the mode chosen by the BSP. */
+int lapic_access_init_ap(void);
 bool lapic_access_ready(void);
 lapic_mode_t lapic_access_mode(void);
 const char* lapic_access_mode_name(void);
@@ -18,4 +20,11 @@ const char* lapic_access_mode_name(void);
 uint32_t lapic_access_read(uint32_t reg_off);
 void lapic_access_write(uint32_t reg_off, uint32_t value);
 
+/* LAPIC ID of the calling CPU (full 32-bit value in x2APIC mode). */
+uint32_t lapic_access_id(void);
+/* Send an IPI: @p dest is an APIC ID, @p icr_low carries vector/mode/shorthand. */
+void lapic_access_write_icr(uint32_t dest, uint32_t icr_low);
+/* True while the previous IPI is still pending delivery (always false on x2APIC). */
+bool lapic_access_icr_busy(void);
+
 #endif /* _RODNIX_ARCH_X86_64_LAPIC_ACCESS_H */
@@ -40,5 +40,22 @@
 /* APIC LVT flags */
 #define APIC_LVT_MASKED      (1U << 16)
 #define APIC_LVT_TIMER_PERIODIC   (1U << 17)
+#define APIC_LVT_TIMER_TSC_DEADLINE (2U << 17)
+#define APIC_LVT_TIMER_MODE_MASK  (3U << 17)
+
+/* TSC-deadline timer: absolute TSC value, 0 disarms */
+#define IA32_TSC_DEADLINE_MSR 0x6E0
+
+/* APIC ICR (low dword) fields */
+#define APIC_ICR_DM_FIXED         (0U << 8)
+#define APIC_ICR_DM_NMI           (4U << 8)
+#define APIC_ICR_DM_INIT          (5U << 8)
+#define APIC_ICR_DM_STARTUP       (6U << 8)
+#define APIC_ICR_DELIVERY_PENDING (1U << 12)
+#define APIC_ICR_LEVEL_ASSERT     (1U << 14)
+#define APIC_ICR_TRIGGER_LEVEL    (1U << 15)
+#define APIC_ICR_DEST_SELF        (1U << 18)
+#define APIC_ICR_DEST_ALL         (2U << 18)
+#define APIC_ICR_DEST_OTHERS      (3U << 18)
 
 #endif /* _RODNIX_ARCH_X86_64_LAPIC_REGS_H */
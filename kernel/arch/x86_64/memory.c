@@ -10,9 +10,11 @@
 
 #include "../../core/memory.h"
 #include "../../core/boot.h"
+#include "../../core/interrupts.h"
 #include "../../common/tracev2.h"
 #include "../../../include/console.h"
 #include "../../../include/debug.h"
+#include "../../../include/common.h"
 #include "types.h"
 #include "config.h"
 #include "pmm.h"
@@ -400,6 +402,121 @@ void vmm_free_pages(void* virt, uint32_t count)
     pmm_free_pages((uint64_t)X86_64_VIRT_TO_PHYS(virt), count);
 }
 
+/*
+ * Module image window: physmap is NX, so code loaded at run time gets its
+ * own mapping. One bit per page of [X86_64_KMOD_VIRT_BASE, _END).
+ *
+ * LOCKING: kmod_window_map — IRQL giant.
+ */
+#define KMOD_WINDOW_PAGES ((X86_64_KMOD_VIRT_END - X86_64_KMOD_VIRT_BASE) / PAGE_SIZE)
+static uint64_t kmod_window_map[KMOD_WINDOW_PAGES / 64u];
+
+static bool kmod_window_test(uint64_t page)
+{
+    return (kmod_window_map[page / 64u] >> (page % 64u)) & 1u;
+}
+
+static void kmod_window_set(uint64_t first, uint64_t count, bool used)
+{
+    for (uint64_t page = first; page < first + count; page++) {
+        uint64_t bit = 1ULL << (page % 64u);
+        if (used) {
+            kmod_window_map[page / 64u] |= bit;
+        } else {
+            kmod_window_map[page / 64u] &= ~bit;
+        }
+    }
+}
+
+/* Unmap and free the first @count pages of a window range */
+static void vmm_exec_release(uint64_t virt, uint64_t count)
+{
+    for (uint64_t i = 0; i < count; i++) {
+        uint64_t va = virt + i * PAGE_SIZE;
+        uint64_t phys = paging_get_physical(va);
+        (void)paging_unmap_page(va);
+        if (phys) {
+            pmm_free_page(phys & ~(uint64_t)(PAGE_SIZE - 1u));
+        }
+    }
+    paging_tlb_shootdown_kernel(virt, count);
+}
+
+/**
+ * @function vmm_alloc_exec
+ * @brief Allocate zeroed, writable and executable pages for a module image
+ *
+ * @param count Number of pages
+ *
+ * @return Virtual address of first page, or NULL on failure
+ */
+void* vmm_alloc_exec(uint32_t count)
+{
+    if (count == 0 || count > KMOD_WINDOW_PAGES) {
+        return NULL;
+    }
+
+    irql_t old = set_irql(IRQL_HIGH);
+    uint64_t first = 0;
+    uint64_t run = 0;
+    for (uint64_t page = 0; page < KMOD_WINDOW_PAGES && run < count; page++) {
+        if (kmod_window_test(page)) {
+            run = 0;
+            first = page + 1u;
+        } else {
+            run++;
+        }
+    }
+    if (run < count) {
+        (void)set_irql(old);
+        memory_oom_inc_vmm();
+        return NULL;
+    }
+    kmod_window_set(first, count, true);
+    (void)set_irql(old);
+
+    uint64_t virt = X86_64_KMOD_VIRT_BASE + first * PAGE_SIZE;
+    for (uint32_t i = 0; i < count; i++) {
+        uint64_t phys = pmm_alloc_page();
+        if (!phys ||
+            page_map(virt + (uint64_t)i * PAGE_SIZE, phys,
+                     PAGE_FLAG_PRESENT | PAGE_FLAG_WRITABLE | PAGE_FLAG_EXECUTE,
+                     PAGE_TYPE_4KB) != 0) {
+            if (phys) {
+                pmm_free_page(phys);
+            }
+            vmm_exec_release(virt, i);
+            old = set_irql(IRQL_HIGH);
+            kmod_window_set(first, count, false);
+            (void)set_irql(old);
+            memory_oom_inc_vmm();
+            return NULL;
+        }
+        memset((void*)(uintptr_t)(virt + (uint64_t)i * PAGE_SIZE), 0, PAGE_SIZE);
+    }
+    return (void*)(uintptr_t)virt;
+}
+
+/**
+ * @function vmm_free_exec
+ * @brief Free pages from vmm_alloc_exec()
+ *
+ * @param virt Virtual address of first page
+ * @param count Number of pages
+ */
+void vmm_free_exec(void* virt, uint32_t count)
+{
+    uint64_t va = (uint64_t)(uintptr_t)virt;
+    if (!virt || count == 0 || va < X86_64_KMOD_VIRT_BASE ||
+        va + (uint64_t)count * PAGE_SIZE > X86_64_KMOD_VIRT_END) {
+        return;
+    }
+    vmm_exec_release(va, count);
+    irql_t old = set_irql(IRQL_HIGH);
+    kmod_window_set((va - X86_64_KMOD_VIRT_BASE) / PAGE_SIZE, count, false);
+    (void)set_irql(old);
+}
+
 /**
  * @function memory_get_info
  * @brief Get memory statistics
@@ -15,6 +15,8 @@
 #include "types.h"
 #include "config.h"
 #include "pmm.h"
+#include "apic.h"
+#include "percpu.h"
 #include "../../../include/debug.h"
 #include "../../../include/error.h"
 #include <stddef.h>
@@ -66,8 +68,16 @@ static bool physmap_ready = false;
  * Current Page Table
  * ============================================================================ */
 
-/* Current PML4 (CR3 register value) */
-static uint64_t current_pml4_phys = 0;
+/*
+ * Current PML4 is whatever CR3 of the calling CPU holds: with several CPUs
+ * online each one may run a different address space, so no global copy.
+ */
+static inline uint64_t paging_current_pml4(void)
+{
+    uint64_t cr3;
+    __asm__ volatile ("mov %%cr3, %0" : "=r"(cr3));
+    return cr3 & ~0xFFFULL;
+}
 
 /* ============================================================================
  * Helper Functions
@@ -163,7 +173,7 @@ static uint64_t paging_alloc_page_table(void)
 
 static uint64_t paging_alloc_page_table_low(void)
 {
-    uint64_t phys = pmm_alloc_page_in_zone(PMM_ZONE_LOW);
+    uint64_t phys = pmm_alloc_page_in_zone(PMM_ZONE_DMA);
     if (!phys) {
         return 0;
     }
@@ -176,7 +186,7 @@ static uint64_t paging_alloc_page_table_low(void)
 
 static uint64_t paging_alloc_page_table_identity(void)
 {
-    uint64_t phys = pmm_alloc_page_in_zone(PMM_ZONE_LOW);
+    uint64_t phys = pmm_alloc_page_in_zone(PMM_ZONE_DMA);
     if (!phys) {
         return 0;
     }
@@ -198,10 +208,10 @@ static uint64_t paging_alloc_page_table_identity(void)
  */
 static uint64_t* paging_get_pml4(void)
 {
-    if (!current_pml4_phys) {
+    if (!paging_current_pml4()) {
         return NULL;
     }
-    return (uint64_t*)(current_pml4_phys + X86_64_KERNEL_VIRT_BASE);
+    return (uint64_t*)(paging_current_pml4() + X86_64_KERNEL_VIRT_BASE);
 }
 
 uint64_t paging_create_user_pml4(void)
@@ -315,7 +325,7 @@ int paging_map_page_4kb_pml4(uint64_t pml4_phys, uint64_t virt, uint64_t phys, u
 
     uint64_t entry = phys | (flags & (PTE_PRESENT | PTE_RW | PTE_USER | PTE_NX));
     pt[pt_idx] = entry;
-    if (current_pml4_phys == pml4_phys) {
+    if (paging_current_pml4() == pml4_phys) {
         paging_flush_tlb((void*)virt);
     }
     return 0;
@@ -326,8 +336,13 @@ void paging_switch_pml4(uint64_t pml4_phys)
     if (!pml4_phys) {
         return;
     }
-    __asm__ volatile ("mov %0, %%cr3" : : "r"(pml4_phys));
-    current_pml4_phys = pml4_phys;
+    /*
+     * Publish before loading CR3: a shootdown that misses this CPU has
+     * already updated the tables this CR3 load starts from.
+     */
+    percpu_self()->active_pml4 = pml4_phys;
+    __asm__ volatile ("mfence" ::: "memory");
+    __asm__ volatile ("mov %0, %%cr3" : : "r"(pml4_phys) : "memory");
 }
 
 /**
@@ -401,6 +416,31 @@ static void paging_flush_tlb(void* virt)
     }
 }
 
+/* Above this many pages a shootdown reloads CR3 instead of INVLPG per page */
+#define PAGING_SHOOTDOWN_FULL_PAGES 32u
+
+typedef struct {
+    uint64_t virt;
+    uint64_t pages;
+} paging_shootdown_t;
+
+static void paging_flush_range(uint64_t virt, uint64_t pages)
+{
+    if (pages > PAGING_SHOOTDOWN_FULL_PAGES) {
+        paging_flush_tlb(NULL);
+        return;
+    }
+    for (uint64_t i = 0; i < pages; i++) {
+        paging_flush_tlb((void*)(virt + i * 0x1000ULL));
+    }
+}
+
+static void paging_shootdown_ipi(void* arg)
+{
+    const paging_shootdown_t* req = (const paging_shootdown_t*)arg;
+    paging_flush_range(req->virt, req->pages);
+}
+
 /* ============================================================================
  * Public Interface
  * ============================================================================ */
@@ -419,10 +459,8 @@ static void paging_flush_tlb(void* virt)
  */
 int paging_init(void)
 {
-    /* Get current CR3 (PML4 physical address) */
-    __asm__ volatile ("mov %%cr3, %0" : "=r"(current_pml4_phys));
-    
-    if (!current_pml4_phys) {
+    /* Current CR3 (PML4 physical address) must be set up by boot code */
+    if (!paging_current_pml4()) {
         return RDNX_E_GENERIC;
     }
     return 0;
@@ -614,7 +652,7 @@ int paging_map_page_4kb_noalloc_identity(uint64_t virt, uint64_t phys, uint64_t
         return RDNX_E_GENERIC; /* Not page-aligned */
     }
 
-    if (!current_pml4_phys) {
+    if (!paging_current_pml4()) {
         return RDNX_E_GENERIC;
     }
 
@@ -678,7 +716,7 @@ int paging_map_page_4kb_identity_alloc(uint64_t virt, uint64_t phys, uint64_t fl
         return RDNX_E_GENERIC; /* Not page-aligned */
     }
 
-    if (!current_pml4_phys) {
+    if (!paging_current_pml4()) {
         return RDNX_E_GENERIC;
     }
 
@@ -688,7 +726,7 @@ int paging_map_page_4kb_identity_alloc(uint64_t virt, uint64_t phys, uint64_t fl
     uint64_t pd_idx = paging_get_pd_index(virt);
     uint64_t pt_idx = paging_get_pt_index(virt);
 
-    uint64_t* pml4 = (uint64_t*)current_pml4_phys;
+    uint64_t* pml4 = (uint64_t*)paging_current_pml4();
     uint64_t pml4_entry = pml4[pml4_idx];
     uint64_t* pdpt;
 
@@ -767,7 +805,7 @@ int paging_map_page_2mb_identity_alloc(uint64_t virt, uint64_t phys, uint64_t fl
         return RDNX_E_GENERIC; /* Not 2MB aligned */
     }
 
-    if (!current_pml4_phys) {
+    if (!paging_current_pml4()) {
         return RDNX_E_GENERIC;
     }
 
@@ -776,7 +814,7 @@ int paging_map_page_2mb_identity_alloc(uint64_t virt, uint64_t phys, uint64_t fl
     uint64_t pdpt_idx = paging_get_pdpt_index(virt);
     uint64_t pd_idx = paging_get_pd_index(virt);
 
-    uint64_t* pml4 = (uint64_t*)current_pml4_phys;
+    uint64_t* pml4 = (uint64_t*)paging_current_pml4();
     uint64_t pml4_entry = pml4[pml4_idx];
     uint64_t* pdpt;
 
@@ -817,12 +855,45 @@ int paging_map_page_2mb_identity_alloc(uint64_t virt, uint64_t phys, uint64_t fl
     return 0;
 }
 
+/*
+ * Remap the 2MB page at @virt with 4KB pages, executable only inside
+ * [@x_start, @x_end). The 2MB mapping is set up first so the tables exist
+ * and the code running from this page never sees a hole; the PD entry is
+ * then swapped for the finished page table in one store.
+ */
+static int paging_split_2mb_identity(uint64_t virt, uint64_t phys,
+                                     uint64_t x_start, uint64_t x_end)
+{
+    if (paging_map_page_2mb_identity_alloc(virt, phys, PTE_RW) != 0) {
+        return RDNX_E_GENERIC;
+    }
+    uint64_t pt_phys = paging_alloc_page_table_identity();
+    if (!pt_phys) {
+        return RDNX_E_GENERIC;
+    }
+    uint64_t* pt = (uint64_t*)pt_phys;
+    for (uint64_t i = 0; i < PT_ENTRIES; i++) {
+        uint64_t p = phys + i * 0x1000ULL;
+        uint64_t nx = (p >= x_start && p < x_end) ? 0 : PTE_NX;
+        pt[i] = p | PTE_PRESENT | PTE_RW | nx;
+    }
+
+    uint64_t* pml4 = (uint64_t*)paging_current_pml4();
+    uint64_t* pdpt = (uint64_t*)(pml4[paging_get_pml4_index(virt)] & PTE_ADDR_MASK_4KB);
+    uint64_t* pd = (uint64_t*)(pdpt[paging_get_pdpt_index(virt)] & PTE_ADDR_MASK_4KB);
+    pd[paging_get_pd_index(virt)] = pt_phys | PTE_PRESENT | PTE_RW;
+    paging_flush_tlb((void*)virt);
+    return 0;
+}
+
 /**
  * @function paging_bootstrap_physmap
  * @brief Create a higher-half direct-map window for low physical memory
  *
  * Maps [0, max_phys) to X86_64_KERNEL_VIRT_BASE + phys using 2MB pages.
  * This is intended for early/bootstrapping use before full VM is ready.
+ * Everything but the kernel .text is mapped PTE_NX; the 2MB pages that
+ * hold .text are split so their non-text part is NX as well.
  *
  * @param max_phys Maximum physical address (bytes) to map (rounded down to 2MB)
  *
@@ -830,6 +901,9 @@ int paging_map_page_2mb_identity_alloc(uint64_t virt, uint64_t phys, uint64_t fl
  */
 int paging_bootstrap_physmap(uint64_t max_phys)
 {
+    extern char __text_start[];
+    extern char __text_end[];
+
     if (max_phys == 0) {
         return 0;
     }
@@ -837,9 +911,17 @@ int paging_bootstrap_physmap(uint64_t max_phys)
     /* Round down to 2MB */
     max_phys &= ~0x1FFFFFULL;
 
+    uint64_t text_start = (uint64_t)X86_64_VIRT_TO_PHYS(__text_start) & ~0xFFFULL;
+    uint64_t text_end = ((uint64_t)X86_64_VIRT_TO_PHYS(__text_end) + 0xFFFULL) & ~0xFFFULL;
     for (uint64_t phys = 0; phys < max_phys; phys += 0x200000ULL) {
         uint64_t virt = X86_64_KERNEL_VIRT_BASE + phys;
-        if (paging_map_page_2mb_identity_alloc(virt, phys, PTE_RW) != 0) {
+        int rc;
+        if (phys + 0x200000ULL <= text_start || phys >= text_end) {
+            rc = paging_map_page_2mb_identity_alloc(virt, phys, PTE_RW | PTE_NX);
+        } else {
+            rc = paging_split_2mb_identity(virt, phys, text_start, text_end);
+        }
+        if (rc != 0) {
             return RDNX_E_GENERIC;
         }
     }
@@ -921,6 +1003,79 @@ int paging_unmap_page(uint64_t virt)
     return 0;
 }
 
+/**
+ * @function paging_tlb_shootdown
+ * @brief Invalidate a range of an address space on every CPU running it
+ *
+ * Call after changing or removing PTEs of @p pml4_phys and before the old
+ * physical pages are reused. The calling CPU is flushed when it has the
+ * address space loaded; other CPUs with it in CR3 get a synchronous IPI.
+ *
+ * @param pml4_phys Address space that was modified
+ * @param virt First virtual address of the range (page aligned)
+ * @param pages Number of 4KB pages
+ */
+void paging_tlb_shootdown(uint64_t pml4_phys, uint64_t virt, uint64_t pages)
+{
+    if (!pml4_phys || pages == 0) {
+        return;
+    }
+
+    /* No migration between building the mask and flushing locally. */
+    uint64_t rflags;
+    __asm__ volatile ("pushfq; pop %0; cli" : "=r"(rflags) : : "memory");
+    __asm__ volatile ("mfence" ::: "memory");
+
+    uint32_t self = percpu_self()->cpu_id;
+    uint32_t mask = 0;
+    for (uint32_t cpu = 0; cpu < CPU_MAX_COUNT; cpu++) {
+        x86_percpu_t* pc = percpu_get(cpu);
+        if (cpu != self && pc->online && pc->active_pml4 == pml4_phys) {
+            mask |= 1u << cpu;
+        }
+    }
+    if (paging_current_pml4() == pml4_phys) {
+        paging_flush_range(virt, pages);
+    }
+    if (mask) {
+        paging_shootdown_t req = { .virt = virt, .pages = pages };
+        (void)apic_ipi_call(mask, paging_shootdown_ipi, &req);
+    }
+
+    if (rflags & (1ULL << 9)) {
+        __asm__ volatile ("sti" ::: "memory");
+    }
+}
+
+void paging_tlb_shootdown_kernel(uint64_t virt, uint64_t pages)
+{
+    if (pages == 0) {
+        return;
+    }
+
+    uint64_t rflags;
+    __asm__ volatile ("pushfq; pop %0; cli" : "=r"(rflags) : : "memory");
+    __asm__ volatile ("mfence" ::: "memory");
+
+    /* Kernel-half tables are shared by every PML4: flush all online CPUs */
+    uint32_t self = percpu_self()->cpu_id;
+    uint32_t mask = 0;
+    for (uint32_t cpu = 0; cpu < CPU_MAX_COUNT; cpu++) {
+        if (cpu != self && percpu_get(cpu)->online) {
+            mask |= 1u << cpu;
+        }
+    }
+    paging_flush_range(virt, pages);
+    if (mask) {
+        paging_shootdown_t req = { .virt = virt, .pages = pages };
+        (void)apic_ipi_call(mask, paging_shootdown_ipi, &req);
+    }
+
+    if (rflags & (1ULL << 9)) {
+        __asm__ volatile ("sti" ::: "memory");
+    }
+}
+
 int paging_unmap_page_pml4(uint64_t pml4_phys, uint64_t virt)
 {
     if (!pml4_phys) {
@@ -955,7 +1110,7 @@ int paging_unmap_page_pml4(uint64_t pml4_phys, uint64_t virt)
 
     if (pd_entry & PTE_SIZE_2MB) {
         pd[pd_idx] = 0;
-        if (current_pml4_phys == pml4_phys) {
+        if (paging_current_pml4() == pml4_phys) {
             paging_flush_tlb((void*)virt);
         }
         return 0;
@@ -963,7 +1118,7 @@ int paging_unmap_page_pml4(uint64_t pml4_phys, uint64_t virt)
 
     uint64_t* pt = paging_get_pt(pd_entry);
     pt[pt_idx] = 0;
-    if (current_pml4_phys == pml4_phys) {
+    if (paging_current_pml4() == pml4_phys) {
         paging_flush_tlb((void*)virt);
     }
     return 0;
@@ -1069,6 +1224,48 @@ uint64_t paging_get_physical_pml4(uint64_t pml4_phys, uint64_t virt)
     return (pte & PTE_ADDR_MASK_4KB) | (virt & PAGE_OFFSET_MASK);
 }
 
+/**
+ * @function paging_test_clear_accessed_pml4
+ * @brief Sample and clear the accessed bit of a 4 KiB user mapping
+ *
+ * The TLB is not flushed: a CPU that still caches the entry will not set
+ * the bit again until the entry is evicted, which only makes the page
+ * look a little older to the page-out daemon.
+ *
+ * @param pml4_phys Address space
+ * @param virt Virtual address
+ * @return 1 if the page was accessed, 0 if not, RDNX_E_NOTFOUND if unmapped
+ */
+int paging_test_clear_accessed_pml4(uint64_t pml4_phys, uint64_t virt)
+{
+    if (!pml4_phys) {
+        return RDNX_E_NOTFOUND;
+    }
+    uint64_t* pml4 = (uint64_t*)X86_64_PHYS_TO_VIRT(pml4_phys);
+    uint64_t pml4_entry = pml4[paging_get_pml4_index(virt)];
+    if (!(pml4_entry & PTE_PRESENT)) {
+        return RDNX_E_NOTFOUND;
+    }
+    uint64_t* pdpt = paging_get_pdpt(pml4_entry);
+    uint64_t pdpt_entry = pdpt[paging_get_pdpt_index(virt)];
+    if (!(pdpt_entry & PTE_PRESENT)) {
+        return RDNX_E_NOTFOUND;
+    }
+    uint64_t* pd = paging_get_pd(pdpt_entry);
+    uint64_t pd_entry = pd[paging_get_pd_index(virt)];
+    if (!(pd_entry & PTE_PRESENT) || (pd_entry & PTE_SIZE_2MB)) {
+        return RDNX_E_NOTFOUND;
+    }
+    uint64_t* pt = paging_get_pt(pd_entry);
+    uint64_t* pte = &pt[paging_get_pt_index(virt)];
+    if (!(*pte & PTE_PRESENT)) {
+        return RDNX_E_NOTFOUND;
+    }
+    /* The CPU sets the bit with a locked update: clear it the same way */
+    uint64_t old = __atomic_fetch_and(pte, ~(uint64_t)PTE_ACCESSED, __ATOMIC_SEQ_CST);
+    return (old & PTE_ACCESSED) ? 1 : 0;
+}
+
 /**
  * @function paging_map_page_2mb
  * @brief Map a 2MB page (large page)
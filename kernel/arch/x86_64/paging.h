@@ -36,11 +36,19 @@ int paging_unmap_page_pml4(uint64_t pml4_phys, uint64_t virt);
 uint64_t paging_get_physical(uint64_t virt);
 uint64_t paging_get_physical_pml4(uint64_t pml4_phys, uint64_t virt);
 
+/* Sample and clear PTE_ACCESSED: 1 accessed, 0 not, RDNX_E_NOTFOUND unmapped */
+int paging_test_clear_accessed_pml4(uint64_t pml4_phys, uint64_t virt);
+
 /* User address space helpers */
 uint64_t paging_create_user_pml4(void);
 int paging_map_page_4kb_pml4(uint64_t pml4_phys, uint64_t virt, uint64_t phys, uint64_t flags);
 void paging_switch_pml4(uint64_t pml4_phys);
 
+/* Flush a range of @p pml4_phys on all CPUs that have it loaded (IPI) */
+void paging_tlb_shootdown(uint64_t pml4_phys, uint64_t virt, uint64_t pages);
+/* Same for a kernel-half range, which every address space shares */
+void paging_tlb_shootdown_kernel(uint64_t virt, uint64_t pages);
+
 /* Page table entry flags */
 #define PTE_PRESENT     0x001
 #define PTE_RW          0x002
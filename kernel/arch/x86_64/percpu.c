@@ -0,0 +1,69 @@
+/**
+ * @file percpu.c
+ * @brief x86_64 per-CPU data blocks
+ */
+
+#include "percpu.h"
+#include "../../../include/common.h"
+
+static x86_percpu_t g_percpu[CPU_MAX_COUNT] __attribute__((aligned(64)));
+static volatile bool g_percpu_ready = false;
+
+static inline void percpu_wrmsr(uint32_t msr, uint64_t value)
+{
+    uint32_t lo = (uint32_t)(value & 0xFFFFFFFFu);
+    uint32_t hi = (uint32_t)(value >> 32);
+    __asm__ volatile ("wrmsr" : : "a"(lo), "d"(hi), "c"(msr));
+}
+
+void percpu_init_cpu(uint32_t cpu_id, uint32_t apic_id)
+{
+    if (cpu_id >= CPU_MAX_COUNT) {
+        return;
+    }
+    x86_percpu_t* pc = &g_percpu[cpu_id];
+    if (!g_percpu_ready && cpu_id == 0) {
+        /* BSP slot may already carry IRQL state from early boot. */
+        pc->self = pc;
+    } else {
+        memset(pc, 0, sizeof(*pc));
+        pc->self = pc;
+        pc->irql = 0; /* IRQL_PASSIVE */
+    }
+    pc->cpu_id = cpu_id;
+    pc->apic_id = apic_id;
+
+    /*
+     * Kernel runs with GS base = per-CPU block. KERNEL_GS_BASE holds the
+     * user value and is swapped in by SWAPGS on the way back to ring 3.
+     */
+    percpu_wrmsr(X86_MSR_GS_BASE, (uint64_t)(uintptr_t)pc);
+    percpu_wrmsr(X86_MSR_KERNEL_GS_BASE, 0);
+    __asm__ volatile ("" ::: "memory");
+    if (cpu_id == 0) {
+        g_percpu_ready = true;
+    }
+}
+
+x86_percpu_t* percpu_self(void)
+{
+    if (!g_percpu_ready) {
+        return &g_percpu[0];
+    }
+    x86_percpu_t* pc;
+    __asm__ volatile ("movq %%gs:0, %0" : "=r"(pc));
+    return pc;
+}
+
+x86_percpu_t* percpu_get(uint32_t cpu_id)
+{
+    if (cpu_id >= CPU_MAX_COUNT) {
+        return NULL;
+    }
+    return &g_percpu[cpu_id];
+}
+
+void cpu_defer_switch_release(volatile uint8_t* on_cpu_flag)
+{
+    percpu_self()->switch_release = (uint64_t)(uintptr_t)on_cpu_flag;
+}
@@ -0,0 +1,84 @@
+/**
+ * @file percpu.h
+ * @brief x86_64 per-CPU data block addressed through GS base
+ *
+ * Each logical CPU owns one x86_percpu_t. In kernel mode IA32_GS_BASE points
+ * at the block of the current CPU; on user entry/exit the stubs swap it with
+ * IA32_KERNEL_GS_BASE via SWAPGS. Field offsets are shared with assembly
+ * (isr_stubs.S, syscall_fast_entry.S) and must stay in sync.
+ */
+
+#ifndef _RODNIX_ARCH_X86_64_PERCPU_H
+#define _RODNIX_ARCH_X86_64_PERCPU_H
+
+#include "../../core/cpu.h"
+#include "../../core/interrupts.h"
+#include <stddef.h>
+#include <stdint.h>
+#include <stdbool.h>
+
+#define X86_MSR_GS_BASE        0xC0000101
+#define X86_MSR_KERNEL_GS_BASE 0xC0000102
+
+/* Offsets used from assembly */
+#define PERCPU_OFF_SELF           0x00
+#define PERCPU_OFF_KERNEL_RSP     0x08
+#define PERCPU_OFF_USER_RSP       0x10
+#define PERCPU_OFF_SWITCH_RELEASE 0x18
+#define PERCPU_OFF_CPU_ID         0x20
+
+typedef struct x86_percpu {
+    struct x86_percpu* self;      /* 0x00: self pointer (GS:0) */
+    uint64_t kernel_rsp;          /* 0x08: RSP0 for SYSCALL entry (mirrors TSS.rsp0) */
+    uint64_t user_rsp;            /* 0x10: user RSP scratch on SYSCALL entry */
+    uint64_t switch_release;      /* 0x18: on_cpu flag of the previous thread */
+    uint32_t cpu_id;              /* 0x20: logical CPU index (0 = BSP) */
+    uint32_t apic_id;             /* LAPIC ID of this CPU */
+    volatile uint32_t irql;       /* current IRQL of this CPU */
+    volatile uint32_t online;     /* CPU finished arch bring-up */
+    volatile uint64_t ipi_call_pending; /* cross-CPU call posted to this CPU */
+    volatile uint64_t active_pml4; /* PML4 loaded into CR3 (TLB shootdown) */
+    uint64_t tsc_deadline;        /* next LAPIC TSC-deadline of this CPU */
+    volatile uint32_t dbreg_gen;  /* debug register generation loaded here (dbreg.c) */
+} x86_percpu_t;
+
+_Static_assert(offsetof(x86_percpu_t, self) == PERCPU_OFF_SELF, "percpu self offset");
+_Static_assert(offsetof(x86_percpu_t, kernel_rsp) == PERCPU_OFF_KERNEL_RSP, "percpu kernel_rsp offset");
+_Static_assert(offsetof(x86_percpu_t, user_rsp) == PERCPU_OFF_USER_RSP, "percpu user_rsp offset");
+_Static_assert(offsetof(x86_percpu_t, switch_release) == PERCPU_OFF_SWITCH_RELEASE, "percpu switch_release offset");
+_Static_assert(offsetof(x86_percpu_t, cpu_id) == PERCPU_OFF_CPU_ID, "percpu cpu_id offset");
+
+/**
+ * Install the per-CPU block for @p cpu_id on the calling CPU (writes GS base).
+ * @param cpu_id Logical CPU index
+ * @param apic_id LAPIC ID of the calling CPU
+ */
+void percpu_init_cpu(uint32_t cpu_id, uint32_t apic_id);
+
+/**
+ * Per-CPU block of the calling CPU. Before percpu_init_cpu() ran on the BSP
+ * this returns the BSP slot so early boot code keeps working.
+ */
+x86_percpu_t* percpu_self(void);
+
+/**
+ * Per-CPU block by logical index.
+ * @return Block pointer or NULL if @p cpu_id is out of range
+ */
+x86_percpu_t* percpu_get(uint32_t cpu_id);
+
+/*
+ * Kernel entry/exit IRQL bookkeeping (interrupts.c). Entry from PASSIVE takes
+ * the IRQL giant lock; leaving to PASSIVE drops it.
+ */
+irql_t interrupt_enter_irql(irql_t level);
+void interrupt_leave_irql(irql_t level);
+
+/*
+ * Last step before IRET/SYSRET to user mode, after interrupt_leave_irql()
+ * dropped to PASSIVE (isr_handlers.c). Called with interrupts off; a task
+ * marked by the OOM killer exits here and the call does not return.
+ */
+void interrupt_return_to_user(void);
+
+#endif /* _RODNIX_ARCH_X86_64_PERCPU_H */
@@ -394,6 +394,32 @@ void pit_sleep_ms(uint32_t milliseconds)
     }
 }
 
+/**
+ * @function pit_delay_us
+ * @brief Busy-wait by polling the PIT channel 0 counter
+ *
+ * Works with interrupts disabled and with IRQ 0 masked; needs the PIT to be
+ * programmed (pit_init) and does nothing otherwise.
+ *
+ * @param us Delay in microseconds
+ */
+void pit_delay_us(uint32_t us)
+{
+    if (pit_reload_value == 0) {
+        return;
+    }
+    uint64_t start = pit_get_uptime_us();
+    uint64_t now = start;
+    if (now == 0) {
+        /* First sample only latched the counter */
+        start = now = pit_get_uptime_us();
+    }
+    while (now - start < (uint64_t)us) {
+        __asm__ volatile ("pause");
+        now = pit_get_uptime_us();
+    }
+}
+
 /**
  * @function pit_disable
  * @brief Disable PIT timer
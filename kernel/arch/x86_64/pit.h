@@ -25,6 +25,7 @@ int pit_unregister_callback(void (*handler)(void* arg), void* arg);
 
 /* Sleep function */
 void pit_sleep_ms(uint32_t milliseconds);
+void pit_delay_us(uint32_t us);
 
 /* Enable/disable */
 void pit_enable(void);
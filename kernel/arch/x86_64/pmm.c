@@ -2,9 +2,15 @@
  * @file pmm.c
  * @brief Physical Memory Manager (PMM) implementation for x86_64
  * 
- * This module implements physical memory management using a bitmap-based
- * allocator. It tracks free and used physical pages and provides allocation
- * and deallocation functions.
+ * Page state lives in a bitmap (1 = used). Free pages are kept in per-zone
+ * binary buddy free lists: blocks of 2^order pages, naturally aligned to
+ * their size, up to PMM_MAX_ORDER. A freed block merges with its buddy
+ * while the buddy is a free block of the same order and zone.
+ *
+ * The free-list links live inside the free pages themselves (reached via
+ * the higher-half direct map), so the allocator needs no memory beyond the
+ * bitmap. Every free page belongs to exactly one listed block; a buddy is
+ * therefore either a listed block head or (partly) in use.
  * 
  * @note This implementation is adapted for RodNIX.
  */
@@ -14,6 +20,7 @@
 #include "config.h"
 #include "../../../include/debug.h"
 #include "../../../include/error.h"
+#include "../../core/interrupts.h"
 #include "../../core/memory.h"
 #include <stddef.h>
 #include <stdbool.h>
@@ -45,20 +52,21 @@
 
 /* Fixed bitmap storage cap (matches low-memory placement) */
 #define PMM_BITMAP_MAX_SIZE 0x100000ULL
-#define PMM_MAX_FREE_RANGES 128
 #define PMM_MAX_REGIONS     128
 
+/* Zone boundaries (physical); both are multiples of the largest block */
+#define PMM_ZONE_DMA_END    0x1000000ULL    /* 16 MiB */
+#define PMM_ZONE_DMA32_END  0x100000000ULL  /* 4 GiB */
+
+/* pmm_block.tag of a free block head is PMM_BLOCK_TAG ^ its physical address */
+#define PMM_BLOCK_TAG       0x504D4D46524545ULL
+
 /* Page descriptor states */
 typedef enum {
     PMM_PAGE_FREE = 0,
     PMM_PAGE_USED = 1
 } pmm_page_state_t;
 
-typedef struct {
-    uint64_t start;
-    uint64_t count;
-} pmm_free_range_t;
-
 typedef struct {
     uint64_t phys;
     uint8_t zone;
@@ -66,9 +74,22 @@ typedef struct {
     uint16_t reserved;
 } pmm_page_desc_t;
 
+/* Header stored in the first page of every free buddy block */
+typedef struct pmm_block {
+    uint64_t tag;
+    uint32_t order;
+    uint32_t zone;
+    struct pmm_block* prev;
+    struct pmm_block* next;
+} pmm_block_t;
+
 /**
  * @struct pmm_state
  * @brief PMM internal state
+ *
+ * LOCKING: pmm_lock()/pmm_unlock() (IRQL giant section, as in kmem).
+ *   Protects: everything below after pmm_init*; init runs single-threaded
+ *   with interrupts off.
  */
 struct pmm_state {
     uint64_t total_pages;        /* Total number of physical pages */
@@ -85,8 +106,10 @@ struct pmm_state {
         uint64_t total_pages;
         uint64_t free_pages;
         uint64_t used_pages;
-        uint32_t free_range_count;
-        pmm_free_range_t free_ranges[PMM_MAX_FREE_RANGES];
+        struct {
+            pmm_block_t* head;
+            uint64_t count;
+        } free_area[PMM_ORDER_COUNT];
     } zones[PMM_ZONE_COUNT];
 
     pmm_region_t usable_regions[PMM_MAX_REGIONS];
@@ -205,13 +228,34 @@ static void pmm_zero_page(uint64_t phys)
 
 static inline pmm_zone_t pmm_zone_for_addr(uint64_t addr)
 {
-    /* Simple split: low memory below 16MB, everything else is normal. */
-    if (addr < 0x1000000ULL) {
-        return PMM_ZONE_LOW;
+    if (addr < PMM_ZONE_DMA_END) {
+        return PMM_ZONE_DMA;
+    }
+    if (addr < PMM_ZONE_DMA32_END) {
+        return PMM_ZONE_DMA32;
     }
     return PMM_ZONE_NORMAL;
 }
 
+/* Zone a page is accounted to: descriptors also know firmware (MMIO) ranges */
+static inline pmm_zone_t pmm_page_zone(uint64_t index)
+{
+    if (index < pmm_state.pages_count) {
+        return (pmm_zone_t)pmm_state.pages[index].zone;
+    }
+    return pmm_zone_for_addr(pmm_index_to_page(index));
+}
+
+static inline irql_t pmm_lock(void)
+{
+    return set_irql(IRQL_HIGH);
+}
+
+static inline void pmm_unlock(irql_t old)
+{
+    (void)set_irql(old);
+}
+
 static void pmm_regions_clear(void)
 {
     pmm_state.usable_count = 0;
@@ -260,123 +304,127 @@ static void pmm_mark_range_mmio(uint64_t start, uint64_t end)
     }
 }
 
-static void pmm_freelist_clear(void)
+/* ============================================================================
+ * Buddy Free Lists
+ * ============================================================================ */
+
+static inline pmm_block_t* pmm_block_at(uint64_t phys)
 {
-    for (int z = 0; z < PMM_ZONE_COUNT; z++) {
-        pmm_state.zones[z].free_range_count = 0;
-    }
+    return (pmm_block_t*)X86_64_PHYS_TO_VIRT(phys);
 }
 
-static void pmm_freelist_append(pmm_zone_t zone, uint64_t start, uint64_t count)
+static void pmm_area_push(pmm_zone_t zone, uint32_t order, uint64_t phys)
 {
-    if (count == 0) {
-        return;
-    }
-    if (pmm_state.zones[zone].free_range_count >= PMM_MAX_FREE_RANGES) {
-        return;
-    }
-    pmm_free_range_t* r = &pmm_state.zones[zone].free_ranges[pmm_state.zones[zone].free_range_count++];
-    r->start = start;
-    r->count = count;
+    pmm_block_t* b = pmm_block_at(phys);
+    b->tag = PMM_BLOCK_TAG ^ phys;
+    b->order = order;
+    b->zone = (uint32_t)zone;
+    b->prev = NULL;
+    b->next = pmm_state.zones[zone].free_area[order].head;
+    if (b->next) {
+        b->next->prev = b;
+    }
+    pmm_state.zones[zone].free_area[order].head = b;
+    pmm_state.zones[zone].free_area[order].count++;
 }
 
-static void pmm_freelist_insert(pmm_zone_t zone, uint64_t start, uint64_t count)
+static void pmm_area_unlink(pmm_block_t* b)
 {
-    if (count == 0) {
-        return;
-    }
-
-    uint32_t n = pmm_state.zones[zone].free_range_count;
-    if (n == 0) {
-        pmm_freelist_append(zone, start, count);
-        return;
-    }
+    pmm_zone_t zone = (pmm_zone_t)b->zone;
+    uint32_t order = b->order;
+    if (b->prev) {
+        b->prev->next = b->next;
+    } else {
+        pmm_state.zones[zone].free_area[order].head = b->next;
+    }
+    if (b->next) {
+        b->next->prev = b->prev;
+    }
+    pmm_state.zones[zone].free_area[order].count--;
+    b->tag = 0;
+}
 
-    uint32_t pos = 0;
-    while (pos < n && pmm_state.zones[zone].free_ranges[pos].start < start) {
-        pos++;
+/* Drop all lists; tags are wiped so no page outside a list looks like a head */
+static void pmm_areas_clear(void)
+{
+    for (int z = 0; z < PMM_ZONE_COUNT; z++) {
+        for (uint32_t o = 0; o < PMM_ORDER_COUNT; o++) {
+            for (pmm_block_t* b = pmm_state.zones[z].free_area[o].head; b; b = b->next) {
+                b->tag = 0;
+            }
+            pmm_state.zones[z].free_area[o].head = NULL;
+            pmm_state.zones[z].free_area[o].count = 0;
+        }
     }
+}
 
-    if (n >= PMM_MAX_FREE_RANGES) {
-        return;
+/* True if @phys heads a listed free block of exactly @order in @zone */
+static bool pmm_block_is_free(uint64_t phys, uint32_t order, pmm_zone_t zone)
+{
+    if (phys < pmm_state.memory_start ||
+        phys + ((uint64_t)PAGE_SIZE << order) > pmm_state.memory_end) {
+        return false;
     }
-
-    for (uint32_t i = n; i > pos; i--) {
-        pmm_state.zones[zone].free_ranges[i] = pmm_state.zones[zone].free_ranges[i - 1];
+    if (pmm_bitmap_test(pmm_page_to_index(phys))) {
+        return false;
     }
-    pmm_state.zones[zone].free_ranges[pos].start = start;
-    pmm_state.zones[zone].free_ranges[pos].count = count;
-    pmm_state.zones[zone].free_range_count++;
+    const pmm_block_t* b = pmm_block_at(phys);
+    return b->tag == (PMM_BLOCK_TAG ^ phys) && b->order == order && b->zone == (uint32_t)zone;
+}
 
-    /* Merge with previous/next if adjacent */
-    if (pos > 0) {
-        pmm_free_range_t* prev = &pmm_state.zones[zone].free_ranges[pos - 1];
-        pmm_free_range_t* cur = &pmm_state.zones[zone].free_ranges[pos];
-        if (prev->start + prev->count == cur->start) {
-            prev->count += cur->count;
-            for (uint32_t i = pos; i + 1 < pmm_state.zones[zone].free_range_count; i++) {
-                pmm_state.zones[zone].free_ranges[i] = pmm_state.zones[zone].free_ranges[i + 1];
-            }
-            pmm_state.zones[zone].free_range_count--;
-            pos--;
+/* List a block whose pages are free in the bitmap, merging with free buddies */
+static void pmm_buddy_insert(uint64_t phys, uint32_t order, pmm_zone_t zone, bool merge)
+{
+    while (merge && order < PMM_MAX_ORDER) {
+        uint64_t buddy = phys ^ ((uint64_t)PAGE_SIZE << order);
+        if (!pmm_block_is_free(buddy, order, zone)) {
+            break;
         }
+        pmm_area_unlink(pmm_block_at(buddy));
+        phys &= ~((uint64_t)PAGE_SIZE << order);
+        order++;
     }
-    if (pos + 1 < pmm_state.zones[zone].free_range_count) {
-        pmm_free_range_t* cur = &pmm_state.zones[zone].free_ranges[pos];
-        pmm_free_range_t* next = &pmm_state.zones[zone].free_ranges[pos + 1];
-        if (cur->start + cur->count == next->start) {
-            cur->count += next->count;
-            for (uint32_t i = pos + 1; i + 1 < pmm_state.zones[zone].free_range_count; i++) {
-                pmm_state.zones[zone].free_ranges[i] = pmm_state.zones[zone].free_ranges[i + 1];
-            }
-            pmm_state.zones[zone].free_range_count--;
+    pmm_area_push(zone, order, phys);
+}
+
+/* List a free run as the largest naturally aligned blocks it contains */
+static void pmm_buddy_insert_run(uint64_t phys, uint64_t count, pmm_zone_t zone, bool merge)
+{
+    while (count > 0) {
+        uint64_t pfn = phys >> PAGE_SHIFT;
+        uint32_t order = 0;
+        while (order < PMM_MAX_ORDER && (pfn & ((2ULL << order) - 1)) == 0 &&
+               (2ULL << order) <= count) {
+            order++;
         }
+        pmm_buddy_insert(phys, order, zone, merge);
+        phys += (uint64_t)PAGE_SIZE << order;
+        count -= 1ULL << order;
     }
 }
 
-static bool pmm_freelist_remove(pmm_zone_t zone, uint64_t start, uint64_t count)
+/* Unlist a block of @order from @zone, splitting a larger one if needed */
+static uint64_t pmm_buddy_take(pmm_zone_t zone, uint32_t order)
 {
-    if (count == 0) {
-        return false;
-    }
-    uint32_t n = pmm_state.zones[zone].free_range_count;
-    for (uint32_t i = 0; i < n; i++) {
-        pmm_free_range_t* r = &pmm_state.zones[zone].free_ranges[i];
-        if (start >= r->start && start + count <= r->start + r->count) {
-            uint64_t tail_start = start + count;
-            uint64_t tail_count = (r->start + r->count) - tail_start;
-            uint64_t head_count = start - r->start;
-
-            if (head_count > 0 && tail_count > 0) {
-                r->count = head_count;
-                if (pmm_state.zones[zone].free_range_count < PMM_MAX_FREE_RANGES) {
-                    for (uint32_t j = n; j > i + 1; j--) {
-                        pmm_state.zones[zone].free_ranges[j] = pmm_state.zones[zone].free_ranges[j - 1];
-                    }
-                    pmm_state.zones[zone].free_ranges[i + 1].start = tail_start;
-                    pmm_state.zones[zone].free_ranges[i + 1].count = tail_count;
-                    pmm_state.zones[zone].free_range_count++;
-                }
-            } else if (head_count > 0) {
-                r->count = head_count;
-            } else if (tail_count > 0) {
-                r->start = tail_start;
-                r->count = tail_count;
-            } else {
-                for (uint32_t j = i; j + 1 < n; j++) {
-                    pmm_state.zones[zone].free_ranges[j] = pmm_state.zones[zone].free_ranges[j + 1];
-                }
-                pmm_state.zones[zone].free_range_count--;
-            }
-            return true;
+    for (uint32_t k = order; k < PMM_ORDER_COUNT; k++) {
+        pmm_block_t* b = pmm_state.zones[zone].free_area[k].head;
+        if (!b) {
+            continue;
         }
+        pmm_area_unlink(b);
+        uint64_t phys = (uint64_t)X86_64_VIRT_TO_PHYS(b);
+        while (k > order) {
+            k--;
+            pmm_area_push(zone, k, phys + ((uint64_t)PAGE_SIZE << k));
+        }
+        return phys;
     }
-    return false;
+    return 0;
 }
 
 static void pmm_rebuild_free_lists(void)
 {
-    pmm_freelist_clear();
+    pmm_areas_clear();
 
     uint64_t run_start = 0;
     uint64_t run_count = 0;
@@ -384,8 +432,7 @@ static void pmm_rebuild_free_lists(void)
 
     for (uint64_t i = 0; i < pmm_state.total_pages; i++) {
         if (!pmm_bitmap_test(i)) {
-            uint64_t phys = pmm_index_to_page(i);
-            pmm_zone_t zone = pmm_zone_for_addr(phys);
+            pmm_zone_t zone = pmm_page_zone(i);
             if (run_count == 0) {
                 run_start = i;
                 run_count = 1;
@@ -393,19 +440,19 @@ static void pmm_rebuild_free_lists(void)
             } else if (zone == run_zone && run_start + run_count == i) {
                 run_count++;
             } else {
-                pmm_freelist_append(run_zone, run_start, run_count);
+                pmm_buddy_insert_run(pmm_index_to_page(run_start), run_count, run_zone, false);
                 run_start = i;
                 run_count = 1;
                 run_zone = zone;
             }
         } else if (run_count > 0) {
-            pmm_freelist_append(run_zone, run_start, run_count);
+            pmm_buddy_insert_run(pmm_index_to_page(run_start), run_count, run_zone, false);
             run_count = 0;
         }
     }
 
     if (run_count > 0) {
-        pmm_freelist_append(run_zone, run_start, run_count);
+        pmm_buddy_insert_run(pmm_index_to_page(run_start), run_count, run_zone, false);
     }
 }
 
@@ -438,6 +485,33 @@ static void pmm_setup_page_descs(uint64_t memory_start, uint64_t memory_end,
     }
 }
 
+/* Bitmap, descriptor and counter update for one page changing state */
+static void pmm_page_set_used(uint64_t index)
+{
+    pmm_zone_t zone = pmm_page_zone(index);
+    pmm_bitmap_set(index);
+    pmm_state.free_pages--;
+    pmm_state.used_pages++;
+    pmm_state.zones[zone].free_pages--;
+    pmm_state.zones[zone].used_pages++;
+    if (index < pmm_state.pages_count) {
+        pmm_state.pages[index].state = PMM_PAGE_USED;
+    }
+}
+
+static void pmm_page_set_free(uint64_t index)
+{
+    pmm_zone_t zone = pmm_page_zone(index);
+    pmm_bitmap_clear(index);
+    pmm_state.free_pages++;
+    pmm_state.used_pages--;
+    pmm_state.zones[zone].free_pages++;
+    pmm_state.zones[zone].used_pages--;
+    if (index < pmm_state.pages_count) {
+        pmm_state.pages[index].state = PMM_PAGE_FREE;
+    }
+}
+
 static void pmm_mark_range_free(uint64_t start, uint64_t end)
 {
     if (end <= start) {
@@ -457,15 +531,7 @@ static void pmm_mark_range_free(uint64_t start, uint64_t end)
     for (uint64_t addr = start; addr < end; addr += PAGE_SIZE) {
         uint64_t index = pmm_page_to_index(addr);
         if (pmm_bitmap_test(index)) {
-            pmm_bitmap_clear(index);
-            pmm_state.free_pages++;
-            pmm_state.used_pages--;
-            if (index < pmm_state.pages_count) {
-                pmm_state.pages[index].state = PMM_PAGE_FREE;
-                pmm_zone_t zone = (pmm_zone_t)pmm_state.pages[index].zone;
-                pmm_state.zones[zone].free_pages++;
-                pmm_state.zones[zone].used_pages--;
-            }
+            pmm_page_set_free(index);
         }
     }
 }
@@ -489,15 +555,7 @@ static void pmm_mark_range_used(uint64_t start, uint64_t end)
     for (uint64_t addr = start; addr < end; addr += PAGE_SIZE) {
         uint64_t index = pmm_page_to_index(addr);
         if (!pmm_bitmap_test(index)) {
-            pmm_bitmap_set(index);
-            pmm_state.free_pages--;
-            pmm_state.used_pages++;
-            if (index < pmm_state.pages_count) {
-                pmm_state.pages[index].state = PMM_PAGE_USED;
-                pmm_zone_t zone = (pmm_zone_t)pmm_state.pages[index].zone;
-                pmm_state.zones[zone].free_pages--;
-                pmm_state.zones[zone].used_pages++;
-            }
+            pmm_page_set_used(index);
         }
     }
 }
@@ -661,13 +719,13 @@ int pmm_init(uint64_t memory_start, uint64_t memory_end, void* bitmap_virt)
     pmm_setup_page_descs(memory_start, memory_end,
                          (uint64_t)((uintptr_t)bitmap_virt),
                          bitmap_size);
-    if (pmm_state.pages_count > 0) {
-        for (uint64_t i = 0; i < pmm_state.pages_count; i++) {
+    for (uint64_t i = 0; i < total_pages; i++) {
+        if (i < pmm_state.pages_count) {
             pmm_state.pages[i].state = PMM_PAGE_FREE;
-            pmm_zone_t zone = (pmm_zone_t)pmm_state.pages[i].zone;
-            pmm_state.zones[zone].total_pages++;
-            pmm_state.zones[zone].free_pages++;
         }
+        pmm_zone_t zone = pmm_page_zone(i);
+        pmm_state.zones[zone].total_pages++;
+        pmm_state.zones[zone].free_pages++;
     }
 
     pmm_add_region(pmm_state.usable_regions, &pmm_state.usable_count,
@@ -751,13 +809,12 @@ int pmm_init_from_mmap(uint64_t memory_start, uint64_t memory_end,
             }
             mmio_off += entry_size;
         }
+    }
 
-        for (uint64_t i = 0; i < pmm_state.pages_count; i++) {
-            pmm_state.pages[i].state = PMM_PAGE_USED;
-            pmm_zone_t zone = (pmm_zone_t)pmm_state.pages[i].zone;
-            pmm_state.zones[zone].total_pages++;
-            pmm_state.zones[zone].used_pages++;
-        }
+    for (uint64_t i = 0; i < total_pages; i++) {
+        pmm_zone_t zone = pmm_page_zone(i);
+        pmm_state.zones[zone].total_pages++;
+        pmm_state.zones[zone].used_pages++;
     }
 
     uint32_t free_off = offset;
@@ -789,80 +846,152 @@ int pmm_init_from_mmap(uint64_t memory_start, uint64_t memory_end,
     return RDNX_OK;
 }
 
-/**
- * @function pmm_alloc_page
- * @brief Allocate a single physical page
- * 
- * This function implements a first-fit allocation strategy.
- * It finds the first free page and marks it as used.
- * 
- * @return Physical address of allocated page, or 0 on failure
- * 
- * @note The returned address is page-aligned.
- * @note This would be part of a zone-based allocator in a more advanced implementation.
- *       For now, we use a simple bitmap-based approach.
+/* Take 2^order pages from @zone; pages are marked used but not zeroed */
+static uint64_t pmm_alloc_block_locked(pmm_zone_t zone, uint32_t order)
+{
+    uint64_t phys = pmm_buddy_take(zone, order);
+    if (!phys) {
+        return 0;
+    }
+    uint64_t index = pmm_page_to_index(phys);
+    for (uint64_t p = 0; p < (1ULL << order); p++) {
+        pmm_page_set_used(index + p);
+    }
+    return phys;
+}
+
+/*
+ * Contiguous run larger than the biggest buddy block: scan the bitmap, as
+ * the range allocator did, then rebuild the lists around the hole.
  */
-uint64_t pmm_alloc_page(void)
+static uint64_t pmm_alloc_run_locked(pmm_zone_t zone, uint64_t count)
 {
-    uint64_t phys = pmm_alloc_page_in_zone(PMM_ZONE_NORMAL);
-    if (phys) {
-        return phys;
+    uint64_t run_start = 0;
+    uint64_t run_count = 0;
+    for (uint64_t i = 0; i < pmm_state.total_pages; i++) {
+        if (pmm_bitmap_test(i) || pmm_page_zone(i) != zone) {
+            run_count = 0;
+            continue;
+        }
+        if (run_count == 0) {
+            run_start = i;
+        }
+        if (++run_count == count) {
+            for (uint64_t p = 0; p < count; p++) {
+                pmm_page_set_used(run_start + p);
+            }
+            pmm_rebuild_free_lists();
+            return pmm_index_to_page(run_start);
+        }
     }
-    return pmm_alloc_page_in_zone(PMM_ZONE_LOW);
+    return 0;
 }
 
-uint64_t pmm_alloc_page_in_zone(pmm_zone_t zone)
+static uint32_t pmm_order_for(uint64_t count)
 {
-    if (pmm_state.free_pages == 0 || zone >= PMM_ZONE_COUNT) {
-        TRACE_EVENT("oom: pmm_alloc_page");
-        memory_oom_inc_pmm();
-        return 0;
+    uint32_t order = 0;
+    while ((1ULL << order) < count) {
+        order++;
     }
+    return order;
+}
 
-    uint32_t n = pmm_state.zones[zone].free_range_count;
-    uint64_t index = 0;
-    bool found = false;
-    if (n > 0) {
-        pmm_free_range_t* r = &pmm_state.zones[zone].free_ranges[0];
-        index = r->start;
-        found = pmm_freelist_remove(zone, index, 1);
-    }
-    if (!found) {
-        for (uint64_t i = 0; i < pmm_state.total_pages; i++) {
-            if (!pmm_bitmap_test(i)) {
-                uint64_t phys = pmm_index_to_page(i);
-                if (pmm_zone_for_addr(phys) == zone) {
-                    index = i;
-                    found = true;
-                    break;
-                }
-            }
+static void pmm_zero_pages(uint64_t phys, uint64_t count)
+{
+    for (uint64_t p = 0; p < count; p++) {
+        pmm_zero_page(phys + p * PAGE_SIZE);
+    }
+}
+
+/* Free @count pages, listing runs of pages that were actually in use */
+static void pmm_free_pages_locked(uint64_t phys, uint64_t count)
+{
+    uint64_t run_start = 0;
+    uint64_t run_count = 0;
+    pmm_zone_t run_zone = PMM_ZONE_NORMAL;
+
+    for (uint64_t p = 0; p < count; p++, phys += PAGE_SIZE) {
+        if (phys < pmm_state.memory_start || phys >= pmm_state.memory_end) {
+            break; /* Invalid address */
+        }
+        uint64_t index = pmm_page_to_index(phys);
+        pmm_zone_t zone = pmm_page_zone(index);
+        if (run_count > 0 && (!pmm_bitmap_test(index) || zone != run_zone)) {
+            pmm_buddy_insert_run(run_start, run_count, run_zone, true);
+            run_count = 0;
+        }
+        if (!pmm_bitmap_test(index)) {
+            continue; /* Already free */
+        }
+        pmm_page_set_free(index);
+        pmm_block_at(phys)->tag = 0; /* Owner data must not pass for a block head */
+        if (run_count == 0) {
+            run_start = phys;
+            run_zone = zone;
         }
+        run_count++;
     }
-    if (!found) {
-        TRACE_EVENT("oom: pmm_alloc_page");
-        memory_oom_inc_pmm();
-        return 0;
+    if (run_count > 0) {
+        pmm_buddy_insert_run(run_start, run_count, run_zone, true);
     }
+}
 
-    pmm_bitmap_set(index);
-    pmm_state.free_pages--;
-    pmm_state.used_pages++;
-
-    uint64_t phys = pmm_index_to_page(index);
-    pmm_zero_page(phys);
+/*
+ * @zone is the highest acceptable zone: allocation tries it first and then
+ * falls back to the lower ones, so DMA memory is used last.
+ */
+static uint64_t pmm_alloc_contig(pmm_zone_t zone, uint64_t count, bool exact_order)
+{
+    if (count == 0 || zone >= PMM_ZONE_MMIO) {
+        return 0;
+    }
 
-    if (index < pmm_state.pages_count) {
-        pmm_state.pages[index].state = PMM_PAGE_USED;
-        if (pmm_state.zones[zone].free_pages > 0) {
-            pmm_state.zones[zone].free_pages--;
-            pmm_state.zones[zone].used_pages++;
+    uint32_t order = pmm_order_for(count);
+    uint64_t phys = 0;
+    irql_t old = pmm_lock();
+    for (int z = (int)zone; z >= (int)PMM_ZONE_DMA && !phys; z--) {
+        if (pmm_state.zones[z].free_pages < count) {
+            continue;
+        }
+        if (order <= PMM_MAX_ORDER) {
+            phys = pmm_alloc_block_locked((pmm_zone_t)z, order);
+            if (phys && !exact_order && (1ULL << order) > count) {
+                /* Give back the tail beyond @count */
+                pmm_free_pages_locked(phys + count * PAGE_SIZE, (1ULL << order) - count);
+            }
+        } else if (!exact_order) {
+            phys = pmm_alloc_run_locked((pmm_zone_t)z, count);
         }
     }
+    pmm_unlock(old);
 
+    if (!phys) {
+        TRACE_EVENT("oom: pmm_alloc");
+        memory_oom_inc_pmm();
+        return 0;
+    }
+    pmm_zero_pages(phys, exact_order ? (1ULL << order) : count);
     return phys;
 }
 
+/**
+ * @function pmm_alloc_page
+ * @brief Allocate a single zeroed physical page
+ * 
+ * @return Physical address of allocated page, or 0 on failure
+ * 
+ * @note Normal memory is preferred; DMA32 and then DMA are the fallbacks.
+ */
+uint64_t pmm_alloc_page(void)
+{
+    return pmm_alloc_contig(PMM_ZONE_NORMAL, 1, true);
+}
+
+uint64_t pmm_alloc_page_in_zone(pmm_zone_t zone)
+{
+    return pmm_alloc_contig(zone, 1, true);
+}
+
 /**
  * @function pmm_free_page
  * @brief Free a single physical page
@@ -873,151 +1002,45 @@ uint64_t pmm_alloc_page_in_zone(pmm_zone_t zone)
  */
 void pmm_free_page(uint64_t phys)
 {
-    if (phys < pmm_state.memory_start || phys >= pmm_state.memory_end) {
-        return; /* Invalid address */
-    }
-    
-    if ((phys & (PAGE_SIZE - 1)) != 0) {
-        return; /* Not page-aligned */
-    }
-    
-    uint64_t index = pmm_page_to_index(phys);
-    
-    if (pmm_bitmap_test(index)) {
-        /* Mark as free */
-        pmm_bitmap_clear(index);
-        pmm_state.free_pages++;
-        pmm_state.used_pages--;
-        pmm_zone_t zone = pmm_zone_for_addr(phys);
-        if (index < pmm_state.pages_count) {
-            pmm_state.pages[index].state = PMM_PAGE_FREE;
-            pmm_state.zones[zone].free_pages++;
-            if (pmm_state.zones[zone].used_pages > 0) {
-                pmm_state.zones[zone].used_pages--;
-            }
-        }
-        pmm_freelist_insert(zone, index, 1);
-    }
+    pmm_free_pages(phys, 1);
 }
 
 /**
  * @function pmm_alloc_pages
  * @brief Allocate multiple contiguous physical pages
  * 
- * This function attempts to allocate contiguous physical pages, which is
- * important for large page mappings (2MB, 1GB) and DMA buffers.
+ * The run is carved from a buddy block of the next power-of-two size, so it
+ * starts aligned to that size (a 3-page request is 16 KiB aligned); the
+ * unused tail goes straight back to the free lists. Runs above the largest
+ * block fall back to a bitmap scan.
  * 
  * @param count Number of pages to allocate
  * @return Physical address of first page, or 0 on failure
- * 
- * @note This implements a best-fit contiguous allocation algorithm.
- *       Zone-based allocation would provide better performance, but this
- *       simple approach works for initial implementation.
  */
 uint64_t pmm_alloc_pages(uint32_t count)
 {
-    uint64_t phys = pmm_alloc_pages_in_zone(PMM_ZONE_NORMAL, count);
-    if (phys) {
-        return phys;
-    }
-    return pmm_alloc_pages_in_zone(PMM_ZONE_LOW, count);
+    return pmm_alloc_contig(PMM_ZONE_NORMAL, count, false);
 }
 
 uint64_t pmm_alloc_pages_in_zone(pmm_zone_t zone, uint32_t count)
 {
-    if (count == 0 || zone >= PMM_ZONE_COUNT) {
-        return 0;
-    }
-    if (pmm_state.free_pages < count) {
-        return 0;
-    }
-
-    uint32_t n = pmm_state.zones[zone].free_range_count;
-    for (uint32_t i = 0; i < n; i++) {
-        pmm_free_range_t* r = &pmm_state.zones[zone].free_ranges[i];
-        if (r->count >= count) {
-            uint64_t start = r->start;
-            if (!pmm_freelist_remove(zone, start, count)) {
-                return 0;
-            }
-            for (uint32_t p = 0; p < count; p++) {
-                uint64_t idx = start + p;
-                pmm_bitmap_set(idx);
-                if (idx < pmm_state.pages_count) {
-                    pmm_state.pages[idx].state = PMM_PAGE_USED;
-                }
-            }
-
-            pmm_state.free_pages -= count;
-            pmm_state.used_pages += count;
-            if (pmm_state.zones[zone].free_pages >= count) {
-                pmm_state.zones[zone].free_pages -= count;
-                pmm_state.zones[zone].used_pages += count;
-            }
-
-            uint64_t phys = pmm_index_to_page(start);
-            for (uint32_t p = 0; p < count; p++) {
-                pmm_zero_page(phys + (uint64_t)p * PAGE_SIZE);
-            }
-
-            return pmm_index_to_page(start);
-        }
-    }
-
-    /*
-     * Fallback path: if free-list metadata is stale/trimmed, scan bitmap
-     * directly for a contiguous run in the requested zone.
-     */
-    uint64_t run_start = 0;
-    uint32_t run_count = 0;
-    bool found = false;
-    for (uint64_t i = 0; i < pmm_state.total_pages; i++) {
-        if (pmm_bitmap_test(i)) {
-            run_count = 0;
-            continue;
-        }
-        uint64_t phys = pmm_index_to_page(i);
-        if (pmm_zone_for_addr(phys) != zone) {
-            run_count = 0;
-            continue;
-        }
-        if (run_count == 0) {
-            run_start = i;
-        }
-        run_count++;
-        if (run_count >= count) {
-            found = true;
-            break;
-        }
-    }
+    return pmm_alloc_contig(zone, count, false);
+}
 
-    if (!found) {
+uint64_t pmm_alloc_order(pmm_zone_t zone, uint32_t order)
+{
+    if (order > PMM_MAX_ORDER) {
         return 0;
     }
+    return pmm_alloc_contig(zone, 1ULL << order, true);
+}
 
-    for (uint32_t p = 0; p < count; p++) {
-        uint64_t idx = run_start + p;
-        pmm_bitmap_set(idx);
-        if (idx < pmm_state.pages_count) {
-            pmm_state.pages[idx].state = PMM_PAGE_USED;
-        }
-    }
-
-    pmm_state.free_pages -= count;
-    pmm_state.used_pages += count;
-    if (pmm_state.zones[zone].free_pages >= count) {
-        pmm_state.zones[zone].free_pages -= count;
-        pmm_state.zones[zone].used_pages += count;
-    }
-
-    uint64_t phys = pmm_index_to_page(run_start);
-    for (uint32_t p = 0; p < count; p++) {
-        pmm_zero_page(phys + (uint64_t)p * PAGE_SIZE);
+void pmm_free_order(uint64_t phys, uint32_t order)
+{
+    if (order > PMM_MAX_ORDER || (phys & (((uint64_t)PAGE_SIZE << order) - 1)) != 0) {
+        return; /* Not a block of this order */
     }
-
-    /* Re-sync free-list metadata after bitmap fallback allocation. */
-    pmm_rebuild_free_lists();
-    return phys;
+    pmm_free_pages(phys, 1U << order);
 }
 
 void pmm_reserve_range(uint64_t start, uint64_t end)
@@ -1026,10 +1049,12 @@ void pmm_reserve_range(uint64_t start, uint64_t end)
         return;
     }
 
+    irql_t old = pmm_lock();
     pmm_mark_range_used(start, end);
     pmm_add_region(pmm_state.reserved_regions, &pmm_state.reserved_count,
                    start, end - start);
     pmm_rebuild_free_lists();
+    pmm_unlock(old);
 }
 
 void pmm_release_range(uint64_t start, uint64_t end)
@@ -1037,22 +1062,30 @@ void pmm_release_range(uint64_t start, uint64_t end)
     if (end <= start) {
         return;
     }
+    irql_t old = pmm_lock();
     pmm_mark_range_free(start, end);
     pmm_rebuild_free_lists();
+    pmm_unlock(old);
 }
 
 /**
  * @function pmm_free_pages
  * @brief Free multiple physical pages
  * 
+ * Pages need not be a single buddy block: the range is split into aligned
+ * blocks, each merged with its free buddies. Pages already free are skipped.
+ * 
  * @param phys Physical address of first page
  * @param count Number of pages to free
  */
 void pmm_free_pages(uint64_t phys, uint32_t count)
 {
-    for (uint32_t i = 0; i < count; i++) {
-        pmm_free_page(phys + i * PAGE_SIZE);
+    if ((phys & (PAGE_SIZE - 1)) != 0) {
+        return; /* Not page-aligned */
     }
+    irql_t old = pmm_lock();
+    pmm_free_pages_locked(phys, count);
+    pmm_unlock(old);
 }
 
 /**
@@ -1093,30 +1126,55 @@ int pmm_get_zone_stats(pmm_zone_t zone, pmm_zone_stats_t* out)
     if (!out || zone < 0 || zone >= PMM_ZONE_COUNT) {
         return RDNX_E_INVALID;
     }
+    irql_t old = pmm_lock();
     out->total_pages = pmm_state.zones[zone].total_pages;
     out->free_pages = pmm_state.zones[zone].free_pages;
     out->used_pages = pmm_state.zones[zone].used_pages;
+    for (uint32_t o = 0; o < PMM_ORDER_COUNT; o++) {
+        out->free_blocks[o] = pmm_state.zones[zone].free_area[o].count;
+    }
+    pmm_unlock(old);
     return RDNX_OK;
 }
 
+const char* pmm_zone_name(pmm_zone_t zone)
+{
+    static const char* const names[PMM_ZONE_COUNT] = { "dma", "dma32", "normal", "mmio" };
+    if (zone < 0 || zone >= PMM_ZONE_COUNT) {
+        return "?";
+    }
+    return names[zone];
+}
+
+/* Free runs of a zone, in address order (walks the bitmap; for diagnostics) */
 int pmm_get_free_regions(pmm_zone_t zone, pmm_region_t* out, uint32_t max,
                          uint32_t* out_count)
 {
     if (!out_count || zone >= PMM_ZONE_COUNT) {
         return RDNX_E_INVALID;
     }
-    uint32_t n = pmm_state.zones[zone].free_range_count;
-    if (!out || max == 0) {
-        *out_count = n;
-        return RDNX_OK;
-    }
-    uint32_t count = (n < max) ? n : max;
-    for (uint32_t i = 0; i < count; i++) {
-        const pmm_free_range_t* r = &pmm_state.zones[zone].free_ranges[i];
-        out[i].base = pmm_index_to_page(r->start);
-        out[i].length = r->count * PAGE_SIZE;
+    uint32_t n = 0;
+    bool in_run = false;
+    irql_t old = pmm_lock();
+    for (uint64_t i = 0; i < pmm_state.total_pages; i++) {
+        if (pmm_bitmap_test(i) || pmm_page_zone(i) != zone) {
+            in_run = false;
+            continue;
+        }
+        if (!in_run) {
+            in_run = true;
+            if (out && n < max) {
+                out[n].base = pmm_index_to_page(i);
+                out[n].length = 0;
+            }
+            n++;
+        }
+        if (out && n <= max) {
+            out[n - 1].length += PAGE_SIZE;
+        }
     }
-    *out_count = count;
+    pmm_unlock(old);
+    *out_count = (out && max > 0 && n > max) ? max : n;
     return RDNX_OK;
 }
 
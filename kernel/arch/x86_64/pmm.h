@@ -8,17 +8,27 @@
 
 #include <stdint.h>
 
+/*
+ * Physical zones. An allocation "in" a zone may also be served from any
+ * lower zone, so the zone is an upper bound on the address.
+ */
 typedef enum {
-    PMM_ZONE_LOW = 0,
-    PMM_ZONE_NORMAL = 1,
-    PMM_ZONE_MMIO = 2,
+    PMM_ZONE_DMA = 0,     /* Below 16 MiB: ISA DMA, boot identity map */
+    PMM_ZONE_DMA32 = 1,   /* Below 4 GiB: 32-bit bus masters */
+    PMM_ZONE_NORMAL = 2,  /* Everything else */
+    PMM_ZONE_MMIO = 3,    /* Firmware-reserved ranges; never allocated */
     PMM_ZONE_COUNT
 } pmm_zone_t;
 
+/* Largest buddy block is 2^PMM_MAX_ORDER pages (4 MiB) */
+#define PMM_MAX_ORDER   10
+#define PMM_ORDER_COUNT (PMM_MAX_ORDER + 1)
+
 typedef struct {
     uint64_t total_pages;
     uint64_t free_pages;
     uint64_t used_pages;
+    uint64_t free_blocks[PMM_ORDER_COUNT]; /* Free buddy blocks per order */
 } pmm_zone_stats_t;
 
 typedef struct {
@@ -32,13 +42,24 @@ int pmm_init_from_mmap(uint64_t memory_start, uint64_t memory_end,
                        void* bitmap_virt, uint64_t bitmap_phys,
                        const void* mmap_tag, uint32_t mmap_size, uint32_t entry_size);
 
-/* Allocate/free pages */
+/* Allocate/free pages (allocations are zeroed) */
 uint64_t pmm_alloc_page(void);
 uint64_t pmm_alloc_page_in_zone(pmm_zone_t zone);
 void pmm_free_page(uint64_t phys);
 uint64_t pmm_alloc_pages(uint32_t count);
 uint64_t pmm_alloc_pages_in_zone(pmm_zone_t zone, uint32_t count);
 void pmm_free_pages(uint64_t phys, uint32_t count);
+
+/**
+ * Allocate a buddy block of 2^order contiguous pages.
+ * @param zone Highest acceptable zone
+ * @param order 0..PMM_MAX_ORDER
+ * @return Physical address aligned to the block size, or 0
+ */
+uint64_t pmm_alloc_order(pmm_zone_t zone, uint32_t order);
+
+/* Free a pmm_alloc_order() block */
+void pmm_free_order(uint64_t phys, uint32_t order);
 void pmm_reserve_range(uint64_t start, uint64_t end);
 
 /**
@@ -53,6 +74,7 @@ uint64_t pmm_get_total_pages(void);
 uint64_t pmm_get_free_pages(void);
 uint64_t pmm_get_used_pages(void);
 int pmm_get_zone_stats(pmm_zone_t zone, pmm_zone_stats_t* out);
+const char* pmm_zone_name(pmm_zone_t zone);
 int pmm_get_free_regions(pmm_zone_t zone, pmm_region_t* out, uint32_t max,
                          uint32_t* out_count);
 int pmm_get_usable_regions(pmm_region_t* out, uint32_t max, uint32_t* out_count);
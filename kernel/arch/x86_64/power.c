@@ -0,0 +1,96 @@
+/**
+ * @file power.c
+ * @brief x86_64 power-off and reset
+ *
+ * Power-off is ACPI S5 only; there is no legacy way to cut power. Reset
+ * tries the FADT reset register, then the 8042 keyboard controller pulse,
+ * then a triple fault through an empty IDT. Each path first parks the
+ * other CPUs with an NMI so none of them keeps running (or holds devices)
+ * while this one halts or resets the machine.
+ */
+
+#include "acpi.h"
+#include "apic.h"
+#include "../../core/power.h"
+#include "../../../include/console.h"
+
+#define KBC_STATUS_PORT     0x64
+#define KBC_STATUS_IBF      (1u << 1)
+#define KBC_CMD_PULSE_RESET 0xFE
+#define KBC_SPIN_LIMIT      100000U
+#define RESET_SETTLE_SPIN   10000000U
+
+static inline void power_outb(uint16_t port, uint8_t value)
+{
+    __asm__ volatile ("outb %0, %1" : : "a"(value), "Nd"(port));
+}
+
+static inline uint8_t power_inb(uint16_t port)
+{
+    uint8_t value;
+    __asm__ volatile ("inb %1, %0" : "=a"(value) : "Nd"(port));
+    return value;
+}
+
+static void power_kbc_reset(void)
+{
+    for (uint32_t i = 0; i < KBC_SPIN_LIMIT; i++) {
+        if ((power_inb(KBC_STATUS_PORT) & KBC_STATUS_IBF) == 0) {
+            break;
+        }
+        __asm__ volatile ("pause");
+    }
+    power_outb(KBC_STATUS_PORT, KBC_CMD_PULSE_RESET);
+    for (uint32_t i = 0; i < RESET_SETTLE_SPIN; i++) {
+        __asm__ volatile ("pause");
+    }
+}
+
+static void power_triple_fault(void)
+{
+    struct {
+        uint16_t limit;
+        uint64_t base;
+    } __attribute__((packed)) empty_idt = { 0, 0 };
+
+    __asm__ volatile ("lidt %0; int3" : : "m"(empty_idt) : "memory");
+}
+
+void power_halt(void)
+{
+    __asm__ volatile ("cli");
+    (void)apic_ipi_stop_others();
+    for (;;) {
+        __asm__ volatile ("hlt");
+    }
+}
+
+void power_off(void)
+{
+    __asm__ volatile ("cli");
+    (void)apic_ipi_stop_others();
+    if (acpi_s5_available()) {
+        kputs("[POWER] entering ACPI S5\n");
+        (void)acpi_enter_s5();
+        kputs("[POWER] ACPI S5 failed\n");
+    } else {
+        kputs("[POWER] ACPI S5 unavailable\n");
+    }
+    kputs("[POWER] system halted, it is safe to turn off the machine\n");
+    power_halt();
+}
+
+void power_reboot(void)
+{
+    __asm__ volatile ("cli");
+    (void)apic_ipi_stop_others();
+    if (acpi_reset_available()) {
+        kputs("[POWER] reset via FADT reset register\n");
+        (void)acpi_reset();
+    }
+    kputs("[POWER] reset via keyboard controller\n");
+    power_kbc_reset();
+    kputs("[POWER] reset via triple fault\n");
+    power_triple_fault();
+    power_halt();
+}
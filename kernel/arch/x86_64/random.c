@@ -0,0 +1,74 @@
+/**
+ * @file random.c
+ * @brief x86_64 entropy sources: RDRAND and the TSC
+ *
+ * RDRAND is used when CPUID.01H:ECX[30] advertises it. The instruction may
+ * transiently fail (CF=0) when the DRNG is drained; Intel recommends ten
+ * retries before treating it as broken.
+ */
+
+#include "clocksource.h"
+#include "../../core/random.h"
+#include <stddef.h>
+
+#define CPUID1_ECX_RDRAND   (1u << 30)
+#define RDRAND_RETRIES      10u
+
+static bool rng_probed = false;
+static bool rng_rdrand = false;
+
+static inline void rng_cpuid(uint32_t leaf, uint32_t subleaf,
+                             uint32_t* eax, uint32_t* ebx, uint32_t* ecx, uint32_t* edx)
+{
+    uint32_t a, b, c, d;
+    __asm__ volatile ("cpuid"
+                      : "=a"(a), "=b"(b), "=c"(c), "=d"(d)
+                      : "a"(leaf), "c"(subleaf));
+    if (eax) *eax = a;
+    if (ebx) *ebx = b;
+    if (ecx) *ecx = c;
+    if (edx) *edx = d;
+}
+
+static void rng_probe(void)
+{
+    if (rng_probed) {
+        return;
+    }
+    uint32_t max_basic = 0;
+    rng_cpuid(0, 0, &max_basic, NULL, NULL, NULL);
+    if (max_basic >= 1) {
+        uint32_t ecx = 0;
+        rng_cpuid(1, 0, NULL, NULL, &ecx, NULL);
+        rng_rdrand = (ecx & CPUID1_ECX_RDRAND) != 0;
+    }
+    rng_probed = true;
+}
+
+bool random_hw_available(void)
+{
+    rng_probe();
+    return rng_rdrand;
+}
+
+bool random_hw_u64(uint64_t* out)
+{
+    if (!out || !random_hw_available()) {
+        return false;
+    }
+    for (uint32_t i = 0; i < RDRAND_RETRIES; i++) {
+        uint64_t v;
+        uint8_t ok;
+        __asm__ volatile ("rdrand %0; setc %1" : "=r"(v), "=qm"(ok) : : "cc");
+        if (ok) {
+            *out = v;
+            return true;
+        }
+    }
+    return false;
+}
+
+uint64_t random_hw_cycles(void)
+{
+    return clocksource_rdtsc();
+}
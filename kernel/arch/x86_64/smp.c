@@ -0,0 +1,284 @@
+/**
+ * @file smp.c
+ * @brief x86_64 application processor bring-up (INIT-SIPI-SIPI)
+ *
+ * Bring-up is strictly sequential: one AP at a time uses the trampoline page
+ * and its mailbox, the BSP waits until the AP reports itself online before
+ * starting the next one. Logical CPU indices are assigned in MADT order and
+ * stay contiguous; bring-up stops at the first AP that does not respond.
+ */
+
+#include "smp.h"
+#include "apic.h"
+#include "acpi.h"
+#include "config.h"
+#include "paging.h"
+#include "pmm.h"
+#include "pit.h"
+#include "percpu.h"
+#include "lapic_access.h"
+#include "../../core/cpu.h"
+#include "../../common/heap.h"
+#include "../../common/scheduler.h"
+#include "../../../include/common.h"
+#include "../../../include/console.h"
+#include "../../../include/error.h"
+#include <stddef.h>
+
+#define SMP_AP_STACK_SIZE     16384u
+#define SMP_INIT_DELAY_US     10000u
+#define SMP_SIPI_DELAY_US     200u
+#define SMP_ONLINE_TIMEOUT_US 100000u
+#define SMP_POLL_STEP_US      100u
+#define SMP_PTE_ADDR_MASK     0x000FFFFFFFFFF000ULL
+
+/* Layout shared with ap_trampoline.S (ap_trampoline_mailbox) */
+typedef struct smp_ap_mailbox {
+    uint32_t cr3;
+    uint32_t reserved;
+    uint64_t stack;
+    uint64_t entry;
+    uint32_t cpu;
+    uint32_t apic_id;
+} __attribute__((packed)) smp_ap_mailbox_t;
+
+_Static_assert(offsetof(smp_ap_mailbox_t, stack) == 8, "smp mailbox stack offset");
+_Static_assert(offsetof(smp_ap_mailbox_t, entry) == 16, "smp mailbox entry offset");
+_Static_assert(offsetof(smp_ap_mailbox_t, cpu) == 24, "smp mailbox cpu offset");
+_Static_assert(offsetof(smp_ap_mailbox_t, apic_id) == 28, "smp mailbox apic offset");
+
+extern const uint8_t ap_trampoline_start[];
+extern const uint8_t ap_trampoline_end[];
+extern const uint8_t ap_trampoline_mailbox[];
+
+struct smp_madt_scan {
+    uint32_t bsp_apic_id;
+    uint32_t count;
+    uint32_t apic_ids[CPU_MAX_COUNT];
+};
+
+static uint32_t smp_apic_ids[CPU_MAX_COUNT];
+static volatile uint32_t smp_online_count = 1;
+static uint64_t smp_kernel_pml4 = 0;
+
+static inline uint64_t smp_read_cr3(void)
+{
+    uint64_t cr3;
+    __asm__ volatile ("mov %%cr3, %0" : "=r"(cr3));
+    return cr3;
+}
+
+static inline void smp_write_cr3(uint64_t cr3)
+{
+    __asm__ volatile ("mov %0, %%cr3" : : "r"(cr3) : "memory");
+}
+
+static void smp_scan_add(struct smp_madt_scan* scan, uint32_t apic_id)
+{
+    if (apic_id == scan->bsp_apic_id) {
+        return;
+    }
+    for (uint32_t i = 0; i < scan->count; i++) {
+        if (scan->apic_ids[i] == apic_id) {
+            return;
+        }
+    }
+    /* Slot 0 belongs to the BSP */
+    if (scan->count + 1u >= CPU_MAX_COUNT) {
+        return;
+    }
+    scan->apic_ids[scan->count++] = apic_id;
+}
+
+static int smp_madt_cpu(const struct acpi_madt_entry_header* entry, void* ctx)
+{
+    struct smp_madt_scan* scan = (struct smp_madt_scan*)ctx;
+
+    if (entry->type == ACPI_MADT_TYPE_LAPIC &&
+        entry->length >= sizeof(struct acpi_madt_lapic)) {
+        const struct acpi_madt_lapic* lapic = (const struct acpi_madt_lapic*)entry;
+        if (lapic->flags & ACPI_MADT_LAPIC_ENABLED) {
+            smp_scan_add(scan, lapic->apic_id);
+        }
+    } else if (entry->type == ACPI_MADT_TYPE_X2APIC &&
+               entry->length >= sizeof(struct acpi_madt_x2apic)) {
+        const struct acpi_madt_x2apic* x2 = (const struct acpi_madt_x2apic*)entry;
+        /* IDs above 255 are only addressable in x2APIC mode */
+        if ((x2->flags & ACPI_MADT_LAPIC_ENABLED) &&
+            (x2->x2apic_id <= 0xFFu || lapic_access_mode() == LAPIC_MODE_X2APIC)) {
+            smp_scan_add(scan, x2->x2apic_id);
+        }
+    }
+    return 0;
+}
+
+/* Free the temporary trampoline PML4 and the tables that map its page */
+static void smp_free_trampoline_pml4(uint64_t pml4_phys)
+{
+    uint64_t* pml4 = (uint64_t*)X86_64_PHYS_TO_VIRT(pml4_phys);
+    uint64_t pdpt_phys = pml4[0] & SMP_PTE_ADDR_MASK;
+    if (pml4[0] & PTE_PRESENT) {
+        uint64_t* pdpt = (uint64_t*)X86_64_PHYS_TO_VIRT(pdpt_phys);
+        uint64_t pd_phys = pdpt[0] & SMP_PTE_ADDR_MASK;
+        if (pdpt[0] & PTE_PRESENT) {
+            uint64_t* pd = (uint64_t*)X86_64_PHYS_TO_VIRT(pd_phys);
+            if (pd[0] & PTE_PRESENT) {
+                pmm_free_page(pd[0] & SMP_PTE_ADDR_MASK);
+            }
+            pmm_free_page(pd_phys);
+        }
+        pmm_free_page(pdpt_phys);
+    }
+    pmm_free_page(pml4_phys);
+}
+
+static bool smp_wait_online(uint32_t cpu, uint32_t timeout_us)
+{
+    x86_percpu_t* pc = percpu_get(cpu);
+    for (uint32_t waited = 0; waited < timeout_us; waited += SMP_POLL_STEP_US) {
+        if (pc->online) {
+            return true;
+        }
+        pit_delay_us(SMP_POLL_STEP_US);
+    }
+    return pc->online != 0;
+}
+
+static bool smp_start_ap(volatile smp_ap_mailbox_t* mb, uint32_t cpu, uint32_t apic_id)
+{
+    void* stack = kmalloc(SMP_AP_STACK_SIZE);
+    if (!stack) {
+        kprintf("[SMP] cpu%u: no memory for boot stack\n", cpu);
+        return false;
+    }
+
+    mb->stack = (uint64_t)(uintptr_t)stack + SMP_AP_STACK_SIZE;
+    mb->entry = (uint64_t)(uintptr_t)&smp_ap_entry;
+    mb->cpu = cpu;
+    mb->apic_id = apic_id;
+    __asm__ volatile ("" ::: "memory");
+
+    apic_send_init_ipi(apic_id);
+    pit_delay_us(SMP_INIT_DELAY_US);
+    apic_send_startup_ipi(apic_id, (uint8_t)(SMP_AP_TRAMPOLINE_PHYS >> 12));
+    pit_delay_us(SMP_SIPI_DELAY_US);
+    if (!percpu_get(cpu)->online) {
+        apic_send_startup_ipi(apic_id, (uint8_t)(SMP_AP_TRAMPOLINE_PHYS >> 12));
+    }
+
+    /*
+     * On timeout the stack is deliberately leaked: the AP may still be
+     * running on it somewhere in the trampoline.
+     */
+    return smp_wait_online(cpu, SMP_ONLINE_TIMEOUT_US);
+}
+
+int smp_init(void)
+{
+    x86_percpu_t* bsp = percpu_self();
+    smp_apic_ids[0] = bsp->apic_id;
+    for (uint32_t i = 1; i < CPU_MAX_COUNT; i++) {
+        smp_apic_ids[i] = 0xFFFFFFFFu;
+    }
+    bsp->online = 1;
+
+    if (!apic_is_available() || !ioapic_is_available()) {
+        kputs("[SMP] LAPIC/IOAPIC unavailable, running on BSP only\n");
+        return RDNX_OK;
+    }
+
+    struct smp_madt_scan scan;
+    memset(&scan, 0, sizeof(scan));
+    scan.bsp_apic_id = bsp->apic_id;
+    if (acpi_madt_foreach(smp_madt_cpu, &scan) != 0 || scan.count == 0) {
+        kputs("[SMP] no application processors\n");
+        return RDNX_OK;
+    }
+
+    size_t tramp_size = (size_t)(ap_trampoline_end - ap_trampoline_start);
+    if (tramp_size > X86_64_PAGE_SIZE_4KB) {
+        kprintf("[SMP] trampoline too large (%u bytes)\n", (unsigned)tramp_size);
+        return RDNX_OK;
+    }
+
+    uint64_t pml4 = paging_create_user_pml4();
+    if (!pml4 || paging_map_page_4kb_pml4(pml4, SMP_AP_TRAMPOLINE_PHYS,
+                                          SMP_AP_TRAMPOLINE_PHYS,
+                                          PTE_PRESENT | PTE_RW) != 0) {
+        kputs("[SMP] failed to build trampoline page tables\n");
+        if (pml4) {
+            smp_free_trampoline_pml4(pml4);
+        }
+        return RDNX_OK;
+    }
+
+    /* The trampoline page is borrowed from low memory: keep its contents */
+    static uint8_t saved_page[X86_64_PAGE_SIZE_4KB];
+    uint8_t* tramp = (uint8_t*)X86_64_PHYS_TO_VIRT(SMP_AP_TRAMPOLINE_PHYS);
+    memcpy(saved_page, tramp, X86_64_PAGE_SIZE_4KB);
+    memcpy(tramp, ap_trampoline_start, tramp_size);
+
+    volatile smp_ap_mailbox_t* mb = (volatile smp_ap_mailbox_t*)
+        (tramp + (ap_trampoline_mailbox - ap_trampoline_start));
+    smp_kernel_pml4 = smp_read_cr3() & ~0xFFFULL;
+    mb->cr3 = (uint32_t)pml4;
+
+    kprintf("[SMP] %u application processor(s) in MADT, lapic=%s\n",
+            scan.count, lapic_access_mode_name());
+
+    uint32_t online = 1;
+    for (uint32_t i = 0; i < scan.count; i++) {
+        uint32_t cpu = online;
+        uint32_t apic_id = scan.apic_ids[i];
+        smp_apic_ids[cpu] = apic_id;
+        if (!smp_start_ap(mb, cpu, apic_id)) {
+            kprintf("[SMP] cpu%u apic=%u did not start, stopping bring-up\n",
+                    cpu, apic_id);
+            smp_apic_ids[cpu] = 0xFFFFFFFFu;
+            break;
+        }
+        kprintf("[SMP] cpu%u apic=%u online\n", cpu, apic_id);
+        online++;
+        smp_online_count = online;
+    }
+
+    memcpy(tramp, saved_page, X86_64_PAGE_SIZE_4KB);
+    smp_free_trampoline_pml4(pml4);
+    x86_64_cpu_set_count(online);
+    kprintf("[SMP] %u CPU(s) online\n", online);
+    return RDNX_OK;
+}
+
+uint32_t smp_cpus_online(void)
+{
+    return smp_online_count;
+}
+
+uint32_t smp_cpu_apic_id(uint32_t cpu)
+{
+    if (cpu >= CPU_MAX_COUNT) {
+        return 0xFFFFFFFFu;
+    }
+    return smp_apic_ids[cpu];
+}
+
+void smp_ap_entry(uint32_t cpu)
+{
+    /*
+     * Leave the trampoline PML4: the BSP frees it once all APs are up.
+     * Raw CR3 write, GS base (per-CPU block) is not set up yet.
+     */
+    smp_write_cr3(smp_kernel_pml4);
+
+    cpu_init_ap(cpu, smp_apic_ids[cpu]);
+    (void)apic_init_ap();
+
+    __asm__ volatile ("" ::: "memory");
+    percpu_self()->online = 1;
+
+    apic_timer_start_ap();
+    scheduler_start_ap();
+    for (;;) {
+        cpu_idle();
+    }
+}
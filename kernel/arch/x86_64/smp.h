@@ -0,0 +1,48 @@
+/**
+ * @file smp.h
+ * @brief x86_64 application processor bring-up
+ *
+ * The BSP enumerates processors from the ACPI MADT and starts every enabled
+ * AP with INIT-SIPI-SIPI through a real-mode trampoline (ap_trampoline.S).
+ * Each AP gets its own GDT/TSS, per-CPU block, LAPIC and LAPIC timer, and
+ * then joins the scheduler on its pinned idle thread.
+ */
+
+#ifndef _RODNIX_ARCH_X86_64_SMP_H
+#define _RODNIX_ARCH_X86_64_SMP_H
+
+#include <stdint.h>
+#include <stdbool.h>
+
+/* Physical page the trampoline is copied to (SIPI vector 0x08) */
+#define SMP_AP_TRAMPOLINE_PHYS 0x8000ULL
+
+/**
+ * Start all application processors listed in the MADT.
+ * Requires LAPIC + IOAPIC; otherwise the system stays single-CPU.
+ * @return RDNX_OK (also when no AP could be started)
+ */
+int smp_init(void);
+
+/**
+ * Number of CPUs that finished arch bring-up (BSP included).
+ */
+uint32_t smp_cpus_online(void);
+
+/**
+ * LAPIC ID of a logical CPU.
+ * @return LAPIC ID or 0xFFFFFFFF if @p cpu is unknown
+ */
+uint32_t smp_cpu_apic_id(uint32_t cpu);
+
+/**
+ * C entry of an AP, jumped to from the trampoline on the AP boot stack.
+ * @param cpu Logical CPU index assigned by smp_init()
+ */
+void smp_ap_entry(uint32_t cpu) __attribute__((noreturn));
+
+/* Arch per-CPU bring-up (cpu.c) */
+void cpu_init_ap(uint32_t cpu, uint32_t apic_id);
+void x86_64_cpu_set_count(uint32_t count);
+
+#endif /* _RODNIX_ARCH_X86_64_SMP_H */
@@ -2,9 +2,9 @@
 #include "interrupt_frame.h"
 #include "../../common/syscall.h"
 #include "../../core/task.h"
+#include "percpu.h"
 
 extern void x86_64_syscall_fast_entry(void);
-uint64_t g_syscall_user_rsp_shadow = 0;
 
 enum {
     X86_MSR_EFER = 0xC0000080,
@@ -102,5 +102,11 @@ uint64_t x86_64_syscall_dispatch_frame(interrupt_frame_t* frame, int fast_entry)
 
 uint64_t x86_64_syscall_fast_dispatch_frame(interrupt_frame_t* frame)
 {
-    return x86_64_syscall_dispatch_frame(frame, 1);
+    /* SYSCALL path bypasses interrupt_dispatch: do the IRQL entry/exit here. */
+    irql_t level = interrupt_enter_irql(IRQL_APC);
+    uint64_t ret = x86_64_syscall_dispatch_frame(frame, 1);
+    __asm__ volatile ("cli" ::: "memory");
+    interrupt_leave_irql(level);
+    interrupt_return_to_user();
+    return ret;
 }
@@ -0,0 +1,85 @@
+/**
+ * @file uaccess.c
+ * @brief x86_64 user memory copies that survive a bad user pointer
+ *
+ * The primitives run inside the SMAP window opened by the caller and keep
+ * nothing on the stack while they touch user memory. A page fault that
+ * vm_fault_handle() cannot resolve with RIP inside them is redirected to
+ * a common fixup that returns RDNX_E_INVALID, so a syscall handed an
+ * unmapped (but canonical, user-range) pointer fails instead of panicking.
+ */
+
+#include "../../core/cpu.h"
+#include "../../../include/error.h"
+
+#define UACCESS_STR_(x) #x
+#define UACCESS_STR(x) UACCESS_STR_(x)
+
+extern const char uaccess_text_start[];
+extern const char uaccess_text_end[];
+extern const char uaccess_fault[];
+
+__asm__(
+    ".text\n"
+    ".globl uaccess_text_start\n"
+    "uaccess_text_start:\n"
+
+    /* int cpu_user_copy(void* dst, const void* src, size_t len) */
+    ".globl cpu_user_copy\n"
+    ".type cpu_user_copy, @function\n"
+    "cpu_user_copy:\n"
+    "    mov %rdx, %rcx\n"
+    "    xor %eax, %eax\n"
+    "    rep movsb\n"
+    "    ret\n"
+    ".size cpu_user_copy, . - cpu_user_copy\n"
+
+    /* int cpu_user_clear(void* dst, size_t len) */
+    ".globl cpu_user_clear\n"
+    ".type cpu_user_clear, @function\n"
+    "cpu_user_clear:\n"
+    "    mov %rsi, %rcx\n"
+    "    xor %eax, %eax\n"
+    "    rep stosb\n"
+    "    ret\n"
+    ".size cpu_user_clear, . - cpu_user_clear\n"
+
+    /* int cpu_user_copy_str(char* dst, const char* src, size_t len) */
+    ".globl cpu_user_copy_str\n"
+    ".type cpu_user_copy_str, @function\n"
+    "cpu_user_copy_str:\n"
+    "    mov $" UACCESS_STR(RDNX_E_INVALID) ", %eax\n"
+    "    test %rdx, %rdx\n"
+    "    jz 2f\n"
+    "1:  movb (%rsi), %cl\n"
+    "    movb %cl, (%rdi)\n"
+    "    inc %rsi\n"
+    "    inc %rdi\n"
+    "    test %cl, %cl\n"
+    "    jz 3f\n"
+    "    dec %rdx\n"
+    "    jnz 1b\n"
+    "2:  ret\n"
+    "3:  xor %eax, %eax\n"
+    "    ret\n"
+    ".size cpu_user_copy_str, . - cpu_user_copy_str\n"
+
+    /* Fixup: the faulting primitive returns the error to its caller */
+    ".globl uaccess_fault\n"
+    "uaccess_fault:\n"
+    "    mov $" UACCESS_STR(RDNX_E_INVALID) ", %eax\n"
+    "    ret\n"
+
+    ".globl uaccess_text_end\n"
+    "uaccess_text_end:\n"
+);
+
+bool cpu_user_fault_fixup(uint64_t* rip)
+{
+    if (!rip || *rip < (uint64_t)(uintptr_t)uaccess_text_start ||
+        *rip >= (uint64_t)(uintptr_t)uaccess_text_end) {
+        return false;
+    }
+    *rip = (uint64_t)(uintptr_t)uaccess_fault;
+    return true;
+}
@@ -7,6 +7,7 @@
 #include "paging.h"
 #include "pmm.h"
 #include "gdt.h"
+#include "percpu.h"
 #include "types.h"
 #include "config.h"
 #include "../../common/bootlog.h"
@@ -49,8 +50,8 @@ int usermode_prepare_stub(void** entry, void** user_stack, uint64_t* rsp0_out)
         return RDNX_E_NOMEM;
     }
 
-    uint64_t code_phys = pmm_alloc_page_in_zone(PMM_ZONE_LOW);
-    uint64_t stack_phys = pmm_alloc_page_in_zone(PMM_ZONE_LOW);
+    uint64_t code_phys = pmm_alloc_page_in_zone(PMM_ZONE_DMA);
+    uint64_t stack_phys = pmm_alloc_page_in_zone(PMM_ZONE_DMA);
     if (!code_phys || !stack_phys) {
         if (bootlog_is_verbose()) {
             kputs("[USERMODE] alloc pages failed\n");
@@ -64,7 +65,8 @@ int usermode_prepare_stub(void** entry, void** user_stack, uint64_t* rsp0_out)
         }
         return RDNX_E_GENERIC;
     }
-    if (paging_map_page_4kb_pml4(user_pml4_phys, USER_STACK_VA, stack_phys, PTE_PRESENT | PTE_RW | PTE_USER) != 0) {
+    if (paging_map_page_4kb_pml4(user_pml4_phys, USER_STACK_VA, stack_phys,
+                                 PTE_PRESENT | PTE_RW | PTE_USER | PTE_NX) != 0) {
         if (bootlog_is_verbose()) {
             kputs("[USERMODE] map stack failed\n");
         }
@@ -101,12 +103,20 @@ void usermode_enter(void* entry, void* user_stack, uint64_t rsp0, uint64_t arg0,
     uint64_t user_cs = GDT_USER_CS | 0x3;
     uint64_t user_ds = GDT_USER_DS | 0x3;
 
+    /* A new image starts with clean x87/SSE/AVX state (also on execve). */
+    cpu_fpu_reset();
+
+    /* May be reached from a syscall (execve): user mode always runs at PASSIVE. */
+    __asm__ volatile ("cli" ::: "memory");
+    interrupt_leave_irql(IRQL_PASSIVE);
+
     __asm__ volatile (
         "cli\n\t"
         "movw %w0, %%ax\n\t"
         "mov %%ax, %%ds\n\t"
         "mov %%ax, %%es\n\t"
         "mov %%ax, %%fs\n\t"
+        "swapgs\n\t"
         "mov %%ax, %%gs\n\t"
         "mov %4, %%rdi\n\t"
         "mov %5, %%rsi\n\t"
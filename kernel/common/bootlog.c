@@ -8,7 +8,7 @@
  */
 
 #include "bootlog.h"
-#include "../core/boot.h"
+#include "bootparam.h"
 #include "../core/cpu.h"
 #include "scheduler.h"
 #include "../../include/common.h"
@@ -66,13 +66,8 @@ static const bootlog_name_id_t bootlog_event_ids[] = {
     {"create_enter", 16},
 };
 
-static bool bootlog_has_flag(const char* cmdline, const char* needle)
-{
-    if (!cmdline || !needle) {
-        return false;
-    }
-    return strstr(cmdline, needle) != NULL;
-}
+BOOTPARAM_STRING(bootlog_mode, "bootlog", "", "Boot phase log: quiet or verbose");
+BOOTPARAM_STRING(bootlog_startup_debug, "startup_debug", "", "Legacy bootlog switch: 0, 1 or verbose");
 
 static uint16_t bootlog_lookup_id(const bootlog_name_id_t* map, size_t map_len, const char* name)
 {
@@ -154,21 +149,9 @@ static void bootlog_compose_event(char* out, size_t out_len,
 
 void bootlog_init(void)
 {
-    boot_info_t* info = boot_get_info();
-    bootlog_verbose = false;
-
-    if (info) {
-        const char* cmdline = info->cmdline;
-        if (bootlog_has_flag(cmdline, "startup_debug=0") ||
-            bootlog_has_flag(cmdline, "bootlog=quiet")) {
-            bootlog_verbose = false;
-        }
-        if (bootlog_has_flag(cmdline, "startup_debug=1") ||
-            bootlog_has_flag(cmdline, "startup_debug=verbose") ||
-            bootlog_has_flag(cmdline, "bootlog=verbose")) {
-            bootlog_verbose = true;
-        }
-    }
+    bootlog_verbose = strcmp(bootlog_mode, "verbose") == 0 ||
+                      strcmp(bootlog_startup_debug, "1") == 0 ||
+                      strcmp(bootlog_startup_debug, "verbose") == 0;
 
     bootlog_initialized = true;
     bootlog_mark("startup", "bootlog_init");
@@ -0,0 +1,249 @@
+/**
+ * @file bootparam.c
+ * @brief Typed kernel command-line parameters
+ */
+
+#include "bootparam.h"
+#include "../../include/console.h"
+#include "../../include/common.h"
+#include "../../include/error.h"
+
+/* Linker set bounds (link.ld) */
+extern bootparam_t __bootparam_start[];
+extern bootparam_t __bootparam_end[];
+
+#define BOOTPARAM_TOKEN_MAX 128
+
+uint32_t bootparam_count(void)
+{
+    return (uint32_t)(__bootparam_end - __bootparam_start);
+}
+
+const bootparam_t* bootparam_get(uint32_t index)
+{
+    if (index >= bootparam_count()) {
+        return NULL;
+    }
+    return &__bootparam_start[index];
+}
+
+static bootparam_t* bootparam_find(const char* name, size_t len)
+{
+    for (bootparam_t* p = __bootparam_start; p < __bootparam_end; p++) {
+        if (strlen(p->name) == len && strncmp(p->name, name, len) == 0) {
+            return p;
+        }
+        if (p->alias && strlen(p->alias) == len && strncmp(p->alias, name, len) == 0) {
+            return p;
+        }
+    }
+    return NULL;
+}
+
+static bool bootparam_parse_bool(const char* s, bool* out)
+{
+    if (!s || strcmp(s, "1") == 0 || strcmp(s, "yes") == 0 ||
+        strcmp(s, "on") == 0 || strcmp(s, "true") == 0) {
+        *out = true;
+        return true;
+    }
+    if (strcmp(s, "0") == 0 || strcmp(s, "no") == 0 ||
+        strcmp(s, "off") == 0 || strcmp(s, "false") == 0) {
+        *out = false;
+        return true;
+    }
+    return false;
+}
+
+static bool bootparam_parse_int(const char* s, int64_t* out)
+{
+    bool neg = false;
+    uint64_t base = 10;
+    uint64_t v = 0;
+
+    if (!s) {
+        return false;
+    }
+    if (*s == '-') {
+        neg = true;
+        s++;
+    }
+    if (s[0] == '0' && (s[1] == 'x' || s[1] == 'X')) {
+        base = 16;
+        s += 2;
+    }
+    if (!*s) {
+        return false;
+    }
+    for (; *s; s++) {
+        uint64_t d;
+        if (*s >= '0' && *s <= '9') {
+            d = (uint64_t)(*s - '0');
+        } else if (base == 16 && *s >= 'a' && *s <= 'f') {
+            d = (uint64_t)(*s - 'a' + 10);
+        } else if (base == 16 && *s >= 'A' && *s <= 'F') {
+            d = (uint64_t)(*s - 'A' + 10);
+        } else {
+            return false;
+        }
+        if (v > (UINT64_MAX - d) / base) {
+            return false;
+        }
+        v = v * base + d;
+    }
+    if (v > (uint64_t)INT64_MAX) {
+        return false;
+    }
+    *out = neg ? -(int64_t)v : (int64_t)v;
+    return true;
+}
+
+/* @param val Text after '=', or NULL for a bare name */
+static void bootparam_apply(bootparam_t* p, const char* val)
+{
+    switch (p->type) {
+        case BOOTPARAM_T_BOOL: {
+            bool b;
+            if (!bootparam_parse_bool(val, &b)) {
+                kprintf("[BOOTPARAM] %s: expected a boolean, got '%s'\n", p->name, val);
+                return;
+            }
+            *(bool*)p->value = b;
+            break;
+        }
+        case BOOTPARAM_T_INT: {
+            int64_t v;
+            if (!bootparam_parse_int(val, &v)) {
+                kprintf("[BOOTPARAM] %s: expected a number\n", p->name);
+                return;
+            }
+            if (v < p->min || v > p->max) {
+                kprintf("[BOOTPARAM] %s: %s out of range, keeping %s\n", p->name, val, p->defval);
+                return;
+            }
+            *(int64_t*)p->value = v;
+            break;
+        }
+        case BOOTPARAM_T_STRING: {
+            char* dst = (char*)p->value;
+            size_t len = val ? strlen(val) : 0;
+            if (len >= p->size) {
+                kprintf("[BOOTPARAM] %s: value truncated\n", p->name);
+                len = p->size - 1;
+            }
+            memcpy(dst, val ? val : "", len);
+            dst[len] = '\0';
+            break;
+        }
+        default:
+            return;
+    }
+    p->flags |= BOOTPARAM_F_SET;
+}
+
+void bootparam_init(const char* cmdline)
+{
+    char token[BOOTPARAM_TOKEN_MAX];
+    const char* p = cmdline;
+
+    if (!p) {
+        return;
+    }
+    while (*p) {
+        while (*p == ' ') {
+            p++;
+        }
+        if (*p == '\0') {
+            break;
+        }
+        const char* start = p;
+        while (*p && *p != ' ') {
+            p++;
+        }
+        size_t len = (size_t)(p - start);
+        if (len >= sizeof(token)) {
+            kputs("[BOOTPARAM] overlong token ignored\n");
+            continue;
+        }
+        memcpy(token, start, len);
+        token[len] = '\0';
+
+        char* eq = strchr(token, '=');
+        size_t name_len = eq ? (size_t)(eq - token) : len;
+        bootparam_t* param = bootparam_find(token, name_len);
+        if (!param) {
+            if (strncmp(token, "rdnx.", 5) == 0) {
+                kprintf("[BOOTPARAM] unknown parameter %s\n", token);
+            }
+            continue;
+        }
+        if (!eq && param->type != BOOTPARAM_T_BOOL) {
+            kprintf("[BOOTPARAM] %s needs a value\n", param->name);
+            continue;
+        }
+        bootparam_apply(param, eq ? eq + 1 : NULL);
+    }
+}
+
+int bootparam_format(const bootparam_t* p, char* out, size_t out_len)
+{
+    if (!p || !out || out_len < 2) {
+        return RDNX_E_INVALID;
+    }
+    switch (p->type) {
+        case BOOTPARAM_T_BOOL:
+            out[0] = *(const bool*)p->value ? '1' : '0';
+            out[1] = '\0';
+            return RDNX_OK;
+        case BOOTPARAM_T_INT: {
+            char tmp[24];
+            int64_t v = *(const int64_t*)p->value;
+            uint64_t u = v < 0 ? (uint64_t)(-(v + 1)) + 1u : (uint64_t)v;
+            size_t n = 0;
+            do {
+                tmp[n++] = (char)('0' + (u % 10u));
+                u /= 10u;
+            } while (u);
+            if (v < 0) {
+                tmp[n++] = '-';
+            }
+            if (n >= out_len) {
+                return RDNX_E_INVALID;
+            }
+            for (size_t i = 0; i < n; i++) {
+                out[i] = tmp[n - 1 - i];
+            }
+            out[n] = '\0';
+            return RDNX_OK;
+        }
+        case BOOTPARAM_T_STRING:
+            strncpy(out, (const char*)p->value, out_len - 1);
+            out[out_len - 1] = '\0';
+            return RDNX_OK;
+        default:
+            return RDNX_E_INVALID;
+    }
+}
+
+bool bootparam_list_has(const char* list, const char* item)
+{
+    size_t item_len = item ? strlen(item) : 0;
+    const char* p = list;
+
+    if (!p || item_len == 0) {
+        return false;
+    }
+    while (*p) {
+        const char* start = p;
+        while (*p && *p != ',') {
+            p++;
+        }
+        if ((size_t)(p - start) == item_len && strncmp(start, item, item_len) == 0) {
+            return true;
+        }
+        if (*p == ',') {
+            p++;
+        }
+    }
+    return false;
+}
@@ -0,0 +1,100 @@
+/**
+ * @file bootparam.h
+ * @brief Typed kernel command-line parameters
+ *
+ * A subsystem declares its parameters next to the code that uses them:
+ *
+ *     BOOTPARAM_BOOL(smp_enabled, "rdnx.smp", true, "Start application processors");
+ *
+ * which defines a static variable holding the default. Descriptors are
+ * collected in the .bootparam linker section; bootparam_init() parses the
+ * Multiboot2 command line once, early in kmain, and stores the values.
+ * Code reads the variable directly afterwards.
+ *
+ * Syntax: "name=value" or a bare "name" (bool true). Bools accept
+ * 1/0, yes/no, on/off, true/false. Ints are decimal or 0x-hex and are
+ * range-checked. Unknown "rdnx.*" names are reported, anything else
+ * (BOOT_IMAGE=..., loader options) is ignored.
+ */
+
+#ifndef _RODNIX_COMMON_BOOTPARAM_H
+#define _RODNIX_COMMON_BOOTPARAM_H
+
+#include <stdint.h>
+#include <stdbool.h>
+#include <stddef.h>
+
+typedef enum {
+    BOOTPARAM_T_BOOL = 1,
+    BOOTPARAM_T_INT = 2,
+    BOOTPARAM_T_STRING = 3
+} bootparam_type_t;
+
+/* bootparam_t.flags */
+#define BOOTPARAM_F_SET 0x1u    /* Given on the command line */
+
+typedef struct bootparam {
+    const char* name;
+    const char* alias;          /* Older spelling, or NULL */
+    const char* desc;
+    const char* defval;         /* Default as text, for listings */
+    void* value;                /* bool*, int64_t* or char[size] */
+    uint32_t size;
+    uint32_t type;
+    int64_t min;
+    int64_t max;
+    uint32_t flags;
+} bootparam_t;
+
+#define BOOTPARAM_STR_MAX 64
+
+#define BOOTPARAM_STR_(x)  #x
+#define BOOTPARAM_XSTR_(x) BOOTPARAM_STR_(x)
+
+#define BOOTPARAM_ENTRY_(var, name_, alias_, type_, size_, min_, max_, def_, desc_) \
+    static bootparam_t bootparam_##var                                          \
+        __attribute__((used, section(".bootparam"), aligned(8))) = {            \
+        .name = (name_), .alias = (alias_), .desc = (desc_), .defval = (def_),  \
+        .value = &(var), .size = (size_), .type = (type_),                      \
+        .min = (min_), .max = (max_), .flags = 0                                \
+    }
+
+#define BOOTPARAM_BOOL_ALIAS(var, name, alias, def, desc)                       \
+    static bool var = (def);                                                    \
+    BOOTPARAM_ENTRY_(var, name, alias, BOOTPARAM_T_BOOL, sizeof(bool), 0, 1,    \
+                     (def) ? "1" : "0", desc)
+
+#define BOOTPARAM_BOOL(var, name, def, desc)                                    \
+    BOOTPARAM_BOOL_ALIAS(var, name, NULL, def, desc)
+
+#define BOOTPARAM_INT(var, name, def, min, max, desc)                           \
+    static int64_t var = (def);                                                 \
+    BOOTPARAM_ENTRY_(var, name, NULL, BOOTPARAM_T_INT, sizeof(int64_t), min, max, \
+                     BOOTPARAM_XSTR_(def), desc)
+
+#define BOOTPARAM_STRING(var, name, def, desc)                                  \
+    static char var[BOOTPARAM_STR_MAX] = def;                                   \
+    BOOTPARAM_ENTRY_(var, name, NULL, BOOTPARAM_T_STRING, BOOTPARAM_STR_MAX, 0, 0, def, desc)
+
+/**
+ * Parse the command line into all declared parameters. Called once,
+ * before anything reads them; until then the variables hold defaults.
+ */
+void bootparam_init(const char* cmdline);
+
+/* Number of declared parameters */
+uint32_t bootparam_count(void);
+
+/* Descriptor by index (0..count-1), NULL past the end */
+const bootparam_t* bootparam_get(uint32_t index);
+
+/**
+ * Current value as text ("1"/"0", decimal, or the string).
+ * @return RDNX_OK, RDNX_E_INVALID on a bad buffer
+ */
+int bootparam_format(const bootparam_t* p, char* out, size_t out_len);
+
+/* True if item appears in a comma-separated list (e.g. rdnx.nodrv) */
+bool bootparam_list_has(const char* list, const char* item);
+
+#endif /* _RODNIX_COMMON_BOOTPARAM_H */
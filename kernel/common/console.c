@@ -6,6 +6,14 @@
 #include "../../include/console.h"
 #include "startup_trace.h"
 #include "bootlog.h"
+#include "fbcon.h"
+#include "ddb.h"
+#include "gdbstub.h"
+#include "../core/clock.h"
+#include "../core/interrupts.h"
+#include "../arch/apic.h"
+#include "../arch/pic.h"
+#include "../../include/error.h"
 #include <stdarg.h>
 
 /* Simple VGA text mode implementation */
@@ -20,9 +28,27 @@
 #define SERIAL_LCR       0x3
 #define SERIAL_MCR       0x4
 #define SERIAL_LSR       0x5
+#define SERIAL_IRQ       4
+#define SERIAL_COM2_BASE 0x2F8
+#define SERIAL2_IRQ      3
+#define SERIAL_RX_RING   256
 
 static bool serial_enabled = false;
 
+/*
+ * LOCKING: lock-free
+ *   serial_rx_head is advanced only by the COM1 IRQ handler; readers claim
+ *   bytes with a CAS on serial_rx_tail, so any CPU may read.
+ */
+static volatile uint8_t serial_rx_ring[SERIAL_RX_RING];
+static volatile uint32_t serial_rx_head = 0;
+static volatile uint32_t serial_rx_tail = 0;
+static volatile bool serial_rx_irq = false;
+static uint8_t serial_break_state = 0;
+
+/* COM2 belongs to the GDB stub: no console mirroring, no ring */
+static bool serial2_enabled = false;
+
 /* VGA cursor control ports */
 #define VGA_CRTC_INDEX  0x3D4
 #define VGA_CRTC_DATA   0x3D5
@@ -30,8 +56,15 @@ static bool serial_enabled = false;
 #define VGA_CURSOR_HIGH 0x0E
 
 static uint16_t* vga_buffer = (uint16_t*)VGA_MEMORY;
-static uint8_t vga_row = 0;
-static uint8_t vga_col = 0;
+/*
+ * Screen state shared by both backends. The framebuffer console renders
+ * the same char + VGA attribute cells; VGA text memory is the fallback.
+ */
+static bool fb_console = false;
+static uint16_t con_cols = VGA_WIDTH;
+static uint16_t con_rows = VGA_HEIGHT;
+static uint16_t con_row = 0;
+static uint16_t con_col = 0;
 static uint8_t vga_color = 0x0F; /* White on black */
 static volatile bool kputs_in_progress = false; /* Prevent recursive calls from exception handlers */
 static bool log_prefix_enabled = true;
@@ -44,6 +77,11 @@ typedef enum {
     ANSI_STATE_CSI
 } ansi_state_t;
 
+/* Tail of everything printed, for crash dumps (see console_log_read) */
+#define CONSOLE_LOG_SIZE 16384
+static char console_log_buf[CONSOLE_LOG_SIZE];
+static uint64_t console_log_total = 0;
+
 static ansi_state_t ansi_state = ANSI_STATE_NORMAL;
 static char ansi_csi_buf[16];
 static uint8_t ansi_csi_len = 0;
@@ -174,7 +212,8 @@ static uint64_t rtc_unix_seconds(uint32_t y, uint32_t mo, uint32_t d,
     return days * 86400ULL + (uint64_t)h * 3600ULL + (uint64_t)mi * 60ULL + (uint64_t)s;
 }
 
-static uint64_t console_get_uptime_us_internal(void)
+/* Tick-based uptime for machines without a clocksource */
+static uint64_t console_get_tick_uptime_us(void)
 {
     extern const char* kernel_timer_source_name(void);
     extern uint32_t apic_timer_get_ticks(void);
@@ -221,25 +260,55 @@ done:
     }
     return now_us;
 }
+
+static uint64_t console_get_uptime_ns_internal(void)
+{
+    if (!clocksource_available()) {
+        return console_get_tick_uptime_us() * 1000ULL;
+    }
+    uptime_source_name = clocksource_name();
+    uint64_t ns = clocksource_monotonic_ns();
+    if (ns / 1000ULL < uptime_last_us) {
+        return uptime_last_us * 1000ULL;
+    }
+    uptime_last_us = ns / 1000ULL;
+    return ns;
+}
+
+static uint64_t console_get_uptime_us_internal(void)
+{
+    return console_get_uptime_ns_internal() / 1000ULL;
+}
+
 uint64_t console_get_uptime_us(void)
 {
     return console_get_uptime_us_internal();
 }
 
+uint64_t console_get_uptime_ns(void)
+{
+    return console_get_uptime_ns_internal();
+}
+
 const char* console_get_uptime_source(void)
 {
     return uptime_source_name;
 }
 
-uint64_t console_get_realtime_us(void)
+uint64_t console_get_realtime_ns(void)
 {
     uint32_t y = 0, mo = 0, d = 0, h = 0, mi = 0, s = 0;
     if (rtc_read_datetime(&y, &mo, &d, &h, &mi, &s)) {
         uint64_t sec = rtc_unix_seconds(y, mo, d, h, mi, s);
-        uint64_t sub = console_get_uptime_us_internal() % 1000000ULL;
-        return sec * 1000000ULL + sub;
+        uint64_t sub = console_get_uptime_ns_internal() % 1000000000ULL;
+        return sec * 1000000000ULL + sub;
     }
-    return console_get_uptime_us_internal();
+    return console_get_uptime_ns_internal();
+}
+
+uint64_t console_get_realtime_us(void)
+{
+    return console_get_realtime_ns() / 1000ULL;
 }
 
 static void console_write_dec_fixed(uint64_t value, int width)
@@ -331,26 +400,37 @@ static void console_write_log_prefix(void)
     kputs("rodnix: ");
 }
 
-static void serial_init(void)
+/* 8N1, FIFO on; returns false if nothing answers at @p base */
+static bool serial_port_setup(uint16_t base, uint8_t divisor)
 {
     /* Disable interrupts */
-    outb(SERIAL_COM1_BASE + SERIAL_IER, 0x00);
+    outb(base + SERIAL_IER, 0x00);
     /* Enable DLAB */
-    outb(SERIAL_COM1_BASE + SERIAL_LCR, 0x80);
-    /* Set divisor to 3 (38400 baud) */
-    outb(SERIAL_COM1_BASE + SERIAL_DATA, 0x03);
-    outb(SERIAL_COM1_BASE + SERIAL_IER, 0x00);
+    outb(base + SERIAL_LCR, 0x80);
+    /* Divisor of the 115200 baud base clock */
+    outb(base + SERIAL_DATA, divisor);
+    outb(base + SERIAL_IER, 0x00);
     /* 8 bits, no parity, one stop bit */
-    outb(SERIAL_COM1_BASE + SERIAL_LCR, 0x03);
+    outb(base + SERIAL_LCR, 0x03);
     /* Enable FIFO, clear, 14-byte threshold */
-    outb(SERIAL_COM1_BASE + 2, 0xC7);
+    outb(base + 2, 0xC7);
     /* IRQs enabled, RTS/DSR set */
-    outb(SERIAL_COM1_BASE + SERIAL_MCR, 0x0B);
+    outb(base + SERIAL_MCR, 0x0B);
 
     /* Basic presence check: LSR should not read as 0xFF on absent port. */
-    if (inb(SERIAL_COM1_BASE + SERIAL_LSR) != 0xFF) {
-        serial_enabled = true;
-    }
+    return inb(base + SERIAL_LSR) != 0xFF;
+}
+
+static void serial_init(void)
+{
+    /* 38400 baud */
+    serial_enabled = serial_port_setup(SERIAL_COM1_BASE, 3);
+}
+
+static void console_log_char(char c)
+{
+    console_log_buf[console_log_total % CONSOLE_LOG_SIZE] = c;
+    console_log_total++;
 }
 
 static void serial_write_char(char c)
@@ -412,16 +492,21 @@ static bool console_verbose_mode(void)
 
 /**
  * @function update_cursor
- * @brief Update VGA text mode cursor position
+ * @brief Update cursor position
  * 
- * This function updates the hardware cursor position in VGA text mode.
- * The cursor is controlled via I/O ports 0x3D4 (index) and 0x3D5 (data).
+ * In VGA text mode the hardware cursor is controlled via I/O ports
+ * 0x3D4 (index) and 0x3D5 (data); the framebuffer console draws its own.
  * 
- * @param row Cursor row (0-24)
- * @param col Cursor column (0-79)
+ * @param row Cursor row
+ * @param col Cursor column
  */
-static void update_cursor(uint8_t row, uint8_t col)
+static void update_cursor(uint16_t row, uint16_t col)
 {
+    if (fb_console) {
+        fbcon_set_cursor(row, col);
+        return;
+    }
+
     uint16_t pos = row * VGA_WIDTH + col;
     uint8_t pos_low = (uint8_t)(pos & 0xFF);
     uint8_t pos_high = (uint8_t)((pos >> 8) & 0xFF);
@@ -436,17 +521,27 @@ static void update_cursor(uint8_t row, uint8_t col)
     __asm__ volatile ("outb %%al, %1" : : "a"(pos_high), "Nd"((uint16_t)0x3D5));
 }
 
-static void console_set_cursor(uint8_t row, uint8_t col)
+static void console_set_cursor(uint16_t row, uint16_t col)
 {
-    if (row >= VGA_HEIGHT) {
-        row = VGA_HEIGHT - 1;
+    if (row >= con_rows) {
+        row = con_rows - 1;
     }
-    if (col >= VGA_WIDTH) {
-        col = VGA_WIDTH - 1;
+    if (col >= con_cols) {
+        col = con_cols - 1;
     }
-    vga_row = row;
-    vga_col = col;
-    update_cursor(vga_row, vga_col);
+    con_row = row;
+    con_col = col;
+    update_cursor(con_row, con_col);
+}
+
+/* Write one character cell at (row, col) with the current color */
+static void put_cell(uint16_t row, uint16_t col, char c)
+{
+    if (fb_console) {
+        fbcon_put(row, col, (uint8_t)c, vga_color);
+        return;
+    }
+    vga_buffer[row * VGA_WIDTH + col] = (uint16_t)(uint8_t)c | ((uint16_t)vga_color << 8);
 }
 
 static int ansi_parse_uint(const char* s, int* out)
@@ -571,7 +666,9 @@ static bool console_handle_ansi_char(char c)
             if (ansi_parse_row_col(ansi_csi_buf, &row, &col) == 0) {
                 if (row < 1) row = 1;
                 if (col < 1) col = 1;
-                console_set_cursor((uint8_t)(row - 1), (uint8_t)(col - 1));
+                if (row > 0xFFFF) row = 0xFFFF;
+                if (col > 0xFFFF) col = 0xFFFF;
+                console_set_cursor((uint16_t)(row - 1), (uint16_t)(col - 1));
             }
         }
 
@@ -587,13 +684,13 @@ static bool console_handle_ansi_char(char c)
 
 void console_init(void)
 {
-    vga_row = 0;
-    vga_col = 0;
+    con_row = 0;
+    con_col = 0;
     vga_color = 0x0F;
 
     serial_init();
     /* Initialize cursor position */
-    update_cursor(vga_row, vga_col);
+    update_cursor(con_row, con_col);
 }
 
 void console_set_vga_buffer(void* buffer)
@@ -604,14 +701,31 @@ void console_set_vga_buffer(void* buffer)
     vga_buffer = (uint16_t*)buffer;
 }
 
+int console_attach_framebuffer(void)
+{
+    int rc = fbcon_init();
+    if (rc != RDNX_OK) {
+        return rc;
+    }
+    fb_console = true;
+    con_cols = (uint16_t)fbcon_cols();
+    con_rows = (uint16_t)fbcon_rows();
+    console_clear();
+    return RDNX_OK;
+}
+
 void console_clear(void)
 {
-    for (uint32_t i = 0; i < VGA_WIDTH * VGA_HEIGHT; i++) {
-        vga_buffer[i] = (uint16_t)' ' | ((uint16_t)vga_color << 8);
+    if (fb_console) {
+        fbcon_clear(vga_color);
+    } else {
+        for (uint32_t i = 0; i < VGA_WIDTH * VGA_HEIGHT; i++) {
+            vga_buffer[i] = (uint16_t)' ' | ((uint16_t)vga_color << 8);
+        }
     }
-    vga_row = 0;
-    vga_col = 0;
-    update_cursor(vga_row, vga_col);
+    con_row = 0;
+    con_col = 0;
+    update_cursor(con_row, con_col);
 }
 
 /**
@@ -625,6 +739,11 @@ void console_clear(void)
  */
 static void scroll_screen(void)
 {
+    if (fb_console) {
+        fbcon_scroll(vga_color);
+        return;
+    }
+
     /* Safety check: ensure vga_buffer is valid */
     if (!vga_buffer) {
         return;
@@ -668,81 +787,80 @@ void kputc(char c)
         serial_write_char('\r');
     }
     serial_write_char(c);
+    console_log_char(c);
 
     /* Handle backspace */
     if (c == '\b') {
-        if (vga_col > 0) {
-            vga_col--;
-        } else if (vga_row > 0) {
-            vga_row--;
-            vga_col = VGA_WIDTH - 1;
+        if (con_col > 0) {
+            con_col--;
+        } else if (con_row > 0) {
+            con_row--;
+            con_col = con_cols - 1;
         } else {
-            update_cursor(vga_row, vga_col);
+            update_cursor(con_row, con_col);
             return;
         }
-        uint32_t index = vga_row * VGA_WIDTH + vga_col;
-        vga_buffer[index] = (uint16_t)' ' | ((uint16_t)vga_color << 8);
-        update_cursor(vga_row, vga_col);
+        put_cell(con_row, con_col, ' ');
+        update_cursor(con_row, con_col);
         return;
     }
 
     /* Handle newline */
     if (c == '\n') {
-        vga_col = 0;
-        vga_row++;
-        if (vga_row >= VGA_HEIGHT) {
+        con_col = 0;
+        con_row++;
+        if (con_row >= con_rows) {
             /* Scroll screen when reaching bottom */
             scroll_screen();
-            vga_row = VGA_HEIGHT - 1;  /* Stay on last line after scroll */
+            con_row = con_rows - 1;  /* Stay on last line after scroll */
         }
-        update_cursor(vga_row, vga_col);
+        update_cursor(con_row, con_col);
         log_at_line_start = true;
         return;
     }
     
     /* Handle carriage return */
     if (c == '\r') {
-        vga_col = 0;
-        update_cursor(vga_row, vga_col);
+        con_col = 0;
+        update_cursor(con_row, con_col);
         return;
     }
     
     /* Handle tab (expand to spaces) */
     if (c == '\t') {
         do {
-            uint32_t index = vga_row * VGA_WIDTH + vga_col;
-            vga_buffer[index] = (uint16_t)' ' | ((uint16_t)vga_color << 8);
-            vga_col++;
-            if (vga_col >= VGA_WIDTH) {
-                vga_col = 0;
-                vga_row++;
-                if (vga_row >= VGA_HEIGHT) {
+            put_cell(con_row, con_col, ' ');
+            con_col++;
+            if (con_col >= con_cols) {
+                con_col = 0;
+                con_row++;
+                if (con_row >= con_rows) {
                     scroll_screen();
-                    vga_row = VGA_HEIGHT - 1;
+                    con_row = con_rows - 1;
                 }
             }
-        } while ((vga_col & 7) != 0);  /* Tab stops every 8 columns (use bitwise AND instead of modulo) */
+        } while ((con_col & 7) != 0);  /* Tab stops every 8 columns (use bitwise AND instead of modulo) */
+        update_cursor(con_row, con_col);
         return;
     }
     
     /* Write character to screen */
-    uint32_t index = vga_row * VGA_WIDTH + vga_col;
-    vga_buffer[index] = (uint16_t)c | ((uint16_t)vga_color << 8);
+    put_cell(con_row, con_col, c);
     
     /* Advance cursor */
-    vga_col++;
-    if (vga_col >= VGA_WIDTH) {
-        vga_col = 0;
-        vga_row++;
-        if (vga_row >= VGA_HEIGHT) {
+    con_col++;
+    if (con_col >= con_cols) {
+        con_col = 0;
+        con_row++;
+        if (con_row >= con_rows) {
             /* Scroll screen when reaching bottom */
             scroll_screen();
-            vga_row = VGA_HEIGHT - 1;  /* Stay on last line after scroll */
+            con_row = con_rows - 1;  /* Stay on last line after scroll */
         }
     }
     
     /* Update hardware cursor position */
-    update_cursor(vga_row, vga_col);
+    update_cursor(con_row, con_col);
 }
 
 void kputs(const char* str)
@@ -753,27 +871,33 @@ void kputs(const char* str)
 
     /* Prevent recursive calls from exception handlers */
     if (kputs_in_progress) {
-        /* If already in kputs, just write directly to VGA to avoid recursion */
+        /*
+         * If already in kputs, just write directly to the screen to avoid
+         * recursion: raw VGA memory, or plain cells on the framebuffer.
+         */
         volatile uint16_t* vga = (volatile uint16_t*)VGA_MEMORY;
-        static uint8_t safe_row = 0;
-        static uint8_t safe_col = 0;
+        static uint16_t safe_row = 0;
+        static uint16_t safe_col = 0;
         
-        while (*str && safe_row < VGA_HEIGHT) {
+        while (*str && safe_row < con_rows) {
             /* Still mirror to serial in the safe path */
             if (*str == '\n') {
                 serial_write_char('\r');
             }
             serial_write_char(*str);
+            console_log_char(*str);
             if (*str == '\n') {
                 safe_col = 0;
                 safe_row++;
             } else if (*str != '\r') {
                 uint32_t idx = safe_row * VGA_WIDTH + safe_col;
-                if (idx < VGA_WIDTH * VGA_HEIGHT) {
+                if (fb_console) {
+                    fbcon_put(safe_row, safe_col, (uint8_t)*str, 0x0F);
+                } else if (idx < VGA_WIDTH * VGA_HEIGHT) {
                     vga[idx] = (uint16_t)*str | ((uint16_t)0x0F << 8);
                 }
                 safe_col++;
-                if (safe_col >= VGA_WIDTH) {
+                if (safe_col >= con_cols) {
                     safe_col = 0;
                     safe_row++;
                 }
@@ -793,6 +917,189 @@ void kputs(const char* str)
     __asm__ volatile ("" ::: "memory");
 }
 
+uint64_t console_log_position(void)
+{
+    return console_log_total;
+}
+
+size_t console_log_read(uint64_t from, char* out, size_t max)
+{
+    if (!out || max == 0) {
+        return 0;
+    }
+    uint64_t end = console_log_total;
+    uint64_t oldest = end > CONSOLE_LOG_SIZE ? end - CONSOLE_LOG_SIZE : 0;
+    if (from < oldest) {
+        from = oldest;
+    }
+    size_t n = 0;
+    while (from < end && n < max) {
+        out[n++] = console_log_buf[from % CONSOLE_LOG_SIZE];
+        from++;
+    }
+    return n;
+}
+
+void console_serial_write(const char* buf, size_t len)
+{
+    if (!buf) {
+        return;
+    }
+    for (size_t i = 0; i < len; i++) {
+        if (buf[i] == '\n') {
+            serial_write_char('\r');
+        }
+        serial_write_char(buf[i]);
+    }
+}
+
+static bool serial_rx_ready(void)
+{
+    uint8_t lsr = inb(SERIAL_COM1_BASE + SERIAL_LSR);
+    return lsr != 0xFF && (lsr & 0x01) != 0;
+}
+
+/*
+ * With the RX interrupt on, the UART belongs to the IRQ handler. Code that
+ * runs with interrupts off (the debugger) still has to poll it directly.
+ */
+static bool serial_rx_may_poll(void)
+{
+    uint64_t rflags;
+    __asm__ volatile ("pushfq; pop %0" : "=r"(rflags));
+    return !serial_rx_irq || (rflags & (1ULL << 9)) == 0;
+}
+
+/* Enter, '~', Ctrl-B (the BSD "alt break" sequence) */
+static bool serial_break_check(uint8_t c)
+{
+    static const uint8_t seq[] = { '\r', '~', 0x02 };
+    if (c == seq[serial_break_state] || (serial_break_state == 0 && c == '\n')) {
+        if (++serial_break_state == sizeof(seq)) {
+            serial_break_state = 0;
+            return true;
+        }
+        return false;
+    }
+    serial_break_state = (c == '\r' || c == '\n') ? 1 : 0;
+    return false;
+}
+
+static void serial_rx_intr(interrupt_context_t* ctx)
+{
+    bool brk = false;
+    while (serial_rx_ready()) {
+        uint8_t c = inb(SERIAL_COM1_BASE + SERIAL_DATA);
+        if (serial_break_check(c)) {
+            brk = true;
+        }
+        uint32_t head = serial_rx_head;
+        if (head - __atomic_load_n(&serial_rx_tail, __ATOMIC_ACQUIRE) < SERIAL_RX_RING) {
+            serial_rx_ring[head % SERIAL_RX_RING] = c;
+            __atomic_store_n(&serial_rx_head, head + 1, __ATOMIC_RELEASE);
+        }
+    }
+    if (brk) {
+        ddb_enter("break sequence", ctx ? ctx->arch_specific : NULL);
+    }
+}
+
+int console_serial_rx_irq_init(void)
+{
+    if (!serial_enabled) {
+        return RDNX_E_NOTFOUND;
+    }
+    if (interrupt_register(32 + SERIAL_IRQ, serial_rx_intr) != 0) {
+        return RDNX_E_BUSY;
+    }
+    if (apic_is_available() && ioapic_is_available()) {
+        apic_enable_irq(SERIAL_IRQ);
+    } else {
+        pic_enable_irq(SERIAL_IRQ);
+    }
+    serial_rx_irq = true;
+    /* Received-data interrupt only; OUT2 is already set in serial_init */
+    outb(SERIAL_COM1_BASE + SERIAL_IER, 0x01);
+    return RDNX_OK;
+}
+
+int console_serial_getc(void)
+{
+    uint32_t tail = __atomic_load_n(&serial_rx_tail, __ATOMIC_ACQUIRE);
+    while (tail != __atomic_load_n(&serial_rx_head, __ATOMIC_ACQUIRE)) {
+        uint8_t c = serial_rx_ring[tail % SERIAL_RX_RING];
+        if (__atomic_compare_exchange_n(&serial_rx_tail, &tail, tail + 1, false,
+                                        __ATOMIC_ACQ_REL, __ATOMIC_ACQUIRE)) {
+            return c;
+        }
+    }
+    if (serial_enabled && serial_rx_may_poll() && serial_rx_ready()) {
+        return inb(SERIAL_COM1_BASE + SERIAL_DATA);
+    }
+    return -1;
+}
+
+bool console_serial_has_char(void)
+{
+    if (__atomic_load_n(&serial_rx_tail, __ATOMIC_ACQUIRE) !=
+        __atomic_load_n(&serial_rx_head, __ATOMIC_ACQUIRE)) {
+        return true;
+    }
+    return serial_enabled && serial_rx_may_poll() && serial_rx_ready();
+}
+
+int console_serial2_init(void)
+{
+    /* 115200 baud, what gdb's "set serial baud" defaults to */
+    serial2_enabled = serial_port_setup(SERIAL_COM2_BASE, 1);
+    return serial2_enabled ? RDNX_OK : RDNX_E_NOTFOUND;
+}
+
+void console_serial2_putc(uint8_t c)
+{
+    if (!serial2_enabled) {
+        return;
+    }
+    while ((inb(SERIAL_COM2_BASE + SERIAL_LSR) & 0x20) == 0) {
+        __asm__ volatile ("pause");
+    }
+    outb(SERIAL_COM2_BASE + SERIAL_DATA, c);
+}
+
+int console_serial2_getc(void)
+{
+    if (serial2_enabled && (inb(SERIAL_COM2_BASE + SERIAL_LSR) & 0x01) != 0) {
+        return inb(SERIAL_COM2_BASE + SERIAL_DATA);
+    }
+    return -1;
+}
+
+/* Anything gdb sends while the kernel runs (Ctrl-C, a new connection) stops it */
+static void serial2_rx_intr(interrupt_context_t* ctx)
+{
+    int c = console_serial2_getc();
+    if (c >= 0) {
+        gdbstub_break((uint8_t)c, ctx ? ctx->arch_specific : NULL);
+    }
+}
+
+int console_serial2_rx_irq_init(void)
+{
+    if (!serial2_enabled) {
+        return RDNX_E_NOTFOUND;
+    }
+    if (interrupt_register(32 + SERIAL2_IRQ, serial2_rx_intr) != 0) {
+        return RDNX_E_BUSY;
+    }
+    if (apic_is_available() && ioapic_is_available()) {
+        apic_enable_irq(SERIAL2_IRQ);
+    } else {
+        pic_enable_irq(SERIAL2_IRQ);
+    }
+    outb(SERIAL_COM2_BASE + SERIAL_IER, 0x01);
+    return RDNX_OK;
+}
+
 void console_set_log_prefix_enabled(bool enabled)
 {
     log_prefix_enabled = enabled;
@@ -0,0 +1,446 @@
+/**
+ * @file crashdump.c
+ * @brief Post-mortem crash dump writer
+ *
+ * Everything here may run from panic() with interrupts off, locks held
+ * and the heap corrupted: the dump is assembled in a static buffer and
+ * written with polled I/O only (the IDE driver's PIO path or COM1).
+ */
+
+#include "crashdump.h"
+#include "tracev2.h"
+#include "bootparam.h"
+#include "../core/backtrace.h"
+#include "../core/cpu.h"
+#include "../core/task.h"
+#include "../arch/interrupt_frame.h"
+#include "../fabric/service/block_service.h"
+#include "../../include/console.h"
+#include "../../include/common.h"
+#include "../../include/error.h"
+
+#define CRASHDUMP_SECTOR_BUF 4096u
+#define CRASHDUMP_B64_LINE   57u /* 57 input bytes -> 76 base64 chars */
+
+typedef enum {
+    CRASHDUMP_TARGET_SERIAL = 0,
+    CRASHDUMP_TARGET_DISK,
+    CRASHDUMP_TARGET_NONE
+} crashdump_target_t;
+
+/*
+ * LOCKING: none
+ *   Target is chosen once during boot before other CPUs run; the write
+ *   path is serialized by crashdump_busy (first panicking CPU wins).
+ */
+static struct {
+    crashdump_target_t target;
+    fabric_blockdev_t* dev;
+    uint64_t lba;
+    uint64_t sectors;
+} dump;
+
+static volatile uint32_t crashdump_busy;
+static uint8_t crashdump_buf[CRASHDUMP_MAX_SIZE] __attribute__((aligned(16)));
+static uint8_t crashdump_sector[CRASHDUMP_SECTOR_BUF] __attribute__((aligned(16)));
+static tracev2_record_t crashdump_trace[TRACEV2_RING_SIZE];
+
+static uint32_t crc32_update(uint32_t crc, const uint8_t* p, size_t len)
+{
+    crc = ~crc;
+    for (size_t i = 0; i < len; i++) {
+        crc ^= p[i];
+        for (int b = 0; b < 8; b++) {
+            crc = (crc >> 1) ^ (0xEDB88320u & (0u - (crc & 1u)));
+        }
+    }
+    return ~crc;
+}
+
+static uint32_t rd_le32(const uint8_t* p)
+{
+    return (uint32_t)p[0] | ((uint32_t)p[1] << 8) |
+           ((uint32_t)p[2] << 16) | ((uint32_t)p[3] << 24);
+}
+
+/* ---- Target selection ---- */
+
+BOOTPARAM_STRING(crashdump_dumpdev, "rdnx.dumpdev", "",
+                 "Dump target: none, serial, <blockdev> or <blockdev>:<lba>:<count>");
+
+/* Decimal number in [s, end); false on anything else or overflow */
+static bool crashdump_parse_u64(const char* s, const char* end, uint64_t* out)
+{
+    uint64_t v = 0;
+    if (s >= end) {
+        return false;
+    }
+    for (; s < end; s++) {
+        if (*s < '0' || *s > '9') {
+            return false;
+        }
+        uint64_t d = (uint64_t)(*s - '0');
+        if (v > (UINT64_MAX - d) / 10u) {
+            return false;
+        }
+        v = v * 10u + d;
+    }
+    *out = v;
+    return true;
+}
+
+/*
+ * Split "<dev>:<lba>:<count>" into its parts; a plain "<dev>" leaves
+ * *explicit_range false. @return false if the string is malformed.
+ */
+static bool crashdump_parse_arg(const char* arg, char* name, size_t name_size,
+                                bool* explicit_range, uint64_t* lba, uint64_t* sectors)
+{
+    const char* c1 = strchr(arg, ':');
+    size_t n = c1 ? (size_t)(c1 - arg) : strlen(arg);
+    if (n == 0 || n >= name_size) {
+        return false;
+    }
+    memcpy(name, arg, n);
+    name[n] = '\0';
+    *explicit_range = false;
+    if (!c1) {
+        return true;
+    }
+    const char* c2 = strchr(c1 + 1, ':');
+    if (!c2 || !crashdump_parse_u64(c1 + 1, c2, lba) ||
+        !crashdump_parse_u64(c2 + 1, c2 + 1 + strlen(c2 + 1), sectors)) {
+        return false;
+    }
+    *explicit_range = true;
+    return true;
+}
+
+/*
+ * Look for a dump partition (MBR type 0xDA) on dev.
+ * @return RDNX_OK and fills lba/sectors, RDNX_E_NOTFOUND if the disk has
+ *         no MBR at all, RDNX_E_BUSY if it has an MBR without a dump slot
+ */
+static int crashdump_probe_mbr(fabric_blockdev_t* dev, uint64_t* lba, uint64_t* sectors)
+{
+    if (dev->sector_size < 512 || dev->sector_size > CRASHDUMP_SECTOR_BUF) {
+        return RDNX_E_UNSUPPORTED;
+    }
+    if (fabric_blockdev_read(dev, 0, 1, crashdump_sector) != RDNX_OK) {
+        return RDNX_E_GENERIC;
+    }
+    if (crashdump_sector[510] != 0x55 || crashdump_sector[511] != 0xAA) {
+        return RDNX_E_NOTFOUND;
+    }
+    for (uint32_t i = 0; i < 4; i++) {
+        const uint8_t* e = &crashdump_sector[446 + i * 16];
+        uint32_t start = rd_le32(e + 8);
+        uint32_t count = rd_le32(e + 12);
+        if (e[4] != CRASHDUMP_MBR_TYPE || start == 0 || count == 0) {
+            continue;
+        }
+        if ((uint64_t)start + count > dev->sector_count) {
+            continue;
+        }
+        *lba = start;
+        *sectors = count;
+        return RDNX_OK;
+    }
+    return RDNX_E_BUSY;
+}
+
+static void crashdump_use_disk(fabric_blockdev_t* dev, uint64_t lba, uint64_t sectors)
+{
+    dump.target = CRASHDUMP_TARGET_DISK;
+    dump.dev = dev;
+    dump.lba = lba;
+    dump.sectors = sectors;
+    kprintf("[DUMP] target %s lba=%llu sectors=%llu\n", dev->name,
+            (unsigned long long)lba, (unsigned long long)sectors);
+}
+
+void crashdump_init(void)
+{
+    const char* arg = crashdump_dumpdev;
+    uint64_t lba = 0;
+    uint64_t sectors = 0;
+
+    dump.target = CRASHDUMP_TARGET_SERIAL;
+    dump.dev = NULL;
+
+    if (arg[0]) {
+        if (strcmp(arg, "none") == 0) {
+            dump.target = CRASHDUMP_TARGET_NONE;
+            kputs("[DUMP] disabled\n");
+            return;
+        }
+        if (strcmp(arg, "serial") == 0) {
+            kputs("[DUMP] target serial\n");
+            return;
+        }
+        char name[sizeof(((fabric_blockdev_t*)0)->name)];
+        bool explicit_range = false;
+        if (!crashdump_parse_arg(arg, name, sizeof(name), &explicit_range, &lba, &sectors)) {
+            kprintf("[DUMP] %s: bad target, using serial\n", arg);
+            return;
+        }
+        fabric_blockdev_t* dev = fabric_blockdev_find(name);
+        if (!dev) {
+            kprintf("[DUMP] %s: no such block device, using serial\n", name);
+            return;
+        }
+        if (explicit_range) {
+            if (sectors == 0 || lba >= dev->sector_count ||
+                sectors > dev->sector_count - lba) {
+                kprintf("[DUMP] %s: range outside the device, using serial\n", arg);
+                return;
+            }
+            crashdump_use_disk(dev, lba, sectors);
+            return;
+        }
+        /*
+         * Never fall back to the whole device: a disk without an MBR is
+         * usually a bare filesystem (ext2 starts at LBA 0).
+         */
+        int rc = crashdump_probe_mbr(dev, &lba, &sectors);
+        if (rc == RDNX_OK) {
+            crashdump_use_disk(dev, lba, sectors);
+        } else {
+            kprintf("[DUMP] %s: no dump partition (%d), using serial\n", name, rc);
+        }
+        return;
+    }
+
+    uint32_t count = fabric_blockdev_count();
+    for (uint32_t i = 0; i < count; i++) {
+        fabric_blockdev_t* dev = fabric_blockdev_get(i);
+        if (dev && crashdump_probe_mbr(dev, &lba, &sectors) == RDNX_OK) {
+            crashdump_use_disk(dev, lba, sectors);
+            return;
+        }
+    }
+    kputs("[DUMP] target serial\n");
+}
+
+/* ---- Dump assembly ---- */
+
+static uint32_t crashdump_len;
+static uint32_t crashdump_sections;
+
+static void* crashdump_section_begin(uint32_t type)
+{
+    if (crashdump_len + sizeof(crashdump_section_t) > CRASHDUMP_MAX_SIZE) {
+        return NULL;
+    }
+    crashdump_section_t* sec = (crashdump_section_t*)&crashdump_buf[crashdump_len];
+    sec->type = type;
+    sec->size = 0;
+    return sec + 1;
+}
+
+static uint32_t crashdump_section_room(void)
+{
+    uint32_t used = crashdump_len + (uint32_t)sizeof(crashdump_section_t);
+    return used >= CRASHDUMP_MAX_SIZE ? 0 : CRASHDUMP_MAX_SIZE - used;
+}
+
+static void crashdump_section_end(uint32_t size)
+{
+    crashdump_section_t* sec = (crashdump_section_t*)&crashdump_buf[crashdump_len];
+    uint32_t padded = (size + 7u) & ~7u;
+    sec->size = size;
+    /* Zero the padding so the CRC covers deterministic bytes */
+    memset((uint8_t*)(sec + 1) + size, 0, padded - size);
+    crashdump_len += (uint32_t)sizeof(*sec) + padded;
+    crashdump_sections++;
+}
+
+static void crashdump_add(uint32_t type, const void* data, uint32_t size)
+{
+    uint8_t* payload = crashdump_section_begin(type);
+    if (!payload || size > crashdump_section_room()) {
+        return;
+    }
+    memcpy(payload, data, size);
+    crashdump_section_end(size);
+}
+
+static void crashdump_fill_regs(crashdump_regs_t* r, const interrupt_frame_t* f)
+{
+    memset(r, 0, sizeof(*r));
+    if (f) {
+        r->rip = f->rip;
+        r->rsp = f->rsp;
+        r->rbp = f->rbp;
+        r->rflags = f->rflags;
+        r->rax = f->rax;
+        r->rbx = f->rbx;
+        r->rcx = f->rcx;
+        r->rdx = f->rdx;
+        r->rsi = f->rsi;
+        r->rdi = f->rdi;
+        r->r8 = f->r8;
+        r->r9 = f->r9;
+        r->r10 = f->r10;
+        r->r11 = f->r11;
+        r->r12 = f->r12;
+        r->r13 = f->r13;
+        r->r14 = f->r14;
+        r->r15 = f->r15;
+        r->cs = f->cs;
+        r->ss = f->ss;
+        r->vector = f->int_no;
+        r->err_code = f->err_code;
+    } else {
+        __asm__ volatile ("lea (%%rip), %0" : "=r"(r->rip));
+        __asm__ volatile ("mov %%rsp, %0" : "=r"(r->rsp));
+        __asm__ volatile ("mov %%rbp, %0" : "=r"(r->rbp));
+        __asm__ volatile ("pushfq; pop %0" : "=r"(r->rflags));
+        __asm__ volatile ("mov %%cs, %0" : "=r"(r->cs));
+        __asm__ volatile ("mov %%ss, %0" : "=r"(r->ss));
+    }
+    __asm__ volatile ("mov %%cr0, %0" : "=r"(r->cr0));
+    __asm__ volatile ("mov %%cr2, %0" : "=r"(r->cr2));
+    __asm__ volatile ("mov %%cr3, %0" : "=r"(r->cr3));
+    __asm__ volatile ("mov %%cr4, %0" : "=r"(r->cr4));
+}
+
+static void crashdump_fill_task(crashdump_task_t* t)
+{
+    memset(t, 0, sizeof(*t));
+    task_t* task = task_get_current();
+    thread_t* thread = thread_get_current();
+    if (task) {
+        t->task_id = task->task_id;
+        t->parent_task_id = task->parent_task_id;
+        t->uid = task->uid;
+        t->abi = task->abi;
+        strncpy(t->cwd, task->cwd, sizeof(t->cwd) - 1);
+    }
+    if (thread) {
+        t->thread_id = thread->thread_id;
+        t->thread_state = (uint32_t)thread->state;
+        t->priority = thread->priority;
+    }
+}
+
+static void crashdump_build(const char* msg, const interrupt_frame_t* frame)
+{
+    crashdump_header_t* hdr = (crashdump_header_t*)crashdump_buf;
+    memset(hdr, 0, sizeof(*hdr));
+    memcpy(hdr->magic, CRASHDUMP_MAGIC, sizeof(hdr->magic));
+    hdr->version = CRASHDUMP_VERSION;
+    hdr->header_size = (uint16_t)sizeof(*hdr);
+    hdr->uptime_ns = console_get_uptime_ns();
+    hdr->cpu = cpu_get_id();
+    hdr->flags = frame ? CRASHDUMP_F_FRAME : 0;
+    crashdump_len = (uint32_t)sizeof(*hdr);
+    crashdump_sections = 0;
+
+    if (msg) {
+        crashdump_add(CRASHDUMP_SEC_MESSAGE, msg, (uint32_t)strlen(msg));
+    }
+
+    crashdump_regs_t regs;
+    crashdump_fill_regs(&regs, frame);
+    crashdump_add(CRASHDUMP_SEC_REGS, &regs, sizeof(regs));
+
+    uint64_t* pcs = crashdump_section_begin(CRASHDUMP_SEC_BACKTRACE);
+    if (pcs) {
+        uint32_t n = frame ? backtrace_capture_from(frame->rip, frame->rbp, pcs, CRASHDUMP_BT_MAX)
+                           : backtrace_capture(pcs, CRASHDUMP_BT_MAX);
+        crashdump_section_end(n * (uint32_t)sizeof(uint64_t));
+    }
+
+    crashdump_task_t task;
+    crashdump_fill_task(&task);
+    crashdump_add(CRASHDUMP_SEC_TASK, &task, sizeof(task));
+
+    uint32_t nrec = tracev2_snapshot(crashdump_trace, TRACEV2_RING_SIZE);
+    crashdump_add(CRASHDUMP_SEC_TRACE, crashdump_trace, nrec * (uint32_t)sizeof(tracev2_record_t));
+
+    char* log = crashdump_section_begin(CRASHDUMP_SEC_LOG);
+    if (log) {
+        uint32_t room = crashdump_section_room();
+        uint64_t end = console_log_position();
+        uint64_t from = end > CRASHDUMP_LOG_TAIL ? end - CRASHDUMP_LOG_TAIL : 0;
+        size_t n = console_log_read(from, log, room < CRASHDUMP_LOG_TAIL ? room : CRASHDUMP_LOG_TAIL);
+        crashdump_section_end((uint32_t)n);
+    }
+
+    hdr->total_size = crashdump_len;
+    hdr->section_count = crashdump_sections;
+    hdr->crc32 = crc32_update(0, crashdump_buf, crashdump_len);
+}
+
+/* ---- Output ---- */
+
+static int crashdump_emit_disk(void)
+{
+    uint32_t ss = dump.dev->sector_size;
+    uint32_t count = (crashdump_len + ss - 1) / ss;
+    if (count > dump.sectors) {
+        return RDNX_E_NOMEM;
+    }
+    /* Sector padding past total_size goes out as zeros */
+    memset(&crashdump_buf[crashdump_len], 0, (size_t)count * ss - crashdump_len);
+    return fabric_blockdev_write(dump.dev, dump.lba, count, crashdump_buf);
+}
+
+static void crashdump_emit_serial(void)
+{
+    static const char b64[] =
+        "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    char line[80];
+
+    console_serial_write("\n" CRASHDUMP_SERIAL_BEGIN "\n", sizeof(CRASHDUMP_SERIAL_BEGIN) + 1);
+    for (uint32_t off = 0; off < crashdump_len; off += CRASHDUMP_B64_LINE) {
+        uint32_t chunk = crashdump_len - off;
+        if (chunk > CRASHDUMP_B64_LINE) {
+            chunk = CRASHDUMP_B64_LINE;
+        }
+        size_t n = 0;
+        for (uint32_t i = 0; i < chunk; i += 3) {
+            const uint8_t* p = &crashdump_buf[off + i];
+            uint32_t left = chunk - i;
+            uint32_t v = (uint32_t)p[0] << 16;
+            if (left > 1) {
+                v |= (uint32_t)p[1] << 8;
+            }
+            if (left > 2) {
+                v |= p[2];
+            }
+            line[n++] = b64[(v >> 18) & 0x3F];
+            line[n++] = b64[(v >> 12) & 0x3F];
+            line[n++] = left > 1 ? b64[(v >> 6) & 0x3F] : '=';
+            line[n++] = left > 2 ? b64[v & 0x3F] : '=';
+        }
+        line[n++] = '\n';
+        console_serial_write(line, n);
+    }
+    console_serial_write(CRASHDUMP_SERIAL_END "\n", sizeof(CRASHDUMP_SERIAL_END));
+}
+
+void crashdump_write(const char* msg, const interrupt_frame_t* frame)
+{
+    if (dump.target == CRASHDUMP_TARGET_NONE) {
+        return;
+    }
+    if (__atomic_exchange_n(&crashdump_busy, 1u, __ATOMIC_ACQ_REL) != 0) {
+        return;
+    }
+
+    crashdump_build(msg, frame);
+
+    /* Status goes straight to COM1: the console lock may be held here */
+    if (dump.target == CRASHDUMP_TARGET_DISK) {
+        if (crashdump_emit_disk() == RDNX_OK) {
+            console_serial_write("[DUMP] written to ", 18);
+            console_serial_write(dump.dev->name, strlen(dump.dev->name));
+            console_serial_write("\n", 1);
+            return;
+        }
+        console_serial_write("[DUMP] disk write failed, using serial\n", 39);
+    }
+    crashdump_emit_serial();
+}
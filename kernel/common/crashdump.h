@@ -0,0 +1,98 @@
+/**
+ * @file crashdump.h
+ * @brief Post-mortem crash dump (binary format v1)
+ *
+ * On panic the kernel assembles a self-describing blob and writes it with
+ * polled I/O either to a raw dump partition (MBR type 0xDA) through the
+ * Fabric block service, or to COM1 as base64 between text markers.
+ * scripts/crashdump.py decodes both. All fields are little-endian.
+ *
+ * Layout: crashdump_header_t, then section_count sections, each a
+ * crashdump_section_t followed by size bytes of payload padded to 8.
+ */
+
+#ifndef _RODNIX_COMMON_CRASHDUMP_H
+#define _RODNIX_COMMON_CRASHDUMP_H
+
+#include <stdint.h>
+
+struct interrupt_frame;
+
+#define CRASHDUMP_MAGIC       "RDNXDUMP"
+#define CRASHDUMP_VERSION     1
+#define CRASHDUMP_MAX_SIZE    (64u * 1024u)
+#define CRASHDUMP_MBR_TYPE    0xDA
+#define CRASHDUMP_BT_MAX      32
+#define CRASHDUMP_LOG_TAIL    (12u * 1024u)
+
+#define CRASHDUMP_SERIAL_BEGIN "-----BEGIN RODNIX CRASHDUMP-----"
+#define CRASHDUMP_SERIAL_END   "-----END RODNIX CRASHDUMP-----"
+
+enum {
+    CRASHDUMP_SEC_MESSAGE   = 1, /* Panic message, text */
+    CRASHDUMP_SEC_REGS      = 2, /* crashdump_regs_t */
+    CRASHDUMP_SEC_BACKTRACE = 3, /* uint64_t pcs[], innermost first */
+    CRASHDUMP_SEC_TASK      = 4, /* crashdump_task_t */
+    CRASHDUMP_SEC_TRACE     = 5, /* tracev2_record_t[], oldest first */
+    CRASHDUMP_SEC_LOG       = 6  /* Console log tail, text */
+};
+
+typedef struct {
+    char magic[8];          /* CRASHDUMP_MAGIC */
+    uint16_t version;       /* CRASHDUMP_VERSION */
+    uint16_t header_size;   /* sizeof(crashdump_header_t) */
+    uint32_t total_size;    /* Header + sections, bytes */
+    uint32_t crc32;         /* CRC-32 (IEEE) of total_size bytes, this field as 0 */
+    uint32_t section_count;
+    uint64_t uptime_ns;
+    uint32_t cpu;           /* CPU that panicked */
+    uint32_t flags;         /* CRASHDUMP_F_* */
+} __attribute__((packed)) crashdump_header_t;
+
+/* Registers come from an interrupt frame (exception), not from panic() */
+#define CRASHDUMP_F_FRAME 0x1u
+
+typedef struct {
+    uint32_t type;          /* CRASHDUMP_SEC_* */
+    uint32_t size;          /* Payload bytes (without padding) */
+} __attribute__((packed)) crashdump_section_t;
+
+/* x86_64 register state; GPRs are only meaningful with CRASHDUMP_F_FRAME */
+typedef struct {
+    uint64_t rip, rsp, rbp, rflags;
+    uint64_t rax, rbx, rcx, rdx, rsi, rdi;
+    uint64_t r8, r9, r10, r11, r12, r13, r14, r15;
+    uint64_t cs, ss;
+    uint64_t cr0, cr2, cr3, cr4;
+    uint64_t vector, err_code;
+} __attribute__((packed)) crashdump_regs_t;
+
+typedef struct {
+    uint64_t task_id;       /* 0: no current task */
+    uint64_t parent_task_id;
+    uint64_t thread_id;     /* 0: no current thread */
+    uint32_t uid;
+    uint32_t thread_state;
+    uint8_t abi;
+    uint8_t priority;
+    uint8_t reserved[6];
+    char cwd[64];
+} __attribute__((packed)) crashdump_task_t;
+
+/**
+ * Pick the dump target: rdnx.dumpdev=none|serial|<blockdev>|<blockdev>:<lba>:<count>
+ * on the command line (a bare <blockdev> needs an MBR partition of type
+ * 0xDA), else the first 0xDA partition on any device, else serial.
+ * Call after storage drivers have registered their block devices.
+ */
+void crashdump_init(void);
+
+/**
+ * Assemble and write the dump. Polled I/O only; safe with interrupts off.
+ * Only the first caller writes, nested and concurrent panics return.
+ * @param msg Panic message (may be NULL)
+ * @param frame Interrupt frame of the faulting context, or NULL
+ */
+void crashdump_write(const char* msg, const struct interrupt_frame* frame);
+
+#endif /* _RODNIX_COMMON_CRASHDUMP_H */
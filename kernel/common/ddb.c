@@ -0,0 +1,829 @@
+/**
+ * @file ddb.c
+ * @brief In-kernel debugger on the serial console
+ *
+ * Addresses are hex (0x optional), a symbol, symbol+hexoff or a register
+ * of the stopped frame ($rip, $rsp, ...). Task and thread ids are decimal,
+ * as printed by "ps".
+ */
+
+#include "ddb.h"
+#include "bootparam.h"
+#include "ksyms.h"
+#include "kmem.h"
+#include "memprobe.h"
+#include "../core/backtrace.h"
+#include "../core/cpu.h"
+#include "../core/interrupts.h"
+#include "../core/task.h"
+#include "../arch/interrupt_frame.h"
+#include "../arch/dbreg.h"
+#include "../fabric/fabric.h"
+#include "../vm/vm_map.h"
+#include "../../include/console.h"
+#include "../../include/common.h"
+#include "../../include/error.h"
+#include <stddef.h>
+
+#define DDB_LINE_MAX 128
+#define DDB_ARGS_MAX 8
+
+/*
+ * LOCKING: ddb_owner
+ *   One CPU at a time runs the command loop; a second CPU that traps
+ *   spins in ddb_enter until the first one leaves. Nested entries on the
+ *   owner CPU (a breakpoint hit by a debugger command) just go deeper.
+ */
+static volatile int32_t ddb_owner = -1;
+static uint32_t ddb_depth = 0;
+static bool ddb_ready = false;
+BOOTPARAM_BOOL(ddb_on_panic, "rdnx.ddb", true, "Enter ddb on panic and fatal traps");
+
+/* Context of the innermost entry */
+static interrupt_frame_t* ddb_frame = NULL;
+static bool ddb_resumable = false;
+
+typedef struct {
+    int argc;
+    char* argv[DDB_ARGS_MAX];
+    char size;      /* Modifier after '/', e.g. x/b */
+} ddb_cmd_args_t;
+
+/* Command result: keep reading commands, or leave the debugger */
+enum {
+    DDB_STAY = 0,
+    DDB_LEAVE = 1
+};
+
+/* ---- Output helpers ---- */
+
+static void ddb_hex(uint64_t v, uint32_t digits)
+{
+    static const char hexdig[] = "0123456789abcdef";
+    for (uint32_t i = digits; i > 0; i--) {
+        kputc(hexdig[(v >> ((i - 1) * 4)) & 0xF]);
+    }
+}
+
+static void ddb_print_sym(uint64_t addr)
+{
+    uint64_t off = 0;
+    const char* name = ksyms_lookup(addr, &off);
+    if (name) {
+        kprintf(" <%s+%llx>", name, (unsigned long long)off);
+    }
+}
+
+/* ---- Guarded execution ---- */
+
+/*
+ * Everything that dereferences addresses typed by the user or taken from
+ * possibly corrupt structures goes through memprobe.
+ */
+static bool ddb_guard(void (*fn)(void*), void* arg)
+{
+    if (!memprobe_run(fn, arg)) {
+        kputs("\n[ddb] fault while accessing memory\n");
+        return false;
+    }
+    return true;
+}
+
+static bool ddb_mem_access(uint64_t addr, void* buf, uint32_t len, bool write)
+{
+    if (!memprobe_copy(addr, buf, len, write)) {
+        kputs("\n[ddb] fault while accessing memory\n");
+        return false;
+    }
+    return true;
+}
+
+/* ---- Input ---- */
+
+static int ddb_getc(void)
+{
+    for (;;) {
+        int c = console_serial_getc();
+        if (c >= 0) {
+            return c;
+        }
+        __asm__ volatile ("pause");
+    }
+}
+
+static void ddb_readline(char* buf, size_t cap)
+{
+    size_t len = 0;
+    for (;;) {
+        int c = ddb_getc();
+        if (c == '\r' || c == '\n') {
+            kputc('\n');
+            break;
+        }
+        if (c == 0x7F || c == '\b') {
+            if (len > 0) {
+                len--;
+                kputs("\b \b");
+            }
+            continue;
+        }
+        if (c == 0x15) { /* Ctrl-U */
+            while (len > 0) {
+                len--;
+                kputs("\b \b");
+            }
+            continue;
+        }
+        if (c < 0x20 || c > 0x7E || len + 1 >= cap) {
+            continue;
+        }
+        buf[len++] = (char)c;
+        kputc((char)c);
+    }
+    buf[len] = '\0';
+}
+
+static void ddb_split(char* line, ddb_cmd_args_t* args)
+{
+    args->argc = 0;
+    args->size = 0;
+    char* p = line;
+    while (*p && args->argc < DDB_ARGS_MAX) {
+        while (*p == ' ' || *p == '\t') {
+            p++;
+        }
+        if (!*p) {
+            break;
+        }
+        args->argv[args->argc++] = p;
+        while (*p && *p != ' ' && *p != '\t') {
+            p++;
+        }
+        if (*p) {
+            *p++ = '\0';
+        }
+    }
+    if (args->argc > 0) {
+        char* slash = strchr(args->argv[0], '/');
+        if (slash) {
+            *slash = '\0';
+            args->size = slash[1];
+        }
+    }
+}
+
+/* ---- Argument parsing ---- */
+
+static bool ddb_parse_num(const char* s, uint32_t base, uint64_t* out)
+{
+    uint64_t v = 0;
+    if (base == 16 && s[0] == '0' && (s[1] == 'x' || s[1] == 'X')) {
+        s += 2;
+    }
+    if (!*s) {
+        return false;
+    }
+    for (; *s; s++) {
+        uint32_t d;
+        if (*s >= '0' && *s <= '9') {
+            d = (uint32_t)(*s - '0');
+        } else if (*s >= 'a' && *s <= 'f') {
+            d = (uint32_t)(*s - 'a' + 10);
+        } else if (*s >= 'A' && *s <= 'F') {
+            d = (uint32_t)(*s - 'A' + 10);
+        } else {
+            return false;
+        }
+        if (d >= base) {
+            return false;
+        }
+        v = v * base + d;
+    }
+    *out = v;
+    return true;
+}
+
+static const struct {
+    const char* name;
+    size_t offset;
+} ddb_regs[] = {
+    { "rip", offsetof(interrupt_frame_t, rip) },
+    { "rsp", offsetof(interrupt_frame_t, rsp) },
+    { "rbp", offsetof(interrupt_frame_t, rbp) },
+    { "rflags", offsetof(interrupt_frame_t, rflags) },
+    { "rax", offsetof(interrupt_frame_t, rax) },
+    { "rbx", offsetof(interrupt_frame_t, rbx) },
+    { "rcx", offsetof(interrupt_frame_t, rcx) },
+    { "rdx", offsetof(interrupt_frame_t, rdx) },
+    { "rsi", offsetof(interrupt_frame_t, rsi) },
+    { "rdi", offsetof(interrupt_frame_t, rdi) },
+    { "r8", offsetof(interrupt_frame_t, r8) },
+    { "r9", offsetof(interrupt_frame_t, r9) },
+    { "r10", offsetof(interrupt_frame_t, r10) },
+    { "r11", offsetof(interrupt_frame_t, r11) },
+    { "r12", offsetof(interrupt_frame_t, r12) },
+    { "r13", offsetof(interrupt_frame_t, r13) },
+    { "r14", offsetof(interrupt_frame_t, r14) },
+    { "r15", offsetof(interrupt_frame_t, r15) },
+    { "cs", offsetof(interrupt_frame_t, cs) },
+    { "ss", offsetof(interrupt_frame_t, ss) },
+};
+
+static bool ddb_parse_addr(const char* s, uint64_t* out)
+{
+    if (s[0] == '$') {
+        if (!ddb_frame) {
+            kputs("no stopped frame\n");
+            return false;
+        }
+        for (size_t i = 0; i < sizeof(ddb_regs) / sizeof(ddb_regs[0]); i++) {
+            if (strcmp(s + 1, ddb_regs[i].name) == 0) {
+                *out = *(uint64_t*)((uint8_t*)ddb_frame + ddb_regs[i].offset);
+                return true;
+            }
+        }
+        kprintf("unknown register %s\n", s);
+        return false;
+    }
+    if (ddb_parse_num(s, 16, out)) {
+        return true;
+    }
+
+    char name[64];
+    const char* plus = strchr(s, '+');
+    size_t len = plus ? (size_t)(plus - s) : strlen(s);
+    uint64_t off = 0;
+    if (len == 0 || len >= sizeof(name) || (plus && !ddb_parse_num(plus + 1, 16, &off))) {
+        kprintf("bad address %s\n", s);
+        return false;
+    }
+    memcpy(name, s, len);
+    name[len] = '\0';
+    uint64_t addr = ksyms_resolve(name);
+    if (!addr) {
+        kprintf("no symbol %s\n", name);
+        return false;
+    }
+    *out = addr + off;
+    return true;
+}
+
+static uint32_t ddb_size_bytes(char size)
+{
+    switch (size) {
+        case 'b': return 1;
+        case 'h': return 2;
+        case 'w': return 4;
+        case 'g':
+        case 0: return 8;
+        default: return 0;
+    }
+}
+
+static thread_t* ddb_find_thread(uint64_t tid)
+{
+    for (task_t* task = task_debug_next(NULL); task; task = task_debug_next(task)) {
+        thread_t* th;
+        TAILQ_FOREACH(th, &task->threads, task_link) {
+            if (th->thread_id == tid) {
+                return th;
+            }
+        }
+    }
+    return NULL;
+}
+
+/* ---- Commands ---- */
+
+static const char* const ddb_task_states[] = {
+    "new", "ready", "run", "blocked", "sleep", "zombie", "dead"
+};
+static const char* const ddb_thread_states[] = {
+    "new", "ready", "run", "blocked", "sleep", "dead"
+};
+
+static void ddb_ps_walk(void* arg)
+{
+    (void)arg;
+    thread_t* cur = thread_get_current();
+    kputs("  task  ppid  uid  state    threads\n");
+    for (task_t* task = task_debug_next(NULL); task; task = task_debug_next(task)) {
+        uint32_t st = (uint32_t)task->state;
+        kprintf("  %llu  %llu  %u  %s  %u%s\n",
+                (unsigned long long)task->task_id,
+                (unsigned long long)task->parent_task_id,
+                task->uid,
+                st < 7 ? ddb_task_states[st] : "?",
+                task->thread_count,
+                task->abi == TASK_ABI_LINUX ? "  [linux]" : "");
+        thread_t* th;
+        TAILQ_FOREACH(th, &task->threads, task_link) {
+            uint32_t ts = (uint32_t)th->state;
+            kprintf("      tid %llu %s prio=%u cpu=%u%s",
+                    (unsigned long long)th->thread_id,
+                    ts < 6 ? ddb_thread_states[ts] : "?",
+                    th->priority,
+                    th->sched_cpu,
+                    th->sched_on_cpu ? " on-cpu" : "");
+            if (th->waitq_owner) {
+                kprintf(" wait=%p", th->waitq_owner);
+            }
+            if (th->entry) {
+                ddb_print_sym((uint64_t)(uintptr_t)th->entry);
+            }
+            kputs(th == cur ? "  <- current\n" : "\n");
+        }
+    }
+}
+
+static int ddb_cmd_ps(ddb_cmd_args_t* a)
+{
+    (void)a;
+    ddb_guard(ddb_ps_walk, NULL);
+    return DDB_STAY;
+}
+
+typedef struct {
+    uint64_t pc;
+    uint64_t fp;
+    bool here;
+} ddb_bt_t;
+
+static void ddb_bt_run(void* arg)
+{
+    ddb_bt_t* bt = (ddb_bt_t*)arg;
+    if (bt->here) {
+        backtrace_print(NULL);
+    } else {
+        backtrace_print_from(bt->pc, bt->fp, NULL);
+    }
+}
+
+static int ddb_cmd_bt(ddb_cmd_args_t* a)
+{
+    ddb_bt_t bt = { 0, 0, false };
+    thread_t* cur = thread_get_current();
+    thread_t* th = cur;
+
+    if (a->argc > 1) {
+        uint64_t tid;
+        if (!ddb_parse_num(a->argv[1], 10, &tid)) {
+            kputs("usage: bt [tid]\n");
+            return DDB_STAY;
+        }
+        th = ddb_find_thread(tid);
+        if (!th) {
+            kprintf("no thread %llu\n", (unsigned long long)tid);
+            return DDB_STAY;
+        }
+    }
+
+    if (th == cur) {
+        if (ddb_frame) {
+            bt.pc = ddb_frame->rip;
+            bt.fp = ddb_frame->rbp;
+        } else {
+            bt.here = true;
+        }
+    } else if (th->sched_on_cpu) {
+        kprintf("thread %llu is running on cpu%u, no saved frame\n",
+                (unsigned long long)th->thread_id, th->sched_cpu);
+        return DDB_STAY;
+    } else {
+        /* Switched-out threads keep an interrupt frame at their saved SP */
+        interrupt_frame_t* f = (interrupt_frame_t*)(uintptr_t)th->context.stack_pointer;
+        if (!f) {
+            kputs("thread has no saved context\n");
+            return DDB_STAY;
+        }
+        uint64_t regs[2];
+        if (!ddb_mem_access((uint64_t)(uintptr_t)&f->rip, &regs[0], 8, false) ||
+            !ddb_mem_access((uint64_t)(uintptr_t)&f->rbp, &regs[1], 8, false)) {
+            return DDB_STAY;
+        }
+        bt.pc = regs[0];
+        bt.fp = regs[1];
+    }
+    ddb_guard(ddb_bt_run, &bt);
+    return DDB_STAY;
+}
+
+static int ddb_cmd_regs(ddb_cmd_args_t* a)
+{
+    (void)a;
+    if (!ddb_frame) {
+        kputs("no stopped frame (entered from panic or the shell)\n");
+        return DDB_STAY;
+    }
+    for (size_t i = 0; i < sizeof(ddb_regs) / sizeof(ddb_regs[0]); i++) {
+        uint64_t v = *(uint64_t*)((uint8_t*)ddb_frame + ddb_regs[i].offset);
+        kprintf("%s%s= ", ddb_regs[i].name, strlen(ddb_regs[i].name) < 3 ? "  " : " ");
+        ddb_hex(v, 16);
+        kputs((i % 3 == 2) ? "\n" : "   ");
+    }
+    kputs("\nvector=");
+    ddb_hex(ddb_frame->int_no, 2);
+    kputs(" err=");
+    ddb_hex(ddb_frame->err_code, 8);
+    ddb_print_sym(ddb_frame->rip);
+    kputs("\n");
+    return DDB_STAY;
+}
+
+static int ddb_cmd_examine(ddb_cmd_args_t* a)
+{
+    uint32_t size = ddb_size_bytes(a->size);
+    uint64_t addr;
+    uint64_t count = 8;
+    if (a->argc < 2 || size == 0 || !ddb_parse_addr(a->argv[1], &addr) ||
+        (a->argc > 2 && !ddb_parse_num(a->argv[2], 10, &count))) {
+        kputs("usage: x[/b|h|w|g] addr [count]\n");
+        return DDB_STAY;
+    }
+    uint32_t per_line = 16u / size;
+    for (uint64_t i = 0; i < count; i++) {
+        uint64_t at = addr + i * size;
+        uint64_t v = 0;
+        if (i % per_line == 0) {
+            if (i) {
+                kputs("\n");
+            }
+            ddb_hex(at, 16);
+            kputs(":");
+        }
+        if (!ddb_mem_access(at, &v, size, false)) {
+            return DDB_STAY;
+        }
+        kputs(" ");
+        ddb_hex(v, size * 2);
+    }
+    kputs("\n");
+    return DDB_STAY;
+}
+
+static int ddb_cmd_write(ddb_cmd_args_t* a)
+{
+    uint32_t size = ddb_size_bytes(a->size);
+    uint64_t addr;
+    if (a->argc < 3 || size == 0 || !ddb_parse_addr(a->argv[1], &addr)) {
+        kputs("usage: w[/b|h|w|g] addr value...\n");
+        return DDB_STAY;
+    }
+    for (int i = 2; i < a->argc; i++, addr += size) {
+        uint64_t v;
+        uint64_t old = 0;
+        if (!ddb_parse_num(a->argv[i], 16, &v)) {
+            kprintf("bad value %s\n", a->argv[i]);
+            return DDB_STAY;
+        }
+        if (!ddb_mem_access(addr, &old, size, false) ||
+            !ddb_mem_access(addr, &v, size, true)) {
+            return DDB_STAY;
+        }
+        ddb_hex(addr, 16);
+        kputs(": ");
+        ddb_hex(old, size * 2);
+        kputs(" -> ");
+        ddb_hex(v, size * 2);
+        kputs("\n");
+    }
+    return DDB_STAY;
+}
+
+static void ddb_show_fabric(void)
+{
+    fabric_node_info_t info;
+    for (uint32_t i = 0; fabric_node_get_info_nolock(i, &info) == RDNX_OK; i++) {
+        kprintf("  %s  type=%s class=%s state=%u", info.path, info.type,
+                info.class_name, info.state);
+        if (info.driver[0]) {
+            kprintf(" driver=%s", info.driver);
+        }
+        if (info.provider_path[0]) {
+            kprintf(" provider=%s", info.provider_path);
+        }
+        kputs("\n");
+    }
+}
+
+static void ddb_show_map_walk(void* arg)
+{
+    task_t* task = (task_t*)arg;
+    vm_map_t* map = (vm_map_t*)task->vm_map;
+    if (!map) {
+        kprintf("task %llu has no user map\n", (unsigned long long)task->task_id);
+        return;
+    }
+    kprintf("task %llu pml4=%llx entries=%u brk=%llx-%llx rss=%llu vsz=%lluK%s\n",
+            (unsigned long long)task->task_id,
+            (unsigned long long)map->pml4_phys, map->entry_count,
+            (unsigned long long)task->vm_brk_base,
+            (unsigned long long)task->vm_brk_end,
+            (unsigned long long)map->resident,
+            (unsigned long long)(map->size / 1024u),
+            task->oom_killed ? " oom-killed" : "");
+    for (vm_map_entry_t* e = vm_map_first(map); e; e = vm_map_next(e)) {
+        kputs("  ");
+        ddb_hex(e->start, 16);
+        kputs("-");
+        ddb_hex(e->end, 16);
+        kprintf(" %c%c%c %s%s%s%s%s obj=%p+%llx\n",
+                (e->prot & VM_PROT_READ) ? 'r' : '-',
+                (e->prot & VM_PROT_WRITE) ? 'w' : '-',
+                (e->prot & VM_PROT_EXEC) ? 'x' : '-',
+                (e->flags & VM_MAP_F_ANON) ? "anon " : "",
+                (e->flags & VM_MAP_F_PRIVATE) ? "private " : "shared ",
+                (e->flags & VM_MAP_F_STACK) ? "stack " : "",
+                (e->flags & VM_MAP_F_COW) ? "cow " : "",
+                (e->flags & VM_MAP_F_WIRED) ? "wired " : "",
+                e->object, (unsigned long long)e->object_offset);
+    }
+}
+
+static void ddb_show_kmem_walk(void* arg)
+{
+    (void)arg;
+    kmem_cache_stats_t st;
+    for (uint32_t i = 0; kmem_cache_get_stats(i, &st) == RDNX_OK; i++) {
+        kprintf("  %s size=%u inuse=%llu cached=%llu total=%llu slabs=%u\n", st.name,
+                st.obj_size, (unsigned long long)st.objs_inuse,
+                (unsigned long long)st.objs_cached, (unsigned long long)st.objs_total,
+                st.slabs);
+    }
+    kprintf("  large: %llu pages\n", (unsigned long long)kmem_page_alloc_pages());
+}
+
+static void ddb_show_break(void)
+{
+    static const char* const kinds[] = { "exec", "write", "?", "rw" };
+    for (uint32_t i = 0; i < DBREG_COUNT; i++) {
+        uint64_t addr;
+        uint32_t type;
+        uint32_t len;
+        if (!dbreg_get(i, &addr, &type, &len)) {
+            continue;
+        }
+        kprintf("  %u: %s len=%u ", i, kinds[type & 3u], len);
+        ddb_hex(addr, 16);
+        ddb_print_sym(addr);
+        kputs("\n");
+    }
+}
+
+static int ddb_cmd_show(ddb_cmd_args_t* a)
+{
+    const char* what = a->argc > 1 ? a->argv[1] : "";
+    if (strcmp(what, "fabric") == 0) {
+        ddb_show_fabric();
+    } else if (strcmp(what, "map") == 0) {
+        task_t* task = task_get_current();
+        if (a->argc > 2) {
+            uint64_t id;
+            task = NULL;
+            if (ddb_parse_num(a->argv[2], 10, &id)) {
+                for (task_t* t = task_debug_next(NULL); t; t = task_debug_next(t)) {
+                    if (t->task_id == id) {
+                        task = t;
+                        break;
+                    }
+                }
+            }
+        }
+        if (!task) {
+            kputs("no such task\n");
+        } else {
+            ddb_guard(ddb_show_map_walk, task);
+        }
+    } else if (strcmp(what, "kmem") == 0) {
+        ddb_guard(ddb_show_kmem_walk, NULL);
+    } else if (strcmp(what, "break") == 0) {
+        ddb_show_break();
+    } else {
+        kputs("usage: show fabric | map [task] | kmem | break\n");
+    }
+    return DDB_STAY;
+}
+
+static int ddb_cmd_break(ddb_cmd_args_t* a)
+{
+    uint64_t addr;
+    bool watch = strcmp(a->argv[0], "watch") == 0;
+    if (a->argc < 2 || !ddb_parse_addr(a->argv[1], &addr)) {
+        kputs(watch ? "usage: watch[/rw] addr [len]\n" : "usage: break addr\n");
+        return DDB_STAY;
+    }
+    uint32_t type = DBREG_EXEC;
+    uint64_t len = 1;
+    if (watch) {
+        type = (a->size == 'r') ? DBREG_RW : DBREG_WRITE;
+        len = 8;
+        if (a->argc > 2 && !ddb_parse_num(a->argv[2], 10, &len)) {
+            kputs("bad length\n");
+            return DDB_STAY;
+        }
+    }
+    for (uint32_t i = 0; i < DBREG_COUNT; i++) {
+        if (dbreg_get(i, NULL, NULL, NULL)) {
+            continue;
+        }
+        if (dbreg_set(i, addr, type, (uint32_t)len) != RDNX_OK) {
+            kputs("bad address/length (data watch needs len 1/2/4/8, aligned)\n");
+        } else {
+            kprintf("set %u\n", i);
+        }
+        return DDB_STAY;
+    }
+    kputs("all 4 debug registers are in use\n");
+    return DDB_STAY;
+}
+
+static int ddb_cmd_delete(ddb_cmd_args_t* a)
+{
+    uint64_t slot;
+    if (a->argc < 2 || !ddb_parse_num(a->argv[1], 10, &slot) ||
+        dbreg_clear((uint32_t)slot) != RDNX_OK) {
+        kputs("usage: delete slot (see show break)\n");
+    }
+    return DDB_STAY;
+}
+
+static int ddb_cmd_continue(ddb_cmd_args_t* a)
+{
+    (void)a;
+    if (ddb_frame && ddb_resumable) {
+        /* Do not re-hit an execute breakpoint at the resume address */
+        ddb_frame->rflags |= DBREG_RFLAGS_RF;
+        ddb_frame->rflags &= ~DBREG_RFLAGS_TF;
+    }
+    return DDB_LEAVE;
+}
+
+static int ddb_cmd_step(ddb_cmd_args_t* a)
+{
+    (void)a;
+    if (!ddb_frame || !ddb_resumable) {
+        kputs("nothing to step: not stopped at a resumable trap\n");
+        return DDB_STAY;
+    }
+    ddb_frame->rflags |= DBREG_RFLAGS_TF | DBREG_RFLAGS_RF;
+    return DDB_LEAVE;
+}
+
+static int ddb_cmd_help(ddb_cmd_args_t* a);
+
+static const struct {
+    const char* name;
+    int (*fn)(ddb_cmd_args_t* a);
+    const char* help;
+} ddb_cmds[] = {
+    { "help",     ddb_cmd_help,     "this list" },
+    { "continue", ddb_cmd_continue, "leave the debugger (also: c)" },
+    { "c",        ddb_cmd_continue, NULL },
+    { "step",     ddb_cmd_step,     "execute one instruction (also: s)" },
+    { "s",        ddb_cmd_step,     NULL },
+    { "bt",       ddb_cmd_bt,       "bt [tid] - backtrace of a thread" },
+    { "ps",       ddb_cmd_ps,       "tasks and threads" },
+    { "regs",     ddb_cmd_regs,     "registers of the stopped frame" },
+    { "x",        ddb_cmd_examine,  "x[/b|h|w|g] addr [count] - examine memory" },
+    { "w",        ddb_cmd_write,    "w[/b|h|w|g] addr value... - write memory" },
+    { "show",     ddb_cmd_show,     "show fabric | map [task] | kmem | break" },
+    { "break",    ddb_cmd_break,    "break addr - hardware execute breakpoint" },
+    { "watch",    ddb_cmd_break,    "watch[/rw] addr [len] - hardware data watchpoint" },
+    { "delete",   ddb_cmd_delete,   "delete slot - remove a breakpoint/watchpoint" },
+};
+
+static int ddb_cmd_help(ddb_cmd_args_t* a)
+{
+    (void)a;
+    for (size_t i = 0; i < sizeof(ddb_cmds) / sizeof(ddb_cmds[0]); i++) {
+        if (ddb_cmds[i].help) {
+            kprintf("  %s  %s\n", ddb_cmds[i].name, ddb_cmds[i].help);
+        }
+    }
+    kputs("  addresses: hex, symbol[+hexoff] or $reg; ids are decimal\n");
+    return DDB_STAY;
+}
+
+static void ddb_loop(void)
+{
+    char line[DDB_LINE_MAX];
+    ddb_cmd_args_t args;
+
+    for (;;) {
+        kputs("db> ");
+        ddb_readline(line, sizeof(line));
+        ddb_split(line, &args);
+        if (args.argc == 0) {
+            continue;
+        }
+        size_t i;
+        for (i = 0; i < sizeof(ddb_cmds) / sizeof(ddb_cmds[0]); i++) {
+            if (strcmp(args.argv[0], ddb_cmds[i].name) == 0) {
+                break;
+            }
+        }
+        if (i == sizeof(ddb_cmds) / sizeof(ddb_cmds[0])) {
+            kprintf("unknown command %s (try help)\n", args.argv[0]);
+            continue;
+        }
+        if (ddb_cmds[i].fn(&args) == DDB_LEAVE) {
+            return;
+        }
+    }
+}
+
+/* ---- Entry ---- */
+
+static void ddb_enter_common(const char* reason, interrupt_frame_t* frame, bool resumable)
+{
+    irql_t old_irql = set_irql(IRQL_HIGH);
+    int32_t self = (int32_t)cpu_get_id();
+
+    if (ddb_owner != self) {
+        int32_t expected = -1;
+        while (!__atomic_compare_exchange_n(&ddb_owner, &expected, self, false,
+                                            __ATOMIC_ACQUIRE, __ATOMIC_RELAXED)) {
+            expected = -1;
+            __asm__ volatile ("pause");
+        }
+    }
+    ddb_depth++;
+
+    interrupt_frame_t* outer_frame = ddb_frame;
+    bool outer_resumable = ddb_resumable;
+    ddb_frame = frame;
+    ddb_resumable = resumable && frame != NULL;
+
+    kprintf("\n[ddb] cpu%u: %s\n", (uint32_t)self, reason ? reason : "stopped");
+    if (frame) {
+        kputs("[ddb] at ");
+        ddb_hex(frame->rip, 16);
+        ddb_print_sym(frame->rip);
+        kputs((frame->cs & 3u) ? " (user)\n" : "\n");
+    }
+    if (!ddb_resumable && frame) {
+        kputs("[ddb] fatal trap: continue halts\n");
+    }
+
+    ddb_loop();
+
+    ddb_frame = outer_frame;
+    ddb_resumable = outer_resumable;
+    if (--ddb_depth == 0) {
+        __atomic_store_n(&ddb_owner, -1, __ATOMIC_RELEASE);
+    }
+    set_irql(old_irql);
+}
+
+void ddb_enter(const char* reason, interrupt_frame_t* frame)
+{
+    ddb_enter_common(reason, frame, true);
+}
+
+void ddb_panic(const char* msg, interrupt_frame_t* frame)
+{
+    if (!ddb_ready || !ddb_on_panic) {
+        return;
+    }
+    kputs("[ddb] type 'help' for commands\n");
+    ddb_enter_common(msg ? msg : "panic", frame, false);
+}
+
+static void ddb_trap_debug(interrupt_context_t* ctx)
+{
+    interrupt_frame_t* frame = (interrupt_frame_t*)ctx->arch_specific;
+    uint64_t dr6 = dbreg_take_status();
+
+    if (dr6 & DBREG_DR6_BS) {
+        frame->rflags &= ~DBREG_RFLAGS_TF;
+        ddb_enter("single step", frame);
+        return;
+    }
+    for (uint32_t i = 0; i < DBREG_COUNT; i++) {
+        if (dr6 & (DBREG_DR6_B0 << i)) {
+            static const char* const hit[DBREG_COUNT] = {
+                "breakpoint 0", "breakpoint 1", "breakpoint 2", "breakpoint 3"
+            };
+            ddb_enter(hit[i], frame);
+            return;
+        }
+    }
+    ddb_enter("debug trap", frame);
+}
+
+static void ddb_trap_breakpoint(interrupt_context_t* ctx)
+{
+    ddb_enter("int3", (interrupt_frame_t*)ctx->arch_specific);
+}
+
+void ddb_init(void)
+{
+    (void)interrupt_register(1, ddb_trap_debug);
+    (void)interrupt_register(3, ddb_trap_breakpoint);
+    if (console_serial_rx_irq_init() == RDNX_OK) {
+        kputs("[DDB] ready: Enter ~ Ctrl-B on COM1 breaks in\n");
+    } else {
+        kputs("[DDB] ready (no serial break-in)\n");
+    }
+    ddb_ready = true;
+}
@@ -0,0 +1,39 @@
+/**
+ * @file ddb.h
+ * @brief In-kernel debugger on the serial console
+ *
+ * A small ddb-style command loop driven by polled COM1 I/O. It is entered
+ * on panic, on Enter ~ Ctrl-B typed on the serial line, on int3 and
+ * hardware breakpoints / single-step traps, and from the kernel shell.
+ * While it runs the CPU sits at IRQL_HIGH with interrupts off; CPUs that
+ * need the IRQL giant wait, others keep running.
+ */
+
+#ifndef _RODNIX_COMMON_DDB_H
+#define _RODNIX_COMMON_DDB_H
+
+#include <stdbool.h>
+
+struct interrupt_frame;
+
+/**
+ * Hook #DB/#BP, switch COM1 input to its RX interrupt (break sequence)
+ * and read rdnx.ddb=0 (do not stop in the debugger on panic).
+ * Needs interrupts and the APIC/PIC to be set up.
+ */
+void ddb_init(void);
+
+/**
+ * Stop in the debugger until "continue" or "step".
+ * @param reason Shown in the banner
+ * @param frame Interrupted context (resumable), or NULL to debug the caller
+ */
+void ddb_enter(const char* reason, struct interrupt_frame* frame);
+
+/**
+ * Entry from panic()/fatal traps: enters unless disabled with rdnx.ddb=0.
+ * Returning from it means the caller halts.
+ */
+void ddb_panic(const char* msg, struct interrupt_frame* frame);
+
+#endif /* _RODNIX_COMMON_DDB_H */
@@ -7,9 +7,14 @@
 #include "../../include/console.h"
 #include "../../include/common.h"
 #include "../core/task.h"
+#include "../core/backtrace.h"
+#include "crashdump.h"
+#include "ddb.h"
+#include "gdbstub.h"
 
 #define PANIC_EVENT_MAX 16
 #define PANIC_EVENT_LEN 80
+#define PANIC_MSG_MAX   160
 
 static char panic_events[PANIC_EVENT_MAX][PANIC_EVENT_LEN];
 static uint32_t panic_event_head = 0;
@@ -53,6 +58,8 @@ static void panic_dump_state(void)
             (unsigned long long)cr3,
             (unsigned long long)cr4);
 
+    backtrace_print(NULL);
+
     task_t* task = task_get_current();
     thread_t* thread = thread_get_current();
     if (task || thread) {
@@ -84,6 +91,9 @@ __attribute__((noreturn)) void panic(const char* msg)
         kputs("\n");
     }
     panic_dump_state();
+    crashdump_write(msg, NULL);
+    gdbstub_fatal(msg, NULL);
+    ddb_panic(msg, NULL);
     kputs("System halted.\n");
     
     __asm__ volatile ("cli; hlt");
@@ -99,9 +109,17 @@ __attribute__((noreturn)) void panicf(const char* fmt, ...)
     
     kputs("\n\n*** KERNEL PANIC ***\n");
     kputs("Message: ");
+    /* No vsnprintf in the kernel: take the formatted text back from the log */
+    uint64_t msg_start = console_log_position();
     kvprintf(fmt, args);
+    static char msg[PANIC_MSG_MAX];
+    size_t msg_len = console_log_read(msg_start, msg, sizeof(msg) - 1);
+    msg[msg_len] = '\0';
     kputs("\n");
     panic_dump_state();
+    crashdump_write(msg, NULL);
+    gdbstub_fatal(msg, NULL);
+    ddb_panic(msg, NULL);
     kputs("System halted.\n");
     
     va_end(args);
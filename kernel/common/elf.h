@@ -24,9 +24,12 @@
 #define SHF_ALLOC 0x2
 
 #define ET_EXEC 2
+#define ET_DYN 3
 #define EM_X86_64 62
 
 #define PT_LOAD 1
+#define PT_INTERP 3
+#define PT_PHDR 6
 
 #define PF_X 0x1
 #define PF_W 0x2
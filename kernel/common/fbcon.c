@@ -0,0 +1,249 @@
+/**
+ * @file fbcon.c
+ * @brief Framebuffer text console backend
+ *
+ * Keeps a shadow copy of the character grid (char + VGA attribute, same
+ * layout as VGA text memory) so scrolling only repaints cells whose
+ * content actually changed and never reads back from the framebuffer,
+ * which is mapped uncached and slow to read.
+ */
+
+#include "fbcon.h"
+#include "../core/boot.h"
+#include "../fabric/fabric.h"
+#include "../../include/error.h"
+#include <stddef.h>
+
+#define FBCON_CURSOR_FIRST_LINE 14
+
+/* Standard 16-color VGA palette, 0xRRGGBB */
+static const uint32_t fbcon_vga_rgb[16] = {
+    0x000000, 0x0000AA, 0x00AA00, 0x00AAAA,
+    0xAA0000, 0xAA00AA, 0xAA5500, 0xAAAAAA,
+    0x555555, 0x5555FF, 0x55FF55, 0x55FFFF,
+    0xFF5555, 0xFF55FF, 0xFFFF55, 0xFFFFFF
+};
+
+/* Drawn for characters the font does not cover */
+static const uint8_t fbcon_glyph_unknown[FBCON_FONT_HEIGHT] = {
+    0x00, 0x00, 0x00, 0x7E, 0x42, 0x42, 0x42, 0x42,
+    0x42, 0x42, 0x42, 0x42, 0x7E, 0x00, 0x00, 0x00
+};
+
+/*
+ * LOCKING: none
+ *   Shares the console's serialization: only console.c calls in here.
+ */
+static struct {
+    volatile uint8_t* base;
+    uint32_t pitch;
+    uint32_t bytes_pp;
+    uint32_t cols;
+    uint32_t rows;
+    uint32_t palette[16];
+    uint32_t cursor_row;
+    uint32_t cursor_col;
+    bool cursor_shown;
+    bool active;
+} fb;
+
+static uint16_t fb_cells[FBCON_MAX_ROWS * FBCON_MAX_COLS];
+
+static uint32_t fb_pack(uint32_t c, uint8_t pos, uint8_t size)
+{
+    if (size == 0) {
+        return 0;
+    }
+    return (c >> (8u - size)) << pos;
+}
+
+static uint32_t fb_color(uint32_t rgb, const boot_info_t* bi)
+{
+    return fb_pack((rgb >> 16) & 0xFFu, bi->fb_red_pos, bi->fb_red_size) |
+           fb_pack((rgb >> 8) & 0xFFu, bi->fb_green_pos, bi->fb_green_size) |
+           fb_pack(rgb & 0xFFu, bi->fb_blue_pos, bi->fb_blue_size);
+}
+
+static inline void fb_store(volatile uint8_t* p, uint32_t px)
+{
+    switch (fb.bytes_pp) {
+        case 4:
+            *(volatile uint32_t*)p = px;
+            break;
+        case 3:
+            p[0] = (uint8_t)px;
+            p[1] = (uint8_t)(px >> 8);
+            p[2] = (uint8_t)(px >> 16);
+            break;
+        default:
+            *(volatile uint16_t*)p = (uint16_t)px;
+            break;
+    }
+}
+
+static const uint8_t* fb_glyph(uint8_t ch)
+{
+    if (ch < FBCON_FONT_FIRST || ch >= FBCON_FONT_FIRST + FBCON_FONT_GLYPHS) {
+        return fbcon_glyph_unknown;
+    }
+    return fbcon_font8x16[ch - FBCON_FONT_FIRST];
+}
+
+static void fb_draw_cell(uint32_t row, uint32_t col, bool cursor)
+{
+    uint16_t cell = fb_cells[row * fb.cols + col];
+    uint8_t attr = (uint8_t)(cell >> 8);
+    const uint8_t* glyph = fb_glyph((uint8_t)cell);
+    uint32_t fg = fb.palette[attr & 0x0Fu];
+    uint32_t bg = fb.palette[(attr >> 4) & 0x0Fu];
+
+    volatile uint8_t* line = fb.base +
+                             (size_t)row * FBCON_FONT_HEIGHT * fb.pitch +
+                             (size_t)col * FBCON_FONT_WIDTH * fb.bytes_pp;
+    for (uint32_t y = 0; y < FBCON_FONT_HEIGHT; y++) {
+        uint8_t bits = glyph[y];
+        if (cursor && y >= FBCON_CURSOR_FIRST_LINE) {
+            bits = 0xFF;
+        }
+        volatile uint8_t* p = line;
+        for (uint32_t x = 0; x < FBCON_FONT_WIDTH; x++) {
+            fb_store(p, (bits & (0x80u >> x)) ? fg : bg);
+            p += fb.bytes_pp;
+        }
+        line += fb.pitch;
+    }
+}
+
+static void fb_hide_cursor(void)
+{
+    if (fb.cursor_shown) {
+        fb.cursor_shown = false;
+        fb_draw_cell(fb.cursor_row, fb.cursor_col, false);
+    }
+}
+
+int fbcon_init(void)
+{
+    boot_info_t* bi = boot_get_info();
+    if (!bi || bi->fb_addr == 0 || bi->fb_type != BOOT_FB_TYPE_RGB) {
+        return RDNX_E_NOTFOUND;
+    }
+    if (bi->fb_bpp != 16 && bi->fb_bpp != 24 && bi->fb_bpp != 32) {
+        return RDNX_E_UNSUPPORTED;
+    }
+    if (bi->fb_red_size > 8 || bi->fb_green_size > 8 || bi->fb_blue_size > 8) {
+        return RDNX_E_UNSUPPORTED;
+    }
+
+    uint32_t bytes_pp = bi->fb_bpp / 8u;
+    if (bi->fb_width < FBCON_FONT_WIDTH || bi->fb_height < FBCON_FONT_HEIGHT ||
+        bi->fb_pitch < bi->fb_width * bytes_pp) {
+        return RDNX_E_INVALID;
+    }
+
+    void* base = fabric_mmio_map(bi->fb_addr, (uint64_t)bi->fb_pitch * bi->fb_height);
+    if (!base) {
+        return RDNX_E_NOMEM;
+    }
+
+    fb.base = (volatile uint8_t*)base;
+    fb.pitch = bi->fb_pitch;
+    fb.bytes_pp = bytes_pp;
+    fb.cols = bi->fb_width / FBCON_FONT_WIDTH;
+    fb.rows = bi->fb_height / FBCON_FONT_HEIGHT;
+    if (fb.cols > FBCON_MAX_COLS) {
+        fb.cols = FBCON_MAX_COLS;
+    }
+    if (fb.rows > FBCON_MAX_ROWS) {
+        fb.rows = FBCON_MAX_ROWS;
+    }
+    for (uint32_t i = 0; i < 16; i++) {
+        fb.palette[i] = fb_color(fbcon_vga_rgb[i], bi);
+    }
+    fb.cursor_row = 0;
+    fb.cursor_col = 0;
+    fb.cursor_shown = false;
+    fb.active = true;
+    return RDNX_OK;
+}
+
+bool fbcon_is_active(void)
+{
+    return fb.active;
+}
+
+uint32_t fbcon_cols(void)
+{
+    return fb.cols;
+}
+
+uint32_t fbcon_rows(void)
+{
+    return fb.rows;
+}
+
+void fbcon_put(uint32_t row, uint32_t col, uint8_t ch, uint8_t attr)
+{
+    if (!fb.active || row >= fb.rows || col >= fb.cols) {
+        return;
+    }
+    fb_cells[row * fb.cols + col] = (uint16_t)ch | ((uint16_t)attr << 8);
+    fb_draw_cell(row, col, fb.cursor_shown &&
+                           row == fb.cursor_row && col == fb.cursor_col);
+}
+
+void fbcon_scroll(uint8_t attr)
+{
+    if (!fb.active) {
+        return;
+    }
+    fb_hide_cursor();
+
+    uint16_t blank = (uint16_t)' ' | ((uint16_t)attr << 8);
+    for (uint32_t row = 0; row < fb.rows; row++) {
+        for (uint32_t col = 0; col < fb.cols; col++) {
+            uint16_t next = (row + 1 < fb.rows) ? fb_cells[(row + 1) * fb.cols + col] : blank;
+            uint16_t* cell = &fb_cells[row * fb.cols + col];
+            if (*cell != next) {
+                *cell = next;
+                fb_draw_cell(row, col, false);
+            }
+        }
+    }
+}
+
+void fbcon_clear(uint8_t attr)
+{
+    if (!fb.active) {
+        return;
+    }
+    uint16_t blank = (uint16_t)' ' | ((uint16_t)attr << 8);
+    for (uint32_t row = 0; row < fb.rows; row++) {
+        for (uint32_t col = 0; col < fb.cols; col++) {
+            fb_cells[row * fb.cols + col] = blank;
+            fb_draw_cell(row, col, false);
+        }
+    }
+    fb.cursor_shown = false;
+}
+
+void fbcon_set_cursor(uint32_t row, uint32_t col)
+{
+    if (!fb.active) {
+        return;
+    }
+    if (row >= fb.rows) {
+        row = fb.rows - 1;
+    }
+    if (col >= fb.cols) {
+        col = fb.cols - 1;
+    }
+    if (fb.cursor_shown && row == fb.cursor_row && col == fb.cursor_col) {
+        return;
+    }
+    fb_hide_cursor();
+    fb.cursor_row = row;
+    fb.cursor_col = col;
+    fb.cursor_shown = true;
+    fb_draw_cell(row, col, true);
+}
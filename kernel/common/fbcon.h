@@ -0,0 +1,46 @@
+/**
+ * @file fbcon.h
+ * @brief Framebuffer text console backend
+ *
+ * Renders the console character grid into a linear RGB framebuffer
+ * handed over by the bootloader (Multiboot2 framebuffer tag). The
+ * character/attribute logic stays in console.c; this backend only
+ * draws cells, scrolls and shows the cursor.
+ */
+
+#ifndef _RODNIX_COMMON_FBCON_H
+#define _RODNIX_COMMON_FBCON_H
+
+#include <stdint.h>
+#include <stdbool.h>
+
+#define FBCON_FONT_WIDTH  8
+#define FBCON_FONT_HEIGHT 16
+#define FBCON_FONT_FIRST  0x20
+#define FBCON_FONT_GLYPHS 95
+
+/* Upper bound of the character grid (shadow buffer size) */
+#define FBCON_MAX_COLS 320
+#define FBCON_MAX_ROWS 128
+
+extern const uint8_t fbcon_font8x16[FBCON_FONT_GLYPHS][FBCON_FONT_HEIGHT];
+
+/**
+ * Map the boot framebuffer and switch the backend on.
+ * Requires paging/physmap to be up (uses the MMIO window).
+ * @return RDNX_OK, RDNX_E_NOTFOUND if the loader gave no usable
+ *         RGB framebuffer, or another RDNX_E_* on mapping failure
+ */
+int fbcon_init(void);
+
+bool fbcon_is_active(void);
+uint32_t fbcon_cols(void);
+uint32_t fbcon_rows(void);
+
+/* attr is a VGA text attribute byte: low nibble fg, high nibble bg */
+void fbcon_put(uint32_t row, uint32_t col, uint8_t ch, uint8_t attr);
+void fbcon_scroll(uint8_t attr);
+void fbcon_clear(uint8_t attr);
+void fbcon_set_cursor(uint32_t row, uint32_t col);
+
+#endif /* _RODNIX_COMMON_FBCON_H */
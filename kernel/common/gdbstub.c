@@ -0,0 +1,906 @@
+/**
+ * @file gdbstub.c
+ * @brief GDB remote serial protocol stub on COM2
+ *
+ * Host side: qemu ... -serial stdio -serial tcp::1234,server,nowait
+ *            gdb build/x86_64/rodnix.kernel -ex 'target remote :1234'
+ *
+ * Supported packets: ? g G P m M c s D k H T qC qfThreadInfo qsThreadInfo
+ * qThreadExtraInfo qSupported qAttached qOffsets QStartNoAckMode Z0-Z2 Z4
+ * and z0-z2 z4. Software breakpoints are int3 bytes written through
+ * memprobe; hardware ones and watchpoints use DR0-DR3 (shared with ddb).
+ */
+
+#include "gdbstub.h"
+#include "bootparam.h"
+#include "ksyms.h"
+#include "memprobe.h"
+#include "../core/cpu.h"
+#include "../core/interrupts.h"
+#include "../core/task.h"
+#include "../arch/interrupt_frame.h"
+#include "../arch/dbreg.h"
+#include "../../include/console.h"
+#include "../../include/common.h"
+#include "../../include/error.h"
+#include <stddef.h>
+
+#define GDB_PKT_MAX     4096
+#define GDB_SW_BP_MAX   32
+#define GDB_INT3        0xCC
+#define GDB_TLIST_CHUNK 64
+
+/* Signals in stop replies */
+#define GDB_SIGINT  2
+#define GDB_SIGILL  4
+#define GDB_SIGTRAP 5
+#define GDB_SIGABRT 6
+#define GDB_SIGFPE  8
+#define GDB_SIGSEGV 11
+
+/* amd64 'g' packet: 17 x 8-byte registers, then eflags cs ss ds es fs gs */
+#define GDB_NREGS   24
+#define GDB_REG_RIP 16
+
+typedef struct {
+    uint64_t addr;
+    uint8_t saved;
+    bool used;
+} gdb_sw_bp_t;
+
+BOOTPARAM_STRING(gdb_mode, "rdnx.gdb", "0", "GDB stub on COM2: 0, 1 or wait");
+
+/*
+ * LOCKING: gdb_owner
+ *   The stub runs on one CPU at a time with interrupts off; a CPU that
+ *   traps while another one talks to gdb spins until it is released.
+ *   Everything below is touched only by the owner.
+ */
+static volatile int32_t gdb_owner = -1;
+static bool gdb_enabled = false;
+static bool gdb_no_ack = false;
+static int gdb_pushback = -1;
+/* A gdb is known to listen: stop replies may be sent unasked */
+static bool gdb_attached = false;
+static const char* gdb_stop_msg = NULL;
+
+static interrupt_frame_t* gdb_frame = NULL;
+static thread_t* gdb_cur = NULL;
+static uint64_t gdb_reg_tid = 0;      /* Hg selection, 0 = stopped thread */
+static uint32_t gdb_tlist_pos = 0;
+static char gdb_stop[64];
+
+static gdb_sw_bp_t gdb_sw_bps[GDB_SW_BP_MAX];
+
+static char gdb_in[GDB_PKT_MAX];
+static char gdb_out[GDB_PKT_MAX];
+
+static const char gdb_hexdig[] = "0123456789abcdef";
+
+/* Offsets of GDB register numbers 0..23 in the interrupt frame */
+static const uint16_t gdb_reg_off[GDB_NREGS] = {
+    offsetof(interrupt_frame_t, rax), offsetof(interrupt_frame_t, rbx),
+    offsetof(interrupt_frame_t, rcx), offsetof(interrupt_frame_t, rdx),
+    offsetof(interrupt_frame_t, rsi), offsetof(interrupt_frame_t, rdi),
+    offsetof(interrupt_frame_t, rbp), offsetof(interrupt_frame_t, rsp),
+    offsetof(interrupt_frame_t, r8),  offsetof(interrupt_frame_t, r9),
+    offsetof(interrupt_frame_t, r10), offsetof(interrupt_frame_t, r11),
+    offsetof(interrupt_frame_t, r12), offsetof(interrupt_frame_t, r13),
+    offsetof(interrupt_frame_t, r14), offsetof(interrupt_frame_t, r15),
+    offsetof(interrupt_frame_t, rip), offsetof(interrupt_frame_t, rflags),
+    offsetof(interrupt_frame_t, cs),  offsetof(interrupt_frame_t, ss),
+    offsetof(interrupt_frame_t, ds),  offsetof(interrupt_frame_t, es),
+    offsetof(interrupt_frame_t, fs),  offsetof(interrupt_frame_t, gs),
+};
+
+static uint32_t gdb_reg_size(uint32_t regno)
+{
+    return regno <= GDB_REG_RIP ? 8u : 4u;
+}
+
+/* ---- Hex helpers ---- */
+
+static int gdb_hexval(char c)
+{
+    if (c >= '0' && c <= '9') {
+        return c - '0';
+    }
+    if (c >= 'a' && c <= 'f') {
+        return c - 'a' + 10;
+    }
+    if (c >= 'A' && c <= 'F') {
+        return c - 'A' + 10;
+    }
+    return -1;
+}
+
+/* Parse hex up to a non-hex char; *pp is left on it */
+static uint64_t gdb_parse_hex(const char** pp)
+{
+    const char* p = *pp;
+    uint64_t v = 0;
+    int d;
+    while ((d = gdb_hexval(*p)) >= 0) {
+        v = (v << 4) | (uint64_t)d;
+        p++;
+    }
+    *pp = p;
+    return v;
+}
+
+/* Little-endian value as target-order hex bytes */
+static char* gdb_put_le(char* out, uint64_t v, uint32_t size)
+{
+    for (uint32_t i = 0; i < size; i++) {
+        uint8_t b = (uint8_t)(v >> (i * 8));
+        *out++ = gdb_hexdig[b >> 4];
+        *out++ = gdb_hexdig[b & 0xF];
+    }
+    return out;
+}
+
+static bool gdb_get_le(const char** pp, uint32_t size, uint64_t* v)
+{
+    const char* p = *pp;
+    uint64_t r = 0;
+    for (uint32_t i = 0; i < size; i++) {
+        int hi = gdb_hexval(p[0]);
+        int lo = gdb_hexval(hi >= 0 ? p[1] : '\0');
+        if (hi < 0 || lo < 0) {
+            return false;
+        }
+        r |= (uint64_t)((hi << 4) | lo) << (i * 8);
+        p += 2;
+    }
+    *pp = p;
+    *v = r;
+    return true;
+}
+
+static char* gdb_put_num(char* out, uint64_t v)
+{
+    char tmp[16];
+    int n = 0;
+    do {
+        tmp[n++] = gdb_hexdig[v & 0xF];
+        v >>= 4;
+    } while (v);
+    while (n) {
+        *out++ = tmp[--n];
+    }
+    return out;
+}
+
+static char* gdb_put_str(char* out, const char* s)
+{
+    while (*s) {
+        *out++ = *s++;
+    }
+    return out;
+}
+
+/* ---- Packet I/O ---- */
+
+static int gdb_getc(void)
+{
+    if (gdb_pushback >= 0) {
+        int c = gdb_pushback;
+        gdb_pushback = -1;
+        return c;
+    }
+    for (;;) {
+        int c = console_serial2_getc();
+        if (c >= 0) {
+            return c;
+        }
+        __asm__ volatile ("pause");
+    }
+}
+
+/* Next well-formed packet body into gdb_in (NUL-terminated) */
+static void gdb_recv(void)
+{
+    for (;;) {
+        int c;
+        while ((c = gdb_getc()) != '$') {
+            /* Stray acks and Ctrl-C while already stopped */
+        }
+        uint32_t len = 0;
+        uint8_t sum = 0;
+        bool overflow = false;
+        while ((c = gdb_getc()) != '#') {
+            if (c == '$') {
+                len = 0;
+                sum = 0;
+                overflow = false;
+                continue;
+            }
+            sum = (uint8_t)(sum + (uint8_t)c);
+            if (len + 1 < GDB_PKT_MAX) {
+                gdb_in[len++] = (char)c;
+            } else {
+                overflow = true;
+            }
+        }
+        int hi = gdb_hexval((char)gdb_getc());
+        int lo = gdb_hexval((char)gdb_getc());
+        gdb_in[len] = '\0';
+        if (gdb_no_ack) {
+            return;
+        }
+        if (!overflow && hi >= 0 && lo >= 0 && (uint8_t)((hi << 4) | lo) == sum) {
+            console_serial2_putc('+');
+            return;
+        }
+        console_serial2_putc('-');
+    }
+}
+
+static void gdb_send(const char* body, size_t len)
+{
+    for (;;) {
+        uint8_t sum = 0;
+        console_serial2_putc('$');
+        for (size_t i = 0; i < len; i++) {
+            console_serial2_putc((uint8_t)body[i]);
+            sum = (uint8_t)(sum + (uint8_t)body[i]);
+        }
+        console_serial2_putc('#');
+        console_serial2_putc((uint8_t)gdb_hexdig[sum >> 4]);
+        console_serial2_putc((uint8_t)gdb_hexdig[sum & 0xF]);
+        if (gdb_no_ack) {
+            return;
+        }
+        int c;
+        while ((c = gdb_getc()) != '+' && c != '-') {
+        }
+        if (c == '+') {
+            return;
+        }
+    }
+}
+
+static void gdb_send_str(const char* s)
+{
+    gdb_send(s, strlen(s));
+}
+
+/* Console output packet, shown by gdb as-is */
+static void gdb_send_console(const char* msg)
+{
+    char* o = gdb_out;
+    *o++ = 'O';
+    while (*msg && o + 4 < gdb_out + GDB_PKT_MAX) {
+        o = gdb_put_le(o, (uint8_t)*msg++, 1);
+    }
+    o = gdb_put_le(o, '\n', 1);
+    gdb_send(gdb_out, (size_t)(o - gdb_out));
+}
+
+/* ---- Threads ---- */
+
+static thread_t* gdb_find_thread(uint64_t tid)
+{
+    for (task_t* task = task_debug_next(NULL); task; task = task_debug_next(task)) {
+        thread_t* th;
+        TAILQ_FOREACH(th, &task->threads, task_link) {
+            if (th->thread_id == tid) {
+                return th;
+            }
+        }
+    }
+    return NULL;
+}
+
+/*
+ * Registers of a thread: the trap frame for the stopped one, the frame
+ * saved at its stack pointer for a switched-out one, none if it is
+ * running on another CPU right now.
+ */
+static interrupt_frame_t* gdb_thread_frame(uint64_t tid)
+{
+    if (tid == 0 || (gdb_cur && gdb_cur->thread_id == tid)) {
+        return gdb_frame;
+    }
+    thread_t* th = gdb_find_thread(tid);
+    if (!th || th->sched_on_cpu || !th->context.stack_pointer) {
+        return NULL;
+    }
+    return (interrupt_frame_t*)(uintptr_t)th->context.stack_pointer;
+}
+
+static bool gdb_frame_reg(interrupt_frame_t* f, uint32_t regno, uint64_t* v, bool write)
+{
+    uint64_t addr = (uint64_t)(uintptr_t)f + gdb_reg_off[regno];
+    if (f == gdb_frame) {
+        if (write) {
+            *(uint64_t*)(uintptr_t)addr = *v;
+        } else {
+            *v = *(uint64_t*)(uintptr_t)addr;
+        }
+        return true;
+    }
+    /* Saved frames of other threads may be garbage: probe them */
+    return memprobe_copy(addr, v, 8, write);
+}
+
+static void gdb_cmd_read_regs(void)
+{
+    interrupt_frame_t* f = gdb_thread_frame(gdb_reg_tid);
+    char* o = gdb_out;
+    for (uint32_t i = 0; i < GDB_NREGS; i++) {
+        uint32_t size = gdb_reg_size(i);
+        uint64_t v;
+        if (f && gdb_frame_reg(f, i, &v, false)) {
+            o = gdb_put_le(o, v, size);
+        } else {
+            for (uint32_t k = 0; k < size * 2; k++) {
+                *o++ = 'x';
+            }
+        }
+    }
+    gdb_send(gdb_out, (size_t)(o - gdb_out));
+}
+
+static void gdb_cmd_write_regs(const char* p)
+{
+    interrupt_frame_t* f = gdb_thread_frame(gdb_reg_tid);
+    if (!f) {
+        gdb_send_str("E01");
+        return;
+    }
+    for (uint32_t i = 0; i < GDB_NREGS && *p; i++) {
+        uint64_t v;
+        uint32_t size = gdb_reg_size(i);
+        if (p[0] == 'x') {
+            p += size * 2;
+            continue;
+        }
+        if (!gdb_get_le(&p, size, &v)) {
+            gdb_send_str("E02");
+            return;
+        }
+        if (size == 4) {
+            uint64_t old = 0;
+            (void)gdb_frame_reg(f, i, &old, false);
+            v |= old & 0xFFFFFFFF00000000ULL;
+        }
+        if (!gdb_frame_reg(f, i, &v, true)) {
+            gdb_send_str("E14");
+            return;
+        }
+    }
+    gdb_send_str("OK");
+}
+
+static void gdb_cmd_write_reg(const char* p)
+{
+    uint64_t regno = gdb_parse_hex(&p);
+    interrupt_frame_t* f = gdb_thread_frame(gdb_reg_tid);
+    uint64_t v;
+    if (*p++ != '=' || regno >= GDB_NREGS || !f ||
+        !gdb_get_le(&p, gdb_reg_size((uint32_t)regno), &v) ||
+        !gdb_frame_reg(f, (uint32_t)regno, &v, true)) {
+        gdb_send_str("E01");
+        return;
+    }
+    gdb_send_str("OK");
+}
+
+static void gdb_cmd_thread_list(bool first)
+{
+    if (first) {
+        gdb_tlist_pos = 0;
+    }
+    char* o = gdb_out;
+    uint32_t pos = 0;
+    uint32_t emitted = 0;
+    for (task_t* task = task_debug_next(NULL); task; task = task_debug_next(task)) {
+        thread_t* th;
+        TAILQ_FOREACH(th, &task->threads, task_link) {
+            if (pos++ < gdb_tlist_pos || emitted == GDB_TLIST_CHUNK) {
+                continue;
+            }
+            *o++ = emitted ? ',' : 'm';
+            o = gdb_put_num(o, th->thread_id);
+            emitted++;
+        }
+    }
+    gdb_tlist_pos += emitted;
+    if (!emitted) {
+        gdb_send_str("l");
+        return;
+    }
+    gdb_send(gdb_out, (size_t)(o - gdb_out));
+}
+
+static void gdb_cmd_thread_info(uint64_t tid)
+{
+    static const char* const states[] = {
+        "new", "ready", "running", "blocked", "sleeping", "dead"
+    };
+    thread_t* th = gdb_find_thread(tid);
+    if (!th) {
+        gdb_send_str("E01");
+        return;
+    }
+    char text[128];
+    char* t = text;
+    t = gdb_put_str(t, "task 0x");
+    t = gdb_put_num(t, th->task ? th->task->task_id : 0);
+    *t++ = ' ';
+    t = gdb_put_str(t, (uint32_t)th->state < 6 ? states[th->state] : "?");
+    if (th->sched_on_cpu) {
+        t = gdb_put_str(t, " on-cpu");
+    }
+    uint64_t off;
+    const char* sym = th->entry ? ksyms_lookup((uint64_t)(uintptr_t)th->entry, &off) : NULL;
+    if (sym && strlen(sym) < 64) {
+        *t++ = ' ';
+        t = gdb_put_str(t, sym);
+    }
+    *t = '\0';
+
+    char* o = gdb_out;
+    for (t = text; *t; t++) {
+        o = gdb_put_le(o, (uint8_t)*t, 1);
+    }
+    gdb_send(gdb_out, (size_t)(o - gdb_out));
+}
+
+/* ---- Memory and breakpoints ---- */
+
+/* "addr,len" of m/M/Z packets; *pp is left after len */
+static bool gdb_parse_addr_len(const char** pp, uint64_t* addr, uint64_t* len)
+{
+    *addr = gdb_parse_hex(pp);
+    if (**pp != ',') {
+        return false;
+    }
+    (*pp)++;
+    *len = gdb_parse_hex(pp);
+    return true;
+}
+
+static void gdb_cmd_read_mem(const char* p)
+{
+    uint64_t addr;
+    uint64_t len;
+    uint8_t buf[256];
+    char* o = gdb_out;
+
+    if (!gdb_parse_addr_len(&p, &addr, &len)) {
+        gdb_send_str("E01");
+        return;
+    }
+    if (len > (GDB_PKT_MAX - 1) / 2) {
+        len = (GDB_PKT_MAX - 1) / 2;
+    }
+    while (len) {
+        uint32_t n = len > sizeof(buf) ? (uint32_t)sizeof(buf) : (uint32_t)len;
+        if (!memprobe_copy(addr, buf, n, false)) {
+            if (o == gdb_out) {
+                gdb_send_str("E14");
+                return;
+            }
+            break;
+        }
+        for (uint32_t i = 0; i < n; i++) {
+            o = gdb_put_le(o, buf[i], 1);
+        }
+        addr += n;
+        len -= n;
+    }
+    gdb_send(gdb_out, (size_t)(o - gdb_out));
+}
+
+static void gdb_cmd_write_mem(const char* p)
+{
+    uint64_t addr;
+    uint64_t len;
+    if (!gdb_parse_addr_len(&p, &addr, &len) || *p++ != ':') {
+        gdb_send_str("E01");
+        return;
+    }
+    for (uint64_t i = 0; i < len; i++, addr++) {
+        uint64_t b;
+        if (!gdb_get_le(&p, 1, &b)) {
+            gdb_send_str("E02");
+            return;
+        }
+        uint8_t byte = (uint8_t)b;
+        if (!memprobe_copy(addr, &byte, 1, true)) {
+            gdb_send_str("E14");
+            return;
+        }
+    }
+    gdb_send_str("OK");
+}
+
+static int gdb_sw_bp_set(uint64_t addr)
+{
+    int free_slot = -1;
+    for (int i = 0; i < GDB_SW_BP_MAX; i++) {
+        if (gdb_sw_bps[i].used && gdb_sw_bps[i].addr == addr) {
+            return RDNX_OK;
+        }
+        if (!gdb_sw_bps[i].used && free_slot < 0) {
+            free_slot = i;
+        }
+    }
+    if (free_slot < 0) {
+        return RDNX_E_BUSY;
+    }
+    uint8_t saved;
+    uint8_t int3 = GDB_INT3;
+    if (!memprobe_copy(addr, &saved, 1, false) || !memprobe_copy(addr, &int3, 1, true)) {
+        return RDNX_E_INVALID;
+    }
+    gdb_sw_bps[free_slot].addr = addr;
+    gdb_sw_bps[free_slot].saved = saved;
+    gdb_sw_bps[free_slot].used = true;
+    return RDNX_OK;
+}
+
+static int gdb_sw_bp_clear(uint64_t addr)
+{
+    for (int i = 0; i < GDB_SW_BP_MAX; i++) {
+        if (gdb_sw_bps[i].used && gdb_sw_bps[i].addr == addr) {
+            gdb_sw_bps[i].used = false;
+            return memprobe_copy(addr, &gdb_sw_bps[i].saved, 1, true) ? RDNX_OK : RDNX_E_INVALID;
+        }
+    }
+    return RDNX_E_NOTFOUND;
+}
+
+static bool gdb_sw_bp_at(uint64_t addr)
+{
+    for (int i = 0; i < GDB_SW_BP_MAX; i++) {
+        if (gdb_sw_bps[i].used && gdb_sw_bps[i].addr == addr) {
+            return true;
+        }
+    }
+    return false;
+}
+
+static void gdb_sw_bp_clear_all(void)
+{
+    for (int i = 0; i < GDB_SW_BP_MAX; i++) {
+        if (gdb_sw_bps[i].used) {
+            (void)gdb_sw_bp_clear(gdb_sw_bps[i].addr);
+        }
+    }
+}
+
+static int gdb_hw_bp(uint32_t type, uint64_t addr, uint32_t len, bool set)
+{
+    for (uint32_t i = 0; i < DBREG_COUNT; i++) {
+        uint64_t a;
+        uint32_t t;
+        uint32_t l;
+        bool used = dbreg_get(i, &a, &t, &l);
+        if (!set && used && a == addr && t == type) {
+            return dbreg_clear(i);
+        }
+        if (set && !used) {
+            return dbreg_set(i, addr, type, len);
+        }
+    }
+    return set ? RDNX_E_BUSY : RDNX_E_NOTFOUND;
+}
+
+/* Z/z: 0 software, 1 hardware, 2 write watch, 4 access watch */
+static void gdb_cmd_breakpoint(const char* p, bool set)
+{
+    char kind = *p++;
+    uint64_t addr;
+    uint64_t len;
+    if (*p++ != ',' || !gdb_parse_addr_len(&p, &addr, &len)) {
+        gdb_send_str("E01");
+        return;
+    }
+    int rc;
+    switch (kind) {
+        case '0':
+            rc = set ? gdb_sw_bp_set(addr) : gdb_sw_bp_clear(addr);
+            break;
+        case '1':
+            rc = gdb_hw_bp(DBREG_EXEC, addr, 1, set);
+            break;
+        case '2':
+            rc = gdb_hw_bp(DBREG_WRITE, addr, (uint32_t)len, set);
+            break;
+        case '4':
+            rc = gdb_hw_bp(DBREG_RW, addr, (uint32_t)len, set);
+            break;
+        default:
+            /* Read-only watchpoints do not exist on x86 */
+            gdb_send_str("");
+            return;
+    }
+    gdb_send_str(rc == RDNX_OK ? "OK" : "E01");
+}
+
+/* ---- Stop/resume ---- */
+
+static void gdb_make_stop(uint32_t sig, const char* extra)
+{
+    char* o = gdb_stop;
+    *o++ = 'T';
+    o = gdb_put_le(o, sig, 1);
+    if (gdb_cur) {
+        o = gdb_put_str(o, "thread:");
+        o = gdb_put_num(o, gdb_cur->thread_id);
+        *o++ = ';';
+    }
+    if (extra) {
+        o = gdb_put_str(o, extra);
+    }
+    *o = '\0';
+}
+
+/*
+ * Serve gdb until it resumes or detaches. A fatal trap is not resumable:
+ * c/s are refused there and only a detach lets the caller halt.
+ */
+static void gdb_loop(bool resumable)
+{
+    if (gdb_attached) {
+        if (gdb_stop_msg) {
+            gdb_send_console(gdb_stop_msg);
+            gdb_stop_msg = NULL;
+        }
+        gdb_send_str(gdb_stop);
+    }
+    for (;;) {
+        gdb_recv();
+        gdb_attached = true;
+        const char* p = gdb_in;
+        char cmd = *p++;
+        switch (cmd) {
+            case '?':
+                if (gdb_stop_msg) {
+                    gdb_send_console(gdb_stop_msg);
+                    gdb_stop_msg = NULL;
+                }
+                gdb_send_str(gdb_stop);
+                break;
+            case 'g':
+                gdb_cmd_read_regs();
+                break;
+            case 'G':
+                gdb_cmd_write_regs(p);
+                break;
+            case 'P':
+                gdb_cmd_write_reg(p);
+                break;
+            case 'm':
+                gdb_cmd_read_mem(p);
+                break;
+            case 'M':
+                gdb_cmd_write_mem(p);
+                break;
+            case 'Z':
+            case 'z':
+                gdb_cmd_breakpoint(p, cmd == 'Z');
+                break;
+            case 'H':
+                if (*p == 'g') {
+                    p++;
+                    /* "-1" (all) and "0" (any) mean the stopped thread */
+                    gdb_reg_tid = (*p == '-') ? 0 : gdb_parse_hex(&p);
+                }
+                gdb_send_str("OK");
+                break;
+            case 'T': {
+                uint64_t tid = gdb_parse_hex(&p);
+                gdb_send_str(gdb_find_thread(tid) ? "OK" : "E01");
+                break;
+            }
+            case 'c':
+            case 's':
+                if (!resumable || !gdb_frame) {
+                    gdb_send_console("rodnix: stopped at a fatal trap, cannot resume");
+                    gdb_send_str(gdb_stop);
+                    break;
+                }
+                if (*p) {
+                    gdb_frame->rip = gdb_parse_hex(&p);
+                }
+                if (cmd == 's') {
+                    gdb_frame->rflags |= DBREG_RFLAGS_TF;
+                } else {
+                    gdb_frame->rflags &= ~DBREG_RFLAGS_TF;
+                }
+                gdb_frame->rflags |= DBREG_RFLAGS_RF;
+                return;
+            case 'D':
+            case 'k':
+                gdb_sw_bp_clear_all();
+                if (cmd == 'D') {
+                    gdb_send_str("OK");
+                }
+                gdb_no_ack = false;
+                gdb_attached = false;
+                if (gdb_frame && resumable) {
+                    gdb_frame->rflags &= ~DBREG_RFLAGS_TF;
+                    gdb_frame->rflags |= DBREG_RFLAGS_RF;
+                }
+                return;
+            case 'q':
+                if (strncmp(p, "Supported", 9) == 0) {
+                    gdb_send_str("PacketSize=1000;QStartNoAckMode+;swbreak+;hwbreak+");
+                } else if (strcmp(p, "C") == 0) {
+                    char* o = gdb_put_str(gdb_out, "QC");
+                    o = gdb_put_num(o, gdb_cur ? gdb_cur->thread_id : 0);
+                    gdb_send(gdb_out, (size_t)(o - gdb_out));
+                } else if (strcmp(p, "fThreadInfo") == 0 || strcmp(p, "sThreadInfo") == 0) {
+                    gdb_cmd_thread_list(p[0] == 'f');
+                } else if (strncmp(p, "ThreadExtraInfo,", 16) == 0) {
+                    p += 16;
+                    gdb_cmd_thread_info(gdb_parse_hex(&p));
+                } else if (strncmp(p, "Attached", 8) == 0) {
+                    gdb_send_str("1");
+                } else if (strcmp(p, "Offsets") == 0) {
+                    gdb_send_str("Text=0;Data=0;Bss=0");
+                } else {
+                    gdb_send_str("");
+                }
+                break;
+            case 'Q':
+                if (strcmp(p, "StartNoAckMode") == 0) {
+                    gdb_send_str("OK");
+                    gdb_no_ack = true;
+                } else {
+                    gdb_send_str("");
+                }
+                break;
+            default:
+                gdb_send_str("");
+                break;
+        }
+    }
+}
+
+static void gdb_enter(uint32_t sig, const char* extra, const char* msg,
+                      interrupt_frame_t* frame, bool resumable)
+{
+    irql_t old_irql = set_irql(IRQL_HIGH);
+    int32_t self = (int32_t)cpu_get_id();
+    int32_t expected = -1;
+    while (!__atomic_compare_exchange_n(&gdb_owner, &expected, self, false,
+                                        __ATOMIC_ACQUIRE, __ATOMIC_RELAXED)) {
+        expected = -1;
+        __asm__ volatile ("pause");
+    }
+
+    gdb_frame = frame;
+    gdb_cur = thread_get_current();
+    gdb_reg_tid = 0;
+    gdb_make_stop(sig, extra);
+    gdb_stop_msg = msg;
+    gdb_loop(resumable);
+    gdb_frame = NULL;
+    gdb_cur = NULL;
+
+    __atomic_store_n(&gdb_owner, -1, __ATOMIC_RELEASE);
+    set_irql(old_irql);
+}
+
+bool gdbstub_trap(interrupt_frame_t* frame)
+{
+    if (!gdb_enabled || gdb_owner == (int32_t)cpu_get_id()) {
+        return false;
+    }
+    if (frame->int_no == 3) {
+        /* int3 leaves RIP after itself; report our own breakpoints at their address */
+        if (gdb_sw_bp_at(frame->rip - 1)) {
+            frame->rip--;
+            gdb_enter(GDB_SIGTRAP, "swbreak:;", NULL, frame, true);
+        } else {
+            gdb_enter(GDB_SIGTRAP, NULL, NULL, frame, true);
+        }
+        return true;
+    }
+
+    uint64_t dr6 = dbreg_take_status();
+    frame->rflags &= ~DBREG_RFLAGS_TF;
+    for (uint32_t i = 0; i < DBREG_COUNT; i++) {
+        uint64_t addr;
+        uint32_t type;
+        uint32_t len;
+        if (!(dr6 & (DBREG_DR6_B0 << i)) || !dbreg_get(i, &addr, &type, &len)) {
+            continue;
+        }
+        if (type == DBREG_EXEC) {
+            gdb_enter(GDB_SIGTRAP, "hwbreak:;", NULL, frame, true);
+        } else {
+            char extra[40];
+            char* o = gdb_put_str(extra, type == DBREG_RW ? "awatch:" : "watch:");
+            o = gdb_put_num(o, addr);
+            *o++ = ';';
+            *o = '\0';
+            gdb_enter(GDB_SIGTRAP, extra, NULL, frame, true);
+        }
+        return true;
+    }
+    gdb_enter(GDB_SIGTRAP, NULL, NULL, frame, true);
+    return true;
+}
+
+void gdbstub_break(uint8_t c, interrupt_frame_t* frame)
+{
+    if (!gdb_enabled || !frame) {
+        return;
+    }
+    if (c == 0x03) {
+        gdb_enter(GDB_SIGINT, NULL, NULL, frame, true);
+        return;
+    }
+    /* A fresh gdb starts talking: stop and let the loop read its packet */
+    gdb_pushback = c;
+    gdb_no_ack = false;
+    gdb_attached = false;
+    gdb_enter(GDB_SIGTRAP, NULL, NULL, frame, true);
+}
+
+void gdbstub_fatal(const char* msg, interrupt_frame_t* frame)
+{
+    if (!gdb_enabled || gdb_owner == (int32_t)cpu_get_id()) {
+        return;
+    }
+    uint32_t sig = GDB_SIGABRT;
+    interrupt_frame_t here;
+    if (frame) {
+        switch (frame->int_no) {
+            case 0:
+            case 16:
+            case 19:
+                sig = GDB_SIGFPE;
+                break;
+            case 6:
+                sig = GDB_SIGILL;
+                break;
+            case 13:
+            case 14:
+                sig = GDB_SIGSEGV;
+                break;
+            default:
+                sig = GDB_SIGTRAP;
+                break;
+        }
+    } else {
+        /* panic(): show gdb the caller as the stopped frame */
+        uint64_t* fp = (uint64_t*)__builtin_frame_address(0);
+        memset(&here, 0, sizeof(here));
+        here.rip = (uint64_t)(uintptr_t)__builtin_return_address(0);
+        here.rbp = fp[0];
+        here.rsp = (uint64_t)(uintptr_t)(fp + 2);
+        __asm__ volatile ("mov %%cs, %0" : "=r"(here.cs));
+        __asm__ volatile ("mov %%ss, %0" : "=r"(here.ss));
+        __asm__ volatile ("pushfq; pop %0" : "=r"(here.rflags));
+        frame = &here;
+    }
+    gdb_enter(sig, NULL, msg ? msg : "panic", frame, false);
+}
+
+void gdbstub_init(void)
+{
+    const char* mode = gdb_mode;
+    if (strcmp(mode, "wait") != 0 && strcmp(mode, "1") != 0) {
+        return;
+    }
+    if (console_serial2_init() != RDNX_OK) {
+        kputs("[GDB] rdnx.gdb set but COM2 is absent\n");
+        return;
+    }
+    if (console_serial2_rx_irq_init() != RDNX_OK) {
+        kputs("[GDB] COM2 RX interrupt unavailable, attach only on traps\n");
+    }
+    gdb_enabled = true;
+    kputs("[GDB] remote stub on COM2 (115200 8N1)\n");
+    if (strcmp(mode, "wait") == 0) {
+        kputs("[GDB] waiting for gdb to attach\n");
+        __asm__ volatile ("int3");
+    }
+}
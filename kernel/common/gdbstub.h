@@ -0,0 +1,42 @@
+/**
+ * @file gdbstub.h
+ * @brief GDB remote serial protocol stub on COM2
+ *
+ * Enabled with rdnx.gdb=1 (rdnx.gdb=wait also stops at boot until gdb
+ * attaches). While enabled the stub owns #DB and #BP; fatal traps and
+ * panics are reported to gdb before the kernel halts. RodNIX threads are
+ * GDB threads (tid = thread_id). Like ddb, the stopped CPU runs at
+ * IRQL_HIGH; other CPUs are not stopped, only kept off the IRQL giant.
+ */
+
+#ifndef _RODNIX_COMMON_GDBSTUB_H
+#define _RODNIX_COMMON_GDBSTUB_H
+
+#include <stdint.h>
+#include <stdbool.h>
+
+struct interrupt_frame;
+
+/* Read rdnx.gdb, set up COM2 and its RX interrupt. Needs APIC/PIC. */
+void gdbstub_init(void);
+
+/**
+ * #DB/#BP hook from the exception path.
+ * @return true if the stub handled the trap (frame may have been changed)
+ */
+bool gdbstub_trap(struct interrupt_frame* frame);
+
+/**
+ * Byte received on COM2 while the kernel runs (from the RX interrupt):
+ * Ctrl-C stops with SIGINT, anything else is the start of a new gdb
+ * session and is fed to the packet reader.
+ */
+void gdbstub_break(uint8_t c, struct interrupt_frame* frame);
+
+/**
+ * Report a fatal trap or panic (frame == NULL) to gdb and serve it until
+ * it continues or detaches. Returning means the caller halts.
+ */
+void gdbstub_fatal(const char* msg, struct interrupt_frame* frame);
+
+#endif /* _RODNIX_COMMON_GDBSTUB_H */
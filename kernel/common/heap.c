@@ -1,246 +1,117 @@
 /**
  * @file heap.c
- * @brief Simple kernel heap allocator
+ * @brief kmalloc size classes on top of the kmem object caches
+ *
+ * Requests up to the largest class are served by "kmalloc-N" caches;
+ * bigger ones take whole pages (kmem_page_alloc). Every block is at
+ * least 16-byte aligned, page-sized blocks are page aligned.
  */
 
 #include "heap.h"
+#include "kmem.h"
 #include "../../include/common.h"
 #include "../../include/debug.h"
 #include "../../include/error.h"
 #include "../core/memory.h"
-#include "../core/config.h"
-#include "../core/interrupts.h"
 
-typedef struct heap_block {
-    size_t size;
-    bool free;
-    struct heap_block* next;
-    struct heap_block* prev;
-} heap_block_t;
+typedef struct {
+    uint32_t size;
+    const char* name;
+} kmalloc_class_t;
 
-static heap_block_t* heap_head = NULL;
-static heap_block_t* heap_tail = NULL;
+static const kmalloc_class_t kmalloc_classes[] = {
+    {16, "kmalloc-16"},     {32, "kmalloc-32"},     {48, "kmalloc-48"},
+    {64, "kmalloc-64"},     {96, "kmalloc-96"},     {128, "kmalloc-128"},
+    {192, "kmalloc-192"},   {256, "kmalloc-256"},   {384, "kmalloc-384"},
+    {512, "kmalloc-512"},   {768, "kmalloc-768"},   {1024, "kmalloc-1024"},
+    {1536, "kmalloc-1536"}, {2048, "kmalloc-2048"}, {3072, "kmalloc-3072"},
+    {4096, "kmalloc-4096"},
+};
 
-static inline irql_t heap_lock(void)
-{
-    return set_irql(IRQL_HIGH);
-}
-
-static inline void heap_unlock(irql_t old)
-{
-    (void)set_irql(old);
-}
-
-static bool heap_block_in_list(const heap_block_t* needle)
-{
-    if (!needle) {
-        return false;
-    }
-    uint32_t guard = 0;
-    for (heap_block_t* cur = heap_head; cur; cur = cur->next) {
-        if (cur == needle) {
-            return true;
-        }
-        guard++;
-        if (guard > 1000000u) {
-            break;
-        }
-    }
-    return false;
-}
-
-static size_t heap_align(size_t size)
-{
-    const size_t align = 16;
-    return ALIGN_UP(size, align);
-}
+#define KMALLOC_CLASSES ARRAY_SIZE(kmalloc_classes)
 
-static void heap_insert_block(heap_block_t* block)
-{
-    block->next = NULL;
-    block->prev = heap_tail;
-    if (heap_tail) {
-        heap_tail->next = block;
-    } else {
-        heap_head = block;
-    }
-    heap_tail = block;
-}
+/* Written once by heap_init() before any other CPU runs */
+static kmem_cache_t* kmalloc_caches[KMALLOC_CLASSES];
+static bool heap_ready = false;
 
-static void heap_merge_if_possible(heap_block_t* block)
+/* @param initial_pages Unused: slabs grow on demand */
+int heap_init(size_t initial_pages)
 {
-    if (!block) {
-        return;
-    }
-
-    if (block->next && block->next->free) {
-        uint8_t* end = (uint8_t*)block + sizeof(heap_block_t) + block->size;
-        if (end == (uint8_t*)block->next) {
-            heap_block_t* next = block->next;
-            block->size += sizeof(heap_block_t) + next->size;
-            block->next = next->next;
-            if (next->next) {
-                next->next->prev = block;
-            } else {
-                heap_tail = block;
-            }
-        }
+    (void)initial_pages;
+    if (heap_ready) {
+        return RDNX_OK;
     }
 
-    if (block->prev && block->prev->free) {
-        uint8_t* end = (uint8_t*)block->prev + sizeof(heap_block_t) + block->prev->size;
-        if (end == (uint8_t*)block) {
-            heap_block_t* prev = block->prev;
-            prev->size += sizeof(heap_block_t) + block->size;
-            prev->next = block->next;
-            if (block->next) {
-                block->next->prev = prev;
-            } else {
-                heap_tail = prev;
-            }
+    kmem_init();
+    for (uint32_t i = 0; i < KMALLOC_CLASSES; i++) {
+        kmalloc_caches[i] = kmem_cache_create(kmalloc_classes[i].name, kmalloc_classes[i].size,
+                                              KMEM_ALIGN_DEFAULT, NULL, NULL, NULL, 0);
+        if (!kmalloc_caches[i]) {
+            TRACE_EVENT("oom: heap_init");
+            memory_oom_inc_heap();
+            PANIC("OOM: heap_init cache=%s", kmalloc_classes[i].name);
         }
     }
+    heap_ready = true;
+    return RDNX_OK;
 }
 
-static heap_block_t* heap_find_fit(size_t size)
+static kmem_cache_t* kmalloc_cache_for(size_t size)
 {
-    for (heap_block_t* cur = heap_head; cur; cur = cur->next) {
-        if (cur->free && cur->size >= size) {
-            return cur;
+    for (uint32_t i = 0; i < KMALLOC_CLASSES; i++) {
+        if (size <= kmalloc_classes[i].size) {
+            return kmalloc_caches[i];
         }
     }
     return NULL;
 }
 
-static void heap_split_block(heap_block_t* block, size_t size)
-{
-    size_t aligned = heap_align(size);
-    if (block->size <= aligned + sizeof(heap_block_t) + 16) {
-        return;
-    }
-
-    uint8_t* next_addr = (uint8_t*)block + sizeof(heap_block_t) + aligned;
-    heap_block_t* next = (heap_block_t*)next_addr;
-    next->size = block->size - aligned - sizeof(heap_block_t);
-    next->free = true;
-    next->prev = block;
-    next->next = block->next;
-    if (block->next) {
-        block->next->prev = next;
-    } else {
-        heap_tail = next;
-    }
-    block->next = next;
-    block->size = aligned;
-}
-
-static heap_block_t* heap_grow(size_t min_size)
+static bool kmalloc_cache_owned(const kmem_cache_t* cache)
 {
-    size_t total = heap_align(min_size) + sizeof(heap_block_t);
-    size_t pages = ALIGN_UP(total, PAGE_SIZE) / PAGE_SIZE;
-    if (pages == 0) {
-        pages = 1;
-    }
-
-    void* mem = vmm_alloc_pages((uint32_t)pages, PAGE_FLAG_WRITABLE);
-    if (!mem) {
-        TRACE_EVENT("oom: heap_grow");
-        memory_oom_inc_heap();
-        PANIC("OOM: heap_grow pages=%u", (unsigned)pages);
-    }
-
-    heap_block_t* block = (heap_block_t*)mem;
-    block->size = pages * PAGE_SIZE - sizeof(heap_block_t);
-    block->free = true;
-    block->next = NULL;
-    block->prev = NULL;
-    heap_insert_block(block);
-
-    if (block->prev && block->prev->free) {
-        uint8_t* prev_end = (uint8_t*)block->prev + sizeof(heap_block_t) + block->prev->size;
-        if (prev_end == (uint8_t*)block) {
-            heap_merge_if_possible(block->prev);
-            /* Newly inserted block is absorbed into prev. */
-            return block->prev;
+    for (uint32_t i = 0; i < KMALLOC_CLASSES; i++) {
+        if (kmalloc_caches[i] == cache) {
+            return true;
         }
     }
-
-    return block;
-}
-
-int heap_init(size_t initial_pages)
-{
-    if (heap_head) {
-        return RDNX_OK;
-    }
-
-    if (initial_pages == 0) {
-        initial_pages = 16;
-    }
-
-    void* mem = vmm_alloc_pages((uint32_t)initial_pages, PAGE_FLAG_WRITABLE);
-    if (!mem) {
-        TRACE_EVENT("oom: heap_init");
-        memory_oom_inc_heap();
-        PANIC("OOM: heap_init pages=%u", (unsigned)initial_pages);
-    }
-
-    heap_block_t* block = (heap_block_t*)mem;
-    block->size = initial_pages * PAGE_SIZE - sizeof(heap_block_t);
-    block->free = true;
-    block->next = NULL;
-    block->prev = NULL;
-    heap_head = block;
-    heap_tail = block;
-
-    return RDNX_OK;
+    return false;
 }
 
 void* kmalloc(size_t size)
 {
-    irql_t old = heap_lock();
     if (size == 0) {
-        heap_unlock(old);
         return NULL;
     }
 
-    size_t aligned = heap_align(size);
-    heap_block_t* block = heap_find_fit(aligned);
-    if (!block) {
-        block = heap_grow(aligned);
-        if (!block) {
-            heap_unlock(old);
-            return NULL;
-        }
+    kmem_cache_t* cache = kmalloc_cache_for(size);
+    void* ptr = cache ? kmem_cache_alloc(cache) : kmem_page_alloc(size);
+    if (!ptr) {
+        TRACE_EVENT("oom: kmalloc");
+        memory_oom_inc_heap();
+        PANIC("OOM: kmalloc size=%u", (unsigned)size);
     }
-
-    heap_split_block(block, aligned);
-    block->free = false;
-    void* ptr = (uint8_t*)block + sizeof(heap_block_t);
-    heap_unlock(old);
     return ptr;
 }
 
 void kfree(void* ptr)
 {
-    irql_t old = heap_lock();
+    kmem_cache_t* cache = NULL;
+    size_t size = 0;
+
     if (!ptr) {
-        heap_unlock(old);
         return;
     }
-
-    heap_block_t* block = (heap_block_t*)((uint8_t*)ptr - sizeof(heap_block_t));
-    if (!heap_block_in_list(block)) {
-        heap_unlock(old);
-        PANIC("kfree: invalid pointer %p block=%p ra=%p", ptr, block, __builtin_return_address(0));
+    if (!kmem_lookup(ptr, &cache, &size)) {
+        PANIC("kfree: invalid pointer or double free %p ra=%p", ptr, __builtin_return_address(0));
+    }
+    if (!cache) {
+        kmem_page_free(ptr);
+        return;
     }
-    if (block->free) {
-        heap_unlock(old);
-        PANIC("kfree: double free ptr=%p block=%p ra=%p", ptr, block, __builtin_return_address(0));
+    if (!kmalloc_cache_owned(cache)) {
+        PANIC("kfree: %p belongs to a kmem cache, not kmalloc ra=%p", ptr,
+              __builtin_return_address(0));
     }
-    block->free = true;
-    heap_merge_if_possible(block);
-    heap_unlock(old);
+    kmem_cache_free(cache, ptr);
 }
 
 void* kcalloc(size_t count, size_t size)
@@ -272,9 +143,12 @@ void* krealloc(void* ptr, size_t new_size)
         return NULL;
     }
 
-    heap_block_t* block = (heap_block_t*)((uint8_t*)ptr - sizeof(heap_block_t));
-    if (block->size >= new_size) {
-        heap_split_block(block, new_size);
+    kmem_cache_t* cache = NULL;
+    size_t old_size = 0;
+    if (!kmem_lookup(ptr, &cache, &old_size)) {
+        PANIC("krealloc: invalid pointer %p ra=%p", ptr, __builtin_return_address(0));
+    }
+    if (new_size <= old_size) {
         return ptr;
     }
 
@@ -282,7 +156,7 @@ void* krealloc(void* ptr, size_t new_size)
     if (!new_mem) {
         return NULL;
     }
-    memcpy(new_mem, ptr, block->size);
+    memcpy(new_mem, ptr, old_size);
     kfree(ptr);
     return new_mem;
 }
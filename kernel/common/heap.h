@@ -1,6 +1,6 @@
 /**
  * @file heap.h
- * @brief Simple kernel heap interface
+ * @brief Kernel heap interface (kmalloc size classes over kmem caches)
  */
 
 #ifndef _RODNIX_COMMON_HEAP_H
@@ -8,6 +8,7 @@
 #include "scheduler.h"
 #include "../fabric/spin.h"
 #include "heap.h"
+#include "kmem.h"
 #include "../../include/common.h"
 #include "../../include/debug.h"
 #include "../../include/error.h"
@@ -24,6 +25,9 @@ static port_set_t* all_port_sets_head = NULL;
 #define IPC_MAX_PORTS 1024
 static port_t* port_table[IPC_MAX_PORTS];
 
+/* port_t cache, created by ipc_init() */
+static kmem_cache_t* port_cache = NULL;
+
 /*
  * LOCKING: g_port_table_lock (spinlock_t)
  *   Protects: port_table[], next_port_id, and port->ref_count mutations.
@@ -212,6 +216,9 @@ int ipc_init(void)
     }
 
     spinlock_init(&g_port_table_lock);
+    if (!port_cache) {
+        port_cache = kmem_cache_create("ipc_port", sizeof(port_t), 0, NULL, NULL, NULL, 0);
+    }
     ipc_initialized = true;
     /* Reserve bootstrap port (placeholder, no protocol yet) */
     bootstrap_port = port_allocate(PORT_TYPE_CONTROL);
@@ -224,14 +231,14 @@ port_t* port_allocate(port_type_t type)
         ipc_init();
     }
     
-    port_t* port = (port_t*)kmalloc(sizeof(port_t));
+    port_t* port = (port_t*)kmem_cache_zalloc(port_cache);
     
     if (!port) {
         return NULL;
     }
 
     if (next_port_id > IPC_MAX_PORTS) {
-        kfree(port);
+        kmem_cache_free(port_cache, port);
         return NULL;
     }
 
@@ -244,7 +251,7 @@ port_t* port_allocate(port_type_t type)
     port->ref_count = 1;
     port->queue = ipc_queue_create();
     if (!port->queue) {
-        kfree(port);
+        kmem_cache_free(port_cache, port);
         return NULL;
     }
     port->active = true;
@@ -290,7 +297,7 @@ void port_deallocate(port_t* port)
         waitq_wake_all(&port->waiters);
         ipc_queue_destroy((ipc_queue_t*)port->queue);
         port->queue = NULL;
-        kfree(port);
+        kmem_cache_free(port_cache, port);
     }
 }
 
@@ -0,0 +1,851 @@
+/**
+ * @file kmem.c
+ * @brief Object-cache (slab) kernel allocator
+ *
+ * Three layers, after Bonwick's slab allocator:
+ *  - per-CPU: two magazines per cache, used with local interrupts off;
+ *  - depot: per-cache lists of full and empty magazines;
+ *  - slabs: runs of 1..KMEM_SLAB_MAX_PAGES pages from the PMM, with the
+ *    slab header and a 16-bit free-index array (bufctl) at the front.
+ *
+ * Objects reach the magazines only through frees; an allocation that
+ * finds no full magazine goes straight to the slab layer.
+ *
+ * A two-level page map records, for every page handed out here, either
+ * its slab or (first page only) the size of a whole-page allocation, so
+ * a bare pointer can be traced back to its owner.
+ */
+
+#include "kmem.h"
+#include "../core/memory.h"
+#include "../core/config.h"
+#include "../core/cpu.h"
+#include "../core/interrupts.h"
+#include "../arch/config.h"
+#include "../../include/common.h"
+#include "../../include/debug.h"
+#include "../../include/error.h"
+#include <bsd/sys/queue.h>
+
+#define KMEM_MAG_ROUNDS    30
+#define KMEM_DEPOT_MAX     8       /* Full (and empty) magazines kept per cache */
+#define KMEM_EMPTY_KEEP    1       /* Empty slabs kept per cache before release */
+#define KMEM_BUFCTL_END    0xFFFFu
+#define KMEM_BUFCTL_INUSE  0xFFFEu
+#define KMEM_MAX_OBJS      0xFFF0u
+
+/* Page map: covers the kernel direct map (2 GiB of physical memory) */
+#define KMEM_PAGEMAP_LEAF  (PAGE_SIZE / sizeof(uintptr_t))
+#define KMEM_PAGEMAP_PAGES (0x80000000ULL / PAGE_SIZE)
+#define KMEM_PAGEMAP_TOP   (KMEM_PAGEMAP_PAGES / KMEM_PAGEMAP_LEAF)
+#define KMEM_PAGEMAP_LARGE 0x1u    /* Entry is (pages << 1) | 1 */
+
+typedef struct kmem_magazine {
+    struct kmem_magazine* next;
+    uint32_t rounds;
+    uint32_t reserved;
+    void* objs[KMEM_MAG_ROUNDS];
+} kmem_magazine_t;
+
+typedef struct kmem_cpu {
+    kmem_magazine_t* loaded;
+    kmem_magazine_t* prev;
+    uint64_t allocs;
+    uint64_t frees;
+} kmem_cpu_t;
+
+typedef struct kmem_slab {
+    LIST_ENTRY(kmem_slab) link;
+    kmem_cache_t* cache;
+    uint8_t* objs;
+    uint16_t free_head;
+    uint16_t inuse;
+    uint32_t reserved;
+    uint16_t bufctl[];
+} kmem_slab_t;
+
+LIST_HEAD(kmem_slab_list, kmem_slab);
+
+struct kmem_cache {
+    char name[KMEM_NAME_MAX];
+    uint32_t obj_size;
+    uint32_t align;
+    uint32_t slab_pages;
+    uint32_t objs_per_slab;
+    uint32_t hdr_size;
+    uint32_t flags;
+    kmem_ctor_t ctor;
+    kmem_dtor_t dtor;
+    void* arg;
+    struct kmem_slab_list partial;
+    struct kmem_slab_list full;
+    struct kmem_slab_list empty;
+    uint32_t slabs;
+    uint32_t empty_slabs;
+    kmem_magazine_t* depot_full;
+    kmem_magazine_t* depot_empty;
+    uint32_t depot_full_count;
+    uint32_t depot_empty_count;
+    uint64_t slab_allocs;
+    uint64_t slab_frees;
+    uint64_t fails;
+    TAILQ_ENTRY(kmem_cache) link;
+    kmem_cpu_t cpu[CPU_MAX_COUNT];
+};
+
+TAILQ_HEAD(kmem_cache_list, kmem_cache);
+
+/*
+ * LOCKING: kmem giant section (kmem_lock / kmem_unlock, IRQL_HIGH)
+ *   Protects: cache lists and slab lists, depots, slab bufctl arrays,
+ *   kmem_pagemap leaves (allocation), kmem_caches, kmem_large_pages.
+ *   Also serialises the PMM calls made from here, as heap.c did.
+ *
+ * LOCKING: kmem_cache.cpu[] — each slot used only by its own CPU, with
+ *   local interrupts off (interrupts_local_save) or inside the giant
+ *   section. kmem_cache_destroy() requires an idle cache and is the only
+ *   code touching other CPUs' slots; statistics read them racily.
+ *
+ * Page map entries are written under the giant before the memory is
+ * handed out and read without it: a caller freeing a pointer must have
+ * obtained it after that write.
+ */
+static uintptr_t* kmem_pagemap[KMEM_PAGEMAP_TOP];
+static struct kmem_cache_list kmem_caches = TAILQ_HEAD_INITIALIZER(kmem_caches);
+static struct kmem_cache kmem_cache_cache;  /* struct kmem_cache objects */
+static struct kmem_cache kmem_mag_cache;    /* kmem_magazine_t */
+static uint64_t kmem_large_pages = 0;
+static bool kmem_ready = false;
+
+static inline irql_t kmem_lock(void)
+{
+    return set_irql(IRQL_HIGH);
+}
+
+static inline void kmem_unlock(irql_t old)
+{
+    (void)set_irql(old);
+}
+
+/* ---- Page map ---- */
+
+static uintptr_t* kmem_pagemap_slot(const void* ptr, bool create)
+{
+    uintptr_t va = (uintptr_t)ptr;
+    if (va < ARCH_KERNEL_VIRT_BASE) {
+        return NULL;
+    }
+    uint64_t pfn = (uint64_t)ARCH_VIRT_TO_PHYS(va) / PAGE_SIZE;
+    if (pfn >= KMEM_PAGEMAP_PAGES) {
+        return NULL;
+    }
+    uintptr_t* leaf = kmem_pagemap[pfn / KMEM_PAGEMAP_LEAF];
+    if (!leaf) {
+        if (!create) {
+            return NULL;
+        }
+        /* PMM pages come zeroed */
+        leaf = (uintptr_t*)vmm_alloc_pages(1, PAGE_FLAG_WRITABLE);
+        if (!leaf) {
+            return NULL;
+        }
+        cpu_write_barrier();
+        kmem_pagemap[pfn / KMEM_PAGEMAP_LEAF] = leaf;
+    }
+    return &leaf[pfn % KMEM_PAGEMAP_LEAF];
+}
+
+/* Point every page of [mem, mem + pages) at value (0 clears) */
+static int kmem_pagemap_set(void* mem, uint32_t pages, uintptr_t value)
+{
+    for (uint32_t i = 0; i < pages; i++) {
+        uintptr_t* slot = kmem_pagemap_slot((uint8_t*)mem + (size_t)i * PAGE_SIZE, value != 0);
+        if (!slot) {
+            if (value != 0) {
+                (void)kmem_pagemap_set(mem, i, 0);
+                return RDNX_E_NOMEM;
+            }
+            continue;
+        }
+        *slot = value;
+    }
+    return RDNX_OK;
+}
+
+static kmem_slab_t* kmem_obj_slab(const void* ptr, uint32_t* out_idx)
+{
+    uintptr_t* slot = kmem_pagemap_slot(ptr, false);
+    if (!slot || *slot == 0 || (*slot & KMEM_PAGEMAP_LARGE)) {
+        return NULL;
+    }
+    kmem_slab_t* s = (kmem_slab_t*)*slot;
+    uintptr_t p = (uintptr_t)ptr;
+    uintptr_t base = (uintptr_t)s->objs;
+    if (p < base) {
+        return NULL;
+    }
+    uintptr_t off = p - base;
+    uint32_t size = s->cache->obj_size;
+    if (off % size != 0 || off / size >= s->cache->objs_per_slab) {
+        return NULL;
+    }
+    *out_idx = (uint32_t)(off / size);
+    return s;
+}
+
+/* ---- Slab layer (giant held) ---- */
+
+static bool kmem_geometry(kmem_cache_t* c)
+{
+    for (uint32_t pages = 1; pages <= KMEM_SLAB_MAX_PAGES; pages <<= 1) {
+        size_t bytes = (size_t)pages * PAGE_SIZE;
+        if (bytes <= sizeof(kmem_slab_t)) {
+            continue;
+        }
+        size_t n = (bytes - sizeof(kmem_slab_t)) / (c->obj_size + sizeof(uint16_t));
+        if (n > KMEM_MAX_OBJS) {
+            n = KMEM_MAX_OBJS;
+        }
+        while (n > 0 &&
+               ALIGN_UP(sizeof(kmem_slab_t) + n * sizeof(uint16_t), (size_t)c->align) +
+               n * c->obj_size > bytes) {
+            n--;
+        }
+        if (n == 0) {
+            continue;
+        }
+        /* Accept at most 1/8 of the slab unused, else try a bigger slab */
+        size_t waste = bytes - n * c->obj_size;
+        if (waste * 8 > bytes && pages < KMEM_SLAB_MAX_PAGES) {
+            continue;
+        }
+        c->slab_pages = pages;
+        c->objs_per_slab = (uint32_t)n;
+        c->hdr_size = (uint32_t)ALIGN_UP(sizeof(kmem_slab_t) + n * sizeof(uint16_t), (size_t)c->align);
+        return true;
+    }
+    return false;
+}
+
+static kmem_slab_t* kmem_slab_grow(kmem_cache_t* c)
+{
+    uint8_t* mem = (uint8_t*)vmm_alloc_pages(c->slab_pages, PAGE_FLAG_WRITABLE);
+    if (!mem) {
+        return NULL;
+    }
+    if (kmem_pagemap_set(mem, c->slab_pages, (uintptr_t)mem) != RDNX_OK) {
+        vmm_free_pages(mem, c->slab_pages);
+        return NULL;
+    }
+
+    kmem_slab_t* s = (kmem_slab_t*)mem;
+    s->cache = c;
+    s->objs = mem + c->hdr_size;
+    s->inuse = 0;
+    for (uint32_t i = 0; i < c->objs_per_slab; i++) {
+        s->bufctl[i] = (uint16_t)(i + 1);
+    }
+    s->bufctl[c->objs_per_slab - 1] = KMEM_BUFCTL_END;
+    s->free_head = 0;
+
+    if (c->ctor) {
+        for (uint32_t i = 0; i < c->objs_per_slab; i++) {
+            if (c->ctor(s->objs + (size_t)i * c->obj_size, c->arg) == 0) {
+                continue;
+            }
+            while (c->dtor && i-- > 0) {
+                c->dtor(s->objs + (size_t)i * c->obj_size, c->arg);
+            }
+            (void)kmem_pagemap_set(mem, c->slab_pages, 0);
+            vmm_free_pages(mem, c->slab_pages);
+            return NULL;
+        }
+    }
+    c->slabs++;
+    return s;
+}
+
+static void kmem_slab_release(kmem_cache_t* c, kmem_slab_t* s)
+{
+    if (c->dtor) {
+        for (uint32_t i = 0; i < c->objs_per_slab; i++) {
+            c->dtor(s->objs + (size_t)i * c->obj_size, c->arg);
+        }
+    }
+    (void)kmem_pagemap_set(s, c->slab_pages, 0);
+    vmm_free_pages(s, c->slab_pages);
+    c->slabs--;
+}
+
+static void kmem_release_empty(kmem_cache_t* c)
+{
+    kmem_slab_t* s;
+    while ((s = LIST_FIRST(&c->empty)) != NULL) {
+        LIST_REMOVE(s, link);
+        c->empty_slabs--;
+        kmem_slab_release(c, s);
+    }
+}
+
+static void* kmem_slab_get(kmem_cache_t* c)
+{
+    kmem_slab_t* s = LIST_FIRST(&c->partial);
+    if (!s) {
+        s = LIST_FIRST(&c->empty);
+        if (s) {
+            LIST_REMOVE(s, link);
+            c->empty_slabs--;
+        } else {
+            s = kmem_slab_grow(c);
+            if (!s) {
+                return NULL;
+            }
+        }
+        LIST_INSERT_HEAD(&c->partial, s, link);
+    }
+
+    uint16_t idx = s->free_head;
+    s->free_head = s->bufctl[idx];
+    s->bufctl[idx] = KMEM_BUFCTL_INUSE;
+    s->inuse++;
+    if (s->free_head == KMEM_BUFCTL_END) {
+        LIST_REMOVE(s, link);
+        LIST_INSERT_HEAD(&c->full, s, link);
+    }
+    return s->objs + (size_t)idx * c->obj_size;
+}
+
+static void kmem_slab_put(kmem_cache_t* c, kmem_slab_t* s, uint32_t idx)
+{
+    bool was_full = (s->free_head == KMEM_BUFCTL_END);
+    s->bufctl[idx] = s->free_head;
+    s->free_head = (uint16_t)idx;
+    s->inuse--;
+
+    if (s->inuse == 0) {
+        LIST_REMOVE(s, link);
+        if (c->empty_slabs >= KMEM_EMPTY_KEEP) {
+            kmem_slab_release(c, s);
+        } else {
+            LIST_INSERT_HEAD(&c->empty, s, link);
+            c->empty_slabs++;
+        }
+    } else if (was_full) {
+        LIST_REMOVE(s, link);
+        LIST_INSERT_HEAD(&c->partial, s, link);
+    }
+}
+
+/* ---- Magazines ---- */
+
+static void* kmem_mag_pop(kmem_cpu_t* cc)
+{
+    kmem_magazine_t* m = cc->loaded;
+    if (!m || m->rounds == 0) {
+        if (!cc->prev || cc->prev->rounds == 0) {
+            return NULL;
+        }
+        cc->loaded = cc->prev;
+        cc->prev = m;
+        m = cc->loaded;
+    }
+    cc->allocs++;
+    return m->objs[--m->rounds];
+}
+
+static bool kmem_mag_push(kmem_cpu_t* cc, void* obj)
+{
+    kmem_magazine_t* m = cc->loaded;
+    if (m) {
+        for (uint32_t i = 0; i < m->rounds; i++) {
+            if (m->objs[i] == obj) {
+                PANIC("kmem: double free %p ra=%p", obj, __builtin_return_address(0));
+            }
+        }
+    }
+    if (!m || m->rounds == KMEM_MAG_ROUNDS) {
+        if (!cc->prev || cc->prev->rounds == KMEM_MAG_ROUNDS) {
+            return false;
+        }
+        cc->loaded = cc->prev;
+        cc->prev = m;
+        m = cc->loaded;
+    }
+    m->objs[m->rounds++] = obj;
+    cc->frees++;
+    return true;
+}
+
+/* Return every round of m to its slab */
+static void kmem_mag_flush(kmem_cache_t* c, kmem_magazine_t* m)
+{
+    while (m->rounds > 0) {
+        uint32_t idx;
+        void* obj = m->objs[--m->rounds];
+        kmem_slab_t* s = kmem_obj_slab(obj, &idx);
+        if (s) {
+            kmem_slab_put(c, s, idx);
+        }
+    }
+}
+
+/* Free an internal object (magazine, cache descriptor) back to its slab */
+static void kmem_internal_put(kmem_cache_t* c, void* obj)
+{
+    uint32_t idx;
+    kmem_slab_t* s = kmem_obj_slab(obj, &idx);
+    if (s) {
+        kmem_slab_put(c, s, idx);
+        c->slab_frees++;
+    }
+}
+
+static void* kmem_internal_get(kmem_cache_t* c)
+{
+    void* obj = kmem_slab_get(c);
+    if (obj) {
+        c->slab_allocs++;
+    } else {
+        c->fails++;
+    }
+    return obj;
+}
+
+static void kmem_mag_discard(kmem_magazine_t* m)
+{
+    kmem_internal_put(&kmem_mag_cache, m);
+}
+
+static void kmem_depot_put_empty(kmem_cache_t* c, kmem_magazine_t* m)
+{
+    if (c->depot_empty_count >= KMEM_DEPOT_MAX) {
+        kmem_mag_discard(m);
+        return;
+    }
+    m->next = c->depot_empty;
+    c->depot_empty = m;
+    c->depot_empty_count++;
+}
+
+static void kmem_depot_put_full(kmem_cache_t* c, kmem_magazine_t* m)
+{
+    if (c->depot_full_count >= KMEM_DEPOT_MAX) {
+        kmem_mag_flush(c, m);
+        kmem_depot_put_empty(c, m);
+        return;
+    }
+    m->next = c->depot_full;
+    c->depot_full = m;
+    c->depot_full_count++;
+}
+
+static kmem_magazine_t* kmem_depot_get_empty(kmem_cache_t* c)
+{
+    kmem_magazine_t* m = c->depot_empty;
+    if (m) {
+        c->depot_empty = m->next;
+        c->depot_empty_count--;
+    } else {
+        m = (kmem_magazine_t*)kmem_internal_get(&kmem_mag_cache);
+        if (!m) {
+            return NULL;
+        }
+    }
+    m->next = NULL;
+    m->rounds = 0;
+    return m;
+}
+
+static void kmem_depot_drain(kmem_cache_t* c)
+{
+    kmem_magazine_t* m;
+    while ((m = c->depot_full) != NULL) {
+        c->depot_full = m->next;
+        c->depot_full_count--;
+        kmem_mag_flush(c, m);
+        kmem_mag_discard(m);
+    }
+    while ((m = c->depot_empty) != NULL) {
+        c->depot_empty = m->next;
+        c->depot_empty_count--;
+        kmem_mag_discard(m);
+    }
+}
+
+static void kmem_reap_locked(void)
+{
+    kmem_cache_t* c;
+    TAILQ_FOREACH(c, &kmem_caches, link) {
+        kmem_depot_drain(c);
+    }
+    /* Second pass: draining filled the magazine cache's slabs */
+    TAILQ_FOREACH(c, &kmem_caches, link) {
+        kmem_release_empty(c);
+    }
+}
+
+/* ---- Caches ---- */
+
+static bool kmem_cache_setup(kmem_cache_t* c, const char* name, size_t size, size_t align,
+                             kmem_ctor_t ctor, kmem_dtor_t dtor, void* arg, uint32_t flags)
+{
+    if (align == 0) {
+        align = KMEM_ALIGN_DEFAULT;
+    }
+    if (!name || size == 0 || (align & (align - 1)) != 0 || align > PAGE_SIZE) {
+        return false;
+    }
+    if (size < sizeof(void*)) {
+        size = sizeof(void*);
+    }
+    size = ALIGN_UP(size, align);
+    if (size > (size_t)KMEM_SLAB_MAX_PAGES * PAGE_SIZE) {
+        return false;
+    }
+
+    memset(c, 0, sizeof(*c));
+    strncpy(c->name, name, sizeof(c->name) - 1);
+    c->obj_size = (uint32_t)size;
+    c->align = (uint32_t)align;
+    c->ctor = ctor;
+    c->dtor = dtor;
+    c->arg = arg;
+    c->flags = flags;
+    LIST_INIT(&c->partial);
+    LIST_INIT(&c->full);
+    LIST_INIT(&c->empty);
+    return kmem_geometry(c);
+}
+
+void kmem_init(void)
+{
+    if (kmem_ready) {
+        return;
+    }
+    irql_t old = kmem_lock();
+    (void)kmem_cache_setup(&kmem_cache_cache, "kmem_cache", sizeof(struct kmem_cache), 0,
+                           NULL, NULL, NULL, KMEM_CACHE_NOMAG);
+    (void)kmem_cache_setup(&kmem_mag_cache, "kmem_magazine", sizeof(kmem_magazine_t), 0,
+                           NULL, NULL, NULL, KMEM_CACHE_NOMAG);
+    TAILQ_INSERT_TAIL(&kmem_caches, &kmem_cache_cache, link);
+    TAILQ_INSERT_TAIL(&kmem_caches, &kmem_mag_cache, link);
+    kmem_ready = true;
+    kmem_unlock(old);
+}
+
+kmem_cache_t* kmem_cache_create(const char* name, size_t size, size_t align,
+                                kmem_ctor_t ctor, kmem_dtor_t dtor, void* arg,
+                                uint32_t flags)
+{
+    if (!kmem_ready) {
+        return NULL;
+    }
+    irql_t old = kmem_lock();
+    kmem_cache_t* c = (kmem_cache_t*)kmem_internal_get(&kmem_cache_cache);
+    if (!c) {
+        kmem_unlock(old);
+        return NULL;
+    }
+    if (!kmem_cache_setup(c, name, size, align, ctor, dtor, arg, flags)) {
+        kmem_internal_put(&kmem_cache_cache, c);
+        kmem_unlock(old);
+        return NULL;
+    }
+    TAILQ_INSERT_TAIL(&kmem_caches, c, link);
+    kmem_unlock(old);
+    return c;
+}
+
+int kmem_cache_destroy(kmem_cache_t* c)
+{
+    if (!c || c == &kmem_cache_cache || c == &kmem_mag_cache) {
+        return RDNX_E_INVALID;
+    }
+    irql_t old = kmem_lock();
+    for (uint32_t cpu = 0; cpu < CPU_MAX_COUNT; cpu++) {
+        kmem_cpu_t* cc = &c->cpu[cpu];
+        kmem_magazine_t* mags[2] = { cc->loaded, cc->prev };
+        cc->loaded = NULL;
+        cc->prev = NULL;
+        for (uint32_t i = 0; i < 2; i++) {
+            if (mags[i]) {
+                kmem_mag_flush(c, mags[i]);
+                kmem_mag_discard(mags[i]);
+            }
+        }
+    }
+    kmem_depot_drain(c);
+    if (!LIST_EMPTY(&c->partial) || !LIST_EMPTY(&c->full)) {
+        kmem_unlock(old);
+        return RDNX_E_BUSY;
+    }
+    kmem_release_empty(c);
+    TAILQ_REMOVE(&kmem_caches, c, link);
+    kmem_internal_put(&kmem_cache_cache, c);
+    kmem_unlock(old);
+    return RDNX_OK;
+}
+
+void* kmem_cache_alloc(kmem_cache_t* c)
+{
+    if (!c) {
+        return NULL;
+    }
+    bool mag = (c->flags & KMEM_CACHE_NOMAG) == 0;
+    if (mag) {
+        uint64_t st = interrupts_local_save();
+        void* obj = kmem_mag_pop(&c->cpu[cpu_get_id()]);
+        interrupts_local_restore(st);
+        if (obj) {
+            return obj;
+        }
+    }
+
+    irql_t old = kmem_lock();
+    void* obj = NULL;
+    if (mag) {
+        /* Re-check: this may be another CPU than the one that missed */
+        kmem_cpu_t* cc = &c->cpu[cpu_get_id()];
+        obj = kmem_mag_pop(cc);
+        if (!obj && c->depot_full) {
+            kmem_magazine_t* full = c->depot_full;
+            c->depot_full = full->next;
+            c->depot_full_count--;
+            /* Both CPU magazines are empty here */
+            if (cc->prev) {
+                kmem_depot_put_empty(c, cc->prev);
+            }
+            cc->prev = cc->loaded;
+            cc->loaded = full;
+            obj = kmem_mag_pop(cc);
+        }
+    }
+    if (!obj) {
+        obj = kmem_slab_get(c);
+        if (!obj) {
+            kmem_reap_locked();
+            obj = kmem_slab_get(c);
+        }
+        if (obj) {
+            c->slab_allocs++;
+        } else {
+            c->fails++;
+        }
+    }
+    kmem_unlock(old);
+    return obj;
+}
+
+void* kmem_cache_zalloc(kmem_cache_t* c)
+{
+    if (!c || c->ctor) {
+        return NULL;
+    }
+    void* obj = kmem_cache_alloc(c);
+    if (obj) {
+        memset(obj, 0, c->obj_size);
+    }
+    return obj;
+}
+
+void kmem_cache_free(kmem_cache_t* c, void* obj)
+{
+    if (!obj) {
+        return;
+    }
+    uint32_t idx;
+    kmem_slab_t* s = kmem_obj_slab(obj, &idx);
+    if (!c || !s || s->cache != c) {
+        PANIC("kmem_cache_free: %p not from cache %s ra=%p", obj,
+              c ? c->name : "(null)", __builtin_return_address(0));
+    }
+    if (s->bufctl[idx] != KMEM_BUFCTL_INUSE) {
+        PANIC("kmem: double free %p cache=%s ra=%p", obj, c->name, __builtin_return_address(0));
+    }
+
+    bool mag = (c->flags & KMEM_CACHE_NOMAG) == 0;
+    if (mag) {
+        uint64_t st = interrupts_local_save();
+        bool done = kmem_mag_push(&c->cpu[cpu_get_id()], obj);
+        interrupts_local_restore(st);
+        if (done) {
+            return;
+        }
+    }
+
+    irql_t old = kmem_lock();
+    if (mag) {
+        kmem_cpu_t* cc = &c->cpu[cpu_get_id()];
+        if (kmem_mag_push(cc, obj)) {
+            kmem_unlock(old);
+            return;
+        }
+        kmem_magazine_t* empty = kmem_depot_get_empty(c);
+        if (empty) {
+            /* Both CPU magazines are full (or missing) here */
+            if (cc->prev) {
+                kmem_depot_put_full(c, cc->prev);
+            }
+            cc->prev = cc->loaded;
+            cc->loaded = empty;
+            (void)kmem_mag_push(cc, obj);
+            kmem_unlock(old);
+            return;
+        }
+    }
+    kmem_slab_put(c, s, idx);
+    c->slab_frees++;
+    kmem_unlock(old);
+}
+
+void kmem_reap(void)
+{
+    irql_t old = kmem_lock();
+    kmem_reap_locked();
+    kmem_unlock(old);
+}
+
+/* ---- Whole-page allocations ---- */
+
+void* kmem_page_alloc(size_t size)
+{
+    if (size == 0 || size > (size_t)UINT32_MAX * PAGE_SIZE) {
+        return NULL;
+    }
+    uint32_t pages = (uint32_t)(ALIGN_UP(size, PAGE_SIZE) / PAGE_SIZE);
+
+    irql_t old = kmem_lock();
+    void* mem = vmm_alloc_pages(pages, PAGE_FLAG_WRITABLE);
+    if (!mem) {
+        kmem_reap_locked();
+        mem = vmm_alloc_pages(pages, PAGE_FLAG_WRITABLE);
+    }
+    if (mem) {
+        uintptr_t* slot = kmem_pagemap_slot(mem, true);
+        if (slot) {
+            *slot = ((uintptr_t)pages << 1) | KMEM_PAGEMAP_LARGE;
+            kmem_large_pages += pages;
+        } else {
+            vmm_free_pages(mem, pages);
+            mem = NULL;
+        }
+    }
+    kmem_unlock(old);
+    return mem;
+}
+
+void kmem_page_free(void* ptr)
+{
+    irql_t old = kmem_lock();
+    uintptr_t* slot = kmem_pagemap_slot(ptr, false);
+    if (!slot || !(*slot & KMEM_PAGEMAP_LARGE) || ((uintptr_t)ptr & (PAGE_SIZE - 1)) != 0) {
+        kmem_unlock(old);
+        PANIC("kmem_page_free: invalid pointer %p ra=%p", ptr, __builtin_return_address(0));
+    }
+    uint32_t pages = (uint32_t)(*slot >> 1);
+    *slot = 0;
+    kmem_large_pages -= pages;
+    vmm_free_pages(ptr, pages);
+    kmem_unlock(old);
+}
+
+bool kmem_lookup(const void* ptr, kmem_cache_t** cache, size_t* size)
+{
+    uintptr_t* slot = kmem_pagemap_slot(ptr, false);
+    if (!slot || *slot == 0) {
+        return false;
+    }
+    if (*slot & KMEM_PAGEMAP_LARGE) {
+        if (((uintptr_t)ptr & (PAGE_SIZE - 1)) != 0) {
+            return false;
+        }
+        *cache = NULL;
+        *size = (size_t)(*slot >> 1) * PAGE_SIZE;
+        return true;
+    }
+    uint32_t idx;
+    kmem_slab_t* s = kmem_obj_slab(ptr, &idx);
+    if (!s || s->bufctl[idx] != KMEM_BUFCTL_INUSE) {
+        return false;
+    }
+    *cache = s->cache;
+    *size = s->cache->obj_size;
+    return true;
+}
+
+/* ---- Statistics ---- */
+
+uint32_t kmem_cache_count(void)
+{
+    uint32_t n = 0;
+    irql_t old = kmem_lock();
+    kmem_cache_t* c;
+    TAILQ_FOREACH(c, &kmem_caches, link) {
+        n++;
+    }
+    kmem_unlock(old);
+    return n;
+}
+
+static void kmem_cache_fill_stats(const kmem_cache_t* c, kmem_cache_stats_t* out)
+{
+    uint64_t slab_inuse = 0;
+    uint64_t cached = 0;
+    const kmem_slab_t* s;
+
+    memset(out, 0, sizeof(*out));
+    strncpy(out->name, c->name, sizeof(out->name) - 1);
+    out->obj_size = c->obj_size;
+    out->slab_pages = c->slab_pages;
+    out->objs_per_slab = c->objs_per_slab;
+    out->slabs = c->slabs;
+    out->objs_total = (uint64_t)c->slabs * c->objs_per_slab;
+    out->allocs = c->slab_allocs;
+    out->frees = c->slab_frees;
+    out->fails = c->fails;
+
+    LIST_FOREACH(s, &c->partial, link) {
+        slab_inuse += s->inuse;
+    }
+    LIST_FOREACH(s, &c->full, link) {
+        slab_inuse += s->inuse;
+    }
+    for (const kmem_magazine_t* m = c->depot_full; m; m = m->next) {
+        cached += m->rounds;
+    }
+    for (uint32_t cpu = 0; cpu < CPU_MAX_COUNT; cpu++) {
+        const kmem_cpu_t* cc = &c->cpu[cpu];
+        const kmem_magazine_t* loaded = cc->loaded;
+        const kmem_magazine_t* prev = cc->prev;
+        cached += loaded ? loaded->rounds : 0;
+        cached += prev ? prev->rounds : 0;
+        out->allocs += cc->allocs;
+        out->frees += cc->frees;
+    }
+    out->objs_cached = cached;
+    out->objs_inuse = (slab_inuse > cached) ? slab_inuse - cached : 0;
+}
+
+int kmem_cache_get_stats(uint32_t index, kmem_cache_stats_t* out)
+{
+    if (!out) {
+        return RDNX_E_INVALID;
+    }
+    irql_t old = kmem_lock();
+    uint32_t i = 0;
+    kmem_cache_t* c;
+    TAILQ_FOREACH(c, &kmem_caches, link) {
+        if (i++ == index) {
+            kmem_cache_fill_stats(c, out);
+            kmem_unlock(old);
+            return RDNX_OK;
+        }
+    }
+    kmem_unlock(old);
+    return RDNX_E_NOTFOUND;
+}
+
+uint64_t kmem_page_alloc_pages(void)
+{
+    return kmem_large_pages;
+}
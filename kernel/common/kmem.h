@@ -0,0 +1,113 @@
+/**
+ * @file kmem.h
+ * @brief Object-cache (slab) kernel allocator
+ *
+ * A cache hands out fixed-size objects carved from page-backed slabs.
+ * Each CPU keeps two magazines (small stacks of free objects) per cache,
+ * so the common alloc/free touches only CPU-local state with interrupts
+ * off; magazines are exchanged with a per-cache depot, and slabs are
+ * grown or released, under the IRQL giant.
+ *
+ * Constructors run when a slab is populated and destructors when it is
+ * released, so objects sit in the cache in constructed state: an object
+ * must be returned to that state before kmem_cache_free(). Caches without
+ * a constructor may use kmem_cache_zalloc().
+ *
+ * kmalloc() is a set of "kmalloc-N" caches on top (see heap.c).
+ */
+
+#ifndef _RODNIX_COMMON_KMEM_H
+#define _RODNIX_COMMON_KMEM_H
+
+#include <stddef.h>
+#include <stdint.h>
+#include <stdbool.h>
+
+typedef struct kmem_cache kmem_cache_t;
+
+/* @return 0 on success; a failing ctor fails the slab grow */
+typedef int (*kmem_ctor_t)(void* obj, void* arg);
+typedef void (*kmem_dtor_t)(void* obj, void* arg);
+
+#define KMEM_NAME_MAX       24
+#define KMEM_ALIGN_DEFAULT  16
+#define KMEM_SLAB_MAX_PAGES 16
+
+/* kmem_cache_create() flags */
+#define KMEM_CACHE_NOMAG    0x1u /* No per-CPU magazines (always slab path) */
+
+typedef struct kmem_cache_stats {
+    char name[KMEM_NAME_MAX];
+    uint32_t obj_size;
+    uint32_t slab_pages;
+    uint32_t objs_per_slab;
+    uint32_t slabs;
+    uint64_t objs_total;   /* Objects in all slabs */
+    uint64_t objs_inuse;   /* Held by callers */
+    uint64_t objs_cached;  /* Free in magazines (CPU + depot) */
+    uint64_t allocs;
+    uint64_t frees;
+    uint64_t fails;
+} kmem_cache_stats_t;
+
+/**
+ * Set up the page map and the internal caches. Called once from
+ * heap_init(), before any cache is created.
+ */
+void kmem_init(void);
+
+/**
+ * Create a cache.
+ * @param name Short name shown in statistics (copied)
+ * @param size Object size in bytes
+ * @param align Object alignment, power of two; 0 means KMEM_ALIGN_DEFAULT
+ * @param ctor Optional constructor, dtor optional destructor, arg passed to both
+ * @param flags KMEM_CACHE_*
+ * @return Cache, or NULL if the geometry is impossible or memory is short
+ */
+kmem_cache_t* kmem_cache_create(const char* name, size_t size, size_t align,
+                                kmem_ctor_t ctor, kmem_dtor_t dtor, void* arg,
+                                uint32_t flags);
+
+/**
+ * Destroy an idle cache.
+ * @return RDNX_OK, RDNX_E_BUSY if objects are still allocated
+ */
+int kmem_cache_destroy(kmem_cache_t* cache);
+
+/* @return Constructed object, or NULL when out of memory */
+void* kmem_cache_alloc(kmem_cache_t* cache);
+
+/* Zero-filled object; only for caches without a constructor */
+void* kmem_cache_zalloc(kmem_cache_t* cache);
+
+/* Return an object; panics on a foreign pointer or an obvious double free */
+void kmem_cache_free(kmem_cache_t* cache, void* obj);
+
+/* Give cached magazines and empty slabs of every cache back to the PMM */
+void kmem_reap(void);
+
+/* Number of caches, and statistics by index (0..count-1) */
+uint32_t kmem_cache_count(void);
+int kmem_cache_get_stats(uint32_t index, kmem_cache_stats_t* out);
+
+/* ---- Used by heap.c ---- */
+
+/* Whole-page allocation recorded in the page map (for kmalloc > slab sizes) */
+void* kmem_page_alloc(size_t size);
+
+/**
+ * Classify a kernel pointer.
+ * @param cache Set to the owning cache for slab objects, NULL for page allocations
+ * @param size Usable size of the allocation
+ * @return false if ptr is not the start of a live kmem allocation
+ */
+bool kmem_lookup(const void* ptr, kmem_cache_t** cache, size_t* size);
+
+/* Free a kmem_page_alloc() block */
+void kmem_page_free(void* ptr);
+
+/* Pages currently held by kmem_page_alloc() blocks */
+uint64_t kmem_page_alloc_pages(void);
+
+#endif /* _RODNIX_COMMON_KMEM_H */
@@ -2,10 +2,13 @@
 
 #include "../../include/common.h"
 #include "../../include/error.h"
+#include "../core/memory.h"
 #include "../fs/vfs.h"
 #include "elf.h"
 #include "heap.h"
 
+#define KMOD_PAGE_SIZE 4096u
+
 typedef struct {
     int used;
     kmod_info_t info;
@@ -32,6 +35,19 @@ static void kmod_copy_text(char* dst, uint32_t cap, const char* src)
     dst[cap - 1u] = '\0';
 }
 
+/* Module images live in the executable module window, not the heap */
+static uint32_t kmod_image_pages(uint64_t size)
+{
+    return (uint32_t)((size + KMOD_PAGE_SIZE - 1u) / KMOD_PAGE_SIZE);
+}
+
+static void kmod_image_free(void* mem, uint64_t size)
+{
+    if (mem) {
+        vmm_free_exec(mem, kmod_image_pages(size));
+    }
+}
+
 static int kmod_find_slot(const char* name)
 {
     if (!name || !name[0]) {
@@ -199,12 +215,11 @@ static int kmod_parse_elf_module(const uint8_t* image,
 
     void* image_mem = NULL;
     if (total > 0) {
-        image_mem = kmalloc((size_t)total);
+        image_mem = vmm_alloc_exec(kmod_image_pages(total));
         if (!image_mem) {
             kfree(sec_runtime);
             return RDNX_E_NOMEM;
         }
-        memset(image_mem, 0, (size_t)total);
     }
 
     uint64_t cursor = 0;
@@ -218,9 +233,7 @@ static int kmod_parse_elf_module(const uint8_t* image,
         sec_runtime[i] = (uint64_t)(uintptr_t)image_mem + cursor;
         if (sec->sh_type != SHT_NOBITS) {
             if (sec->sh_offset >= size || (sec->sh_offset + sec->sh_size) > size) {
-                if (image_mem) {
-                    kfree(image_mem);
-                }
+                kmod_image_free(image_mem, total);
                 kfree(sec_runtime);
                 return RDNX_E_INVALID;
             }
@@ -235,9 +248,7 @@ static int kmod_parse_elf_module(const uint8_t* image,
             ss->sh_offset >= size ||
             (ss->sh_offset + ss->sh_size) > size ||
             ss->sh_link >= eh->e_shnum) {
-            if (image_mem) {
-                kfree(image_mem);
-            }
+            kmod_image_free(image_mem, total);
             kfree(sec_runtime);
             return RDNX_E_INVALID;
         }
@@ -245,9 +256,7 @@ static int kmod_parse_elf_module(const uint8_t* image,
         if (strsec->sh_type != SHT_STRTAB ||
             strsec->sh_offset >= size ||
             (strsec->sh_offset + strsec->sh_size) > size) {
-            if (image_mem) {
-                kfree(image_mem);
-            }
+            kmod_image_free(image_mem, total);
             kfree(sec_runtime);
             return RDNX_E_INVALID;
         }
@@ -382,18 +391,14 @@ int kmod_load(const char* path)
 
     int idx = kmod_find_slot(hdr.name);
     if (idx >= 0) {
-        if (mod_image_mem) {
-            kfree(mod_image_mem);
-        }
+        kmod_image_free(mod_image_mem, mod_image_mem_size);
         return RDNX_E_BUSY;
     }
 
     if (mod_init) {
         int init_rc = mod_init();
         if (init_rc != 0) {
-            if (mod_image_mem) {
-                kfree(mod_image_mem);
-            }
+            kmod_image_free(mod_image_mem, mod_image_mem_size);
             return RDNX_E_GENERIC;
         }
     }
@@ -426,7 +431,7 @@ int kmod_unload(const char* name)
         g_kmods[idx].mod_fini();
     }
     if (g_kmods[idx].image_mem) {
-        kfree(g_kmods[idx].image_mem);
+        kmod_image_free(g_kmods[idx].image_mem, g_kmods[idx].image_mem_size);
         g_kmods[idx].image_mem = NULL;
         g_kmods[idx].image_mem_size = 0;
     }
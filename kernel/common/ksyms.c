@@ -0,0 +1,51 @@
+/**
+ * @file ksyms.c
+ * @brief Embedded kernel symbol table lookup
+ */
+
+#include "ksyms.h"
+#include "../../include/common.h"
+#include <stddef.h>
+
+const char* ksyms_lookup(uint64_t addr, uint64_t* offset_out)
+{
+    uint32_t count = ksyms_count;
+    if (count == 0 || addr < KSYMS_BASE || addr - KSYMS_BASE > 0xFFFFFFFFULL) {
+        return NULL;
+    }
+
+    uint32_t off = (uint32_t)(addr - KSYMS_BASE);
+    if (off < ksyms_table[0].offset || off >= ksyms_table[count].offset) {
+        return NULL;
+    }
+
+    /* Last entry with offset <= off */
+    uint32_t lo = 0;
+    uint32_t hi = count;
+    while (hi - lo > 1) {
+        uint32_t mid = lo + (hi - lo) / 2;
+        if (ksyms_table[mid].offset <= off) {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+
+    if (offset_out) {
+        *offset_out = off - ksyms_table[lo].offset;
+    }
+    return &ksyms_names[ksyms_table[lo].name];
+}
+
+uint64_t ksyms_resolve(const char* name)
+{
+    if (!name) {
+        return 0;
+    }
+    for (uint32_t i = 0; i < ksyms_count; i++) {
+        if (strcmp(&ksyms_names[ksyms_table[i].name], name) == 0) {
+            return KSYMS_BASE + ksyms_table[i].offset;
+        }
+    }
+    return 0;
+}
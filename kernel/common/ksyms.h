@@ -0,0 +1,41 @@
+/**
+ * @file ksyms.h
+ * @brief Embedded kernel symbol table
+ *
+ * The table is generated at link time (scripts/mkksyms.py, second link
+ * pass) and covers kernel text only. Addresses are stored as 32-bit
+ * offsets from KSYMS_BASE to keep the image small.
+ */
+
+#ifndef _RODNIX_COMMON_KSYMS_H
+#define _RODNIX_COMMON_KSYMS_H
+
+#include <stdint.h>
+
+#define KSYMS_BASE 0xFFFFFFFF80000000ULL
+
+typedef struct {
+    uint32_t offset; /* Symbol address - KSYMS_BASE */
+    uint32_t name;   /* Offset into ksyms_names */
+} ksym_entry_t;
+
+/* Generated; ksyms_table has ksyms_count + 1 entries (end-of-text marker) */
+extern const uint32_t ksyms_count;
+extern const ksym_entry_t ksyms_table[];
+extern const char ksyms_names[];
+
+/**
+ * Find the function containing an address
+ * @param addr Kernel text address
+ * @param offset_out Distance from the symbol start (optional)
+ * @return Symbol name, or NULL if addr is outside known text
+ */
+const char* ksyms_lookup(uint64_t addr, uint64_t* offset_out);
+
+/**
+ * Find a symbol by name (linear scan, for the debugger)
+ * @return Symbol address, or 0 if there is no such symbol
+ */
+uint64_t ksyms_resolve(const char* name);
+
+#endif /* _RODNIX_COMMON_KSYMS_H */
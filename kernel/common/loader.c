@@ -1,6 +1,6 @@
 /**
  * @file loader.c
- * @brief Minimal userland loader placeholder
+ * @brief ELF loader: segments are mapped from the file's vm_object
  */
 
 #include "loader.h"
@@ -12,13 +12,15 @@
 #include "../fs/vfs.h"
 #include "../core/task.h"
 #include "../vm/vm_map.h"
-#include "heap.h"
+#include "../vm/vm_aslr.h"
+#include "../vm/vm_pager.h"
 #include "bootlog.h"
 #include "../../include/console.h"
 #include "../../include/error.h"
 #include "../../include/common.h"
 
 #define USER_STACK_TOP 0x0000000080000000ULL
+#define USER_STACK_MAX (64ULL * 1024 * 1024) /* Reservation cap for an unlimited RLIMIT_STACK */
 #define USER_PAGE_SIZE ARCH_PAGE_SIZE_4KB
 #define LOADER_ARG_MAX 16
 #define LOADER_ENV_MAX 32
@@ -26,6 +28,17 @@
 #define LOADER_ENV_STR_MAX 128
 #define ELFOSABI_SYSV 0
 #define ELFOSABI_LINUX 3
+#define LOADER_ET_DYN_BASE 0x0000000010000000ULL /* Before the ASLR offset */
+#define LOADER_AUXV_MAX 8 /* Pairs, AT_NULL included */
+
+/* Linux auxiliary vector tags */
+#define AT_NULL 0
+#define AT_PHDR 3
+#define AT_PHENT 4
+#define AT_PHNUM 5
+#define AT_PAGESZ 6
+#define AT_BASE 7
+#define AT_ENTRY 9
 
 static inline uint64_t align_down(uint64_t v, uint64_t align)
 {
@@ -37,124 +50,149 @@ static inline uint64_t align_up(uint64_t v, uint64_t align)
     return (v + align - 1) & ~(align - 1);
 }
 
-static int loader_read_file(const char* path, uint8_t** out_buf, size_t* out_size)
+/* Copy [off, off + len) of a file object through its pager */
+static int loader_object_read(vm_object_t* obj, uint64_t off, void* buf, size_t len)
 {
-    if (!path || !out_buf || !out_size) {
+    if (off > obj->size || len > obj->size - off) {
         return RDNX_E_INVALID;
     }
+    uint8_t* out = (uint8_t*)buf;
+    while (len > 0) {
+        uint64_t in_page = off & (USER_PAGE_SIZE - 1u);
+        size_t chunk = (size_t)(USER_PAGE_SIZE - in_page);
+        if (chunk > len) {
+            chunk = len;
+        }
+        uint64_t phys = 0;
+        int rc = vm_pager_getpages(obj, off / USER_PAGE_SIZE, 1, 0, &phys);
+        if (rc != RDNX_OK) {
+            return rc;
+        }
+        memcpy(out, (const uint8_t*)ARCH_PHYS_TO_VIRT(phys) + in_page, chunk);
+        vm_pager_release(&phys, 1);
+        out += chunk;
+        off += chunk;
+        len -= chunk;
+    }
+    return RDNX_OK;
+}
 
+/* File object of an executable, referenced for the caller */
+static int loader_open_object(const char* path, vm_object_t** out_obj)
+{
     vfs_file_t file;
     if (vfs_open(path, VFS_OPEN_READ, &file) != 0) {
         return RDNX_E_NOTFOUND;
     }
-
-    size_t size_hint = 0;
-    if (file.node && file.node->inode) {
-        size_hint = file.node->inode->size;
+    vm_object_t* obj = vfs_node_object(file.node);
+    if (obj) {
+        vm_object_ref(obj);
     }
-    if (size_hint == 0) {
-        size_hint = 4096;
+    vfs_close(&file);
+    if (!obj) {
+        return RDNX_E_INVALID;
     }
+    *out_obj = obj;
+    return RDNX_OK;
+}
 
-    uint8_t* buf = (uint8_t*)kmalloc(size_hint);
-    if (!buf) {
-        vfs_close(&file);
-        return RDNX_E_NOMEM;
-    }
+static int loader_add_segment(loader_image_t* img,
+                              uint64_t start,
+                              uint64_t end,
+                              uint32_t prot,
+                              vm_object_t* obj,
+                              uint64_t object_offset)
+{
+    if (img->seg_count >= LOADER_MAX_SEGMENTS) {
+        return RDNX_E_BUSY;
+    }
+    loader_segment_t* seg = &img->segs[img->seg_count++];
+    seg->start = start;
+    seg->end = end;
+    seg->prot = prot;
+    seg->object = obj;
+    seg->object_offset = object_offset;
+    vm_object_ref(obj);
+    return RDNX_OK;
+}
 
-    size_t len = 0;
-    for (;;) {
-        size_t chunk = size_hint - len;
-        if (chunk > 1024) {
-            chunk = 1024;
-        }
-        if (chunk == 0) {
-            break;
-        }
-        int r = vfs_read(&file, buf + len, chunk);
-        if (r <= 0) {
-            break;
-        }
-        len += (size_t)r;
+static void loader_release_segments(loader_image_t* img)
+{
+    for (uint32_t i = 0; i < img->seg_count; i++) {
+        vm_object_unref(img->segs[i].object);
+        img->segs[i].object = NULL;
     }
-
-    vfs_close(&file);
-    *out_buf = buf;
-    *out_size = len;
-    return RDNX_OK;
+    img->seg_count = 0;
 }
 
-static int loader_map_segment(uint64_t pml4_phys,
-                              const uint8_t* image,
-                              size_t image_size,
-                              const elf64_phdr_t* ph,
-                              loader_image_t* out_img)
+/*
+ * Describe a PT_LOAD segment as mappings: whole file pages come from the
+ * file object through its pager (copied on write), the rest of memsz is
+ * an anonymous object. The page holding the end of the file data is
+ * copied into that object up front with the bytes past p_filesz zeroed.
+ */
+static int loader_map_segment(vm_object_t* obj, const elf64_phdr_t* ph, loader_image_t* out_img)
 {
-    if (!ph || !image) {
+    if (!ph || !obj) {
         return RDNX_E_INVALID;
     }
     if (ph->p_memsz == 0) {
         return RDNX_OK;
     }
-    if (ph->p_offset + ph->p_filesz > image_size) {
+    if (ph->p_filesz > ph->p_memsz ||
+        ph->p_offset > obj->size || ph->p_filesz > obj->size - ph->p_offset ||
+        ph->p_vaddr + ph->p_memsz < ph->p_vaddr) {
         return RDNX_E_INVALID;
     }
+    if (((ph->p_vaddr - ph->p_offset) & (USER_PAGE_SIZE - 1u)) != 0) {
+        /* Not mappable: file offset and address disagree within a page */
+        return RDNX_E_UNSUPPORTED;
+    }
 
-    uint64_t seg_start = ph->p_vaddr;
-    uint64_t seg_end = ph->p_vaddr + ph->p_memsz;
-
-    uint64_t page_start = align_down(seg_start, USER_PAGE_SIZE);
-    uint64_t page_end = align_up(seg_end, USER_PAGE_SIZE);
+    uint32_t prot = VM_PROT_READ |
+                    ((ph->p_flags & PF_W) ? VM_PROT_WRITE : 0u) |
+                    ((ph->p_flags & PF_X) ? VM_PROT_EXEC : 0u);
+    uint64_t page_start = align_down(ph->p_vaddr, USER_PAGE_SIZE);
+    uint64_t file_off = align_down(ph->p_offset, USER_PAGE_SIZE);
+    uint64_t file_end = ph->p_vaddr + ph->p_filesz;
+    uint64_t mem_end = align_up(ph->p_vaddr + ph->p_memsz, USER_PAGE_SIZE);
+    uint64_t file_map_end = (ph->p_memsz > ph->p_filesz) ? align_down(file_end, USER_PAGE_SIZE)
+                                                         : align_up(file_end, USER_PAGE_SIZE);
 
-    uint64_t flags = PTE_PRESENT | PTE_USER;
-    if (ph->p_flags & PF_W) {
-        flags |= PTE_RW;
+    if (ph->p_filesz > 0 && file_map_end > page_start) {
+        int rc = loader_add_segment(out_img, page_start, file_map_end, prot, obj, file_off);
+        if (rc != RDNX_OK) {
+            return rc;
+        }
     }
-    if ((ph->p_flags & PF_X) == 0) {
-        flags |= PTE_NX;
+    uint64_t anon_start = (file_map_end > page_start) ? file_map_end : page_start;
+    if (mem_end <= anon_start) {
+        return RDNX_OK;
     }
 
-    for (uint64_t va = page_start; va < page_end; va += USER_PAGE_SIZE) {
-        uint64_t phys = pmm_alloc_page_in_zone(PMM_ZONE_NORMAL);
+    vm_object_t* anon = vm_object_create(VM_OBJECT_ANON, mem_end - anon_start);
+    if (!anon) {
+        return RDNX_E_NOMEM;
+    }
+    int rc = RDNX_OK;
+    if (file_end > anon_start) {
+        uint64_t phys = vm_pager_alloc_zero_page();
         if (!phys) {
+            vm_object_unref(anon);
             return RDNX_E_NOMEM;
         }
-        if (paging_map_page_4kb_pml4(pml4_phys, va, phys, flags) != RDNX_OK) {
-            return RDNX_E_GENERIC;
-        }
-
-        uint8_t* dst = (uint8_t*)ARCH_PHYS_TO_VIRT(phys);
-        memset(dst, 0, USER_PAGE_SIZE);
-
-        if (va < seg_start) {
-            uint64_t skip = seg_start - va;
-            size_t avail = USER_PAGE_SIZE - (size_t)skip;
-            size_t copy = (ph->p_filesz < avail) ? (size_t)ph->p_filesz : avail;
-            if (copy > 0) {
-                memcpy(dst + skip, image + ph->p_offset, copy);
-            }
-        } else {
-            uint64_t seg_off = va - seg_start;
-            if (seg_off < ph->p_filesz) {
-                size_t copy = ph->p_filesz - seg_off;
-                if (copy > USER_PAGE_SIZE) {
-                    copy = USER_PAGE_SIZE;
-                }
-                memcpy(dst, image + ph->p_offset + seg_off, copy);
-            }
+        rc = loader_object_read(obj, file_off + (anon_start - page_start),
+                                ARCH_PHYS_TO_VIRT(phys), (size_t)(file_end - anon_start));
+        if (rc == RDNX_OK) {
+            rc = vm_object_set_resident_page(anon, 0, phys);
         }
+        vm_pager_release(&phys, 1);
     }
-
-    if (out_img && out_img->seg_count < LOADER_MAX_SEGMENTS) {
-        loader_segment_t* seg = &out_img->segs[out_img->seg_count++];
-        seg->start = page_start;
-        seg->end = page_end;
-        seg->prot = VM_PROT_READ |
-                    ((ph->p_flags & PF_W) ? VM_PROT_WRITE : 0u) |
-                    ((ph->p_flags & PF_X) ? VM_PROT_EXEC : 0u);
+    if (rc == RDNX_OK) {
+        rc = loader_add_segment(out_img, anon_start, mem_end, prot, anon, 0);
     }
-
-    return RDNX_OK;
+    vm_object_unref(anon);
+    return rc;
 }
 
 static int loader_map_stack(uint64_t pml4_phys, loader_image_t* out_img)
@@ -163,7 +201,7 @@ static int loader_map_stack(uint64_t pml4_phys, loader_image_t* out_img)
         return RDNX_E_INVALID;
     }
 
-    out_img->stack_bottom = USER_STACK_TOP - (uint64_t)LOADER_USER_STACK_PAGES * USER_PAGE_SIZE;
+    out_img->stack_bottom = out_img->stack_top - (uint64_t)LOADER_USER_STACK_PAGES * USER_PAGE_SIZE;
     for (uint32_t i = 0; i < LOADER_USER_STACK_PAGES; i++) {
         uint64_t va = out_img->stack_bottom + (uint64_t)i * USER_PAGE_SIZE;
         uint64_t phys = pmm_alloc_page_in_zone(PMM_ZONE_NORMAL);
@@ -177,7 +215,7 @@ static int loader_map_stack(uint64_t pml4_phys, loader_image_t* out_img)
             }
             return RDNX_E_NOMEM;
         }
-        uint64_t flags = PTE_PRESENT | PTE_USER | PTE_RW;
+        uint64_t flags = PTE_PRESENT | PTE_USER | PTE_RW | PTE_NX;
         if (paging_map_page_4kb_pml4(pml4_phys, va, phys, flags) != RDNX_OK) {
             /* Free the unmap-failed page, then rollback prior pages */
             pmm_free_page(phys);
@@ -193,17 +231,32 @@ static int loader_map_stack(uint64_t pml4_phys, loader_image_t* out_img)
         out_img->stack_phys[i] = phys;
     }
 
-    out_img->user_stack = USER_STACK_TOP - 16;
+    out_img->user_stack = out_img->stack_top - 16;
     return RDNX_OK;
 }
 
+/*
+ * The stack entry reserves RLIMIT_STACK below the stack top; only the
+ * pages loader_map_stack() filled are present, the rest fault in.
+ */
+static uint64_t loader_stack_reserve(const task_t* task)
+{
+    uint64_t min = (uint64_t)LOADER_USER_STACK_PAGES * USER_PAGE_SIZE;
+    uint64_t len = task ? task->rlimit[TASK_RLIMIT_STACK].cur : min;
+    if (len > USER_STACK_MAX) {
+        len = USER_STACK_MAX;
+    }
+    len = align_down(len, USER_PAGE_SIZE);
+    return len < min ? min : len;
+}
+
 static int loader_stack_write(const loader_image_t* img, uint64_t user_va, const void* src, size_t len)
 {
     if (!img || !src || len == 0) {
         return RDNX_E_INVALID;
     }
 
-    uint64_t stack_top = USER_STACK_TOP;
+    uint64_t stack_top = img->stack_top;
     if (user_va < img->stack_bottom || user_va + len > stack_top) {
         return RDNX_E_INVALID;
     }
@@ -232,6 +285,29 @@ static int loader_stack_write(const loader_image_t* img, uint64_t user_va, const
     return RDNX_OK;
 }
 
+/* Linux auxiliary vector as tag/value words; AT_PHDR only if the headers are mapped */
+static uint32_t loader_build_auxv(const loader_image_t* img, uint64_t* auxv)
+{
+    uint32_t n = 0;
+    if (img->phdr) {
+        auxv[n++] = AT_PHDR;
+        auxv[n++] = img->phdr;
+        auxv[n++] = AT_PHENT;
+        auxv[n++] = sizeof(elf64_phdr_t);
+        auxv[n++] = AT_PHNUM;
+        auxv[n++] = img->phnum;
+    }
+    auxv[n++] = AT_PAGESZ;
+    auxv[n++] = USER_PAGE_SIZE;
+    auxv[n++] = AT_BASE;
+    auxv[n++] = 0; /* No interpreter */
+    auxv[n++] = AT_ENTRY;
+    auxv[n++] = img->entry;
+    auxv[n++] = AT_NULL;
+    auxv[n++] = 0;
+    return n;
+}
+
 static int loader_prepare_user_args(loader_image_t* img,
                                     int argc,
                                     const char* const argv[],
@@ -262,6 +338,8 @@ static int loader_prepare_user_args(loader_image_t* img,
     uint64_t sp = img->user_stack;
     uint64_t argv_user[LOADER_ARG_MAX];
     uint64_t envp_user[LOADER_ENV_MAX];
+    uint64_t auxv[LOADER_AUXV_MAX * 2];
+    uint32_t auxc = 0;
 
     for (int i = envc - 1; i >= 0; i--) {
         const char* s = envp[i] ? envp[i] : "";
@@ -301,7 +379,8 @@ static int loader_prepare_user_args(loader_image_t* img,
     uint64_t env_slots = (uint64_t)(envc + 1);
     if (img->abi == TASK_ABI_LINUX) {
         /* This guest ABI stack layout expects an auxv list terminated by AT_NULL. */
-        env_slots += 2; /* AT_NULL, 0 */
+        auxc = loader_build_auxv(img, auxv);
+        env_slots += auxc;
     }
     sp -= env_slots * sizeof(uint64_t);
     if (sp < img->stack_bottom) {
@@ -318,12 +397,9 @@ static int loader_prepare_user_args(loader_image_t* img,
     if (wr != RDNX_OK) {
         return wr;
     }
-    if (img->abi == TASK_ABI_LINUX) {
-        wr = loader_stack_write(img, sp + (uint64_t)(envc + 1) * sizeof(uint64_t), &null_ptr, sizeof(uint64_t));
-        if (wr != RDNX_OK) {
-            return wr;
-        }
-        wr = loader_stack_write(img, sp + (uint64_t)(envc + 2) * sizeof(uint64_t), &null_ptr, sizeof(uint64_t));
+    if (auxc > 0) {
+        wr = loader_stack_write(img, sp + (uint64_t)(envc + 1) * sizeof(uint64_t), auxv,
+                                (size_t)auxc * sizeof(uint64_t));
         if (wr != RDNX_OK) {
             return wr;
         }
@@ -366,53 +442,92 @@ static int loader_prepare_user_args(loader_image_t* img,
     return RDNX_OK;
 }
 
-static int loader_load_elf(const uint8_t* image, size_t size, loader_image_t* out)
+/*
+ * ET_DYN images (static PIE) are loaded at LOADER_ET_DYN_BASE plus the
+ * ASLR offset; there is no dynamic linker, so PT_INTERP is refused.
+ * @task is the one that will run the image, NULL outside exec.
+ */
+static int loader_load_elf(vm_object_t* obj, const task_t* task, loader_image_t* out)
 {
-    if (!image || size < sizeof(elf64_ehdr_t) || !out) {
+    if (!obj || !out) {
         return RDNX_E_INVALID;
     }
 
-    const elf64_ehdr_t* eh = (const elf64_ehdr_t*)image;
-    if (eh->e_magic != ELF_MAGIC ||
-        eh->e_class != ELFCLASS64 ||
-        eh->e_data != ELFDATA2LSB ||
-        eh->e_type != ET_EXEC ||
-        eh->e_machine != EM_X86_64) {
+    elf64_ehdr_t eh;
+    if (loader_object_read(obj, 0, &eh, sizeof(eh)) != RDNX_OK) {
         return RDNX_E_INVALID;
     }
-    if (eh->e_phoff + (uint64_t)eh->e_phnum * eh->e_phentsize > size) {
+    if (eh.e_magic != ELF_MAGIC ||
+        eh.e_class != ELFCLASS64 ||
+        eh.e_data != ELFDATA2LSB ||
+        (eh.e_type != ET_EXEC && eh.e_type != ET_DYN) ||
+        eh.e_machine != EM_X86_64 ||
+        eh.e_phentsize != sizeof(elf64_phdr_t)) {
         return RDNX_E_INVALID;
     }
 
-    uint64_t pml4_phys = paging_create_user_pml4();
-    if (!pml4_phys) {
-        return RDNX_E_NOMEM;
+    uint64_t bias = 0;
+    if (eh.e_type == ET_DYN) {
+        bias = LOADER_ET_DYN_BASE + vm_aslr_offset(task, VM_ASLR_EXEC);
     }
-
+    uint64_t phdrs_len = (uint64_t)eh.e_phnum * sizeof(elf64_phdr_t);
     out->seg_count = 0;
     out->brk_base = 0;
-    const elf64_phdr_t* ph = (const elf64_phdr_t*)(image + eh->e_phoff);
-    for (uint16_t i = 0; i < eh->e_phnum; i++) {
-        if (ph[i].p_type != PT_LOAD) {
-            continue;
+    out->phdr = 0;
+    out->phnum = eh.e_phnum;
+    for (uint16_t i = 0; i < eh.e_phnum; i++) {
+        elf64_phdr_t ph;
+        int ret = loader_object_read(obj, eh.e_phoff + (uint64_t)i * sizeof(ph), &ph, sizeof(ph));
+        if (ret == RDNX_OK && ph.p_type == PT_INTERP && eh.e_type == ET_DYN) {
+            ret = RDNX_E_UNSUPPORTED;
+        }
+        if (ret == RDNX_OK && (ph.p_type == PT_LOAD || ph.p_type == PT_PHDR)) {
+            if (ph.p_vaddr >= ARCH_KERNEL_VIRT_BASE - bias) {
+                ret = RDNX_E_INVALID;
+            } else {
+                ph.p_vaddr += bias;
+            }
         }
-        if (ph[i].p_vaddr >= ARCH_KERNEL_VIRT_BASE) {
-            return RDNX_E_INVALID;
+        if (ret == RDNX_OK && ph.p_type == PT_LOAD) {
+            ret = loader_map_segment(obj, &ph, out);
         }
-        int ret = loader_map_segment(pml4_phys, image, size, &ph[i], out);
         if (ret != RDNX_OK) {
+            loader_release_segments(out);
             return ret;
         }
-        uint64_t seg_end = align_up(ph[i].p_vaddr + ph[i].p_memsz, USER_PAGE_SIZE);
+        if (ph.p_type == PT_PHDR) {
+            out->phdr = ph.p_vaddr;
+            continue;
+        }
+        if (ph.p_type != PT_LOAD) {
+            continue;
+        }
+        if (!out->phdr && eh.e_phoff >= ph.p_offset &&
+            phdrs_len <= ph.p_filesz && eh.e_phoff - ph.p_offset <= ph.p_filesz - phdrs_len) {
+            /* The headers are part of this segment's file data */
+            out->phdr = ph.p_vaddr + (eh.e_phoff - ph.p_offset);
+        }
+        uint64_t seg_end = align_up(ph.p_vaddr + ph.p_memsz, USER_PAGE_SIZE);
         if (seg_end > out->brk_base) {
             out->brk_base = seg_end;
         }
     }
 
+    if (out->brk_base) {
+        out->brk_base += vm_aslr_offset(task, VM_ASLR_BRK);
+    }
+
+    uint64_t pml4_phys = paging_create_user_pml4();
+    if (!pml4_phys) {
+        loader_release_segments(out);
+        return RDNX_E_NOMEM;
+    }
+
     out->pml4_phys = pml4_phys;
-    out->entry = eh->e_entry;
-    out->abi = (eh->e_osabi == ELFOSABI_LINUX) ? TASK_ABI_LINUX : TASK_ABI_NATIVE;
+    out->entry = eh.e_entry + bias;
+    out->abi = (eh.e_osabi == ELFOSABI_LINUX) ? TASK_ABI_LINUX : TASK_ABI_NATIVE;
     out->user_stack = 0;
+    out->stack_top = USER_STACK_TOP - vm_aslr_offset(task, VM_ASLR_STACK);
     out->stack_bottom = 0;
     for (uint32_t i = 0; i < LOADER_USER_STACK_PAGES; i++) {
         out->stack_phys[i] = 0;
@@ -420,6 +535,7 @@ static int loader_load_elf(const uint8_t* image, size_t size, loader_image_t* ou
 
     int ret = loader_map_stack(pml4_phys, out);
     if (ret != RDNX_OK) {
+        loader_release_segments(out);
         return ret;
     }
     return RDNX_OK;
@@ -430,10 +546,34 @@ int loader_init(void)
     return 0;
 }
 
+/* Load an in-memory image through a memory-only file object */
 int loader_load_image(const void* image, size_t size)
 {
+    if (!image || size == 0) {
+        return RDNX_E_INVALID;
+    }
+    vm_object_t* obj = vm_vnode_pager_alloc(size, NULL, NULL);
+    if (!obj) {
+        return RDNX_E_NOMEM;
+    }
+    int ret = RDNX_OK;
+    for (uint64_t off = 0; off < size && ret == RDNX_OK; off += USER_PAGE_SIZE) {
+        uint64_t phys = 0;
+        ret = vm_pager_getpages(obj, off / USER_PAGE_SIZE, 1, 0, &phys);
+        if (ret == RDNX_OK) {
+            size_t chunk = (size - off < USER_PAGE_SIZE) ? (size_t)(size - off) : USER_PAGE_SIZE;
+            memcpy(ARCH_PHYS_TO_VIRT(phys), (const uint8_t*)image + off, chunk);
+            vm_pager_release(&phys, 1);
+        }
+    }
     loader_image_t img;
-    int ret = loader_load_elf((const uint8_t*)image, size, &img);
+    if (ret == RDNX_OK) {
+        ret = loader_load_elf(obj, NULL, &img);
+    }
+    if (ret == RDNX_OK) {
+        loader_release_segments(&img);
+    }
+    vm_object_unref(obj);
     return ret;
 }
 
@@ -480,9 +620,8 @@ int loader_execve_ex(const char* path,
     if (!path) {
         return RDNX_E_INVALID;
     }
-    uint8_t* buf = NULL;
-    size_t size = 0;
-    int ret = loader_read_file(path, &buf, &size);
+    vm_object_t* obj = NULL;
+    int ret = loader_open_object(path, &obj);
     if (ret != RDNX_OK) {
         if (bootlog_is_verbose()) {
             kputs("[LOADER] file not found\n");
@@ -491,8 +630,8 @@ int loader_execve_ex(const char* path,
     }
 
     loader_image_t img;
-    ret = loader_load_elf(buf, size, &img);
-    kfree(buf);
+    ret = loader_load_elf(obj, task_get_current(), &img);
+    vm_object_unref(obj); /* Segments hold their own references */
     if (ret != RDNX_OK) {
         if (bootlog_is_verbose()) {
             kputs("[LOADER] ELF load failed\n");
@@ -510,6 +649,7 @@ int loader_execve_ex(const char* path,
         rsp0 = (uint64_t)(uintptr_t)cur->stack + cur->stack_size - 16;
     }
     if (!rsp0) {
+        loader_release_segments(&img);
         return RDNX_E_INVALID;
     }
 
@@ -517,12 +657,14 @@ int loader_execve_ex(const char* path,
     uint64_t envp_ptr = 0;
     ret = loader_prepare_user_args(&img, argc, argv, envp, &argv_ptr, &envp_ptr);
     if (ret != RDNX_OK) {
+        loader_release_segments(&img);
         return ret;
     }
 
     if (pre_commit) {
         ret = pre_commit(pre_commit_ctx);
         if (ret != RDNX_OK) {
+            loader_release_segments(&img);
             return ret;
         }
     }
@@ -534,20 +676,28 @@ int loader_execve_ex(const char* path,
         if (vm_task_prepare_exec(cur->task, img.pml4_phys) == RDNX_OK) {
             for (uint32_t i = 0; i < img.seg_count; i++) {
                 const loader_segment_t* s = &img.segs[i];
-                (void)vm_task_map_fixed(cur->task,
-                                        s->start,
-                                        s->end - s->start,
-                                        s->prot,
-                                        VM_MAP_F_PRIVATE);
+                uint32_t flags = VM_MAP_F_PRIVATE;
+                if (s->object->type != VM_OBJECT_FILE) {
+                    flags |= VM_MAP_F_ANON;
+                }
+                (void)vm_task_map_fixed_object(cur->task,
+                                               s->start,
+                                               s->end - s->start,
+                                               s->prot,
+                                               flags,
+                                               s->object,
+                                               s->object_offset);
             }
+            uint64_t stack_len = loader_stack_reserve(cur->task);
             (void)vm_task_map_fixed(cur->task,
-                                    img.stack_bottom,
-                                    (uint64_t)LOADER_USER_STACK_PAGES * USER_PAGE_SIZE,
+                                    img.stack_top - stack_len,
+                                    stack_len,
                                     VM_PROT_READ | VM_PROT_WRITE,
                                     VM_MAP_F_STACK | VM_MAP_F_PRIVATE);
             (void)vm_task_set_brk_base(cur->task, img.brk_base);
         }
     }
+    loader_release_segments(&img);
     if (bootlog_is_verbose()) {
         kputs("[LOADER] entering userland\n");
     }
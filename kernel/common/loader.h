@@ -7,21 +7,28 @@
 #define LOADER_USER_STACK_PAGES 4
 #define LOADER_MAX_SEGMENTS 16
 
+struct vm_object;
+
 typedef struct {
     uint64_t start;
     uint64_t end;
     uint32_t prot;
+    struct vm_object* object;   /* File object, or anonymous for bss */
+    uint64_t object_offset;
 } loader_segment_t;
 
 typedef struct {
     uint64_t pml4_phys;
     uint64_t entry;
     uint64_t user_stack;
+    uint64_t stack_top;        /* USER_STACK_TOP minus the ASLR offset */
     uint64_t stack_bottom;
     uint64_t stack_phys[LOADER_USER_STACK_PAGES];
     uint32_t seg_count;
     loader_segment_t segs[LOADER_MAX_SEGMENTS];
     uint64_t brk_base;
+    uint64_t phdr;             /* User address of the program headers, 0 if not mapped */
+    uint16_t phnum;
     uint8_t abi;
 } loader_image_t;
 
@@ -0,0 +1,80 @@
+/**
+ * @file memprobe.c
+ * @brief Fault-tolerant memory access for the kernel debuggers
+ */
+
+#include "memprobe.h"
+#include "../core/cpu.h"
+
+#define MEMPROBE_CR0_WP (1ULL << 16)
+
+/*
+ * LOCKING: none
+ *   Used only by a debugger that owns the machine with interrupts off;
+ *   memprobe_cpu tells a fault on another CPU apart from the probe.
+ */
+static void* memprobe_jmpbuf[5];
+static volatile int32_t memprobe_cpu = -1;
+
+bool memprobe_run(void (*fn)(void*), void* arg)
+{
+    if (__builtin_setjmp(memprobe_jmpbuf) != 0) {
+        memprobe_cpu = -1;
+        cpu_user_access_end();
+        return false;
+    }
+    memprobe_cpu = (int32_t)cpu_get_id();
+    fn(arg);
+    memprobe_cpu = -1;
+    return true;
+}
+
+void memprobe_fault(void)
+{
+    if (memprobe_cpu >= 0 && memprobe_cpu == (int32_t)cpu_get_id()) {
+        __builtin_longjmp(memprobe_jmpbuf, 1);
+    }
+}
+
+typedef struct {
+    uint64_t addr;
+    uint8_t* buf;
+    uint32_t len;
+    bool write;
+} memprobe_op_t;
+
+static void memprobe_op(void* arg)
+{
+    memprobe_op_t* op = (memprobe_op_t*)arg;
+    volatile uint8_t* mem = (volatile uint8_t*)(uintptr_t)op->addr;
+
+    cpu_user_access_begin();
+    if (op->write) {
+        uint64_t cr0;
+        __asm__ volatile ("mov %%cr0, %0" : "=r"(cr0));
+        __asm__ volatile ("mov %0, %%cr0" : : "r"(cr0 & ~MEMPROBE_CR0_WP) : "memory");
+        for (uint32_t i = 0; i < op->len; i++) {
+            mem[i] = op->buf[i];
+        }
+        __asm__ volatile ("mov %0, %%cr0" : : "r"(cr0) : "memory");
+    } else {
+        for (uint32_t i = 0; i < op->len; i++) {
+            op->buf[i] = mem[i];
+        }
+    }
+    cpu_user_access_end();
+}
+
+bool memprobe_copy(uint64_t addr, void* buf, uint32_t len, bool write)
+{
+    memprobe_op_t op = { addr, (uint8_t*)buf, len, write };
+    if (!write) {
+        return memprobe_run(memprobe_op, &op);
+    }
+    /* A fault with WP cleared must not leave it cleared */
+    uint64_t cr0;
+    __asm__ volatile ("mov %%cr0, %0" : "=r"(cr0));
+    bool ok = memprobe_run(memprobe_op, &op);
+    __asm__ volatile ("mov %0, %%cr0" : : "r"(cr0) : "memory");
+    return ok;
+}
@@ -0,0 +1,36 @@
+/**
+ * @file memprobe.h
+ * @brief Fault-tolerant memory access for the kernel debuggers
+ *
+ * ddb and the GDB stub read and write addresses typed by a user. A page
+ * fault or #GP inside a probe unwinds back to the prober instead of
+ * panicking. Probes run with interrupts off on the CPU that owns the
+ * debugger; only one probe is active at a time.
+ */
+
+#ifndef _RODNIX_COMMON_MEMPROBE_H
+#define _RODNIX_COMMON_MEMPROBE_H
+
+#include <stdint.h>
+#include <stdbool.h>
+
+/**
+ * Run fn(arg); a fault inside it abandons fn.
+ * @return false if fn faulted
+ */
+bool memprobe_run(void (*fn)(void*), void* arg);
+
+/**
+ * Copy between a buffer and arbitrary (kernel or current user) memory.
+ * Writes ignore CR0.WP so breakpoints can be patched into kernel text.
+ * @return false on fault
+ */
+bool memprobe_copy(uint64_t addr, void* buf, uint32_t len, bool write);
+
+/**
+ * Called from the #PF/#GP path before the fault is treated as fatal:
+ * resumes the active probe on this CPU (does not return then).
+ */
+void memprobe_fault(void);
+
+#endif /* _RODNIX_COMMON_MEMPROBE_H */
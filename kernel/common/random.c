@@ -0,0 +1,99 @@
+/**
+ * @file random.c
+ * @brief Kernel random numbers: xoshiro256** fed by the arch entropy sources
+ *
+ * The state is seeded on first use from RDRAND (when present), the cycle
+ * counter and the monotonic clock, expanded with splitmix64. Every call
+ * folds a fresh cycle count and, if available, an RDRAND value into the
+ * state, so outputs do not follow from an earlier snapshot of it alone.
+ */
+
+#include "../core/random.h"
+#include "../core/clock.h"
+#include "../core/interrupts.h"
+
+#define RANDOM_SEED_WORDS 4u
+
+/*
+ * LOCKING: g_random_state, g_random_seeded — IRQL_HIGH (the giant);
+ *   random_u64() may be called from any IRQL.
+ */
+static uint64_t g_random_state[4];
+static bool g_random_seeded = false;
+
+static inline uint64_t random_rotl(uint64_t x, int k)
+{
+    return (x << k) | (x >> (64 - k));
+}
+
+static uint64_t random_splitmix(uint64_t* x)
+{
+    uint64_t z = (*x += 0x9E3779B97F4A7C15ULL);
+    z = (z ^ (z >> 30)) * 0xBF58476D1CE4E5B9ULL;
+    z = (z ^ (z >> 27)) * 0x94D049BB133111EBULL;
+    return z ^ (z >> 31);
+}
+
+static void random_seed_locked(void)
+{
+    uint64_t mix = random_hw_cycles() ^ clocksource_monotonic_ns() ^
+                   (uint64_t)(uintptr_t)&mix;
+    for (uint32_t i = 0; i < RANDOM_SEED_WORDS; i++) {
+        uint64_t hw = 0;
+        if (random_hw_u64(&hw)) {
+            mix ^= hw;
+        }
+        mix ^= random_rotl(random_hw_cycles(), (int)(i * 16u + 1u));
+        g_random_state[i] = random_splitmix(&mix);
+    }
+    g_random_seeded = true;
+}
+
+static uint64_t random_next_locked(void)
+{
+    uint64_t* s = g_random_state;
+    uint64_t result = random_rotl(s[1] * 5u, 7) * 9u;
+    uint64_t t = s[1] << 17;
+    s[2] ^= s[0];
+    s[3] ^= s[1];
+    s[1] ^= s[2];
+    s[0] ^= s[3];
+    s[2] ^= t;
+    s[3] = random_rotl(s[3], 45);
+    return result;
+}
+
+uint64_t random_u64(void)
+{
+    irql_t old = set_irql(IRQL_HIGH);
+    if (!g_random_seeded) {
+        random_seed_locked();
+    }
+    uint64_t hw = 0;
+    if (random_hw_u64(&hw)) {
+        g_random_state[1] ^= hw;
+    }
+    g_random_state[0] ^= random_hw_cycles();
+    if ((g_random_state[0] | g_random_state[1] | g_random_state[2] | g_random_state[3]) == 0) {
+        /* xoshiro never leaves the all-zero state */
+        random_seed_locked();
+    }
+    uint64_t v = random_next_locked();
+    (void)set_irql(old);
+    return v;
+}
+
+uint64_t random_below(uint64_t bound)
+{
+    if (bound == 0) {
+        return 0;
+    }
+    /* Reject the short last interval so every value is equally likely */
+    uint64_t limit = (uint64_t)0 - ((uint64_t)0 - bound) % bound;
+    for (;;) {
+        uint64_t v = random_u64();
+        if (limit == 0 || v < limit) {
+            return v % bound;
+        }
+    }
+}
@@ -80,6 +80,17 @@ int scheduler_init(void);
  */
 void scheduler_start(void);
 
+/**
+ * Join the scheduler on an application processor (never returns).
+ * Waits until the BSP has registered the idle thread of this CPU.
+ */
+void scheduler_start_ap(void);
+
+/**
+ * Number of CPUs accepting threads
+ */
+uint32_t scheduler_online_cpus(void);
+
 /* ============================================================================
  * Task management
  * ============================================================================ */
@@ -115,6 +126,14 @@ task_t* scheduler_get_current_task(void);
  */
 int scheduler_add_thread(thread_t* thread);
 
+/**
+ * Register the idle thread of a CPU (pinned to it) and make it runnable
+ * @param thread Idle thread
+ * @param cpu Logical CPU index
+ * @return 0 on success, negative value on error
+ */
+int scheduler_add_idle_thread(thread_t* thread, uint32_t cpu);
+
 /**
  * Remove a thread from the scheduler
  * @param thread Thread to remove
@@ -166,6 +185,19 @@ void scheduler_exit_current(void);
  */
 void scheduler_sleep(uint64_t milliseconds);
 
+/**
+ * Sleep with sub-tick resolution (exact with dynamic tick, rounded up to
+ * the next tick otherwise)
+ * @param nanoseconds Time to sleep in nanoseconds
+ */
+void scheduler_sleep_ns(uint64_t nanoseconds);
+
+/**
+ * Body of a per-CPU idle thread: halt until this CPU has work, then
+ * dispatch. Never returns.
+ */
+void scheduler_idle_loop(void);
+
 /**
  * Set thread priority
  * @param thread Thread to modify
@@ -205,6 +237,31 @@ void scheduler_tick(void);
  */
 void scheduler_set_tick_rate(uint32_t hz);
 
+/**
+ * Switch timers to dynamic tick: each CPU programs its next one-shot event
+ * (earliest wait deadline, quantum expiry) and stops the tick while idle.
+ * Needs a clocksource and a one-shot clockevent.
+ * @return RDNX_OK on success, RDNX_E_UNSUPPORTED if the hardware cannot do it
+ */
+int scheduler_dyntick_enable(void);
+
+/**
+ * Whether dynamic tick is active
+ */
+bool scheduler_dyntick_active(void);
+
+/**
+ * Program this CPU's next timer event for the thread that runs now.
+ * Called from the timer vector after dispatch; no-op with a periodic tick.
+ */
+void scheduler_tick_rearm(void);
+
+/**
+ * Notify the timekeeping CPU about a newly armed wait deadline
+ * @param deadline_ns Deadline on the scheduler_now_ns() scale
+ */
+void scheduler_timeout_armed(uint64_t deadline_ns);
+
 /**
  * Check pending reschedule at safe points (AST-like)
  * Should be called from non-IRQ context
@@ -243,6 +300,17 @@ int scheduler_get_waitq_stats(scheduler_waitq_stats_t* stats);
  */
 uint64_t scheduler_get_ticks(void);
 
+/**
+ * Scheduler time in nanoseconds: the clocksource when there is one,
+ * ticks otherwise. Wait deadlines use this scale.
+ */
+uint64_t scheduler_now_ns(void);
+
+/**
+ * Tick length in nanoseconds (1 / tick rate)
+ */
+uint64_t scheduler_tick_ns(void);
+
 /**
  * Apply priority inheritance to target thread
  * @param target Target thread
@@ -29,7 +29,7 @@ static void scheduler_yield_internal(bool irq_context)
         return;
     }
     /* Request preemption on next timer interrupt */
-    resched_pending = true;
+    sched_cpu_self()->resched_pending = true;
 }
 
 void scheduler_yield(void)
@@ -44,7 +44,8 @@ void scheduler_block(void)
         return;
     }
 
-    if (in_scheduler) {
+    sched_cpu_t* sc = sched_cpu_self();
+    if (sc->in_scheduler) {
         return;
     }
 
@@ -56,7 +57,8 @@ void scheduler_block(void)
         log_count++;
     }
 
-    in_scheduler = true;
+    irql_t old = scheduler_lock();
+    sc->in_scheduler = true;
 
     if (cur->state != THREAD_STATE_RUNNING) {
         DEBUG_WARN("block: current thread %llu state=%d", (unsigned long long)cur->thread_id, cur->state);
@@ -71,8 +73,9 @@ void scheduler_block(void)
                 (int)cur->state);
     }
     stats.blocked_tasks++;
-    resched_pending = true;
-    in_scheduler = false;
+    sc->resched_pending = true;
+    sc->in_scheduler = false;
+    scheduler_unlock(old);
 }
 
 void scheduler_unblock(thread_t* thread)
@@ -81,6 +84,7 @@ void scheduler_unblock(thread_t* thread)
         return;
     }
 
+    irql_t old = scheduler_lock();
     if (thread->state == THREAD_STATE_BLOCKED) {
         if (thread->sched_class == SCHED_CLASS_TIMESHARE) {
             uint64_t sleep_ticks = sched_ticks - thread->last_sleep_tick;
@@ -102,13 +106,11 @@ void scheduler_unblock(thread_t* thread)
     } else {
         DEBUG_WARN("unblock: thread %llu state=%d", (unsigned long long)thread->thread_id, thread->state);
     }
+    scheduler_unlock(old);
 }
 
-void scheduler_wake(thread_t* thread)
+static void scheduler_wake_locked(thread_t* thread)
 {
-    if (!thread) {
-        return;
-    }
     if (thread->state == THREAD_STATE_BLOCKED) {
         if (thread->sched_class == SCHED_CLASS_TIMESHARE) {
             uint64_t sleep_ticks = sched_ticks - thread->last_sleep_tick;
@@ -127,19 +129,29 @@ void scheduler_wake(thread_t* thread)
         }
         scheduler_thread_set_state(thread, THREAD_STATE_READY, "scheduler_wake_blocked");
         ready_enqueue(thread);
-        resched_pending = true;
+        sched_cpu_self()->resched_pending = true;
         return;
     }
     if (thread->state != THREAD_STATE_READY) {
         scheduler_thread_set_state(thread, THREAD_STATE_READY, "scheduler_wake_other");
         ready_enqueue(thread);
-        resched_pending = true;
+        sched_cpu_self()->resched_pending = true;
         return;
     }
     if (!ready_thread_is_queued(thread) && thread != thread_get_current()) {
         ready_enqueue(thread);
-        resched_pending = true;
+        sched_cpu_self()->resched_pending = true;
+    }
+}
+
+void scheduler_wake(thread_t* thread)
+{
+    if (!thread) {
+        return;
     }
+    irql_t old = scheduler_lock();
+    scheduler_wake_locked(thread);
+    scheduler_unlock(old);
 }
 
 void scheduler_exit_current(void)
@@ -149,6 +161,8 @@ void scheduler_exit_current(void)
         return;
     }
 
+    /* Не возвращаемся: старый IRQL восстанавливать некому */
+    (void)scheduler_lock();
     scheduler_exit_wake_joiner(cur);
     scheduler_thread_set_state(cur, THREAD_STATE_DEAD, "scheduler_exit_current");
     tracev2_emit(TR2_CAT_SCHED, TR2_EV_SCHED_EXIT,
@@ -157,7 +171,7 @@ void scheduler_exit_current(void)
     if (cur->task && cur->task->state != TASK_STATE_DEAD) {
         scheduler_task_set_state(cur->task, TASK_STATE_ZOMBIE, "scheduler_exit_current");
     }
-    resched_pending = true;
+    sched_cpu_self()->resched_pending = true;
     __asm__ volatile ("int $32");
     for (;;) {
         cpu_idle();
@@ -165,15 +179,45 @@ void scheduler_exit_current(void)
 }
 
 void scheduler_sleep(uint64_t milliseconds)
+{
+    uint64_t ns = milliseconds * 1000000ULL;
+    if (milliseconds > UINT64_MAX / 1000000ULL) {
+        ns = UINT64_MAX;
+    }
+    scheduler_sleep_ns(ns);
+}
+
+void scheduler_sleep_ns(uint64_t nanoseconds)
 {
     if (!thread_get_current()) {
         return;
     }
-    if (milliseconds == 0) {
+    if (nanoseconds == 0) {
         scheduler_yield();
         return;
     }
-    (void)waitq_wait(&scheduler_sleep_waitq, milliseconds);
+    (void)waitq_wait_ns(&scheduler_sleep_waitq, nanoseconds);
+}
+
+void scheduler_idle_loop(void)
+{
+    for (;;) {
+        /*
+         * Работа могла появиться из прерывания, не связанного с таймером:
+         * проверка при закрытых прерываниях, "sti; hlt" не пропустит
+         * пробуждение между проверкой и остановом. Без этого в режиме
+         * dynamic tick CPU спал бы до следующего события таймера.
+         */
+        __asm__ volatile ("cli" ::: "memory");
+        sched_cpu_t* sc = sched_cpu_self();
+        if (scheduler_running && (sc->resched_pending || sched_cpu_load(sc) > 0)) {
+            sc->resched_pending = true;
+            __asm__ volatile ("sti" ::: "memory");
+            __asm__ volatile ("int $32");
+            continue;
+        }
+        __asm__ volatile ("sti; hlt" ::: "memory");
+    }
 }
 
 void scheduler_set_priority(thread_t* thread, uint8_t priority)
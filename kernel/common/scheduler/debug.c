@@ -30,16 +30,27 @@ void scheduler_debug_dump(void)
         kputs("[SCHED] current none\n");
     }
 
-    for (int q = READY_QUEUE_LEVELS - 1; q >= 0; q--) {
-        uint32_t count = 0;
-        thread_t* it = NULL;
-        TAILQ_FOREACH(it, &ready_queues[q], sched_link) {
-            count++;
-            if (count >= 1024) {
-                break;
+    for (uint32_t cpu = 0; cpu < CPU_MAX_COUNT; cpu++) {
+        sched_cpu_t* sc = &sched_cpus[cpu];
+        if (!sc->online) {
+            continue;
+        }
+        kprintf("[SCHED] cpu%u curr=%llu nr_ready=%u load=%u\n",
+                (unsigned)cpu,
+                (unsigned long long)(sc->curr ? sc->curr->thread_id : 0),
+                (unsigned)sc->nr_ready,
+                (unsigned)sched_cpu_load(sc));
+        for (int q = READY_QUEUE_LEVELS - 1; q >= 0; q--) {
+            uint32_t count = 0;
+            thread_t* it = NULL;
+            TAILQ_FOREACH(it, &sc->ready_queues[q], sched_link) {
+                count++;
+                if (count >= 1024) {
+                    break;
+                }
             }
+            kprintf("[SCHED] cpu%u q%d count=%u\n", (unsigned)cpu, q, count);
         }
-        kprintf("[SCHED] q%d count=%u\n", q, count);
     }
     kputs("[SCHED] --------------\n");
 }
@@ -4,6 +4,8 @@
 #include "../scheduler.h"
 #include "../waitq.h"
 #include "../../arch/interrupt_frame.h"
+#include "../../core/cpu.h"
+#include "../../core/interrupts.h"
 #include "../../../include/bsd/sys/queue.h"
 #include <stdbool.h>
 #include <stdint.h>
@@ -28,29 +30,74 @@
 /* Starvation avoidance: если бакет не получал CPU N тиков, он получает внеочередной слот. */
 #define STARVATION_THRESHOLD_TICKS 500
 
+/* Dynamic tick: самый долгий сон таймера простаивающего CPU */
+#define DYNTICK_IDLE_MAX_NS 1000000000ULL
+/* Dynamic tick: сколько пропущенных тиков учитывается за одно прерывание */
+#define DYNTICK_CATCHUP_MAX_TICKS 64
+
 extern bool scheduler_initialized;
 extern bool scheduler_running;
 extern sched_policy_t current_policy;
 extern scheduler_stats_t stats;
 
-extern volatile bool in_scheduler;
-extern uint32_t ticks_until_preempt;
 extern uint32_t ticks_per_slice;
-extern volatile bool resched_pending;
 extern uint64_t sched_ticks;
+extern uint64_t sched_tick_len_ns;
+extern bool sched_dyntick;
 
 TAILQ_HEAD(ready_queue_head, thread);
-extern struct ready_queue_head ready_queues[READY_QUEUE_LEVELS];
+
+/*
+ * Состояние планировщика одного CPU.
+ * LOCKING: все поля меняются только при IRQL > PASSIVE (т.е. под IRQL giant
+ *   на SMP). Чужой CPU может читать nr_ready/curr для балансировки и снимать
+ *   потоки из ready_queues при переносе (pull).
+ */
+typedef struct sched_cpu {
+    struct ready_queue_head ready_queues[READY_QUEUE_LEVELS];
+    uint64_t bucket_last_run_tick[READY_QUEUE_LEVELS]; /* последний тик каждого бакета */
+    uint32_t nr_ready;              /* потоков в ready_queues (включая idle) */
+    uint32_t ticks_until_preempt;
+    volatile bool resched_pending;
+    volatile bool in_scheduler;
+    volatile bool online;           /* CPU принимает потоки */
+    thread_t* idle_thread;          /* idle-поток этого CPU (закреплён) */
+    thread_t* curr;                 /* текущий поток (для чтения с других CPU) */
+    uint64_t last_tick;             /* dynamic tick: тик последнего учёта кванта */
+    uint64_t next_event_ns;         /* dynamic tick: взведённое событие таймера */
+    volatile bool tick_stopped;     /* dynamic tick: событие дальше тика, будить cpu_kick() */
+} sched_cpu_t;
+
+extern sched_cpu_t sched_cpus[CPU_MAX_COUNT];
+
+static inline sched_cpu_t* sched_cpu_self(void)
+{
+    return &sched_cpus[cpu_get_id()];
+}
+
+/* Секция планировщика: на SMP IRQL_HIGH означает владение IRQL giant. */
+static inline irql_t scheduler_lock(void)
+{
+    return set_irql(IRQL_HIGH);
+}
+
+static inline void scheduler_unlock(irql_t old)
+{
+    (void)set_irql(old);
+}
 
 extern scheduler_reap_stats_t reap_stats;
 extern waitq_t scheduler_sleep_waitq;
-extern uint64_t bucket_last_run_tick[READY_QUEUE_LEVELS]; /* последний тик каждого бакета */
+
+uint64_t sched_ticks_now(void);
 
 void scheduler_thread_set_state(thread_t* thread, thread_state_t new_state, const char* reason);
 void scheduler_task_set_state(task_t* task, task_state_t new_state, const char* reason);
 
 void ready_enqueue(thread_t* thread);
-thread_t* ready_dequeue(void);
+void ready_enqueue_local(thread_t* thread);
+thread_t* ready_dequeue(sched_cpu_t* sc);
+uint32_t sched_cpu_load(const sched_cpu_t* sc);
 int ready_queue_index_for_thread(const thread_t* thread);
 bool ready_thread_is_queued(const thread_t* thread);
 
@@ -107,7 +107,8 @@ void scheduler_reap_dead_threads(void)
         if (!head) {
             break;
         }
-        if (head->reap_after_tick > sched_ticks) {
+        /* Стек ещё может быть занят CPU, который только что с него ушёл */
+        if (head->reap_after_tick > sched_ticks || head->sched_on_cpu) {
             reap_stats.deferred++;
             break;
         }
@@ -49,12 +49,13 @@ int thread_effective_priority(const thread_t* thread)
 
 void scheduler_reset_timeslice(const thread_t* thread)
 {
+    sched_cpu_t* sc = sched_cpu_self();
     if (!thread) {
-        ticks_until_preempt = ticks_per_slice;
+        sc->ticks_until_preempt = ticks_per_slice;
         return;
     }
     if (thread->sched_class == SCHED_CLASS_REALTIME) {
-        ticks_until_preempt = REALTIME_QUANTUM_TICKS;
+        sc->ticks_until_preempt = REALTIME_QUANTUM_TICKS;
         return;
     }
     uint32_t base = ticks_per_slice;
@@ -73,9 +74,9 @@ void scheduler_reset_timeslice(const thread_t* thread)
     if (bucket >= SCHED_BUCKET_COUNT) {
         bucket = SCHED_BUCKET_DEFAULT;
     }
-    ticks_until_preempt = base * bucket_mult[bucket];
-    if (ticks_until_preempt == 0) {
-        ticks_until_preempt = 1;
+    sc->ticks_until_preempt = base * bucket_mult[bucket];
+    if (sc->ticks_until_preempt == 0) {
+        sc->ticks_until_preempt = 1;
     }
 }
 
@@ -84,12 +85,61 @@ bool ready_thread_is_queued(const thread_t* thread)
     return thread && thread->ready_queued != 0;
 }
 
-void ready_enqueue(thread_t* thread)
+/*
+ * Нагрузка CPU: потоки в очереди плюс текущий, без idle-потока.
+ * @p self — поток, который сейчас размещают: он не считается дважды.
+ */
+static uint32_t sched_cpu_load_excluding(const sched_cpu_t* sc, const thread_t* self)
 {
-    if (!thread) {
-        return;
+    uint32_t load = sc->nr_ready;
+    if (sc->idle_thread && ready_thread_is_queued(sc->idle_thread) && load > 0) {
+        load--;
     }
+    if (sc->curr && sc->curr != sc->idle_thread && sc->curr != self) {
+        load++;
+    }
+    return load;
+}
+
+uint32_t sched_cpu_load(const sched_cpu_t* sc)
+{
+    return sched_cpu_load_excluding(sc, NULL);
+}
+
+/*
+ * Выбор CPU для готового потока: закреплённый — на свой CPU, иначе наименее
+ * загруженный online CPU. При равенстве предпочитается CPU, где поток
+ * выполнялся последним (тёплый кэш), затем текущий CPU.
+ */
+static uint32_t ready_pick_cpu(const thread_t* thread)
+{
+    uint32_t last = thread->sched_cpu;
+    if (last >= CPU_MAX_COUNT) {
+        last = 0;
+    }
+    if (thread->sched_pinned) {
+        return last;
+    }
+
+    uint32_t self = cpu_get_id();
+    uint32_t best = sched_cpus[last].online ? last : self;
+    uint32_t best_load = sched_cpu_load_excluding(&sched_cpus[best], thread);
+    for (uint32_t cpu = 0; cpu < CPU_MAX_COUNT; cpu++) {
+        const sched_cpu_t* sc = &sched_cpus[cpu];
+        if (!sc->online || cpu == best) {
+            continue;
+        }
+        uint32_t load = sched_cpu_load_excluding(sc, thread);
+        if (load < best_load || (load == best_load && cpu == self && best != last)) {
+            best = cpu;
+            best_load = load;
+        }
+    }
+    return best;
+}
 
+static void ready_enqueue_cpu(thread_t* thread, uint32_t cpu)
+{
     if (ready_thread_is_queued(thread)) {
         DEBUG_WARN("ready_enqueue: thread %llu already queued", (unsigned long long)thread->thread_id);
         return;
@@ -98,54 +148,98 @@ void ready_enqueue(thread_t* thread)
     if (q < 0 || q >= READY_QUEUE_LEVELS) {
         q = (int)SCHED_BUCKET_DEFAULT;
     }
-    TAILQ_INSERT_TAIL(&ready_queues[q], thread, sched_link);
+    sched_cpu_t* sc = &sched_cpus[cpu];
+    TAILQ_INSERT_TAIL(&sc->ready_queues[q], thread, sched_link);
+    thread->sched_cpu = (uint8_t)cpu;
     thread->ready_queued = 1;
+    sc->nr_ready++;
     stats.ready_tasks++;
+    /* Тик удалённого CPU остановлен: разбудить, иначе он проспит работу */
+    if (sched_dyntick && cpu != cpu_get_id() && sc->tick_stopped) {
+        sc->tick_stopped = false;
+        cpu_kick(cpu);
+    }
 }
 
-/* Вспомогательная функция: извлечь первый поток из очереди q и обновить метрики. */
-static thread_t* dequeue_from(int q)
+void ready_enqueue(thread_t* thread)
 {
-    struct ready_queue_head* queue = &ready_queues[q];
-    thread_t* thread = TAILQ_FIRST(queue);
     if (!thread) {
-        return NULL;
+        return;
     }
-    if (thread->state != THREAD_STATE_READY) {
-        DEBUG_WARN("ready_dequeue: thread %llu state=%d",
-                   (unsigned long long)thread->thread_id, thread->state);
+    ready_enqueue_cpu(thread, ready_pick_cpu(thread));
+}
+
+void ready_enqueue_local(thread_t* thread)
+{
+    if (!thread) {
+        return;
     }
-    TAILQ_REMOVE(queue, thread, sched_link);
+    ready_enqueue_cpu(thread, cpu_get_id());
+}
+
+/* Снять поток из очереди q CPU @p sc и обновить метрики. */
+static void ready_remove(sched_cpu_t* sc, int q, thread_t* thread)
+{
+    TAILQ_REMOVE(&sc->ready_queues[q], thread, sched_link);
     thread->sched_link.tqe_next = NULL;
     thread->sched_link.tqe_prev = NULL;
     thread->ready_queued = 0;
+    if (sc->nr_ready > 0) {
+        sc->nr_ready--;
+    }
     if (stats.ready_tasks > 0) {
         stats.ready_tasks--;
     }
-    bucket_last_run_tick[q] = sched_ticks;
+}
+
+/*
+ * Вспомогательная функция: извлечь первый поток из очереди q и обновить метрики.
+ * Поток, стек которого ещё занят другим CPU (sched_on_cpu), пропускается.
+ */
+static thread_t* dequeue_from(sched_cpu_t* sc, int q)
+{
+    thread_t* cur = thread_get_current();
+    thread_t* thread = NULL;
+    TAILQ_FOREACH(thread, &sc->ready_queues[q], sched_link) {
+        if (!thread->sched_on_cpu || thread == cur) {
+            break;
+        }
+    }
+    if (!thread) {
+        return NULL;
+    }
+    if (thread->state != THREAD_STATE_READY) {
+        DEBUG_WARN("ready_dequeue: thread %llu state=%d",
+                   (unsigned long long)thread->thread_id, thread->state);
+    }
+    ready_remove(sc, q, thread);
+    sc->bucket_last_run_tick[q] = sched_ticks;
     return thread;
 }
 
-thread_t* ready_dequeue(void)
+static thread_t* ready_dequeue_local(sched_cpu_t* sc)
 {
     /* RR/FIFO: всё в DEFAULT-очереди */
     if (current_policy == SCHED_POLICY_RR || current_policy == SCHED_POLICY_FIFO) {
-        return dequeue_from((int)SCHED_BUCKET_DEFAULT);
+        return dequeue_from(sc, (int)SCHED_BUCKET_DEFAULT);
     }
 
     /* Starvation avoidance: если нижний бакет не получал CPU давно — дать ему слот.
      * Проверяем снизу вверх (BACKGROUND → UTILITY → DEFAULT), исключая INTERACTIVE —
      * он и так всегда имеет приоритет. */
     for (int b = (int)SCHED_BUCKET_BACKGROUND; b < (int)SCHED_BUCKET_INTERACTIVE; b++) {
-        if (!TAILQ_EMPTY(&ready_queues[b]) &&
-            (sched_ticks - bucket_last_run_tick[b]) >= STARVATION_THRESHOLD_TICKS) {
-            return dequeue_from(b);
+        if (!TAILQ_EMPTY(&sc->ready_queues[b]) &&
+            (sched_ticks - sc->bucket_last_run_tick[b]) >= STARVATION_THRESHOLD_TICKS) {
+            thread_t* t = dequeue_from(sc, b);
+            if (t) {
+                return t;
+            }
         }
     }
 
     /* Нормальный путь: выбрать из наиболее приоритетного непустого бакета */
     for (int q = READY_QUEUE_LEVELS - 1; q >= 0; q--) {
-        thread_t* t = dequeue_from(q);
+        thread_t* t = dequeue_from(sc, q);
         if (t) {
             return t;
         }
@@ -154,6 +248,60 @@ thread_t* ready_dequeue(void)
     return NULL;
 }
 
+/*
+ * Перенос (pull) потока с самого загруженного CPU на простаивающий.
+ * Закреплённые потоки и потоки, чей стек ещё занят (sched_on_cpu), не трогаем.
+ */
+static thread_t* ready_pull(sched_cpu_t* self)
+{
+    sched_cpu_t* busiest = NULL;
+    uint32_t busiest_load = 0;
+    for (uint32_t cpu = 0; cpu < CPU_MAX_COUNT; cpu++) {
+        sched_cpu_t* sc = &sched_cpus[cpu];
+        if (sc == self || !sc->online || sc->nr_ready == 0) {
+            continue;
+        }
+        uint32_t load = sched_cpu_load(sc);
+        if (load > busiest_load) {
+            busiest = sc;
+            busiest_load = load;
+        }
+    }
+    if (!busiest || busiest_load < 2) {
+        return NULL;
+    }
+
+    for (int q = READY_QUEUE_LEVELS - 1; q >= 0; q--) {
+        thread_t* t = NULL;
+        TAILQ_FOREACH(t, &busiest->ready_queues[q], sched_link) {
+            if (!t->sched_pinned && !t->sched_on_cpu) {
+                ready_remove(busiest, q, t);
+                t->sched_cpu = (uint8_t)(self - sched_cpus);
+                return t;
+            }
+        }
+    }
+    return NULL;
+}
+
+thread_t* ready_dequeue(sched_cpu_t* sc)
+{
+    thread_t* t = ready_dequeue_local(sc);
+    if (t && t != sc->idle_thread) {
+        return t;
+    }
+
+    /* Локально работы нет: попробовать забрать поток у соседа */
+    thread_t* pulled = ready_pull(sc);
+    if (!pulled) {
+        return t;
+    }
+    if (t) {
+        ready_enqueue(t);
+    }
+    return pulled;
+}
+
 int ready_queue_index_for_thread(const thread_t* thread)
 {
     if (!thread) {
@@ -1,4 +1,5 @@
 #include "internal.h"
+#include "../../core/clock.h"
 #include "../../../include/debug.h"
 #include "../../../include/error.h"
 
@@ -7,17 +8,15 @@ bool scheduler_running = false;
 sched_policy_t current_policy = SCHED_POLICY_PRIORITY;
 scheduler_stats_t stats = {0};
 
-volatile bool in_scheduler = false;
-uint32_t ticks_until_preempt = 1;
 uint32_t ticks_per_slice = 1;
-volatile bool resched_pending = false;
 uint64_t sched_ticks = 0;
+uint64_t sched_tick_len_ns = 1000000000ULL / 100;
+bool sched_dyntick = false;
 
-struct ready_queue_head ready_queues[READY_QUEUE_LEVELS];
+sched_cpu_t sched_cpus[CPU_MAX_COUNT];
 
 scheduler_reap_stats_t reap_stats = {0};
 waitq_t scheduler_sleep_waitq;
-uint64_t bucket_last_run_tick[READY_QUEUE_LEVELS] = {0};
 
 static bool scheduler_thread_transition_valid(thread_state_t from, thread_state_t to)
 {
@@ -107,14 +106,27 @@ int scheduler_init(void)
     thread_set_current(NULL);
     current_policy = SCHED_POLICY_PRIORITY;
     scheduler_running = false;
-    for (int i = 0; i < READY_QUEUE_LEVELS; i++) {
-        TAILQ_INIT(&ready_queues[i]);
-        bucket_last_run_tick[i] = 0;
+    ticks_per_slice = 1;
+    for (uint32_t cpu = 0; cpu < CPU_MAX_COUNT; cpu++) {
+        sched_cpu_t* sc = &sched_cpus[cpu];
+        for (int i = 0; i < READY_QUEUE_LEVELS; i++) {
+            TAILQ_INIT(&sc->ready_queues[i]);
+            sc->bucket_last_run_tick[i] = 0;
+        }
+        sc->nr_ready = 0;
+        sc->ticks_until_preempt = ticks_per_slice;
+        sc->resched_pending = false;
+        sc->in_scheduler = false;
+        sc->online = false;
+        sc->idle_thread = NULL;
+        sc->curr = NULL;
+        sc->last_tick = 0;
+        sc->next_event_ns = 0;
+        sc->tick_stopped = false;
     }
+    /* BSP принимает потоки сразу; AP — после scheduler_start_ap() */
+    sched_cpus[0].online = true;
     waitq_init(&scheduler_sleep_waitq, "scheduler_sleep");
-    ticks_per_slice = 1;
-    ticks_until_preempt = ticks_per_slice;
-    resched_pending = false;
     sched_ticks = 0;
     stats.running_tasks = 0;
     stats.ready_tasks = 0;
@@ -133,15 +145,37 @@ void scheduler_start(void)
     scheduler_reaper_start();
 
     scheduler_running = true;
-    ticks_until_preempt = ticks_per_slice;
+    sched_cpu_t* sc = sched_cpu_self();
+    sc->ticks_until_preempt = ticks_per_slice;
 
     /* Kick preemption to start the first thread on the next timer IRQ */
-    resched_pending = true;
+    sc->resched_pending = true;
     /* Force a timer-like IRQ to start the first thread */
     __asm__ volatile ("int $32");
     /* If we return here, we did not switch yet */
 }
 
+void scheduler_start_ap(void)
+{
+    sched_cpu_t* sc = sched_cpu_self();
+    /* idle-поток для AP создаёт BSP (kmain) уже после smp_init() */
+    while (!((volatile sched_cpu_t*)sc)->idle_thread) {
+        cpu_pause();
+    }
+
+    irql_t old = scheduler_lock();
+    sc->ticks_until_preempt = ticks_per_slice;
+    sc->online = true;
+    sc->resched_pending = true;
+    scheduler_unlock(old);
+
+    /* Первое переключение: с загрузочного стека AP на его idle-поток */
+    __asm__ volatile ("int $32");
+    for (;;) {
+        cpu_idle();
+    }
+}
+
 int scheduler_add_task(task_t* task)
 {
     if (!task) {
@@ -191,12 +225,29 @@ int scheduler_add_thread(thread_t* thread)
     if (!thread->sched_bucket_explicit) {
         thread->sched_bucket = (uint8_t)SCHED_BUCKET_DEFAULT;
     }
+    irql_t old = scheduler_lock();
     ready_enqueue(thread);
     stats.total_tasks++;
+    scheduler_unlock(old);
 
     return 0;
 }
 
+int scheduler_add_idle_thread(thread_t* thread, uint32_t cpu)
+{
+    if (!thread || cpu >= CPU_MAX_COUNT) {
+        return RDNX_E_INVALID;
+    }
+    sched_cpu_t* sc = &sched_cpus[cpu];
+    if (sc->idle_thread) {
+        return RDNX_E_BUSY;
+    }
+    thread->sched_cpu = (uint8_t)cpu;
+    thread->sched_pinned = 1;
+    sc->idle_thread = thread;
+    return scheduler_add_thread(thread);
+}
+
 int scheduler_remove_thread(thread_t* thread)
 {
     if (!thread) {
@@ -232,10 +283,30 @@ int scheduler_get_stats(scheduler_stats_t* out_stats)
         return RDNX_E_INVALID;
     }
 
+    irql_t old = scheduler_lock();
     *out_stats = stats;
+    out_stats->running_tasks = 0;
+    for (uint32_t cpu = 0; cpu < CPU_MAX_COUNT; cpu++) {
+        const sched_cpu_t* sc = &sched_cpus[cpu];
+        if (sc->online && sc->curr && sc->curr != sc->idle_thread) {
+            out_stats->running_tasks++;
+        }
+    }
+    scheduler_unlock(old);
     return RDNX_OK;
 }
 
+uint32_t scheduler_online_cpus(void)
+{
+    uint32_t count = 0;
+    for (uint32_t cpu = 0; cpu < CPU_MAX_COUNT; cpu++) {
+        if (sched_cpus[cpu].online) {
+            count++;
+        }
+    }
+    return count;
+}
+
 int scheduler_get_reap_stats(scheduler_reap_stats_t* out_stats)
 {
     if (!out_stats) {
@@ -258,5 +329,22 @@ int scheduler_get_waitq_stats(scheduler_waitq_stats_t* out_stats)
 
 uint64_t scheduler_get_ticks(void)
 {
+    /* Без периодического тика sched_ticks догоняет время только в прерываниях */
+    if (sched_dyntick) {
+        return sched_ticks_now();
+    }
     return sched_ticks;
 }
+
+uint64_t scheduler_now_ns(void)
+{
+    if (clocksource_available()) {
+        return clocksource_monotonic_ns();
+    }
+    return sched_ticks * sched_tick_len_ns;
+}
+
+uint64_t scheduler_tick_ns(void)
+{
+    return sched_tick_len_ns;
+}
@@ -38,16 +38,18 @@ interrupt_frame_t* scheduler_switch_from_irq(interrupt_frame_t* frame)
     if (!scheduler_running || !frame) {
         return frame;
     }
-    if (in_scheduler) {
+    sched_cpu_t* sc = sched_cpu_self();
+    if (sc->in_scheduler) {
         return frame;
     }
 
-    in_scheduler = true;
+    sc->in_scheduler = true;
     static int log_count = 0;
     thread_t* cur = thread_get_current();
     if (bootlog_is_verbose() && log_count < 8) {
-        kprintf("[SCHED] irq switch: resched=%d current=%llu state=%d ready=%llu\n",
-                resched_pending ? 1 : 0,
+        kprintf("[SCHED] irq switch: cpu=%u resched=%d current=%llu state=%d ready=%llu\n",
+                (unsigned)cpu_get_id(),
+                sc->resched_pending ? 1 : 0,
                 (unsigned long long)(cur ? cur->thread_id : 0),
                 (cur ? (int)cur->state : -1),
                 (unsigned long long)stats.ready_tasks);
@@ -55,40 +57,44 @@ interrupt_frame_t* scheduler_switch_from_irq(interrupt_frame_t* frame)
     }
     TRACE_EVENT("sched: switch_from_irq");
     /* If no reschedule is pending and we already have a current thread, keep running */
-    if (cur && !resched_pending) {
-        in_scheduler = false;
+    if (cur && !sc->resched_pending) {
+        sc->in_scheduler = false;
         return frame;
     }
-    resched_pending = false;
+    sc->resched_pending = false;
 
     if (!cur) {
-        thread_t* first = ready_dequeue();
+        thread_t* first = ready_dequeue(sc);
         PANIC_IF(!first, "scheduler: no runnable threads on first switch");
+        first->sched_on_cpu = 1;
+        first->sched_cpu = (uint8_t)cpu_get_id();
+        sc->curr = first;
         thread_set_current(first);
         if (first->task) {
             task_set_current(first->task);
         }
         scheduler_switch_address_space(first);
         scheduler_update_tss(first);
-        stats.running_tasks = 1;
+        cpu_fpu_restore(first->context.fpu_state);
         stats.total_switches++;
         scheduler_thread_set_state(first, THREAD_STATE_RUNNING, "switch_first");
         scheduler_reset_timeslice(first);
-        in_scheduler = false;
+        sc->in_scheduler = false;
         return (interrupt_frame_t*)(uintptr_t)first->context.stack_pointer;
     }
 
     cur->context.stack_pointer = (uint64_t)(uintptr_t)frame;
     if (cur->state == THREAD_STATE_RUNNING) {
         scheduler_thread_set_state(cur, THREAD_STATE_READY, "switch_preempt");
-        ready_enqueue(cur);
+        /* Вытесненный поток остаётся на своём CPU; перенос — через pull */
+        ready_enqueue_local(cur);
     }
 
-    thread_t* next = ready_dequeue();
+    thread_t* next = ready_dequeue(sc);
     if (!next || next == cur) {
         if (cur && cur->state != THREAD_STATE_DEAD) {
             scheduler_thread_set_state(cur, THREAD_STATE_RUNNING, "switch_continue_current");
-            in_scheduler = false;
+            sc->in_scheduler = false;
             return frame;
         }
         /*
@@ -96,18 +102,26 @@ interrupt_frame_t* scheduler_switch_from_irq(interrupt_frame_t* frame)
          * This indicates that no runnable fallback thread exists.
          */
         PANIC_IF(true, "scheduler: no runnable threads after current thread exit");
-        in_scheduler = false;
+        sc->in_scheduler = false;
         return frame;
     }
 
     thread_t* prev = cur;
+    /*
+     * Регистры FPU/SIMD всё ещё принадлежат prev. Сохраняем до снятия
+     * sched_on_cpu: после этого prev может продолжить другой CPU.
+     */
+    cpu_fpu_save(prev->context.fpu_state);
+    next->sched_on_cpu = 1;
+    next->sched_cpu = (uint8_t)cpu_get_id();
+    sc->curr = next;
     thread_set_current(next);
     if (next->task) {
         task_set_current(next->task);
     }
     scheduler_switch_address_space(next);
     scheduler_update_tss(next);
-    stats.running_tasks = 1;
+    cpu_fpu_restore(next->context.fpu_state);
     stats.total_switches++;
 
     if (prev && prev->state == THREAD_STATE_RUNNING) {
@@ -116,12 +130,19 @@ interrupt_frame_t* scheduler_switch_from_irq(interrupt_frame_t* frame)
     if (prev && prev->state == THREAD_STATE_DEAD) {
         scheduler_reap_enqueue(prev);
     }
+    /*
+     * prev's stack stays in use until the IRQ stub has moved RSP to next's
+     * frame; the stub clears prev->sched_on_cpu after that point.
+     */
+    if (prev) {
+        cpu_defer_switch_release(&prev->sched_on_cpu);
+    }
     scheduler_thread_set_state(next, THREAD_STATE_RUNNING, "switch_next_running");
     scheduler_reset_timeslice(next);
     tracev2_emit(TR2_CAT_SCHED, TR2_EV_SCHED_SWITCH,
                  prev ? prev->thread_id : 0, next->thread_id);
 
-    in_scheduler = false;
+    sc->in_scheduler = false;
     if (bootlog_is_verbose() && log_count < 8) {
         kprintf("[SCHED] switch to tid=%llu\n",
                 (unsigned long long)next->thread_id);
@@ -1,4 +1,27 @@
 #include "internal.h"
+#include "../../core/clock.h"
+#include "../bootparam.h"
+#include "../../../include/error.h"
+
+BOOTPARAM_INT(sched_quantum_ms, "rdnx.quantum_ms", SCHEDULER_TIME_SLICE_MS, 1, 1000,
+              "Scheduler time slice, ms");
+
+/* Точка отсчёта тиков в режиме dynamic tick */
+static uint64_t dyntick_base_tick = 0;
+static uint64_t dyntick_base_ns = 0;
+
+/*
+ * Тики, прошедшие по clocksource. В режиме dynamic tick прерывания приходят
+ * нерегулярно, поэтому номер тика вычисляется из времени, а не считается.
+ */
+uint64_t sched_ticks_now(void)
+{
+    uint64_t ns = clocksource_monotonic_ns();
+    if (ns <= dyntick_base_ns || sched_tick_len_ns == 0) {
+        return dyntick_base_tick;
+    }
+    return dyntick_base_tick + (ns - dyntick_base_ns) / sched_tick_len_ns;
+}
 
 void scheduler_tick(void)
 {
@@ -6,34 +29,139 @@ void scheduler_tick(void)
         return;
     }
 
-    sched_ticks++;
-    waitq_tick(sched_ticks);
+    sched_cpu_t* sc = sched_cpu_self();
+    /*
+     * Сколько тиков учесть. Периодический тик — ровно один. В dynamic tick
+     * прерывание может покрывать много тиков (простой, длинный квант) или
+     * ни одного (высокоточный таймаут, cpu_kick, "int $32" из waitq).
+     */
+    uint64_t elapsed = 1;
+    if (sched_dyntick) {
+        uint64_t now = sched_ticks_now();
+        elapsed = (now > sc->last_tick) ? now - sc->last_tick : 0;
+        sc->last_tick = now;
+        /* LOCKING: вектор таймера выполняется под IRQL giant */
+        if (now > sched_ticks) {
+            sched_ticks = now;
+        }
+        if (elapsed > DYNTICK_CATCHUP_MAX_TICKS) {
+            elapsed = DYNTICK_CATCHUP_MAX_TICKS;
+        }
+    }
+
+    /* Системное время и таймауты ведёт только BSP; AP считают свой квант */
+    if (cpu_get_id() == 0) {
+        if (!sched_dyntick) {
+            sched_ticks++;
+        }
+        waitq_expire(scheduler_now_ns());
+    }
     thread_t* cur = thread_get_current();
-    if (cur && cur->state == THREAD_STATE_RUNNING) {
-        cur->sched_usage = (cur->sched_usage * 7) / 8;
-        cur->sched_usage++;
+    if (elapsed > 0 && cur && cur->state == THREAD_STATE_RUNNING) {
+        for (uint64_t i = 0; i < elapsed; i++) {
+            cur->sched_usage = (cur->sched_usage * 7) / 8;
+            cur->sched_usage++;
+            if (cur->sched_class == SCHED_CLASS_TIMESHARE) {
+                if ((cur->sched_usage % PENALTY_STEP_TICKS) == 0) {
+                    int base = cur->base_priority;
+                    int dyn = cur->dyn_priority - 1;
+                    cur->dyn_priority = clamp_dyn_priority(dyn, base);
+                }
+            }
+        }
         /* Обновить CPU-счётчики группы (task_t.thread_group) */
         if (cur->task) {
-            cur->task->thread_group.cpu_ticks++;
+            cur->task->thread_group.cpu_ticks += elapsed;
             cur->task->thread_group.last_run_tick = sched_ticks;
         }
-        if (cur->sched_class == SCHED_CLASS_TIMESHARE) {
-            if ((cur->sched_usage % PENALTY_STEP_TICKS) == 0) {
-                int base = cur->base_priority;
-                int dyn = cur->dyn_priority - 1;
-                cur->dyn_priority = clamp_dyn_priority(dyn, base);
+    }
+
+    if (elapsed >= sc->ticks_until_preempt) {
+        sc->ticks_until_preempt = ticks_per_slice;
+        sc->resched_pending = true;
+    } else {
+        sc->ticks_until_preempt -= (uint32_t)elapsed;
+    }
+
+    /* idle уступает сразу, как только для CPU появилась работа */
+    if (cur && cur == sc->idle_thread && sched_cpu_load(sc) > 0) {
+        sc->resched_pending = true;
+    }
+}
+
+void scheduler_tick_rearm(void)
+{
+    if (!sched_dyntick) {
+        return;
+    }
+
+    sched_cpu_t* sc = sched_cpu_self();
+    uint64_t now = clocksource_monotonic_ns();
+    uint64_t next = now + sched_tick_len_ns;
+
+    if (scheduler_running) {
+        thread_t* cur = thread_get_current();
+        uint32_t load = sched_cpu_load(sc);
+        if ((!cur || cur == sc->idle_thread) && load == 0) {
+            /* Простой: тик остановлен, будят дедлайн или cpu_kick() */
+            next = now + DYNTICK_IDLE_MAX_NS;
+        } else if (load <= 1 && sc->ticks_until_preempt > 1) {
+            /* Соперников на CPU нет: достаточно события в конце кванта */
+            next = now + (uint64_t)sc->ticks_until_preempt * sched_tick_len_ns;
+        }
+        if (cpu_get_id() == 0) {
+            uint64_t deadline = waitq_next_deadline_ns();
+            if (deadline != 0 && deadline < next) {
+                next = deadline;
             }
         }
     }
 
-    if (ticks_until_preempt > 0) {
-        ticks_until_preempt--;
+    sc->next_event_ns = next;
+    sc->tick_stopped = next > now + sched_tick_len_ns;
+    clockevent_program(next);
+}
+
+void scheduler_timeout_armed(uint64_t deadline_ns)
+{
+    if (!sched_dyntick || cpu_get_id() == 0) {
+        return;
+    }
+    /* Таймауты обслуживает BSP: его событие должно быть не позже дедлайна */
+    sched_cpu_t* bsp = &sched_cpus[0];
+    if (bsp->next_event_ns == 0 || deadline_ns < bsp->next_event_ns) {
+        bsp->next_event_ns = deadline_ns;
+        cpu_kick(0);
+    }
+}
+
+int scheduler_dyntick_enable(void)
+{
+    if (sched_dyntick) {
+        return RDNX_OK;
+    }
+    if (!clocksource_available() || !clockevent_oneshot_capable()) {
+        return RDNX_E_UNSUPPORTED;
     }
 
-    if (ticks_until_preempt == 0) {
-        ticks_until_preempt = ticks_per_slice;
-        resched_pending = true;
+    irql_t old = scheduler_lock();
+    dyntick_base_ns = clocksource_monotonic_ns();
+    dyntick_base_tick = sched_ticks;
+    for (uint32_t cpu = 0; cpu < CPU_MAX_COUNT; cpu++) {
+        sched_cpus[cpu].last_tick = sched_ticks;
     }
+    if (clockevent_enable_oneshot() != 0) {
+        scheduler_unlock(old);
+        return RDNX_E_UNSUPPORTED;
+    }
+    sched_dyntick = true;
+    scheduler_unlock(old);
+    return RDNX_OK;
+}
+
+bool scheduler_dyntick_active(void)
+{
+    return sched_dyntick;
 }
 
 void scheduler_set_tick_rate(uint32_t hz)
@@ -42,13 +170,16 @@ void scheduler_set_tick_rate(uint32_t hz)
         return;
     }
 
-    uint32_t ticks = (hz * SCHEDULER_TIME_SLICE_MS + 999) / 1000;
+    sched_tick_len_ns = 1000000000ULL / hz;
+    uint64_t ticks = ((uint64_t)hz * (uint64_t)sched_quantum_ms + 999u) / 1000u;
     if (ticks == 0) {
         ticks = 1;
     }
-    ticks_per_slice = ticks;
-    if (ticks_until_preempt > ticks_per_slice) {
-        ticks_until_preempt = ticks_per_slice;
+    ticks_per_slice = (uint32_t)ticks;
+    for (uint32_t cpu = 0; cpu < CPU_MAX_COUNT; cpu++) {
+        if (sched_cpus[cpu].ticks_until_preempt > ticks_per_slice) {
+            sched_cpus[cpu].ticks_until_preempt = ticks_per_slice;
+        }
     }
 }
 
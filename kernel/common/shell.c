@@ -18,7 +18,12 @@
 #include "../fs/vfs.h"
 #include "../core/cpu.h"
 #include "../core/memory.h"
+#include "../arch/pmm.h"
+#include "../vm/vm_page_cache.h"
+#include "../vm/vm_pageout.h"
+#include "../vm/vm_swap.h"
 #include "../core/task.h"
+#include "../core/power.h"
 #include "../../include/console.h"
 #include "../../include/debug.h"
 #include "../../include/common.h"
@@ -32,6 +37,7 @@
 #include "../common/kmod.h"
 #include "../core/interrupts.h"
 #include "../fabric/fabric.h"
+#include "ddb.h"
 #include <stddef.h>
 #include <stdbool.h>
 #include <stdint.h>
@@ -417,13 +423,6 @@ static int shell_cmd_memory(int argc, char** argv)
     (void)argc;
     (void)argv;
     
-    extern uint64_t pmm_get_total_pages(void);
-    extern uint64_t pmm_get_free_pages(void);
-    extern uint64_t pmm_get_used_pages(void);
-    extern int pmm_get_zone_stats(int zone, void* out);
-    extern int pmm_get_free_regions(int zone, void* out, uint32_t max, uint32_t* out_count);
-    extern int pmm_get_usable_regions(void* out, uint32_t max, uint32_t* out_count);
-    extern int pmm_get_reserved_regions(void* out, uint32_t max, uint32_t* out_count);
     extern boot_info_t* boot_get_info(void);
     
     uint64_t total = pmm_get_total_pages();
@@ -433,21 +432,6 @@ static int shell_cmd_memory(int argc, char** argv)
     memory_info_t mem;
     bool mem_ok = (memory_get_info(&mem) == RDNX_OK);
 
-    typedef struct {
-        uint64_t total_pages;
-        uint64_t free_pages;
-        uint64_t used_pages;
-    } pmm_zone_stats_t;
-
-    typedef struct {
-        uint64_t base;
-        uint64_t length;
-    } pmm_region_t;
-
-    enum { PMM_ZONE_LOW = 0, PMM_ZONE_NORMAL = 1, PMM_ZONE_MMIO = 2, PMM_ZONE_COUNT = 3 };
-
-    const char* zone_names[] = { "low", "normal", "mmio" };
-    
     kprintf("Physical Memory:\n");
     kprintf("  Total: %llu pages (%llu KB)\n", total, (total * 4));
     kprintf("  Free:  %llu pages (%llu KB)\n", free, (free * 4));
@@ -458,6 +442,30 @@ static int shell_cmd_memory(int argc, char** argv)
                 (unsigned long long)mem.oom_vmm,
                 (unsigned long long)mem.oom_heap);
     }
+    vm_page_cache_stats_t pc;
+    vm_page_cache_get_stats(&pc);
+    kprintf("  Cache: %llu pages (%llu dirty, limit %llu) objects=%llu\n",
+            (unsigned long long)pc.resident, (unsigned long long)pc.dirty,
+            (unsigned long long)pc.limit, (unsigned long long)pc.objects);
+    kprintf("         hits=%llu misses=%llu fills=%llu writebacks=%llu evictions=%llu io_errors=%llu\n",
+            (unsigned long long)pc.hits, (unsigned long long)pc.misses,
+            (unsigned long long)pc.fills, (unsigned long long)pc.writebacks,
+            (unsigned long long)pc.evictions, (unsigned long long)pc.io_errors);
+    vm_swap_stats_t sw;
+    vm_pageout_stats_t po;
+    vm_swap_get_stats(&sw);
+    vm_pageout_get_stats(&po);
+    kprintf("  Swap:  %llu/%llu pages used, %u devices; pageouts=%llu pageins=%llu io_errors=%llu\n",
+            (unsigned long long)sw.used, (unsigned long long)sw.total,
+            (unsigned)vm_swap_device_count(), (unsigned long long)sw.pageouts,
+            (unsigned long long)sw.pageins, (unsigned long long)sw.io_errors);
+    kprintf("  Pageout: active=%llu inactive=%llu free_min=%llu free_target=%llu\n",
+            (unsigned long long)po.active, (unsigned long long)po.inactive,
+            (unsigned long long)po.free_min, (unsigned long long)po.free_target);
+    kprintf("         wakeups=%llu direct=%llu scans=%llu reactivated=%llu pageouts=%llu cache_freed=%llu\n",
+            (unsigned long long)po.wakeups, (unsigned long long)po.direct,
+            (unsigned long long)po.scans, (unsigned long long)po.reactivated,
+            (unsigned long long)po.pageouts, (unsigned long long)po.cache_freed);
     if (bi) {
         kprintf("Boot Memory Info:\n");
         kprintf("  Usable (MB2): %llu KB\n", (unsigned long long)(bi->mem_lower / 1024ULL));
@@ -470,17 +478,22 @@ static int shell_cmd_memory(int argc, char** argv)
     kprintf("Free Ranges:\n");
     for (int z = 0; z < PMM_ZONE_COUNT; z++) {
         pmm_zone_stats_t stats;
-        if (pmm_get_zone_stats(z, &stats) != 0) {
+        if (pmm_get_zone_stats((pmm_zone_t)z, &stats) != 0) {
             continue;
         }
         kprintf("  Zone %s: total=%llu free=%llu used=%llu pages\n",
-                zone_names[z],
+                pmm_zone_name((pmm_zone_t)z),
                 (unsigned long long)stats.total_pages,
                 (unsigned long long)stats.free_pages,
                 (unsigned long long)stats.used_pages);
+        kputs("    free blocks by order:");
+        for (uint32_t o = 0; o < PMM_ORDER_COUNT; o++) {
+            kprintf(" %llu", (unsigned long long)stats.free_blocks[o]);
+        }
+        kputs("\n");
 
         uint32_t total_ranges = 0;
-        pmm_get_free_regions(z, NULL, 0, &total_ranges);
+        pmm_get_free_regions((pmm_zone_t)z, NULL, 0, &total_ranges);
         if (total_ranges == 0) {
             kprintf("    (no free ranges)\n");
             continue;
@@ -488,7 +501,7 @@ static int shell_cmd_memory(int argc, char** argv)
         uint32_t show = (total_ranges > 8) ? 8 : total_ranges;
         pmm_region_t regions[8];
         uint32_t returned = 0;
-        if (pmm_get_free_regions(z, regions, show, &returned) != 0) {
+        if (pmm_get_free_regions((pmm_zone_t)z, regions, show, &returned) != 0) {
             continue;
         }
         for (uint32_t i = 0; i < returned; i++) {
@@ -923,6 +936,24 @@ static int shell_cmd_kmodunload(int argc, char** argv)
     return RDNX_OK;
 }
 
+/**
+ * @function shell_cmd_ddb
+ * @brief Stop in the kernel debugger (serial console)
+ * 
+ * @param argc Number of arguments
+ * @param argv Argument array
+ * 
+ * @return 0 on success
+ */
+static int shell_cmd_ddb(int argc, char** argv)
+{
+    (void)argc;
+    (void)argv;
+    kputs("Entering ddb on the serial console, 'c' to return\n");
+    ddb_enter("shell", NULL);
+    return 0;
+}
+
 /**
  * @function shell_cmd_exit
  * @brief Exit shell (reboot system)
@@ -938,12 +969,8 @@ static int shell_cmd_exit(int argc, char** argv)
     (void)argv;
     
     kputs("Rebooting...\n");
-    /* TODO: Implement actual reboot */
-    for (;;) {
-        __asm__ volatile ("hlt");
-    }
-    
-    return 0;
+    (void)vfs_sync();
+    power_reboot();
 }
 
 /* ============================================================================
@@ -983,6 +1010,7 @@ static const struct shell_command commands[] = {
     {"cat",     shell_cmd_cat,     "Show file contents"},
     {"ring3",   shell_cmd_ring3,   "Enter ring3 test stub"},
     {"run",     shell_cmd_run,     "Run userland program"},
+    {"ddb",     shell_cmd_ddb,     "Enter kernel debugger (serial console)"},
     {"exit",    shell_cmd_exit,    "Exit shell and reboot"},
     {NULL, NULL, NULL}  /* End marker */
 };
@@ -5,6 +5,7 @@
 
 #include "startup_trace.h"
 #include "bootlog.h"
+#include "bootparam.h"
 #include "../../include/console.h"
 #include "../../include/common.h"
 #include <stdbool.h>
@@ -12,33 +13,16 @@
 static bool g_bootverbose = false;
 static bool g_verbose_sysinit = false;
 
-static bool cmdline_has_substr(const char* cmdline, const char* needle)
-{
-    if (!cmdline || !needle || needle[0] == '\0') {
-        return false;
-    }
-    return strstr(cmdline, needle) != NULL;
-}
+BOOTPARAM_BOOL_ALIAS(param_bootverbose, "bootverbose", "debug.bootverbose", false,
+                     "Verbose boot messages and sysinit trace");
+BOOTPARAM_BOOL_ALIAS(param_verbose_sysinit, "verbose_sysinit", "debug.verbose_sysinit", false,
+                     "Trace every sysinit step");
 
-void startup_trace_init(const char* cmdline)
+void startup_trace_init(void)
 {
-    g_bootverbose = false;
-    g_verbose_sysinit = false;
-
-    if (!cmdline) {
-        return;
-    }
-
-    if (cmdline_has_substr(cmdline, "bootverbose") ||
-        cmdline_has_substr(cmdline, "debug.bootverbose=1") ||
-        cmdline_has_substr(cmdline, "bootlog=verbose") ||
-        cmdline_has_substr(cmdline, "startup_debug=verbose")) {
-        g_bootverbose = true;
-    }
-    if (cmdline_has_substr(cmdline, "verbose_sysinit") ||
-        cmdline_has_substr(cmdline, "debug.verbose_sysinit=1")) {
-        g_verbose_sysinit = true;
-    }
+    /* bootlog=verbose implies bootverbose: bootlog_init() runs first */
+    g_bootverbose = param_bootverbose || bootlog_is_verbose();
+    g_verbose_sysinit = param_verbose_sysinit;
 }
 
 int startup_trace_bootverbose(void)
@@ -21,6 +21,7 @@ enum startup_sub_id {
     SI_SUB_VFS        = 0x4000000,
     SI_SUB_PROTO      = 0x8800000,
     SI_SUB_KTHREAD    = 0xE000000,
+    SI_SUB_SMP        = 0xF000000,
     SI_SUB_LAST       = 0xFFFFFFF
 };
 
@@ -32,7 +33,7 @@ enum startup_order {
     SI_ORDER_ANY      = 0xFFFFFFF
 };
 
-void startup_trace_init(const char* cmdline);
+void startup_trace_init(void);
 int startup_trace_bootverbose(void);
 int startup_trace_verbose_sysinit(void);
 void startup_trace_step_begin(uint32_t subsystem, uint32_t order, const char* name);
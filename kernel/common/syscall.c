@@ -2,6 +2,7 @@
 #include "../posix/posix_syscall.h"
 #include "../linux/linux_compat.h"
 #include "../core/task.h"
+#include "../unix/unix_layer.h"
 #include "scheduler.h"
 #include "../../include/error.h"
 #include "../../include/console.h"
@@ -50,8 +51,19 @@ static uint64_t sys_write(uint64_t a1,
     if (len > 4096) {
         len = 4096;
     }
-    for (uint64_t i = 0; i < len; i++) {
-        kputc(buf[i]);
+    char chunk[128];
+    for (uint64_t done = 0; done < len;) {
+        uint64_t n = len - done;
+        if (n > sizeof(chunk)) {
+            n = sizeof(chunk);
+        }
+        if (unix_copy_from_user(chunk, buf + done, (size_t)n) != RDNX_OK) {
+            return (done > 0) ? done : (uint64_t)RDNX_E_INVALID;
+        }
+        for (uint64_t i = 0; i < n; i++) {
+            kputc(chunk[i]);
+        }
+        done += n;
     }
     return (uint64_t)len;
 }
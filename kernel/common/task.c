@@ -6,6 +6,7 @@
 #include "../core/task.h"
 #include "../vm/vm_map.h"
 #include "heap.h"
+#include "kmem.h"
 #include "../core/cpu.h"
 #include "../arch/interrupt_frame.h"
 #include "../core/interrupts.h"
@@ -21,16 +22,14 @@
 /*
  * LOCKING: per-CPU locals — no lock needed.
  *   Each CPU reads/writes only its own slot via cpu_get_id().
- *   On UP (single CPU) cpu_get_id() always returns 0.
- *   On SMP cpu_get_id() must return the correct APIC id before
- *   the scheduler starts (currently always 0 — see cpu.c TODO).
+ *   cpu_get_id() returns the logical CPU index (0 = BSP) from the per-CPU
+ *   block, so it is valid on every CPU before it enters the scheduler.
  */
-#define MAX_CPUS 8
 typedef struct {
     task_t*   task;
     thread_t* thread;
 } cpu_local_t;
-static cpu_local_t g_cpu_locals[MAX_CPUS];
+static cpu_local_t g_cpu_locals[CPU_MAX_COUNT];
 
 /*
  * LOCKING: task registry — protected by IRQL_HIGH (task_registry_lock / task_registry_unlock).
@@ -43,8 +42,10 @@ static cpu_local_t g_cpu_locals[MAX_CPUS];
  *   Protects: stack_cache[], stack_cache_count, stack_cache_hits, stack_cache_misses.
  *
  * LOCKING: g_cpu_locals[] — each slot written only by the owning CPU.
- *   On UP (current target) cpu_get_id() always returns 0; no explicit lock needed.
- *   On SMP: each CPU accesses only its own slot; cross-CPU reads are advisory only.
+ *   Each CPU accesses only its own slot; cross-CPU reads are advisory only.
+ *
+ * On SMP "IRQL_HIGH" above also means holding the IRQL giant lock
+ * (see arch interrupts.c), so these sections stay exclusive across CPUs.
  */
 static uint64_t next_task_id = 1;
 static uint64_t next_thread_id = 1;
@@ -71,6 +72,12 @@ RB_GENERATE_STATIC(task_id_index, task, task_id_link, task_id_cmp);
 #endif
 static struct task_id_index all_tasks_by_id = RB_INITIALIZER(&all_tasks_by_id);
 
+/*
+ * thread_t cache, created by the first thread_alloc().
+ * LOCKING: creation under task_registry_lock(); the pointer never changes after.
+ */
+static kmem_cache_t* thread_cache = NULL;
+
 static inline irql_t task_registry_lock(void)
 {
     return set_irql(IRQL_HIGH);
@@ -264,7 +271,19 @@ task_t* task_create(void)
     }
     task->sig_pending = 0;
     task->sig_in_handler = 0;
+    task->oom_killed = 0;
+    for (uint32_t i = 0; i < TASK_RLIMIT_COUNT; i++) {
+        task->rlimit[i].cur = TASK_RLIM_INFINITY;
+        task->rlimit[i].max = TASK_RLIM_INFINITY;
+    }
+    task->rlimit[TASK_RLIMIT_STACK].cur = TASK_DEFAULT_STACK_LIMIT;
+    task->rlimit[TASK_RLIMIT_NOFILE].cur = TASK_MAX_FD;
+    task->rlimit[TASK_RLIMIT_NOFILE].max = TASK_MAX_FD;
+    task->rlimit_cpu_next = 0;
+    task->sig_fpu_state = NULL;
     task->abi = TASK_ABI_NATIVE;
+    task->aslr_ctl = TASK_ASLR_DEFAULT;
+    task->aslr_active = 0;
     task->tls_fs_base = 0;
     {
         uint64_t* p = (uint64_t*)&task->sig_saved;
@@ -319,6 +338,7 @@ void task_destroy(task_t* task)
         }
     }
     vm_task_destroy(task);
+    cpu_fpu_state_free(task->sig_fpu_state);
     kfree(task);
 }
 
@@ -335,6 +355,16 @@ task_t* task_find_by_id(uint64_t task_id)
     return found;
 }
 
+task_t* task_debug_next(task_t* prev)
+{
+    return prev ? prev->next_all : all_tasks_head;
+}
+
+task_t* task_next_locked(task_t* prev)
+{
+    return prev ? prev->next_all : all_tasks_head;
+}
+
 void task_set_ids(task_t* task, uint32_t uid, uint32_t gid, uint32_t euid, uint32_t egid)
 {
     if (!task) {
@@ -362,6 +392,11 @@ task_abi_t task_get_abi(const task_t* task)
     return (task->abi == (uint8_t)TASK_ABI_LINUX) ? TASK_ABI_LINUX : TASK_ABI_NATIVE;
 }
 
+bool task_kill_pending(const task_t* task)
+{
+    return task && task->oom_killed;
+}
+
 uint32_t task_get_euid(const task_t* task)
 {
     return task ? task->euid : 0;
@@ -382,7 +417,8 @@ int task_fd_alloc(task_t* task, void* handle)
     if (!task || !handle) {
         return RDNX_E_INVALID;
     }
-    for (int i = 0; i < TASK_MAX_FD; i++) {
+    int limit = task_fd_limit(task);
+    for (int i = 0; i < limit; i++) {
         if (!task->fd_table[i]) {
             task->fd_table[i] = handle;
             task->fd_flags[i] = 0;
@@ -393,6 +429,14 @@ int task_fd_alloc(task_t* task, void* handle)
     return RDNX_E_BUSY;
 }
 
+int task_fd_limit(const task_t* task)
+{
+    if (!task || task->rlimit[TASK_RLIMIT_NOFILE].cur >= TASK_MAX_FD) {
+        return TASK_MAX_FD;
+    }
+    return (int)task->rlimit[TASK_RLIMIT_NOFILE].cur;
+}
+
 void* task_fd_get(task_t* task, int fd)
 {
     if (!task || fd < 0 || fd >= TASK_MAX_FD) {
@@ -415,20 +459,41 @@ int task_fd_close(task_t* task, int fd)
     return RDNX_OK;
 }
 
+static thread_t* thread_alloc(void)
+{
+    if (!thread_cache) {
+        irql_t old = task_registry_lock();
+        if (!thread_cache) {
+            thread_cache = kmem_cache_create("thread", sizeof(thread_t), 0, NULL, NULL, NULL, 0);
+        }
+        task_registry_unlock(old);
+        if (!thread_cache) {
+            return NULL;
+        }
+    }
+    return (thread_t*)kmem_cache_zalloc(thread_cache);
+}
+
 thread_t* thread_create(task_t* task, void (*entry)(void*), void* arg)
 {
     if (!task || !entry) {
         return NULL;
     }
 
-    thread_t* thread = (thread_t*)kmalloc(sizeof(thread_t));
+    thread_t* thread = thread_alloc();
     if (!thread) {
         return NULL;
     }
 
     void* stack = task_kernel_stack_acquire();
     if (!stack) {
-        kfree(thread);
+        kmem_cache_free(thread_cache, thread);
+        return NULL;
+    }
+    void* fpu_state = cpu_fpu_state_alloc();
+    if (!fpu_state) {
+        task_kernel_stack_retire(stack, KERNEL_STACK_SIZE);
+        kmem_cache_free(thread_cache, thread);
         return NULL;
     }
 
@@ -454,6 +519,7 @@ thread_t* thread_create(task_t* task, void (*entry)(void*), void* arg)
     thread->task = task;
     thread->context.stack_pointer = (uint64_t)(uintptr_t)frame;
     thread->context.program_counter = frame->rip;
+    thread->context.fpu_state = fpu_state;
     thread->state = THREAD_STATE_NEW;
     thread->sched_class = SCHED_CLASS_TIMESHARE;
     thread->priority = PRIORITY_DEFAULT;
@@ -481,12 +547,16 @@ thread_t* thread_create(task_t* task, void (*entry)(void*), void* arg)
     thread->wait_timeout_link.tqe_next = NULL;
     thread->wait_timeout_link.tqe_prev = NULL;
     thread->waitq_owner = NULL;
-    thread->wait_deadline_tick = 0;
+    thread->wait_deadline_ns = 0;
     thread->wait_timeout_armed = 0;
     thread->wait_timed_out = 0;
     thread->joiner = NULL;
     thread->reap_queued = 0;
     thread->reap_after_tick = 0;
+    thread->sched_cpu = 0;
+    thread->sched_pinned = 0;
+    thread->sched_on_cpu = 0;
+    thread->saved_irql = (uint8_t)IRQL_PASSIVE;
     thread->arch_specific = NULL;
     task->thread_count++;
     TAILQ_INSERT_TAIL(&task->threads, thread, task_link);
@@ -503,14 +573,20 @@ thread_t* thread_create_user_clone(task_t* task, const interrupt_frame_t* frame)
         return NULL;
     }
 
-    thread_t* thread = (thread_t*)kmalloc(sizeof(thread_t));
+    thread_t* thread = thread_alloc();
     if (!thread) {
         return NULL;
     }
 
     void* stack = task_kernel_stack_acquire();
     if (!stack) {
-        kfree(thread);
+        kmem_cache_free(thread_cache, thread);
+        return NULL;
+    }
+    void* fpu_state = cpu_fpu_state_alloc();
+    if (!fpu_state) {
+        task_kernel_stack_retire(stack, KERNEL_STACK_SIZE);
+        kmem_cache_free(thread_cache, thread);
         return NULL;
     }
 
@@ -526,6 +602,7 @@ thread_t* thread_create_user_clone(task_t* task, const interrupt_frame_t* frame)
     thread->task = task;
     thread->context.stack_pointer = (uint64_t)(uintptr_t)child_frame;
     thread->context.program_counter = child_frame->rip;
+    thread->context.fpu_state = fpu_state;
     thread->state = THREAD_STATE_NEW;
     thread->sched_class = SCHED_CLASS_TIMESHARE;
     thread->priority = PRIORITY_DEFAULT;
@@ -553,12 +630,16 @@ thread_t* thread_create_user_clone(task_t* task, const interrupt_frame_t* frame)
     thread->wait_timeout_link.tqe_next = NULL;
     thread->wait_timeout_link.tqe_prev = NULL;
     thread->waitq_owner = NULL;
-    thread->wait_deadline_tick = 0;
+    thread->wait_deadline_ns = 0;
     thread->wait_timeout_armed = 0;
     thread->wait_timed_out = 0;
     thread->joiner = NULL;
     thread->reap_queued = 0;
     thread->reap_after_tick = 0;
+    thread->sched_cpu = 0;
+    thread->sched_pinned = 0;
+    thread->sched_on_cpu = 0;
+    thread->saved_irql = (uint8_t)IRQL_PASSIVE;
     thread->arch_specific = NULL;
     task->thread_count++;
     TAILQ_INSERT_TAIL(&task->threads, thread, task_link);
@@ -583,7 +664,8 @@ void thread_destroy(thread_t* thread)
     if (thread->stack) {
         task_kernel_stack_retire(thread->stack, thread->stack_size);
     }
-    kfree(thread);
+    cpu_fpu_state_free(thread->context.fpu_state);
+    kmem_cache_free(thread_cache, thread);
 }
 
 void thread_switch(thread_t* from, thread_t* to)
@@ -12,6 +12,7 @@
 #include "../../include/debug.h"
 
 static uint32_t tracev2_seq = 0;
+static tracev2_record_t tracev2_ring[TRACEV2_RING_SIZE];
 
 static void tr2_append_str(char* out, size_t out_len, size_t* pos, const char* s)
 {
@@ -52,6 +53,16 @@ void tracev2_emit(uint16_t cat, uint16_t ev, uint64_t a0, uint64_t a1)
     uint32_t cpu = cpu_get_id();
     uint64_t tk = scheduler_get_ticks();
 
+    tracev2_record_t* rec = &tracev2_ring[seq % TRACEV2_RING_SIZE];
+    rec->seq = seq;
+    rec->cat = cat;
+    rec->ev = ev;
+    rec->cpu = cpu;
+    rec->reserved = 0;
+    rec->tick = tk;
+    rec->a0 = a0;
+    rec->a1 = a1;
+
     char ring_line[80];
     size_t pos = 0;
     ring_line[0] = '\0';
@@ -83,3 +94,19 @@ void tracev2_emit(uint16_t cat, uint16_t ev, uint64_t a0, uint64_t a1)
                 (unsigned long long)a1);
     }
 }
+
+uint32_t tracev2_snapshot(tracev2_record_t* out, uint32_t max)
+{
+    if (!out || max == 0) {
+        return 0;
+    }
+    uint32_t end = tracev2_seq;
+    uint32_t n = end < TRACEV2_RING_SIZE ? end : TRACEV2_RING_SIZE;
+    if (n > max) {
+        n = max;
+    }
+    for (uint32_t i = 0; i < n; i++) {
+        out[i] = tracev2_ring[(end - n + i) % TRACEV2_RING_SIZE];
+    }
+    return n;
+}
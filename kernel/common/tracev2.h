@@ -26,6 +26,7 @@ enum {
     TR2_EV_MEM_INIT_ENTER = 1,
     TR2_EV_MEM_INIT_DONE = 2,
     TR2_EV_MEM_INIT_FAIL = 3,
+    TR2_EV_MEM_OOM_KILL = 4,     /* a0 = task_id, a1 = resident pages */
 };
 
 enum {
@@ -33,6 +34,27 @@ enum {
     TR2_EV_FAULT_PAGE = 2,
 };
 
+/* Binary copy of the most recent events (crash dump, debugger) */
+#define TRACEV2_RING_SIZE 64
+
+typedef struct {
+    uint32_t seq;
+    uint16_t cat;
+    uint16_t ev;
+    uint32_t cpu;
+    uint32_t reserved;
+    uint64_t tick;
+    uint64_t a0;
+    uint64_t a1;
+} tracev2_record_t;
+
 void tracev2_emit(uint16_t cat, uint16_t ev, uint64_t a0, uint64_t a1);
 
+/**
+ * Copy the recent events, oldest first.
+ * Lock-free and lossy: meant for post-mortem use.
+ * @return Number of records written to out
+ */
+uint32_t tracev2_snapshot(tracev2_record_t* out, uint32_t max);
+
 #endif /* _RODNIX_COMMON_TRACEV2_H */
@@ -5,9 +5,14 @@
 
 #include "waitq.h"
 #include "scheduler.h"
+#include "../core/interrupts.h"
 #include "../../include/error.h"
 #include <stddef.h>
 
+/*
+ * Потоки с дедлайном, по возрастанию wait_deadline_ns: ближайший — первый.
+ * LOCKING: меняется только при IRQL_HIGH (IRQL giant на SMP).
+ */
 TAILQ_HEAD(waitq_timeout_head, thread);
 static struct waitq_timeout_head waitq_timeouts;
 static bool waitq_timeouts_initialized = false;
@@ -28,36 +33,47 @@ static void waitq_disarm_timeout(thread_t* t)
     }
     TAILQ_REMOVE(&waitq_timeouts, t, wait_timeout_link);
     t->wait_timeout_armed = 0;
-    t->wait_deadline_tick = 0;
+    t->wait_deadline_ns = 0;
     t->wait_timeout_link.tqe_next = NULL;
     t->wait_timeout_link.tqe_prev = NULL;
 }
 
-static void waitq_arm_timeout(thread_t* t, uint64_t deadline_ticks)
+static void waitq_arm_timeout(thread_t* t, uint64_t deadline_ns)
 {
-    if (!t || deadline_ticks == 0) {
+    if (!t || deadline_ns == 0) {
         return;
     }
     waitq_timeouts_init_once();
     if (t->wait_timeout_armed) {
         waitq_disarm_timeout(t);
     }
-    t->wait_deadline_tick = deadline_ticks;
-    TAILQ_INSERT_TAIL(&waitq_timeouts, t, wait_timeout_link);
+    t->wait_deadline_ns = deadline_ns;
+
+    thread_t* it = NULL;
+    TAILQ_FOREACH(it, &waitq_timeouts, wait_timeout_link) {
+        if (it->wait_deadline_ns > deadline_ns) {
+            break;
+        }
+    }
+    if (it) {
+        TAILQ_INSERT_BEFORE(it, t, wait_timeout_link);
+    } else {
+        TAILQ_INSERT_TAIL(&waitq_timeouts, t, wait_timeout_link);
+    }
     t->wait_timeout_armed = 1;
+    scheduler_timeout_armed(deadline_ns);
 }
 
-static uint64_t waitq_deadline_from_timeout_ms(uint64_t timeout_ms)
+static uint64_t waitq_deadline_from_timeout_ns(uint64_t timeout_ns)
 {
-    if (timeout_ms == 0) {
+    if (timeout_ns == 0) {
         return 0;
     }
-    uint64_t now = scheduler_get_ticks();
-    uint64_t ticks = (timeout_ms + (SCHEDULER_TIME_SLICE_MS - 1)) / SCHEDULER_TIME_SLICE_MS;
-    if (ticks == 0) {
-        ticks = 1;
+    uint64_t now = scheduler_now_ns();
+    if (timeout_ns > UINT64_MAX - now) {
+        return UINT64_MAX;
     }
-    return now + ticks;
+    return now + timeout_ns;
 }
 
 void waitq_init(waitq_t* q, const char* name)
@@ -175,7 +191,36 @@ uint32_t waitq_timed_count(void)
     return count;
 }
 
+void waitq_interrupt(thread_t* t)
+{
+    if (!t) {
+        return;
+    }
+    irql_t old = set_irql(IRQL_HIGH);
+    waitq_t* owner = t->waitq_owner;
+    if (owner && waitq_remove(owner, t) == RDNX_OK) {
+        t->wait_timed_out = 1;
+        scheduler_wake(t);
+    }
+    (void)set_irql(old);
+}
+
 int waitq_wait_until(waitq_t* q, uint64_t deadline_ticks)
+{
+    if (deadline_ticks == 0) {
+        return waitq_wait_until_ns(q, 0);
+    }
+    uint64_t now_ticks = scheduler_get_ticks();
+    uint64_t now_ns = scheduler_now_ns();
+    uint64_t deadline_ns = now_ns;
+    if (deadline_ticks > now_ticks) {
+        deadline_ns += (deadline_ticks - now_ticks) * scheduler_tick_ns();
+    }
+    /* 0 означает "без дедлайна" */
+    return waitq_wait_until_ns(q, deadline_ns ? deadline_ns : 1);
+}
+
+int waitq_wait_until_ns(waitq_t* q, uint64_t deadline_ns)
 {
     if (!q) {
         return RDNX_E_INVALID;
@@ -186,15 +231,26 @@ int waitq_wait_until(waitq_t* q, uint64_t deadline_ticks)
         return RDNX_E_INVALID;
     }
 
+    /*
+     * Enqueue, block and dispatch must not interleave with a wakeup from an
+     * IRQ or another CPU: keep IRQL raised until the thread is switched out.
+     */
+    irql_t old_irql = set_irql(IRQL_HIGH);
+    if (task_kill_pending(self->task)) {
+        /* Dying task: do not sleep, let it reach its exit */
+        (void)set_irql(old_irql);
+        return RDNX_E_TIMEOUT;
+    }
     self->wait_timed_out = 0;
     if (!waitq_contains(q, self)) {
         int qret = waitq_enqueue(q, self);
         if (qret != RDNX_OK && qret != RDNX_E_BUSY) {
+            (void)set_irql(old_irql);
             return qret;
         }
     }
-    if (deadline_ticks) {
-        waitq_arm_timeout(self, deadline_ticks);
+    if (deadline_ns) {
+        waitq_arm_timeout(self, deadline_ns);
     }
 
     while (waitq_contains(q, self)) {
@@ -209,26 +265,46 @@ int waitq_wait_until(waitq_t* q, uint64_t deadline_ticks)
 
     int ret = self->wait_timed_out ? RDNX_E_TIMEOUT : RDNX_OK;
     self->wait_timed_out = 0;
+    (void)set_irql(old_irql);
     return ret;
 }
 
 int waitq_wait(waitq_t* q, uint64_t timeout_ms)
 {
-    return waitq_wait_until(q, waitq_deadline_from_timeout_ms(timeout_ms));
+    uint64_t timeout_ns = timeout_ms * 1000000ULL;
+    if (timeout_ms > UINT64_MAX / 1000000ULL) {
+        timeout_ns = UINT64_MAX;
+    }
+    return waitq_wait_until_ns(q, waitq_deadline_from_timeout_ns(timeout_ns));
+}
+
+int waitq_wait_ns(waitq_t* q, uint64_t timeout_ns)
+{
+    return waitq_wait_until_ns(q, waitq_deadline_from_timeout_ns(timeout_ns));
 }
 
-void waitq_tick(uint64_t now_ticks)
+uint64_t waitq_next_deadline_ns(void)
+{
+    if (!waitq_timeouts_initialized) {
+        return 0;
+    }
+    thread_t* first = TAILQ_FIRST(&waitq_timeouts);
+    return first ? first->wait_deadline_ns : 0;
+}
+
+void waitq_expire(uint64_t now_ns)
 {
     waitq_timeouts_init_once();
     thread_t* it = NULL;
     thread_t* next = NULL;
     TAILQ_FOREACH_SAFE(it, &waitq_timeouts, wait_timeout_link, next) {
-        if (!it->wait_timeout_armed || it->wait_deadline_tick == 0) {
+        if (!it->wait_timeout_armed || it->wait_deadline_ns == 0) {
             waitq_disarm_timeout(it);
             continue;
         }
-        if (it->wait_deadline_tick > now_ticks) {
-            continue;
+        /* Список упорядочен: дальше дедлайны только позже */
+        if (it->wait_deadline_ns > now_ns) {
+            break;
         }
 
         waitq_t* owner = it->waitq_owner;
@@ -27,9 +27,15 @@ thread_t* waitq_dequeue(waitq_t* q);
 thread_t* waitq_wake_one(waitq_t* q);
 uint32_t waitq_wake_all(waitq_t* q);
 uint32_t waitq_count(const waitq_t* q);
+/* Take @t off its waitq as if its deadline had passed */
+void waitq_interrupt(thread_t* t);
 int waitq_wait_until(waitq_t* q, uint64_t deadline_ticks);
+int waitq_wait_until_ns(waitq_t* q, uint64_t deadline_ns);
 int waitq_wait(waitq_t* q, uint64_t timeout_ms);
-void waitq_tick(uint64_t now_ticks);
+int waitq_wait_ns(waitq_t* q, uint64_t timeout_ns);
+/* Deadlines are on the scheduler_now_ns() scale */
+void waitq_expire(uint64_t now_ns);
+uint64_t waitq_next_deadline_ns(void);
 uint32_t waitq_timed_count(void);
 
 #endif /* _RODNIX_COMMON_WAITQ_H */
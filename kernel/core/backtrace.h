@@ -0,0 +1,46 @@
+/**
+ * @file backtrace.h
+ * @brief Архитектурно-независимый интерфейс трассировки стека
+ *
+ * Раскрутка идёт по цепочке frame pointer (ядро собирается с
+ * -fno-omit-frame-pointer) и продолжается через кадр прерывания в
+ * прерванный контекст. Адреса символизируются встроенной таблицей
+ * символов (common/ksyms.h).
+ */
+
+#ifndef _RODNIX_CORE_BACKTRACE_H
+#define _RODNIX_CORE_BACKTRACE_H
+
+#include <stdint.h>
+
+/* Функция вывода строки; NULL - kputs */
+typedef void (*backtrace_out_t)(const char* str);
+
+/**
+ * Напечатать цепочку вызовов, начиная с вызывающего
+ * @param out Вывод (для путей, где kprintf небезопасен, - serial)
+ */
+void backtrace_print(backtrace_out_t out);
+
+/**
+ * Напечатать цепочку вызовов прерванного контекста
+ * @param pc Адрес инструкции (первая строка трассы)
+ * @param fp Frame pointer этого контекста
+ * @param out Вывод (NULL - kputs)
+ */
+void backtrace_print_from(uint64_t pc, uint64_t fp, backtrace_out_t out);
+
+/**
+ * Собрать адреса цепочки вызовов без печати (для crash dump)
+ * @param pcs Буфер адресов
+ * @param max Размер буфера
+ * @return Число записанных адресов
+ */
+uint32_t backtrace_capture(uint64_t* pcs, uint32_t max);
+
+/**
+ * То же для прерванного контекста; первым идёт pc
+ */
+uint32_t backtrace_capture_from(uint64_t pc, uint64_t fp, uint64_t* pcs, uint32_t max);
+
+#endif /* _RODNIX_CORE_BACKTRACE_H */
@@ -32,8 +32,25 @@ typedef struct {
     void* mmap_addr;          /* Multiboot2 memory map tag address */
     uint32_t mmap_size;       /* Multiboot2 memory map tag size */
     uint32_t mmap_entry_size; /* Multiboot2 memory map entry size */
+    uint64_t fb_addr;         /* Физический адрес framebuffer (0 - нет) */
+    uint32_t fb_pitch;        /* Байт на строку развертки */
+    uint32_t fb_width;        /* Ширина в пикселях */
+    uint32_t fb_height;       /* Высота в пикселях */
+    uint8_t fb_bpp;           /* Бит на пиксель */
+    uint8_t fb_type;          /* BOOT_FB_TYPE_* */
+    uint8_t fb_red_pos;       /* Позиция/ширина компонент для BOOT_FB_TYPE_RGB */
+    uint8_t fb_red_size;
+    uint8_t fb_green_pos;
+    uint8_t fb_green_size;
+    uint8_t fb_blue_pos;
+    uint8_t fb_blue_size;
 } boot_info_t;
 
+/* Тип framebuffer (совпадает с Multiboot2 framebuffer_type) */
+#define BOOT_FB_TYPE_INDEXED  0
+#define BOOT_FB_TYPE_RGB      1
+#define BOOT_FB_TYPE_EGA_TEXT 2
+
 /* ============================================================================
  * Функции инициализации
  * ============================================================================ */
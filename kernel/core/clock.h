@@ -0,0 +1,99 @@
+/**
+ * @file clock.h
+ * @brief Архитектурно-независимый интерфейс источника времени (clocksource)
+ *
+ * Clocksource — свободно бегущий счётчик с известной частотой, из которого
+ * строится монотонное время с наносекундным разрешением. Выбор источника
+ * делает архитектурный код (x86_64: invariant TSC, затем HPET). Если ни один
+ * не доступен, время продолжает считаться по тикам таймера (console uptime).
+ */
+
+#ifndef _RODNIX_CORE_CLOCK_H
+#define _RODNIX_CORE_CLOCK_H
+
+#include <stdint.h>
+#include <stdbool.h>
+
+#define CLOCK_NSEC_PER_SEC 1000000000ULL
+
+/**
+ * Обнаружение и выбор источника времени (BSP, после ACPI, до таймера)
+ * @return 0 при успехе, отрицательное значение если источника нет
+ */
+int clocksource_init(void);
+
+/**
+ * Выбран ли источник времени
+ */
+bool clocksource_available(void);
+
+/**
+ * Монотонное время с момента clocksource_init() в наносекундах.
+ * Значение не убывает между вызовами на любых CPU.
+ * @return Время в нс или 0, если источник не выбран
+ */
+uint64_t clocksource_monotonic_ns(void);
+
+/**
+ * Имя выбранного источника ("tsc", "hpet") или "none"
+ */
+const char* clocksource_name(void);
+
+/**
+ * Частота выбранного источника в Гц (0 — источника нет)
+ */
+uint64_t clocksource_frequency(void);
+
+/**
+ * Перевод числа тактов счётчика с частотой @p hz в наносекунды без
+ * переполнения для частот до ~18 ГГц
+ */
+static inline uint64_t clock_cycles_to_ns(uint64_t cycles, uint64_t hz)
+{
+    if (hz == 0) {
+        return 0;
+    }
+    return (cycles / hz) * CLOCK_NSEC_PER_SEC +
+           ((cycles % hz) * CLOCK_NSEC_PER_SEC) / hz;
+}
+
+/**
+ * Перевод наносекунд в такты счётчика с частотой @p hz
+ */
+static inline uint64_t clock_ns_to_cycles(uint64_t ns, uint64_t hz)
+{
+    return (ns / CLOCK_NSEC_PER_SEC) * hz +
+           ((ns % CLOCK_NSEC_PER_SEC) * hz) / CLOCK_NSEC_PER_SEC;
+}
+
+/* ============================================================================
+ * Clockevent: однократные события таймера текущего CPU
+ * ============================================================================ */
+
+/*
+ * В периодическом режиме таймер CPU сам генерирует тик с частотой
+ * scheduler_set_tick_rate(). В однократном (one-shot) режиме следующее
+ * прерывание таймера задаёт планировщик через clockevent_program().
+ * Прерывание приходит на тот же вектор, что и периодический тик.
+ */
+
+/**
+ * Может ли таймер работать в однократном режиме (x86_64: LAPIC TSC-deadline)
+ */
+bool clockevent_oneshot_capable(void);
+
+/**
+ * Перевести таймеры всех CPU в однократный режим. После вызова таймер
+ * больше не перевзводится сам: каждое событие задаёт clockevent_program().
+ * @return 0 при успехе, отрицательное значение если режим недоступен
+ */
+int clockevent_enable_oneshot(void);
+
+/**
+ * Взвести событие таймера текущего CPU
+ * @param deadline_ns Момент clocksource_monotonic_ns(); прошедший момент —
+ *                    прерывание сразу, 0 — снять событие
+ */
+void clockevent_program(uint64_t deadline_ns);
+
+#endif /* _RODNIX_CORE_CLOCK_H */
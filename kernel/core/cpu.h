@@ -12,6 +12,10 @@
 #include "arch_types.h"
 #include <stdint.h>
 #include <stdbool.h>
+#include <stddef.h>
+
+/* Максимальное число логических процессоров, которое поддерживает ядро */
+#define CPU_MAX_COUNT 8
 
 /* ============================================================================
  * Информация о процессоре
@@ -71,6 +75,7 @@ typedef struct thread_context {
     void* arch_specific;       /* Архитектурно-зависимые данные */
     uint64_t stack_pointer;    /* Указатель стека */
     uint64_t program_counter;  /* Счетчик команд */
+    void* fpu_state;           /* Область FPU/SIMD (cpu_fpu_state_alloc), NULL — нет */
 } thread_context_t;
 
 /**
@@ -92,6 +97,115 @@ void cpu_restore_context(thread_context_t* ctx);
  */
 void cpu_switch_thread(thread_context_t* from, thread_context_t* to);
 
+/**
+ * Отложенный сброс флага "поток на CPU" после переключения.
+ * Флаг сбрасывается архитектурным кодом уже после перехода на стек
+ * нового потока, чтобы другой CPU не подхватил стек предыдущего раньше времени.
+ * @param on_cpu_flag Указатель на флаг предыдущего потока (или NULL)
+ */
+void cpu_defer_switch_release(volatile uint8_t* on_cpu_flag);
+
+/* ============================================================================
+ * Состояние FPU/SIMD потока
+ * ============================================================================ */
+
+/*
+ * Переключение активное (eager): ядро не использует FPU/SIMD, поэтому
+ * регистры CPU всегда содержат пользовательское состояние текущего потока.
+ * Планировщик сохраняет их в thread_context_t.fpu_state при уходе с потока
+ * и загружает при возврате. Функции допускают state == NULL (no-op).
+ */
+
+/**
+ * Размер области состояния FPU/SIMD в байтах
+ */
+size_t cpu_fpu_state_size(void);
+
+/**
+ * Выделение области с начальным состоянием (как после FNINIT)
+ * @return Указатель на область или NULL при нехватке памяти
+ */
+void* cpu_fpu_state_alloc(void);
+
+/**
+ * Освобождение области cpu_fpu_state_alloc()
+ */
+void cpu_fpu_state_free(void* state);
+
+/**
+ * Копирование области состояния
+ */
+void cpu_fpu_state_copy(void* dst, const void* src);
+
+/**
+ * Сохранение регистров FPU/SIMD текущего CPU в область
+ */
+void cpu_fpu_save(void* state);
+
+/**
+ * Загрузка регистров FPU/SIMD текущего CPU из области
+ */
+void cpu_fpu_restore(const void* state);
+
+/**
+ * Сброс регистров FPU/SIMD текущего CPU в начальное состояние
+ */
+void cpu_fpu_reset(void);
+
+/* ============================================================================
+ * Доступ к пользовательской памяти
+ * ============================================================================ */
+
+/*
+ * При включённом SMAP ядро не может читать и писать пользовательские
+ * страницы вне явно открытого окна. Окно короткое, не пересекает сон и
+ * переключение потока; проверку диапазона делает вызывающий код
+ * (unix_user_range_ok, unix_copy_from_user/unix_copy_to_user).
+ */
+
+/**
+ * Открыть окно доступа к пользовательской памяти (x86_64: STAC)
+ */
+void cpu_user_access_begin(void);
+
+/**
+ * Закрыть окно доступа к пользовательской памяти (x86_64: CLAC)
+ */
+void cpu_user_access_end(void);
+
+/*
+ * Примитивы копирования для вызова внутри окна. Fault на неотображённом
+ * пользовательском адресе внутри них не ведёт к panic: примитив
+ * возвращает RDNX_E_INVALID.
+ */
+
+/**
+ * Копирование len байт (одна из сторон — пользовательская память)
+ * @return RDNX_OK или RDNX_E_INVALID при fault
+ */
+int cpu_user_copy(void* dst, const void* src, size_t len);
+
+/**
+ * Обнуление len байт пользовательской памяти
+ * @return RDNX_OK или RDNX_E_INVALID при fault
+ */
+int cpu_user_clear(void* dst, size_t len);
+
+/**
+ * Копирование строки не длиннее len байт вместе с завершающим нулём
+ * @return RDNX_OK, если ноль скопирован; RDNX_E_INVALID при fault или
+ *         если нуля нет в первых len байтах
+ */
+int cpu_user_copy_str(char* dst, const char* src, size_t len);
+
+/**
+ * Вызывается из обработчика page fault для неразрешённого fault ядра на
+ * пользовательском адресе: если *rip внутри примитивов, переносит его на
+ * возврат ошибки.
+ * @return true, если fault исправлен
+ */
+bool cpu_user_fault_fixup(uint64_t* rip);
+
 /* ============================================================================
  * Барьеры памяти
  * ============================================================================ */
@@ -191,6 +305,14 @@ void cpu_pause(void);
  */
 void cpu_idle(void);
 
+/**
+ * Разбудить другой CPU: он получает прерывание таймера вне расписания,
+ * выходит из cpu_idle() и проходит scheduler_tick() и перепланирование.
+ * Вызов для текущего CPU и для CPU не в сети ничего не делает.
+ * @param cpu_id Логический номер CPU
+ */
+void cpu_kick(uint32_t cpu_id);
+
 /**
  * Получение тактовой частоты процессора
  * @return Частота в Гц или 0 если неизвестна
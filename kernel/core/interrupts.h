@@ -97,6 +97,20 @@ void interrupts_enable(void);
  */
 void interrupts_disable(void);
 
+/**
+ * Запрет прерываний только на текущем CPU, без смены IRQL и giant lock.
+ * Для коротких per-CPU секций (magazine-слой kmem); внутри нельзя
+ * вызывать set_irql() и ничего, что может заснуть.
+ * @return Состояние для interrupts_local_restore()
+ */
+uint64_t interrupts_local_save(void);
+
+/**
+ * Восстановление состояния, сохранённого interrupts_local_save()
+ * @param state Значение, возвращённое interrupts_local_save()
+ */
+void interrupts_local_restore(uint64_t state);
+
 /**
  * Получение текущего уровня прерываний
  * @return Текущий IRQL
@@ -127,5 +141,32 @@ void interrupt_wait(void);
  */
 int interrupt_send_ipi(uint32_t cpu_id, uint32_t vector);
 
+/* ============================================================================
+ * Динамические векторы (MSI/MSI-X)
+ * ============================================================================ */
+
+/**
+ * Выделение блока из @p count подряд идущих свободных векторов.
+ * Первый вектор кратен @p align (степень двойки): MSI с несколькими
+ * сообщениями требует выровненного блока. Обработчик на выделенный вектор
+ * ставится обычным interrupt_register() (или fabric_request_irq()).
+ * @return Первый вектор или отрицательное значение, если блока нет
+ */
+int interrupt_alloc_vectors(uint32_t count, uint32_t align);
+
+/**
+ * Освобождение векторов, полученных от interrupt_alloc_vectors().
+ * Обработчики должны быть сняты заранее.
+ */
+void interrupt_free_vectors(uint32_t first, uint32_t count);
+
+/**
+ * Адрес и данные MSI-сообщения, доставляющего @p vector на процессор
+ * @p cpu_id (фиксированная доставка, фронт)
+ * @return 0 при успехе, отрицательное значение при ошибке
+ */
+int interrupt_msi_message(uint32_t vector, uint32_t cpu_id,
+                          uint64_t* addr, uint32_t* data);
+
 #endif /* _RODNIX_CORE_INTERRUPTS_H */
 
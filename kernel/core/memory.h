@@ -160,6 +160,21 @@ void* vmm_alloc_pages(uint32_t count, uint64_t flags);
  */
 void vmm_free_pages(void* virt, uint32_t count);
 
+/**
+ * Выделение страниц под образ загружаемого модуля: обнулённые, доступны
+ * на запись и исполнение. Прочая память ядра, кроме .text, отображена с NX.
+ * @param count Количество страниц
+ * @return Виртуальный адрес первой страницы или NULL при ошибке
+ */
+void* vmm_alloc_exec(uint32_t count);
+
+/**
+ * Освобождение страниц, выделенных vmm_alloc_exec()
+ * @param virt Виртуальный адрес первой страницы
+ * @param count Количество страниц
+ */
+void vmm_free_exec(void* virt, uint32_t count);
+
 /* ============================================================================
  * Информация о памяти
  * ============================================================================ */
@@ -188,7 +203,7 @@ void memory_oom_inc_vmm(void);
 void memory_oom_inc_heap(void);
 
 /* ============================================================================
- * Kernel Heap (simple allocator)
+ * Kernel Heap (kmalloc на kmem-кэшах, см. common/kmem.h)
  * ============================================================================ */
 
 int heap_init(size_t initial_pages);
@@ -0,0 +1,28 @@
+/**
+ * @file power.h
+ * @brief Архитектурно-независимый интерфейс выключения и перезагрузки
+ *
+ * Реализуется архитектурным кодом (x86_64: ACPI S5 и регистр сброса FADT,
+ * затем контроллер клавиатуры и triple fault). Синхронизация файловых
+ * систем — забота вызывающего, функции её не делают.
+ */
+
+#ifndef _RODNIX_CORE_POWER_H
+#define _RODNIX_CORE_POWER_H
+
+/**
+ * Выключить питание машины. Если выключить не удалось, CPU останавливается.
+ */
+void power_off(void) __attribute__((noreturn));
+
+/**
+ * Перезагрузить машину, перебирая доступные способы сброса
+ */
+void power_reboot(void) __attribute__((noreturn));
+
+/**
+ * Остановить все CPU без выключения питания (остальные паркуются через NMI)
+ */
+void power_halt(void) __attribute__((noreturn));
+
+#endif /* _RODNIX_CORE_POWER_H */
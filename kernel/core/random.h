@@ -0,0 +1,58 @@
+/**
+ * @file random.h
+ * @brief Архитектурно-независимый интерфейс источника случайных чисел ядра
+ *
+ * Архитектурный код даёт сырую энтропию: аппаратный генератор (x86_64:
+ * RDRAND), если он есть, и счётчик тактов, младшие биты которого дрожат
+ * между вызовами. Общий код (common/random.c) засевает из них
+ * криптографически нестойкий, но непредсказуемый снаружи PRNG и
+ * подмешивает свежую энтропию при каждом запросе. Этого достаточно для
+ * рандомизации адресного пространства; ключи из random_u64() не делаются.
+ */
+
+#ifndef _RODNIX_CORE_RANDOM_H
+#define _RODNIX_CORE_RANDOM_H
+
+#include <stdint.h>
+#include <stdbool.h>
+
+/* ============================================================================
+ * Архитектурная часть
+ * ============================================================================ */
+
+/**
+ * Есть ли аппаратный генератор случайных чисел
+ */
+bool random_hw_available(void);
+
+/**
+ * 64 бита из аппаратного генератора
+ * @param out Результат
+ * @return true при успехе; false, если генератора нет или он не дал
+ *         значение за несколько попыток
+ */
+bool random_hw_u64(uint64_t* out);
+
+/**
+ * Текущее значение счётчика тактов CPU (x86_64: TSC).
+ * Источник джиттера: само значение предсказуемо, младшие биты — нет.
+ */
+uint64_t random_hw_cycles(void);
+
+/* ============================================================================
+ * Общий генератор
+ * ============================================================================ */
+
+/**
+ * Случайное 64-битное число. Генератор засевается при первом вызове,
+ * можно вызывать с любого IRQL.
+ */
+uint64_t random_u64(void);
+
+/**
+ * Случайное число в диапазоне [0, bound)
+ * @return 0, если bound == 0
+ */
+uint64_t random_below(uint64_t bound);
+
+#endif /* _RODNIX_CORE_RANDOM_H */
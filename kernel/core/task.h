@@ -64,6 +64,35 @@ typedef enum {
 #define TASK_MAX_FD 32
 #define TASK_CWD_MAX 256
 
+/* ============================================================================
+ * Лимиты ресурсов (номера ресурсов как у getrlimit в Linux)
+ * ============================================================================ */
+
+#define TASK_RLIMIT_CPU     0   /* процессорное время, секунды */
+#define TASK_RLIMIT_DATA    2   /* размер кучи (brk), байты */
+#define TASK_RLIMIT_STACK   3   /* резерв под стек при exec, байты */
+#define TASK_RLIMIT_NOFILE  7   /* номер дескриптора + 1 */
+#define TASK_RLIMIT_MEMLOCK 8   /* память, закреплённая mlock, байты */
+#define TASK_RLIMIT_AS      9   /* виртуальный размер, байты */
+#define TASK_RLIMIT_COUNT   16
+#define TASK_RLIM_INFINITY  UINT64_MAX
+#define TASK_DEFAULT_STACK_LIMIT (8ULL * 1024 * 1024) /* мягкий RLIMIT_STACK по умолчанию */
+
+typedef struct {
+    uint64_t cur;              /* мягкий лимит */
+    uint64_t max;              /* жёсткий лимит */
+} task_rlimit_t;
+
+/* ============================================================================
+ * Рандомизация адресного пространства (ASLR)
+ * ============================================================================ */
+
+typedef enum {
+    TASK_ASLR_DEFAULT       = 0, /* как задано rdnx.aslr */
+    TASK_ASLR_FORCE_ENABLE  = 1, /* рандомизировать, даже если rdnx.aslr=0 */
+    TASK_ASLR_FORCE_DISABLE = 2, /* фиксированная раскладка (отладка) */
+} task_aslr_ctl_t;
+
 /* ============================================================================
  * Scheduling class
  * ============================================================================ */
@@ -130,7 +159,12 @@ typedef struct task {
     } sigaction[32];
     uint32_t sig_pending;
     uint8_t sig_in_handler;
+    uint8_t oom_killed;        /* Выбрана OOM killer'ом: завершается при первой возможности */
+    task_rlimit_t rlimit[TASK_RLIMIT_COUNT]; /* Лимиты ресурсов (наследуются) */
+    uint64_t rlimit_cpu_next;  /* Секунда CPU, на которой пошлём следующий SIGXCPU */
     uint8_t abi;               /* task_abi_t */
+    uint8_t aslr_ctl;          /* task_aslr_ctl_t: действует со следующего exec (наследуется) */
+    uint8_t aslr_active;       /* Текущий образ загружен со случайной раскладкой */
     uint64_t tls_fs_base;      /* userspace FS base (arch_prctl/linux ABI) */
     struct {
         uint64_t rip;
@@ -152,6 +186,7 @@ typedef struct task {
         uint64_t r14;
         uint64_t r15;
     } sig_saved;
+    void* sig_fpu_state;       /* FPU/SIMD прерванного кода на время обработчика сигнала */
     struct thread* main_thread;/* Основной поток процесса */
     TAILQ_HEAD(thread_list, thread) threads; /* Список всех потоков задачи */
     uint32_t thread_count;     /* Количество потоков задачи */
@@ -194,12 +229,16 @@ typedef struct thread {
     TAILQ_ENTRY(thread) wait_link;  /* Узел waitq-очереди */
     TAILQ_ENTRY(thread) wait_timeout_link; /* Узел глобального timeout-list ожидания */
     struct waitq* waitq_owner;      /* Текущая waitq, если поток ожидает */
-    uint64_t wait_deadline_tick;    /* Дедлайн ожидания в тиках (0=без дедлайна) */
+    uint64_t wait_deadline_ns;      /* Дедлайн ожидания по scheduler_now_ns() (0=без дедлайна) */
     uint8_t wait_timeout_armed;     /* Поток находится в timeout-list ожидания */
     uint8_t wait_timed_out;         /* Поток разбужен по timeout waitq */
     struct thread* joiner;     /* Поток, ожидающий завершения */
     uint8_t reap_queued;       /* Флаг: поток поставлен в очередь reap */
     uint64_t reap_after_tick;  /* Тик, после которого можно освобождать стек */
+    uint8_t sched_cpu;         /* CPU, в очереди которого находится/исполнялся поток */
+    uint8_t sched_pinned;      /* 1 — поток привязан к sched_cpu (балансировка не трогает) */
+    volatile uint8_t sched_on_cpu; /* 1 — стек потока ещё используется каким-то CPU */
+    uint8_t saved_irql;        /* IRQL потока на момент переключения с него */
     void* arch_specific;       /* Архитектурно-зависимые данные */
 } thread_t;
 
@@ -243,6 +282,13 @@ void task_set_ids(task_t* task, uint32_t uid, uint32_t gid, uint32_t euid, uint3
 void task_set_abi(task_t* task, task_abi_t abi);
 task_abi_t task_get_abi(const task_t* task);
 
+/**
+ * Задача должна завершиться (выбрана OOM killer'ом): ожидания в ядре
+ * прерываются, чтобы она дошла до выхода на границе syscall
+ * @param task Задача (NULL — не завершается)
+ */
+bool task_kill_pending(const task_t* task);
+
 /* ============================================================================
  * File descriptors helpers
  * ============================================================================ */
@@ -265,6 +311,12 @@ void* task_fd_get(task_t* task, int fd);
  */
 int task_fd_close(task_t* task, int fd);
 
+/**
+ * First fd number a task may not allocate (RLIMIT_NOFILE, capped by TASK_MAX_FD)
+ * @return fd limit
+ */
+int task_fd_limit(const task_t* task);
+
 /**
  * Получение эффективного UID
  * @param task Указатель на задачу
@@ -293,6 +345,22 @@ uint32_t task_get_thread_count(const task_t* task);
  */
 task_t* task_find_by_id(uint64_t task_id);
 
+/**
+ * Walk all tasks without taking the registry lock (kernel debugger only:
+ * the rest of the system must be stopped).
+ * @param prev NULL for the first task
+ * @return Next task or NULL at the end
+ */
+task_t* task_debug_next(task_t* prev);
+
+/**
+ * Walk all tasks; the caller holds IRQL_HIGH (the task registry lock)
+ * for the whole walk.
+ * @param prev NULL for the first task
+ * @return Next task or NULL at the end
+ */
+task_t* task_next_locked(task_t* prev);
+
 typedef struct {
     uint32_t cache_count;
     uint32_t cache_capacity;
@@ -2,14 +2,23 @@
  * @file pci.c
  * @brief PCI bus implementation
  * 
- * Minimal PCI enumeration through config space.
+ * PCI enumeration through config space: legacy 0xCF8/0xCFC cycles for the
+ * first 256 bytes, or ECAM (ACPI MCFG) for the full 4 KiB PCIe space.
+ * BARs are sized at enumeration and recorded as Fabric resources, so
+ * drivers ask fabric_resource_map() for a mapped BAR. Also capability list
+ * parsing and MSI/MSI-X vector allocation for drivers.
  */
 
 #include "pci.h"
 #include "bus.h"
 #include "../fabric.h"
+#include "../spin.h"
 #include "../device/device.h"
 #include "../../include/console.h"
+#include "../../../include/common.h"
+#include "../../../include/error.h"
+#include "../../core/interrupts.h"
+#include "../../arch/acpi.h"
 #include <stddef.h>
 #include <stdint.h>
 
@@ -17,19 +26,43 @@
 #define PCI_CONFIG_ADDRESS  0xCF8
 #define PCI_CONFIG_DATA     0xCFC
 
+#define PCI_CFG_LEGACY_SIZE 0x100u
+#define PCI_CFG_ECAM_SIZE   0x1000u
+
+/*
+ * LOCKING: pci_cfg_lock
+ *   Protects: the 0xCF8/0xCFC address/data pair. ECAM accesses are single
+ *   MMIO loads/stores and need no lock.
+ */
+static spinlock_t pci_cfg_lock;
+
+/* ECAM window of segment 0 from ACPI MCFG, base corresponds to bus 0 */
+static uint64_t pci_ecam_base = 0;
+static uint8_t pci_ecam_bus_start = 0;
+static uint8_t pci_ecam_bus_end = 0;
+static bool pci_ecam_ready = false;
+
+static inline uint32_t pci_legacy_address(uint8_t bus, uint8_t device, uint8_t function,
+                                          uint8_t offset)
+{
+    return (1UL << 31) |
+           ((uint32_t)bus << 16) |
+           ((uint32_t)device << 11) |
+           ((uint32_t)function << 8) |
+           (offset & 0xFC);
+}
+
 /* Read 32-bit value from PCI config space */
 static uint32_t pci_read_config(uint8_t bus, uint8_t device, uint8_t function, uint8_t offset)
 {
-    uint32_t address = (1UL << 31) | 
-                       ((uint32_t)bus << 16) | 
-                       ((uint32_t)device << 11) | 
-                       ((uint32_t)function << 8) | 
-                       (offset & 0xFC);
-    
-    __asm__ volatile ("outl %%eax, %0" : : "Nd"((uint16_t)PCI_CONFIG_ADDRESS), "a"(address));
+    uint32_t address = pci_legacy_address(bus, device, function, offset);
     uint32_t value;
+
+    spinlock_lock(&pci_cfg_lock);
+    __asm__ volatile ("outl %%eax, %0" : : "Nd"((uint16_t)PCI_CONFIG_ADDRESS), "a"(address));
     __asm__ volatile ("inl %1, %%eax" : "=a"(value) : "Nd"((uint16_t)PCI_CONFIG_DATA));
-    
+    spinlock_unlock(&pci_cfg_lock);
+
     return value;
 }
 
@@ -47,6 +80,126 @@ static uint8_t pci_read_config8(uint8_t bus, uint8_t device, uint8_t function, u
     return (uint8_t)((v >> shift) & 0xFFu);
 }
 
+/* Write 32-bit value to PCI config space */
+static void pci_write_config(uint8_t bus, uint8_t device, uint8_t function, uint8_t offset,
+                             uint32_t value)
+{
+    uint32_t address = pci_legacy_address(bus, device, function, offset);
+
+    spinlock_lock(&pci_cfg_lock);
+    __asm__ volatile ("outl %%eax, %0" : : "Nd"((uint16_t)PCI_CONFIG_ADDRESS), "a"(address));
+    __asm__ volatile ("outl %%eax, %0" : : "Nd"((uint16_t)PCI_CONFIG_DATA), "a"(value));
+    spinlock_unlock(&pci_cfg_lock);
+}
+
+/* ============================================================================
+ * ECAM (PCIe enhanced configuration access)
+ * ============================================================================ */
+
+static void pci_ecam_init(void)
+{
+    struct acpi_mcfg_allocation alloc;
+    for (uint32_t i = 0; acpi_mcfg_get(i, &alloc) == 0; i++) {
+        if (alloc.segment != 0 || alloc.base_address == 0 ||
+            alloc.end_bus < alloc.start_bus) {
+            continue;
+        }
+        pci_ecam_base = alloc.base_address;
+        pci_ecam_bus_start = alloc.start_bus;
+        pci_ecam_bus_end = alloc.end_bus;
+        pci_ecam_ready = true;
+        kprintf("[PCI] ecam base=%llx buses=%u-%u\n",
+                (unsigned long long)pci_ecam_base,
+                (unsigned)pci_ecam_bus_start,
+                (unsigned)pci_ecam_bus_end);
+        return;
+    }
+    kputs("[PCI] no MCFG, legacy config cycles only\n");
+}
+
+/* Map the 4 KiB config page of a function; only present functions are mapped */
+static volatile uint8_t* pci_ecam_map(uint8_t bus, uint8_t device, uint8_t function)
+{
+    if (!pci_ecam_ready || bus < pci_ecam_bus_start || bus > pci_ecam_bus_end) {
+        return NULL;
+    }
+    uint64_t phys = pci_ecam_base +
+                    ((uint64_t)bus << 20) +
+                    ((uint64_t)device << 15) +
+                    ((uint64_t)function << 12);
+    return (volatile uint8_t*)fabric_mmio_map(phys, PCI_CFG_ECAM_SIZE);
+}
+
+uint32_t pci_cfg_read(const pci_device_info_t* info, uint16_t reg, uint32_t width)
+{
+    uint32_t ones = (width == 1u) ? 0xFFu : (width == 2u) ? 0xFFFFu : 0xFFFFFFFFu;
+    if (!info || (reg & (width - 1u)) != 0) {
+        return ones;
+    }
+
+    if (info->ecam) {
+        if (reg >= PCI_CFG_ECAM_SIZE) {
+            return ones;
+        }
+        volatile uint8_t* p = info->ecam + reg;
+        if (width == 1u) {
+            return *p;
+        }
+        if (width == 2u) {
+            return *(volatile uint16_t*)p;
+        }
+        return *(volatile uint32_t*)p;
+    }
+
+    if (reg >= PCI_CFG_LEGACY_SIZE) {
+        return ones;
+    }
+    uint32_t v = pci_read_config(info->bus, info->device, info->function, (uint8_t)reg);
+    return (v >> ((reg & 0x3u) * 8u)) & ones;
+}
+
+void pci_cfg_write(const pci_device_info_t* info, uint16_t reg, uint32_t value, uint32_t width)
+{
+    if (!info || (reg & (width - 1u)) != 0) {
+        return;
+    }
+
+    if (info->ecam) {
+        if (reg >= PCI_CFG_ECAM_SIZE) {
+            return;
+        }
+        volatile uint8_t* p = info->ecam + reg;
+        if (width == 1u) {
+            *p = (uint8_t)value;
+        } else if (width == 2u) {
+            *(volatile uint16_t*)p = (uint16_t)value;
+        } else {
+            *(volatile uint32_t*)p = value;
+        }
+        return;
+    }
+
+    if (reg >= PCI_CFG_LEGACY_SIZE) {
+        return;
+    }
+    uint8_t off = (uint8_t)(reg & 0xFCu);
+    if (width == 4u) {
+        pci_write_config(info->bus, info->device, info->function, off, value);
+        return;
+    }
+    /* Sub-dword writes are read-modify-write under one lock hold */
+    uint32_t mask = (width == 1u) ? 0xFFu : 0xFFFFu;
+    uint32_t shift = (reg & 0x3u) * 8u;
+    uint32_t address = pci_legacy_address(info->bus, info->device, info->function, off);
+    uint32_t cur;
+    spinlock_lock(&pci_cfg_lock);
+    __asm__ volatile ("outl %%eax, %0" : : "Nd"((uint16_t)PCI_CONFIG_ADDRESS), "a"(address));
+    __asm__ volatile ("inl %1, %%eax" : "=a"(cur) : "Nd"((uint16_t)PCI_CONFIG_DATA));
+    cur = (cur & ~(mask << shift)) | ((value & mask) << shift);
+    __asm__ volatile ("outl %%eax, %0" : : "Nd"((uint16_t)PCI_CONFIG_DATA), "a"(cur));
+    spinlock_unlock(&pci_cfg_lock);
+}
+
 /* Read vendor ID and device ID */
 static uint16_t pci_read_vendor(uint8_t bus, uint8_t device, uint8_t function)
 {
@@ -71,6 +224,419 @@ static bool pci_device_exists(uint8_t bus, uint8_t device, uint8_t function)
     return vendor != 0xFFFF && vendor != 0x0000;
 }
 
+/* ============================================================================
+ * Capabilities
+ * ============================================================================ */
+
+#define PCI_REG_COMMAND         0x04u
+#define PCI_REG_STATUS          0x06u
+#define PCI_REG_CAP_PTR         0x34u
+#define PCI_STATUS_CAP_LIST     (1u << 4)
+#define PCI_COMMAND_MEMORY      (1u << 1)
+#define PCI_COMMAND_MASTER      (1u << 2)
+#define PCI_COMMAND_INTX_OFF    (1u << 10)
+
+/* 48 capabilities fit into the 192 bytes after the header */
+#define PCI_CAP_WALK_LIMIT      48u
+
+/* MSI capability */
+#define PCI_MSI_CTRL            0x02u
+#define PCI_MSI_ADDR_LO         0x04u
+#define PCI_MSI_ADDR_HI         0x08u
+#define PCI_MSI_DATA_32         0x08u
+#define PCI_MSI_DATA_64         0x0Cu
+#define PCI_MSI_CTRL_ENABLE     (1u << 0)
+#define PCI_MSI_CTRL_MMC_SHIFT  1
+#define PCI_MSI_CTRL_MME_SHIFT  4
+#define PCI_MSI_CTRL_MME_MASK   (7u << PCI_MSI_CTRL_MME_SHIFT)
+#define PCI_MSI_CTRL_64BIT      (1u << 7)
+
+/* MSI-X capability and table */
+#define PCI_MSIX_CTRL           0x02u
+#define PCI_MSIX_TABLE          0x04u
+#define PCI_MSIX_CTRL_SIZE_MASK 0x07FFu
+#define PCI_MSIX_CTRL_MASKALL   (1u << 14)
+#define PCI_MSIX_CTRL_ENABLE    (1u << 15)
+#define PCI_MSIX_BIR_MASK       0x7u
+#define PCI_MSIX_ENTRY_SIZE     16u
+#define PCI_MSIX_ENTRY_ADDR_LO  0u
+#define PCI_MSIX_ENTRY_ADDR_HI  1u
+#define PCI_MSIX_ENTRY_DATA     2u
+#define PCI_MSIX_ENTRY_CTRL     3u
+#define PCI_MSIX_ENTRY_MASKED   (1u << 0)
+
+uint8_t pci_find_capability(const pci_device_info_t* info, uint8_t cap_id)
+{
+    if (!info) {
+        return 0;
+    }
+    uint16_t status = (uint16_t)pci_cfg_read(info, PCI_REG_STATUS, 2);
+    if (!(status & PCI_STATUS_CAP_LIST)) {
+        return 0;
+    }
+
+    uint8_t ptr = (uint8_t)(pci_cfg_read(info, PCI_REG_CAP_PTR, 1) & 0xFCu);
+    for (uint32_t guard = 0; ptr >= 0x40u && guard < PCI_CAP_WALK_LIMIT; guard++) {
+        uint16_t hdr = (uint16_t)pci_cfg_read(info, ptr, 2);
+        if ((hdr & 0xFFu) == cap_id) {
+            return ptr;
+        }
+        ptr = (uint8_t)((hdr >> 8) & 0xFCu);
+    }
+    return 0;
+}
+
+static pci_device_info_t* pci_dev_info(fabric_device_t* dev)
+{
+    if (!dev || !dev->name || !dev->bus_private || strcmp(dev->name, "pci-device") != 0) {
+        return NULL;
+    }
+    return (pci_device_info_t*)dev->bus_private;
+}
+
+static void pci_set_intx(pci_device_info_t* info, bool enable)
+{
+    uint16_t cmd = (uint16_t)pci_cfg_read(info, PCI_REG_COMMAND, 2);
+    if (enable) {
+        cmd &= (uint16_t)~PCI_COMMAND_INTX_OFF;
+    } else {
+        cmd |= PCI_COMMAND_INTX_OFF;
+    }
+    /* Messages are memory writes issued by the device */
+    cmd |= PCI_COMMAND_MASTER;
+    pci_cfg_write(info, PCI_REG_COMMAND, cmd, 2);
+    info->command = cmd;
+}
+
+static int pci_msi_enable(pci_device_info_t* info, uint32_t min, uint32_t max)
+{
+    uint8_t cap = info->cap_msi;
+    uint16_t ctrl = (uint16_t)pci_cfg_read(info, cap + PCI_MSI_CTRL, 2);
+    uint32_t supported = 1u << ((ctrl >> PCI_MSI_CTRL_MMC_SHIFT) & 0x7u);
+    if (supported > PCI_IRQ_MAX_VECTORS) {
+        supported = PCI_IRQ_MAX_VECTORS;
+    }
+
+    /* MSI only allocates power-of-two blocks */
+    uint32_t count = 1;
+    while (count * 2u <= max && count * 2u <= supported) {
+        count *= 2u;
+    }
+    if (count < min) {
+        return RDNX_E_UNSUPPORTED;
+    }
+
+    int first = interrupt_alloc_vectors(count, count);
+    if (first < 0) {
+        return RDNX_E_BUSY;
+    }
+    uint64_t addr = 0;
+    uint32_t data = 0;
+    if (interrupt_msi_message((uint32_t)first, 0, &addr, &data) != 0) {
+        interrupt_free_vectors((uint32_t)first, count);
+        return RDNX_E_UNSUPPORTED;
+    }
+
+    ctrl &= (uint16_t)~(PCI_MSI_CTRL_ENABLE | PCI_MSI_CTRL_MME_MASK);
+    pci_cfg_write(info, cap + PCI_MSI_CTRL, ctrl, 2);
+
+    pci_cfg_write(info, cap + PCI_MSI_ADDR_LO, (uint32_t)addr, 4);
+    if (ctrl & PCI_MSI_CTRL_64BIT) {
+        pci_cfg_write(info, cap + PCI_MSI_ADDR_HI, (uint32_t)(addr >> 32), 4);
+        pci_cfg_write(info, cap + PCI_MSI_DATA_64, (uint16_t)data, 2);
+    } else {
+        pci_cfg_write(info, cap + PCI_MSI_DATA_32, (uint16_t)data, 2);
+    }
+
+    /* log2(count) goes into Multiple Message Enable */
+    uint16_t mme = 0;
+    while ((1u << mme) < count) {
+        mme++;
+    }
+    ctrl |= (uint16_t)(mme << PCI_MSI_CTRL_MME_SHIFT);
+    ctrl |= PCI_MSI_CTRL_ENABLE;
+    pci_cfg_write(info, cap + PCI_MSI_CTRL, ctrl, 2);
+
+    info->irq_mode = PCI_IRQ_MODE_MSI;
+    info->irq_count = (uint8_t)count;
+    for (uint32_t i = 0; i < count; i++) {
+        info->irq_vectors[i] = (uint8_t)(first + (int)i);
+    }
+    return (int)count;
+}
+
+static int pci_msix_enable(fabric_device_t* dev, pci_device_info_t* info,
+                           uint32_t min, uint32_t max)
+{
+    uint8_t cap = info->cap_msix;
+    uint16_t ctrl = (uint16_t)pci_cfg_read(info, cap + PCI_MSIX_CTRL, 2);
+    uint32_t table_size = (uint32_t)(ctrl & PCI_MSIX_CTRL_SIZE_MASK) + 1u;
+
+    uint32_t count = max;
+    if (count > table_size) {
+        count = table_size;
+    }
+    if (count > PCI_IRQ_MAX_VECTORS) {
+        count = PCI_IRQ_MAX_VECTORS;
+    }
+    if (count < min) {
+        return RDNX_E_UNSUPPORTED;
+    }
+
+    uint32_t table_reg = pci_cfg_read(info, cap + PCI_MSIX_TABLE, 4);
+    uint32_t table_off = table_reg & ~PCI_MSIX_BIR_MASK;
+    uint64_t bar_size = 0;
+    uint8_t* bar = (uint8_t*)fabric_resource_map(dev, table_reg & PCI_MSIX_BIR_MASK, &bar_size);
+    if (!bar || (uint64_t)table_off + count * PCI_MSIX_ENTRY_SIZE > bar_size) {
+        return RDNX_E_UNSUPPORTED;
+    }
+    volatile uint32_t* table = (volatile uint32_t*)(bar + table_off);
+    info->msix_table = table;
+
+    /* MSI-X vectors need not be contiguous, but one block keeps bookkeeping simple */
+    int first = interrupt_alloc_vectors(count, 1);
+    if (first < 0) {
+        return RDNX_E_BUSY;
+    }
+
+    /* Function mask while the table is rewritten */
+    ctrl |= PCI_MSIX_CTRL_ENABLE | PCI_MSIX_CTRL_MASKALL;
+    pci_cfg_write(info, cap + PCI_MSIX_CTRL, ctrl, 2);
+    uint16_t cmd = (uint16_t)pci_cfg_read(info, PCI_REG_COMMAND, 2);
+    pci_cfg_write(info, PCI_REG_COMMAND, (uint16_t)(cmd | PCI_COMMAND_MEMORY), 2);
+
+    for (uint32_t i = 0; i < count; i++) {
+        uint64_t addr = 0;
+        uint32_t data = 0;
+        if (interrupt_msi_message((uint32_t)first + i, 0, &addr, &data) != 0) {
+            interrupt_free_vectors((uint32_t)first, count);
+            ctrl &= (uint16_t)~(PCI_MSIX_CTRL_ENABLE | PCI_MSIX_CTRL_MASKALL);
+            pci_cfg_write(info, cap + PCI_MSIX_CTRL, ctrl, 2);
+            return RDNX_E_UNSUPPORTED;
+        }
+        volatile uint32_t* entry = table + i * (PCI_MSIX_ENTRY_SIZE / 4u);
+        entry[PCI_MSIX_ENTRY_CTRL] = PCI_MSIX_ENTRY_MASKED;
+        entry[PCI_MSIX_ENTRY_ADDR_LO] = (uint32_t)addr;
+        entry[PCI_MSIX_ENTRY_ADDR_HI] = (uint32_t)(addr >> 32);
+        entry[PCI_MSIX_ENTRY_DATA] = data;
+        entry[PCI_MSIX_ENTRY_CTRL] = 0;
+        info->irq_vectors[i] = (uint8_t)(first + (int)i);
+    }
+
+    ctrl &= (uint16_t)~PCI_MSIX_CTRL_MASKALL;
+    pci_cfg_write(info, cap + PCI_MSIX_CTRL, ctrl, 2);
+
+    info->irq_mode = PCI_IRQ_MODE_MSIX;
+    info->irq_count = (uint8_t)count;
+    return (int)count;
+}
+
+/*
+ * LOCKING: called from driver attach/detach for the device it owns;
+ * per-device state needs no lock, vectors are allocated under the IDT
+ * allocator lock.
+ */
+int pci_alloc_irq_vectors(fabric_device_t* dev, uint32_t min, uint32_t max, uint32_t flags)
+{
+    pci_device_info_t* info = pci_dev_info(dev);
+    if (!info || min == 0 || max < min || !(flags & PCI_IRQ_F_ALL)) {
+        return RDNX_E_INVALID;
+    }
+    if (info->irq_mode != PCI_IRQ_MODE_NONE) {
+        return RDNX_E_BUSY;
+    }
+    if (max > PCI_IRQ_MAX_VECTORS) {
+        max = PCI_IRQ_MAX_VECTORS;
+    }
+    if (min > max) {
+        return RDNX_E_UNSUPPORTED;
+    }
+
+    int ret = RDNX_E_UNSUPPORTED;
+    if ((flags & PCI_IRQ_F_MSIX) && info->cap_msix) {
+        ret = pci_msix_enable(dev, info, min, max);
+    }
+    if (ret < 0 && (flags & PCI_IRQ_F_MSI) && info->cap_msi) {
+        ret = pci_msi_enable(info, min, max);
+    }
+    if (ret < 0) {
+        return ret;
+    }
+
+    pci_set_intx(info, false);
+    kprintf("[PCI] bdf=%u:%u.%u %s vectors=%u first=%u\n",
+            (unsigned)info->bus,
+            (unsigned)info->device,
+            (unsigned)info->function,
+            info->irq_mode == PCI_IRQ_MODE_MSIX ? "msi-x" : "msi",
+            (unsigned)info->irq_count,
+            (unsigned)info->irq_vectors[0]);
+    return ret;
+}
+
+int pci_irq_vector(fabric_device_t* dev, uint32_t index)
+{
+    pci_device_info_t* info = pci_dev_info(dev);
+    if (!info || info->irq_mode == PCI_IRQ_MODE_NONE || index >= info->irq_count) {
+        return RDNX_E_INVALID;
+    }
+    return (int)info->irq_vectors[index];
+}
+
+void pci_free_irq_vectors(fabric_device_t* dev)
+{
+    pci_device_info_t* info = pci_dev_info(dev);
+    if (!info || info->irq_mode == PCI_IRQ_MODE_NONE) {
+        return;
+    }
+
+    if (info->irq_mode == PCI_IRQ_MODE_MSIX) {
+        uint8_t cap = info->cap_msix;
+        for (uint32_t i = 0; i < info->irq_count && info->msix_table; i++) {
+            info->msix_table[i * (PCI_MSIX_ENTRY_SIZE / 4u) + PCI_MSIX_ENTRY_CTRL] =
+                PCI_MSIX_ENTRY_MASKED;
+        }
+        uint16_t ctrl = (uint16_t)pci_cfg_read(info, cap + PCI_MSIX_CTRL, 2);
+        ctrl &= (uint16_t)~(PCI_MSIX_CTRL_ENABLE | PCI_MSIX_CTRL_MASKALL);
+        pci_cfg_write(info, cap + PCI_MSIX_CTRL, ctrl, 2);
+    } else {
+        uint8_t cap = info->cap_msi;
+        uint16_t ctrl = (uint16_t)pci_cfg_read(info, cap + PCI_MSI_CTRL, 2);
+        ctrl &= (uint16_t)~(PCI_MSI_CTRL_ENABLE | PCI_MSI_CTRL_MME_MASK);
+        pci_cfg_write(info, cap + PCI_MSI_CTRL, ctrl, 2);
+    }
+
+    /* MSI-X vectors were allocated as one block as well */
+    interrupt_free_vectors(info->irq_vectors[0], info->irq_count);
+    info->irq_mode = PCI_IRQ_MODE_NONE;
+    info->irq_count = 0;
+    pci_set_intx(info, true);
+}
+
+/* ============================================================================
+ * BAR sizing
+ * ============================================================================ */
+
+#define PCI_REG_BAR0            0x10u
+#define PCI_COMMAND_IO          (1u << 0)
+#define PCI_BAR_IO              (1u << 0)
+#define PCI_BAR_MEM_TYPE_SHIFT  1
+#define PCI_BAR_MEM_TYPE_64     0x2u
+#define PCI_BAR_MEM_PREFETCH    (1u << 3)
+
+/* Write all-ones, read the decoded mask back, restore the original value */
+static uint32_t pci_bar_probe(pci_device_info_t* info, uint16_t reg, uint32_t* orig)
+{
+    *orig = pci_cfg_read(info, reg, 4);
+    pci_cfg_write(info, reg, 0xFFFFFFFFu, 4);
+    uint32_t mask = pci_cfg_read(info, reg, 4);
+    pci_cfg_write(info, reg, *orig, 4);
+    return mask;
+}
+
+typedef struct {
+    uint32_t type;
+    uint32_t flags;
+    uint64_t start;
+    uint64_t size;
+} pci_bar_decode_t;
+
+/*
+ * Size every BAR of @p info and record it as Fabric resource with index =
+ * BAR number. Decoding is switched off while all-ones are written so the
+ * device does not claim a bogus window in the meantime; nothing is logged
+ * until it is back on (the console may sit behind this very device).
+ */
+static void pci_size_bars(fabric_device_t* dev, pci_device_info_t* info)
+{
+    uint32_t count;
+    switch (info->header_type & 0x7Fu) {
+    case 0x00u:
+        count = PCI_BAR_COUNT;
+        break;
+    case 0x01u:
+        count = 2u;     /* PCI-to-PCI bridge */
+        break;
+    default:
+        return;
+    }
+
+    pci_bar_decode_t bars[PCI_BAR_COUNT];
+    for (uint32_t bar = 0; bar < PCI_BAR_COUNT; bar++) {
+        bars[bar].type = FABRIC_RES_NONE;
+    }
+
+    uint16_t cmd = (uint16_t)pci_cfg_read(info, PCI_REG_COMMAND, 2);
+    pci_cfg_write(info, PCI_REG_COMMAND,
+                  (uint16_t)(cmd & ~(PCI_COMMAND_IO | PCI_COMMAND_MEMORY)), 2);
+
+    for (uint32_t bar = 0; bar < count; bar++) {
+        uint16_t reg = (uint16_t)(PCI_REG_BAR0 + bar * 4u);
+        uint32_t lo = 0;
+        uint32_t lo_mask = pci_bar_probe(info, reg, &lo);
+        if (lo_mask == 0) {
+            continue;   /* Not implemented */
+        }
+
+        uint32_t type;
+        uint32_t flags = 0;
+        uint64_t start;
+        uint64_t mask;
+        bool wide = false;
+        if (lo & PCI_BAR_IO) {
+            type = FABRIC_RES_IO;
+            start = lo & ~0x3u;
+            /* 16-bit I/O decoders leave the upper half zero */
+            mask = 0xFFFFFFFFFFFF0000ULL | (lo_mask & ~0x3u);
+        } else {
+            type = FABRIC_RES_MEM;
+            start = lo & ~0xFu;
+            mask = 0xFFFFFFFF00000000ULL | (lo_mask & ~0xFu);
+            if (lo & PCI_BAR_MEM_PREFETCH) {
+                flags |= FABRIC_RES_F_PREFETCH;
+            }
+            if (((lo >> PCI_BAR_MEM_TYPE_SHIFT) & 0x3u) == PCI_BAR_MEM_TYPE_64 &&
+                bar + 1u < count) {
+                uint32_t hi = 0;
+                uint32_t hi_mask = pci_bar_probe(info, (uint16_t)(reg + 4u), &hi);
+                start |= (uint64_t)hi << 32;
+                mask = ((uint64_t)hi_mask << 32) | (lo_mask & ~0xFu);
+                flags |= FABRIC_RES_F_64BIT;
+                wide = true;
+            }
+        }
+
+        uint64_t size = ~mask + 1u;
+        if (size != 0 && start != 0) {
+            bars[bar].type = type;
+            bars[bar].flags = flags;
+            bars[bar].start = start;
+            bars[bar].size = size;
+        }
+        if (wide) {
+            bar++;      /* Upper half of a 64-bit BAR */
+        }
+    }
+
+    pci_cfg_write(info, PCI_REG_COMMAND, cmd, 2);
+
+    for (uint32_t bar = 0; bar < count; bar++) {
+        const pci_bar_decode_t* b = &bars[bar];
+        if (b->type == FABRIC_RES_NONE) {
+            continue;
+        }
+        int rc = fabric_resource_add(dev, b->type, bar, b->start, b->size, b->flags);
+        kprintf("[PCI]   bar%u %s%s%s base=%llx size=%llx%s\n",
+                (unsigned)bar,
+                b->type == FABRIC_RES_IO ? "io" : "mem",
+                (b->flags & FABRIC_RES_F_64BIT) ? "64" : (b->type == FABRIC_RES_MEM ? "32" : ""),
+                (b->flags & FABRIC_RES_F_PREFETCH) ? " pref" : "",
+                (unsigned long long)b->start,
+                (unsigned long long)b->size,
+                rc == RDNX_OK ? "" : " (conflict)");
+    }
+}
+
 /* Enumerate PCI bus */
 static void pci_enumerate(void)
 {
@@ -78,6 +644,8 @@ static void pci_enumerate(void)
     static pci_device_info_t pci_info[256];
     static uint32_t pci_device_count = 0;
 
+    pci_ecam_init();
+
     /* Enumerate bus 0, devices 0-31, functions 0-7 */
     for (uint8_t device = 0; device < 32; device++) {
         if (!pci_device_exists(0, device, 0)) {
@@ -123,6 +691,13 @@ static void pci_enumerate(void)
                     0, device, function, (uint8_t)(0x10u + (bar * 4u))
                 );
             }
+            info->ecam = pci_ecam_map(0, device, function);
+            info->cap_msi = pci_find_capability(info, PCI_CAP_ID_MSI);
+            info->cap_msix = pci_find_capability(info, PCI_CAP_ID_MSIX);
+            info->cap_pcie = pci_find_capability(info, PCI_CAP_ID_PCIE);
+            info->irq_mode = PCI_IRQ_MODE_NONE;
+            info->irq_count = 0;
+            info->msix_table = NULL;
             
             /* Fill device structure */
             dev->name = "pci-device";
@@ -144,6 +719,13 @@ static void pci_enumerate(void)
                     (unsigned)subclass,
                     (unsigned)prog_if,
                     (unsigned)info->bars[0]);
+            if (info->cap_msi || info->cap_msix || info->cap_pcie) {
+                kprintf("[PCI]   caps msi=%x msix=%x pcie=%x\n",
+                        (unsigned)info->cap_msi,
+                        (unsigned)info->cap_msix,
+                        (unsigned)info->cap_pcie);
+            }
+            pci_size_bars(dev, info);
             
             /* Publish device */
             fabric_device_publish(dev);
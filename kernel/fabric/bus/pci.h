@@ -10,6 +10,25 @@
 
 #define PCI_BAR_COUNT 6u
 
+/* Capability IDs */
+#define PCI_CAP_ID_MSI      0x05u
+#define PCI_CAP_ID_PCIE     0x10u
+#define PCI_CAP_ID_MSIX     0x11u
+
+/* pci_alloc_irq_vectors() flags */
+#define PCI_IRQ_F_MSI       (1u << 0)
+#define PCI_IRQ_F_MSIX      (1u << 1)
+#define PCI_IRQ_F_ALL       (PCI_IRQ_F_MSI | PCI_IRQ_F_MSIX)
+
+/* Max vectors per device (MSI multi-message limit) */
+#define PCI_IRQ_MAX_VECTORS 32u
+
+typedef enum {
+    PCI_IRQ_MODE_NONE = 0,  /* INTx or nothing allocated */
+    PCI_IRQ_MODE_MSI,
+    PCI_IRQ_MODE_MSIX
+} pci_irq_mode_t;
+
 typedef struct pci_device_info {
     uint8_t bus;
     uint8_t device;
@@ -19,8 +38,57 @@ typedef struct pci_device_info {
     uint16_t command;
     uint16_t status;
     uint32_t bars[PCI_BAR_COUNT];
+    /* Capability offsets in config space, 0 if absent */
+    uint8_t cap_msi;
+    uint8_t cap_msix;
+    uint8_t cap_pcie;
+    /* Message-signalled interrupts, see pci_alloc_irq_vectors() */
+    pci_irq_mode_t irq_mode;
+    uint8_t irq_count;
+    uint8_t irq_vectors[PCI_IRQ_MAX_VECTORS];
+    volatile uint32_t* msix_table;
+    /* ECAM mapping of the 4 KiB config space, NULL: legacy 256-byte cycles */
+    volatile uint8_t* ecam;
 } pci_device_info_t;
 
+struct fabric_device;
+
 void pci_bus_init(void);
 
+/**
+ * Config-space access of width 1, 2 or 4 bytes at a naturally aligned
+ * @p reg. Offsets 0x100-0xFFF (PCIe extended space) need ECAM; without it
+ * reads return all-ones and writes are dropped.
+ */
+uint32_t pci_cfg_read(const pci_device_info_t* info, uint16_t reg, uint32_t width);
+void pci_cfg_write(const pci_device_info_t* info, uint16_t reg, uint32_t value, uint32_t width);
+
+/**
+ * Find a capability in the standard capability list
+ * @return Config-space offset, or 0 if the device does not have it
+ */
+uint8_t pci_find_capability(const pci_device_info_t* info, uint8_t cap_id);
+
+/**
+ * Allocate between @p min and @p max MSI-X or MSI vectors for @p dev.
+ * MSI-X is tried first when PCI_IRQ_F_MSIX is set. Vectors come from the
+ * dynamic IDT range; handlers are installed with fabric_request_irq() on
+ * pci_irq_vector(dev, i). INTx is disabled while vectors are allocated.
+ * @return Number of vectors allocated, or RDNX_E_* on failure
+ */
+int pci_alloc_irq_vectors(struct fabric_device* dev, uint32_t min,
+                          uint32_t max, uint32_t flags);
+
+/**
+ * Interrupt vector for message @p index of @p dev
+ * @return Vector, or RDNX_E_INVALID if @p index was not allocated
+ */
+int pci_irq_vector(struct fabric_device* dev, uint32_t index);
+
+/**
+ * Disable MSI/MSI-X on @p dev and return its vectors. Handlers must be
+ * removed with fabric_free_irq() beforehand.
+ */
+void pci_free_irq_vectors(struct fabric_device* dev);
+
 #endif /* _RODNIX_FABRIC_BUS_PCI_H */
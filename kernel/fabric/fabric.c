@@ -17,6 +17,7 @@
 #include "../../include/common.h"
 #include "../../core/interrupts.h"
 #include "../../include/error.h"
+#include "../common/bootparam.h"
 #include <stddef.h>
 #include <stdarg.h>
 
@@ -29,6 +30,8 @@
 #define MAX_EVENT_LISTENERS 16
 #define MAX_EVENT_QUEUE 128
 
+BOOTPARAM_STRING(fabric_nodrv, "rdnx.nodrv", "", "Comma-separated drivers not to register");
+
 /* Registries */
 static fabric_bus_t* bus_registry[MAX_BUSES];
 static fabric_driver_t* driver_registry[MAX_DRIVERS];
@@ -468,13 +471,8 @@ uint32_t fabric_node_count(void)
     return count;
 }
 
-int fabric_node_get_info(uint32_t index, fabric_node_info_t* out)
+static int fabric_node_copy(uint32_t index, fabric_node_info_t* out)
 {
-    if (!out) {
-        return RDNX_E_INVALID;
-    }
-    memset(out, 0, sizeof(*out));
-    spinlock_lock(&fabric_lock);
     uint32_t seen = 0;
     for (uint32_t i = 0; i < MAX_NODES; i++) {
         if (!node_registry[i].used) {
@@ -490,15 +488,34 @@ int fabric_node_get_info(uint32_t index, fabric_node_info_t* out)
             memcpy(out->driver, node_registry[i].driver, sizeof(out->driver));
             out->state = node_registry[i].state;
             out->flags = node_registry[i].flags;
-            spinlock_unlock(&fabric_lock);
             return RDNX_OK;
         }
         seen++;
     }
-    spinlock_unlock(&fabric_lock);
     return RDNX_E_NOTFOUND;
 }
 
+int fabric_node_get_info(uint32_t index, fabric_node_info_t* out)
+{
+    if (!out) {
+        return RDNX_E_INVALID;
+    }
+    memset(out, 0, sizeof(*out));
+    spinlock_lock(&fabric_lock);
+    int rc = fabric_node_copy(index, out);
+    spinlock_unlock(&fabric_lock);
+    return rc;
+}
+
+int fabric_node_get_info_nolock(uint32_t index, fabric_node_info_t* out)
+{
+    if (!out) {
+        return RDNX_E_INVALID;
+    }
+    memset(out, 0, sizeof(*out));
+    return fabric_node_copy(index, out);
+}
+
 int fabric_node_list(fabric_node_info_t* out, uint32_t max_entries, uint32_t* out_total)
 {
     if (!out || max_entries == 0) {
@@ -656,6 +673,11 @@ int fabric_driver_register(fabric_driver_t *driver)
     if (!driver || !driver->name) {
         return -1;
     }
+
+    if (bootparam_list_has(fabric_nodrv, driver->name)) {
+        fabric_log("[fabric] driver %s disabled by rdnx.nodrv\n", driver->name);
+        return RDNX_E_DENIED;
+    }
     
     spinlock_lock(&fabric_lock);
     
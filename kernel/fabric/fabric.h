@@ -62,6 +62,8 @@ typedef struct fabric_node_info {
 
 uint32_t fabric_node_count(void);
 int fabric_node_get_info(uint32_t index, fabric_node_info_t* out);
+/* Same without fabric_lock: kernel debugger only, the system is stopped */
+int fabric_node_get_info_nolock(uint32_t index, fabric_node_info_t* out);
 int fabric_node_list(fabric_node_info_t* out, uint32_t max_entries, uint32_t* out_total);
 int fabric_node_set_state(const char* path, uint32_t state);
 int fabric_publish_service_node(const char* service_name, const char* kind, fabric_device_t* provider_dev);
@@ -106,7 +108,7 @@ int fabric_event_drain(fabric_event_t* out, uint32_t max_entries, uint32_t* out_
 /* Bus registration */
 int fabric_bus_register(fabric_bus_t *bus);
 
-/* Driver registration */
+/* Driver registration (RDNX_E_DENIED if listed in rdnx.nodrv) */
 int fabric_driver_register(fabric_driver_t *driver);
 
 /* Device publication */
@@ -121,6 +123,36 @@ typedef void (*fabric_irq_handler_t)(int vector, void *arg);
 int  fabric_request_irq(int vector, fabric_irq_handler_t h, void *arg);
 void fabric_free_irq(int vector, fabric_irq_handler_t h);
 
+/* Device resources (I/O ports, MMIO windows such as PCI BARs) */
+typedef enum fabric_resource_type {
+    FABRIC_RES_NONE = 0,
+    FABRIC_RES_IO = 1,
+    FABRIC_RES_MEM = 2
+} fabric_resource_type_t;
+
+#define FABRIC_RES_F_64BIT     (1u << 0)  /* MEM window decodes 64-bit addresses */
+#define FABRIC_RES_F_PREFETCH  (1u << 1)  /* Prefetchable MEM window */
+
+typedef struct fabric_resource {
+    fabric_device_t* owner;
+    uint32_t type;       /* fabric_resource_type_t */
+    uint32_t index;      /* Bus-defined slot, BAR number for PCI */
+    uint32_t flags;      /* FABRIC_RES_F_* */
+    uint64_t start;      /* Physical address or I/O port */
+    uint64_t size;
+    void* virt;          /* Kernel mapping of a MEM window, NULL until mapped */
+} fabric_resource_t;
+
+/* Record a range decoded by @p dev (buses, at enumeration); RDNX_E_BUSY on overlap */
+int fabric_resource_add(fabric_device_t* dev, uint32_t type, uint32_t index,
+                        uint64_t start, uint64_t size, uint32_t flags);
+int fabric_resource_get(fabric_device_t* dev, uint32_t index, fabric_resource_t* out);
+/* Map MEM resource @p index uncached; repeated calls return the same mapping */
+void* fabric_resource_map(fabric_device_t* dev, uint32_t index, uint64_t* size_out);
+void fabric_resource_release(fabric_device_t* dev);
+/* Map a raw MMIO range uncached into the kernel MMIO window */
+void* fabric_mmio_map(uint64_t phys, uint64_t size);
+
 /* Logging */
 void fabric_log(const char *fmt, ...);
 
@@ -0,0 +1,199 @@
+/**
+ * @file resource.c
+ * @brief Fabric device resource manager (I/O ports, MMIO windows)
+ *
+ * Buses record the address ranges their devices decode (PCI BARs) when
+ * the device is enumerated. Drivers then ask for a resource by index and
+ * get a kernel mapping instead of decoding registers by hand. Overlapping
+ * ranges owned by different devices are refused, which catches firmware
+ * or sizing mistakes before two drivers poke the same registers.
+ */
+
+#include "fabric.h"
+#include "spin.h"
+#include "device/device.h"
+#include "../core/memory.h"
+#include "../arch/config.h"
+#include "../../include/console.h"
+#include "../../include/error.h"
+#include <stddef.h>
+
+#define MAX_RESOURCES 256
+
+#define FABRIC_MMIO_PAGE 4096ULL
+
+/*
+ * LOCKING: resource_lock
+ *   Protects: resource_table, mmio_next.
+ *   MMIO mappings are created under the lock: page_map() does not sleep.
+ */
+static fabric_resource_t resource_table[MAX_RESOURCES];
+static bool resource_used[MAX_RESOURCES];
+static uint64_t mmio_next = ARCH_MMIO_VIRT_BASE;
+static spinlock_t resource_lock;
+
+static bool res_overlaps(const fabric_resource_t* r, uint32_t type, uint64_t start, uint64_t size)
+{
+    if (r->type != type || r->size == 0 || size == 0) {
+        return false;
+    }
+    return start < r->start + r->size && r->start < start + size;
+}
+
+static int res_find_locked(fabric_device_t* dev, uint32_t index)
+{
+    for (uint32_t i = 0; i < MAX_RESOURCES; i++) {
+        if (resource_used[i] && resource_table[i].owner == dev &&
+            resource_table[i].index == index) {
+            return (int)i;
+        }
+    }
+    return -1;
+}
+
+/* Caller holds resource_lock */
+static void* mmio_map_locked(uint64_t phys, uint64_t size)
+{
+    uint64_t page = phys & ~(FABRIC_MMIO_PAGE - 1u);
+    uint64_t span = (phys - page) + size;
+    span = (span + FABRIC_MMIO_PAGE - 1u) & ~(FABRIC_MMIO_PAGE - 1u);
+
+    if (size == 0 || span > ARCH_MMIO_VIRT_END - mmio_next) {
+        return NULL;
+    }
+
+    uint64_t virt = mmio_next;
+    for (uint64_t off = 0; off < span; off += FABRIC_MMIO_PAGE) {
+        if (page_map(virt + off, page + off,
+                     PAGE_FLAG_PRESENT | PAGE_FLAG_WRITABLE | PAGE_FLAG_NOCACHE,
+                     PAGE_TYPE_4KB) != 0) {
+            /* Already mapped pages stay behind; the window is not reused */
+            mmio_next = virt + off + FABRIC_MMIO_PAGE;
+            return NULL;
+        }
+    }
+    mmio_next = virt + span;
+    return (void*)(uintptr_t)(virt + (phys - page));
+}
+
+void* fabric_mmio_map(uint64_t phys, uint64_t size)
+{
+    spinlock_lock(&resource_lock);
+    void* virt = mmio_map_locked(phys, size);
+    spinlock_unlock(&resource_lock);
+    return virt;
+}
+
+int fabric_resource_add(fabric_device_t* dev, uint32_t type, uint32_t index,
+                        uint64_t start, uint64_t size, uint32_t flags)
+{
+    if (!dev || size == 0 || (type != FABRIC_RES_IO && type != FABRIC_RES_MEM)) {
+        return RDNX_E_INVALID;
+    }
+    if (start + size < start) {
+        return RDNX_E_INVALID;
+    }
+
+    spinlock_lock(&resource_lock);
+    if (res_find_locked(dev, index) >= 0) {
+        spinlock_unlock(&resource_lock);
+        return RDNX_E_BUSY;
+    }
+
+    int slot = -1;
+    for (uint32_t i = 0; i < MAX_RESOURCES; i++) {
+        if (!resource_used[i]) {
+            if (slot < 0) {
+                slot = (int)i;
+            }
+            continue;
+        }
+        const fabric_resource_t* r = &resource_table[i];
+        if (r->owner != dev && res_overlaps(r, type, start, size)) {
+            spinlock_unlock(&resource_lock);
+            kprintf("[FABRIC-RES] %s %llx+%llx overlaps %s index %u\n",
+                    type == FABRIC_RES_IO ? "io" : "mem",
+                    (unsigned long long)start,
+                    (unsigned long long)size,
+                    r->owner && r->owner->name ? r->owner->name : "?",
+                    (unsigned)r->index);
+            return RDNX_E_BUSY;
+        }
+    }
+    if (slot < 0) {
+        spinlock_unlock(&resource_lock);
+        return RDNX_E_NOMEM;
+    }
+
+    fabric_resource_t* r = &resource_table[slot];
+    r->owner = dev;
+    r->type = type;
+    r->index = index;
+    r->flags = flags;
+    r->start = start;
+    r->size = size;
+    r->virt = NULL;
+    resource_used[slot] = true;
+    spinlock_unlock(&resource_lock);
+    return RDNX_OK;
+}
+
+int fabric_resource_get(fabric_device_t* dev, uint32_t index, fabric_resource_t* out)
+{
+    if (!dev || !out) {
+        return RDNX_E_INVALID;
+    }
+    spinlock_lock(&resource_lock);
+    int slot = res_find_locked(dev, index);
+    if (slot >= 0) {
+        *out = resource_table[slot];
+    }
+    spinlock_unlock(&resource_lock);
+    return slot >= 0 ? RDNX_OK : RDNX_E_NOTFOUND;
+}
+
+void* fabric_resource_map(fabric_device_t* dev, uint32_t index, uint64_t* size_out)
+{
+    if (!dev) {
+        return NULL;
+    }
+
+    spinlock_lock(&resource_lock);
+    int slot = res_find_locked(dev, index);
+    if (slot < 0 || resource_table[slot].type != FABRIC_RES_MEM) {
+        spinlock_unlock(&resource_lock);
+        return NULL;
+    }
+    fabric_resource_t* r = &resource_table[slot];
+    if (!r->virt) {
+        r->virt = mmio_map_locked(r->start, r->size);
+    }
+    void* virt = r->virt;
+    if (virt && size_out) {
+        *size_out = r->size;
+    }
+    spinlock_unlock(&resource_lock);
+
+    if (!virt) {
+        kprintf("[FABRIC-RES] map failed phys=%llx size=%llx\n",
+                (unsigned long long)r->start, (unsigned long long)r->size);
+    }
+    return virt;
+}
+
+void fabric_resource_release(fabric_device_t* dev)
+{
+    if (!dev) {
+        return;
+    }
+    spinlock_lock(&resource_lock);
+    for (uint32_t i = 0; i < MAX_RESOURCES; i++) {
+        if (resource_used[i] && resource_table[i].owner == dev) {
+            /* The mapping window is a bump allocator: virtual space is not reused */
+            resource_used[i] = false;
+            resource_table[i].owner = NULL;
+            resource_table[i].virt = NULL;
+        }
+    }
+    spinlock_unlock(&resource_lock);
+}
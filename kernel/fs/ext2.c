@@ -30,7 +30,7 @@
 #define EXT2_MAX_INODE_SIZE 512u
 #define EXT2_MAX_TREE_DEPTH 4u
 #define EXT2_MAX_TREE_NODES 2048u
-#define EXT2_MAX_FILE_BYTES (64u * 1024u * 1024u) /* preload cap; files > 64 MB truncated at mount */
+#define EXT2_PAGE_SIZE 4096u /* Page cache unit; a multiple of every block size */
 
 #define EXT2_FEATURE_INCOMPAT_FILETYPE 0x0002u
 #define EXT2_FEATURE_INCOMPAT_SUPP (EXT2_FEATURE_INCOMPAT_FILETYPE)
@@ -123,7 +123,7 @@ typedef struct {
  * LOCKING: g_ext2_rw_lock (spinlock_t)
  *   Protects: g_ext2_live (all fields), g_ext2_live_ready,
  *             ext2_alloc_block, ext2_free_block, ext2_sync_super_and_gdt,
- *             ext2_writeback_file, ext2_resize_file.
+ *             ext2_read_page, ext2_writeback_file, ext2_resize_file.
  *   Lock order: g_ext2_rw_lock -> (no inner locks held by ext2 code).
  *   Callers of ext2_alloc_block / ext2_free_block / ext2_trim_inode_blocks
  *   must already hold g_ext2_rw_lock (caller-holds convention).
@@ -1055,77 +1055,6 @@ static int ext2_trim_inode_blocks(ext2_mount_ctx_t* ctx, uint32_t ino_num, ext2_
     return ext2_write_inode(ctx, ino_num, ino);
 }
 
-static int ext2_load_file(ext2_mount_ctx_t* ctx, uint32_t ino_num, const ext2_inode_t* ino, vfs_node_t* node)
-{
-    if (!ctx || !ino || !node) {
-        return RDNX_E_INVALID;
-    }
-
-    uint64_t fsize64 = ext2_inode_size_bytes(ino);
-    uint32_t fsize = (fsize64 > UINT32_MAX) ? UINT32_MAX : (uint32_t)fsize64;
-    if (fsize > EXT2_MAX_FILE_BYTES) {
-        fsize = EXT2_MAX_FILE_BYTES;
-    }
-
-    if (fsize == 0) {
-        int zrc = vfs_fs_set_file_data(node, NULL, 0);
-        if (zrc == RDNX_OK) {
-            ext2_mark_node(node, ino_num);
-        }
-        return zrc;
-    }
-
-    uint8_t* data = (uint8_t*)kmalloc(fsize);
-    uint8_t* blk = (uint8_t*)kmalloc(ctx->block_size);
-    if (!data || !blk) {
-        if (data) {
-            kfree(data);
-        }
-        if (blk) {
-            kfree(blk);
-        }
-        return RDNX_E_NOMEM;
-    }
-
-    uint32_t done = 0;
-    uint32_t lbn = 0;
-    while (done < fsize) {
-        uint32_t pblk = 0;
-        int rc = ext2_inode_get_block(ctx, ino, lbn, &pblk);
-        if (rc != RDNX_OK) {
-            kfree(blk);
-            kfree(data);
-            return rc;
-        }
-        if (pblk == 0) {
-            memset(blk, 0, ctx->block_size);
-        } else {
-            rc = ext2_read_block(ctx, pblk, blk);
-            if (rc != RDNX_OK) {
-                kfree(blk);
-                kfree(data);
-                return rc;
-            }
-        }
-
-        uint32_t chunk = ctx->block_size;
-        if (chunk > (fsize - done)) {
-            chunk = fsize - done;
-        }
-        memcpy(&data[done], blk, chunk);
-        done += chunk;
-        lbn++;
-    }
-
-    int rc = vfs_fs_set_file_data(node, data, fsize);
-    if (rc == RDNX_OK) {
-        ext2_mark_node(node, ino_num);
-    }
-    kfree(blk);
-    kfree(data);
-    return rc;
-}
-
 static int ext2_build_dir(ext2_mount_ctx_t* ctx,
                           vfs_node_t* parent,
                           uint32_t dir_ino_num,
@@ -1198,7 +1127,8 @@ static int ext2_build_dir(ext2_mount_ctx_t* ctx,
                                     if (nt == VFS_NODE_DIR) {
                                         (void)ext2_build_dir(ctx, child, de->inode, &child_ino, depth + 1u);
                                     } else if (ext2_is_reg(&child_ino)) {
-                                        (void)ext2_load_file(ctx, de->inode, &child_ino, child);
+                                        /* Data is paged in on first access */
+                                        (void)vfs_fs_set_file_size(child, ext2_inode_size_bytes(&child_ino));
                                     }
                                 }
                             }
@@ -1218,6 +1148,67 @@ static int ext2_build_dir(ext2_mount_ctx_t* ctx,
     return RDNX_OK;
 }
 
+int ext2_read_page(vfs_node_t* node, uint64_t offset, void* page)
+{
+    spinlock_lock(&g_ext2_rw_lock);
+    if (!node || !node->inode || !page || (offset % EXT2_PAGE_SIZE) != 0) {
+        spinlock_unlock(&g_ext2_rw_lock);
+        return RDNX_E_INVALID;
+    }
+    if (!g_ext2_live_ready || !g_ext2_live.bdev || !g_ext2_live.gdt) {
+        spinlock_unlock(&g_ext2_rw_lock);
+        return RDNX_E_UNSUPPORTED;
+    }
+    if (node->inode->fs_tag != VFS_FS_TAG_EXT2 || node->inode->fs_ino == 0) {
+        spinlock_unlock(&g_ext2_rw_lock);
+        return RDNX_E_UNSUPPORTED;
+    }
+
+    ext2_inode_t ino;
+    int rc = ext2_read_inode(&g_ext2_live, (uint32_t)node->inode->fs_ino, &ino);
+    if (rc != RDNX_OK) {
+        spinlock_unlock(&g_ext2_rw_lock);
+        return rc;
+    }
+    if (!ext2_is_reg(&ino)) {
+        spinlock_unlock(&g_ext2_rw_lock);
+        return RDNX_E_UNSUPPORTED;
+    }
+
+    /* Holes and everything past the on-disk size read as zero */
+    uint8_t* dst = (uint8_t*)page;
+    memset(dst, 0, EXT2_PAGE_SIZE);
+    uint64_t disk_size = ext2_inode_size_bytes(&ino);
+    uint32_t bs = g_ext2_live.block_size;
+    for (uint32_t done = 0; done < EXT2_PAGE_SIZE && offset + done < disk_size; done += bs) {
+        uint64_t lbn = (offset + done) / bs;
+        uint32_t pblk = 0;
+        if (lbn > UINT32_MAX) {
+            break;
+        }
+        rc = ext2_inode_get_block(&g_ext2_live, &ino, (uint32_t)lbn, &pblk);
+        if (rc != RDNX_OK) {
+            spinlock_unlock(&g_ext2_rw_lock);
+            return rc;
+        }
+        if (pblk == 0) {
+            continue;
+        }
+        rc = ext2_read_block(&g_ext2_live, pblk, dst + done);
+        if (rc != RDNX_OK) {
+            spinlock_unlock(&g_ext2_rw_lock);
+            return rc;
+        }
+    }
+    if (offset < disk_size && disk_size - offset < EXT2_PAGE_SIZE) {
+        uint32_t valid = (uint32_t)(disk_size - offset);
+        memset(dst + valid, 0, EXT2_PAGE_SIZE - valid);
+    }
+
+    spinlock_unlock(&g_ext2_rw_lock);
+    return RDNX_OK;
+}
+
 int ext2_writeback_file(vfs_node_t* node, size_t off, const void* data, size_t len, size_t final_size)
 {
     spinlock_lock(&g_ext2_rw_lock);
@@ -1516,3 +1507,27 @@ int ext2_fs_init(void)
     (void)kmod_register_builtin("fs.ext2", "fs", "0.1", 0);
     return vfs_register_fs(&ext2_driver);
 }
+
+int ext2_sync(void)
+{
+    spinlock_lock(&g_ext2_rw_lock);
+    if (!g_ext2_live_ready) {
+        spinlock_unlock(&g_ext2_rw_lock);
+        return RDNX_OK;
+    }
+    uint64_t now_ns = console_get_realtime_ns();
+    if (now_ns != 0) {
+        g_ext2_live.sb.wtime = (uint32_t)(now_ns / 1000000000ULL);
+    }
+    int rc = ext2_sync_super_and_gdt(&g_ext2_live);
+    spinlock_unlock(&g_ext2_rw_lock);
+    return rc;
+}
+
+int ext2_mounted_on(const struct fabric_blockdev* dev)
+{
+    spinlock_lock(&g_ext2_rw_lock);
+    int mounted = g_ext2_live_ready && g_ext2_live.bdev == dev;
+    spinlock_unlock(&g_ext2_rw_lock);
+    return mounted;
+}
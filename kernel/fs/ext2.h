@@ -8,6 +8,8 @@
 #include <stddef.h>
 #include "vfs.h"
 
+struct fabric_blockdev;
+
 typedef struct ext2_fs_caps {
     int write_in_place;
     int write_extend;
@@ -16,5 +18,12 @@ typedef struct ext2_fs_caps {
 
 int ext2_fs_init(void);
 int ext2_query_caps(ext2_fs_caps_t* out_caps);
+/* Read one 4 KiB page of file data at a page-aligned @offset (page cache fill) */
+int ext2_read_page(vfs_node_t* node, uint64_t offset, void* page);
 int ext2_writeback_file(vfs_node_t* node, size_t off, const void* data, size_t len, size_t final_size);
 int ext2_resize_file(vfs_node_t* node, size_t new_size);
+/* Write the in-memory superblock and group descriptors back to disk.
+ * Dirty file pages are written back by the page cache (vfs_sync). */
+int ext2_sync(void);
+/* @dev holds the mounted ext2 filesystem (so it must not be swapped to) */
+int ext2_mounted_on(const struct fabric_blockdev* dev);
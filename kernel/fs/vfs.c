@@ -1,6 +1,10 @@
 /**
  * @file vfs.c
  * @brief Minimal VFS + RAMFS implementation
+ *
+ * File data goes through the page cache; ramfs files are memory-only
+ * cache objects, ext2 files are backed by ext2_read_page() and
+ * ext2_writeback_file().
  */
 
 #include "vfs.h"
@@ -10,12 +14,17 @@
 #include "../fabric/service/block_service.h"
 #include "../common/tty_console.h"
 #include "../common/heap.h"
+#include "../common/kmem.h"
+#include "../vm/vm_page_cache.h"
+#include "../vm/vm_pager.h"
+#include "../arch/config.h"
 #include "../../include/common.h"
 #include "../../include/console.h"
 #include "../../include/error.h"
 #include "../../include/debug.h"
 
 #define VFS_CACHE_SIZE 64
+#define VFS_DIRTY_FLUSH_PAGES 256u /* Write back a file once this much of it is dirty */
 
 typedef struct vfs_cache_entry {
     char path[64];
@@ -116,13 +125,15 @@ static vfs_inode_t* vfs_alloc_inode(vfs_node_type_t type)
     return inode;
 }
 
+/* vfs_node_t cache, created by vfs_init() */
+static kmem_cache_t* vfs_node_cache = NULL;
+
 static vfs_node_t* vfs_alloc_node(const char* name, vfs_node_type_t type)
 {
-    vfs_node_t* node = (vfs_node_t*)kmalloc(sizeof(vfs_node_t));
+    vfs_node_t* node = (vfs_node_t*)kmem_cache_zalloc(vfs_node_cache);
     if (!node) {
         return NULL;
     }
-    memset(node, 0, sizeof(*node));
     node->ref_count = 1; /* tree holds one reference at birth */
     if (name) {
         strncpy(node->name, name, sizeof(node->name) - 1);
@@ -131,7 +142,7 @@ static vfs_node_t* vfs_alloc_node(const char* name, vfs_node_type_t type)
     node->type = type;
     node->inode = vfs_alloc_inode(type);
     if (!node->inode) {
-        kfree(node);
+        kmem_cache_free(vfs_node_cache, node);
         return NULL;
     }
     return node;
@@ -144,12 +155,16 @@ static void vfs_free_node(vfs_node_t* node)
     }
     if (node->inode) {
         node->inode->node_gen++; /* invalidate any cache entries pointing here (P1-6A) */
-        if (node->inode->data) {
-            kfree(node->inode->data);
+        if (node->inode->object) {
+            /* Mappings may outlive the node: they keep the pages, not the backing */
+            (void)vm_page_cache_flush(node->inode->object, 0, 0);
+            vm_page_cache_object_detach(node->inode->object);
+            vm_object_unref(node->inode->object);
+            node->inode->object = NULL;
         }
         kfree(node->inode);
     }
-    kfree(node);
+    kmem_cache_free(vfs_node_cache, node);
 }
 
 /* Increment the reference count of a node.
@@ -295,31 +310,88 @@ static vfs_node_t* vfs_lookup(const char* path)
     return current;
 }
 
-static int vfs_grow_file(vfs_node_t* node, size_t needed)
+static int vfs_ext2_read_page(void* owner, uint64_t offset, void* page)
+{
+    return ext2_read_page((vfs_node_t*)owner, offset, page);
+}
+
+static int vfs_ext2_write_page(void* owner, uint64_t offset, const void* page, uint64_t len)
+{
+    vfs_node_t* node = (vfs_node_t*)owner;
+    return ext2_writeback_file(node, (size_t)offset, page, (size_t)len, node->inode->size);
+}
+
+static const vm_page_cache_ops_t vfs_ext2_cache_ops = {
+    .read = vfs_ext2_read_page,
+    .write = vfs_ext2_write_page,
+};
+
+vm_object_t* vfs_node_object(vfs_node_t* node)
 {
     if (!node || node->type != VFS_NODE_FILE || !node->inode) {
-        return -1;
+        return NULL;
     }
-    if (needed <= node->inode->capacity) {
-        return 0;
+    vfs_inode_t* inode = node->inode;
+    if (inode->flags & (VFS_INODE_CONSOLE | VFS_INODE_DEV_NULL | VFS_INODE_DEV_ZERO |
+                        VFS_INODE_CHARDEV | VFS_INODE_BLOCKDEV)) {
+        return NULL;
     }
-    size_t new_cap = node->inode->capacity ? node->inode->capacity : 64;
-    while (new_cap < needed) {
-        new_cap *= 2;
+    if (!inode->object) {
+        const vm_page_cache_ops_t* ops = NULL; /* ramfs: the cache is the storage */
+        if (inode->fs_tag == VFS_FS_TAG_EXT2) {
+            ops = &vfs_ext2_cache_ops;
+        }
+        inode->object = vm_vnode_pager_alloc(inode->size, ops, node);
     }
-    uint8_t* new_buf = (uint8_t*)kmalloc(new_cap);
-    if (!new_buf) {
-        return -1;
+    return inode->object;
+}
+
+static void vfs_set_size(vfs_node_t* node, size_t size)
+{
+    node->inode->size = size;
+    if (node->inode->object) {
+        vm_page_cache_truncate(node->inode->object, size);
     }
-    if (node->inode->data && node->inode->size > 0) {
-        memcpy(new_buf, node->inode->data, node->inode->size);
+}
+
+/*
+ * Copy between @buf and the cached pages of a regular file. The file
+ * must already cover [pos, pos + len).
+ * @return Bytes copied, or an error if nothing was
+ */
+static int vfs_cache_io(vfs_node_t* node, size_t pos, void* buf, size_t len, bool write)
+{
+    vm_object_t* obj = vfs_node_object(node);
+    if (!obj) {
+        return RDNX_E_NOMEM;
     }
-    if (node->inode->data) {
-        kfree(node->inode->data);
+    uint8_t* p = (uint8_t*)buf;
+    size_t done = 0;
+    while (done < len) {
+        uint64_t off = (uint64_t)pos + done;
+        uint64_t pindex = off / VM_OBJECT_PAGE_SIZE;
+        size_t page_off = (size_t)(off % VM_OBJECT_PAGE_SIZE);
+        size_t chunk = (size_t)VM_OBJECT_PAGE_SIZE - page_off;
+        if (chunk > len - done) {
+            chunk = len - done;
+        }
+        uint32_t flags = (write && chunk == VM_OBJECT_PAGE_SIZE) ? VM_PAGE_CACHE_NOFILL : 0;
+        uint64_t phys = 0;
+        int rc = vm_page_cache_get(obj, pindex, flags, &phys);
+        if (rc != RDNX_OK) {
+            return done ? (int)done : rc;
+        }
+        uint8_t* page = (uint8_t*)ARCH_PHYS_TO_VIRT(phys);
+        if (write) {
+            memcpy(page + page_off, p + done, chunk);
+            vm_page_cache_dirty(obj, pindex, 0);
+        } else {
+            memcpy(p + done, page + page_off, chunk);
+        }
+        vm_page_cache_put(phys);
+        done += chunk;
     }
-    node->inode->data = new_buf;
-    node->inode->capacity = new_cap;
-    return 0;
+    return (int)done;
 }
 
 static int vfs_resize_file(vfs_file_t* file, size_t new_size)
@@ -338,31 +410,8 @@ static int vfs_resize_file(vfs_file_t* file, size_t new_size)
         if (rc != RDNX_OK) {
             return rc;
         }
-        if (inode->data && new_size > inode->capacity) {
-            if (vfs_grow_file(file->node, new_size) != 0) {
-                return RDNX_E_NOMEM;
-            }
-        }
-        if (inode->data && new_size > inode->size) {
-            memset(inode->data + inode->size, 0, new_size - inode->size);
-        }
-        inode->size = new_size;
-        if (file->pos > new_size) {
-            file->pos = new_size;
-        }
-        return RDNX_OK;
-    }
-
-    size_t old_size = inode->size;
-    if (new_size > inode->capacity) {
-        if (vfs_grow_file(file->node, new_size) != 0) {
-            return RDNX_E_NOMEM;
-        }
     }
-    if (new_size > old_size && inode->data) {
-        memset(inode->data + old_size, 0, new_size - old_size);
-    }
-    inode->size = new_size;
+    vfs_set_size(file->node, new_size);
     if (file->pos > new_size) {
         file->pos = new_size;
     }
@@ -456,6 +505,14 @@ static int vfs_mount_devfs(void)
     if (mrc != RDNX_OK && mrc != RDNX_E_BUSY) {
         return -1;
     }
+    /* POSIX shared memory objects (shm_open) are ramfs files here */
+    if (vfs_mkdir("/dev/shm") != RDNX_OK) {
+        return -1;
+    }
+    mrc = vfs_mount("ramfs", NULL, "/dev/shm");
+    if (mrc != RDNX_OK && mrc != RDNX_E_BUSY) {
+        return -1;
+    }
     return 0;
 }
 
@@ -609,11 +666,7 @@ static int vfs_import_initrd(void)
         if (!node) {
             continue;
         }
-        if (vfs_grow_file(node, e->size) != 0) {
-            continue;
-        }
-        memcpy(node->inode->data, base + e->offset, e->size);
-        node->inode->size = e->size;
+        (void)vfs_fs_set_file_data(node, base + e->offset, e->size);
     }
 
     return 0;
@@ -631,6 +684,15 @@ int vfs_init(void)
         return RDNX_OK;
     }
     TRACE_EVENT("vfs_init");
+    if (vm_page_cache_init() != RDNX_OK) {
+        return RDNX_E_NOMEM;
+    }
+    if (!vfs_node_cache) {
+        vfs_node_cache = kmem_cache_create("vfs_node", sizeof(vfs_node_t), 0, NULL, NULL, NULL, 0);
+        if (!vfs_node_cache) {
+            return RDNX_E_NOMEM;
+        }
+    }
     (void)vfs_register_fs(&vfs_ramfs_driver);
     (void)devfs_fs_init();
     (void)ext2_fs_init();
@@ -807,6 +869,9 @@ int vfs_open(const char* path, int flags, vfs_file_t* out_file)
     }
     TRACE_EVENT("vfs_open");
     vfs_node_t* node = vfs_lookup(path);
+    if (node && (flags & VFS_OPEN_CREATE) && (flags & VFS_OPEN_EXCL)) {
+        return RDNX_E_BUSY;
+    }
     if (!node) {
         if (!(flags & VFS_OPEN_CREATE)) {
             return RDNX_E_NOTFOUND;
@@ -846,16 +911,20 @@ int vfs_open(const char* path, int flags, vfs_file_t* out_file)
 
 int vfs_close(vfs_file_t* file)
 {
+    int rc = RDNX_OK;
     if (!file) {
         return RDNX_E_INVALID;
     }
     if (file->node) {
+        if (file->writable && file->node->inode && file->node->inode->object) {
+            rc = vm_page_cache_flush(file->node->inode->object, 0, 0);
+        }
         vfs_node_release(file->node); /* drops the reference taken in vfs_open */
         file->node = NULL;
     }
     file->pos = 0;
     file->writable = false;
-    return RDNX_OK;
+    return rc;
 }
 
 int vfs_file_dup(const vfs_file_t* src, vfs_file_t* dst)
@@ -926,9 +995,11 @@ int vfs_read(vfs_file_t* file, void* buffer, size_t size)
     }
     size_t avail = inode->size - file->pos;
     size_t to_read = size < avail ? size : avail;
-    memcpy(buffer, inode->data + file->pos, to_read);
-    file->pos += to_read;
-    return (int)to_read;
+    int n = vfs_cache_io(file->node, file->pos, buffer, to_read, false);
+    if (n > 0) {
+        file->pos += (size_t)n;
+    }
+    return n;
 }
 
 int vfs_write(vfs_file_t* file, const void* buffer, size_t size)
@@ -946,31 +1017,6 @@ int vfs_write(vfs_file_t* file, const void* buffer, size_t size)
     if (inode->flags & VFS_INODE_DEV_ZERO) {
         return (int)size;
     }
-    if (inode->fs_tag == VFS_FS_TAG_EXT2) {
-        size_t end = file->pos + size;
-        size_t final_size = (end > inode->size) ? end : inode->size;
-        int wrc = ext2_writeback_file(file->node, file->pos, buffer, size, final_size);
-        if (wrc != RDNX_OK) {
-            return wrc;
-        }
-        if (inode->data && end > inode->capacity) {
-            if (vfs_grow_file(file->node, end) != 0) {
-                return RDNX_E_NOMEM;
-            }
-        }
-        if (end > inode->size) {
-            inode->size = end;
-        }
-        if (inode->data && size > 0) {
-            size_t avail = (file->pos < inode->size) ? (inode->size - file->pos) : 0;
-            size_t copy = (size < avail) ? size : avail;
-            if (copy > 0) {
-                memcpy(inode->data + file->pos, buffer, copy);
-            }
-        }
-        file->pos += size;
-        return (int)size;
-    }
     if (inode->flags & VFS_INODE_BLOCKDEV) {
         fabric_blockdev_t* bdev = fabric_blockdev_find(file->node->name);
         if (!bdev || bdev->sector_size == 0) {
@@ -991,16 +1037,29 @@ int vfs_write(vfs_file_t* file, const void* buffer, size_t size)
         file->pos += size;
         return (int)size;
     }
+    if (size == 0) {
+        return 0;
+    }
     size_t end = file->pos + size;
-    if (vfs_grow_file(file->node, end) != 0) {
-        return RDNX_E_NOMEM;
+    if (end < file->pos) {
+        return RDNX_E_INVALID;
     }
-    memcpy(inode->data + file->pos, buffer, size);
-    file->pos += size;
     if (end > inode->size) {
-        inode->size = end;
+        /* Grow first so write-back of these pages covers them (ext2: at flush) */
+        vfs_set_size(file->node, end);
+    }
+    int n = vfs_cache_io(file->node, file->pos, (void*)buffer, size, true);
+    if (n <= 0) {
+        return n;
+    }
+    file->pos += (size_t)n;
+    if (inode->object && inode->object->cache_dirty >= VFS_DIRTY_FLUSH_PAGES) {
+        int frc = vm_page_cache_flush(inode->object, 0, 0);
+        if (frc != RDNX_OK) {
+            return frc;
+        }
     }
-    return (int)size;
+    return n;
 }
 
 int vfs_seek(vfs_file_t* file, int64_t off, int whence, uint64_t* out_pos)
@@ -1101,6 +1160,14 @@ int vfs_fstat(const vfs_file_t* file, vfs_stat_t* out_stat)
     return RDNX_OK;
 }
 
+int vfs_sync(void)
+{
+    /* ext2 is the only persistent filesystem; ramfs/devfs have nothing to flush */
+    int rc = vm_page_cache_flush_all();
+    int src = ext2_sync();
+    return (rc != RDNX_OK) ? rc : src;
+}
+
 vfs_node_t* vfs_fs_alloc_node(const char* name, vfs_node_type_t type)
 {
     return vfs_alloc_node(name, type);
@@ -1122,13 +1189,24 @@ int vfs_fs_set_file_data(vfs_node_t* node, const void* data, size_t size)
     if (size > 0 && !data) {
         return RDNX_E_INVALID;
     }
-    if (vfs_grow_file(node, size) != 0) {
-        return RDNX_E_NOMEM;
+    vfs_set_size(node, 0);
+    vfs_set_size(node, size);
+    if (size == 0) {
+        return RDNX_OK;
     }
-    if (size > 0) {
-        memcpy(node->inode->data, data, size);
+    int n = vfs_cache_io(node, 0, (void*)data, size, true);
+    if (n < 0) {
+        return n;
     }
-    node->inode->size = size;
+    return ((size_t)n == size) ? RDNX_OK : RDNX_E_NOMEM;
+}
+
+int vfs_fs_set_file_size(vfs_node_t* node, uint64_t size)
+{
+    if (!node || node->type != VFS_NODE_FILE || !node->inode || size > (uint64_t)SIZE_MAX) {
+        return RDNX_E_INVALID;
+    }
+    vfs_set_size(node, (size_t)size);
     return RDNX_OK;
 }
 
@@ -1,6 +1,11 @@
 /**
  * @file vfs.h
- * @brief Minimal VFS interface (RAM-backed implementation)
+ * @brief Minimal VFS interface
+ *
+ * Regular file data lives in the page cache (vm/vm_page_cache.h): each
+ * file has one vm_object shared by vfs_read/vfs_write and mmap. ext2
+ * pages are filled from disk on first use and written back on close,
+ * vfs_sync and memory pressure; ramfs pages are the only copy.
  */
 
 #pragma once
@@ -23,9 +28,7 @@ typedef struct vfs_inode {
     uint32_t fs_aux;
     uint64_t fs_ino;
     size_t size;
-    size_t capacity;
-    uint8_t* data;
-    vm_object_t* mmap_object;
+    vm_object_t* object; /* page cache object, created on first data access */
     uint32_t node_gen; /* incremented on vfs_free_node; cache uses this to detect stale entries */
 } vfs_inode_t;
 
@@ -78,7 +81,8 @@ enum {
     VFS_OPEN_READ   = 1 << 0,
     VFS_OPEN_WRITE  = 1 << 1,
     VFS_OPEN_CREATE = 1 << 2,
-    VFS_OPEN_TRUNC  = 1 << 3
+    VFS_OPEN_TRUNC  = 1 << 3,
+    VFS_OPEN_EXCL   = 1 << 4  /* With CREATE: fail with RDNX_E_BUSY if the file exists */
 };
 
 enum {
@@ -107,6 +111,8 @@ int vfs_mount_initrd_root(void);
 vfs_node_t* vfs_fs_alloc_node(const char* name, vfs_node_type_t type);
 int vfs_fs_add_child(vfs_node_t* parent, vfs_node_t* child);
 int vfs_fs_set_file_data(vfs_node_t* node, const void* data, size_t size);
+/* Set the size of a file whose data the driver supplies through the page cache */
+int vfs_fs_set_file_size(vfs_node_t* node, uint64_t size);
 /* Release a node allocated with vfs_fs_alloc_node that was never added to the
  * tree (or was added and later removed). Drops the tree reference. */
 void vfs_fs_free_node(vfs_node_t* node);
@@ -128,3 +134,7 @@ int vfs_truncate(const char* path, uint64_t size);
 int vfs_ftruncate(vfs_file_t* file, uint64_t size);
 int vfs_stat(const char* path, vfs_stat_t* out_stat);
 int vfs_fstat(const vfs_file_t* file, vfs_stat_t* out_stat);
+/* Page cache object of a regular file (for mmap); NULL for devices */
+vm_object_t* vfs_node_object(vfs_node_t* node);
+/* Write back dirty file pages and filesystem metadata (reboot/poweroff path) */
+int vfs_sync(void);
@@ -28,42 +28,20 @@
 #define SERIAL_INPUT_ENABLED 1
 #endif
 
-#define SERIAL_COM1_BASE 0x3F8
-#define SERIAL_DATA      0
-#define SERIAL_LSR       5
-
 #if SERIAL_INPUT_ENABLED
-static inline uint8_t inb(uint16_t port)
-{
-    uint8_t value;
-    __asm__ volatile ("inb %1, %0" : "=a"(value) : "Nd"(port));
-    return value;
-}
-
+/* COM1 itself (polling or RX interrupt) is owned by console.c */
 static int serial_read_char(void)
 {
-    uint8_t lsr = inb(SERIAL_COM1_BASE + SERIAL_LSR);
-    /* If port absent, LSR may read as 0xFF */
-    if (lsr == 0xFF) {
-        return -1;
-    }
-    if ((lsr & 0x01) == 0) {
-        return -1;
-    }
-    uint8_t c = inb(SERIAL_COM1_BASE + SERIAL_DATA);
+    int c = console_serial_getc();
     if (c == '\r') {
         c = '\n';
     }
-    return (int)c;
+    return c;
 }
 
 static bool serial_has_char(void)
 {
-    uint8_t lsr = inb(SERIAL_COM1_BASE + SERIAL_LSR);
-    if (lsr == 0xFF) {
-        return false;
-    }
-    return (lsr & 0x01) != 0;
+    return console_serial_has_char();
 }
 #else
 static int __attribute__((unused)) serial_read_char(void)
@@ -24,6 +24,7 @@ enum {
     LINUX_O_WRONLY = 00000001,
     LINUX_O_RDWR = 00000002,
     LINUX_O_CREAT = 00000100,
+    LINUX_O_EXCL = 00000200,
     LINUX_O_TRUNC = 00001000,
     LINUX_O_APPEND = 00002000,
     LINUX_AT_FDCWD = -100,
@@ -368,6 +369,9 @@ static int linux_to_rdnx_open_flags(int linux_flags)
     if (linux_flags & LINUX_O_TRUNC) {
         out |= VFS_OPEN_TRUNC;
     }
+    if (linux_flags & LINUX_O_EXCL) {
+        out |= VFS_OPEN_EXCL;
+    }
     return out;
 }
 
@@ -376,6 +380,7 @@ static uint64_t linux_to_rdnx_mmap_flags(uint64_t linux_flags)
     enum {
         LINUX_MAP_SHARED    = 0x01u,
         LINUX_MAP_PRIVATE   = 0x02u,
+        LINUX_MAP_TYPE      = 0x03u, /* 0x03: MAP_SHARED_VALIDATE */
         LINUX_MAP_FIXED     = 0x10u,
         LINUX_MAP_ANONYMOUS = 0x20u,
 
@@ -386,10 +391,11 @@ static uint64_t linux_to_rdnx_mmap_flags(uint64_t linux_flags)
     };
 
     uint64_t out = 0;
-    if (linux_flags & LINUX_MAP_SHARED) {
+    if ((linux_flags & LINUX_MAP_TYPE) == LINUX_MAP_TYPE) {
         out |= RDNX_MAP_SHARED;
-    }
-    if (linux_flags & LINUX_MAP_PRIVATE) {
+    } else if (linux_flags & LINUX_MAP_SHARED) {
+        out |= RDNX_MAP_SHARED;
+    } else if (linux_flags & LINUX_MAP_PRIVATE) {
         out |= RDNX_MAP_PRIVATE;
     }
     if (linux_flags & LINUX_MAP_FIXED) {
@@ -461,11 +467,8 @@ uint64_t linux_compat_dispatch(uint64_t num,
         if (set && !unix_user_range_ok(set, (size_t)sigsetsize)) {
             return (uint64_t)(-LINUX_EINVAL);
         }
-        if (oldset) {
-            if (!unix_user_range_ok(oldset, (size_t)sigsetsize)) {
-                return (uint64_t)(-LINUX_EINVAL);
-            }
-            memset(oldset, 0, (size_t)sigsetsize);
+        if (oldset && unix_clear_user(oldset, (size_t)sigsetsize) != RDNX_OK) {
+            return (uint64_t)(-LINUX_EINVAL);
         }
         return 0;
     }
@@ -492,29 +495,50 @@ uint64_t linux_compat_dispatch(uint64_t num,
         return linux_ret(posix_munmap(a1, a2, 0, 0, 0, 0));
     case 12: /* brk */
         return linux_ret(posix_brk(a1, 0, 0, 0, 0, 0));
+    case 25: { /* mremap */
+        uint64_t r = posix_mremap(a1, a2, a3, a4, a5, 0);
+        if ((long)r == RDNX_E_NOTFOUND) {
+            return (uint64_t)(-LINUX_EFAULT); /* Old range is not one mapping */
+        }
+        return linux_ret(r);
+    }
+    case 27: /* mincore */
+        return linux_ret(posix_mincore(a1, a2, a3, 0, 0, 0));
+    case 28: { /* madvise */
+        enum {
+            LINUX_MADV_FREE = 8
+        };
+        int advice = (int)a3;
+        if (advice == LINUX_MADV_FREE) {
+            advice = VM_MADV_FREE;
+        } else if (advice > VM_MADV_DONTNEED) {
+            return (uint64_t)(-LINUX_EINVAL);
+        }
+        return linux_ret((uint64_t)vm_task_madvise(task_get_current(), a1, a2, advice));
+    }
     case 16: { /* ioctl */
         enum {
             LINUX_TIOCGWINSZ = 0x5413u
         };
         if ((uint32_t)a2 == LINUX_TIOCGWINSZ) {
-            linux_winsize_u_t* ws = (linux_winsize_u_t*)(uintptr_t)a3;
-            if (!ws || !unix_user_range_ok(ws, sizeof(*ws))) {
+            linux_winsize_u_t ws;
+            ws.ws_row = 25;
+            ws.ws_col = 80;
+            ws.ws_xpixel = 0;
+            ws.ws_ypixel = 0;
+            if (!a3 || unix_copy_to_user((void*)(uintptr_t)a3, &ws, sizeof(ws)) != RDNX_OK) {
                 return (uint64_t)(-LINUX_EINVAL);
             }
-            ws->ws_row = 25;
-            ws->ws_col = 80;
-            ws->ws_xpixel = 0;
-            ws->ws_ypixel = 0;
             return 0;
         }
         return linux_ret(posix_ioctl(a1, a2, a3, 0, 0, 0));
     }
     case 21: { /* access */
-        const char* path = (const char*)(uintptr_t)a1;
+        char path[UNIX_PATH_MAX];
         int mode = (int)a2;
         vfs_stat_t st;
         uint16_t m;
-        if (!path) {
+        if (unix_copy_user_cstr(path, sizeof(path), (const char*)(uintptr_t)a1) != RDNX_OK) {
             return (uint64_t)(-LINUX_EINVAL);
         }
         if ((mode & ~LINUX_ACCESS_MODE_MASK) != 0) {
@@ -573,28 +597,28 @@ uint64_t linux_compat_dispatch(uint64_t num,
     case 63: /* uname */
         return linux_ret(posix_uname(a1, 0, 0, 0, 0, 0));
     case 96: { /* gettimeofday */
-        linux_timeval_t* tv = (linux_timeval_t*)(uintptr_t)a1;
-        if (tv) {
-            if (!unix_user_range_ok(tv, sizeof(*tv))) {
+        if (a1) {
+            uint64_t us = console_get_realtime_us();
+            linux_timeval_t tv;
+            tv.tv_sec = (int64_t)(us / 1000000ULL);
+            tv.tv_usec = (int64_t)(us % 1000000ULL);
+            if (unix_copy_to_user((void*)(uintptr_t)a1, &tv, sizeof(tv)) != RDNX_OK) {
                 return (uint64_t)(-LINUX_EINVAL);
             }
-            uint64_t us = console_get_realtime_us();
-            tv->tv_sec = (int64_t)(us / 1000000ULL);
-            tv->tv_usec = (int64_t)(us % 1000000ULL);
         }
         /* timezone argument ignored */
         return 0;
     }
     case 99: { /* sysinfo */
-        linux_sysinfo_u_t* out = (linux_sysinfo_u_t*)(uintptr_t)a1;
-        if (!out || !unix_user_range_ok(out, sizeof(*out))) {
+        linux_sysinfo_u_t out;
+        memset(&out, 0, sizeof(out));
+        out.uptime = (int64_t)(console_get_uptime_us() / 1000000ULL);
+        out.totalram = pmm_get_total_pages() * LINUX_PAGE_SIZE;
+        out.freeram = pmm_get_free_pages() * LINUX_PAGE_SIZE;
+        out.mem_unit = 1;
+        if (!a1 || unix_copy_to_user((void*)(uintptr_t)a1, &out, sizeof(out)) != RDNX_OK) {
             return (uint64_t)(-LINUX_EINVAL);
         }
-        memset(out, 0, sizeof(*out));
-        out->uptime = (int64_t)(console_get_uptime_us() / 1000000ULL);
-        out->totalram = pmm_get_total_pages() * LINUX_PAGE_SIZE;
-        out->freeram = pmm_get_free_pages() * LINUX_PAGE_SIZE;
-        out->mem_unit = 1;
         return 0;
     }
     case 72: /* fcntl */
@@ -607,10 +631,10 @@ uint64_t linux_compat_dispatch(uint64_t num,
         if (a1 == 0 || a2 == 0) {
             return (uint64_t)(-LINUX_EINVAL);
         }
-        if (!unix_user_range_ok((const void*)(uintptr_t)a1, (size_t)a2)) {
+        char buf[UNIX_PATH_MAX];
+        if (unix_copy_user_cstr(buf, sizeof(buf), (const char*)(uintptr_t)a1) != RDNX_OK) {
             return (uint64_t)(-LINUX_EINVAL);
         }
-        const char* buf = (const char*)(uintptr_t)a1;
         size_t n = strlen(buf) + 1u; /* Guest ABI returns the length including the NUL byte. */
         return (uint64_t)n;
     }
@@ -635,10 +659,14 @@ uint64_t linux_compat_dispatch(uint64_t num,
         return 0;
     }
     case 82: { /* rename */
+        char oldp[UNIX_PATH_MAX];
+        char newp[UNIX_PATH_MAX];
+        if (unix_copy_user_cstr(oldp, sizeof(oldp), (const char*)(uintptr_t)a1) != RDNX_OK ||
+            unix_copy_user_cstr(newp, sizeof(newp), (const char*)(uintptr_t)a2) != RDNX_OK) {
+            return (uint64_t)(-LINUX_EINVAL);
+        }
         uint64_t rc = linux_ret(posix_rename(a1, a2, 0, 0, 0, 0));
         if ((int64_t)rc >= 0) {
-            const char* oldp = (const char*)(uintptr_t)a1;
-            const char* newp = (const char*)(uintptr_t)a2;
             linux_symlink_rename_path(oldp, newp);
             linux_mode_rename_path(oldp, newp);
         }
@@ -722,8 +750,11 @@ uint64_t linux_compat_dispatch(uint64_t num,
         return 0;
     }
     case 87: {
-        const char* path = (const char*)(uintptr_t)a1;
-        if (path && linux_symlink_remove(path) == RDNX_OK) {
+        char path[UNIX_PATH_MAX];
+        if (unix_copy_user_cstr(path, sizeof(path), (const char*)(uintptr_t)a1) != RDNX_OK) {
+            return (uint64_t)(-LINUX_EINVAL);
+        }
+        if (linux_symlink_remove(path) == RDNX_OK) {
             linux_mode_remove(path);
             return 0;
         }
@@ -773,7 +804,9 @@ uint64_t linux_compat_dispatch(uint64_t num,
         if (n > (size_t)out_len) {
             n = (size_t)out_len;
         }
-        memcpy(out, src, n);
+        if (unix_copy_to_user(out, src, n) != RDNX_OK) {
+            return (uint64_t)(-LINUX_EINVAL);
+        }
         return (uint64_t)n;
     }
     case 90: { /* chmod */
@@ -814,11 +847,10 @@ uint64_t linux_compat_dispatch(uint64_t num,
             return 0;
         }
         if (a1 == LINUX_ARCH_GET_FS) {
-            uint64_t* out = (uint64_t*)(uintptr_t)a2;
-            if (!out || !unix_user_range_ok(out, sizeof(*out))) {
+            if (!a2 || unix_copy_to_user((void*)(uintptr_t)a2, &task->tls_fs_base,
+                                         sizeof(task->tls_fs_base)) != RDNX_OK) {
                 return (uint64_t)(-LINUX_EINVAL);
             }
-            *out = task->tls_fs_base;
             return 0;
         }
         return (uint64_t)(-LINUX_ENOSYS);
@@ -895,12 +927,16 @@ uint64_t linux_compat_dispatch(uint64_t num,
             return (uint64_t)(-LINUX_EINVAL);
         }
         for (uint64_t i = 0; i < iovcnt; i++) {
-            uint64_t r = linux_compat_dispatch(0, a1, iov[i].iov_base, iov[i].iov_len, 0, 0, 0);
+            linux_iovec_u_t v;
+            if (unix_copy_from_user(&v, &iov[i], sizeof(v)) != RDNX_OK) {
+                return (total > 0) ? total : (uint64_t)(-LINUX_EINVAL);
+            }
+            uint64_t r = linux_compat_dispatch(0, a1, v.iov_base, v.iov_len, 0, 0, 0);
             if ((int64_t)r < 0) {
                 return (total > 0) ? total : r;
             }
             total += r;
-            if (r < iov[i].iov_len) {
+            if (r < v.iov_len) {
                 break;
             }
         }
@@ -914,12 +950,16 @@ uint64_t linux_compat_dispatch(uint64_t num,
             return (uint64_t)(-LINUX_EINVAL);
         }
         for (uint64_t i = 0; i < iovcnt; i++) {
-            uint64_t r = linux_compat_dispatch(1, a1, iov[i].iov_base, iov[i].iov_len, 0, 0, 0);
+            linux_iovec_u_t v;
+            if (unix_copy_from_user(&v, &iov[i], sizeof(v)) != RDNX_OK) {
+                return (total > 0) ? total : (uint64_t)(-LINUX_EINVAL);
+            }
+            uint64_t r = linux_compat_dispatch(1, a1, v.iov_base, v.iov_len, 0, 0, 0);
             if ((int64_t)r < 0) {
                 return (total > 0) ? total : r;
             }
             total += r;
-            if (r < iov[i].iov_len) {
+            if (r < v.iov_len) {
                 break;
             }
         }
@@ -948,6 +988,7 @@ uint64_t linux_compat_dispatch(uint64_t num,
         }
         uint64_t wrote = 0;
         uint64_t idx = 0;
+        uint64_t rec[8]; /* One record: names are shorter than vfs_node_t.name */
         for (vfs_node_t* ch = f->node->children; ch; ch = ch->sibling, idx++) {
             if (idx < f->pos) {
                 continue;
@@ -955,16 +996,19 @@ uint64_t linux_compat_dispatch(uint64_t num,
             size_t nlen = strlen(ch->name);
             size_t reclen = sizeof(linux_dirent_u_t) + nlen + 2; /* +NUL +dtype slot */
             reclen = (reclen + 7u) & ~7u;
-            if (wrote + reclen > out_len) {
+            if (wrote + reclen > out_len || reclen > sizeof(rec)) {
                 break;
             }
-            linux_dirent_u_t* d = (linux_dirent_u_t*)(out + wrote);
+            linux_dirent_u_t* d = (linux_dirent_u_t*)rec;
             memset(d, 0, reclen);
             d->d_ino = idx + 1;
             d->d_off = idx + 1;
             d->d_reclen = (uint16_t)reclen;
             memcpy(d->d_name, ch->name, nlen + 1);
-            out[wrote + reclen - 1] = (ch->type == VFS_NODE_DIR) ? LINUX_DT_DIR : LINUX_DT_REG;
+            ((uint8_t*)rec)[reclen - 1] = (ch->type == VFS_NODE_DIR) ? LINUX_DT_DIR : LINUX_DT_REG;
+            if (unix_copy_to_user(out + wrote, rec, reclen) != RDNX_OK) {
+                return wrote ? wrote : (uint64_t)(-LINUX_EFAULT);
+            }
             wrote += reclen;
             f->pos = idx + 1;
         }
@@ -990,6 +1034,7 @@ uint64_t linux_compat_dispatch(uint64_t num,
         }
         uint64_t wrote = 0;
         uint64_t idx = 0;
+        uint64_t rec[8]; /* One record: names are shorter than vfs_node_t.name */
         for (vfs_node_t* ch = f->node->children; ch; ch = ch->sibling, idx++) {
             if (idx < f->pos) {
                 continue;
@@ -997,21 +1042,36 @@ uint64_t linux_compat_dispatch(uint64_t num,
             size_t nlen = strlen(ch->name);
             size_t reclen = sizeof(linux_dirent64_u_t) + nlen + 1;
             reclen = (reclen + 7u) & ~7u;
-            if (wrote + reclen > out_len) {
+            if (wrote + reclen > out_len || reclen > sizeof(rec)) {
                 break;
             }
-            linux_dirent64_u_t* d = (linux_dirent64_u_t*)(out + wrote);
+            linux_dirent64_u_t* d = (linux_dirent64_u_t*)rec;
             memset(d, 0, reclen);
             d->d_ino = idx + 1;
             d->d_off = (int64_t)(idx + 1);
             d->d_reclen = (uint16_t)reclen;
             d->d_type = (ch->type == VFS_NODE_DIR) ? LINUX_DT_DIR : LINUX_DT_REG;
             memcpy(d->d_name, ch->name, nlen + 1);
+            if (unix_copy_to_user(out + wrote, rec, reclen) != RDNX_OK) {
+                return wrote ? wrote : (uint64_t)(-LINUX_EFAULT);
+            }
             wrote += reclen;
             f->pos = idx + 1;
         }
         return wrote;
     }
+    case 97: /* getrlimit */
+        return linux_ret(posix_getrlimit(a1, a2, 0, 0, 0, 0));
+    case 135: /* personality */
+        return linux_ret(unix_proc_personality(a1));
+    case 149: /* mlock */
+        return linux_ret(posix_mlock(a1, a2, 0, 0, 0, 0));
+    case 150: /* munlock */
+        return linux_ret(posix_munlock(a1, a2, 0, 0, 0, 0));
+    case 160: /* setrlimit */
+        return linux_ret(posix_setrlimit(a1, a2, 0, 0, 0, 0));
+    case 302: /* prlimit64 */
+        return linux_ret(unix_proc_prlimit(a1, a2, a3, a4));
     case 273: /* set_robust_list */
         /* Not implemented yet; keep startup paths alive. */
         return 0;
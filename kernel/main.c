@@ -19,12 +19,19 @@
 #include "common/kmod.h"
 #include "common/shell.h"
 #include "common/bootlog.h"
+#include "common/bootparam.h"
 #include "common/startup_trace.h"
 #include "common/idl_demo.h"
+#include "common/crashdump.h"
+#include "common/ddb.h"
+#include "common/gdbstub.h"
+#include "vm/vm_pageout.h"
 #include "core/boot.h"
+#include "core/clock.h"
 #include "arch/config.h"
 #include "arch/acpi.h"
 #include "arch/syscall_fast.h"
+#include "arch/smp.h"
 #include "../include/common.h"
 
 #define USER_INIT_PATH_MAX 128
@@ -52,91 +59,24 @@ const char* kernel_timer_source_name(void)
     return g_timer_use_apic ? "lapic" : "pit";
 }
 
-static bool bootarg_has_token(const char* cmdline, const char* token)
+/* Timer mode for sysinfo: the LAPIC mode name, or the source name otherwise */
+const char* kernel_timer_mode_name(void)
 {
-    if (!cmdline || !token || token[0] == '\0') {
-        return false;
-    }
-
-    size_t tok_len = strlen(token);
-    const char* p = cmdline;
-
-    while (*p) {
-        while (*p == ' ') {
-            p++;
-        }
-        if (*p == '\0') {
-            break;
-        }
-
-        const char* start = p;
-        while (*p && *p != ' ') {
-            p++;
-        }
-        size_t len = (size_t)(p - start);
-        if (len == tok_len && strncmp(start, token, tok_len) == 0) {
-            return true;
-        }
-    }
-
-    return false;
+    extern const char* apic_timer_mode_name(void);
+    return g_timer_use_apic ? apic_timer_mode_name() : kernel_timer_source_name();
 }
 
-static void bootarg_pick_init_path(char* out, size_t out_len)
-{
-    if (!out || out_len == 0) {
-        return;
-    }
-
-    const char* fallback = "/bin/init";
-    size_t i = 0;
-    while (fallback[i] && i + 1 < out_len) {
-        out[i] = fallback[i];
-        i++;
-    }
-    out[i] = '\0';
-
-    boot_info_t* bi = boot_get_info();
-    if (!bi || !bi->cmdline[0]) {
-        return;
-    }
-
-    const char* cmdline = bi->cmdline;
-    const char* key = "rdnx.init=";
-    size_t key_len = strlen(key);
-    const char* p = cmdline;
-
-    while (*p) {
-        while (*p == ' ') {
-            p++;
-        }
-        if (*p == '\0') {
-            break;
-        }
-
-        const char* start = p;
-        while (*p && *p != ' ') {
-            p++;
-        }
-        size_t len = (size_t)(p - start);
-        if (len > key_len && strncmp(start, key, key_len) == 0) {
-            size_t path_len = len - key_len;
-            if (path_len >= out_len) {
-                path_len = out_len - 1;
-            }
-            memcpy(out, start + key_len, path_len);
-            out[path_len] = '\0';
-            return;
-        }
-    }
-}
+BOOTPARAM_BOOL(bootarg_smp, "rdnx.smp", true, "Start application processors");
+BOOTPARAM_BOOL(bootarg_nosmp, "nosmp", false, "Same as rdnx.smp=0");
+BOOTPARAM_BOOL(bootarg_tickless, "rdnx.tickless", true, "Dynamic (one-shot) LAPIC tick");
+BOOTPARAM_BOOL_ALIAS(bootarg_shell, "rdnx.shell", "shell", false,
+                     "Start the kernel shell instead of /bin/init");
+BOOTPARAM_STRING(bootarg_init, "rdnx.init", "/bin/init", "First user program");
 
 static void idle_thread(void* arg)
 {
     (void)arg;
-    for (;;) {
-        __asm__ volatile ("sti; hlt" ::: "memory");
-    }
+    scheduler_idle_loop();
 }
 
 static void kernel_shell_thread(void* arg)
@@ -194,6 +134,17 @@ static int sysinit_memory(void)
     return memory_init();
 }
 
+static int sysinit_fbcon(void)
+{
+    int rc = console_attach_framebuffer();
+    if (rc == RDNX_OK) {
+        kputs("[INIT-4.1] Framebuffer console active\n");
+    } else if (rc != RDNX_E_NOTFOUND) {
+        kprintf("[INIT-4.1] Framebuffer console unavailable (%d), VGA text fallback\n", rc);
+    }
+    return RDNX_OK;
+}
+
 static int sysinit_apic(void)
 {
     extern int apic_init(void);
@@ -226,6 +177,17 @@ static int sysinit_acpi(void)
     return RDNX_OK;
 }
 
+static int sysinit_clocksource(void)
+{
+    /* HPET/TSC discovery needs ACPI; the LAPIC timer needs the TSC rate */
+    if (clocksource_init() == 0) {
+        bootlog_mark("clock", clocksource_name());
+    } else {
+        bootlog_mark("clock", "none");
+    }
+    return RDNX_OK;
+}
+
 static int sysinit_timer(void)
 {
     extern bool apic_is_available(void);
@@ -264,6 +226,20 @@ static int sysinit_scheduler(void)
     return scheduler_init();
 }
 
+static int sysinit_smp(void)
+{
+    if (!bootarg_smp || bootarg_nosmp) {
+        kputs("[SMP] disabled by boot arg\n");
+        return RDNX_OK;
+    }
+    /* AP scheduling is driven by per-CPU LAPIC timers */
+    if (!g_timer_use_apic) {
+        kputs("[SMP] LAPIC timer inactive, running on BSP only\n");
+        return RDNX_OK;
+    }
+    return smp_init();
+}
+
 static int sysinit_ipc(void)
 {
     return ipc_init();
@@ -347,6 +323,27 @@ static int sysinit_fabric(void)
     return RDNX_OK;
 }
 
+static int sysinit_ddb(void)
+{
+    /* Serial RX is routed through the APIC or PIC chosen by apic_init */
+    ddb_init();
+    return RDNX_OK;
+}
+
+static int sysinit_gdbstub(void)
+{
+    /* After ddb: with rdnx.gdb=wait this stops in the stub right here */
+    gdbstub_init();
+    return RDNX_OK;
+}
+
+static int sysinit_crashdump(void)
+{
+    /* Needs the block devices registered by fabric_init */
+    crashdump_init();
+    return RDNX_OK;
+}
+
 static int sysinit_vfs(void)
 {
     boot_info_t* bi = boot_get_info();
@@ -420,8 +417,9 @@ void kmain(uint32_t magic, void* mbi)
         kputs("[INIT-ERR] Boot init failed\n");
         panic("Boot init failed");
     }
-    startup_trace_init(boot_info.cmdline);
+    bootparam_init(boot_info.cmdline);
     bootlog_init();
+    startup_trace_init();
     bootlog_mark("boot", "done");
     kputs("[INIT] Boot done\n");
     
@@ -434,13 +432,25 @@ void kmain(uint32_t magic, void* mbi)
     if (run_sysinit_step(SI_SUB_VM, SI_ORDER_FIRST, "memory_init", sysinit_memory) != 0) {
         panic("Memory init failed");
     }
+    if (run_sysinit_step(SI_SUB_VM, SI_ORDER_SECOND, "fbcon_init", sysinit_fbcon) != 0) {
+        panic("Framebuffer console init failed");
+    }
     if (run_sysinit_step(SI_SUB_INTR, SI_ORDER_SECOND, "acpi_init", sysinit_acpi) != 0) {
         panic("ACPI init failed");
     }
     if (run_sysinit_step(SI_SUB_INTR, SI_ORDER_THIRD, "apic_init", sysinit_apic) != 0) {
         panic("APIC init failed");
     }
-    if (run_sysinit_step(SI_SUB_CLOCKS, SI_ORDER_FIRST, "timer_init", sysinit_timer) != 0) {
+    if (run_sysinit_step(SI_SUB_INTR, SI_ORDER_MIDDLE, "ddb_init", sysinit_ddb) != 0) {
+        panic("ddb init failed");
+    }
+    if (run_sysinit_step(SI_SUB_INTR, SI_ORDER_ANY, "gdbstub_init", sysinit_gdbstub) != 0) {
+        panic("gdbstub init failed");
+    }
+    if (run_sysinit_step(SI_SUB_CLOCKS, SI_ORDER_FIRST, "clocksource_init", sysinit_clocksource) != 0) {
+        panic("Clocksource init failed");
+    }
+    if (run_sysinit_step(SI_SUB_CLOCKS, SI_ORDER_SECOND, "timer_init", sysinit_timer) != 0) {
         panic("Timer init failed");
     }
     if (run_sysinit_step(SI_SUB_SCHED, SI_ORDER_FIRST, "scheduler_init", sysinit_scheduler) != 0) {
@@ -464,6 +474,9 @@ void kmain(uint32_t magic, void* mbi)
     if (run_sysinit_step(SI_SUB_DRIVERS, SI_ORDER_SECOND, "fabric_init", sysinit_fabric) != 0) {
         panic("Fabric init failed");
     }
+    if (run_sysinit_step(SI_SUB_DRIVERS, SI_ORDER_THIRD, "crashdump_init", sysinit_crashdump) != 0) {
+        panic("Crash dump init failed");
+    }
     if (run_sysinit_step(SI_SUB_VFS, SI_ORDER_FIRST, "vfs_init", sysinit_vfs) != 0) {
         panic("VFS init failed");
     }
@@ -488,17 +501,10 @@ void kmain(uint32_t magic, void* mbi)
     }
     __asm__ volatile ("" ::: "memory");
     
-    /* Set IRQL to PASSIVE */
+    /* Set IRQL to PASSIVE and enable interrupts */
     kputs("[INIT-10.2] Set IRQL\n");
     __asm__ volatile ("" ::: "memory");
-    extern volatile irql_t current_irql;
-    current_irql = IRQL_PASSIVE;
-    __asm__ volatile ("" ::: "memory");
-    
-    /* Enable interrupts */
-    kputs("[INIT-10.3] Execute sti\n");
-    __asm__ volatile ("" ::: "memory");
-    __asm__ volatile ("sti");
+    interrupts_enable();
     __asm__ volatile ("" ::: "memory");
     
     /* Re-enable timer after interrupts are enabled */
@@ -535,8 +541,16 @@ void kmain(uint32_t magic, void* mbi)
         uint32_t lvt0 = apic_timer_get_lvt_raw();
         uint32_t init0 = apic_timer_get_initial_count();
         uint32_t cur0 = apic_timer_get_current_count();
-        for (volatile int i = 0; i < 5000000; i++) {
-            __asm__ volatile ("pause");
+        if (clocksource_available()) {
+            /* Several tick periods of real time: TSC-deadline has no count-down */
+            uint64_t until = clocksource_monotonic_ns() + 50000000ULL;
+            while (clocksource_monotonic_ns() < until) {
+                __asm__ volatile ("pause");
+            }
+        } else {
+            for (volatile int i = 0; i < 5000000; i++) {
+                __asm__ volatile ("pause");
+            }
         }
         uint64_t t1 = scheduler_get_ticks();
         uint32_t ap1 = apic_timer_get_ticks();
@@ -566,18 +580,28 @@ void kmain(uint32_t magic, void* mbi)
         }
     }
 
+    /* Dynamic tick needs the one-shot LAPIC timer; PIT stays periodic */
+    if (g_timer_use_apic) {
+        if (!bootarg_tickless) {
+            kputs("[INIT-10.8] Dynamic tick disabled by boot arg\n");
+        } else if (scheduler_dyntick_enable() == RDNX_OK) {
+            kputs("[INIT-10.8] Dynamic tick enabled\n");
+        } else {
+            kputs("[INIT-10.8] Dynamic tick unsupported, periodic tick\n");
+        }
+    }
+
     kputs("[INIT-10-OK] Interrupts enabled\n");
     bootlog_mark("interrupts", "enable_done");
     __asm__ volatile ("" ::: "memory");
-    
-    bool force_kernel_shell = false;
-    boot_info_t* boot_cfg = boot_get_info();
-    if (boot_cfg) {
-        force_kernel_shell =
-            bootarg_has_token(boot_cfg->cmdline, "rdnx.shell=1") ||
-            bootarg_has_token(boot_cfg->cmdline, "shell=1");
+
+    if (run_sysinit_step(SI_SUB_SMP, SI_ORDER_FIRST, "smp_init", sysinit_smp) != 0) {
+        kputs("[SMP] bring-up failed, running on BSP only\n");
     }
-    bootarg_pick_init_path(g_user_init_path, sizeof(g_user_init_path));
+    
+    bool force_kernel_shell = bootarg_shell;
+    strncpy(g_user_init_path, bootarg_init, sizeof(g_user_init_path) - 1);
+    g_user_init_path[sizeof(g_user_init_path) - 1] = '\0';
 
     /* Step 11: Bootstrap mode selection */
     kputs("[INIT-11] Bootstrap\n");
@@ -627,7 +651,17 @@ void kmain(uint32_t magic, void* mbi)
     if (idle) {
         idle->priority = PRIORITY_MIN;
     }
+    /* One pinned idle thread per AP; releases the APs into the scheduler */
+    for (uint32_t cpu = 1; cpu < cpu_get_count(); cpu++) {
+        thread_t* ap_idle = thread_create(kernel_task, idle_thread, NULL);
+        if (!ap_idle) {
+            panic("AP idle thread create failed");
+        }
+        ap_idle->priority = PRIORITY_MIN;
+        scheduler_add_idle_thread(ap_idle, cpu);
+    }
     bootstrap_start();
+    vm_pageout_start();
     /* Keep IDL demo disabled in baseline boot path; it perturbs contract CI. */
     /* idl_demo_start(); */
     if (!primary || !idle) {
@@ -636,7 +670,7 @@ void kmain(uint32_t magic, void* mbi)
     }
 
     scheduler_add_thread(primary);
-    scheduler_add_thread(idle);
+    scheduler_add_idle_thread(idle, 0);
     bootlog_mark("threads", "created");
 
     kputs("[INIT-12.1] scheduler_start()\n");
@@ -1,14 +1,27 @@
 #include "bsd_mbuf.h"
-#include "../common/heap.h"
+#include "../common/kmem.h"
 #include "../../include/common.h"
 
+/* Created once by bsd_mbuf_init() from net_init() */
+static kmem_cache_t* bsd_mbuf_cache = NULL;
+
+int bsd_mbuf_init(void)
+{
+    if (!bsd_mbuf_cache) {
+        bsd_mbuf_cache = kmem_cache_create("mbuf", sizeof(bsd_mbuf_t), 0, NULL, NULL, NULL, 0);
+        if (!bsd_mbuf_cache) {
+            return -1;
+        }
+    }
+    return 0;
+}
+
 static bsd_mbuf_t* bsd_m_alloc(short type, uint16_t flags)
 {
-    bsd_mbuf_t* m = (bsd_mbuf_t*)kmalloc(sizeof(bsd_mbuf_t));
+    bsd_mbuf_t* m = (bsd_mbuf_t*)kmem_cache_zalloc(bsd_mbuf_cache);
     if (!m) {
         return NULL;
     }
-    memset(m, 0, sizeof(*m));
     m->m_type = (uint8_t)type;
     m->m_flags = flags;
     m->m_data = m->m_dat;
@@ -33,7 +46,7 @@ bsd_mbuf_t* bsd_m_free(bsd_mbuf_t* m)
         return NULL;
     }
     bsd_mbuf_t* next = m->m_next;
-    kfree(m);
+    kmem_cache_free(bsd_mbuf_cache, m);
     return next;
 }
 
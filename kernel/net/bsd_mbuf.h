@@ -32,6 +32,7 @@ typedef struct bsd_mbuf {
 
 #define bsd_mtod(_m, _t) ((_t)((_m)->m_data))
 
+int bsd_mbuf_init(void);
 bsd_mbuf_t* bsd_m_get(int how, short type);
 bsd_mbuf_t* bsd_m_gethdr(int how, short type);
 bsd_mbuf_t* bsd_m_free(bsd_mbuf_t* m);
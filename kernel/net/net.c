@@ -1,5 +1,6 @@
 #include "net.h"
 #include "socket.h"
+#include "bsd_mbuf.h"
 #include "../fabric/service/net_service.h"
 #include "../fabric/spin.h"
 #include "../common/heap.h"
@@ -137,6 +138,10 @@ static int net_queue_pop(net_queue_t* q, net_packet_t* out)
 
 int net_init(void)
 {
+    if (bsd_mbuf_init() != 0) {
+        kputs("[NET] mbuf cache init failed\n");
+        return -1;
+    }
     if (!loopback_queue) {
         loopback_queue = net_queue_create();
         if (!loopback_queue) {
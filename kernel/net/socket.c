@@ -854,7 +854,7 @@ int net_socket_recvfrom(net_socket_t* sock, void* buf, size_t len, sockaddr_in_t
         if (ret >= 0) {
             return ret;
         }
-        if (deadline && scheduler_get_ticks() >= deadline) {
+        if ((deadline && scheduler_get_ticks() >= deadline) || task_kill_pending(task_get_current())) {
             return -1;
         }
         fabric_netif_poll_all();
@@ -3,8 +3,12 @@
 #include "posix_uapi_compat.h"
 #include "../core/cpu.h"
 #include "../core/memory.h"
+#include "../core/clock.h"
 #include "../common/syscall.h"
 #include "../common/kmod.h"
+#include "../common/bootparam.h"
+#include "../common/kmem.h"
+#include "../common/heap.h"
 #include "../fabric/fabric.h"
 #include "../fabric/device/device.h"
 #include "../fabric/service/net_service.h"
@@ -17,6 +21,7 @@
 #include "../../include/common.h"
 #include "../../include/console.h"
 #include "../arch/config.h"
+#include "../arch/pmm.h"
 #include <stddef.h>
 
 uint64_t posix_uname(uint64_t a1,
@@ -31,10 +36,8 @@ uint64_t posix_uname(uint64_t a1,
     (void)a4;
     (void)a5;
     (void)a6;
-    utsname_t* u = (utsname_t*)(uintptr_t)a1;
-    if (!unix_user_range_ok(u, sizeof(*u))) {
-        return (uint64_t)RDNX_E_INVALID;
-    }
+    utsname_t uts;
+    utsname_t* u = &uts;
     memset(u, 0, sizeof(*u));
     u->hdr = RDNX_ABI_INIT(utsname_t);
     strncpy(u->sysname, RODNIX_SYSNAME, sizeof(u->sysname) - 1);
@@ -42,7 +45,7 @@ uint64_t posix_uname(uint64_t a1,
     strncpy(u->release, RODNIX_RELEASE, sizeof(u->release) - 1);
     strncpy(u->version, RODNIX_VERSION, sizeof(u->version) - 1);
     strncpy(u->machine, ARCH_MACHINE, sizeof(u->machine) - 1);
-    return (uint64_t)RDNX_OK;
+    return (uint64_t)unix_copy_to_user((void*)(uintptr_t)a1, u, sizeof(*u));
 }
 
 uint64_t posix_netiflist(uint64_t a1,
@@ -77,18 +80,22 @@ uint64_t posix_netiflist(uint64_t a1,
             break;
         }
         /* Fill explicit fields to keep user ABI stable across padding/layout changes. */
-        memset(&user_entries[i], 0, sizeof(user_entries[i]));
-        strncpy(user_entries[i].name, info.name, sizeof(user_entries[i].name) - 1);
-        memcpy(user_entries[i].mac, info.mac, sizeof(user_entries[i].mac));
-        user_entries[i].mtu = info.mtu;
-        user_entries[i].flags = info.flags;
-        user_entries[i].ipv4_addr = info.ipv4_addr;
-        user_entries[i].ipv4_netmask = info.ipv4_netmask;
-        user_entries[i].ipv4_gateway = info.ipv4_gateway;
-        user_entries[i].stats = info.stats;
-    }
-    if (user_count) {
-        *user_count = total;
+        fabric_netif_info_t out;
+        memset(&out, 0, sizeof(out));
+        strncpy(out.name, info.name, sizeof(out.name) - 1);
+        memcpy(out.mac, info.mac, sizeof(out.mac));
+        out.mtu = info.mtu;
+        out.flags = info.flags;
+        out.ipv4_addr = info.ipv4_addr;
+        out.ipv4_netmask = info.ipv4_netmask;
+        out.ipv4_gateway = info.ipv4_gateway;
+        out.stats = info.stats;
+        if (unix_copy_to_user(&user_entries[i], &out, sizeof(out)) != RDNX_OK) {
+            return (uint64_t)RDNX_E_INVALID;
+        }
+    }
+    if (user_count && unix_copy_to_user(user_count, &total, sizeof(total)) != RDNX_OK) {
+        return (uint64_t)RDNX_E_INVALID;
     }
     return (uint64_t)n;
 }
@@ -152,10 +159,12 @@ uint64_t posix_hwlist(uint64_t a1,
             }
         }
 
-        user_entries[i] = info;
+        if (unix_copy_to_user(&user_entries[i], &info, sizeof(info)) != RDNX_OK) {
+            return (uint64_t)RDNX_E_INVALID;
+        }
     }
-    if (user_count) {
-        *user_count = total;
+    if (user_count && unix_copy_to_user(user_count, &total, sizeof(total)) != RDNX_OK) {
+        return (uint64_t)RDNX_E_INVALID;
     }
     return (uint64_t)n;
 }
@@ -184,13 +193,23 @@ uint64_t posix_fabricls(uint64_t a1,
         return (uint64_t)RDNX_E_INVALID;
     }
 
+    fabric_node_info_t* kentries = (fabric_node_info_t*)kmalloc((size_t)max_entries * sizeof(*kentries));
+    if (!kentries) {
+        return (uint64_t)RDNX_E_NOMEM;
+    }
     uint32_t total = 0;
-    int n = fabric_node_list(user_entries, max_entries, &total);
+    int n = fabric_node_list(kentries, max_entries, &total);
     if (n < 0) {
+        kfree(kentries);
         return (uint64_t)n;
     }
-    if (user_count) {
-        *user_count = total;
+    int crc = unix_copy_to_user(user_entries, kentries, (size_t)n * sizeof(*kentries));
+    kfree(kentries);
+    if (crc != RDNX_OK) {
+        return (uint64_t)crc;
+    }
+    if (user_count && unix_copy_to_user(user_count, &total, sizeof(total)) != RDNX_OK) {
+        return (uint64_t)RDNX_E_INVALID;
     }
     return (uint64_t)n;
 }
@@ -222,17 +241,28 @@ uint64_t posix_fabricevents(uint64_t a1,
         return (uint64_t)RDNX_E_INVALID;
     }
 
+    fabric_event_t* kentries = (fabric_event_t*)kmalloc((size_t)max_entries * sizeof(*kentries));
+    if (!kentries) {
+        return (uint64_t)RDNX_E_NOMEM;
+    }
     uint32_t read = 0;
     uint32_t dropped = 0;
-    int rc = fabric_event_drain(user_entries, max_entries, &read, &dropped);
+    int rc = fabric_event_drain(kentries, max_entries, &read, &dropped);
+    if (rc != RDNX_OK) {
+        kfree(kentries);
+        return (uint64_t)rc;
+    }
+    /* Drained events are gone from the ring: a bad buffer loses them */
+    rc = unix_copy_to_user(user_entries, kentries, (size_t)read * sizeof(*kentries));
+    kfree(kentries);
     if (rc != RDNX_OK) {
         return (uint64_t)rc;
     }
-    if (user_read) {
-        *user_read = read;
+    if (user_read && unix_copy_to_user(user_read, &read, sizeof(read)) != RDNX_OK) {
+        return (uint64_t)RDNX_E_INVALID;
     }
-    if (user_dropped) {
-        *user_dropped = dropped;
+    if (user_dropped && unix_copy_to_user(user_dropped, &dropped, sizeof(dropped)) != RDNX_OK) {
+        return (uint64_t)RDNX_E_INVALID;
     }
     return (uint64_t)read;
 }
@@ -249,18 +279,21 @@ uint64_t posix_sysinfo(uint64_t a1,
     (void)a4;
     (void)a5;
     (void)a6;
-    rodnix_sysinfo_t* out = (rodnix_sysinfo_t*)(uintptr_t)a1;
-    if (!unix_user_range_ok(out, sizeof(*out))) {
+    rodnix_sysinfo_t* user_out = (rodnix_sysinfo_t*)(uintptr_t)a1;
+    if (!unix_user_range_ok(user_out, sizeof(*user_out))) {
         return (uint64_t)RDNX_E_INVALID;
     }
 
+    rodnix_sysinfo_t info;
+    rodnix_sysinfo_t* out = &info;
     memset(out, 0, sizeof(*out));
 
     strncpy(out->sysname, RODNIX_SYSNAME, sizeof(out->sysname) - 1);
     strncpy(out->release, RODNIX_RELEASE, sizeof(out->release) - 1);
     strncpy(out->version, RODNIX_VERSION, sizeof(out->version) - 1);
     strncpy(out->machine, ARCH_MACHINE, sizeof(out->machine) - 1);
-    out->uptime_us = console_get_uptime_us();
+    out->uptime_ns = console_get_uptime_ns();
+    out->uptime_us = out->uptime_ns / 1000ULL;
     {
         const char* src = console_get_uptime_source();
         if (src) {
@@ -301,12 +334,25 @@ uint64_t posix_sysinfo(uint64_t a1,
         out->oom_heap = minfo.oom_heap;
     }
 
-    extern uint64_t pmm_get_total_pages(void);
-    extern uint64_t pmm_get_free_pages(void);
-    extern uint64_t pmm_get_used_pages(void);
     out->pmm_total_pages = pmm_get_total_pages();
     out->pmm_free_pages = pmm_get_free_pages();
     out->pmm_used_pages = pmm_get_used_pages();
+    out->pmm_order_count = PMM_ORDER_COUNT;
+    for (uint32_t z = 0; z < PMM_ZONE_COUNT && z < RODNIX_PMM_ZONE_MAX; z++) {
+        pmm_zone_stats_t zs;
+        rodnix_pmm_zone_info_t* zi = &out->pmm_zones[z];
+        if (pmm_get_zone_stats((pmm_zone_t)z, &zs) != RDNX_OK) {
+            break;
+        }
+        strncpy(zi->name, pmm_zone_name((pmm_zone_t)z), sizeof(zi->name) - 1);
+        zi->total_pages = zs.total_pages;
+        zi->free_pages = zs.free_pages;
+        zi->used_pages = zs.used_pages;
+        for (uint32_t o = 0; o < PMM_ORDER_COUNT && o < RODNIX_PMM_ORDER_MAX; o++) {
+            zi->free_blocks[o] = (uint32_t)zs.free_blocks[o];
+        }
+        out->pmm_zone_count = z + 1;
+    }
 
     fabric_stats_t fstats;
     if (fabric_get_stats(&fstats) == RDNX_OK) {
@@ -323,7 +369,17 @@ uint64_t posix_sysinfo(uint64_t a1,
     out->syscall_int80_count = syscall_get_int80_count();
     out->syscall_fast_count = syscall_get_fast_count();
 
-    return (uint64_t)RDNX_OK;
+    strncpy(out->clocksource, clocksource_name(), sizeof(out->clocksource) - 1);
+    out->clocksource_hz = clocksource_frequency();
+    {
+        extern const char* kernel_timer_mode_name(void);
+        const char* mode = kernel_timer_mode_name();
+        if (mode) {
+            strncpy(out->timer_mode, mode, sizeof(out->timer_mode) - 1);
+        }
+    }
+
+    return (uint64_t)unix_copy_to_user(user_out, out, sizeof(*out));
 }
 
 uint64_t posix_scstat(uint64_t a1,
@@ -362,10 +418,12 @@ uint64_t posix_scstat(uint64_t a1,
         e.int80_count = int80;
         e.fast_count = fast;
         e.total_count = int80 + fast;
-        user_entries[i] = e;
+        if (unix_copy_to_user(&user_entries[i], &e, sizeof(e)) != RDNX_OK) {
+            return (uint64_t)RDNX_E_INVALID;
+        }
     }
-    if (user_count) {
-        *user_count = total;
+    if (user_count && unix_copy_to_user(user_count, &total, sizeof(total)) != RDNX_OK) {
+        return (uint64_t)RDNX_E_INVALID;
     }
     return (uint64_t)n;
 }
@@ -408,10 +466,12 @@ uint64_t posix_blocklist(uint64_t a1,
         out.sector_size = info.sector_size;
         out.sector_count = info.sector_count;
         out.flags = info.flags;
-        user_entries[i] = out;
+        if (unix_copy_to_user(&user_entries[i], &out, sizeof(out)) != RDNX_OK) {
+            return (uint64_t)RDNX_E_INVALID;
+        }
     }
-    if (user_count) {
-        *user_count = total;
+    if (user_count && unix_copy_to_user(user_count, &total, sizeof(total)) != RDNX_OK) {
+        return (uint64_t)RDNX_E_INVALID;
     }
     return (uint64_t)n;
 }
@@ -434,11 +494,13 @@ uint64_t posix_blockread(uint64_t a1,
     if (!name || !out || out_len == 0) {
         return (uint64_t)RDNX_E_INVALID;
     }
-    if (!unix_user_range_ok(name, 1) || !unix_user_range_ok(out, (size_t)out_len)) {
+    char kname[UNIX_PATH_MAX];
+    if (unix_copy_user_cstr(kname, sizeof(kname), name) != RDNX_OK ||
+        !unix_user_range_ok(out, (size_t)out_len)) {
         return (uint64_t)RDNX_E_INVALID;
     }
 
-    fabric_blockdev_t* dev = fabric_blockdev_find(name);
+    fabric_blockdev_t* dev = fabric_blockdev_find(kname);
     if (!dev) {
         return (uint64_t)RDNX_E_NOTFOUND;
     }
@@ -451,7 +513,9 @@ uint64_t posix_blockread(uint64_t a1,
     if (rc != RDNX_OK) {
         return (uint64_t)rc;
     }
-    memcpy(out, bounce, dev->sector_size);
+    if (unix_copy_to_user(out, bounce, dev->sector_size) != RDNX_OK) {
+        return (uint64_t)RDNX_E_INVALID;
+    }
     return (uint64_t)dev->sector_size;
 }
 
@@ -473,11 +537,13 @@ uint64_t posix_blockwrite(uint64_t a1,
     if (!name || !in || in_len == 0) {
         return (uint64_t)RDNX_E_INVALID;
     }
-    if (!unix_user_range_ok(name, 1) || !unix_user_range_ok(in, (size_t)in_len)) {
+    char kname[UNIX_PATH_MAX];
+    if (unix_copy_user_cstr(kname, sizeof(kname), name) != RDNX_OK ||
+        !unix_user_range_ok(in, (size_t)in_len)) {
         return (uint64_t)RDNX_E_INVALID;
     }
 
-    fabric_blockdev_t* dev = fabric_blockdev_find(name);
+    fabric_blockdev_t* dev = fabric_blockdev_find(kname);
     if (!dev) {
         return (uint64_t)RDNX_E_NOTFOUND;
     }
@@ -486,7 +552,9 @@ uint64_t posix_blockwrite(uint64_t a1,
     }
 
     uint8_t bounce[4096];
-    memcpy(bounce, in, dev->sector_size);
+    if (unix_copy_from_user(bounce, in, dev->sector_size) != RDNX_OK) {
+        return (uint64_t)RDNX_E_INVALID;
+    }
     int rc = fabric_blockdev_write(dev, lba, 1, bounce);
     if (rc != RDNX_OK) {
         return (uint64_t)rc;
@@ -534,10 +602,118 @@ uint64_t posix_kmodls(uint64_t a1,
         out.flags = ki.flags;
         out.builtin = ki.builtin;
         out.loaded = ki.loaded;
-        user_entries[i] = out;
+        if (unix_copy_to_user(&user_entries[i], &out, sizeof(out)) != RDNX_OK) {
+            return (uint64_t)RDNX_E_INVALID;
+        }
+    }
+    if (user_count && unix_copy_to_user(user_count, &total, sizeof(total)) != RDNX_OK) {
+        return (uint64_t)RDNX_E_INVALID;
+    }
+    return (uint64_t)n;
+}
+
+uint64_t posix_bootparams(uint64_t a1,
+                          uint64_t a2,
+                          uint64_t a3,
+                          uint64_t a4,
+                          uint64_t a5,
+                          uint64_t a6)
+{
+    (void)a4;
+    (void)a5;
+    (void)a6;
+
+    rodnix_bootparam_info_t* user_entries = (rodnix_bootparam_info_t*)(uintptr_t)a1;
+    uint32_t max_entries = (uint32_t)a2;
+    uint32_t* user_count = (uint32_t*)(uintptr_t)a3;
+    uint32_t total = bootparam_count();
+    uint32_t n = (max_entries < total) ? max_entries : total;
+
+    if (max_entries == 0 || !user_entries) {
+        return (uint64_t)RDNX_E_INVALID;
+    }
+    if (!unix_user_range_ok(user_entries, (size_t)max_entries * sizeof(*user_entries))) {
+        return (uint64_t)RDNX_E_INVALID;
+    }
+    if (user_count && !unix_user_range_ok(user_count, sizeof(uint32_t))) {
+        return (uint64_t)RDNX_E_INVALID;
+    }
+
+    for (uint32_t i = 0; i < n; i++) {
+        const bootparam_t* p = bootparam_get(i);
+        if (!p) {
+            break;
+        }
+        rodnix_bootparam_info_t out;
+        memset(&out, 0, sizeof(out));
+        strncpy(out.name, p->name, sizeof(out.name) - 1);
+        (void)bootparam_format(p, out.value, sizeof(out.value));
+        strncpy(out.defval, p->defval, sizeof(out.defval) - 1);
+        strncpy(out.desc, p->desc, sizeof(out.desc) - 1);
+        out.type = p->type;
+        out.flags = (p->flags & BOOTPARAM_F_SET) ? RODNIX_BOOTPARAM_SET : 0;
+        if (unix_copy_to_user(&user_entries[i], &out, sizeof(out)) != RDNX_OK) {
+            return (uint64_t)RDNX_E_INVALID;
+        }
+    }
+    if (user_count && unix_copy_to_user(user_count, &total, sizeof(total)) != RDNX_OK) {
+        return (uint64_t)RDNX_E_INVALID;
+    }
+    return (uint64_t)n;
+}
+
+uint64_t posix_kmemstat(uint64_t a1,
+                        uint64_t a2,
+                        uint64_t a3,
+                        uint64_t a4,
+                        uint64_t a5,
+                        uint64_t a6)
+{
+    (void)a4;
+    (void)a5;
+    (void)a6;
+
+    rodnix_kmem_cache_info_t* user_entries = (rodnix_kmem_cache_info_t*)(uintptr_t)a1;
+    uint32_t max_entries = (uint32_t)a2;
+    uint32_t* user_count = (uint32_t*)(uintptr_t)a3;
+    uint32_t total = kmem_cache_count();
+    uint32_t n = 0;
+
+    if (max_entries == 0 || !user_entries) {
+        return (uint64_t)RDNX_E_INVALID;
     }
-    if (user_count) {
-        *user_count = total;
+    if (!unix_user_range_ok(user_entries, (size_t)max_entries * sizeof(*user_entries))) {
+        return (uint64_t)RDNX_E_INVALID;
+    }
+    if (user_count && !unix_user_range_ok(user_count, sizeof(uint32_t))) {
+        return (uint64_t)RDNX_E_INVALID;
+    }
+
+    for (uint32_t i = 0; i < total && n < max_entries; i++) {
+        kmem_cache_stats_t st;
+        if (kmem_cache_get_stats(i, &st) != RDNX_OK) {
+            break;
+        }
+        rodnix_kmem_cache_info_t out;
+        memset(&out, 0, sizeof(out));
+        strncpy(out.name, st.name, sizeof(out.name) - 1);
+        out.obj_size = st.obj_size;
+        out.slab_pages = st.slab_pages;
+        out.objs_per_slab = st.objs_per_slab;
+        out.slabs = st.slabs;
+        out.objs_total = st.objs_total;
+        out.objs_inuse = st.objs_inuse;
+        out.objs_cached = st.objs_cached;
+        out.allocs = st.allocs;
+        out.frees = st.frees;
+        out.fails = st.fails;
+        if (unix_copy_to_user(&user_entries[n], &out, sizeof(out)) != RDNX_OK) {
+            return (uint64_t)RDNX_E_INVALID;
+        }
+        n++;
+    }
+    if (user_count && unix_copy_to_user(user_count, &total, sizeof(total)) != RDNX_OK) {
+        return (uint64_t)RDNX_E_INVALID;
     }
     return (uint64_t)n;
 }
@@ -554,8 +730,8 @@ uint64_t posix_kmodload(uint64_t a1,
     (void)a4;
     (void)a5;
     (void)a6;
-    const char* path = (const char*)(uintptr_t)a1;
-    if (!path || !unix_user_range_ok(path, 1)) {
+    char path[UNIX_PATH_MAX];
+    if (unix_copy_user_cstr(path, sizeof(path), (const char*)(uintptr_t)a1) != RDNX_OK) {
         return (uint64_t)RDNX_E_INVALID;
     }
     return (uint64_t)kmod_load(path);
@@ -573,8 +749,8 @@ uint64_t posix_kmodunload(uint64_t a1,
     (void)a4;
     (void)a5;
     (void)a6;
-    const char* name = (const char*)(uintptr_t)a1;
-    if (!name || !unix_user_range_ok(name, 1)) {
+    char name[UNIX_PATH_MAX];
+    if (unix_copy_user_cstr(name, sizeof(name), (const char*)(uintptr_t)a1) != RDNX_OK) {
         return (uint64_t)RDNX_E_INVALID;
     }
     return (uint64_t)kmod_unload(name);
@@ -597,22 +773,23 @@ uint64_t posix_clock_gettime(uint64_t a1,
         CLOCK_MONOTONIC_ALT = 1
     };
     int clock_id = (int)a1;
-    rdnx_timespec_t* out = (rdnx_timespec_t*)(uintptr_t)a2;
-    if (!unix_user_range_ok(out, sizeof(*out))) {
+    rdnx_timespec_t* user_out = (rdnx_timespec_t*)(uintptr_t)a2;
+    if (!unix_user_range_ok(user_out, sizeof(*user_out))) {
         return (uint64_t)RDNX_E_INVALID;
     }
-    uint64_t us = 0;
+    uint64_t ns = 0;
     if (clock_id == CLOCK_MONOTONIC || clock_id == CLOCK_MONOTONIC_ALT) {
-        us = console_get_uptime_us();
+        ns = console_get_uptime_ns();
     } else if (clock_id == CLOCK_REALTIME) {
-        us = console_get_realtime_us();
+        ns = console_get_realtime_ns();
     } else {
         /* Be permissive for early userland ABI drift: treat unknown clocks as monotonic. */
-        us = console_get_uptime_us();
+        ns = console_get_uptime_ns();
     }
-    out->tv_sec = (int64_t)(us / 1000000ULL);
-    out->tv_nsec = (int64_t)((us % 1000000ULL) * 1000ULL);
-    return (uint64_t)RDNX_OK;
+    rdnx_timespec_t ts;
+    ts.tv_sec = (int64_t)(ns / 1000000000ULL);
+    ts.tv_nsec = (int64_t)(ns % 1000000000ULL);
+    return (uint64_t)unix_copy_to_user(user_out, &ts, sizeof(ts));
 }
 
 uint64_t posix_nanosleep(uint64_t a1,
@@ -653,8 +830,8 @@ uint64_t posix_ping(uint64_t a1,
         return (uint64_t)RDNX_E_TIMEOUT;
     }
 
-    if (user_rtt_ms) {
-        *user_rtt_ms = rtt_ms;
+    if (user_rtt_ms && unix_copy_to_user(user_rtt_ms, &rtt_ms, sizeof(rtt_ms)) != RDNX_OK) {
+        return (uint64_t)RDNX_E_INVALID;
     }
     return (uint64_t)RDNX_OK;
 }
@@ -1,5 +1,18 @@
 #include "posix_sys_proc.h"
 #include "../unix/unix_layer.h"
+#include "../core/power.h"
+#include "../fs/vfs.h"
+#include "../common/security.h"
+#include "../../include/console.h"
+#include "../../include/error.h"
+
+/* reboot(2) howto bits, FreeBSD values (sys/reboot.h) */
+enum {
+    POSIX_RB_AUTOBOOT = 0,
+    POSIX_RB_NOSYNC   = 0x0004,
+    POSIX_RB_HALT     = 0x0008,
+    POSIX_RB_POWEROFF = 0x4000
+};
 
 uint64_t posix_exit(uint64_t a1,
                            uint64_t a2,
@@ -111,3 +124,80 @@ uint64_t posix_futex(uint64_t a1,
 {
     return unix_proc_futex(a1, a2, a3, a4, a5, a6);
 }
+
+uint64_t posix_reboot(uint64_t a1,
+                             uint64_t a2,
+                             uint64_t a3,
+                             uint64_t a4,
+                             uint64_t a5,
+                             uint64_t a6)
+{
+    (void)a2;
+    (void)a3;
+    (void)a4;
+    (void)a5;
+    (void)a6;
+    uint32_t howto = (uint32_t)a1;
+    if (howto & ~(uint32_t)(POSIX_RB_NOSYNC | POSIX_RB_HALT | POSIX_RB_POWEROFF)) {
+        return (uint64_t)RDNX_E_INVALID;
+    }
+    if (security_check_euid(0) != SEC_OK) {
+        return (uint64_t)RDNX_E_DENIED;
+    }
+
+    if ((howto & POSIX_RB_NOSYNC) == 0) {
+        kputs("[POWER] syncing filesystems\n");
+        int rc = vfs_sync();
+        if (rc != RDNX_OK) {
+            kprintf("[POWER] sync failed (%d)\n", rc);
+        }
+    }
+    if (howto & POSIX_RB_POWEROFF) {
+        power_off();
+    }
+    if (howto & POSIX_RB_HALT) {
+        kputs("[POWER] system halted\n");
+        power_halt();
+    }
+    power_reboot();
+}
+
+uint64_t posix_getrlimit(uint64_t a1,
+                                uint64_t a2,
+                                uint64_t a3,
+                                uint64_t a4,
+                                uint64_t a5,
+                                uint64_t a6)
+{
+    (void)a3;
+    (void)a4;
+    (void)a5;
+    (void)a6;
+    return unix_proc_getrlimit(a1, a2);
+}
+
+uint64_t posix_setrlimit(uint64_t a1,
+                                uint64_t a2,
+                                uint64_t a3,
+                                uint64_t a4,
+                                uint64_t a5,
+                                uint64_t a6)
+{
+    (void)a3;
+    (void)a4;
+    (void)a5;
+    (void)a6;
+    return unix_proc_setrlimit(a1, a2);
+}
+
+uint64_t posix_procctl(uint64_t a1,
+                              uint64_t a2,
+                              uint64_t a3,
+                              uint64_t a4,
+                              uint64_t a5,
+                              uint64_t a6)
+{
+    (void)a5;
+    (void)a6;
+    return unix_proc_procctl(a1, a2, a3, a4);
+}
@@ -1,16 +1,13 @@
 #include "posix_sys_vm.h"
 #include "posix_syscall.h"
+#include "posix_uapi_compat.h"
 #include "../fs/vfs.h"
 #include "../vm/vm_map.h"
+#include "../vm/vm_swap.h"
 #include "../unix/unix_layer.h"
-#include "../common/heap.h"
+#include "../../include/common.h"
 #include "../../include/error.h"
 
-static uint64_t vm_align_up_local(uint64_t v)
-{
-    return (v + VM_PAGE_SIZE - 1u) & ~(VM_PAGE_SIZE - 1u);
-}
-
 uint64_t posix_mmap(uint64_t a1,
                            uint64_t a2,
                            uint64_t a3,
@@ -49,8 +46,13 @@ uint64_t posix_mmap(uint64_t a1,
         prot = VM_PROT_READ;
     }
 
+    /* Exactly one of MAP_SHARED and MAP_PRIVATE, anonymous or not */
+    if ((a4 & (MAP_PRIVATE | MAP_SHARED)) == 0 ||
+        (a4 & (MAP_PRIVATE | MAP_SHARED)) == (MAP_PRIVATE | MAP_SHARED)) {
+        return (uint64_t)RDNX_E_INVALID;
+    }
+
     uint32_t flags = 0;
-    bool is_shared = (a4 & MAP_SHARED) != 0;
     if (a4 & MAP_PRIVATE) {
         flags |= VM_MAP_F_PRIVATE;
     }
@@ -61,13 +63,11 @@ uint64_t posix_mmap(uint64_t a1,
         flags |= VM_MAP_F_ANON;
     }
     if ((flags & VM_MAP_F_ANON) != 0) {
+        /* MAP_SHARED: one object that fork hands to the child as is */
         long ret = vm_task_mmap(task, a1, a2, prot, flags);
         return (uint64_t)ret;
     }
 
-    if ((a4 & (MAP_PRIVATE | MAP_SHARED)) == 0) {
-        return (uint64_t)RDNX_E_INVALID;
-    }
     int fd = (int)a5;
     if (fd < 0 || fd >= TASK_MAX_FD || task->fd_kind[fd] != UNIX_FD_KIND_VFS) {
         return (uint64_t)RDNX_E_INVALID;
@@ -80,33 +80,15 @@ uint64_t posix_mmap(uint64_t a1,
     if ((off & (VM_PAGE_SIZE - 1u)) != 0) {
         return (uint64_t)RDNX_E_INVALID;
     }
-    const uint8_t* data = file->node->inode->data;
-    uint64_t data_size = (uint64_t)file->node->inode->size;
-    if (!data) {
-        return (uint64_t)RDNX_E_INVALID;
-    }
-    if (is_shared) {
-        vm_object_t* obj = file->node->inode->mmap_object;
-        if (!obj) {
-            obj = vm_object_create(VM_OBJECT_FILE, vm_align_up_local(data_size ? data_size : VM_PAGE_SIZE));
-            if (!obj) {
-                return (uint64_t)RDNX_E_NOMEM;
-            }
-            vm_file_backing_t* fb = (vm_file_backing_t*)kmalloc(sizeof(vm_file_backing_t));
-            if (!fb) {
-                vm_object_unref(obj);
-                return (uint64_t)RDNX_E_NOMEM;
-            }
-            fb->data = data;
-            fb->size = data_size;
-            fb->file_offset = 0;
-            obj->pager_private = fb;
-            file->node->inode->mmap_object = obj;
-        }
-        long ret = vm_task_mmap_object(task, a1, a2, prot, flags, obj, off);
-        return (uint64_t)ret;
+    if ((a4 & MAP_SHARED) && (prot & VM_PROT_WRITE) && !file->writable) {
+        return (uint64_t)RDNX_E_DENIED;
+    }
+    /* Shared and private mappings both map the file's page cache object */
+    vm_object_t* obj = vfs_node_object(file->node);
+    if (!obj) {
+        return (uint64_t)RDNX_E_INVALID;
     }
-    long ret = vm_task_mmap_file(task, a1, a2, prot, flags, data, data_size, off);
+    long ret = vm_task_mmap_object(task, a1, a2, prot, flags, obj, off);
     return (uint64_t)ret;
 }
 
@@ -149,6 +131,129 @@ uint64_t posix_msync(uint64_t a1,
     return (uint64_t)rc;
 }
 
+uint64_t posix_madvise(uint64_t a1,
+                       uint64_t a2,
+                       uint64_t a3,
+                       uint64_t a4,
+                       uint64_t a5,
+                       uint64_t a6)
+{
+    (void)a4;
+    (void)a5;
+    (void)a6;
+    task_t* task = task_get_current();
+    if (!task) {
+        return (uint64_t)RDNX_E_INVALID;
+    }
+    /* MADV_* numbers are the VM_MADV_* ones */
+    return (uint64_t)vm_task_madvise(task, a1, a2, (int)a3);
+}
+
+uint64_t posix_mlock(uint64_t a1,
+                     uint64_t a2,
+                     uint64_t a3,
+                     uint64_t a4,
+                     uint64_t a5,
+                     uint64_t a6)
+{
+    (void)a3;
+    (void)a4;
+    (void)a5;
+    (void)a6;
+    task_t* task = task_get_current();
+    if (!task) {
+        return (uint64_t)RDNX_E_INVALID;
+    }
+    return (uint64_t)vm_task_mlock(task, a1, a2);
+}
+
+uint64_t posix_munlock(uint64_t a1,
+                       uint64_t a2,
+                       uint64_t a3,
+                       uint64_t a4,
+                       uint64_t a5,
+                       uint64_t a6)
+{
+    (void)a3;
+    (void)a4;
+    (void)a5;
+    (void)a6;
+    task_t* task = task_get_current();
+    if (!task) {
+        return (uint64_t)RDNX_E_INVALID;
+    }
+    return (uint64_t)vm_task_munlock(task, a1, a2);
+}
+
+uint64_t posix_mincore(uint64_t a1,
+                       uint64_t a2,
+                       uint64_t a3,
+                       uint64_t a4,
+                       uint64_t a5,
+                       uint64_t a6)
+{
+    (void)a4;
+    (void)a5;
+    (void)a6;
+    enum {
+        MINCORE_CHUNK = 256 /* pages per copy-out */
+    };
+    task_t* task = task_get_current();
+    uint8_t* user_vec = (uint8_t*)(uintptr_t)a3;
+    if (!task || (a1 & (VM_PAGE_SIZE - 1u)) != 0 || a2 + VM_PAGE_SIZE - 1u < a2) {
+        return (uint64_t)RDNX_E_INVALID;
+    }
+    uint64_t pages = (a2 + VM_PAGE_SIZE - 1u) / VM_PAGE_SIZE;
+    if (pages == 0) {
+        return RDNX_OK;
+    }
+    if (!user_vec || !unix_user_range_ok(user_vec, (size_t)pages)) {
+        return (uint64_t)RDNX_E_INVALID;
+    }
+    uint8_t vec[MINCORE_CHUNK];
+    for (uint64_t done = 0; done < pages;) {
+        uint64_t n = pages - done;
+        if (n > MINCORE_CHUNK) {
+            n = MINCORE_CHUNK;
+        }
+        int rc = vm_task_mincore(task, a1 + done * VM_PAGE_SIZE, n * VM_PAGE_SIZE, vec);
+        if (rc != RDNX_OK) {
+            return (uint64_t)rc;
+        }
+        if (unix_copy_to_user(user_vec + done, vec, (size_t)n) != RDNX_OK) {
+            return (uint64_t)RDNX_E_INVALID;
+        }
+        done += n;
+    }
+    return RDNX_OK;
+}
+
+uint64_t posix_mremap(uint64_t a1,
+                      uint64_t a2,
+                      uint64_t a3,
+                      uint64_t a4,
+                      uint64_t a5,
+                      uint64_t a6)
+{
+    (void)a6;
+    enum {
+        MREMAP_MAYMOVE = 0x1,
+        MREMAP_FIXED = 0x2
+    };
+    task_t* task = task_get_current();
+    if (!task || (a4 & ~(uint64_t)(MREMAP_MAYMOVE | MREMAP_FIXED)) != 0) {
+        return (uint64_t)RDNX_E_INVALID;
+    }
+    uint32_t flags = 0;
+    if (a4 & MREMAP_MAYMOVE) {
+        flags |= VM_MREMAP_MAYMOVE;
+    }
+    if (a4 & MREMAP_FIXED) {
+        flags |= VM_MREMAP_FIXED;
+    }
+    return (uint64_t)vm_task_mremap(task, a1, a2, a3, flags, a5);
+}
+
 uint64_t posix_brk(uint64_t a1,
                           uint64_t a2,
                           uint64_t a3,
@@ -167,3 +272,124 @@ uint64_t posix_brk(uint64_t a1,
     }
     return (uint64_t)vm_task_brk(task, a1);
 }
+
+uint64_t posix_swapon(uint64_t a1,
+                      uint64_t a2,
+                      uint64_t a3,
+                      uint64_t a4,
+                      uint64_t a5,
+                      uint64_t a6)
+{
+    (void)a2;
+    (void)a3;
+    (void)a4;
+    (void)a5;
+    (void)a6;
+    char path[UNIX_PATH_MAX];
+    if (unix_copy_user_cstr(path, sizeof(path), (const char*)(uintptr_t)a1) != RDNX_OK) {
+        return (uint64_t)RDNX_E_INVALID;
+    }
+    return (uint64_t)vm_swap_on(path);
+}
+
+uint64_t posix_swapoff(uint64_t a1,
+                       uint64_t a2,
+                       uint64_t a3,
+                       uint64_t a4,
+                       uint64_t a5,
+                       uint64_t a6)
+{
+    (void)a2;
+    (void)a3;
+    (void)a4;
+    (void)a5;
+    (void)a6;
+    char path[UNIX_PATH_MAX];
+    if (unix_copy_user_cstr(path, sizeof(path), (const char*)(uintptr_t)a1) != RDNX_OK) {
+        return (uint64_t)RDNX_E_INVALID;
+    }
+    return (uint64_t)vm_swap_off(path);
+}
+
+uint64_t posix_swapinfo(uint64_t a1,
+                        uint64_t a2,
+                        uint64_t a3,
+                        uint64_t a4,
+                        uint64_t a5,
+                        uint64_t a6)
+{
+    (void)a4;
+    (void)a5;
+    (void)a6;
+
+    rodnix_swap_info_t* user_entries = (rodnix_swap_info_t*)(uintptr_t)a1;
+    uint32_t max_entries = (uint32_t)a2;
+    uint32_t* user_count = (uint32_t*)(uintptr_t)a3;
+    uint32_t total = vm_swap_device_count();
+    uint32_t n = 0;
+
+    if (max_entries == 0 || !user_entries) {
+        return (uint64_t)RDNX_E_INVALID;
+    }
+    if (!unix_user_range_ok(user_entries, (size_t)max_entries * sizeof(*user_entries))) {
+        return (uint64_t)RDNX_E_INVALID;
+    }
+    if (user_count && !unix_user_range_ok(user_count, sizeof(uint32_t))) {
+        return (uint64_t)RDNX_E_INVALID;
+    }
+
+    for (uint32_t i = 0; i < total && n < max_entries; i++) {
+        vm_swap_info_t si;
+        if (vm_swap_get_info(i, &si) != RDNX_OK) {
+            break;
+        }
+        rodnix_swap_info_t out;
+        memset(&out, 0, sizeof(out));
+        strncpy(out.path, si.path, sizeof(out.path) - 1);
+        out.pages = si.pages;
+        out.used = si.used;
+        if (si.flags & VM_SWAP_F_FILE) {
+            out.flags |= RODNIX_SWAP_FILE;
+        }
+        if (si.flags & VM_SWAP_F_DRAINING) {
+            out.flags |= RODNIX_SWAP_DRAINING;
+        }
+        if (unix_copy_to_user(&user_entries[n], &out, sizeof(out)) != RDNX_OK) {
+            return (uint64_t)RDNX_E_INVALID;
+        }
+        n++;
+    }
+    if (user_count && unix_copy_to_user(user_count, &total, sizeof(total)) != RDNX_OK) {
+        return (uint64_t)RDNX_E_INVALID;
+    }
+    return (uint64_t)n;
+}
+
+uint64_t posix_shm_open(uint64_t a1,
+                        uint64_t a2,
+                        uint64_t a3,
+                        uint64_t a4,
+                        uint64_t a5,
+                        uint64_t a6)
+{
+    (void)a3; /* mode: there are no permissions to apply yet */
+    (void)a4;
+    (void)a5;
+    (void)a6;
+    return unix_fs_shm_open(a1, a2);
+}
+
+uint64_t posix_shm_unlink(uint64_t a1,
+                          uint64_t a2,
+                          uint64_t a3,
+                          uint64_t a4,
+                          uint64_t a5,
+                          uint64_t a6)
+{
+    (void)a2;
+    (void)a3;
+    (void)a4;
+    (void)a5;
+    (void)a6;
+    return unix_fs_shm_unlink(a1);
+}
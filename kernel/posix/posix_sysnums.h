@@ -72,8 +72,24 @@ enum {
     POSIX_SYS_SENDTO = 66,
     POSIX_SYS_RECVFROM = 67,
     POSIX_SYS_PING = 68,
+    POSIX_SYS_REBOOT = 69,
+    POSIX_SYS_BOOTPARAMS = 70,
+    POSIX_SYS_KMEMSTAT = 71,
+    POSIX_SYS_SWAPON = 72,
+    POSIX_SYS_SWAPOFF = 73,
+    POSIX_SYS_SWAPINFO = 74,
+    POSIX_SYS_SHM_OPEN = 75,
+    POSIX_SYS_SHM_UNLINK = 76,
+    POSIX_SYS_GETRLIMIT = 77,
+    POSIX_SYS_SETRLIMIT = 78,
+    POSIX_SYS_MADVISE = 79,
+    POSIX_SYS_MLOCK = 80,
+    POSIX_SYS_MUNLOCK = 81,
+    POSIX_SYS_MINCORE = 82,
+    POSIX_SYS_MREMAP = 83,
+    POSIX_SYS_PROCCTL = 84,
 };
 
-#define POSIX_SYS_LAST 68
+#define POSIX_SYS_LAST 84
 
 #endif /* _RODNIX_POSIX_SYSNUMS_H */
@@ -23,6 +23,17 @@ typedef struct hwdev_info {
     uint32_t bars[PCI_BAR_COUNT];
 } hwdev_info_t;
 
+#define RODNIX_PMM_ZONE_MAX  4
+#define RODNIX_PMM_ORDER_MAX 16
+
+typedef struct rodnix_pmm_zone_info {
+    char name[8];
+    uint64_t total_pages;
+    uint64_t free_pages;
+    uint64_t used_pages;
+    uint32_t free_blocks[RODNIX_PMM_ORDER_MAX]; /* Free buddy blocks of 2^i pages */
+} rodnix_pmm_zone_info_t;
+
 typedef struct rodnix_sysinfo {
     char sysname[32];
     char release[32];
@@ -71,6 +82,15 @@ typedef struct rodnix_sysinfo {
 
     uint64_t syscall_int80_count;
     uint64_t syscall_fast_count;
+
+    uint64_t uptime_ns;
+    uint64_t clocksource_hz;
+    char clocksource[16];
+    char timer_mode[16];
+
+    uint32_t pmm_zone_count;
+    uint32_t pmm_order_count;
+    rodnix_pmm_zone_info_t pmm_zones[RODNIX_PMM_ZONE_MAX];
 } rodnix_sysinfo_t;
 
 typedef struct rdnx_timespec {
@@ -105,4 +125,47 @@ typedef struct rodnix_kmod_info {
     uint8_t reserved1;
 } rodnix_kmod_info_t;
 
+/* rodnix_bootparam_info.type */
+#define RODNIX_BOOTPARAM_BOOL   1
+#define RODNIX_BOOTPARAM_INT    2
+#define RODNIX_BOOTPARAM_STRING 3
+
+/* rodnix_bootparam_info.flags */
+#define RODNIX_BOOTPARAM_SET    0x1u
+
+typedef struct rodnix_bootparam_info {
+    char name[32];
+    char value[64];
+    char defval[64];
+    char desc[80];
+    uint32_t type;
+    uint32_t flags;
+} rodnix_bootparam_info_t;
+
+typedef struct rodnix_kmem_cache_info {
+    char name[24];
+    uint32_t obj_size;
+    uint32_t slab_pages;
+    uint32_t objs_per_slab;
+    uint32_t slabs;
+    uint64_t objs_total;
+    uint64_t objs_inuse;
+    uint64_t objs_cached;
+    uint64_t allocs;
+    uint64_t frees;
+    uint64_t fails;
+} rodnix_kmem_cache_info_t;
+
+/* rodnix_swap_info.flags */
+#define RODNIX_SWAP_FILE     0x1u
+#define RODNIX_SWAP_DRAINING 0x2u
+
+typedef struct rodnix_swap_info {
+    char path[64];
+    uint64_t pages;
+    uint64_t used;
+    uint32_t flags;
+    uint32_t reserved;
+} rodnix_swap_info_t;
+
 #endif /* _RODNIX_POSIX_UAPI_COMPAT_H */
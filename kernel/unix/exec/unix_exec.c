@@ -50,11 +50,10 @@ static int unix_copy_user_strv(const char* const* user_vec,
     }
 
     for (int i = 0; i < max_count; i++) {
-        const void* slot = (const void*)(uintptr_t)((uintptr_t)user_vec + (uintptr_t)i * sizeof(uintptr_t));
-        if (!unix_user_range_ok(slot, sizeof(uintptr_t))) {
+        const char* uptr = NULL;
+        if (unix_copy_from_user(&uptr, &user_vec[i], sizeof(uptr)) != RDNX_OK) {
             return RDNX_E_INVALID;
         }
-        const char* uptr = user_vec[i];
         if (!uptr) {
             break;
         }
@@ -226,6 +225,8 @@ uint64_t unix_proc_spawn(uint64_t user_path_ptr, uint64_t user_argv_ptr)
     child->state = TASK_STATE_READY;
     child->parent_task_id = parent->task_id;
     task_set_ids(child, parent->uid, parent->gid, parent->euid, parent->egid);
+    memcpy(child->rlimit, parent->rlimit, sizeof(child->rlimit));
+    child->aslr_ctl = parent->aslr_ctl;
     strncpy(child->cwd, parent->cwd, sizeof(child->cwd) - 1);
     child->cwd[sizeof(child->cwd) - 1] = '\0';
 
@@ -272,12 +273,10 @@ uint64_t unix_proc_spawn(uint64_t user_path_ptr, uint64_t user_argv_ptr)
         int argv_rc = RDNX_OK;
         for (; argc < UNIX_ARG_MAX; argc++) {
             const char* uptr = NULL;
-            const void* slot = (const void*)(uintptr_t)((uintptr_t)user_argv + (uintptr_t)argc * sizeof(uintptr_t));
-            if (!unix_user_range_ok(slot, sizeof(uintptr_t))) {
+            if (unix_copy_from_user(&uptr, &user_argv[argc], sizeof(uptr)) != RDNX_OK) {
                 argv_rc = RDNX_E_INVALID;
                 break;
             }
-            uptr = user_argv[argc];
             if (!uptr) {
                 break;
             }
@@ -15,7 +15,8 @@ enum {
     UNIX_FD_CLOEXEC = 1,
     UNIX_PIPE_CAP = 4096,
     UNIX_O_NONBLOCK = 0x0004,
-    UNIX_O_CLOEXEC = 0x00100000
+    UNIX_O_CLOEXEC = 0x00100000,
+    UNIX_IO_CHUNK = 512
 };
 
 enum {
@@ -503,6 +504,78 @@ uint64_t unix_fs_open(uint64_t user_path_ptr, uint64_t flags)
     return (uint64_t)fd;
 }
 
+/*
+ * POSIX shared memory objects are files of the ramfs mounted on /dev/shm:
+ * ftruncate, mmap and close go through the regular VFS descriptor paths,
+ * and each mapping holds its own reference on the file's vm_object, so
+ * the memory survives shm_unlink until the last mapping goes away.
+ */
+#define UNIX_SHM_DIR "/dev/shm"
+
+static int unix_shm_path(uint64_t user_name_ptr, char* out, size_t out_sz)
+{
+    char name[32];
+    if (unix_copy_user_cstr(name, sizeof(name), (const char*)(uintptr_t)user_name_ptr) != RDNX_OK) {
+        return RDNX_E_INVALID;
+    }
+    /* "/name": one leading slash, no others, fits a VFS node name */
+    if (name[0] != '/' || name[1] == '\0' || strchr(name + 1, '/')) {
+        return RDNX_E_INVALID;
+    }
+    if (strcmp(name, "/.") == 0 || strcmp(name, "/..") == 0) {
+        return RDNX_E_INVALID;
+    }
+    size_t dlen = strlen(UNIX_SHM_DIR);
+    size_t nlen = strlen(name);
+    if (dlen + nlen + 1 > out_sz) {
+        return RDNX_E_INVALID;
+    }
+    memcpy(out, UNIX_SHM_DIR, dlen);
+    memcpy(out + dlen, name, nlen + 1);
+    return RDNX_OK;
+}
+
+uint64_t unix_fs_shm_open(uint64_t user_name_ptr, uint64_t flags)
+{
+    char path_buf[UNIX_PATH_MAX];
+    int rc = unix_shm_path(user_name_ptr, path_buf, sizeof(path_buf));
+    if (rc != RDNX_OK) {
+        return (uint64_t)rc;
+    }
+    task_t* task = task_get_current();
+    if (!task) {
+        return (uint64_t)RDNX_E_INVALID;
+    }
+    vfs_file_t* file = (vfs_file_t*)kmalloc(sizeof(vfs_file_t));
+    if (!file) {
+        return (uint64_t)RDNX_E_NOMEM;
+    }
+    int orc = vfs_open(path_buf, (int)flags, file);
+    if (orc != RDNX_OK) {
+        kfree(file);
+        return (uint64_t)orc;
+    }
+    int fd = task_fd_alloc(task, file);
+    if (fd < 0) {
+        vfs_close(file);
+        kfree(file);
+        return (uint64_t)RDNX_E_BUSY;
+    }
+    task->fd_kind[fd] = UNIX_FD_KIND_VFS;
+    task->fd_flags[fd] |= UNIX_FD_CLOEXEC; /* POSIX: FD_CLOEXEC is set */
+    return (uint64_t)fd;
+}
+
+uint64_t unix_fs_shm_unlink(uint64_t user_name_ptr)
+{
+    char path_buf[UNIX_PATH_MAX];
+    int rc = unix_shm_path(user_name_ptr, path_buf, sizeof(path_buf));
+    if (rc != RDNX_OK) {
+        return (uint64_t)rc;
+    }
+    return (uint64_t)vfs_unlink(path_buf);
+}
+
 uint64_t unix_fs_dup(uint64_t oldfd)
 {
     task_t* task = task_get_current();
@@ -512,7 +585,8 @@ uint64_t unix_fs_dup(uint64_t oldfd)
     }
 
     int newfd = -1;
-    for (int i = 0; i < TASK_MAX_FD; i++) {
+    int limit = task_fd_limit(task);
+    for (int i = 0; i < limit; i++) {
         if (!task->fd_table[i]) {
             newfd = i;
             break;
@@ -534,7 +608,8 @@ uint64_t unix_fs_dup2(uint64_t oldfd, uint64_t newfd)
     task_t* task = task_get_current();
     int oldi = (int)oldfd;
     int newi = (int)newfd;
-    if (!task || oldi < 0 || oldi >= TASK_MAX_FD || newi < 0 || newi >= TASK_MAX_FD || !task->fd_table[oldi]) {
+    if (!task || oldi < 0 || oldi >= TASK_MAX_FD || newi < 0 || newi >= task_fd_limit(task) ||
+        !task->fd_table[oldi]) {
         return (uint64_t)RDNX_E_INVALID;
     }
     if (oldi == newi) {
@@ -556,7 +631,8 @@ uint64_t unix_fs_dup3(uint64_t oldfd, uint64_t newfd, uint64_t flags)
     int oldi = (int)oldfd;
     int newi = (int)newfd;
     uint32_t uflags = (uint32_t)flags;
-    if (!task || oldi < 0 || oldi >= TASK_MAX_FD || newi < 0 || newi >= TASK_MAX_FD || !task->fd_table[oldi]) {
+    if (!task || oldi < 0 || oldi >= TASK_MAX_FD || newi < 0 || newi >= task_fd_limit(task) ||
+        !task->fd_table[oldi]) {
         return (uint64_t)RDNX_E_INVALID;
     }
     if (oldi == newi) {
@@ -612,9 +688,28 @@ uint64_t unix_fs_read(uint64_t fd, uint64_t user_buf_ptr, uint64_t len)
     }
 
     if (task->fd_kind[fdi] == UNIX_FD_KIND_VFS) {
+        /* vfs_read may block: bounce through a kernel buffer, no open window */
         vfs_file_t* file = (vfs_file_t*)h;
-        int ret = vfs_read(file, buf, n);
-        return (uint64_t)ret;
+        uint8_t kbuf[UNIX_IO_CHUNK];
+        size_t done = 0;
+        while (done < n) {
+            size_t chunk = n - done;
+            if (chunk > sizeof(kbuf)) {
+                chunk = sizeof(kbuf);
+            }
+            int ret = vfs_read(file, kbuf, chunk);
+            if (ret < 0) {
+                return (done > 0) ? (uint64_t)done : (uint64_t)ret;
+            }
+            if (unix_copy_to_user((uint8_t*)buf + done, kbuf, (size_t)ret) != RDNX_OK) {
+                return (uint64_t)RDNX_E_INVALID;
+            }
+            done += (size_t)ret;
+            if ((size_t)ret < chunk) {
+                break;
+            }
+        }
+        return (uint64_t)done;
     }
 
     if (task->fd_kind[fdi] == UNIX_FD_KIND_PIPE_R) {
@@ -643,13 +738,16 @@ uint64_t unix_fs_read(uint64_t fd, uint64_t user_buf_ptr, uint64_t len)
             unix_pipe_unlock(old);
 
             if (have_byte) {
-                out[done++] = ch;
+                if (unix_copy_to_user(out + done, &ch, 1) != RDNX_OK) {
+                    return (uint64_t)RDNX_E_INVALID;
+                }
+                done++;
                 continue;
             }
             if (writers == 0) {
                 break;
             }
-            if (done > 0) {
+            if (done > 0 || task_kill_pending(task)) {
                 break;
             }
             scheduler_yield();
@@ -684,8 +782,26 @@ uint64_t unix_fs_write(uint64_t fd, uint64_t user_buf_ptr, uint64_t len)
 
     if (task->fd_kind[fdi] == UNIX_FD_KIND_VFS) {
         vfs_file_t* file = (vfs_file_t*)h;
-        int ret = vfs_write(file, buf, n);
-        return (uint64_t)ret;
+        uint8_t kbuf[UNIX_IO_CHUNK];
+        size_t done = 0;
+        while (done < n) {
+            size_t chunk = n - done;
+            if (chunk > sizeof(kbuf)) {
+                chunk = sizeof(kbuf);
+            }
+            if (unix_copy_from_user(kbuf, (const uint8_t*)buf + done, chunk) != RDNX_OK) {
+                return (uint64_t)RDNX_E_INVALID;
+            }
+            int ret = vfs_write(file, kbuf, chunk);
+            if (ret < 0) {
+                return (done > 0) ? (uint64_t)done : (uint64_t)ret;
+            }
+            done += (size_t)ret;
+            if ((size_t)ret < chunk) {
+                break;
+            }
+        }
+        return (uint64_t)done;
     }
 
     if (task->fd_kind[fdi] == UNIX_FD_KIND_PIPE_W) {
@@ -700,12 +816,16 @@ uint64_t unix_fs_write(uint64_t fd, uint64_t user_buf_ptr, uint64_t len)
             uint32_t readers;
             uint32_t count;
             bool pushed = false;
+            uint8_t ch = 0;
 
+            if (unix_copy_from_user(&ch, in + done, 1) != RDNX_OK) {
+                return (uint64_t)RDNX_E_INVALID;
+            }
             irql_t old = unix_pipe_lock();
             readers = p->readers;
             count = p->count;
             if (readers > 0 && count < UNIX_PIPE_CAP) {
-                p->data[p->head] = in[done];
+                p->data[p->head] = ch;
                 p->head = (p->head + 1u) % UNIX_PIPE_CAP;
                 p->count++;
                 pushed = true;
@@ -719,7 +839,7 @@ uint64_t unix_fs_write(uint64_t fd, uint64_t user_buf_ptr, uint64_t len)
                 done++;
                 continue;
             }
-            if (done > 0) {
+            if (done > 0 || task_kill_pending(task)) {
                 break;
             }
             scheduler_yield();
@@ -813,8 +933,7 @@ uint64_t unix_fs_getcwd(uint64_t user_buf_ptr, uint64_t size)
     if (len + 1 > n || !unix_user_range_ok(out, n)) {
         return (uint64_t)RDNX_E_INVALID;
     }
-    memcpy(out, task->cwd, len + 1);
-    return (uint64_t)RDNX_OK;
+    return (uint64_t)unix_copy_to_user(out, task->cwd, len + 1);
 }
 
 uint64_t unix_fs_mkdir(uint64_t user_path_ptr)
@@ -897,21 +1016,26 @@ uint64_t unix_fs_ioctl(uint64_t fd, uint64_t request, uint64_t user_arg_ptr)
             if (!out || !unix_user_range_ok(out, sizeof(*out))) {
                 return (uint64_t)RDNX_E_INVALID;
             }
-            memset(out, 0, sizeof(*out));
-            out->c_lflag = tty_console_get_lflag();
+            unix_termios_u_t t;
+            memset(&t, 0, sizeof(t));
+            t.c_lflag = tty_console_get_lflag();
             for (uint32_t i = 0; i < 20; i++) {
-                out->c_cc[i] = tty_console_get_cc(i);
+                t.c_cc[i] = tty_console_get_cc(i);
             }
-            return (uint64_t)RDNX_OK;
+            return (uint64_t)unix_copy_to_user(out, &t, sizeof(t));
         }
         case UNIX_TTY_IOCTL_SETATTR: {
             const unix_termios_u_t* in = (const unix_termios_u_t*)(uintptr_t)user_arg_ptr;
             if (!in || !unix_user_range_ok(in, sizeof(*in))) {
                 return (uint64_t)RDNX_E_INVALID;
             }
-            tty_console_set_lflag(in->c_lflag);
+            unix_termios_u_t t;
+            if (unix_copy_from_user(&t, in, sizeof(t)) != RDNX_OK) {
+                return (uint64_t)RDNX_E_INVALID;
+            }
+            tty_console_set_lflag(t.c_lflag);
             for (uint32_t i = 0; i < 20; i++) {
-                tty_console_set_cc(i, in->c_cc[i]);
+                tty_console_set_cc(i, t.c_cc[i]);
             }
             return (uint64_t)RDNX_OK;
         }
@@ -938,9 +1062,11 @@ uint64_t unix_fs_stat(uint64_t user_path_ptr, uint64_t user_stat_ptr)
     if (rc != RDNX_OK) {
         return (uint64_t)rc;
     }
-    ustat->st_mode = st.mode;
-    ustat->st_size = (int64_t)st.size;
-    return (uint64_t)RDNX_OK;
+    unix_stat_u_t out;
+    memset(&out, 0, sizeof(out));
+    out.st_mode = st.mode;
+    out.st_size = (int64_t)st.size;
+    return (uint64_t)unix_copy_to_user(ustat, &out, sizeof(out));
 }
 
 uint64_t unix_fs_fstat(uint64_t fd, uint64_t user_stat_ptr)
@@ -968,9 +1094,11 @@ uint64_t unix_fs_fstat(uint64_t fd, uint64_t user_stat_ptr)
     if (rc != RDNX_OK) {
         return (uint64_t)rc;
     }
-    ustat->st_mode = st.mode;
-    ustat->st_size = (int64_t)st.size;
-    return (uint64_t)RDNX_OK;
+    unix_stat_u_t out;
+    memset(&out, 0, sizeof(out));
+    out.st_mode = st.mode;
+    out.st_size = (int64_t)st.size;
+    return (uint64_t)unix_copy_to_user(ustat, &out, sizeof(out));
 }
 
 uint64_t unix_fs_fcntl(uint64_t fd, uint64_t cmd, uint64_t arg)
@@ -1094,7 +1222,14 @@ uint64_t unix_fs_poll(uint64_t user_fds_ptr, uint64_t nfds, int64_t timeout_ms)
     for (;;) {
         int ready = 0;
         for (uint64_t i = 0; i < nfds; i++) {
-            ready += unix_poll_one(task, &pfds[i]);
+            unix_pollfd_u_t pfd;
+            if (unix_copy_from_user(&pfd, &pfds[i], sizeof(pfd)) != RDNX_OK) {
+                return (uint64_t)RDNX_E_INVALID;
+            }
+            ready += unix_poll_one(task, &pfd);
+            if (unix_copy_to_user(&pfds[i].revents, &pfd.revents, sizeof(pfd.revents)) != RDNX_OK) {
+                return (uint64_t)RDNX_E_INVALID;
+            }
         }
         if (ready > 0) {
             return (uint64_t)ready;
@@ -1105,6 +1240,9 @@ uint64_t unix_fs_poll(uint64_t user_fds_ptr, uint64_t nfds, int64_t timeout_ms)
         if (timeout_ms > 0 && scheduler_get_ticks() >= deadline) {
             return 0;
         }
+        if (task_kill_pending(task_get_current())) {
+            return 0;
+        }
         scheduler_yield();
     }
 }
@@ -1130,35 +1268,26 @@ uint64_t unix_fs_select(uint64_t nfds,
     unix_fdset_u_t in_r = {0}, in_w = {0}, in_e = {0};
     unix_fdset_u_t out_r = {0}, out_w = {0}, out_e = {0};
 
-    if (user_r) {
-        if (!unix_user_range_ok(user_r, sizeof(*user_r))) {
-            return (uint64_t)RDNX_E_INVALID;
-        }
-        in_r = *user_r;
+    if (user_r && unix_copy_from_user(&in_r, user_r, sizeof(in_r)) != RDNX_OK) {
+        return (uint64_t)RDNX_E_INVALID;
     }
-    if (user_w) {
-        if (!unix_user_range_ok(user_w, sizeof(*user_w))) {
-            return (uint64_t)RDNX_E_INVALID;
-        }
-        in_w = *user_w;
+    if (user_w && unix_copy_from_user(&in_w, user_w, sizeof(in_w)) != RDNX_OK) {
+        return (uint64_t)RDNX_E_INVALID;
     }
-    if (user_e) {
-        if (!unix_user_range_ok(user_e, sizeof(*user_e))) {
-            return (uint64_t)RDNX_E_INVALID;
-        }
-        in_e = *user_e;
+    if (user_e && unix_copy_from_user(&in_e, user_e, sizeof(in_e)) != RDNX_OK) {
+        return (uint64_t)RDNX_E_INVALID;
     }
 
     int64_t timeout_ms = -1;
     if (user_timeout_ptr != 0) {
-        const unix_timeval_u_t* tv = (const unix_timeval_u_t*)(uintptr_t)user_timeout_ptr;
-        if (!unix_user_range_ok(tv, sizeof(*tv))) {
+        unix_timeval_u_t tv;
+        if (unix_copy_from_user(&tv, (const void*)(uintptr_t)user_timeout_ptr, sizeof(tv)) != RDNX_OK) {
             return (uint64_t)RDNX_E_INVALID;
         }
-        if (tv->tv_sec < 0 || tv->tv_usec < 0 || tv->tv_usec >= 1000000) {
+        if (tv.tv_sec < 0 || tv.tv_usec < 0 || tv.tv_usec >= 1000000) {
             return (uint64_t)RDNX_E_INVALID;
         }
-        uint64_t total_ms = (uint64_t)tv->tv_sec * 1000ULL + (uint64_t)((tv->tv_usec + 999) / 1000);
+        uint64_t total_ms = (uint64_t)tv.tv_sec * 1000ULL + (uint64_t)((tv.tv_usec + 999) / 1000);
         timeout_ms = (int64_t)total_ms;
     }
 
@@ -1216,14 +1345,14 @@ uint64_t unix_fs_select(uint64_t nfds,
             }
         }
 
-        if (user_r) {
-            * (unix_fdset_u_t*) user_r = out_r;
+        if (user_r && unix_copy_to_user((void*)user_r, &out_r, sizeof(out_r)) != RDNX_OK) {
+            return (uint64_t)RDNX_E_INVALID;
         }
-        if (user_w) {
-            * (unix_fdset_u_t*) user_w = out_w;
+        if (user_w && unix_copy_to_user((void*)user_w, &out_w, sizeof(out_w)) != RDNX_OK) {
+            return (uint64_t)RDNX_E_INVALID;
         }
-        if (user_e) {
-            * (unix_fdset_u_t*) user_e = out_e;
+        if (user_e && unix_copy_to_user((void*)user_e, &out_e, sizeof(out_e)) != RDNX_OK) {
+            return (uint64_t)RDNX_E_INVALID;
         }
 
         if (ready > 0) {
@@ -1235,6 +1364,9 @@ uint64_t unix_fs_select(uint64_t nfds,
         if (timeout_ms > 0 && scheduler_get_ticks() >= deadline) {
             return 0;
         }
+        if (task_kill_pending(task_get_current())) {
+            return 0;
+        }
         scheduler_yield();
     }
 }
@@ -1273,8 +1405,12 @@ uint64_t unix_fs_pipe(uint64_t user_pipefd_ptr)
     }
     task->fd_kind[fd_w] = UNIX_FD_KIND_PIPE_W;
 
-    out[0] = fd_r;
-    out[1] = fd_w;
+    int fds[2] = { fd_r, fd_w };
+    if (unix_copy_to_user(out, fds, sizeof(fds)) != RDNX_OK) {
+        unix_fd_release(task, fd_w);
+        unix_fd_release(task, fd_r);
+        return (uint64_t)RDNX_E_INVALID;
+    }
     return (uint64_t)RDNX_OK;
 }
 
@@ -1293,12 +1429,12 @@ uint64_t unix_fs_pipe2(uint64_t user_pipefd_ptr, uint64_t flags)
     }
 
     task_t* task = task_get_current();
-    int* out = (int*)(uintptr_t)user_pipefd_ptr;
-    if (!task || !out || !unix_user_range_ok(out, sizeof(int) * 2u)) {
+    int fds[2];
+    if (!task || unix_copy_from_user(fds, (const void*)(uintptr_t)user_pipefd_ptr, sizeof(fds)) != RDNX_OK) {
         return (uint64_t)RDNX_E_INVALID;
     }
-    int fd_r = out[0];
-    int fd_w = out[1];
+    int fd_r = fds[0];
+    int fd_w = fds[1];
     if (fd_r >= 0 && fd_r < TASK_MAX_FD && task->fd_table[fd_r]) {
         task->fd_flags[fd_r] |= UNIX_FD_CLOEXEC;
     }
@@ -1334,36 +1470,38 @@ uint64_t unix_fs_bind(uint64_t fd, uint64_t user_addr_ptr)
 {
     task_t* task = task_get_current();
     int fdi = (int)fd;
-    sockaddr_in_t* addr = (sockaddr_in_t*)(uintptr_t)user_addr_ptr;
+    sockaddr_in_t addr;
     if (!task || fdi < 0 || fdi >= TASK_MAX_FD || task->fd_kind[fdi] != UNIX_FD_KIND_SOCKET) {
         return (uint64_t)RDNX_E_INVALID;
     }
-    if (!addr || !unix_user_range_ok(addr, sizeof(*addr))) {
+    if (!user_addr_ptr ||
+        unix_copy_from_user(&addr, (const void*)(uintptr_t)user_addr_ptr, sizeof(addr)) != RDNX_OK) {
         return (uint64_t)RDNX_E_INVALID;
     }
     net_socket_t* sock = (net_socket_t*)task_fd_get(task, fdi);
     if (!sock) {
         return (uint64_t)RDNX_E_INVALID;
     }
-    return (net_socket_bind(sock, addr) == 0) ? (uint64_t)RDNX_OK : (uint64_t)RDNX_E_INVALID;
+    return (net_socket_bind(sock, &addr) == 0) ? (uint64_t)RDNX_OK : (uint64_t)RDNX_E_INVALID;
 }
 
 uint64_t unix_fs_connect(uint64_t fd, uint64_t user_addr_ptr)
 {
     task_t* task = task_get_current();
     int fdi = (int)fd;
-    sockaddr_in_t* addr = (sockaddr_in_t*)(uintptr_t)user_addr_ptr;
+    sockaddr_in_t addr;
     if (!task || fdi < 0 || fdi >= TASK_MAX_FD || task->fd_kind[fdi] != UNIX_FD_KIND_SOCKET) {
         return (uint64_t)RDNX_E_INVALID;
     }
-    if (!addr || !unix_user_range_ok(addr, sizeof(*addr))) {
+    if (!user_addr_ptr ||
+        unix_copy_from_user(&addr, (const void*)(uintptr_t)user_addr_ptr, sizeof(addr)) != RDNX_OK) {
         return (uint64_t)RDNX_E_INVALID;
     }
     net_socket_t* sock = (net_socket_t*)task_fd_get(task, fdi);
     if (!sock) {
         return (uint64_t)RDNX_E_INVALID;
     }
-    return (net_socket_connect(sock, addr) == 0) ? (uint64_t)RDNX_OK : (uint64_t)RDNX_E_INVALID;
+    return (net_socket_connect(sock, &addr) == 0) ? (uint64_t)RDNX_OK : (uint64_t)RDNX_E_INVALID;
 }
 
 uint64_t unix_fs_sendto(uint64_t fd,
@@ -1378,7 +1516,7 @@ uint64_t unix_fs_sendto(uint64_t fd,
     int fdi = (int)fd;
     const void* buf = (const void*)(uintptr_t)user_buf_ptr;
     size_t n = (size_t)len;
-    sockaddr_in_t* dst = (sockaddr_in_t*)(uintptr_t)user_dst_addr_ptr;
+    sockaddr_in_t dst;
     if (!task || fdi < 0 || fdi >= TASK_MAX_FD || task->fd_kind[fdi] != UNIX_FD_KIND_SOCKET) {
         return (uint64_t)RDNX_E_INVALID;
     }
@@ -1388,7 +1526,8 @@ uint64_t unix_fs_sendto(uint64_t fd,
     if (!unix_user_io_range_mapped(task, buf, n, false)) {
         return (uint64_t)RDNX_E_INVALID;
     }
-    if (!dst || user_dst_len < sizeof(sockaddr_in_t) || !unix_user_range_ok(dst, sizeof(*dst))) {
+    if (!user_dst_addr_ptr || user_dst_len < sizeof(sockaddr_in_t) ||
+        unix_copy_from_user(&dst, (const void*)(uintptr_t)user_dst_addr_ptr, sizeof(dst)) != RDNX_OK) {
         return (uint64_t)RDNX_E_INVALID;
     }
     net_socket_t* sock = (net_socket_t*)task_fd_get(task, fdi);
@@ -1396,7 +1535,17 @@ uint64_t unix_fs_sendto(uint64_t fd,
         return (uint64_t)RDNX_E_INVALID;
     }
 
-    int rc = net_socket_sendto(sock, buf, n, dst);
+    /* Datagrams go out in one piece: bounce the whole payload */
+    void* kbuf = kmalloc(n);
+    if (!kbuf) {
+        return (uint64_t)RDNX_E_NOMEM;
+    }
+    if (unix_copy_from_user(kbuf, buf, n) != RDNX_OK) {
+        kfree(kbuf);
+        return (uint64_t)RDNX_E_INVALID;
+    }
+    int rc = net_socket_sendto(sock, kbuf, n, &dst);
+    kfree(kbuf);
     if (rc < 0) {
         return (uint64_t)RDNX_E_INVALID;
     }
@@ -1433,9 +1582,22 @@ uint64_t unix_fs_recvfrom(uint64_t fd,
         return (uint64_t)RDNX_E_INVALID;
     }
 
-    int rc = net_socket_recvfrom(sock, buf, n, src, timeout_ms);
+    /* The receive may block: no uaccess window across it */
+    void* kbuf = kmalloc(n);
+    if (!kbuf) {
+        return (uint64_t)RDNX_E_NOMEM;
+    }
+    sockaddr_in_t ksrc;
+    memset(&ksrc, 0, sizeof(ksrc));
+    int rc = net_socket_recvfrom(sock, kbuf, n, src ? &ksrc : NULL, timeout_ms);
     if (rc < 0) {
+        kfree(kbuf);
         return (uint64_t)RDNX_E_NOTFOUND;
     }
+    int crc = unix_copy_to_user(buf, kbuf, (size_t)rc);
+    kfree(kbuf);
+    if (crc != RDNX_OK || (src && unix_copy_to_user(src, &ksrc, sizeof(ksrc)) != RDNX_OK)) {
+        return (uint64_t)RDNX_E_INVALID;
+    }
     return (uint64_t)rc;
 }
@@ -14,19 +14,21 @@ typedef struct {
     size_t cap_bytes;
     size_t used_bytes;
     uint64_t next_ino;
+    bool fault;
 } unix_readdir_ctx_t;
 
 static void unix_readdir_cb(const vfs_node_t* node, void* ctx)
 {
     unix_readdir_ctx_t* c = (unix_readdir_ctx_t*)ctx;
-    if (!node || !c || !c->out) {
+    if (!node || !c || !c->out || c->fault) {
         return;
     }
     if (c->used_bytes + sizeof(unix_dirent_u_t) > c->cap_bytes) {
         return;
     }
 
-    unix_dirent_u_t* de = (unix_dirent_u_t*)((uint8_t*)c->out + c->used_bytes);
+    unix_dirent_u_t entry;
+    unix_dirent_u_t* de = &entry;
     memset(de, 0, sizeof(*de));
     de->d_fileno = c->next_ino++;
     de->d_reclen = (uint16_t)sizeof(*de);
@@ -51,6 +53,10 @@ static void unix_readdir_cb(const vfs_node_t* node, void* ctx)
     memcpy(de->d_name, node->name, nlen);
     de->d_name[nlen] = '\0';
     de->d_namlen = (uint8_t)nlen;
+    if (unix_copy_to_user((uint8_t*)c->out + c->used_bytes, de, sizeof(*de)) != RDNX_OK) {
+        c->fault = true;
+        return;
+    }
     c->used_bytes += sizeof(*de);
 }
 
@@ -80,5 +86,8 @@ uint64_t unix_fs_readdir(uint64_t user_path_ptr, uint64_t user_entries_ptr, uint
     if (rc != RDNX_OK) {
         return (uint64_t)rc;
     }
+    if (ctx.fault) {
+        return (uint64_t)RDNX_E_INVALID;
+    }
     return (uint64_t)ctx.used_bytes;
 }
@@ -22,11 +22,31 @@ typedef struct unix_sigaction_u {
     uint64_t sa_mask;
 } unix_sigaction_u_t;
 
+/* struct rlimit: the same layout for the native and the Linux ABI */
+typedef struct unix_rlimit_u {
+    uint64_t rlim_cur;
+    uint64_t rlim_max;
+} unix_rlimit_u_t;
+
+/* procctl(2) ASLR commands (FreeBSD numbering) and personality(2) bits */
+enum {
+    UNIX_P_PID = 0,
+    UNIX_PROC_ASLR_FORCE_ENABLE = 1,
+    UNIX_PROC_ASLR_FORCE_DISABLE = 2,
+    UNIX_PROC_ASLR_NOFORCE = 3,
+    UNIX_PROC_ASLR_CTL = 13,
+    UNIX_PROC_ASLR_STATUS = 14,
+    UNIX_ADDR_NO_RANDOMIZE = 0x0040000
+};
+#define UNIX_PROC_ASLR_ACTIVE 0x80000000u
+#define UNIX_PERSONALITY_QUERY 0xffffffffu
+
 enum {
     UNIX_SIG_DFL = 0,
     UNIX_SIG_IGN = 1,
     UNIX_SIG_MAX = 31,
-    UNIX_SIGKILL = 9
+    UNIX_SIGKILL = 9,
+    UNIX_SIGXCPU = 24
 };
 
 enum {
@@ -107,6 +127,9 @@ static void unix_signal_save_frame(task_t* task, const interrupt_frame_t* frame)
     task->sig_saved.r13 = frame->r13;
     task->sig_saved.r14 = frame->r14;
     task->sig_saved.r15 = frame->r15;
+    /* Handler starts with clean FPU/SIMD state; the interrupted one is kept. */
+    cpu_fpu_save(task->sig_fpu_state);
+    cpu_fpu_reset();
 }
 
 static uint64_t unix_signal_restore_frame(task_t* task, interrupt_frame_t* frame)
@@ -133,6 +156,7 @@ static uint64_t unix_signal_restore_frame(task_t* task, interrupt_frame_t* frame
     frame->r13 = task->sig_saved.r13;
     frame->r14 = task->sig_saved.r14;
     frame->r15 = task->sig_saved.r15;
+    cpu_fpu_restore(task->sig_fpu_state);
 
     task->sig_in_handler = 0;
     task->sig_pending = 0;
@@ -175,6 +199,10 @@ uint64_t unix_proc_exit(uint64_t status)
     task_t* task = task_get_current();
     if (task) {
         unix_proc_close_fds(task);
+        if (task->thread_count <= 1) {
+            /* A zombie holds no user memory (the OOM killer counts on it) */
+            vm_task_destroy(task);
+        }
         task->exit_code = (int32_t)status;
         task->exited = 1;
         task->state = TASK_STATE_ZOMBIE;
@@ -189,6 +217,39 @@ uint64_t unix_proc_exit(uint64_t status)
     return 0;
 }
 
+/*
+ * RLIMIT_CPU: SIGXCPU once the soft limit is reached and then every
+ * further CPU second, SIGKILL at the hard limit. CPU time is the tick
+ * count of the whole thread group.
+ */
+static void unix_rlimit_cpu_check(task_t* task)
+{
+    const task_rlimit_t* rl = &task->rlimit[TASK_RLIMIT_CPU];
+    if (rl->cur == TASK_RLIM_INFINITY) {
+        return;
+    }
+    uint64_t secs = task->thread_group.cpu_ticks * scheduler_tick_ns() / 1000000000ULL;
+    if (secs >= rl->max) {
+        unix_proc_exit(128u + UNIX_SIGKILL);
+        return;
+    }
+    uint64_t due = task->rlimit_cpu_next ? task->rlimit_cpu_next : rl->cur;
+    if (secs >= due) {
+        task->rlimit_cpu_next = secs + 1u;
+        if (task->sig_pending == 0) {
+            task->sig_pending = UNIX_SIGXCPU;
+        }
+    }
+}
+
+void unix_proc_kill_checkpoint(void)
+{
+    task_t* task = task_get_current();
+    if (task && task->oom_killed) {
+        unix_proc_exit(128u + UNIX_SIGKILL);
+    }
+}
+
 void unix_proc_signal_checkpoint(void)
 {
     task_t* task = task_get_current();
@@ -196,6 +257,8 @@ void unix_proc_signal_checkpoint(void)
     if (!task || !thr) {
         return;
     }
+    unix_proc_kill_checkpoint();
+    unix_rlimit_cpu_check(task);
     if (task->sig_pending == 0 || task->sig_pending > UNIX_SIG_MAX || task->sig_in_handler) {
         return;
     }
@@ -230,8 +293,19 @@ void unix_proc_signal_checkpoint(void)
         return;
     }
 
+    if (!task->sig_fpu_state) {
+        task->sig_fpu_state = cpu_fpu_state_alloc();
+        if (!task->sig_fpu_state) {
+            unix_proc_exit(128u + sig);
+            return;
+        }
+    }
+
+    if (unix_copy_to_user(ret_addr, &restorer, sizeof(restorer)) != RDNX_OK) {
+        unix_proc_exit(128u + sig);
+        return;
+    }
     unix_signal_save_frame(task, frame);
-    *ret_addr = restorer;
     frame->rsp = new_rsp;
     frame->rip = handler;
     frame->rdi = sig;
@@ -276,23 +350,26 @@ uint64_t unix_proc_sigaction(uint64_t signum, uint64_t user_act_ptr, uint64_t us
     }
 
     if (old_act) {
-        if (!unix_user_range_ok(old_act, sizeof(*old_act))) {
+        unix_sigaction_u_t act;
+        memset(&act, 0, sizeof(act));
+        act.sa_handler = task->sigaction[sig].handler;
+        act.sa_flags = task->sigaction[sig].flags;
+        act.sa_restorer = task->sigaction[sig].restorer;
+        act.sa_mask = task->sigaction[sig].mask;
+        if (unix_copy_to_user(old_act, &act, sizeof(act)) != RDNX_OK) {
             return (uint64_t)RDNX_E_INVALID;
         }
-        old_act->sa_handler = task->sigaction[sig].handler;
-        old_act->sa_flags = task->sigaction[sig].flags;
-        old_act->sa_restorer = task->sigaction[sig].restorer;
-        old_act->sa_mask = task->sigaction[sig].mask;
     }
 
     if (new_act) {
-        if (!unix_user_range_ok(new_act, sizeof(*new_act))) {
+        unix_sigaction_u_t act;
+        if (unix_copy_from_user(&act, new_act, sizeof(act)) != RDNX_OK) {
             return (uint64_t)RDNX_E_INVALID;
         }
-        task->sigaction[sig].handler = new_act->sa_handler;
-        task->sigaction[sig].flags = new_act->sa_flags;
-        task->sigaction[sig].restorer = new_act->sa_restorer;
-        task->sigaction[sig].mask = new_act->sa_mask;
+        task->sigaction[sig].handler = act.sa_handler;
+        task->sigaction[sig].flags = act.sa_flags;
+        task->sigaction[sig].restorer = act.sa_restorer;
+        task->sigaction[sig].mask = act.sa_mask;
     }
 
     return (uint64_t)RDNX_OK;
@@ -309,6 +386,157 @@ uint64_t unix_proc_sigreturn(void)
     return unix_signal_restore_frame(task, frame);
 }
 
+/*
+ * Resource limits. Lowering is always allowed, raising the hard limit
+ * needs euid 0; RLIMIT_NOFILE cannot exceed the fd table.
+ */
+static int unix_rlimit_set(task_t* task, uint32_t resource, const unix_rlimit_u_t* in)
+{
+    if (in->rlim_cur > in->rlim_max) {
+        return RDNX_E_INVALID;
+    }
+    task_rlimit_t* rl = &task->rlimit[resource];
+    if (in->rlim_max > rl->max && task_get_euid(task_get_current()) != 0) {
+        return RDNX_E_DENIED;
+    }
+    if (resource == TASK_RLIMIT_NOFILE && in->rlim_max > TASK_MAX_FD) {
+        return RDNX_E_DENIED;
+    }
+    rl->cur = in->rlim_cur;
+    rl->max = in->rlim_max;
+    if (resource == TASK_RLIMIT_CPU) {
+        task->rlimit_cpu_next = 0;
+    }
+    return RDNX_OK;
+}
+
+uint64_t unix_proc_prlimit(uint64_t pid, uint64_t resource, uint64_t user_new_ptr, uint64_t user_old_ptr)
+{
+    task_t* self = task_get_current();
+    if (!self || resource >= TASK_RLIMIT_COUNT) {
+        return (uint64_t)RDNX_E_INVALID;
+    }
+    task_t* task = pid == 0 ? self : task_find_by_id(pid);
+    if (!task) {
+        return (uint64_t)RDNX_E_NOTFOUND;
+    }
+    if (!unix_signal_may_send(self, task)) {
+        return (uint64_t)RDNX_E_DENIED;
+    }
+
+    unix_rlimit_u_t in;
+    if (user_new_ptr &&
+        unix_copy_from_user(&in, (const void*)(uintptr_t)user_new_ptr, sizeof(in)) != RDNX_OK) {
+        return (uint64_t)RDNX_E_INVALID;
+    }
+    if (user_old_ptr) {
+        unix_rlimit_u_t out;
+        out.rlim_cur = task->rlimit[resource].cur;
+        out.rlim_max = task->rlimit[resource].max;
+        if (unix_copy_to_user((void*)(uintptr_t)user_old_ptr, &out, sizeof(out)) != RDNX_OK) {
+            return (uint64_t)RDNX_E_INVALID;
+        }
+    }
+    if (user_new_ptr) {
+        return (uint64_t)unix_rlimit_set(task, (uint32_t)resource, &in);
+    }
+    return (uint64_t)RDNX_OK;
+}
+
+uint64_t unix_proc_getrlimit(uint64_t resource, uint64_t user_rlim_ptr)
+{
+    if (!user_rlim_ptr) {
+        return (uint64_t)RDNX_E_INVALID;
+    }
+    return unix_proc_prlimit(0, resource, 0, user_rlim_ptr);
+}
+
+uint64_t unix_proc_setrlimit(uint64_t resource, uint64_t user_rlim_ptr)
+{
+    if (!user_rlim_ptr) {
+        return (uint64_t)RDNX_E_INVALID;
+    }
+    return unix_proc_prlimit(0, resource, user_rlim_ptr, 0);
+}
+
+/*
+ * procctl(2) subset: PROC_ASLR_CTL and PROC_ASLR_STATUS on one process
+ * (P_PID, id 0 is the caller). The control takes effect at the next
+ * exec; the status adds PROC_ASLR_ACTIVE if the running image is
+ * randomized.
+ */
+uint64_t unix_proc_procctl(uint64_t idtype, uint64_t id, uint64_t cmd, uint64_t user_data_ptr)
+{
+    task_t* self = task_get_current();
+    if (!self || idtype != UNIX_P_PID || !user_data_ptr) {
+        return (uint64_t)RDNX_E_INVALID;
+    }
+    task_t* task = id == 0 ? self : task_find_by_id(id);
+    if (!task) {
+        return (uint64_t)RDNX_E_NOTFOUND;
+    }
+    if (!unix_signal_may_send(self, task)) {
+        return (uint64_t)RDNX_E_DENIED;
+    }
+
+    uint32_t data = 0;
+    switch (cmd) {
+    case UNIX_PROC_ASLR_CTL:
+        if (unix_copy_from_user(&data, (const void*)(uintptr_t)user_data_ptr, sizeof(data)) != RDNX_OK) {
+            return (uint64_t)RDNX_E_INVALID;
+        }
+        if (data == UNIX_PROC_ASLR_FORCE_ENABLE) {
+            task->aslr_ctl = TASK_ASLR_FORCE_ENABLE;
+        } else if (data == UNIX_PROC_ASLR_FORCE_DISABLE) {
+            task->aslr_ctl = TASK_ASLR_FORCE_DISABLE;
+        } else if (data == UNIX_PROC_ASLR_NOFORCE) {
+            task->aslr_ctl = TASK_ASLR_DEFAULT;
+        } else {
+            return (uint64_t)RDNX_E_INVALID;
+        }
+        return (uint64_t)RDNX_OK;
+    case UNIX_PROC_ASLR_STATUS:
+        if (task->aslr_ctl == TASK_ASLR_FORCE_ENABLE) {
+            data = UNIX_PROC_ASLR_FORCE_ENABLE;
+        } else if (task->aslr_ctl == TASK_ASLR_FORCE_DISABLE) {
+            data = UNIX_PROC_ASLR_FORCE_DISABLE;
+        } else {
+            data = UNIX_PROC_ASLR_NOFORCE;
+        }
+        if (task->aslr_active) {
+            data |= UNIX_PROC_ASLR_ACTIVE;
+        }
+        if (unix_copy_to_user((void*)(uintptr_t)user_data_ptr, &data, sizeof(data)) != RDNX_OK) {
+            return (uint64_t)RDNX_E_INVALID;
+        }
+        return (uint64_t)RDNX_OK;
+    default:
+        return (uint64_t)RDNX_E_UNSUPPORTED;
+    }
+}
+
+/*
+ * Linux personality(2): only ADDR_NO_RANDOMIZE means anything here and is
+ * kept as the caller's ASLR control. Returns the previous persona.
+ */
+uint64_t unix_proc_personality(uint64_t persona)
+{
+    task_t* self = task_get_current();
+    if (!self) {
+        return (uint64_t)RDNX_E_INVALID;
+    }
+    uint64_t old = (self->aslr_ctl == TASK_ASLR_FORCE_DISABLE) ? UNIX_ADDR_NO_RANDOMIZE : 0;
+    if ((uint32_t)persona == UNIX_PERSONALITY_QUERY) {
+        return old;
+    }
+    if (persona & UNIX_ADDR_NO_RANDOMIZE) {
+        self->aslr_ctl = TASK_ASLR_FORCE_DISABLE;
+    } else if (self->aslr_ctl == TASK_ASLR_FORCE_DISABLE) {
+        self->aslr_ctl = TASK_ASLR_DEFAULT;
+    }
+    return old;
+}
+
 uint64_t unix_proc_waitpid(uint64_t pid, uint64_t user_status_ptr)
 {
     /* CT-004/CT-005/CT-006 contract point. */
@@ -340,10 +568,10 @@ uint64_t unix_proc_waitpid(uint64_t pid, uint64_t user_status_ptr)
         return (uint64_t)RDNX_E_NOTFOUND;
     }
 
-    child->waited = 1;
-    if (user_status) {
-        *user_status = child->exit_code;
+    if (user_status && unix_copy_to_user(user_status, &child->exit_code, sizeof(int)) != RDNX_OK) {
+        return (uint64_t)RDNX_E_INVALID;
     }
+    child->waited = 1;
     bool destroy_now = (child->thread_count == 0);
     if (destroy_now) {
         child->state = TASK_STATE_DEAD;
@@ -377,6 +605,9 @@ uint64_t unix_proc_fork(void)
     task_set_abi(child, task_get_abi(parent));
     child->tls_fs_base = parent->tls_fs_base;
     child->umask = parent->umask;
+    memcpy(child->rlimit, parent->rlimit, sizeof(child->rlimit));
+    child->aslr_ctl = parent->aslr_ctl;
+    child->aslr_active = parent->aslr_active;
     strncpy(child->cwd, parent->cwd, sizeof(child->cwd) - 1);
     child->cwd[sizeof(child->cwd) - 1] = '\0';
 
@@ -404,40 +635,40 @@ uint64_t unix_proc_fork(void)
     child_thread->priority = self_thread->priority;
     child_thread->base_priority = self_thread->base_priority;
     child_thread->dyn_priority = self_thread->dyn_priority;
+    /* Live FPU/SIMD registers are the parent's user state at the syscall. */
+    cpu_fpu_save(child_thread->context.fpu_state);
     scheduler_add_thread(child_thread);
     return (uint64_t)child->task_id;
 }
 
 uint64_t unix_time_nanosleep(uint64_t user_req_ptr, uint64_t user_rem_ptr)
 {
-    const unix_timespec_u_t* req = (const unix_timespec_u_t*)(uintptr_t)user_req_ptr;
+    unix_timespec_u_t req;
     unix_timespec_u_t* rem = (unix_timespec_u_t*)(uintptr_t)user_rem_ptr;
-    if (!req || !unix_user_range_ok(req, sizeof(*req))) {
+    if (!user_req_ptr ||
+        unix_copy_from_user(&req, (const void*)(uintptr_t)user_req_ptr, sizeof(req)) != RDNX_OK) {
         return (uint64_t)RDNX_E_INVALID;
     }
     if (rem && !unix_user_range_ok(rem, sizeof(*rem))) {
         return (uint64_t)RDNX_E_INVALID;
     }
-    if (req->tv_sec < 0 || req->tv_nsec < 0 || req->tv_nsec >= 1000000000LL) {
+    if (req.tv_sec < 0 || req.tv_nsec < 0 || req.tv_nsec >= 1000000000LL) {
         return (uint64_t)RDNX_E_INVALID;
     }
 
-    uint64_t ms_from_sec = (uint64_t)req->tv_sec * 1000ULL;
-    uint64_t ms_from_nsec = (uint64_t)req->tv_nsec / 1000000ULL;
-    uint64_t total_ms = ms_from_sec + ms_from_nsec;
-    if ((req->tv_nsec % 1000000LL) != 0) {
-        total_ms++;
+    uint64_t total_ns = UINT64_MAX;
+    if ((uint64_t)req.tv_sec < UINT64_MAX / 1000000000ULL) {
+        total_ns = (uint64_t)req.tv_sec * 1000000000ULL + (uint64_t)req.tv_nsec;
     }
 
-    if (total_ms > 0) {
-        scheduler_sleep(total_ms);
+    if (total_ns > 0) {
+        scheduler_sleep_ns(total_ns);
     } else {
         scheduler_yield();
     }
 
-    if (rem) {
-        rem->tv_sec = 0;
-        rem->tv_nsec = 0;
+    if (rem && unix_clear_user(rem, sizeof(*rem)) != RDNX_OK) {
+        return (uint64_t)RDNX_E_INVALID;
     }
     return (uint64_t)RDNX_OK;
 }
@@ -460,23 +691,27 @@ uint64_t unix_proc_futex(uint64_t user_uaddr_ptr,
 
     if ((uint32_t)op == UNIX_FUTEX_WAIT) {
         int32_t expected = (int32_t)val;
-        if (*uaddr != expected) {
+        int32_t cur = 0;
+        if (unix_copy_from_user(&cur, uaddr, sizeof(cur)) != RDNX_OK) {
+            return (uint64_t)RDNX_E_INVALID;
+        }
+        if (cur != expected) {
             return (uint64_t)RDNX_E_BUSY;
         }
 
         int64_t timeout_ms = -1;
         if (user_timeout_ptr != 0) {
-            const unix_timespec_u_t* ts = (const unix_timespec_u_t*)(uintptr_t)user_timeout_ptr;
-            if (!unix_user_range_ok(ts, sizeof(*ts))) {
+            unix_timespec_u_t ts;
+            if (unix_copy_from_user(&ts, (const void*)(uintptr_t)user_timeout_ptr, sizeof(ts)) != RDNX_OK) {
                 return (uint64_t)RDNX_E_INVALID;
             }
-            if (ts->tv_sec < 0 || ts->tv_nsec < 0 || ts->tv_nsec >= 1000000000LL) {
+            if (ts.tv_sec < 0 || ts.tv_nsec < 0 || ts.tv_nsec >= 1000000000LL) {
                 return (uint64_t)RDNX_E_INVALID;
             }
-            uint64_t ms_from_sec = (uint64_t)ts->tv_sec * 1000ULL;
-            uint64_t ms_from_nsec = (uint64_t)ts->tv_nsec / 1000000ULL;
+            uint64_t ms_from_sec = (uint64_t)ts.tv_sec * 1000ULL;
+            uint64_t ms_from_nsec = (uint64_t)ts.tv_nsec / 1000000ULL;
             timeout_ms = (int64_t)(ms_from_sec + ms_from_nsec);
-            if ((ts->tv_nsec % 1000000LL) != 0) {
+            if ((ts.tv_nsec % 1000000LL) != 0) {
                 timeout_ms++;
             }
         }
@@ -505,7 +740,11 @@ uint64_t unix_proc_futex(uint64_t user_uaddr_ptr,
 
         uint64_t rc = (uint64_t)RDNX_OK;
         for (;;) {
-            if (*uaddr != expected) {
+            if (unix_copy_from_user(&cur, uaddr, sizeof(cur)) != RDNX_OK) {
+                rc = (uint64_t)RDNX_E_INVALID;
+                break;
+            }
+            if (cur != expected) {
                 rc = (uint64_t)RDNX_E_BUSY;
                 break;
             }
@@ -520,7 +759,8 @@ uint64_t unix_proc_futex(uint64_t user_uaddr_ptr,
                 rc = (uint64_t)RDNX_E_TIMEOUT;
                 break;
             }
-            if (timeout_ms > 0 && scheduler_get_ticks() >= deadline) {
+            if ((timeout_ms > 0 && scheduler_get_ticks() >= deadline) ||
+                task_kill_pending(task_get_current())) {
                 rc = (uint64_t)RDNX_E_TIMEOUT;
                 break;
             }
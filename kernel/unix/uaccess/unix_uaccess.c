@@ -1,5 +1,7 @@
 #include "../unix_layer.h"
 #include "../../arch/config.h"
+#include "../../core/cpu.h"
+#include "../../../include/common.h"
 #include "../../../include/error.h"
 
 #define UNIX_USER_MIN_VA 0x1000ULL
@@ -36,17 +38,68 @@ int unix_copy_user_cstr(char* dst, size_t dst_size, const char* user_src)
         base >= ARCH_KERNEL_VIRT_BASE) {
         return RDNX_E_INVALID;
     }
-    for (size_t i = 0; i < dst_size; i++) {
-        uintptr_t cur = base + i;
-        if (cur > ARCH_USER_CANON_MAX || cur >= ARCH_KERNEL_VIRT_BASE) {
-            return RDNX_E_INVALID;
-        }
-        char c = user_src[i];
-        dst[i] = c;
-        if (c == '\0') {
-            return RDNX_OK;
-        }
-    }
-    dst[dst_size - 1] = '\0';
-    return RDNX_E_INVALID;
+    /* Stop at the top of the user range rather than walk into the kernel */
+    size_t len = dst_size;
+    if ((uint64_t)len > (uint64_t)(ARCH_USER_CANON_MAX - base) + 1u) {
+        len = (size_t)(ARCH_USER_CANON_MAX - base) + 1u;
+    }
+    unix_user_access_begin();
+    int rc = cpu_user_copy_str(dst, user_src, len);
+    unix_user_access_end();
+    if (rc != RDNX_OK) {
+        dst[dst_size - 1] = '\0';
+    }
+    return rc;
+}
+
+void unix_user_access_begin(void)
+{
+    cpu_user_access_begin();
+}
+
+void unix_user_access_end(void)
+{
+    cpu_user_access_end();
+}
+
+int unix_copy_from_user(void* dst, const void* user_src, size_t len)
+{
+    if (len == 0) {
+        return RDNX_OK;
+    }
+    if (!dst || !unix_user_range_ok(user_src, len)) {
+        return RDNX_E_INVALID;
+    }
+    unix_user_access_begin();
+    int rc = cpu_user_copy(dst, user_src, len);
+    unix_user_access_end();
+    return rc;
+}
+
+int unix_copy_to_user(void* user_dst, const void* src, size_t len)
+{
+    if (len == 0) {
+        return RDNX_OK;
+    }
+    if (!src || !unix_user_range_ok(user_dst, len)) {
+        return RDNX_E_INVALID;
+    }
+    unix_user_access_begin();
+    int rc = cpu_user_copy(user_dst, src, len);
+    unix_user_access_end();
+    return rc;
+}
+
+int unix_clear_user(void* user_dst, size_t len)
+{
+    if (len == 0) {
+        return RDNX_OK;
+    }
+    if (!unix_user_range_ok(user_dst, len)) {
+        return RDNX_E_INVALID;
+    }
+    unix_user_access_begin();
+    int rc = cpu_user_clear(user_dst, len);
+    unix_user_access_end();
+    return rc;
 }
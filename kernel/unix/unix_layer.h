@@ -28,8 +28,18 @@ enum {
     UNIX_FD_KIND_SOCKET = 4
 };
 
+/*
+ * User memory access. With SMAP the kernel may only touch user pages between
+ * unix_user_access_begin()/unix_user_access_end(); the window must not span
+ * a sleep or a yield. Prefer the copy helpers, they check the range too.
+ */
 bool unix_user_range_ok(const void* ptr, size_t len);
 int unix_copy_user_cstr(char* dst, size_t dst_size, const char* user_src);
+void unix_user_access_begin(void);
+void unix_user_access_end(void);
+int unix_copy_from_user(void* dst, const void* user_src, size_t len);
+int unix_copy_to_user(void* user_dst, const void* src, size_t len);
+int unix_clear_user(void* user_dst, size_t len);
 int unix_resolve_path(const task_t* task, const char* in, char* out, size_t out_sz);
 int unix_resolve_user_path(const char* user_src, char* out, size_t out_sz);
 
@@ -52,6 +62,9 @@ typedef struct {
 } unix_stat_u_t;
 
 uint64_t unix_fs_open(uint64_t user_path_ptr, uint64_t flags);
+/* POSIX shared memory: "/name" objects on the /dev/shm ramfs */
+uint64_t unix_fs_shm_open(uint64_t user_name_ptr, uint64_t flags);
+uint64_t unix_fs_shm_unlink(uint64_t user_name_ptr);
 /* CT-007/CT-008 */
 uint64_t unix_fs_close(uint64_t fd);
 uint64_t unix_fs_dup(uint64_t oldfd);
@@ -114,6 +127,15 @@ uint64_t unix_proc_futex(uint64_t user_uaddr_ptr,
                          uint64_t user_uaddr2_ptr,
                          uint64_t val3);
 void unix_proc_signal_checkpoint(void);
+/* Exit the calling task if the OOM killer marked it (run at PASSIVE) */
+void unix_proc_kill_checkpoint(void);
+/* Resource limits; prlimit() with pid 0 acts on the caller */
+uint64_t unix_proc_getrlimit(uint64_t resource, uint64_t user_rlim_ptr);
+uint64_t unix_proc_setrlimit(uint64_t resource, uint64_t user_rlim_ptr);
+uint64_t unix_proc_prlimit(uint64_t pid, uint64_t resource, uint64_t user_new_ptr, uint64_t user_old_ptr);
+/* ASLR control: procctl(PROC_ASLR_CTL/STATUS) and Linux personality() */
+uint64_t unix_proc_procctl(uint64_t idtype, uint64_t id, uint64_t cmd, uint64_t user_data_ptr);
+uint64_t unix_proc_personality(uint64_t persona);
 /* CT-004/CT-005/CT-006 */
 uint64_t unix_proc_waitpid(uint64_t pid, uint64_t user_status_ptr);
 uint64_t unix_time_nanosleep(uint64_t user_req_ptr, uint64_t user_rem_ptr);
@@ -0,0 +1,43 @@
+/**
+ * @file vm_aslr.c
+ * @brief User address-space layout randomization: policy and offsets
+ */
+
+#include "vm_aslr.h"
+#include "vm_map.h"
+#include "../common/bootparam.h"
+#include "../core/random.h"
+
+BOOTPARAM_INT(vm_aslr_mode, "rdnx.aslr", 2, 0, 2,
+              "User ASLR: 0 off, 1 stack/mmap/PIE base, 2 also brk");
+
+/* Offsets are below 2^bits pages; a region moves only from min_level on */
+static const struct {
+    uint8_t bits;
+    uint8_t min_level;
+} g_vm_aslr_regions[VM_ASLR_REGION_COUNT] = {
+    [VM_ASLR_STACK] = { 14, 1 },
+    [VM_ASLR_MMAP]  = { 16, 1 },
+    [VM_ASLR_EXEC]  = { 16, 1 },
+    [VM_ASLR_BRK]   = { 13, 2 },
+};
+
+int vm_aslr_level(const task_t* task)
+{
+    if (task && task->aslr_ctl == TASK_ASLR_FORCE_DISABLE) {
+        return 0;
+    }
+    if (task && task->aslr_ctl == TASK_ASLR_FORCE_ENABLE) {
+        return 2;
+    }
+    return (int)vm_aslr_mode;
+}
+
+uint64_t vm_aslr_offset(const task_t* task, vm_aslr_region_t region)
+{
+    if ((uint32_t)region >= VM_ASLR_REGION_COUNT ||
+        vm_aslr_level(task) < g_vm_aslr_regions[region].min_level) {
+        return 0;
+    }
+    return random_below(1ULL << g_vm_aslr_regions[region].bits) * VM_PAGE_SIZE;
+}
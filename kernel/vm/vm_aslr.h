@@ -0,0 +1,37 @@
+/**
+ * @file vm_aslr.h
+ * @brief User address-space layout randomization
+ *
+ * At exec the stack top and the mmap base move down, and the load base
+ * of ET_DYN executables and (at level 2) the start of the brk heap move
+ * up, each by a random number of pages. The global level comes from
+ * rdnx.aslr; task_t.aslr_ctl overrides it per process for reproducible
+ * debugging and takes effect at the next exec.
+ */
+
+#ifndef _RODNIX_VM_ASLR_H
+#define _RODNIX_VM_ASLR_H
+
+#include "../core/task.h"
+
+typedef enum {
+    VM_ASLR_STACK = 0,  /* Down from the fixed stack top, up to 64 MiB */
+    VM_ASLR_MMAP,       /* Down from the default mmap base, up to 256 MiB */
+    VM_ASLR_EXEC,       /* Up from the ET_DYN load base, up to 256 MiB */
+    VM_ASLR_BRK,        /* Up from the end of the image, up to 32 MiB */
+    VM_ASLR_REGION_COUNT
+} vm_aslr_region_t;
+
+/**
+ * Randomization level the next exec of @task uses.
+ * @return 0 fixed layout, 1 stack, mmap and ET_DYN base, 2 also brk
+ */
+int vm_aslr_level(const task_t* task);
+
+/**
+ * Random page-aligned displacement of @region for the next exec.
+ * @return Offset in bytes, 0 when the level leaves the region fixed
+ */
+uint64_t vm_aslr_offset(const task_t* task, vm_aslr_region_t region);
+
+#endif /* _RODNIX_VM_ASLR_H */
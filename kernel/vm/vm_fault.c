@@ -19,6 +19,56 @@ static uint64_t vm_pte_flags_from_prot(uint32_t prot)
     return flags;
 }
 
+/*
+ * Fault in a page of a file object through its pager. Shared
+ * mappings map the pager's frame itself, read-only until the first write
+ * so that write marks it dirty; private mappings map it read-only and
+ * copy on the first write (VM_MAP_F_COW). @current_phys is the frame
+ * already mapped at @va when a shared page is being made writable.
+ */
+static int vm_fault_pager_page(task_t* task, vm_map_entry_t* e, uint64_t va, int is_write,
+                               uint64_t current_phys)
+{
+    uint64_t pml4 = (uint64_t)(uintptr_t)task->address_space;
+    uint64_t pindex = (e->object_offset + (va - e->start)) / VM_PAGE_SIZE;
+    int private_map = (e->flags & VM_MAP_F_PRIVATE) != 0;
+    uint32_t flags = (is_write && !private_map) ? VM_PAGER_GET_WRITE : 0;
+    uint64_t phys = 0;
+    int rc = vm_pager_getpages(e->object, pindex, 1, flags, &phys);
+    if (rc != RDNX_OK) {
+        return rc;
+    }
+
+    uint32_t prot = e->prot;
+    if (private_map && is_write) {
+        uint64_t copy = vm_pager_alloc_zero_page();
+        if (!copy) {
+            vm_pager_release(&phys, 1);
+            return RDNX_E_NOMEM;
+        }
+        memcpy(ARCH_PHYS_TO_VIRT(copy), ARCH_PHYS_TO_VIRT(phys), VM_PAGE_SIZE);
+        vm_pager_release(&phys, 1);
+        phys = copy;
+    } else if (!is_write) {
+        prot &= ~VM_PROT_WRITE;
+    }
+    /* The pager reference (or the copy's) becomes the mapping reference */
+    rc = paging_map_page_4kb_pml4(pml4, va, phys, vm_pte_flags_from_prot(prot));
+    if (rc != RDNX_OK) {
+        vm_pager_release(&phys, 1);
+        return rc;
+    }
+    if (current_phys) {
+        if (current_phys != phys) {
+            paging_tlb_shootdown(pml4, va, 1);
+        }
+        (void)vm_page_ref_release(current_phys); /* Old mapping reference */
+    } else {
+        ((vm_map_t*)task->vm_map)->resident++;
+    }
+    return RDNX_OK;
+}
+
 int vm_fault_handle(task_t* task, uint64_t fault_addr, uint64_t err_code, uint64_t rip)
 {
     (void)rip;
@@ -55,6 +105,16 @@ int vm_fault_handle(task_t* task, uint64_t fault_addr, uint64_t err_code, uint64
 
     uint64_t current_phys = paging_get_physical(va) & ~(VM_PAGE_SIZE - 1u);
 
+    if (e->object && e->object->type == VM_OBJECT_FILE) {
+        if (current_phys == 0) {
+            return vm_fault_pager_page(task, e, va, is_write, 0);
+        }
+        if (is_write && (e->flags & VM_MAP_F_PRIVATE) == 0) {
+            /* First store to a shared page: the pager marks it dirty */
+            return vm_fault_pager_page(task, e, va, is_write, current_phys);
+        }
+    }
+
     if (current_phys != 0 && is_write && (e->flags & VM_MAP_F_COW)) {
         uint64_t new_phys = vm_pager_alloc_zero_page();
         if (!new_phys) {
@@ -65,6 +125,8 @@ int vm_fault_handle(task_t* task, uint64_t fault_addr, uint64_t err_code, uint64
                                        va,
                                        new_phys,
                                        vm_pte_flags_from_prot(e->prot));
+        /* Other threads of the task may still cache the shared frame. */
+        paging_tlb_shootdown((uint64_t)(uintptr_t)task->address_space, va, 1);
         (void)vm_page_ref_release(current_phys); /* Drop this mapping's old COW reference. */
         return RDNX_OK;
     }
@@ -80,6 +142,34 @@ int vm_fault_handle(task_t* task, uint64_t fault_addr, uint64_t err_code, uint64
             if (phys) {
                 has_obj_page = 1;
                 (void)vm_page_ref_retain(phys); /* New mapping reference. */
+            } else if (e->object->pager) {
+                /* Swapped out: the swap pager reads it back in */
+                int rc = vm_pager_getpages(e->object, obj_page_idx, 1, 0, &phys);
+                if (rc == RDNX_OK) {
+                    has_obj_page = 1;
+                } else if (rc != RDNX_E_NOTFOUND) {
+                    return rc;
+                }
+            }
+        }
+        /*
+         * After fork both maps share the object (VM_MAP_F_COW): its pages
+         * are mapped read-only and new pages stay private to this map.
+         */
+        int frozen = (e->flags & VM_MAP_F_COW) != 0;
+        uint32_t prot = e->prot;
+        if (has_obj_page && frozen) {
+            if (is_write) {
+                uint64_t copy = vm_pager_alloc_zero_page();
+                if (!copy) {
+                    (void)vm_page_ref_release(phys);
+                    return RDNX_E_NOMEM;
+                }
+                memcpy(ARCH_PHYS_TO_VIRT(copy), ARCH_PHYS_TO_VIRT(phys), VM_PAGE_SIZE);
+                (void)vm_page_ref_release(phys);
+                phys = copy;
+            } else {
+                prot &= ~VM_PROT_WRITE;
             }
         }
         if (!has_obj_page) {
@@ -87,29 +177,18 @@ int vm_fault_handle(task_t* task, uint64_t fault_addr, uint64_t err_code, uint64
             if (!phys) {
                 return RDNX_E_NOMEM;
             }
-            if (e->object && e->object->type == VM_OBJECT_FILE && e->object->pager_private) {
-                vm_file_backing_t* fb = (vm_file_backing_t*)e->object->pager_private;
-                if (fb->data && fb->size > 0) {
-                    uint64_t rel = va - e->start;
-                    uint64_t off = fb->file_offset + e->object_offset + rel;
-                    if (off < fb->size) {
-                        uint64_t avail = fb->size - off;
-                        uint64_t copy = (avail > VM_PAGE_SIZE) ? VM_PAGE_SIZE : avail;
-                        memcpy(ARCH_PHYS_TO_VIRT(phys), fb->data + off, (size_t)copy);
-                    }
-                }
-            }
-            if (e->object) {
+            if (e->object && !frozen) {
                 (void)vm_object_set_resident_page(e->object, obj_page_idx, phys);
             }
         }
         int rc = paging_map_page_4kb_pml4((uint64_t)(uintptr_t)task->address_space,
                                           va,
                                           phys,
-                                          vm_pte_flags_from_prot(e->prot));
+                                          vm_pte_flags_from_prot(prot));
         if (rc != RDNX_OK) {
             return rc;
         }
+        map->resident++;
         return RDNX_OK;
     }
 
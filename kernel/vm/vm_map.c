@@ -1,15 +1,75 @@
 #include "vm_map.h"
+#include "vm_aslr.h"
+#include "vm_fault.h"
 #include "vm_pager.h"
 #include "vm_page_ref.h"
 #include "../arch/paging.h"
 #include "../arch/config.h"
 #include "../common/heap.h"
+#include "../common/kmem.h"
+#include "../core/interrupts.h"
 #include "../../include/common.h"
 #include "../../include/error.h"
 
 #define VM_USER_MIN      0x0000000000001000ULL
 #define VM_USER_MAX      0x0000000080000000ULL
 #define VM_DEFAULT_MMAP  0x0000000060000000ULL
+#define VM_UNMAP_BATCH   32u /* frames held back until their TLB shootdown */
+#define VM_MINCORE_RESIDENT 0x1u
+
+/* Page-fault error code bits for faults raised on behalf of the task */
+#define VM_FAULT_WRITE   (1u << 1)
+#define VM_FAULT_USER    (1u << 2)
+
+/*
+ * LOCKING: a task's vm_map_t — IRQL_HIGH (the giant): syscalls and faults
+ *   already hold it, vm_task_destroy raises to it for the page-out scan.
+ *   g_vm_map_entries is created once, by the first exec.
+ */
+static kmem_cache_t* g_vm_map_entries = NULL;
+
+static int vm_map_entry_cmp(vm_map_entry_t* lhs, vm_map_entry_t* rhs)
+{
+    if (lhs->start < rhs->start) {
+        return -1;
+    }
+    if (lhs->start > rhs->start) {
+        return 1;
+    }
+    return 0;
+}
+
+/* Recompute max_free of @e from its children; nonzero if it changed */
+static int vm_map_entry_augment(vm_map_entry_t* e)
+{
+    uint64_t max = e->adj_free;
+    vm_map_entry_t* child = RB_LEFT(e, link);
+    if (child && child->max_free > max) {
+        max = child->max_free;
+    }
+    child = RB_RIGHT(e, link);
+    if (child && child->max_free > max) {
+        max = child->max_free;
+    }
+    if (e->max_free == max) {
+        return 0;
+    }
+    e->max_free = max;
+    return 1;
+}
+
+#undef RB_AUGMENT_CHECK
+#define RB_AUGMENT_CHECK(x) vm_map_entry_augment(x)
+
+RB_PROTOTYPE_STATIC(vm_map_tree, vm_map_entry, link, vm_map_entry_cmp);
+#if defined(__GNUC__)
+#pragma GCC diagnostic push
+#pragma GCC diagnostic ignored "-Wmaybe-uninitialized"
+#endif
+RB_GENERATE_STATIC(vm_map_tree, vm_map_entry, link, vm_map_entry_cmp);
+#if defined(__GNUC__)
+#pragma GCC diagnostic pop
+#endif
 
 static inline uint64_t vm_align_down(uint64_t v)
 {
@@ -23,29 +83,100 @@ static inline uint64_t vm_align_up(uint64_t v)
 
 static vm_map_t* vm_map_create(uint64_t pml4_phys)
 {
+    if (!g_vm_map_entries) {
+        g_vm_map_entries = kmem_cache_create("vm_map_entry", sizeof(vm_map_entry_t),
+                                             0, NULL, NULL, NULL, 0);
+        if (!g_vm_map_entries) {
+            return NULL;
+        }
+    }
     vm_map_t* map = (vm_map_t*)kmalloc(sizeof(vm_map_t));
     if (!map) {
         return NULL;
     }
     memset(map, 0, sizeof(*map));
     map->pml4_phys = pml4_phys;
+    RB_INIT(&map->entries);
     return map;
 }
 
+vm_map_entry_t* vm_map_first(vm_map_t* map)
+{
+    return map ? RB_MIN(vm_map_tree, &map->entries) : NULL;
+}
+
+vm_map_entry_t* vm_map_next(vm_map_entry_t* e)
+{
+    return e ? RB_NEXT(vm_map_tree, NULL, e) : NULL;
+}
+
+/* Refresh the gap after @e once its end or its successor moved */
+static void vm_map_entry_regap(vm_map_entry_t* e)
+{
+    if (!e) {
+        return;
+    }
+    vm_map_entry_t* next = RB_NEXT(vm_map_tree, NULL, e);
+    e->adj_free = (next ? next->start : VM_USER_MAX) - e->end;
+    RB_UPDATE_AUGMENT(e, link);
+}
+
+static void vm_map_link(vm_map_t* map, vm_map_entry_t* e)
+{
+    e->adj_free = 0;
+    e->max_free = 0;
+    (void)RB_INSERT(vm_map_tree, &map->entries, e);
+    map->entry_count++;
+    vm_map_entry_regap(e);
+    vm_map_entry_regap(RB_PREV(vm_map_tree, &map->entries, e));
+}
+
+static void vm_map_unlink(vm_map_t* map, vm_map_entry_t* e)
+{
+    vm_map_entry_t* prev = RB_PREV(vm_map_tree, &map->entries, e);
+    (void)RB_REMOVE(vm_map_tree, &map->entries, e);
+    map->entry_count--;
+    vm_map_entry_regap(prev);
+}
+
+/* Unlink @e and drop its object reference */
+static void vm_map_entry_delete(vm_map_t* map, vm_map_entry_t* e)
+{
+    vm_map_unlink(map, e);
+    if (e->object) {
+        vm_object_unref(e->object);
+    }
+    kmem_cache_free(g_vm_map_entries, e);
+}
+
 static void vm_map_destroy(vm_map_t* map)
 {
     if (!map) {
         return;
     }
-    for (uint32_t i = 0; i < map->entry_count; i++) {
-        if (map->entries[i].object) {
-            vm_object_unref(map->entries[i].object);
-            map->entries[i].object = NULL;
-        }
+    vm_map_entry_t* e;
+    while ((e = RB_ROOT(&map->entries)) != NULL) {
+        vm_map_entry_delete(map, e);
     }
     kfree(map);
 }
 
+/* Lowest entry ending above @addr: the one holding @addr, or the next one */
+static vm_map_entry_t* vm_map_entry_above(vm_map_t* map, uint64_t addr)
+{
+    vm_map_entry_t* best = NULL;
+    vm_map_entry_t* e = RB_ROOT(&map->entries);
+    while (e) {
+        if (e->end > addr) {
+            best = e;
+            e = RB_LEFT(e, link);
+        } else {
+            e = RB_RIGHT(e, link);
+        }
+    }
+    return best;
+}
+
 static int vm_range_valid(uint64_t start, uint64_t end)
 {
     if (start < VM_USER_MIN || end <= start || end > VM_USER_MAX) {
@@ -59,14 +190,8 @@ static int vm_range_valid(uint64_t start, uint64_t end)
 
 static int vm_map_overlap(vm_map_t* map, uint64_t start, uint64_t end)
 {
-    for (uint32_t i = 0; i < map->entry_count; i++) {
-        vm_map_entry_t* e = &map->entries[i];
-        if (end <= e->start || start >= e->end) {
-            continue;
-        }
-        return 1;
-    }
-    return 0;
+    vm_map_entry_t* e = vm_map_entry_above(map, start);
+    return e && e->start < end;
 }
 
 static uint64_t vm_pte_flags_from_prot(uint32_t prot)
@@ -81,6 +206,88 @@ static uint64_t vm_pte_flags_from_prot(uint32_t prot)
     return flags;
 }
 
+/* @b continues @a: same protection, flags and backing at the next offset */
+static int vm_map_entry_mergeable(const vm_map_entry_t* a, const vm_map_entry_t* b)
+{
+    if (a->end != b->start || a->prot != b->prot || a->flags != b->flags ||
+        a->object != b->object) {
+        return 0;
+    }
+    return !a->object || a->object_offset + (a->end - a->start) == b->object_offset;
+}
+
+/* Coalesce @e with compatible neighbours; @return the surviving entry */
+static vm_map_entry_t* vm_map_simplify(vm_map_t* map, vm_map_entry_t* e)
+{
+    vm_map_entry_t* prev = RB_PREV(vm_map_tree, &map->entries, e);
+    if (prev && vm_map_entry_mergeable(prev, e)) {
+        uint64_t end = e->end;
+        vm_map_entry_delete(map, e);
+        prev->end = end;
+        vm_map_entry_regap(prev);
+        e = prev;
+    }
+    vm_map_entry_t* next = RB_NEXT(vm_map_tree, &map->entries, e);
+    if (next && vm_map_entry_mergeable(e, next)) {
+        uint64_t end = next->end;
+        vm_map_entry_delete(map, next);
+        e->end = end;
+        vm_map_entry_regap(e);
+    }
+    return e;
+}
+
+/*
+ * Grow the anonymous object behind @me, which only @me maps, to back @len
+ * more bytes past the entry end, as FreeBSD's vm_object_coalesce() does.
+ */
+static int vm_map_object_coalesce(vm_map_entry_t* me, uint64_t len)
+{
+    vm_object_t* obj = me->object;
+    uint64_t off = me->object_offset + (me->end - me->start);
+    /* Pages left behind by an earlier shrink must not reappear */
+    vm_object_free_pages(obj, off / VM_PAGE_SIZE, len / VM_PAGE_SIZE);
+    return vm_object_grow(obj, off + len);
+}
+
+/* Split @e at @addr; @e keeps the lower part */
+static int vm_map_split(vm_map_t* map, vm_map_entry_t* e, uint64_t addr)
+{
+    if (addr <= e->start || addr >= e->end) {
+        return RDNX_OK;
+    }
+    vm_map_entry_t* tail = (vm_map_entry_t*)kmem_cache_zalloc(g_vm_map_entries);
+    if (!tail) {
+        return RDNX_E_NOMEM;
+    }
+    tail->start = addr;
+    tail->end = e->end;
+    tail->prot = e->prot;
+    tail->flags = e->flags;
+    tail->object = e->object;
+    tail->object_offset = e->object_offset + (addr - e->start);
+    if (tail->object) {
+        vm_object_ref(tail->object);
+    }
+    e->end = addr;
+    vm_map_link(map, tail);
+    return RDNX_OK;
+}
+
+/* Make [start, end) begin and end on entry boundaries */
+static int vm_map_clip(vm_map_t* map, uint64_t start, uint64_t end)
+{
+    vm_map_entry_t* e = vm_map_lookup(map, start);
+    if (e) {
+        int rc = vm_map_split(map, e, start);
+        if (rc != RDNX_OK) {
+            return rc;
+        }
+    }
+    e = vm_map_lookup(map, end);
+    return e ? vm_map_split(map, e, end) : RDNX_OK;
+}
+
 static int vm_map_add(vm_map_t* map,
                       uint64_t start,
                       uint64_t len,
@@ -94,15 +301,14 @@ static int vm_map_add(vm_map_t* map,
     if (!map || len == 0 || !vm_range_valid(s, e)) {
         return RDNX_E_INVALID;
     }
-    if (map->entry_count >= VM_MAP_MAX_ENTRIES) {
-        return RDNX_E_BUSY;
-    }
     if (vm_map_overlap(map, s, e)) {
         return RDNX_E_BUSY;
     }
 
-    vm_map_entry_t* ne = &map->entries[map->entry_count++];
-    memset(ne, 0, sizeof(*ne));
+    vm_map_entry_t* ne = (vm_map_entry_t*)kmem_cache_zalloc(g_vm_map_entries);
+    if (!ne) {
+        return RDNX_E_NOMEM;
+    }
     ne->start = s;
     ne->end = e;
     ne->prot = prot;
@@ -112,9 +318,42 @@ static int vm_map_add(vm_map_t* map,
     if (obj) {
         vm_object_ref(obj);
     }
+    vm_map_link(map, ne);
+    map->size += e - s;
+    (void)vm_map_simplify(map, ne);
     return RDNX_OK;
 }
 
+/*
+ * Unmap [start, end) of an address space. Frames are released only after
+ * other CPUs running the address space dropped their stale TLB entries.
+ */
+static void vm_unmap_range(vm_map_t* map, uint64_t start, uint64_t end)
+{
+    uint64_t pml4_phys = map->pml4_phys;
+    uint64_t batch[VM_UNMAP_BATCH];
+    uint64_t va = start;
+    while (va < end) {
+        uint64_t chunk = va;
+        uint32_t n = 0;
+        for (; va < end && n < VM_UNMAP_BATCH; va += VM_PAGE_SIZE) {
+            uint64_t phys = paging_get_physical_pml4(pml4_phys, va) & ~(VM_PAGE_SIZE - 1u);
+            if (phys != 0) {
+                (void)paging_unmap_page_pml4(pml4_phys, va);
+                batch[n++] = phys;
+            }
+        }
+        if (n == 0) {
+            continue;
+        }
+        paging_tlb_shootdown(pml4_phys, chunk, (va - chunk) / VM_PAGE_SIZE);
+        map->resident -= n;
+        for (uint32_t i = 0; i < n; i++) {
+            (void)vm_page_ref_release(batch[i]);
+        }
+    }
+}
+
 static int vm_map_remove(vm_map_t* map, uint64_t start, uint64_t len, uint64_t pml4_phys)
 {
     uint64_t s = vm_align_down(start);
@@ -122,68 +361,24 @@ static int vm_map_remove(vm_map_t* map, uint64_t start, uint64_t len, uint64_t p
     if (!map || len == 0 || e <= s) {
         return RDNX_E_INVALID;
     }
+    int rc = vm_map_clip(map, s, e);
+    if (rc != RDNX_OK) {
+        return rc;
+    }
     int removed = 0;
-    for (uint32_t i = 0; i < map->entry_count;) {
-        vm_map_entry_t* cur = &map->entries[i];
-        uint64_t rs = (s > cur->start) ? s : cur->start;
-        uint64_t re = (e < cur->end) ? e : cur->end;
-        if (re <= rs) {
-            i++;
-            continue;
-        }
-
+    vm_map_entry_t* cur = vm_map_entry_above(map, s);
+    while (cur && cur->start < e) {
+        vm_map_entry_t* next = RB_NEXT(vm_map_tree, &map->entries, cur);
         if (pml4_phys == map->pml4_phys) {
-            for (uint64_t va = rs; va < re; va += VM_PAGE_SIZE) {
-                uint64_t phys = paging_get_physical_pml4(pml4_phys, va) & ~(VM_PAGE_SIZE - 1u);
-                if (phys != 0) {
-                    (void)paging_unmap_page_pml4(pml4_phys, va);
-                    (void)vm_page_ref_release(phys);
-                }
-            }
+            vm_unmap_range(map, cur->start, cur->end);
         }
-        removed = 1;
-
-        if (rs == cur->start && re == cur->end) {
-            if (cur->object) {
-                vm_object_unref(cur->object);
-            }
-            if (i + 1 < map->entry_count) {
-                memmove(cur, cur + 1, (map->entry_count - i - 1) * sizeof(vm_map_entry_t));
-            }
-            map->entry_count--;
-            continue;
-        }
-
-        if (rs == cur->start) {
-            cur->start = re;
-            cur->object_offset += (re - rs);
-            i++;
-            continue;
+        map->size -= cur->end - cur->start;
+        if (cur->flags & VM_MAP_F_WIRED) {
+            map->wired -= cur->end - cur->start;
         }
-
-        if (re == cur->end) {
-            cur->end = rs;
-            i++;
-            continue;
-        }
-
-        if (map->entry_count >= VM_MAP_MAX_ENTRIES) {
-            return RDNX_E_BUSY;
-        }
-        vm_map_entry_t tail = *cur;
-        tail.start = re;
-        tail.object_offset += (re - cur->start);
-        if (tail.object) {
-            vm_object_ref(tail.object);
-        }
-        cur->end = rs;
-        if (i + 1 < map->entry_count) {
-            memmove(&map->entries[i + 2], &map->entries[i + 1],
-                    (map->entry_count - i - 1) * sizeof(vm_map_entry_t));
-        }
-        map->entries[i + 1] = tail;
-        map->entry_count++;
-        i += 2;
+        vm_map_entry_delete(map, cur);
+        removed = 1;
+        cur = next;
     }
     return removed ? RDNX_OK : RDNX_E_NOTFOUND;
 }
@@ -193,35 +388,56 @@ vm_map_entry_t* vm_map_lookup(vm_map_t* map, uint64_t addr)
     if (!map) {
         return NULL;
     }
-    for (uint32_t i = 0; i < map->entry_count; i++) {
-        vm_map_entry_t* e = &map->entries[i];
-        if (addr >= e->start && addr < e->end) {
-            return e;
-        }
-    }
-    return NULL;
+    vm_map_entry_t* e = vm_map_entry_above(map, addr);
+    return (e && e->start <= addr) ? e : NULL;
 }
 
-static uint64_t vm_find_gap(vm_map_t* map, uint64_t hint, uint64_t len)
+/* Leftmost entry at or above @floor followed by a gap of at least @len */
+static vm_map_entry_t* vm_map_gap_search(vm_map_entry_t* e, uint64_t floor, uint64_t len)
 {
-    uint64_t s = vm_align_up(hint);
-    uint64_t e = vm_align_up(hint + len);
-    if (e <= s) {
-        return 0;
+    if (!e || e->max_free < len) {
+        return NULL;
+    }
+    if (e->end < floor) {
+        return vm_map_gap_search(RB_RIGHT(e, link), floor, len);
     }
-    if (s < VM_DEFAULT_MMAP) {
-        s = VM_DEFAULT_MMAP;
-        e = s + vm_align_up(len);
+    vm_map_entry_t* found = vm_map_gap_search(RB_LEFT(e, link), floor, len);
+    if (found) {
+        return found;
     }
+    if (e->adj_free >= len) {
+        return e;
+    }
+    return vm_map_gap_search(RB_RIGHT(e, link), floor, len);
+}
 
-    while (e < VM_USER_MAX) {
-        if (!vm_map_overlap(map, s, e)) {
-            return s;
+/*
+ * First free range of @len bytes at or above @hint, wrapping around to
+ * the task's mmap @base once the top of the user range is reached.
+ */
+static uint64_t vm_find_gap(vm_map_t* map, uint64_t base, uint64_t hint, uint64_t len)
+{
+    uint64_t floor = vm_align_up(hint);
+    len = vm_align_up(len);
+    if (base < VM_USER_MIN || base >= VM_USER_MAX) {
+        base = VM_DEFAULT_MMAP;
+    }
+    if (floor < base || floor >= VM_USER_MAX) {
+        floor = base;
+    }
+    for (;;) {
+        if (len <= VM_USER_MAX - floor && !vm_map_overlap(map, floor, floor + len)) {
+            return floor;
+        }
+        vm_map_entry_t* e = vm_map_gap_search(RB_ROOT(&map->entries), floor, len);
+        if (e) {
+            return e->end;
+        }
+        if (floor == base) {
+            return 0;
         }
-        s += VM_PAGE_SIZE;
-        e += VM_PAGE_SIZE;
+        floor = base;
     }
-    return 0;
 }
 
 int vm_task_prepare_exec(task_t* task, uint64_t user_pml4_phys)
@@ -242,8 +458,9 @@ int vm_task_prepare_exec(task_t* task, uint64_t user_pml4_phys)
     task->vm_map = map;
     task->vm_brk_base = 0;
     task->vm_brk_end = 0;
-    task->vm_mmap_base = VM_DEFAULT_MMAP;
-    task->vm_mmap_hint = VM_DEFAULT_MMAP;
+    task->vm_mmap_base = VM_DEFAULT_MMAP - vm_aslr_offset(task, VM_ASLR_MMAP);
+    task->vm_mmap_hint = task->vm_mmap_base;
+    task->aslr_active = vm_aslr_level(task) != 0;
     return RDNX_OK;
 }
 
@@ -252,7 +469,42 @@ int vm_task_map_fixed(task_t* task, uint64_t start, uint64_t len, uint32_t prot,
     if (!task || !task->vm_map) {
         return RDNX_E_INVALID;
     }
-    return vm_map_add((vm_map_t*)task->vm_map, start, len, prot, flags | VM_MAP_F_FIXED, NULL, 0);
+    vm_map_t* map = (vm_map_t*)task->vm_map;
+    int rc = vm_map_add(map, start, len, prot, flags | VM_MAP_F_FIXED, NULL, 0);
+    if (rc != RDNX_OK) {
+        return rc;
+    }
+    /* The loader maps the top of the stack before registering it */
+    for (uint64_t va = vm_align_down(start); va < vm_align_up(start + len); va += VM_PAGE_SIZE) {
+        if (paging_get_physical_pml4(map->pml4_phys, va) & ~(VM_PAGE_SIZE - 1u)) {
+            map->resident++;
+        }
+    }
+    return RDNX_OK;
+}
+
+static uint32_t vm_object_map_flags(const vm_object_t* obj, uint32_t flags)
+{
+    if (obj->type == VM_OBJECT_FILE && (flags & VM_MAP_F_PRIVATE) != 0) {
+        /* Private file pages are mapped from the pager until written */
+        flags |= VM_MAP_F_COW;
+    }
+    return flags | VM_MAP_F_LAZY;
+}
+
+int vm_task_map_fixed_object(task_t* task,
+                             uint64_t start,
+                             uint64_t len,
+                             uint32_t prot,
+                             uint32_t flags,
+                             vm_object_t* obj,
+                             uint64_t object_offset)
+{
+    if (!task || !task->vm_map || !obj) {
+        return RDNX_E_INVALID;
+    }
+    return vm_map_add((vm_map_t*)task->vm_map, start, len, prot,
+                      vm_object_map_flags(obj, flags | VM_MAP_F_FIXED), obj, object_offset);
 }
 
 int vm_task_set_brk_base(task_t* task, uint64_t brk_base)
@@ -266,6 +518,16 @@ int vm_task_set_brk_base(task_t* task, uint64_t brk_base)
     return RDNX_OK;
 }
 
+/* RLIMIT_AS: @len more bytes of mappings stay within the soft limit */
+static int vm_task_as_fits(const task_t* task, const vm_map_t* map, uint64_t len)
+{
+    uint64_t limit = task->rlimit[TASK_RLIMIT_AS].cur;
+    if (limit == TASK_RLIM_INFINITY) {
+        return 1;
+    }
+    return map->size <= limit && len <= limit - map->size;
+}
+
 long vm_task_mmap(task_t* task, uint64_t addr_hint, uint64_t len, uint32_t prot, uint32_t flags)
 {
     if (!task || !task->vm_map || len == 0) {
@@ -274,6 +536,9 @@ long vm_task_mmap(task_t* task, uint64_t addr_hint, uint64_t len, uint32_t prot,
     uint64_t alen = vm_align_up(len);
     vm_map_t* map = (vm_map_t*)task->vm_map;
     uint64_t addr = 0;
+    if (!vm_task_as_fits(task, map, alen)) {
+        return (long)RDNX_E_NOMEM;
+    }
 
     if ((flags & VM_MAP_F_FIXED) != 0) {
         addr = vm_align_down(addr_hint);
@@ -281,7 +546,7 @@ long vm_task_mmap(task_t* task, uint64_t addr_hint, uint64_t len, uint32_t prot,
         (void)vm_map_remove(map, addr, alen, (uint64_t)(uintptr_t)task->address_space);
     } else {
         uint64_t hint = addr_hint ? addr_hint : task->vm_mmap_hint;
-        addr = vm_find_gap(map, hint, alen);
+        addr = vm_find_gap(map, task->vm_mmap_base, hint, alen);
     }
     if (!addr) {
         return (long)RDNX_E_NOMEM;
@@ -314,6 +579,9 @@ long vm_task_mmap_object(task_t* task,
     uint64_t alen = vm_align_up(len);
     vm_map_t* map = (vm_map_t*)task->vm_map;
     uint64_t addr = 0;
+    if (!vm_task_as_fits(task, map, alen)) {
+        return (long)RDNX_E_NOMEM;
+    }
 
     if ((flags & VM_MAP_F_FIXED) != 0) {
         addr = vm_align_down(addr_hint);
@@ -321,12 +589,12 @@ long vm_task_mmap_object(task_t* task,
         (void)vm_map_remove(map, addr, alen, (uint64_t)(uintptr_t)task->address_space);
     } else {
         uint64_t hint = addr_hint ? addr_hint : task->vm_mmap_hint;
-        addr = vm_find_gap(map, hint, alen);
+        addr = vm_find_gap(map, task->vm_mmap_base, hint, alen);
     }
     if (!addr) {
         return (long)RDNX_E_NOMEM;
     }
-    int rc = vm_map_add(map, addr, alen, prot, flags | VM_MAP_F_LAZY, obj, object_offset);
+    int rc = vm_map_add(map, addr, alen, prot, vm_object_map_flags(obj, flags), obj, object_offset);
     if (rc != RDNX_OK) {
         return (long)rc;
     }
@@ -334,63 +602,57 @@ long vm_task_mmap_object(task_t* task,
     return (long)addr;
 }
 
-long vm_task_mmap_file(task_t* task,
-                       uint64_t addr_hint,
-                       uint64_t len,
-                       uint32_t prot,
-                       uint32_t flags,
-                       const uint8_t* data,
-                       uint64_t data_size,
-                       uint64_t file_offset)
+/*
+ * Shared file mappings are written back once their pages are unmapped:
+ * a page mapped writable stays dirty only while some mapping can still
+ * store to it without faulting.
+ */
+int vm_task_munmap(task_t* task, uint64_t addr, uint64_t len)
 {
-    if (!task || !task->vm_map || len == 0 || !data) {
-        return (long)RDNX_E_INVALID;
+    if (!task || !task->vm_map || !task->address_space || len == 0) {
+        return RDNX_E_INVALID;
     }
-    uint64_t alen = vm_align_up(len);
     vm_map_t* map = (vm_map_t*)task->vm_map;
-    uint64_t addr = 0;
-
-    if ((flags & VM_MAP_F_FIXED) != 0) {
-        addr = vm_align_down(addr_hint);
-        /* MAP_FIXED replaces existing mappings in target range. */
-        (void)vm_map_remove(map, addr, alen, (uint64_t)(uintptr_t)task->address_space);
-    } else {
-        uint64_t hint = addr_hint ? addr_hint : task->vm_mmap_hint;
-        addr = vm_find_gap(map, hint, alen);
-    }
-    if (!addr) {
-        return (long)RDNX_E_NOMEM;
-    }
-
-    vm_object_t* obj = vm_object_create(VM_OBJECT_FILE, alen);
-    if (!obj) {
-        return (long)RDNX_E_NOMEM;
-    }
-    vm_file_backing_t* fb = (vm_file_backing_t*)kmalloc(sizeof(vm_file_backing_t));
-    if (!fb) {
-        vm_object_unref(obj);
-        return (long)RDNX_E_NOMEM;
+    uint64_t pml4 = (uint64_t)(uintptr_t)task->address_space;
+    uint64_t s = vm_align_down(addr);
+    uint64_t e = vm_align_up(addr + len);
+    if (e <= s) {
+        return RDNX_E_INVALID;
     }
-    fb->data = data;
-    fb->size = data_size;
-    fb->file_offset = 0;
-    obj->pager_private = fb;
 
-    int rc = vm_map_add(map, addr, alen, prot, flags | VM_MAP_F_LAZY, obj, file_offset);
-    vm_object_unref(obj);
-    if (rc != RDNX_OK) {
-        return (long)rc;
+    int removed = 0;
+    int result = RDNX_OK;
+    for (;;) {
+        /* Looked up again each round: the writeback may sleep */
+        vm_map_entry_t* me = vm_map_entry_above(map, s);
+        if (!me || me->start >= e) {
+            break;
+        }
+        uint64_t rs = (s > me->start) ? s : me->start;
+        uint64_t re = (e < me->end) ? e : me->end;
+        vm_object_t* writeback = NULL;
+        uint64_t off = me->object_offset + (rs - me->start);
+        if (me->object && me->object->type == VM_OBJECT_FILE && (me->flags & VM_MAP_F_PRIVATE) == 0) {
+            writeback = me->object;
+            vm_object_ref(writeback);
+        }
+        int rc = vm_map_remove(map, rs, re - rs, pml4);
+        if (writeback) {
+            int wrc = vm_pager_putpages(writeback, off, re - rs);
+            if (wrc != RDNX_OK && result == RDNX_OK) {
+                result = wrc;
+            }
+            vm_object_unref(writeback);
+        }
+        if (rc != RDNX_OK) {
+            return rc;
+        }
+        removed = 1;
     }
-    task->vm_mmap_hint = addr + alen;
-    return (long)addr;
-}
-
-int vm_task_munmap(task_t* task, uint64_t addr, uint64_t len)
-{
-    if (!task || !task->vm_map || !task->address_space) {
-        return RDNX_E_INVALID;
+    if (!removed) {
+        return RDNX_E_NOTFOUND;
     }
-    return vm_map_remove((vm_map_t*)task->vm_map, addr, len, (uint64_t)(uintptr_t)task->address_space);
+    return result;
 }
 
 long vm_task_brk(task_t* task, uint64_t new_break)
@@ -412,16 +674,35 @@ long vm_task_brk(task_t* task, uint64_t new_break)
     vm_map_t* map = (vm_map_t*)task->vm_map;
     if (new_end > task->vm_brk_end) {
         uint64_t len = new_end - task->vm_brk_end;
-        if (len > 0) {
+        uint64_t data_limit = task->rlimit[TASK_RLIMIT_DATA].cur;
+        if ((data_limit != TASK_RLIM_INFINITY && new_end - task->vm_brk_base > data_limit) ||
+            !vm_task_as_fits(task, map, len)) {
+            return (long)RDNX_E_NOMEM;
+        }
+        uint32_t prot = VM_PROT_READ | VM_PROT_WRITE;
+        uint32_t flags = VM_MAP_F_ANON | VM_MAP_F_PRIVATE | VM_MAP_F_LAZY;
+        vm_map_entry_t* heap = (task->vm_brk_end > task->vm_brk_base)
+                                   ? vm_map_lookup(map, task->vm_brk_end - 1u) : NULL;
+        if (len > 0 && heap && heap->end == task->vm_brk_end && heap->prot == prot &&
+            heap->flags == flags && heap->object && heap->object->type == VM_OBJECT_ANON &&
+            heap->object->ref_count == 1) {
+            /* Extend the heap entry and its object rather than adding one per sbrk */
+            if (vm_map_overlap(map, task->vm_brk_end, new_end)) {
+                return (long)RDNX_E_NOMEM;
+            }
+            int rc = vm_map_object_coalesce(heap, len);
+            if (rc != RDNX_OK) {
+                return (long)rc;
+            }
+            heap->end = new_end;
+            map->size += len;
+            vm_map_entry_regap(heap);
+        } else if (len > 0) {
             vm_object_t* obj = vm_object_create(VM_OBJECT_ANON, len);
             if (!obj) {
                 return (long)RDNX_E_NOMEM;
             }
-            int rc = vm_map_add(map, task->vm_brk_end, len,
-                                VM_PROT_READ | VM_PROT_WRITE,
-                                VM_MAP_F_ANON | VM_MAP_F_PRIVATE | VM_MAP_F_LAZY,
-                                obj,
-                                0);
+            int rc = vm_map_add(map, task->vm_brk_end, len, prot, flags, obj, 0);
             vm_object_unref(obj);
             if (rc != RDNX_OK) {
                 return (long)rc;
@@ -461,24 +742,30 @@ int vm_task_fork_clone(task_t* parent, task_t* child, uint64_t child_pml4_phys)
         return RDNX_E_NOMEM;
     }
 
-    for (uint32_t i = 0; i < pmap->entry_count; i++) {
-        if (cmap->entry_count >= VM_MAP_MAX_ENTRIES) {
+    for (vm_map_entry_t* pme = vm_map_first(pmap); pme; pme = vm_map_next(pme)) {
+        vm_map_entry_t* ce = (vm_map_entry_t*)kmem_cache_zalloc(g_vm_map_entries);
+        if (!ce) {
             vm_map_destroy(cmap);
-            return RDNX_E_BUSY;
+            return RDNX_E_NOMEM;
+        }
+        int cow = vm_entry_is_cow_candidate(pme);
+        if (cow) {
+            pme->flags |= VM_MAP_F_COW;
         }
-        vm_map_entry_t pe = pmap->entries[i];
-        vm_map_entry_t* ce = &cmap->entries[cmap->entry_count++];
-        *ce = pe;
+        vm_map_entry_t pe = *pme;
+        ce->start = pe.start;
+        ce->end = pe.end;
+        ce->prot = pe.prot;
+        ce->flags = pe.flags & ~VM_MAP_F_WIRED; /* Locks are not inherited */
+        ce->object = pe.object;
+        ce->object_offset = pe.object_offset;
         if (ce->object) {
             vm_object_ref(ce->object);
         }
+        vm_map_link(cmap, ce);
+        cmap->size += pe.end - pe.start;
 
-        int cow = vm_entry_is_cow_candidate(&pe);
-        if (cow) {
-            pmap->entries[i].flags |= VM_MAP_F_COW;
-            ce->flags |= VM_MAP_F_COW;
-        }
-
+        int downgraded = 0;
         for (uint64_t va = pe.start; va < pe.end; va += VM_PAGE_SIZE) {
             uint64_t phys = paging_get_physical(va) & ~(VM_PAGE_SIZE - 1u);
             if (!phys) {
@@ -501,11 +788,18 @@ int vm_task_fork_clone(task_t* parent, task_t* child, uint64_t child_pml4_phys)
                 return RDNX_E_GENERIC;
             }
             (void)vm_page_ref_retain(phys); /* Child mapping reference. */
+            cmap->resident++;
 
             if (cow) {
                 (void)paging_map_page_4kb_pml4((uint64_t)(uintptr_t)parent->address_space, va, phys, flags);
+                downgraded = 1;
             }
         }
+        if (downgraded) {
+            /* Other parent threads must not keep writable TLB entries. */
+            paging_tlb_shootdown((uint64_t)(uintptr_t)parent->address_space,
+                                 pe.start, (pe.end - pe.start) / VM_PAGE_SIZE);
+        }
     }
 
     child->vm_map = cmap;
@@ -522,16 +816,19 @@ void vm_task_destroy(task_t* task)
         return;
     }
     if (task->vm_map) {
+        /* The reaper runs at PASSIVE: the page-out scan walks this map under giant */
+        irql_t old = set_irql(IRQL_HIGH);
         vm_map_t* map = (vm_map_t*)task->vm_map;
-        while (map->entry_count > 0) {
-            vm_map_entry_t e = map->entries[0];
+        vm_map_entry_t* e;
+        while ((e = vm_map_first(map)) != NULL) {
             (void)vm_map_remove(map,
-                                e.start,
-                                e.end - e.start,
+                                e->start,
+                                e->end - e->start,
                                 (uint64_t)(uintptr_t)task->address_space);
         }
-        vm_map_destroy(map);
         task->vm_map = NULL;
+        (void)set_irql(old);
+        vm_map_destroy(map);
     }
     task->vm_brk_base = 0;
     task->vm_brk_end = 0;
@@ -539,6 +836,18 @@ void vm_task_destroy(task_t* task)
     task->vm_mmap_hint = 0;
 }
 
+uint64_t vm_task_vsize(const task_t* task)
+{
+    const vm_map_t* map = task ? (const vm_map_t*)task->vm_map : NULL;
+    return map ? map->size : 0;
+}
+
+uint64_t vm_task_rss(const task_t* task)
+{
+    const vm_map_t* map = task ? (const vm_map_t*)task->vm_map : NULL;
+    return map ? map->resident : 0;
+}
+
 int vm_task_msync(task_t* task, uint64_t addr, uint64_t len, uint32_t flags)
 {
     (void)flags;
@@ -553,39 +862,25 @@ int vm_task_msync(task_t* task, uint64_t addr, uint64_t len, uint32_t flags)
     }
 
     int did = 0;
-    for (uint32_t i = 0; i < map->entry_count; i++) {
-        vm_map_entry_t* me = &map->entries[i];
-        uint64_t rs = (s > me->start) ? s : me->start;
+    uint64_t cur = s;
+    vm_map_entry_t* me;
+    /* Looked up again after each entry: the writeback may sleep */
+    while (cur < e && (me = vm_map_entry_above(map, cur)) != NULL && me->start < e) {
+        uint64_t rs = (cur > me->start) ? cur : me->start;
         uint64_t re = (e < me->end) ? e : me->end;
-        if (re <= rs) {
-            continue;
-        }
+        cur = re;
         if (!me->object || me->object->type != VM_OBJECT_FILE) {
             continue;
         }
         if (me->flags & VM_MAP_F_PRIVATE) {
             continue;
         }
-        vm_file_backing_t* fb = (vm_file_backing_t*)me->object->pager_private;
-        if (!fb || !fb->data || fb->size == 0) {
-            continue;
-        }
-        uint8_t* dst = (uint8_t*)fb->data;
-        for (uint64_t va = rs; va < re; va += VM_PAGE_SIZE) {
-            uint64_t page_idx = (me->object_offset + (va - me->start)) / VM_PAGE_SIZE;
-            uint64_t phys = vm_object_get_resident_page(me->object, page_idx);
-            if (!phys) {
-                continue;
-            }
-            uint64_t off = fb->file_offset + me->object_offset + (va - me->start);
-            if (off >= fb->size) {
-                continue;
-            }
-            uint64_t avail = fb->size - off;
-            uint64_t copy = (avail > VM_PAGE_SIZE) ? VM_PAGE_SIZE : avail;
-            memcpy(dst + off, ARCH_PHYS_TO_VIRT(phys), (size_t)copy);
-            did = 1;
+        uint64_t off = me->object_offset + (rs - me->start);
+        int rc = vm_pager_putpages(me->object, off, re - rs);
+        if (rc != RDNX_OK) {
+            return rc;
         }
+        did = 1;
     }
     return did ? RDNX_OK : RDNX_E_NOTFOUND;
 }
@@ -606,20 +901,25 @@ int vm_task_mprotect(task_t* task, uint64_t addr, uint64_t len, uint32_t prot)
         return RDNX_E_INVALID;
     }
 
-    int changed = 0;
-    for (uint32_t i = 0; i < map->entry_count; i++) {
-        vm_map_entry_t* me = &map->entries[i];
-        uint64_t rs = (s > me->start) ? s : me->start;
-        uint64_t re = (e < me->end) ? e : me->end;
-        if (re <= rs) {
-            continue;
-        }
-        if (rs != me->start || re != me->end) {
-            return RDNX_E_UNSUPPORTED;
-        }
+    int rc = vm_map_clip(map, s, e);
+    if (rc != RDNX_OK) {
+        return rc;
+    }
 
+    int changed = 0;
+    vm_map_entry_t* me = vm_map_entry_above(map, s);
+    while (me && me->start < e) {
+        uint64_t rs = me->start;
+        uint64_t re = me->end;
         me->prot = prot;
-        uint64_t pte_flags = vm_pte_flags_from_prot(prot);
+        uint32_t map_prot = prot;
+        if ((me->flags & VM_MAP_F_COW) ||
+            (me->object && me->object->type == VM_OBJECT_FILE)) {
+            /* Write access is granted per page by the fault path */
+            map_prot &= ~VM_PROT_WRITE;
+        }
+        uint64_t pte_flags = vm_pte_flags_from_prot(map_prot);
+        int remapped = 0;
         for (uint64_t va = rs; va < re; va += VM_PAGE_SIZE) {
             uint64_t phys = paging_get_physical_pml4((uint64_t)(uintptr_t)task->address_space, va);
             phys &= ~(VM_PAGE_SIZE - 1u);
@@ -627,9 +927,496 @@ int vm_task_mprotect(task_t* task, uint64_t addr, uint64_t len, uint32_t prot)
                 continue;
             }
             (void)paging_map_page_4kb_pml4((uint64_t)(uintptr_t)task->address_space, va, phys, pte_flags);
+            remapped = 1;
+        }
+        if (remapped) {
+            paging_tlb_shootdown((uint64_t)(uintptr_t)task->address_space,
+                                 rs, (re - rs) / VM_PAGE_SIZE);
         }
         changed = 1;
+        me = vm_map_next(vm_map_simplify(map, me));
     }
 
     return changed ? RDNX_OK : RDNX_E_NOTFOUND;
 }
+
+/* [s, e) is mapped without holes */
+static int vm_map_covers(vm_map_t* map, uint64_t s, uint64_t e)
+{
+    uint64_t cur = s;
+    for (vm_map_entry_t* me = vm_map_lookup(map, s); me && me->start <= cur; me = vm_map_next(me)) {
+        cur = me->end;
+        if (cur >= e) {
+            return 1;
+        }
+    }
+    return 0;
+}
+
+/*
+ * Fault in every missing page of [s, e) in the current task's address
+ * space. Private writable pages are faulted for write so that wiring
+ * does not leave them shared with the object or a fork peer.
+ */
+static int vm_map_fault_in(task_t* task, uint64_t s, uint64_t e)
+{
+    vm_map_t* map = (vm_map_t*)task->vm_map;
+    for (uint64_t va = s; va < e; va += VM_PAGE_SIZE) {
+        vm_map_entry_t* me = vm_map_lookup(map, va);
+        if (!me) {
+            return RDNX_E_NOMEM;
+        }
+        if (paging_get_physical_pml4(map->pml4_phys, va) & ~(VM_PAGE_SIZE - 1u)) {
+            continue;
+        }
+        uint64_t err = VM_FAULT_USER;
+        if ((me->prot & VM_PROT_WRITE) && (me->flags & VM_MAP_F_PRIVATE)) {
+            err |= VM_FAULT_WRITE;
+        }
+        int rc = vm_fault_handle(task, va, err, 0);
+        if (rc != RDNX_OK) {
+            return rc;
+        }
+    }
+    return RDNX_OK;
+}
+
+/*
+ * MADV_DONTNEED / MADV_FREE: drop the pages of [s, e). Private anonymous
+ * memory reads back as zeroes, shared and file mappings refault from
+ * their object. Both are refused on wired ranges; MADV_FREE applies to
+ * private anonymous memory only and is not deferred.
+ */
+static int vm_map_dontneed(vm_map_t* map, uint64_t s, uint64_t e, int advice)
+{
+    for (vm_map_entry_t* me = vm_map_lookup(map, s); me && me->start < e; me = vm_map_next(me)) {
+        if (me->flags & VM_MAP_F_WIRED) {
+            return RDNX_E_INVALID;
+        }
+        if (advice == VM_MADV_FREE &&
+            ((me->flags & VM_MAP_F_PRIVATE) == 0 || (me->flags & VM_MAP_F_ANON) == 0)) {
+            return RDNX_E_INVALID;
+        }
+    }
+    int rc = vm_map_clip(map, s, e);
+    if (rc != RDNX_OK) {
+        return rc;
+    }
+    vm_map_entry_t* me = vm_map_entry_above(map, s);
+    while (me && me->start < e) {
+        vm_unmap_range(map, me->start, me->end);
+        vm_object_t* obj = me->object;
+        if (obj && obj->type == VM_OBJECT_ANON && (me->flags & VM_MAP_F_PRIVATE)) {
+            if (me->flags & VM_MAP_F_COW) {
+                /* The object is shared with a fork peer: continue with an empty one */
+                vm_object_t* fresh = vm_object_create(VM_OBJECT_ANON, me->end - me->start);
+                if (!fresh) {
+                    return RDNX_E_NOMEM;
+                }
+                vm_object_unref(obj);
+                me->object = fresh;
+                me->object_offset = 0;
+                me->flags &= ~VM_MAP_F_COW;
+            } else {
+                vm_object_free_pages(obj, me->object_offset / VM_PAGE_SIZE,
+                                     (me->end - me->start) / VM_PAGE_SIZE);
+            }
+        }
+        me = vm_map_next(vm_map_simplify(map, me));
+    }
+    return RDNX_OK;
+}
+
+/* MADV_WILLNEED: read swapped-out and file pages in ahead of the faults */
+static void vm_map_willneed(vm_map_t* map, uint64_t s, uint64_t e)
+{
+    uint64_t va = s;
+    while (va < e) {
+        /* Looked up again for every page: the pager may sleep */
+        vm_map_entry_t* me = vm_map_lookup(map, va);
+        if (!me) {
+            break;
+        }
+        vm_object_t* obj = me->object;
+        if (!obj || !obj->pager) {
+            va = (e < me->end) ? e : me->end;
+            continue;
+        }
+        uint64_t pindex = (me->object_offset + (va - me->start)) / VM_PAGE_SIZE;
+        va += VM_PAGE_SIZE;
+        if (vm_object_get_resident_page(obj, pindex)) {
+            continue;
+        }
+        if (obj->type == VM_OBJECT_ANON &&
+            (pindex >= obj->page_count || (obj->resident_pages[pindex] & VM_OBJECT_SWAPPED) == 0)) {
+            continue; /* Never touched: the fault hands out a zero page */
+        }
+        uint64_t phys = 0;
+        vm_object_ref(obj);
+        int rc = vm_pager_getpages(obj, pindex, 1, 0, &phys);
+        vm_object_unref(obj);
+        if (rc == RDNX_OK) {
+            vm_pager_release(&phys, 1); /* The object or the page cache keeps it */
+        } else if (rc == RDNX_E_NOMEM) {
+            break;
+        }
+    }
+}
+
+int vm_task_madvise(task_t* task, uint64_t addr, uint64_t len, int advice)
+{
+    if (!task || !task->vm_map || !task->address_space || (addr & (VM_PAGE_SIZE - 1u)) != 0) {
+        return RDNX_E_INVALID;
+    }
+    uint64_t e = vm_align_up(addr + len);
+    if (e < addr) {
+        return RDNX_E_INVALID;
+    }
+    if (e == addr) {
+        return RDNX_OK;
+    }
+    vm_map_t* map = (vm_map_t*)task->vm_map;
+    if (!vm_map_covers(map, addr, e)) {
+        return RDNX_E_NOMEM;
+    }
+    switch (advice) {
+    case VM_MADV_NORMAL:
+    case VM_MADV_RANDOM:
+    case VM_MADV_SEQUENTIAL:
+        return RDNX_OK; /* No read-ahead to tune yet */
+    case VM_MADV_WILLNEED:
+        vm_map_willneed(map, addr, e);
+        return RDNX_OK;
+    case VM_MADV_DONTNEED:
+    case VM_MADV_FREE:
+        return vm_map_dontneed(map, addr, e, advice);
+    default:
+        return RDNX_E_INVALID;
+    }
+}
+
+/*
+ * RLIMIT_MEMLOCK bounds the wired bytes of the map; euid 0 is exempt.
+ * Wired entries are faulted in here and their frames are never unmapped
+ * by the page-out scan, so they stay resident until munlock or munmap.
+ */
+int vm_task_mlock(task_t* task, uint64_t addr, uint64_t len)
+{
+    if (!task || !task->vm_map || !task->address_space) {
+        return RDNX_E_INVALID;
+    }
+    uint64_t s = vm_align_down(addr);
+    uint64_t e = vm_align_up(addr + len);
+    if (e < s) {
+        return RDNX_E_INVALID;
+    }
+    if (e == s) {
+        return RDNX_OK;
+    }
+    vm_map_t* map = (vm_map_t*)task->vm_map;
+    if (!vm_map_covers(map, s, e)) {
+        return RDNX_E_NOMEM;
+    }
+
+    uint64_t add = 0;
+    for (vm_map_entry_t* me = vm_map_lookup(map, s); me && me->start < e; me = vm_map_next(me)) {
+        if ((me->flags & VM_MAP_F_WIRED) == 0) {
+            add += ((e < me->end) ? e : me->end) - ((s > me->start) ? s : me->start);
+        }
+    }
+    uint64_t limit = task->rlimit[TASK_RLIMIT_MEMLOCK].cur;
+    if (limit != TASK_RLIM_INFINITY && task_get_euid(task) != 0 &&
+        (map->wired > limit || add > limit - map->wired)) {
+        return RDNX_E_NOMEM;
+    }
+
+    int rc = vm_map_clip(map, s, e);
+    if (rc != RDNX_OK) {
+        return rc;
+    }
+    vm_map_entry_t* me = vm_map_entry_above(map, s);
+    while (me && me->start < e) {
+        if ((me->flags & VM_MAP_F_WIRED) == 0) {
+            me->flags |= VM_MAP_F_WIRED;
+            map->wired += me->end - me->start;
+        }
+        me = vm_map_next(vm_map_simplify(map, me));
+    }
+    return vm_map_fault_in(task, s, e);
+}
+
+int vm_task_munlock(task_t* task, uint64_t addr, uint64_t len)
+{
+    if (!task || !task->vm_map) {
+        return RDNX_E_INVALID;
+    }
+    uint64_t s = vm_align_down(addr);
+    uint64_t e = vm_align_up(addr + len);
+    if (e < s) {
+        return RDNX_E_INVALID;
+    }
+    if (e == s) {
+        return RDNX_OK;
+    }
+    vm_map_t* map = (vm_map_t*)task->vm_map;
+    if (!vm_map_covers(map, s, e)) {
+        return RDNX_E_NOMEM;
+    }
+    int rc = vm_map_clip(map, s, e);
+    if (rc != RDNX_OK) {
+        return rc;
+    }
+    vm_map_entry_t* me = vm_map_entry_above(map, s);
+    while (me && me->start < e) {
+        if (me->flags & VM_MAP_F_WIRED) {
+            me->flags &= ~VM_MAP_F_WIRED;
+            map->wired -= me->end - me->start;
+        }
+        me = vm_map_next(vm_map_simplify(map, me));
+    }
+    return RDNX_OK;
+}
+
+/*
+ * A page counts as resident when it is mapped here or when its object
+ * holds it in memory (not swapped out, or in the page cache).
+ */
+int vm_task_mincore(task_t* task, uint64_t addr, uint64_t len, uint8_t* vec)
+{
+    if (!task || !task->vm_map || !vec || (addr & (VM_PAGE_SIZE - 1u)) != 0) {
+        return RDNX_E_INVALID;
+    }
+    uint64_t e = vm_align_up(addr + len);
+    if (e < addr) {
+        return RDNX_E_INVALID;
+    }
+    vm_map_t* map = (vm_map_t*)task->vm_map;
+    if (e > addr && !vm_map_covers(map, addr, e)) {
+        return RDNX_E_NOMEM;
+    }
+    for (uint64_t va = addr; va < e; va += VM_PAGE_SIZE) {
+        vm_map_entry_t* me = vm_map_lookup(map, va);
+        uint8_t in = 0;
+        if (paging_get_physical_pml4(map->pml4_phys, va) & ~(VM_PAGE_SIZE - 1u)) {
+            in = VM_MINCORE_RESIDENT;
+        } else if (me && me->object) {
+            uint64_t pindex = (me->object_offset + (va - me->start)) / VM_PAGE_SIZE;
+            if (vm_object_get_resident_page(me->object, pindex)) {
+                in = VM_MINCORE_RESIDENT;
+            }
+        }
+        vec[(va - addr) / VM_PAGE_SIZE] = in;
+    }
+    return RDNX_OK;
+}
+
+/* Shared anonymous objects grow only while no other entry maps them */
+static int vm_map_extendable(const vm_map_entry_t* me, uint64_t len)
+{
+    const vm_object_t* obj = me->object;
+    if (!obj || obj->type != VM_OBJECT_ANON || (me->flags & VM_MAP_F_PRIVATE)) {
+        return 1;
+    }
+    uint64_t off = me->object_offset + (me->end - me->start);
+    return obj->ref_count == 1 || off + len <= obj->page_count * VM_PAGE_SIZE;
+}
+
+/*
+ * Grow @me by @len bytes into the free range above it. An anonymous
+ * object mapped by @me alone grows with it; other private anonymous
+ * entries get a fresh object for the new range.
+ */
+static int vm_map_extend(vm_map_t* map, vm_map_entry_t* me, uint64_t len)
+{
+    vm_object_t* obj = me->object;
+    if (obj && obj->type == VM_OBJECT_ANON) {
+        if (obj->ref_count == 1 && (me->flags & VM_MAP_F_COW) == 0) {
+            int rc = vm_map_object_coalesce(me, len);
+            if (rc != RDNX_OK) {
+                return rc;
+            }
+        } else if (me->flags & VM_MAP_F_PRIVATE) {
+            vm_object_t* fresh = vm_object_create(VM_OBJECT_ANON, len);
+            if (!fresh) {
+                return RDNX_E_NOMEM;
+            }
+            int rc = vm_map_add(map, me->end, len, me->prot,
+                                (me->flags & ~VM_MAP_F_COW) | VM_MAP_F_LAZY, fresh, 0);
+            vm_object_unref(fresh);
+            if (rc == RDNX_OK && (me->flags & VM_MAP_F_WIRED)) {
+                map->wired += len;
+            }
+            return rc;
+        } else if (!vm_map_extendable(me, len)) {
+            return RDNX_E_NOMEM;
+        }
+    }
+    me->end += len;
+    map->size += len;
+    if (me->flags & VM_MAP_F_WIRED) {
+        map->wired += len;
+    }
+    vm_map_entry_regap(me);
+    return RDNX_OK;
+}
+
+/*
+ * Move @me and its pages to the free range at @dst. Frame references
+ * move with the PTEs, so the resident count does not change.
+ */
+static int vm_map_move(vm_map_t* map, vm_map_entry_t* me, uint64_t dst)
+{
+    uint64_t pml4 = map->pml4_phys;
+    uint64_t src = me->start;
+    uint64_t len = me->end - me->start;
+    uint32_t prot = me->prot;
+    if ((me->flags & VM_MAP_F_COW) || (me->object && me->object->type == VM_OBJECT_FILE)) {
+        /* Write access is granted per page by the fault path */
+        prot &= ~VM_PROT_WRITE;
+    }
+    uint64_t pte_flags = vm_pte_flags_from_prot(prot);
+    int moved = 0;
+    for (uint64_t off = 0; off < len; off += VM_PAGE_SIZE) {
+        uint64_t phys = paging_get_physical_pml4(pml4, src + off) & ~(VM_PAGE_SIZE - 1u);
+        if (!phys) {
+            continue;
+        }
+        if (paging_map_page_4kb_pml4(pml4, dst + off, phys, pte_flags) != RDNX_OK) {
+            /* No memory for page tables: drop the copies made so far */
+            for (uint64_t undo = 0; undo < off; undo += VM_PAGE_SIZE) {
+                (void)paging_unmap_page_pml4(pml4, dst + undo);
+            }
+            paging_tlb_shootdown(pml4, dst, off / VM_PAGE_SIZE);
+            return RDNX_E_NOMEM;
+        }
+        moved = 1;
+    }
+    if (moved) {
+        for (uint64_t off = 0; off < len; off += VM_PAGE_SIZE) {
+            (void)paging_unmap_page_pml4(pml4, src + off);
+        }
+        paging_tlb_shootdown(pml4, src, len / VM_PAGE_SIZE);
+    }
+    vm_map_unlink(map, me);
+    me->start = dst;
+    me->end = dst + len;
+    vm_map_link(map, me);
+    return RDNX_OK;
+}
+
+/*
+ * Resize the mapping at [old_addr, old_addr + old_len), which must lie in
+ * one entry. Shrinking unmaps the tail; growing extends in place when the
+ * range above is free, otherwise VM_MREMAP_MAYMOVE moves the mapping (to
+ * @new_addr with VM_MREMAP_FIXED). @return the new address or an error.
+ */
+long vm_task_mremap(task_t* task,
+                    uint64_t old_addr,
+                    uint64_t old_len,
+                    uint64_t new_len,
+                    uint32_t flags,
+                    uint64_t new_addr)
+{
+    if (!task || !task->vm_map || !task->address_space) {
+        return (long)RDNX_E_INVALID;
+    }
+    uint64_t olen = vm_align_up(old_len);
+    uint64_t nlen = vm_align_up(new_len);
+    if ((old_addr & (VM_PAGE_SIZE - 1u)) != 0 || olen == 0 || nlen == 0 ||
+        (flags & ~(VM_MREMAP_MAYMOVE | VM_MREMAP_FIXED)) != 0) {
+        return (long)RDNX_E_INVALID;
+    }
+    int fixed = (flags & VM_MREMAP_FIXED) != 0;
+    if (fixed && ((flags & VM_MREMAP_MAYMOVE) == 0 || (new_addr & (VM_PAGE_SIZE - 1u)) != 0 ||
+                  !vm_range_valid(new_addr, new_addr + nlen) ||
+                  (new_addr < old_addr + olen && old_addr < new_addr + nlen))) {
+        return (long)RDNX_E_INVALID;
+    }
+    vm_map_t* map = (vm_map_t*)task->vm_map;
+    vm_map_entry_t* me = vm_map_lookup(map, old_addr);
+    if (!me || me->end - old_addr < olen) {
+        return (long)RDNX_E_NOTFOUND;
+    }
+
+    if (nlen < olen) {
+        int rc = vm_task_munmap(task, old_addr + nlen, olen - nlen);
+        if (rc != RDNX_OK) {
+            return (long)rc;
+        }
+        olen = nlen;
+    }
+    if (nlen == olen && !fixed) {
+        return (long)old_addr;
+    }
+    uint64_t grow = nlen - olen;
+    if (!vm_task_as_fits(task, map, grow)) {
+        return (long)RDNX_E_NOMEM;
+    }
+
+    if (!fixed) {
+        uint64_t end = old_addr + olen;
+        if (me->end == end && vm_range_valid(end, end + grow) &&
+            !vm_map_overlap(map, end, end + grow) && vm_map_extendable(me, grow)) {
+            int rc = vm_map_extend(map, me, grow);
+            if (rc != RDNX_OK) {
+                return (long)rc;
+            }
+            if (me->flags & VM_MAP_F_WIRED) {
+                (void)vm_map_fault_in(task, end, end + grow);
+            }
+            return (long)old_addr;
+        }
+        if ((flags & VM_MREMAP_MAYMOVE) == 0) {
+            return (long)RDNX_E_NOMEM;
+        }
+    }
+
+    /* Everything that can fail runs before the destination is touched */
+    int rc = vm_map_clip(map, old_addr, old_addr + olen);
+    if (rc != RDNX_OK) {
+        return (long)rc;
+    }
+    me = vm_map_lookup(map, old_addr);
+    if (!vm_map_extendable(me, grow)) {
+        (void)vm_map_simplify(map, me);
+        return (long)RDNX_E_NOMEM;
+    }
+    uint64_t dst = new_addr;
+    if (fixed) {
+        /* Split first so that removing the range below cannot fail halfway */
+        rc = vm_map_clip(map, dst, dst + nlen);
+        if (rc != RDNX_OK) {
+            (void)vm_map_simplify(map, me);
+            return (long)rc;
+        }
+        /* Like MAP_FIXED: whatever is mapped there goes away */
+        (void)vm_map_remove(map, dst, nlen, map->pml4_phys);
+    } else {
+        dst = vm_find_gap(map, task->vm_mmap_base, task->vm_mmap_hint, nlen);
+        if (!dst) {
+            (void)vm_map_simplify(map, me);
+            return (long)RDNX_E_NOMEM;
+        }
+    }
+    rc = vm_map_move(map, me, dst);
+    if (rc != RDNX_OK) {
+        (void)vm_map_simplify(map, me);
+        return (long)rc;
+    }
+    if (grow > 0) {
+        rc = vm_map_extend(map, me, grow);
+        if (rc != RDNX_OK) {
+            (void)vm_map_move(map, me, old_addr); /* Its page tables are still there */
+            (void)vm_map_simplify(map, me);
+            return (long)rc;
+        }
+        if (me->flags & VM_MAP_F_WIRED) {
+            (void)vm_map_fault_in(task, dst + olen, dst + nlen);
+        }
+    }
+    (void)vm_map_simplify(map, me);
+    if (!fixed) {
+        task->vm_mmap_hint = dst + nlen;
+    }
+    return (long)dst;
+}
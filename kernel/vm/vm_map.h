@@ -2,11 +2,11 @@
 #define _RODNIX_VM_MAP_H
 
 #include <stdint.h>
+#include <bsd/sys/tree.h>
 #include "../core/task.h"
 #include "vm_object.h"
 
 #define VM_PAGE_SIZE 0x1000ULL
-#define VM_MAP_MAX_ENTRIES 128
 
 #define VM_PROT_NONE  0u
 #define VM_PROT_READ  (1u << 0)
@@ -19,6 +19,19 @@
 #define VM_MAP_F_LAZY    (1u << 3)
 #define VM_MAP_F_STACK   (1u << 4)
 #define VM_MAP_F_COW     (1u << 5)
+#define VM_MAP_F_WIRED   (1u << 6) /* mlock: faulted in, skipped by the page-out scan */
+
+/* madvise advice (FreeBSD values) */
+#define VM_MADV_NORMAL     0
+#define VM_MADV_RANDOM     1
+#define VM_MADV_SEQUENTIAL 2
+#define VM_MADV_WILLNEED   3
+#define VM_MADV_DONTNEED   4
+#define VM_MADV_FREE       5
+
+/* mremap flags (Linux values) */
+#define VM_MREMAP_MAYMOVE (1u << 0)
+#define VM_MREMAP_FIXED   (1u << 1)
 
 typedef struct vm_map_entry {
     uint64_t start;
@@ -27,16 +40,36 @@ typedef struct vm_map_entry {
     uint32_t flags;
     vm_object_t* object;
     uint64_t object_offset;
+    RB_ENTRY(vm_map_entry) link;
+    uint64_t adj_free;  /* Free space up to the next entry (or the user limit) */
+    uint64_t max_free;  /* Largest adj_free in this subtree */
 } vm_map_entry_t;
 
+RB_HEAD(vm_map_tree, vm_map_entry);
+
+/*
+ * Entries are kept in an RB-tree ordered by address; each node caches the
+ * largest free gap of its subtree so that mmap finds free space without a
+ * linear scan.
+ */
 typedef struct vm_map {
     uint64_t pml4_phys;
     uint32_t entry_count;
-    vm_map_entry_t entries[VM_MAP_MAX_ENTRIES];
+    struct vm_map_tree entries;
+    uint64_t size;      /* Bytes covered by entries (virtual size) */
+    uint64_t resident;  /* Pages currently mapped in pml4_phys (RSS) */
+    uint64_t wired;     /* Bytes covered by VM_MAP_F_WIRED entries */
 } vm_map_t;
 
 int vm_task_prepare_exec(task_t* task, uint64_t user_pml4_phys);
 int vm_task_map_fixed(task_t* task, uint64_t start, uint64_t len, uint32_t prot, uint32_t flags);
+int vm_task_map_fixed_object(task_t* task,
+                             uint64_t start,
+                             uint64_t len,
+                             uint32_t prot,
+                             uint32_t flags,
+                             vm_object_t* obj,
+                             uint64_t object_offset);
 int vm_task_set_brk_base(task_t* task, uint64_t brk_base);
 long vm_task_mmap(task_t* task, uint64_t addr_hint, uint64_t len, uint32_t prot, uint32_t flags);
 long vm_task_mmap_object(task_t* task,
@@ -46,21 +79,30 @@ long vm_task_mmap_object(task_t* task,
                          uint32_t flags,
                          vm_object_t* obj,
                          uint64_t object_offset);
-long vm_task_mmap_file(task_t* task,
-                       uint64_t addr_hint,
-                       uint64_t len,
-                       uint32_t prot,
-                       uint32_t flags,
-                       const uint8_t* data,
-                       uint64_t data_size,
-                       uint64_t file_offset);
 int vm_task_munmap(task_t* task, uint64_t addr, uint64_t len);
 int vm_task_msync(task_t* task, uint64_t addr, uint64_t len, uint32_t flags);
 int vm_task_mprotect(task_t* task, uint64_t addr, uint64_t len, uint32_t prot);
+int vm_task_madvise(task_t* task, uint64_t addr, uint64_t len, int advice);
+int vm_task_mlock(task_t* task, uint64_t addr, uint64_t len);
+int vm_task_munlock(task_t* task, uint64_t addr, uint64_t len);
+/* One byte per page of [addr, addr + len) into @vec: 1 if resident */
+int vm_task_mincore(task_t* task, uint64_t addr, uint64_t len, uint8_t* vec);
+long vm_task_mremap(task_t* task,
+                    uint64_t old_addr,
+                    uint64_t old_len,
+                    uint64_t new_len,
+                    uint32_t flags,
+                    uint64_t new_addr);
 long vm_task_brk(task_t* task, uint64_t new_break);
 int vm_task_fork_clone(task_t* parent, task_t* child, uint64_t child_pml4_phys);
 void vm_task_destroy(task_t* task);
+/* Accounting of the task's map: virtual size in bytes, resident pages */
+uint64_t vm_task_vsize(const task_t* task);
+uint64_t vm_task_rss(const task_t* task);
 
 vm_map_entry_t* vm_map_lookup(vm_map_t* map, uint64_t addr);
+/* In-order walk; a returned entry stays valid until the map is modified */
+vm_map_entry_t* vm_map_first(vm_map_t* map);
+vm_map_entry_t* vm_map_next(vm_map_entry_t* e);
 
 #endif /* _RODNIX_VM_MAP_H */
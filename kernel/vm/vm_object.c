@@ -1,10 +1,31 @@
 #include "vm_object.h"
 #include "vm_page_ref.h"
+#include "vm_page_cache.h"
+#include "vm_pager.h"
+#include "vm_pageout.h"
+#include "vm_swap.h"
 #include "../common/heap.h"
 #include "../arch/config.h"
+#include "../core/interrupts.h"
 #include "../../include/common.h"
 #include "../../include/error.h"
 
+/*
+ * LOCKING: vm_object_t.ref_count — IRQL giant (vm_object_lock).
+ *   File objects are shared by every mapping and exec image of a file,
+ *   so references are taken and dropped from several CPUs. The drop to
+ *   zero and the unlinking from global lists happen under one hold.
+ */
+static inline irql_t vm_object_lock(void)
+{
+    return set_irql(IRQL_HIGH);
+}
+
+static inline void vm_object_unlock(irql_t old)
+{
+    (void)set_irql(old);
+}
+
 static uint64_t vm_object_align_up(uint64_t value)
 {
     return (value + VM_OBJECT_PAGE_SIZE - 1u) & ~(VM_OBJECT_PAGE_SIZE - 1u);
@@ -21,13 +42,19 @@ vm_object_t* vm_object_create(vm_object_type_t type, uint64_t size)
     obj->size = size;
     uint64_t aligned = vm_object_align_up(size ? size : VM_OBJECT_PAGE_SIZE);
     obj->page_count = aligned / VM_OBJECT_PAGE_SIZE;
+    obj->ref_count = 1;
+    TAILQ_INIT(&obj->cache_pages);
+    if (type == VM_OBJECT_FILE) {
+        /* File pages are indexed by the page cache, not by a fixed array */
+        return obj;
+    }
     obj->resident_pages = (uint64_t*)kmalloc((size_t)(obj->page_count * sizeof(uint64_t)));
     if (!obj->resident_pages) {
         kfree(obj);
         return NULL;
     }
     memset(obj->resident_pages, 0, (size_t)(obj->page_count * sizeof(uint64_t)));
-    obj->ref_count = 1;
+    obj->pager = &vm_swap_pager;
     return obj;
 }
 
@@ -36,7 +63,9 @@ void vm_object_ref(vm_object_t* obj)
     if (!obj) {
         return;
     }
+    irql_t old = vm_object_lock();
     obj->ref_count++;
+    vm_object_unlock(old);
 }
 
 void vm_object_unref(vm_object_t* obj)
@@ -44,52 +73,55 @@ void vm_object_unref(vm_object_t* obj)
     if (!obj) {
         return;
     }
+    irql_t old = vm_object_lock();
     if (obj->ref_count > 0) {
         obj->ref_count--;
     }
-    if (obj->ref_count == 0) {
-        vm_file_backing_t* fb = (vm_file_backing_t*)obj->pager_private;
-        if (obj->type == VM_OBJECT_FILE && fb && fb->data && fb->size > 0) {
-            uint8_t* dst = (uint8_t*)fb->data;
-            for (uint64_t i = 0; i < obj->page_count; i++) {
-                uint64_t phys = obj->resident_pages ? obj->resident_pages[i] : 0;
-                if (!phys) {
-                    continue;
-                }
-                uint64_t off = fb->file_offset + i * VM_OBJECT_PAGE_SIZE;
-                if (off >= fb->size) {
-                    continue;
-                }
-                uint64_t avail = fb->size - off;
-                uint64_t copy = (avail > VM_OBJECT_PAGE_SIZE) ? VM_OBJECT_PAGE_SIZE : avail;
-                memcpy(dst + off, ARCH_PHYS_TO_VIRT(phys), (size_t)copy);
-            }
-        }
-        if (obj->resident_pages) {
-            for (uint64_t i = 0; i < obj->page_count; i++) {
-                uint64_t phys = obj->resident_pages[i];
-                if (phys) {
-                    (void)vm_page_ref_release(phys); /* Drop vm_object ownership ref. */
-                    obj->resident_pages[i] = 0;
-                }
+    if (obj->ref_count != 0) {
+        vm_object_unlock(old);
+        return;
+    }
+    /*
+     * Unlink from the swap object list, the page cache and the page-out
+     * queues before dropping the lock: their scanners take references
+     * on objects they find there, and must not find this one anymore.
+     */
+    if (obj->pager && obj->pager->dealloc) {
+        obj->pager->dealloc(obj);
+    }
+    if (obj->resident_pages) {
+        for (uint64_t i = 0; i < obj->page_count; i++) {
+            uint64_t phys = obj->resident_pages[i];
+            if (phys && (phys & VM_OBJECT_SWAPPED) == 0) {
+                vm_pageout_page_remove(phys);
+                (void)vm_page_ref_release(phys); /* Drop vm_object ownership ref. */
+                obj->resident_pages[i] = 0;
             }
-            kfree(obj->resident_pages);
-            obj->resident_pages = NULL;
-        }
-        if (obj->pager_private) {
-            kfree(obj->pager_private);
-            obj->pager_private = NULL;
         }
-        kfree(obj);
     }
+    vm_object_unlock(old);
+
+    if (obj->resident_pages) {
+        kfree(obj->resident_pages);
+        obj->resident_pages = NULL;
+    }
+    if (obj->pager_private) {
+        kfree(obj->pager_private);
+        obj->pager_private = NULL;
+    }
+    kfree(obj);
 }
 
 uint64_t vm_object_get_resident_page(const vm_object_t* obj, uint64_t page_index)
 {
+    if (obj && obj->type == VM_OBJECT_FILE) {
+        return vm_page_cache_lookup(obj, page_index);
+    }
     if (!obj || !obj->resident_pages || page_index >= obj->page_count) {
         return 0;
     }
-    return obj->resident_pages[page_index];
+    uint64_t phys = obj->resident_pages[page_index];
+    return (phys & VM_OBJECT_SWAPPED) ? 0 : phys;
 }
 
 int vm_object_set_resident_page(vm_object_t* obj, uint64_t page_index, uint64_t phys)
@@ -101,10 +133,60 @@ int vm_object_set_resident_page(vm_object_t* obj, uint64_t page_index, uint64_t
     if (old == phys) {
         return RDNX_OK;
     }
-    if (old) {
+    if (old & VM_OBJECT_SWAPPED) {
+        vm_swap_discard(obj, page_index);
+    } else if (old) {
+        vm_pageout_page_remove(old);
         (void)vm_page_ref_release(old);
     }
     (void)vm_page_ref_retain(phys);
     obj->resident_pages[page_index] = phys;
+    vm_pageout_page_insert(obj, page_index, phys);
+    return RDNX_OK;
+}
+
+void vm_object_free_pages(vm_object_t* obj, uint64_t page_index, uint64_t count)
+{
+    if (!obj || !obj->resident_pages) {
+        return;
+    }
+    for (uint64_t i = page_index; i < obj->page_count && i - page_index < count; i++) {
+        uint64_t phys = obj->resident_pages[i];
+        if (phys & VM_OBJECT_SWAPPED) {
+            vm_swap_discard(obj, i);
+        } else if (phys) {
+            vm_pageout_page_remove(phys);
+            obj->resident_pages[i] = 0;
+            (void)vm_page_ref_release(phys); /* Mappings keep their own references */
+        }
+    }
+}
+
+int vm_object_grow(vm_object_t* obj, uint64_t size)
+{
+    if (!obj || !obj->resident_pages) {
+        return RDNX_E_INVALID;
+    }
+    uint64_t count = vm_object_align_up(size) / VM_OBJECT_PAGE_SIZE;
+    if (count <= obj->page_count) {
+        if (size > obj->size) {
+            obj->size = size;
+        }
+        return RDNX_OK;
+    }
+    uint64_t* pages = (uint64_t*)kmalloc((size_t)(count * sizeof(uint64_t)));
+    if (!pages) {
+        return RDNX_E_NOMEM;
+    }
+    memset(pages, 0, (size_t)(count * sizeof(uint64_t)));
+    /* The swap and page-out code index the array under the same giant */
+    irql_t old = vm_object_lock();
+    memcpy(pages, obj->resident_pages, (size_t)(obj->page_count * sizeof(uint64_t)));
+    uint64_t* stale = obj->resident_pages;
+    obj->resident_pages = pages;
+    obj->page_count = count;
+    obj->size = size;
+    vm_object_unlock(old);
+    kfree(stale);
     return RDNX_OK;
 }
@@ -2,9 +2,20 @@
 #define _RODNIX_VM_OBJECT_H
 
 #include <stdint.h>
+#include <bsd/sys/queue.h>
 
 #define VM_OBJECT_PAGE_SIZE 0x1000ULL
 
+/*
+ * VM_OBJECT_ANON: a resident_pages entry with this bit set is not a frame
+ * but a swap slot (vm_swap.c) holding the page's contents.
+ */
+#define VM_OBJECT_SWAPPED 0x1ULL
+
+struct vm_page_cache_entry;
+struct vm_page_cache_ops;
+struct vm_pager_ops;
+
 typedef enum {
     VM_OBJECT_ANON = 1,
     VM_OBJECT_FILE = 2
@@ -15,20 +26,28 @@ typedef struct vm_object {
     uint32_t ref_count;
     uint64_t size;
     uint64_t page_count;
-    uint64_t* resident_pages;
+    uint64_t* resident_pages;   /* VM_OBJECT_ANON only */
+    uint64_t swapped;           /* VM_OBJECT_ANON: entries on swap */
+    LIST_ENTRY(vm_object) swap_link; /* On the swap list while swapped > 0 */
+    const struct vm_pager_ops* pager; /* vnode pager (FILE) or swap pager (ANON) */
     void* pager_private;
+    /* VM_OBJECT_FILE: pages live in the page cache (vm_page_cache.h) */
+    const struct vm_page_cache_ops* cache_ops; /* NULL: memory only, never evicted */
+    void* cache_owner;
+    TAILQ_HEAD(vm_page_cache_list, vm_page_cache_entry) cache_pages;
+    uint64_t cache_resident;
+    uint64_t cache_dirty;
 } vm_object_t;
 
-typedef struct vm_file_backing {
-    const uint8_t* data;
-    uint64_t size;
-    uint64_t file_offset;
-} vm_file_backing_t;
-
 vm_object_t* vm_object_create(vm_object_type_t type, uint64_t size);
 void vm_object_ref(vm_object_t* obj);
 void vm_object_unref(vm_object_t* obj);
+/* Resident frame of a page, 0 if absent or swapped out */
 uint64_t vm_object_get_resident_page(const vm_object_t* obj, uint64_t page_index);
 int vm_object_set_resident_page(vm_object_t* obj, uint64_t page_index, uint64_t phys);
+/* Anonymous objects: drop @count pages from @page_index on (frames and swap slots) */
+void vm_object_free_pages(vm_object_t* obj, uint64_t page_index, uint64_t count);
+/* Anonymous objects: grow to @size bytes, existing pages keep their indices */
+int vm_object_grow(vm_object_t* obj, uint64_t size);
 
 #endif /* _RODNIX_VM_OBJECT_H */
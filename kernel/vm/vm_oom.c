@@ -0,0 +1,58 @@
+/**
+ * @file vm_oom.c
+ * @brief Out-of-memory killer: victim selection
+ */
+
+#include "vm_oom.h"
+#include "vm_map.h"
+#include "../common/tracev2.h"
+#include "../common/waitq.h"
+#include "../core/interrupts.h"
+#include "../../include/console.h"
+
+/* @a is a better victim than @b: more resident pages, then the newer task */
+static int vm_oom_prefer(const task_t* a, const task_t* b)
+{
+    uint64_t ra = vm_task_rss(a);
+    uint64_t rb = vm_task_rss(b);
+    if (ra != rb) {
+        return ra > rb;
+    }
+    return a->task_id > b->task_id;
+}
+
+task_t* vm_oom_kill(void)
+{
+    /* The task list and the maps are read under the giant */
+    irql_t old = set_irql(IRQL_HIGH);
+    task_t* victim = NULL;
+    for (task_t* t = task_next_locked(NULL); t; t = task_next_locked(t)) {
+        if (!t->vm_map || t->exited ||
+            t->state == TASK_STATE_ZOMBIE || t->state == TASK_STATE_DEAD) {
+            continue;
+        }
+        if (t->oom_killed) {
+            /* Still dying: its memory is about to come back */
+            victim = t;
+            break;
+        }
+        if (!victim || vm_oom_prefer(t, victim)) {
+            victim = t;
+        }
+    }
+    if (victim && !victim->oom_killed) {
+        victim->oom_killed = 1;
+        /* A sleeping victim would never reach its exit: cut the sleep short */
+        thread_t* thr;
+        TAILQ_FOREACH(thr, &victim->threads, task_link) {
+            waitq_interrupt(thr);
+        }
+        tracev2_emit(TR2_CAT_MEMORY, TR2_EV_MEM_OOM_KILL, victim->task_id, vm_task_rss(victim));
+        kprintf("[OOM] out of memory: killed task %llu (rss %llu pages, vsz %llu KiB)\n",
+                (unsigned long long)victim->task_id,
+                (unsigned long long)vm_task_rss(victim),
+                (unsigned long long)(vm_task_vsize(victim) / 1024u));
+    }
+    (void)set_irql(old);
+    return victim;
+}
@@ -0,0 +1,29 @@
+/**
+ * @file vm_oom.h
+ * @brief Out-of-memory killer
+ *
+ * When a page fault cannot get a frame even after the page-out daemon
+ * reclaimed what it could, one user task is chosen to die so that the
+ * rest of the system keeps running. The choice is deterministic: the
+ * task with the largest resident set, the newest one on a tie. While a
+ * chosen task is still alive no other task is chosen.
+ *
+ * The victim gets marked (task_t.oom_killed) and its sleeping threads
+ * are woken; waits in the kernel give up for a marked task. It exits on
+ * its way back to user mode (after a system call, page fault or
+ * interrupt), at PASSIVE outside any handler, and its address space is
+ * freed at exit.
+ */
+
+#ifndef _RODNIX_VM_OOM_H
+#define _RODNIX_VM_OOM_H
+
+#include "../core/task.h"
+
+/**
+ * Pick (or keep) the OOM victim and mark it.
+ * @return The victim, or NULL if no user task holds memory
+ */
+task_t* vm_oom_kill(void);
+
+#endif /* _RODNIX_VM_OOM_H */
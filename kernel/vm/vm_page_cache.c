@@ -0,0 +1,556 @@
+/**
+ * @file vm_page_cache.c
+ * @brief File page cache: hash of (vm_object, page index) -> frame
+ *
+ * Entries sit in a global hash, on their object's list (flush, truncate,
+ * destroy) and, for disk-backed objects, on an LRU used for eviction.
+ * Fills and write-backs run without the lock: a page being filled is
+ * inserted only afterwards (a racing fill loses and frees its copy), a
+ * page being written back is BUSY and holds an extra reference.
+ */
+
+#include "vm_page_cache.h"
+#include "vm_pager.h"
+#include "vm_page_ref.h"
+#include "../common/kmem.h"
+#include "../common/bootparam.h"
+#include "../core/interrupts.h"
+#include "../arch/pmm.h"
+#include "../arch/config.h"
+#include "../../include/common.h"
+#include "../../include/error.h"
+
+#define VM_PAGE_CACHE_HASH_SIZE 1024u
+#define VM_PAGE_CACHE_IO_BATCH  16u   /* Pages written per unlocked round */
+#define VM_PAGE_CACHE_RECLAIM   32u   /* Pages evicted per miss over the limit */
+#define VM_PAGE_CACHE_SCAN_MAX  4096u /* LRU entries examined per eviction pass */
+
+/* vm_page_cache_entry_t.flags */
+#define VM_PCE_DIRTY   0x1u
+#define VM_PCE_BUSY    0x2u /* Being written back */
+#define VM_PCE_WMAPPED 0x4u /* Mapped writable: may change without a fault */
+#define VM_PCE_LRU     0x8u /* Disk-backed, on g_vm_page_cache_lru */
+
+typedef struct vm_page_cache_entry {
+    vm_object_t* obj;
+    uint64_t pindex;
+    uint64_t phys;
+    uint32_t flags;
+    uint32_t flush_gen;
+    struct vm_page_cache_entry* hash_next;
+    TAILQ_ENTRY(vm_page_cache_entry) obj_link;
+    TAILQ_ENTRY(vm_page_cache_entry) lru_link;
+} vm_page_cache_entry_t;
+
+TAILQ_HEAD(vm_page_cache_lru, vm_page_cache_entry);
+
+typedef struct vm_page_cache_io {
+    vm_object_t* obj;
+    const vm_page_cache_ops_t* ops;
+    void* owner;
+    uint64_t pindex;
+    uint64_t phys;
+    uint64_t len;
+} vm_page_cache_io_t;
+
+BOOTPARAM_INT(vm_page_cache_max_mb, "rdnx.pagecache_mb", 0, 0, 1048576,
+              "Page cache soft limit for disk-backed files, MiB (0: half of RAM)");
+
+/*
+ * LOCKING: page cache state — IRQL giant (vm_page_cache_lock).
+ *   Protects: g_vm_page_cache_hash, g_vm_page_cache_lru, every entry,
+ *   the cache_* fields of file objects, g_vm_page_cache_stats.
+ *   Never held across cache_ops calls (they sleep on disk I/O).
+ */
+static vm_page_cache_entry_t* g_vm_page_cache_hash[VM_PAGE_CACHE_HASH_SIZE];
+static struct vm_page_cache_lru g_vm_page_cache_lru = TAILQ_HEAD_INITIALIZER(g_vm_page_cache_lru);
+static kmem_cache_t* g_vm_page_cache_entries = NULL;
+static vm_page_cache_stats_t g_vm_page_cache_stats;
+static uint64_t g_vm_page_cache_backed = 0; /* Entries on the LRU */
+static uint32_t g_vm_page_cache_flush_gen = 0;
+
+static inline irql_t vm_page_cache_lock(void)
+{
+    return set_irql(IRQL_HIGH);
+}
+
+static inline void vm_page_cache_unlock(irql_t old)
+{
+    (void)set_irql(old);
+}
+
+static inline uint32_t vm_page_cache_hash(const vm_object_t* obj, uint64_t pindex)
+{
+    uint64_t h = ((uint64_t)(uintptr_t)obj >> 4) ^ (pindex * 0x9E3779B97F4A7C15ULL);
+    return (uint32_t)((h ^ (h >> 29)) & (VM_PAGE_CACHE_HASH_SIZE - 1u));
+}
+
+static vm_page_cache_entry_t* vm_page_cache_find(const vm_object_t* obj, uint64_t pindex)
+{
+    vm_page_cache_entry_t* e = g_vm_page_cache_hash[vm_page_cache_hash(obj, pindex)];
+    for (; e; e = e->hash_next) {
+        if (e->obj == obj && e->pindex == pindex) {
+            return e;
+        }
+    }
+    return NULL;
+}
+
+static void vm_page_cache_set_dirty(vm_page_cache_entry_t* e)
+{
+    if (!e->obj->cache_ops) {
+        return; /* Memory only: there is nowhere to write back to */
+    }
+    if ((e->flags & VM_PCE_DIRTY) == 0) {
+        e->flags |= VM_PCE_DIRTY;
+        e->obj->cache_dirty++;
+        g_vm_page_cache_stats.dirty++;
+    }
+}
+
+static void vm_page_cache_clear_dirty(vm_page_cache_entry_t* e)
+{
+    if (e->flags & VM_PCE_DIRTY) {
+        e->flags &= ~VM_PCE_DIRTY;
+        e->obj->cache_dirty--;
+        g_vm_page_cache_stats.dirty--;
+    }
+}
+
+static void vm_page_cache_apply(vm_page_cache_entry_t* e, uint32_t flags)
+{
+    if (flags & VM_PAGE_CACHE_WMAP) {
+        e->flags |= VM_PCE_WMAPPED;
+        vm_page_cache_set_dirty(e);
+    }
+}
+
+static void vm_page_cache_insert(vm_page_cache_entry_t* e)
+{
+    uint32_t b = vm_page_cache_hash(e->obj, e->pindex);
+    e->hash_next = g_vm_page_cache_hash[b];
+    g_vm_page_cache_hash[b] = e;
+    TAILQ_INSERT_TAIL(&e->obj->cache_pages, e, obj_link);
+    if (e->obj->cache_ops) {
+        e->flags |= VM_PCE_LRU;
+        TAILQ_INSERT_TAIL(&g_vm_page_cache_lru, e, lru_link);
+        g_vm_page_cache_backed++;
+    }
+    e->obj->cache_resident++;
+    g_vm_page_cache_stats.resident++;
+}
+
+/* Unlink and free an entry, dropping the cache's page reference */
+static void vm_page_cache_remove(vm_page_cache_entry_t* e)
+{
+    vm_page_cache_entry_t** link = &g_vm_page_cache_hash[vm_page_cache_hash(e->obj, e->pindex)];
+    while (*link && *link != e) {
+        link = &(*link)->hash_next;
+    }
+    if (*link) {
+        *link = e->hash_next;
+    }
+    TAILQ_REMOVE(&e->obj->cache_pages, e, obj_link);
+    if (e->flags & VM_PCE_LRU) {
+        TAILQ_REMOVE(&g_vm_page_cache_lru, e, lru_link);
+        g_vm_page_cache_backed--;
+    }
+    vm_page_cache_clear_dirty(e);
+    e->obj->cache_resident--;
+    g_vm_page_cache_stats.resident--;
+    (void)vm_page_ref_release(e->phys);
+    kmem_cache_free(g_vm_page_cache_entries, e);
+}
+
+int vm_page_cache_init(void)
+{
+    if (g_vm_page_cache_entries) {
+        return RDNX_OK;
+    }
+    g_vm_page_cache_entries = kmem_cache_create("vm_page_cache", sizeof(vm_page_cache_entry_t),
+                                                0, NULL, NULL, NULL, 0);
+    if (!g_vm_page_cache_entries) {
+        return RDNX_E_NOMEM;
+    }
+    if (vm_page_cache_max_mb > 0) {
+        g_vm_page_cache_stats.limit = (uint64_t)vm_page_cache_max_mb * (0x100000ULL / VM_OBJECT_PAGE_SIZE);
+    } else {
+        g_vm_page_cache_stats.limit = pmm_get_total_pages() / 2u;
+    }
+    return RDNX_OK;
+}
+
+vm_object_t* vm_page_cache_object_create(uint64_t size, const vm_page_cache_ops_t* ops, void* owner)
+{
+    vm_object_t* obj = vm_object_create(VM_OBJECT_FILE, size);
+    if (!obj) {
+        return NULL;
+    }
+    obj->cache_ops = ops;
+    obj->cache_owner = owner;
+    irql_t old = vm_page_cache_lock();
+    g_vm_page_cache_stats.objects++;
+    vm_page_cache_unlock(old);
+    return obj;
+}
+
+void vm_page_cache_object_detach(vm_object_t* obj)
+{
+    if (!obj) {
+        return;
+    }
+    irql_t old = vm_page_cache_lock();
+    vm_page_cache_entry_t* e;
+    TAILQ_FOREACH(e, &obj->cache_pages, obj_link) {
+        vm_page_cache_clear_dirty(e);
+        e->flags &= ~VM_PCE_WMAPPED;
+        if (e->flags & VM_PCE_LRU) {
+            TAILQ_REMOVE(&g_vm_page_cache_lru, e, lru_link);
+            e->flags &= ~VM_PCE_LRU;
+            g_vm_page_cache_backed--;
+        }
+    }
+    obj->cache_ops = NULL;
+    obj->cache_owner = NULL;
+    vm_page_cache_unlock(old);
+}
+
+void vm_page_cache_object_destroy(vm_object_t* obj)
+{
+    if (!obj) {
+        return;
+    }
+    irql_t old = vm_page_cache_lock();
+    vm_page_cache_entry_t* e;
+    while ((e = TAILQ_FIRST(&obj->cache_pages)) != NULL) {
+        vm_page_cache_remove(e);
+    }
+    g_vm_page_cache_stats.objects--;
+    vm_page_cache_unlock(old);
+}
+
+int vm_page_cache_get(vm_object_t* obj, uint64_t pindex, uint32_t flags, uint64_t* out_phys)
+{
+    if (!obj || obj->type != VM_OBJECT_FILE || !out_phys) {
+        return RDNX_E_INVALID;
+    }
+
+    irql_t old = vm_page_cache_lock();
+    vm_page_cache_entry_t* e = vm_page_cache_find(obj, pindex);
+    if (e) {
+        g_vm_page_cache_stats.hits++;
+        if (e->flags & VM_PCE_LRU) {
+            TAILQ_REMOVE(&g_vm_page_cache_lru, e, lru_link);
+            TAILQ_INSERT_TAIL(&g_vm_page_cache_lru, e, lru_link);
+        }
+        vm_page_cache_apply(e, flags);
+        (void)vm_page_ref_retain(e->phys);
+        *out_phys = e->phys;
+        vm_page_cache_unlock(old);
+        return RDNX_OK;
+    }
+    g_vm_page_cache_stats.misses++;
+    const vm_page_cache_ops_t* ops = obj->cache_ops;
+    void* owner = obj->cache_owner;
+    uint64_t size = obj->size;
+    int over_limit = ops && g_vm_page_cache_backed >= g_vm_page_cache_stats.limit;
+    vm_page_cache_unlock(old);
+
+    if (over_limit) {
+        (void)vm_page_cache_reclaim(VM_PAGE_CACHE_RECLAIM, 0);
+    }
+    uint64_t phys = vm_pager_alloc_zero_page();
+    if (!phys && vm_page_cache_reclaim(VM_PAGE_CACHE_RECLAIM, 1) > 0) {
+        phys = vm_pager_alloc_zero_page();
+    }
+    if (!phys) {
+        return RDNX_E_NOMEM;
+    }
+
+    uint64_t offset = pindex * VM_OBJECT_PAGE_SIZE;
+    int filled = 0;
+    if (ops && ops->read && (flags & VM_PAGE_CACHE_NOFILL) == 0 && offset < size) {
+        int rc = ops->read(owner, offset, ARCH_PHYS_TO_VIRT(phys));
+        if (rc != RDNX_OK) {
+            old = vm_page_cache_lock();
+            g_vm_page_cache_stats.io_errors++;
+            vm_page_cache_unlock(old);
+            (void)vm_page_ref_release(phys);
+            return rc;
+        }
+        filled = 1;
+    }
+
+    vm_page_cache_entry_t* ne = (vm_page_cache_entry_t*)kmem_cache_zalloc(g_vm_page_cache_entries);
+    if (!ne) {
+        (void)vm_page_ref_release(phys);
+        return RDNX_E_NOMEM;
+    }
+
+    old = vm_page_cache_lock();
+    e = vm_page_cache_find(obj, pindex);
+    if (e) {
+        /* Lost a race with another fill: use the page already published */
+        vm_page_cache_apply(e, flags);
+        (void)vm_page_ref_retain(e->phys);
+        *out_phys = e->phys;
+        vm_page_cache_unlock(old);
+        kmem_cache_free(g_vm_page_cache_entries, ne);
+        (void)vm_page_ref_release(phys);
+        return RDNX_OK;
+    }
+    ne->obj = obj;
+    ne->pindex = pindex;
+    ne->phys = phys; /* vm_pager_alloc_zero_page() reference now belongs to the cache */
+    vm_page_cache_insert(ne);
+    vm_page_cache_apply(ne, flags);
+    if (filled) {
+        g_vm_page_cache_stats.fills++;
+    }
+    (void)vm_page_ref_retain(phys);
+    *out_phys = phys;
+    vm_page_cache_unlock(old);
+    return RDNX_OK;
+}
+
+void vm_page_cache_put(uint64_t phys)
+{
+    (void)vm_page_ref_release(phys);
+}
+
+uint64_t vm_page_cache_lookup(const vm_object_t* obj, uint64_t pindex)
+{
+    if (!obj) {
+        return 0;
+    }
+    irql_t old = vm_page_cache_lock();
+    vm_page_cache_entry_t* e = vm_page_cache_find(obj, pindex);
+    uint64_t phys = e ? e->phys : 0;
+    vm_page_cache_unlock(old);
+    return phys;
+}
+
+void vm_page_cache_dirty(vm_object_t* obj, uint64_t pindex, uint32_t flags)
+{
+    if (!obj) {
+        return;
+    }
+    irql_t old = vm_page_cache_lock();
+    vm_page_cache_entry_t* e = vm_page_cache_find(obj, pindex);
+    if (e) {
+        vm_page_cache_set_dirty(e);
+        vm_page_cache_apply(e, flags);
+    }
+    vm_page_cache_unlock(old);
+}
+
+/*
+ * Claim a dirty entry for write-back (locked). A writably mapped page
+ * stays dirty while it is still mapped: stores through the mapping do
+ * not fault again, so only the unmap tells that it went quiet.
+ * @return 1 if @io was filled and needs writing
+ */
+static int vm_page_cache_claim(vm_page_cache_entry_t* e, uint32_t gen, vm_page_cache_io_t* io)
+{
+    vm_object_t* obj = e->obj;
+    e->flush_gen = gen;
+    uint64_t offset = e->pindex * VM_OBJECT_PAGE_SIZE;
+    if (offset >= obj->size) {
+        /* Past EOF (mapped beyond the end): nothing to write */
+        vm_page_cache_clear_dirty(e);
+        return 0;
+    }
+    if ((e->flags & VM_PCE_WMAPPED) == 0 || vm_page_ref_count(e->phys) <= 1) {
+        vm_page_cache_clear_dirty(e);
+        e->flags &= ~VM_PCE_WMAPPED;
+    }
+    e->flags |= VM_PCE_BUSY;
+    (void)vm_page_ref_retain(e->phys);
+    vm_object_ref(obj);
+    io->obj = obj;
+    io->ops = obj->cache_ops;
+    io->owner = obj->cache_owner;
+    io->pindex = e->pindex;
+    io->phys = e->phys;
+    io->len = obj->size - offset;
+    if (io->len > VM_OBJECT_PAGE_SIZE) {
+        io->len = VM_OBJECT_PAGE_SIZE;
+    }
+    return 1;
+}
+
+static int vm_page_cache_write_batch(vm_page_cache_io_t* batch, uint32_t count)
+{
+    int result = RDNX_OK;
+    for (uint32_t i = 0; i < count; i++) {
+        vm_page_cache_io_t* io = &batch[i];
+        int rc = io->ops->write(io->owner, io->pindex * VM_OBJECT_PAGE_SIZE,
+                                ARCH_PHYS_TO_VIRT(io->phys), io->len);
+        irql_t old = vm_page_cache_lock();
+        vm_page_cache_entry_t* e = vm_page_cache_find(io->obj, io->pindex);
+        if (e && e->phys == io->phys) {
+            e->flags &= ~VM_PCE_BUSY;
+            if (rc != RDNX_OK) {
+                vm_page_cache_set_dirty(e);
+            }
+        }
+        if (rc == RDNX_OK) {
+            g_vm_page_cache_stats.writebacks++;
+        } else {
+            g_vm_page_cache_stats.io_errors++;
+            if (result == RDNX_OK) {
+                result = rc;
+            }
+        }
+        vm_page_cache_unlock(old);
+        (void)vm_page_ref_release(io->phys);
+        vm_object_unref(io->obj);
+    }
+    return result;
+}
+
+/* Flush @obj's pages in [first, last), or every object when @obj is NULL */
+static int vm_page_cache_flush_range(vm_object_t* obj, uint64_t first, uint64_t last)
+{
+    vm_page_cache_io_t batch[VM_PAGE_CACHE_IO_BATCH];
+    int result = RDNX_OK;
+
+    irql_t old = vm_page_cache_lock();
+    uint32_t gen = ++g_vm_page_cache_flush_gen;
+    vm_page_cache_unlock(old);
+
+    for (;;) {
+        uint32_t n = 0;
+        old = vm_page_cache_lock();
+        if (obj) {
+            vm_page_cache_entry_t* e;
+            if (obj->cache_ops && obj->cache_ops->write && obj->cache_dirty > 0) {
+                TAILQ_FOREACH(e, &obj->cache_pages, obj_link) {
+                    if ((e->flags & (VM_PCE_DIRTY | VM_PCE_BUSY)) != VM_PCE_DIRTY ||
+                        e->flush_gen == gen || e->pindex < first || e->pindex >= last) {
+                        continue;
+                    }
+                    n += (uint32_t)vm_page_cache_claim(e, gen, &batch[n]);
+                    if (n == VM_PAGE_CACHE_IO_BATCH) {
+                        break;
+                    }
+                }
+            }
+        } else if (g_vm_page_cache_stats.dirty > 0) {
+            vm_page_cache_entry_t* e;
+            TAILQ_FOREACH(e, &g_vm_page_cache_lru, lru_link) {
+                if ((e->flags & (VM_PCE_DIRTY | VM_PCE_BUSY)) != VM_PCE_DIRTY ||
+                    e->flush_gen == gen || !e->obj->cache_ops->write) {
+                    continue;
+                }
+                n += (uint32_t)vm_page_cache_claim(e, gen, &batch[n]);
+                if (n == VM_PAGE_CACHE_IO_BATCH) {
+                    break;
+                }
+            }
+        }
+        vm_page_cache_unlock(old);
+        if (n == 0) {
+            break;
+        }
+        int rc = vm_page_cache_write_batch(batch, n);
+        if (rc != RDNX_OK && result == RDNX_OK) {
+            result = rc;
+        }
+    }
+    return result;
+}
+
+int vm_page_cache_flush(vm_object_t* obj, uint64_t offset, uint64_t len)
+{
+    if (!obj || obj->type != VM_OBJECT_FILE) {
+        return RDNX_E_INVALID;
+    }
+    uint64_t first = offset / VM_OBJECT_PAGE_SIZE;
+    uint64_t last = UINT64_MAX;
+    if (len != 0 && offset + len > offset) {
+        last = (offset + len + VM_OBJECT_PAGE_SIZE - 1u) / VM_OBJECT_PAGE_SIZE;
+    }
+    return vm_page_cache_flush_range(obj, first, last);
+}
+
+int vm_page_cache_flush_all(void)
+{
+    return vm_page_cache_flush_range(NULL, 0, UINT64_MAX);
+}
+
+void vm_page_cache_truncate(vm_object_t* obj, uint64_t size)
+{
+    if (!obj || obj->type != VM_OBJECT_FILE) {
+        return;
+    }
+    uint64_t keep = (size + VM_OBJECT_PAGE_SIZE - 1u) / VM_OBJECT_PAGE_SIZE;
+    uint64_t tail = size % VM_OBJECT_PAGE_SIZE;
+
+    irql_t old = vm_page_cache_lock();
+    obj->size = size;
+    obj->page_count = keep ? keep : 1u;
+    vm_page_cache_entry_t* e;
+    vm_page_cache_entry_t* tmp;
+    TAILQ_FOREACH_SAFE(e, &obj->cache_pages, obj_link, tmp) {
+        if (e->pindex >= keep) {
+            vm_page_cache_remove(e);
+        } else if (tail != 0 && e->pindex == keep - 1u) {
+            /* Bytes past the new EOF must read as zero if the file grows again */
+            memset((uint8_t*)ARCH_PHYS_TO_VIRT(e->phys) + tail, 0, (size_t)(VM_OBJECT_PAGE_SIZE - tail));
+        }
+    }
+    vm_page_cache_unlock(old);
+}
+
+static uint64_t vm_page_cache_evict(uint64_t target)
+{
+    uint64_t freed = 0;
+    uint32_t scanned = 0;
+
+    irql_t old = vm_page_cache_lock();
+    vm_page_cache_entry_t* e = TAILQ_FIRST(&g_vm_page_cache_lru);
+    uint64_t budget = g_vm_page_cache_backed;
+    while (e && freed < target && scanned < VM_PAGE_CACHE_SCAN_MAX && budget > 0) {
+        vm_page_cache_entry_t* next = TAILQ_NEXT(e, lru_link);
+        scanned++;
+        budget--;
+        if (e->flags & (VM_PCE_DIRTY | VM_PCE_BUSY)) {
+            e = next;
+            continue;
+        }
+        if (vm_page_ref_count(e->phys) > 1) {
+            /* Mapped or in use: give it another round at the tail */
+            TAILQ_REMOVE(&g_vm_page_cache_lru, e, lru_link);
+            TAILQ_INSERT_TAIL(&g_vm_page_cache_lru, e, lru_link);
+            e = next;
+            continue;
+        }
+        vm_page_cache_remove(e);
+        g_vm_page_cache_stats.evictions++;
+        freed++;
+        e = next;
+    }
+    vm_page_cache_unlock(old);
+    return freed;
+}
+
+uint64_t vm_page_cache_reclaim(uint64_t target, int writeback)
+{
+    uint64_t freed = vm_page_cache_evict(target);
+    if (freed < target && writeback) {
+        (void)vm_page_cache_flush_all();
+        freed += vm_page_cache_evict(target - freed);
+    }
+    return freed;
+}
+
+void vm_page_cache_get_stats(vm_page_cache_stats_t* out)
+{
+    if (!out) {
+        return;
+    }
+    irql_t old = vm_page_cache_lock();
+    *out = g_vm_page_cache_stats;
+    vm_page_cache_unlock(old);
+}
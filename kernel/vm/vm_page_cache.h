@@ -0,0 +1,105 @@
+/**
+ * @file vm_page_cache.h
+ * @brief File page cache keyed by (vm_object, page index)
+ *
+ * Every regular file has one VM_OBJECT_FILE object; its pages are the
+ * only in-memory copy of the file data. vfs_read/vfs_write copy through
+ * them and file mappings map them directly, so both see the same bytes.
+ *
+ * Pages are filled on first use through the object's cache_ops and
+ * written back through them when dirty (flush, sync, memory pressure).
+ * Objects without cache_ops (ramfs) are memory only: a missing page
+ * reads as zeros and nothing is ever evicted.
+ *
+ * A page returned by vm_page_cache_get() carries a vm_page_ref reference
+ * for the caller; the cache itself holds one more. A page is evicted only
+ * when it is clean and the cache's reference is the last one, so mapped
+ * and in-flight pages stay put.
+ */
+
+#ifndef _RODNIX_VM_PAGE_CACHE_H
+#define _RODNIX_VM_PAGE_CACHE_H
+
+#include <stdint.h>
+#include "vm_object.h"
+
+typedef struct vm_page_cache_ops {
+    /* Fill one page with object bytes at @offset; bytes past EOF read as zero */
+    int (*read)(void* owner, uint64_t offset, void* page);
+    /* Write the first @len bytes of @page back at @offset */
+    int (*write)(void* owner, uint64_t offset, const void* page, uint64_t len);
+} vm_page_cache_ops_t;
+
+/* vm_page_cache_get() flags */
+#define VM_PAGE_CACHE_NOFILL 0x1u /* Caller overwrites the page: skip the read */
+#define VM_PAGE_CACHE_WMAP   0x2u /* Page gets a writable mapping: dirty until unmapped */
+
+typedef struct vm_page_cache_stats {
+    uint64_t objects;
+    uint64_t resident;    /* Pages held by the cache */
+    uint64_t dirty;
+    uint64_t limit;       /* Soft limit on disk-backed pages */
+    uint64_t hits;
+    uint64_t misses;
+    uint64_t fills;       /* Pages read through cache_ops */
+    uint64_t writebacks;  /* Pages written through cache_ops */
+    uint64_t evictions;
+    uint64_t io_errors;
+} vm_page_cache_stats_t;
+
+/* Set up the entry cache and the hash; called from vfs_init() */
+int vm_page_cache_init(void);
+
+/**
+ * Create a file object; vm_vnode_pager_alloc() is the usual entry point.
+ * @param size Current file size in bytes
+ * @param ops Backing store, or NULL for a memory-only file
+ * @param owner Passed back to @ops (the filesystem node)
+ */
+vm_object_t* vm_page_cache_object_create(uint64_t size, const vm_page_cache_ops_t* ops, void* owner);
+
+/* The owner is going away: later misses read zeros, dirty pages are dropped */
+void vm_page_cache_object_detach(vm_object_t* obj);
+
+/* Drop every page of @obj; called by vm_object_unref() on the last reference */
+void vm_page_cache_object_destroy(vm_object_t* obj);
+
+/**
+ * Find or fill a page.
+ * @param flags VM_PAGE_CACHE_*
+ * @param out_phys Page frame, referenced for the caller (vm_page_cache_put)
+ * @return RDNX_OK, RDNX_E_NOMEM, or the cache_ops read error
+ */
+int vm_page_cache_get(vm_object_t* obj, uint64_t pindex, uint32_t flags, uint64_t* out_phys);
+
+/* Drop the reference returned by vm_page_cache_get() */
+void vm_page_cache_put(uint64_t phys);
+
+/* Resident frame of a page, 0 if not cached; takes no reference */
+uint64_t vm_page_cache_lookup(const vm_object_t* obj, uint64_t pindex);
+
+/* Mark a cached page modified (VM_PAGE_CACHE_WMAP: it is mapped writable) */
+void vm_page_cache_dirty(vm_object_t* obj, uint64_t pindex, uint32_t flags);
+
+/**
+ * Write back dirty pages of [offset, offset + len); len 0 means to the end.
+ * @return RDNX_OK or the first cache_ops write error
+ */
+int vm_page_cache_flush(vm_object_t* obj, uint64_t offset, uint64_t len);
+
+/* Flush every object; used by vfs_sync() */
+int vm_page_cache_flush_all(void);
+
+/* New file size: pages past it are dropped, the tail of the last one zeroed */
+void vm_page_cache_truncate(vm_object_t* obj, uint64_t size);
+
+/**
+ * Evict clean unmapped disk-backed pages, oldest first.
+ * @param writeback Also flush dirty pages when clean ones are not enough
+ * @return Pages freed
+ */
+uint64_t vm_page_cache_reclaim(uint64_t target, int writeback);
+
+void vm_page_cache_get_stats(vm_page_cache_stats_t* out);
+
+#endif /* _RODNIX_VM_PAGE_CACHE_H */
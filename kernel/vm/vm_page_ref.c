@@ -1,20 +1,43 @@
 #include "vm_page_ref.h"
 #include "../common/heap.h"
 #include "../arch/pmm.h"
+#include "../core/interrupts.h"
 #include "../../include/common.h"
 #include "../../include/error.h"
 
+#define VM_PAGE_REF_BUCKETS 1024u
+
 typedef struct vm_page_ref_node {
     uint64_t phys;
     uint32_t refs;
     struct vm_page_ref_node* next;
 } vm_page_ref_node_t;
 
-static vm_page_ref_node_t* g_vm_page_refs = NULL;
+/*
+ * LOCKING: g_vm_page_refs — IRQL giant (vm_page_ref_lock).
+ *   Page cache pages are shared by the cache, read/write and every
+ *   mapping, so references are taken from several paths concurrently.
+ */
+static vm_page_ref_node_t* g_vm_page_refs[VM_PAGE_REF_BUCKETS];
+
+static inline irql_t vm_page_ref_lock(void)
+{
+    return set_irql(IRQL_HIGH);
+}
+
+static inline void vm_page_ref_unlock(irql_t old)
+{
+    (void)set_irql(old);
+}
+
+static inline uint32_t vm_page_ref_bucket(uint64_t phys)
+{
+    return (uint32_t)((phys >> 12) & (VM_PAGE_REF_BUCKETS - 1u));
+}
 
 static vm_page_ref_node_t* vm_page_ref_find(uint64_t phys)
 {
-    for (vm_page_ref_node_t* it = g_vm_page_refs; it; it = it->next) {
+    for (vm_page_ref_node_t* it = g_vm_page_refs[vm_page_ref_bucket(phys)]; it; it = it->next) {
         if (it->phys == phys) {
             return it;
         }
@@ -27,19 +50,24 @@ int vm_page_ref_add_new(uint64_t phys)
     if (!phys) {
         return RDNX_E_INVALID;
     }
+    irql_t old = vm_page_ref_lock();
     vm_page_ref_node_t* node = vm_page_ref_find(phys);
     if (node) {
         node->refs++;
+        vm_page_ref_unlock(old);
         return RDNX_OK;
     }
     node = (vm_page_ref_node_t*)kmalloc(sizeof(vm_page_ref_node_t));
     if (!node) {
+        vm_page_ref_unlock(old);
         return RDNX_E_NOMEM;
     }
+    uint32_t b = vm_page_ref_bucket(phys);
     node->phys = phys;
     node->refs = 1;
-    node->next = g_vm_page_refs;
-    g_vm_page_refs = node;
+    node->next = g_vm_page_refs[b];
+    g_vm_page_refs[b] = node;
+    vm_page_ref_unlock(old);
     return RDNX_OK;
 }
 
@@ -53,8 +81,10 @@ int vm_page_ref_release(uint64_t phys)
     if (!phys) {
         return RDNX_E_INVALID;
     }
+    irql_t old = vm_page_ref_lock();
+    uint32_t b = vm_page_ref_bucket(phys);
     vm_page_ref_node_t* prev = NULL;
-    vm_page_ref_node_t* cur = g_vm_page_refs;
+    vm_page_ref_node_t* cur = g_vm_page_refs[b];
     while (cur) {
         if (cur->phys == phys) {
             if (cur->refs > 0) {
@@ -64,15 +94,29 @@ int vm_page_ref_release(uint64_t phys)
                 if (prev) {
                     prev->next = cur->next;
                 } else {
-                    g_vm_page_refs = cur->next;
+                    g_vm_page_refs[b] = cur->next;
                 }
                 pmm_free_page(phys);
                 kfree(cur);
             }
+            vm_page_ref_unlock(old);
             return RDNX_OK;
         }
         prev = cur;
         cur = cur->next;
     }
+    vm_page_ref_unlock(old);
     return RDNX_E_NOTFOUND;
 }
+
+uint32_t vm_page_ref_count(uint64_t phys)
+{
+    if (!phys) {
+        return 0;
+    }
+    irql_t old = vm_page_ref_lock();
+    vm_page_ref_node_t* node = vm_page_ref_find(phys);
+    uint32_t refs = node ? node->refs : 0;
+    vm_page_ref_unlock(old);
+    return refs;
+}
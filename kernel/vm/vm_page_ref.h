@@ -6,5 +6,7 @@
 int vm_page_ref_add_new(uint64_t phys);
 int vm_page_ref_retain(uint64_t phys);
 int vm_page_ref_release(uint64_t phys);
+/* Current reference count, 0 for an untracked frame */
+uint32_t vm_page_ref_count(uint64_t phys);
 
 #endif /* _RODNIX_VM_PAGE_REF_H */
@@ -0,0 +1,383 @@
+/**
+ * @file vm_pageout.c
+ * @brief Page-out daemon and the anonymous page queues
+ *
+ * Queue entries are found by frame through a hash and point back at the
+ * owning object and page index. An entry does not reference its object:
+ * the object removes it (vm_pageout_page_remove) before the page leaves.
+ *
+ * A page is written out only once every user mapping of it is gone and
+ * the object's reference is the last one; vm_swap_pageout() re-checks
+ * that after the I/O, which runs unlocked.
+ */
+
+#include "vm_pageout.h"
+#include "vm_map.h"
+#include "vm_swap.h"
+#include "vm_page_ref.h"
+#include "vm_page_cache.h"
+#include "../arch/paging.h"
+#include "../arch/pmm.h"
+#include "../common/kmem.h"
+#include "../common/scheduler.h"
+#include "../core/task.h"
+#include "../core/interrupts.h"
+#include "../../include/common.h"
+#include "../../include/error.h"
+
+#define VM_PAGEOUT_HASH_SIZE  1024u
+#define VM_PAGEOUT_INTERVAL   100u  /* Daemon period, ms */
+#define VM_PAGEOUT_FREE_MIN   256u  /* Lower bound of the low watermark, pages */
+#define VM_PAGEOUT_DIRECT     32u   /* Pages freed for a failed allocation */
+
+enum {
+    VM_PAGEOUT_Q_NONE = 0,
+    VM_PAGEOUT_Q_ACTIVE,
+    VM_PAGEOUT_Q_INACTIVE
+};
+
+typedef struct vm_pageout_page {
+    uint64_t phys;
+    vm_object_t* obj;
+    uint64_t pindex;
+    uint32_t queue;
+    uint32_t referenced;
+    struct vm_pageout_page* hash_next;
+    TAILQ_ENTRY(vm_pageout_page) link;
+} vm_pageout_page_t;
+
+TAILQ_HEAD(vm_pageout_queue, vm_pageout_page);
+
+/*
+ * LOCKING: page-out queues — IRQL giant (vm_pageout_lock).
+ *   Protects: g_vm_pageout_hash, both queues, every entry,
+ *   g_vm_pageout_stats. A scan holds it while it samples accessed bits
+ *   and unmaps a victim (task list and vm_maps are read under the same
+ *   giant), and drops it for the swap write.
+ */
+static vm_pageout_page_t* g_vm_pageout_hash[VM_PAGEOUT_HASH_SIZE];
+static struct vm_pageout_queue g_vm_pageout_active = TAILQ_HEAD_INITIALIZER(g_vm_pageout_active);
+static struct vm_pageout_queue g_vm_pageout_inactive = TAILQ_HEAD_INITIALIZER(g_vm_pageout_inactive);
+static kmem_cache_t* g_vm_pageout_pages = NULL;
+static vm_pageout_stats_t g_vm_pageout_stats;
+static thread_t* g_vm_pageout_thread = NULL;
+
+static inline irql_t vm_pageout_lock(void)
+{
+    return set_irql(IRQL_HIGH);
+}
+
+static inline void vm_pageout_unlock(irql_t old)
+{
+    (void)set_irql(old);
+}
+
+static inline uint32_t vm_pageout_hash(uint64_t phys)
+{
+    uint64_t h = (phys >> 12) * 0x9E3779B97F4A7C15ULL;
+    return (uint32_t)((h >> 32) & (VM_PAGEOUT_HASH_SIZE - 1u));
+}
+
+static vm_pageout_page_t* vm_pageout_find(uint64_t phys)
+{
+    vm_pageout_page_t* p = g_vm_pageout_hash[vm_pageout_hash(phys)];
+    for (; p; p = p->hash_next) {
+        if (p->phys == phys) {
+            return p;
+        }
+    }
+    return NULL;
+}
+
+static struct vm_pageout_queue* vm_pageout_queue_of(vm_pageout_page_t* p)
+{
+    return (p->queue == VM_PAGEOUT_Q_ACTIVE) ? &g_vm_pageout_active : &g_vm_pageout_inactive;
+}
+
+static void vm_pageout_dequeue(vm_pageout_page_t* p)
+{
+    TAILQ_REMOVE(vm_pageout_queue_of(p), p, link);
+    if (p->queue == VM_PAGEOUT_Q_ACTIVE) {
+        g_vm_pageout_stats.active--;
+    } else {
+        g_vm_pageout_stats.inactive--;
+    }
+    p->queue = VM_PAGEOUT_Q_NONE;
+}
+
+static void vm_pageout_enqueue(vm_pageout_page_t* p, uint32_t queue)
+{
+    p->queue = queue;
+    TAILQ_INSERT_TAIL(vm_pageout_queue_of(p), p, link);
+    if (queue == VM_PAGEOUT_Q_ACTIVE) {
+        g_vm_pageout_stats.active++;
+    } else {
+        g_vm_pageout_stats.inactive++;
+    }
+}
+
+void vm_pageout_page_insert(vm_object_t* obj, uint64_t pindex, uint64_t phys)
+{
+    if (!obj || !phys || !g_vm_pageout_pages) {
+        return; /* Pages queued before the daemon starts are never paged out */
+    }
+    irql_t old = vm_pageout_lock();
+    if (vm_pageout_find(phys)) {
+        vm_pageout_unlock(old);
+        return;
+    }
+    vm_pageout_page_t* p = (vm_pageout_page_t*)kmem_cache_zalloc(g_vm_pageout_pages);
+    if (!p) {
+        vm_pageout_unlock(old);
+        return; /* Untracked: stays resident */
+    }
+    p->phys = phys;
+    p->obj = obj;
+    p->pindex = pindex;
+    p->referenced = 1; /* Just faulted in */
+    uint32_t h = vm_pageout_hash(phys);
+    p->hash_next = g_vm_pageout_hash[h];
+    g_vm_pageout_hash[h] = p;
+    vm_pageout_enqueue(p, VM_PAGEOUT_Q_ACTIVE);
+    vm_pageout_unlock(old);
+}
+
+void vm_pageout_page_remove(uint64_t phys)
+{
+    if (!phys || !g_vm_pageout_pages) {
+        return;
+    }
+    irql_t old = vm_pageout_lock();
+    vm_pageout_page_t** link = &g_vm_pageout_hash[vm_pageout_hash(phys)];
+    while (*link && (*link)->phys != phys) {
+        link = &(*link)->hash_next;
+    }
+    vm_pageout_page_t* p = *link;
+    if (p) {
+        *link = p->hash_next;
+        vm_pageout_dequeue(p);
+    }
+    vm_pageout_unlock(old);
+    if (p) {
+        kmem_cache_free(g_vm_pageout_pages, p);
+    }
+}
+
+/* Mark queued pages that some user mapping touched since the last pass */
+static void vm_pageout_sample_locked(void)
+{
+    for (task_t* t = task_next_locked(NULL); t; t = task_next_locked(t)) {
+        vm_map_t* map = (vm_map_t*)t->vm_map;
+        if (!map || !map->pml4_phys) {
+            continue;
+        }
+        for (vm_map_entry_t* e = vm_map_first(map); e; e = vm_map_next(e)) {
+            vm_object_t* obj = e->object;
+            if (!obj || obj->type != VM_OBJECT_ANON || !obj->resident_pages) {
+                continue;
+            }
+            for (uint64_t va = e->start; va < e->end; va += VM_PAGE_SIZE) {
+                uint64_t idx = (e->object_offset + (va - e->start)) / VM_PAGE_SIZE;
+                if (idx >= obj->page_count) {
+                    break;
+                }
+                uint64_t phys = vm_object_get_resident_page(obj, idx);
+                if (!phys) {
+                    continue;
+                }
+                if (paging_test_clear_accessed_pml4(map->pml4_phys, va) != 1) {
+                    continue;
+                }
+                vm_pageout_page_t* p = vm_pageout_find(phys);
+                if (p) {
+                    p->referenced = 1;
+                }
+            }
+        }
+    }
+}
+
+/* Keep about a third of the queued pages inactive */
+static void vm_pageout_age_locked(void)
+{
+    uint64_t total = g_vm_pageout_stats.active + g_vm_pageout_stats.inactive;
+    uint64_t budget = g_vm_pageout_stats.active;
+    while (budget-- > 0 && g_vm_pageout_stats.inactive < total / 3u) {
+        vm_pageout_page_t* p = TAILQ_FIRST(&g_vm_pageout_active);
+        if (!p) {
+            break;
+        }
+        vm_pageout_dequeue(p);
+        if (p->referenced) {
+            p->referenced = 0;
+            vm_pageout_enqueue(p, VM_PAGEOUT_Q_ACTIVE);
+        } else {
+            vm_pageout_enqueue(p, VM_PAGEOUT_Q_INACTIVE);
+        }
+    }
+}
+
+/* Remove every user mapping of @phys as page @pindex of @obj */
+static void vm_pageout_unmap_locked(vm_object_t* obj, uint64_t pindex, uint64_t phys)
+{
+    uint64_t off = pindex * VM_PAGE_SIZE;
+    for (task_t* t = task_next_locked(NULL); t; t = task_next_locked(t)) {
+        vm_map_t* map = (vm_map_t*)t->vm_map;
+        if (!map || !map->pml4_phys) {
+            continue;
+        }
+        for (vm_map_entry_t* e = vm_map_first(map); e; e = vm_map_next(e)) {
+            if (e->object != obj || off < e->object_offset ||
+                off - e->object_offset >= e->end - e->start) {
+                continue;
+            }
+            if (e->flags & VM_MAP_F_WIRED) {
+                continue; /* mlock: the mapping keeps the page resident */
+            }
+            uint64_t va = e->start + (off - e->object_offset);
+            uint64_t cur = paging_get_physical_pml4(map->pml4_phys, va) & ~(VM_PAGE_SIZE - 1u);
+            if (cur != phys) {
+                continue; /* Not faulted in here, or a private copy */
+            }
+            (void)paging_unmap_page_pml4(map->pml4_phys, va);
+            paging_tlb_shootdown(map->pml4_phys, va, 1);
+            map->resident--;
+            (void)vm_page_ref_release(phys); /* Mapping reference */
+        }
+    }
+}
+
+/* One pass over the anonymous queues; @return pages written to swap */
+static uint64_t vm_pageout_scan(uint64_t target)
+{
+    uint64_t freed = 0;
+    irql_t old = vm_pageout_lock();
+    vm_pageout_sample_locked();
+    vm_pageout_age_locked();
+    uint64_t budget = g_vm_pageout_stats.inactive;
+    while (freed < target && budget-- > 0) {
+        vm_pageout_page_t* p = TAILQ_FIRST(&g_vm_pageout_inactive);
+        if (!p) {
+            break;
+        }
+        g_vm_pageout_stats.scans++;
+        vm_pageout_dequeue(p);
+        if (p->referenced) {
+            p->referenced = 0;
+            g_vm_pageout_stats.reactivated++;
+            vm_pageout_enqueue(p, VM_PAGEOUT_Q_ACTIVE);
+            continue;
+        }
+        /* Retried later unless it goes away */
+        vm_pageout_enqueue(p, VM_PAGEOUT_Q_INACTIVE);
+        vm_object_t* obj = p->obj;
+        uint64_t pindex = p->pindex;
+        uint64_t phys = p->phys;
+        vm_pageout_unmap_locked(obj, pindex, phys);
+        if (vm_page_ref_count(phys) != 1) {
+            continue; /* Still used outside the object (e.g. shared after fork) */
+        }
+        vm_object_ref(obj);
+        vm_pageout_unlock(old);
+        int rc = vm_swap_pageout(obj, pindex, phys); /* Dequeues the page on success */
+        vm_object_unref(obj);
+        old = vm_pageout_lock();
+        if (rc == RDNX_OK) {
+            freed++;
+            g_vm_pageout_stats.pageouts++;
+        } else if (rc == RDNX_E_NOMEM) {
+            break; /* Swap is full */
+        }
+    }
+    vm_pageout_unlock(old);
+    return freed;
+}
+
+static uint64_t vm_pageout_free(uint64_t target)
+{
+    uint64_t cache = vm_page_cache_reclaim(target, 0);
+    uint64_t freed = cache;
+    if (freed < target && vm_swap_has_space()) {
+        freed += vm_pageout_scan(target - freed);
+    }
+    if (freed < target) {
+        uint64_t dirty = vm_page_cache_reclaim(target - freed, 1);
+        cache += dirty;
+        freed += dirty;
+    }
+    irql_t old = vm_pageout_lock();
+    g_vm_pageout_stats.cache_freed += cache;
+    vm_pageout_unlock(old);
+    return freed;
+}
+
+uint64_t vm_pageout_reclaim(uint64_t target)
+{
+    if (!g_vm_pageout_pages) {
+        return 0;
+    }
+    irql_t old = vm_pageout_lock();
+    g_vm_pageout_stats.direct++;
+    vm_pageout_unlock(old);
+    return vm_pageout_free(target ? target : VM_PAGEOUT_DIRECT);
+}
+
+static void vm_pageout_thread_main(void* arg)
+{
+    (void)arg;
+    for (;;) {
+        scheduler_sleep(VM_PAGEOUT_INTERVAL);
+        uint64_t free = pmm_get_free_pages();
+        if (free >= g_vm_pageout_stats.free_min) {
+            continue;
+        }
+        irql_t old = vm_pageout_lock();
+        g_vm_pageout_stats.wakeups++;
+        vm_pageout_unlock(old);
+        (void)vm_pageout_free(g_vm_pageout_stats.free_target - free);
+    }
+}
+
+void vm_pageout_start(void)
+{
+    if (g_vm_pageout_thread) {
+        return;
+    }
+    g_vm_pageout_pages = kmem_cache_create("vm_pageout_page", sizeof(vm_pageout_page_t),
+                                           0, NULL, NULL, NULL, 0);
+    if (!g_vm_pageout_pages) {
+        return;
+    }
+    uint64_t total = pmm_get_total_pages();
+    uint64_t free_min = total / 32u;
+    if (free_min < VM_PAGEOUT_FREE_MIN) {
+        free_min = VM_PAGEOUT_FREE_MIN;
+    }
+    if (free_min > total / 4u) {
+        free_min = total / 4u;
+    }
+    g_vm_pageout_stats.free_min = free_min;
+    g_vm_pageout_stats.free_target = free_min * 2u;
+
+    task_t* kernel_task = task_get_current();
+    if (!kernel_task) {
+        return;
+    }
+    g_vm_pageout_thread = thread_create(kernel_task, vm_pageout_thread_main, NULL);
+    if (!g_vm_pageout_thread) {
+        return;
+    }
+    g_vm_pageout_thread->priority = 16;
+    scheduler_set_bucket(g_vm_pageout_thread, SCHED_BUCKET_BACKGROUND);
+    scheduler_add_thread(g_vm_pageout_thread);
+}
+
+void vm_pageout_get_stats(vm_pageout_stats_t* out)
+{
+    if (!out) {
+        return;
+    }
+    irql_t old = vm_pageout_lock();
+    *out = g_vm_pageout_stats;
+    vm_pageout_unlock(old);
+}
@@ -0,0 +1,51 @@
+/**
+ * @file vm_pageout.h
+ * @brief Page-out daemon: active/inactive queues of anonymous pages
+ *
+ * Every resident page of an anonymous object is queued here. The daemon
+ * wakes periodically; when free memory drops below the low watermark it
+ * frees pages up to the high watermark, first from the page cache, then
+ * by writing the least recently used anonymous pages to swap.
+ *
+ * Recency comes from the accessed bits of the user mappings: a scan pass
+ * samples and clears them, moves pages nobody touched from the active to
+ * the inactive queue, and pages out inactive pages that stayed untouched.
+ */
+
+#ifndef _RODNIX_VM_PAGEOUT_H
+#define _RODNIX_VM_PAGEOUT_H
+
+#include <stdint.h>
+#include "vm_object.h"
+
+typedef struct vm_pageout_stats {
+    uint64_t active;       /* Pages on the active queue */
+    uint64_t inactive;
+    uint64_t free_min;     /* Low watermark, pages */
+    uint64_t free_target;  /* High watermark, pages */
+    uint64_t wakeups;      /* Daemon passes that found memory short */
+    uint64_t scans;        /* Pages examined on the inactive queue */
+    uint64_t reactivated;
+    uint64_t pageouts;
+    uint64_t cache_freed;  /* Page cache pages freed by the daemon */
+    uint64_t direct;       /* Reclaims from an allocation that failed */
+} vm_pageout_stats_t;
+
+/* Queue a resident anonymous page (vm_object_set_resident_page) */
+void vm_pageout_page_insert(vm_object_t* obj, uint64_t pindex, uint64_t phys);
+
+/* The page left its object: forget it */
+void vm_pageout_page_remove(uint64_t phys);
+
+/**
+ * Free up to @target pages now; used when an allocation fails.
+ * @return Pages freed
+ */
+uint64_t vm_pageout_reclaim(uint64_t target);
+
+/* Create the queues and start the daemon thread */
+void vm_pageout_start(void);
+
+void vm_pageout_get_stats(vm_pageout_stats_t* out);
+
+#endif /* _RODNIX_VM_PAGEOUT_H */
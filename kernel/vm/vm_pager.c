@@ -1,12 +1,23 @@
+/**
+ * @file vm_pager.c
+ * @brief Zero-fill pages and the vnode pager
+ */
+
 #include "vm_pager.h"
 #include "vm_page_ref.h"
+#include "vm_page_cache.h"
+#include "vm_pageout.h"
 #include "../arch/pmm.h"
 #include "../arch/config.h"
 #include "../../include/common.h"
+#include "../../include/error.h"
 
 uint64_t vm_pager_alloc_zero_page(void)
 {
     uint64_t phys = pmm_alloc_page_in_zone(PMM_ZONE_NORMAL);
+    if (!phys && vm_pageout_reclaim(0) > 0) {
+        phys = pmm_alloc_page_in_zone(PMM_ZONE_NORMAL);
+    }
     if (!phys) {
         return 0;
     }
@@ -15,3 +26,72 @@ uint64_t vm_pager_alloc_zero_page(void)
     (void)vm_page_ref_add_new(phys);
     return phys;
 }
+
+void vm_pager_release(const uint64_t* phys, uint32_t count)
+{
+    for (uint32_t i = 0; i < count; i++) {
+        if (phys[i]) {
+            (void)vm_page_ref_release(phys[i]);
+        }
+    }
+}
+
+/*
+ * Vnode pager: file pages are the page cache pages, filled from and
+ * written back to the filesystem through the object's cache_ops.
+ */
+static int vm_vnode_getpages(vm_object_t* obj, uint64_t pindex, uint32_t count, uint32_t flags, uint64_t* phys)
+{
+    uint32_t cache_flags = (flags & VM_PAGER_GET_WRITE) ? VM_PAGE_CACHE_WMAP : 0;
+    for (uint32_t i = 0; i < count; i++) {
+        int rc = vm_page_cache_get(obj, pindex + i, cache_flags, &phys[i]);
+        if (rc != RDNX_OK) {
+            vm_pager_release(phys, i);
+            return rc;
+        }
+    }
+    return RDNX_OK;
+}
+
+static int vm_vnode_putpages(vm_object_t* obj, uint64_t offset, uint64_t len)
+{
+    return vm_page_cache_flush(obj, offset, len);
+}
+
+const vm_pager_ops_t vm_vnode_pager = {
+    .name = "vnode",
+    .getpages = vm_vnode_getpages,
+    .putpages = vm_vnode_putpages,
+    .dealloc = vm_page_cache_object_destroy,
+};
+
+vm_object_t* vm_vnode_pager_alloc(uint64_t size, const struct vm_page_cache_ops* ops, void* owner)
+{
+    vm_object_t* obj = vm_page_cache_object_create(size, ops, owner);
+    if (obj) {
+        obj->pager = &vm_vnode_pager;
+    }
+    return obj;
+}
+
+int vm_pager_getpages(vm_object_t* obj, uint64_t pindex, uint32_t count, uint32_t flags, uint64_t* phys)
+{
+    if (!obj || !phys || count == 0) {
+        return RDNX_E_INVALID;
+    }
+    if (!obj->pager || !obj->pager->getpages) {
+        return RDNX_E_UNSUPPORTED;
+    }
+    return obj->pager->getpages(obj, pindex, count, flags, phys);
+}
+
+int vm_pager_putpages(vm_object_t* obj, uint64_t offset, uint64_t len)
+{
+    if (!obj) {
+        return RDNX_E_INVALID;
+    }
+    if (!obj->pager || !obj->pager->putpages) {
+        return RDNX_E_UNSUPPORTED;
+    }
+    return obj->pager->putpages(obj, offset, len);
+}
@@ -1,9 +1,54 @@
+/**
+ * @file vm_pager.h
+ * @brief Pager interface: how a vm_object brings pages in and writes them out
+ *
+ * File objects get their pages from the vnode pager, which serves them
+ * out of the page cache. Anonymous objects use the swap pager: a page
+ * that is neither resident nor on swap is zero-filled by the fault path.
+ */
+
 #ifndef _RODNIX_VM_PAGER_H
 #define _RODNIX_VM_PAGER_H
 
 #include <stdint.h>
+#include "vm_object.h"
+
+struct vm_page_cache_ops;
+
+/* getpages() flags */
+#define VM_PAGER_GET_WRITE 0x1u /* Pages get a writable shared mapping */
+
+typedef struct vm_pager_ops {
+    const char* name;
+    /**
+     * Bring in @count pages starting at @pindex, all or nothing.
+     * @param phys Filled with @count frames, each referenced for the caller
+     */
+    int (*getpages)(vm_object_t* obj, uint64_t pindex, uint32_t count, uint32_t flags, uint64_t* phys);
+    /* Write back modified pages of [offset, offset + len); len 0 means to the end */
+    int (*putpages)(vm_object_t* obj, uint64_t offset, uint64_t len);
+    /* Last reference to @obj is gone: drop the pager's pages and state */
+    void (*dealloc)(vm_object_t* obj);
+} vm_pager_ops_t;
+
+extern const vm_pager_ops_t vm_vnode_pager;
+extern const vm_pager_ops_t vm_swap_pager; /* vm_swap.c */
 
 uint64_t vm_pager_alloc_zero_page(void);
 
-#endif /* _RODNIX_VM_PAGER_H */
+/**
+ * Create a file object served by the vnode pager.
+ * @param ops Filesystem read/write, or NULL for a memory-only file
+ * @param owner Filesystem node passed back to @ops
+ */
+vm_object_t* vm_vnode_pager_alloc(uint64_t size, const struct vm_page_cache_ops* ops, void* owner);
+
+/* RDNX_E_UNSUPPORTED for objects without a pager; RDNX_E_NOTFOUND if the
+ * swap pager holds no copy of a page */
+int vm_pager_getpages(vm_object_t* obj, uint64_t pindex, uint32_t count, uint32_t flags, uint64_t* phys);
+int vm_pager_putpages(vm_object_t* obj, uint64_t offset, uint64_t len);
 
+/* Drop the references returned by vm_pager_getpages() */
+void vm_pager_release(const uint64_t* phys, uint32_t count);
+
+#endif /* _RODNIX_VM_PAGER_H */
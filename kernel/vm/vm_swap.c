@@ -0,0 +1,561 @@
+/**
+ * @file vm_swap.c
+ * @brief Swap devices, slot allocation and the swap pager
+ *
+ * Each device has a bitmap of page slots allocated next-fit. A swapped
+ * page is recorded in its object's resident_pages entry as
+ * ((device << 40 | slot) << 12) | VM_OBJECT_SWAPPED, and the object sits
+ * on g_vm_swap_objects so that swapoff can find every page on a device.
+ *
+ * Swap I/O runs without the lock. Both directions re-check the object
+ * entry afterwards and give up if it changed meanwhile: a page written
+ * out is dropped only if nobody mapped it again, a page read in is
+ * installed only if the slot is still the one that was read.
+ */
+
+#include "vm_swap.h"
+#include "vm_pager.h"
+#include "vm_pageout.h"
+#include "vm_page_ref.h"
+#include "vm_page_cache.h"
+#include "../fs/vfs.h"
+#include "../fs/ext2.h"
+#include "../fabric/service/block_service.h"
+#include "../common/kmem.h"
+#include "../common/scheduler.h"
+#include "../core/interrupts.h"
+#include "../arch/config.h"
+#include "../../include/common.h"
+#include "../../include/error.h"
+
+#define VM_SWAP_PAGE_SIZE  0x1000ULL
+#define VM_SWAP_SLOT_BITS  40u
+#define VM_SWAP_SLOT_MASK  ((1ULL << VM_SWAP_SLOT_BITS) - 1u)
+#define VM_SWAP_DRAIN_WAIT 10u /* ms between checks for in-flight slots */
+
+typedef struct vm_swap_dev {
+    int active;
+    vm_swap_info_t info;
+    fabric_blockdev_t* bdev;   /* Block device */
+    vfs_file_t file;           /* VM_SWAP_F_FILE: kept open while swapping */
+    vm_object_t* object;       /* VM_SWAP_F_FILE: the file's object, for cache_ops */
+    uint64_t* bitmap;          /* One bit per slot */
+    uint64_t cursor;           /* Next-fit start */
+} vm_swap_dev_t;
+
+LIST_HEAD(vm_swap_object_list, vm_object);
+
+/*
+ * LOCKING: swap state — IRQL giant (vm_swap_lock).
+ *   Protects: g_vm_swap_devs, every device bitmap, g_vm_swap_objects,
+ *   VM_OBJECT_SWAPPED entries and the swapped/swap_link fields of anon
+ *   objects, g_vm_swap_stats. Never held across device I/O.
+ */
+static vm_swap_dev_t g_vm_swap_devs[VM_SWAP_MAX_DEVICES];
+static struct vm_swap_object_list g_vm_swap_objects = LIST_HEAD_INITIALIZER(g_vm_swap_objects);
+static vm_swap_stats_t g_vm_swap_stats;
+
+static inline irql_t vm_swap_lock(void)
+{
+    return set_irql(IRQL_HIGH);
+}
+
+static inline void vm_swap_unlock(irql_t old)
+{
+    (void)set_irql(old);
+}
+
+static inline uint64_t vm_swap_entry(uint32_t dev, uint64_t slot)
+{
+    return ((((uint64_t)dev << VM_SWAP_SLOT_BITS) | slot) << 12) | VM_OBJECT_SWAPPED;
+}
+
+static inline uint32_t vm_swap_entry_dev(uint64_t entry)
+{
+    return (uint32_t)((entry >> 12) >> VM_SWAP_SLOT_BITS);
+}
+
+static inline uint64_t vm_swap_entry_slot(uint64_t entry)
+{
+    return (entry >> 12) & VM_SWAP_SLOT_MASK;
+}
+
+static int vm_swap_slot_alloc(uint32_t* out_dev, uint64_t* out_slot)
+{
+    for (uint32_t d = 0; d < VM_SWAP_MAX_DEVICES; d++) {
+        vm_swap_dev_t* dev = &g_vm_swap_devs[d];
+        if (!dev->active || (dev->info.flags & VM_SWAP_F_DRAINING) ||
+            dev->info.used >= dev->info.pages) {
+            continue;
+        }
+        uint64_t slot = dev->cursor;
+        for (uint64_t n = 0; n < dev->info.pages; n++) {
+            uint64_t bit = 1ULL << (slot & 63u);
+            if ((dev->bitmap[slot >> 6] & bit) == 0) {
+                dev->bitmap[slot >> 6] |= bit;
+                dev->cursor = (slot + 1u < dev->info.pages) ? slot + 1u : 0;
+                dev->info.used++;
+                g_vm_swap_stats.used++;
+                *out_dev = d;
+                *out_slot = slot;
+                return RDNX_OK;
+            }
+            slot = (slot + 1u < dev->info.pages) ? slot + 1u : 0;
+        }
+    }
+    return RDNX_E_NOMEM;
+}
+
+static void vm_swap_slot_free(uint32_t d, uint64_t slot)
+{
+    if (d >= VM_SWAP_MAX_DEVICES) {
+        return;
+    }
+    vm_swap_dev_t* dev = &g_vm_swap_devs[d];
+    if (!dev->active || slot >= dev->info.pages) {
+        return;
+    }
+    uint64_t bit = 1ULL << (slot & 63u);
+    if (dev->bitmap[slot >> 6] & bit) {
+        dev->bitmap[slot >> 6] &= ~bit;
+        dev->info.used--;
+        g_vm_swap_stats.used--;
+    }
+}
+
+static void vm_swap_entry_set(vm_object_t* obj, uint64_t pindex, uint64_t entry)
+{
+    obj->resident_pages[pindex] = entry;
+    if (obj->swapped++ == 0) {
+        LIST_INSERT_HEAD(&g_vm_swap_objects, obj, swap_link);
+    }
+}
+
+static void vm_swap_entry_clear(vm_object_t* obj, uint64_t pindex)
+{
+    uint64_t entry = obj->resident_pages[pindex];
+    obj->resident_pages[pindex] = 0;
+    vm_swap_slot_free(vm_swap_entry_dev(entry), vm_swap_entry_slot(entry));
+    if (--obj->swapped == 0) {
+        LIST_REMOVE(obj, swap_link);
+    }
+}
+
+/* Slot 0 sits one page into the device: the first page is left alone */
+static int vm_swap_io(vm_swap_dev_t* dev, uint64_t slot, void* page, int write)
+{
+    uint64_t off = (slot + 1u) * VM_SWAP_PAGE_SIZE;
+    if (dev->info.flags & VM_SWAP_F_FILE) {
+        const vm_page_cache_ops_t* ops = dev->object->cache_ops;
+        if (write) {
+            return ops->write(dev->object->cache_owner, off, page, VM_SWAP_PAGE_SIZE);
+        }
+        return ops->read(dev->object->cache_owner, off, page);
+    }
+    uint32_t sector_size = dev->bdev->sector_size;
+    uint32_t count = (uint32_t)(VM_SWAP_PAGE_SIZE / sector_size);
+    if (write) {
+        return fabric_blockdev_write(dev->bdev, off / sector_size, count, page);
+    }
+    return fabric_blockdev_read(dev->bdev, off / sector_size, count, page);
+}
+
+int vm_swap_has_space(void)
+{
+    irql_t old = vm_swap_lock();
+    int space = 0;
+    for (uint32_t d = 0; d < VM_SWAP_MAX_DEVICES; d++) {
+        vm_swap_dev_t* dev = &g_vm_swap_devs[d];
+        if (dev->active && (dev->info.flags & VM_SWAP_F_DRAINING) == 0 &&
+            dev->info.used < dev->info.pages) {
+            space = 1;
+            break;
+        }
+    }
+    vm_swap_unlock(old);
+    return space;
+}
+
+int vm_swap_pageout(vm_object_t* obj, uint64_t pindex, uint64_t phys)
+{
+    if (!obj || obj->type != VM_OBJECT_ANON || !obj->resident_pages ||
+        pindex >= obj->page_count || !phys) {
+        return RDNX_E_INVALID;
+    }
+    irql_t old = vm_swap_lock();
+    if (obj->resident_pages[pindex] != phys || vm_page_ref_count(phys) != 1) {
+        vm_swap_unlock(old);
+        return RDNX_E_BUSY;
+    }
+    uint32_t d = 0;
+    uint64_t slot = 0;
+    if (vm_swap_slot_alloc(&d, &slot) != RDNX_OK) {
+        vm_swap_unlock(old);
+        return RDNX_E_NOMEM;
+    }
+    vm_swap_dev_t* dev = &g_vm_swap_devs[d];
+    (void)vm_page_ref_retain(phys); /* Keeps the frame ours during the write */
+    vm_swap_unlock(old);
+
+    int rc = vm_swap_io(dev, slot, ARCH_PHYS_TO_VIRT(phys), 1);
+
+    old = vm_swap_lock();
+    if (rc != RDNX_OK) {
+        g_vm_swap_stats.io_errors++;
+    } else if (obj->resident_pages[pindex] != phys || vm_page_ref_count(phys) != 2 ||
+               (dev->info.flags & VM_SWAP_F_DRAINING)) {
+        rc = RDNX_E_BUSY; /* Mapped again, or the device is being drained */
+    }
+    if (rc != RDNX_OK) {
+        vm_swap_slot_free(d, slot);
+        vm_swap_unlock(old);
+        (void)vm_page_ref_release(phys);
+        return rc;
+    }
+    vm_pageout_page_remove(phys);
+    vm_swap_entry_set(obj, pindex, vm_swap_entry(d, slot));
+    g_vm_swap_stats.pageouts++;
+    vm_swap_unlock(old);
+    (void)vm_page_ref_release(phys); /* Object's reference */
+    (void)vm_page_ref_release(phys); /* Ours: the frame is free now */
+    return RDNX_OK;
+}
+
+void vm_swap_discard(vm_object_t* obj, uint64_t pindex)
+{
+    if (!obj || !obj->resident_pages || pindex >= obj->page_count) {
+        return;
+    }
+    irql_t old = vm_swap_lock();
+    if (obj->resident_pages[pindex] & VM_OBJECT_SWAPPED) {
+        vm_swap_entry_clear(obj, pindex);
+    }
+    vm_swap_unlock(old);
+}
+
+/* Bring in one page; the returned frame carries a reference for the caller */
+static int vm_swap_getpage(vm_object_t* obj, uint64_t pindex, uint64_t* out_phys)
+{
+    if (!obj->resident_pages || pindex >= obj->page_count) {
+        return RDNX_E_INVALID;
+    }
+    for (;;) {
+        irql_t old = vm_swap_lock();
+        uint64_t entry = obj->resident_pages[pindex];
+        if ((entry & VM_OBJECT_SWAPPED) == 0) {
+            if (entry) {
+                (void)vm_page_ref_retain(entry); /* Swapped in meanwhile */
+            }
+            vm_swap_unlock(old);
+            if (!entry) {
+                return RDNX_E_NOTFOUND;
+            }
+            *out_phys = entry;
+            return RDNX_OK;
+        }
+        vm_swap_dev_t* dev = &g_vm_swap_devs[vm_swap_entry_dev(entry)];
+        vm_swap_unlock(old);
+
+        uint64_t phys = vm_pager_alloc_zero_page();
+        if (!phys) {
+            return RDNX_E_NOMEM;
+        }
+        int rc = vm_swap_io(dev, vm_swap_entry_slot(entry), ARCH_PHYS_TO_VIRT(phys), 0);
+
+        old = vm_swap_lock();
+        if (obj->resident_pages[pindex] != entry) {
+            vm_swap_unlock(old);
+            (void)vm_page_ref_release(phys);
+            continue;
+        }
+        if (rc != RDNX_OK) {
+            g_vm_swap_stats.io_errors++;
+            vm_swap_unlock(old);
+            (void)vm_page_ref_release(phys);
+            return rc;
+        }
+        vm_swap_entry_clear(obj, pindex);
+        (void)vm_object_set_resident_page(obj, pindex, phys);
+        g_vm_swap_stats.pageins++;
+        vm_swap_unlock(old);
+        *out_phys = phys;
+        return RDNX_OK;
+    }
+}
+
+static int vm_swap_getpages(vm_object_t* obj, uint64_t pindex, uint32_t count, uint32_t flags, uint64_t* phys)
+{
+    (void)flags;
+    for (uint32_t i = 0; i < count; i++) {
+        int rc = vm_swap_getpage(obj, pindex + i, &phys[i]);
+        if (rc != RDNX_OK) {
+            vm_pager_release(phys, i);
+            return rc;
+        }
+    }
+    return RDNX_OK;
+}
+
+/* Anonymous memory has no backing store to write back to */
+static int vm_swap_putpages(vm_object_t* obj, uint64_t offset, uint64_t len)
+{
+    (void)obj;
+    (void)offset;
+    (void)len;
+    return RDNX_OK;
+}
+
+static void vm_swap_dealloc(vm_object_t* obj)
+{
+    if (!obj->resident_pages) {
+        return;
+    }
+    irql_t old = vm_swap_lock();
+    for (uint64_t i = 0; i < obj->page_count && obj->swapped > 0; i++) {
+        if (obj->resident_pages[i] & VM_OBJECT_SWAPPED) {
+            vm_swap_entry_clear(obj, i);
+        }
+    }
+    vm_swap_unlock(old);
+}
+
+const vm_pager_ops_t vm_swap_pager = {
+    .name = "swap",
+    .getpages = vm_swap_getpages,
+    .putpages = vm_swap_putpages,
+    .dealloc = vm_swap_dealloc,
+};
+
+static int vm_swap_find_locked(const char* path)
+{
+    for (uint32_t d = 0; d < VM_SWAP_MAX_DEVICES; d++) {
+        if (g_vm_swap_devs[d].active && strcmp(g_vm_swap_devs[d].info.path, path) == 0) {
+            return (int)d;
+        }
+    }
+    return -1;
+}
+
+int vm_swap_on(const char* path)
+{
+    if (!path || !path[0]) {
+        return RDNX_E_INVALID;
+    }
+    vm_swap_dev_t dev;
+    memset(&dev, 0, sizeof(dev));
+    int rc = vfs_open(path, VFS_OPEN_READ | VFS_OPEN_WRITE, &dev.file);
+    if (rc != RDNX_OK) {
+        return rc;
+    }
+    vfs_inode_t* inode = dev.file.node->inode;
+    uint64_t bytes = 0;
+    if (inode->flags & VFS_INODE_BLOCKDEV) {
+        dev.bdev = fabric_blockdev_find(dev.file.node->name);
+        (void)vfs_close(&dev.file);
+        if (!dev.bdev) {
+            return RDNX_E_NOTFOUND;
+        }
+        uint32_t ss = dev.bdev->sector_size;
+        if ((dev.bdev->flags & FABRIC_BLOCKDEV_F_READONLY) || ss == 0 ||
+            ss > VM_SWAP_PAGE_SIZE || (VM_SWAP_PAGE_SIZE % ss) != 0) {
+            return RDNX_E_UNSUPPORTED;
+        }
+        if ((dev.bdev->flags & FABRIC_BLOCKDEV_F_SYSTEM) || ext2_mounted_on(dev.bdev)) {
+            return RDNX_E_BUSY;
+        }
+        bytes = dev.bdev->sector_count * ss;
+    } else {
+        dev.object = vfs_node_object(dev.file.node);
+        if (!dev.object || !dev.object->cache_ops) {
+            (void)vfs_close(&dev.file);
+            return RDNX_E_UNSUPPORTED; /* Memory-only files cannot hold swap */
+        }
+        /* Cached writes made before swapon must not land on swap slots later */
+        rc = vm_page_cache_flush(dev.object, 0, 0);
+        if (rc != RDNX_OK) {
+            (void)vfs_close(&dev.file);
+            return rc;
+        }
+        vm_object_ref(dev.object);
+        dev.info.flags |= VM_SWAP_F_FILE;
+        bytes = inode->size;
+    }
+
+    uint64_t pages = bytes / VM_SWAP_PAGE_SIZE;
+    if (pages > VM_SWAP_SLOT_MASK) {
+        pages = VM_SWAP_SLOT_MASK;
+    }
+    rc = RDNX_OK;
+    if (pages < 2) {
+        rc = RDNX_E_INVALID;
+    } else {
+        dev.info.pages = pages - 1u;
+        dev.bitmap = (uint64_t*)kmem_page_alloc((size_t)(((dev.info.pages + 63u) / 64u) * sizeof(uint64_t)));
+        if (!dev.bitmap) {
+            rc = RDNX_E_NOMEM;
+        }
+    }
+    if (rc == RDNX_OK) {
+        memset(dev.bitmap, 0, (size_t)(((dev.info.pages + 63u) / 64u) * sizeof(uint64_t)));
+        strncpy(dev.info.path, path, VM_SWAP_PATH_MAX - 1u);
+        dev.info.path[VM_SWAP_PATH_MAX - 1u] = '\0';
+        dev.active = 1;
+
+        irql_t old = vm_swap_lock();
+        vm_swap_dev_t* slot = NULL;
+        for (uint32_t d = 0; d < VM_SWAP_MAX_DEVICES; d++) {
+            vm_swap_dev_t* cur = &g_vm_swap_devs[d];
+            if (!cur->active) {
+                slot = slot ? slot : cur;
+            } else if (strcmp(cur->info.path, dev.info.path) == 0 ||
+                       (dev.bdev && cur->bdev == dev.bdev) ||
+                       (dev.object && cur->object == dev.object)) {
+                slot = NULL; /* Already swapping there */
+                break;
+            }
+        }
+        rc = RDNX_E_BUSY;
+        if (slot) {
+            *slot = dev;
+            g_vm_swap_stats.total += dev.info.pages;
+            rc = RDNX_OK;
+        }
+        vm_swap_unlock(old);
+    }
+    if (rc != RDNX_OK) {
+        if (dev.bitmap) {
+            kmem_page_free(dev.bitmap);
+        }
+        if (dev.object) {
+            vm_object_unref(dev.object);
+            (void)vfs_close(&dev.file);
+        }
+    }
+    return rc;
+}
+
+/* Next object with a page on device @d, referenced for the caller */
+static vm_object_t* vm_swap_next_on_device(uint32_t d, uint64_t* out_pindex)
+{
+    irql_t old = vm_swap_lock();
+    vm_object_t* obj;
+    LIST_FOREACH(obj, &g_vm_swap_objects, swap_link) {
+        for (uint64_t i = 0; i < obj->page_count; i++) {
+            uint64_t entry = obj->resident_pages[i];
+            if ((entry & VM_OBJECT_SWAPPED) && vm_swap_entry_dev(entry) == d) {
+                vm_object_ref(obj);
+                vm_swap_unlock(old);
+                *out_pindex = i;
+                return obj;
+            }
+        }
+    }
+    vm_swap_unlock(old);
+    return NULL;
+}
+
+int vm_swap_off(const char* path)
+{
+    if (!path || !path[0]) {
+        return RDNX_E_INVALID;
+    }
+    irql_t old = vm_swap_lock();
+    int idx = vm_swap_find_locked(path);
+    if (idx < 0) {
+        vm_swap_unlock(old);
+        return RDNX_E_NOTFOUND;
+    }
+    vm_swap_dev_t* dev = &g_vm_swap_devs[idx];
+    if (dev->info.flags & VM_SWAP_F_DRAINING) {
+        vm_swap_unlock(old);
+        return RDNX_E_BUSY;
+    }
+    dev->info.flags |= VM_SWAP_F_DRAINING;
+    vm_swap_unlock(old);
+
+    int rc = RDNX_OK;
+    for (;;) {
+        uint64_t pindex = 0;
+        vm_object_t* obj = vm_swap_next_on_device((uint32_t)idx, &pindex);
+        if (!obj) {
+            old = vm_swap_lock();
+            uint64_t used = dev->info.used;
+            vm_swap_unlock(old);
+            if (used == 0) {
+                break;
+            }
+            scheduler_sleep(VM_SWAP_DRAIN_WAIT); /* Page-outs still in flight */
+            continue;
+        }
+        uint64_t phys = 0;
+        rc = vm_swap_getpage(obj, pindex, &phys);
+        vm_object_unref(obj);
+        if (rc == RDNX_OK) {
+            vm_pager_release(&phys, 1);
+        } else if (rc != RDNX_E_NOTFOUND) {
+            break;
+        }
+        rc = RDNX_OK;
+    }
+
+    old = vm_swap_lock();
+    if (rc != RDNX_OK) {
+        dev->info.flags &= ~VM_SWAP_F_DRAINING;
+        vm_swap_unlock(old);
+        return rc;
+    }
+    vm_swap_dev_t gone = *dev;
+    memset(dev, 0, sizeof(*dev));
+    g_vm_swap_stats.total -= gone.info.pages;
+    vm_swap_unlock(old);
+
+    kmem_page_free(gone.bitmap);
+    if (gone.object) {
+        vm_object_unref(gone.object);
+        (void)vfs_close(&gone.file);
+    }
+    return RDNX_OK;
+}
+
+uint32_t vm_swap_device_count(void)
+{
+    irql_t old = vm_swap_lock();
+    uint32_t n = 0;
+    for (uint32_t d = 0; d < VM_SWAP_MAX_DEVICES; d++) {
+        if (g_vm_swap_devs[d].active) {
+            n++;
+        }
+    }
+    vm_swap_unlock(old);
+    return n;
+}
+
+int vm_swap_get_info(uint32_t index, vm_swap_info_t* out)
+{
+    if (!out) {
+        return RDNX_E_INVALID;
+    }
+    irql_t old = vm_swap_lock();
+    for (uint32_t d = 0; d < VM_SWAP_MAX_DEVICES; d++) {
+        if (!g_vm_swap_devs[d].active) {
+            continue;
+        }
+        if (index-- == 0) {
+            *out = g_vm_swap_devs[d].info;
+            vm_swap_unlock(old);
+            return RDNX_OK;
+        }
+    }
+    vm_swap_unlock(old);
+    return RDNX_E_NOTFOUND;
+}
+
+void vm_swap_get_stats(vm_swap_stats_t* out)
+{
+    if (!out) {
+        return;
+    }
+    irql_t old = vm_swap_lock();
+    *out = g_vm_swap_stats;
+    vm_swap_unlock(old);
+}
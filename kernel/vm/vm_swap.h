@@ -0,0 +1,75 @@
+/**
+ * @file vm_swap.h
+ * @brief Swap devices and the swap pager for anonymous objects
+ *
+ * A swap device is a block device (/dev/diskN) or a pre-allocated
+ * regular file on a disk-backed filesystem. Each is cut into page-sized
+ * slots; the first page of the device is never used. A page written to
+ * swap leaves a VM_OBJECT_SWAPPED entry in its object's resident_pages,
+ * and the swap pager reads it back on the next fault.
+ */
+
+#ifndef _RODNIX_VM_SWAP_H
+#define _RODNIX_VM_SWAP_H
+
+#include <stdint.h>
+#include "vm_object.h"
+
+#define VM_SWAP_MAX_DEVICES 4u
+#define VM_SWAP_PATH_MAX    64u
+
+/* vm_swap_info_t.flags */
+#define VM_SWAP_F_FILE     0x1u /* Regular file, not a block device */
+#define VM_SWAP_F_DRAINING 0x2u /* swapoff in progress: no new slots */
+
+typedef struct vm_swap_info {
+    char path[VM_SWAP_PATH_MAX];
+    uint64_t pages;   /* Usable slots */
+    uint64_t used;
+    uint32_t flags;
+} vm_swap_info_t;
+
+typedef struct vm_swap_stats {
+    uint64_t total;     /* Slots on all devices */
+    uint64_t used;
+    uint64_t pageouts;  /* Pages written to swap */
+    uint64_t pageins;   /* Pages read back */
+    uint64_t io_errors;
+} vm_swap_stats_t;
+
+/**
+ * Start swapping to a block device or a regular file.
+ * @return RDNX_OK, RDNX_E_BUSY (already in use, or holds a mounted
+ *         filesystem), RDNX_E_UNSUPPORTED (not a disk-backed file or a
+ *         writable block device), RDNX_E_NOMEM, RDNX_E_NOTFOUND
+ */
+int vm_swap_on(const char* path);
+
+/**
+ * Read every page on @path back into memory and stop using it.
+ * @return RDNX_OK, RDNX_E_NOTFOUND, RDNX_E_NOMEM if the pages do not fit
+ *         in memory (the device stays active), or an I/O error
+ */
+int vm_swap_off(const char* path);
+
+/* A free slot exists on some active device */
+int vm_swap_has_space(void);
+
+/**
+ * Write a resident anonymous page to swap and drop it from the object.
+ * The caller has unmapped it everywhere; the object's reference must be
+ * the only one left, and the write is abandoned if that changes while
+ * the I/O runs unlocked.
+ * @return RDNX_OK, RDNX_E_NOMEM (swap full), RDNX_E_BUSY (page in use
+ *         again), or an I/O error
+ */
+int vm_swap_pageout(vm_object_t* obj, uint64_t pindex, uint64_t phys);
+
+/* Free the slot held by a VM_OBJECT_SWAPPED entry (the page is being replaced) */
+void vm_swap_discard(vm_object_t* obj, uint64_t pindex);
+
+uint32_t vm_swap_device_count(void);
+int vm_swap_get_info(uint32_t index, vm_swap_info_t* out);
+void vm_swap_get_stats(vm_swap_stats_t* out);
+
+#endif /* _RODNIX_VM_SWAP_H */
@@ -28,6 +28,15 @@ else
     MISSING=1
 fi
 
+# Check for nm (embedded kernel symbol table)
+if command -v x86_64-elf-nm >/dev/null 2>&1; then
+    echo "[OK] x86_64-elf-nm: $(which x86_64-elf-nm)"
+else
+    echo "[MISSING] x86_64-elf-nm"
+    echo "  Install: brew install x86_64-elf-binutils (macOS)"
+    MISSING=1
+fi
+
 # Check for NASM
 if command -v nasm >/dev/null 2>&1; then
     echo "[OK] nasm: $(which nasm)"
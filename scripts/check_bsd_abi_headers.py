@@ -125,6 +125,8 @@ def main() -> int:
         "PROT_NONE", "PROT_READ", "PROT_WRITE", "PROT_EXEC",
         "MAP_SHARED", "MAP_PRIVATE", "MAP_FIXED", "MAP_ANON", "MAP_ANONYMOUS",
         "MS_SYNC", "MS_ASYNC", "MS_INVALIDATE",
+        "MADV_NORMAL", "MADV_RANDOM", "MADV_SEQUENTIAL", "MADV_WILLNEED", "MADV_DONTNEED",
+        "MADV_FREE", "MINCORE_INCORE",
     ]
 
     errors: list[str] = []
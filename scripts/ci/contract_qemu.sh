@@ -13,6 +13,8 @@ ISO_PATH="${ISO_PATH:-${BUILD_DIR}/rodnix.iso}"
 DISK_IMG="${DISK_IMG:-${BUILD_DIR}/rodnix-disk.img}"
 DISK_MB="${DISK_MB:-128}"
 DISK_FS_STAMP="${DISK_FS_STAMP:-${BUILD_DIR}/rodnix-disk.ext2.stamp}"
+CRASHDUMP_BIN="${CRASHDUMP_BIN:-crashdump.bin}"
+CRASHDUMP_TXT="${CRASHDUMP_TXT:-crashdump.txt}"
 FRESH_DISK="${FRESH_DISK:-1}"
 FLAG_FILE="userland/rootfs/etc/contract.auto"
 
@@ -21,6 +23,15 @@ cleanup() {
 }
 trap cleanup EXIT
 
+decode_crashdump() {
+  if [ -f "$LOG_FILE" ] && grep -q "^-----END RODNIX CRASHDUMP-----" "$LOG_FILE"; then
+    echo "[contract] kernel crash dump found, decoding to $CRASHDUMP_TXT"
+    python3 scripts/crashdump.py --serial "$LOG_FILE" --kernel "$BUILD_DIR/rodnix.kernel" \
+      -o "$CRASHDUMP_BIN" >"$CRASHDUMP_TXT" || true
+    head -n 40 "$CRASHDUMP_TXT" || true
+  fi
+}
+
 dump_diag() {
   if [ ! -f "$LOG_FILE" ]; then
     echo "[contract] no log file: $LOG_FILE"
@@ -30,10 +41,11 @@ dump_diag() {
   grep "^\[CT\]" "$LOG_FILE" | tail -n 20 || true
   echo "[contract] last boot log lines:"
   tail -n 60 "$LOG_FILE" || true
+  decode_crashdump
 }
 
 touch "$FLAG_FILE"
-rm -f "$LOG_FILE"
+rm -f "$LOG_FILE" "$CRASHDUMP_BIN" "$CRASHDUMP_TXT"
 
 make iso ARCH="$ARCH"
 mkdir -p "$(dirname "$DISK_IMG")"
@@ -73,6 +85,12 @@ while [ $SECONDS -lt $deadline ]; do
       kill "$QEMU_PID" >/dev/null 2>&1 || true
       exit 1
     fi
+    if grep -q "^-----END RODNIX CRASHDUMP-----" "$LOG_FILE"; then
+      echo "[contract] kernel panic"
+      dump_diag
+      kill "$QEMU_PID" >/dev/null 2>&1 || true
+      exit 1
+    fi
   fi
   sleep 1
 done
@@ -13,8 +13,19 @@ ISO_PATH="${ISO_PATH:-${BUILD_DIR}/rodnix.iso}"
 DISK_IMG="${DISK_IMG:-${BUILD_DIR}/rodnix-disk.img}"
 DISK_MB="${DISK_MB:-128}"
 DISK_FS_STAMP="${DISK_FS_STAMP:-${BUILD_DIR}/rodnix-disk.ext2.stamp}"
+CRASHDUMP_BIN="${CRASHDUMP_BIN:-crashdump.bin}"
+CRASHDUMP_TXT="${CRASHDUMP_TXT:-crashdump.txt}"
 
-rm -f "$LOG_FILE"
+decode_crashdump() {
+  if [ -f "$LOG_FILE" ] && grep -q "^-----END RODNIX CRASHDUMP-----" "$LOG_FILE"; then
+    echo "[smoke] kernel crash dump found, decoding to $CRASHDUMP_TXT"
+    python3 scripts/crashdump.py --serial "$LOG_FILE" --kernel "$BUILD_DIR/rodnix.kernel" \
+      -o "$CRASHDUMP_BIN" >"$CRASHDUMP_TXT" || true
+    head -n 40 "$CRASHDUMP_TXT" || true
+  fi
+}
+
+rm -f "$LOG_FILE" "$CRASHDUMP_BIN" "$CRASHDUMP_TXT"
 
 make iso ARCH="$ARCH"
 mkdir -p "$(dirname "$DISK_IMG")"
@@ -52,6 +63,10 @@ while [ $SECONDS -lt $deadline ]; do
       status_msg="[smoke] userspace init completed"
       break
     fi
+    if grep -q "^-----END RODNIX CRASHDUMP-----" "$LOG_FILE"; then
+      status_msg="[smoke] kernel panic"
+      break
+    fi
   fi
   sleep 1
 done
@@ -62,6 +77,11 @@ if [ $found -eq 1 ]; then
   exit 0
 fi
 
-echo "[smoke] timeout waiting for boot marker (shell prompt or userspace init)"
+if [ -n "$status_msg" ]; then
+  echo "$status_msg"
+else
+  echo "[smoke] timeout waiting for boot marker (shell prompt or userspace init)"
+fi
+decode_crashdump
 kill "$QEMU_PID" >/dev/null 2>&1 || true
 exit 1
@@ -0,0 +1,246 @@
+#!/usr/bin/env python3
+"""
+Decode a RodNIX crash dump (kernel/common/crashdump.h, format v1).
+
+The dump is taken either from a serial log (the last base64 block between
+the BEGIN/END RODNIX CRASHDUMP markers) or from a disk image (the MBR
+partition of type 0xDA, or an explicit --lba). With --kernel the backtrace
+and RIP are symbolized through `nm` of the kernel ELF.
+
+Exit status: 0 dump decoded, 1 no dump found, 2 dump is corrupt.
+"""
+
+from __future__ import annotations
+
+import argparse
+import base64
+import os
+import struct
+import subprocess
+import sys
+import zlib
+
+MAGIC = b"RDNXDUMP"
+VERSION = 1
+SECTOR = 512
+MBR_TYPE = 0xDA
+SERIAL_BEGIN = "-----BEGIN RODNIX CRASHDUMP-----"
+SERIAL_END = "-----END RODNIX CRASHDUMP-----"
+
+HEADER = struct.Struct("<8sHHIIIQII")
+SECTION = struct.Struct("<II")
+REGS = struct.Struct("<26Q")
+TASK = struct.Struct("<QQQIIBB6s64s")
+TRACE_REC = struct.Struct("<IHHIIQQQ")
+
+SEC_MESSAGE, SEC_REGS, SEC_BACKTRACE, SEC_TASK, SEC_TRACE, SEC_LOG = range(1, 7)
+F_FRAME = 0x1
+
+REG_NAMES = ("rip", "rsp", "rbp", "rflags",
+             "rax", "rbx", "rcx", "rdx", "rsi", "rdi",
+             "r8", "r9", "r10", "r11", "r12", "r13", "r14", "r15",
+             "cs", "ss", "cr0", "cr2", "cr3", "cr4", "vector", "err_code")
+THREAD_STATES = ("NEW", "READY", "RUNNING", "BLOCKED", "SLEEPING", "DEAD")
+TRACE_CATS = {1: "BOOT", 2: "SCHED", 3: "MEMORY", 4: "FAULT"}
+
+
+class DumpError(Exception):
+    pass
+
+
+def from_serial(path: str) -> bytes | None:
+    with open(path, "r", encoding="utf-8", errors="replace") as f:
+        lines = [ln.strip() for ln in f]
+    blob = None
+    i = 0
+    while i < len(lines):
+        if lines[i] == SERIAL_BEGIN:
+            body = []
+            i += 1
+            while i < len(lines) and lines[i] != SERIAL_END:
+                body.append(lines[i])
+                i += 1
+            if i < len(lines):
+                blob = "".join(body)
+        i += 1
+    if blob is None:
+        return None
+    try:
+        return base64.b64decode(blob, validate=True)
+    except ValueError as exc:
+        raise DumpError(f"bad base64 in serial dump: {exc}") from exc
+
+
+def find_dump_lba(img) -> int | None:
+    img.seek(0)
+    mbr = img.read(SECTOR)
+    if len(mbr) < SECTOR or mbr[510:512] != b"\x55\xaa":
+        return None
+    for i in range(4):
+        entry = mbr[446 + i * 16:462 + i * 16]
+        start, count = struct.unpack_from("<II", entry, 8)
+        if entry[4] == MBR_TYPE and start and count:
+            return start
+    return None
+
+
+def from_disk(path: str, lba: int | None) -> bytes | None:
+    with open(path, "rb") as img:
+        if lba is None:
+            lba = find_dump_lba(img)
+            if lba is None:
+                return None
+        img.seek(lba * SECTOR)
+        head = img.read(HEADER.size)
+        if len(head) < HEADER.size or head[:8] != MAGIC:
+            return None
+        total = HEADER.unpack(head)[3]
+        img.seek(lba * SECTOR)
+        return img.read(total)
+
+
+def parse(blob: bytes) -> tuple[tuple, list[tuple[int, bytes]]]:
+    if len(blob) < HEADER.size or blob[:8] != MAGIC:
+        raise DumpError("bad magic")
+    hdr = HEADER.unpack_from(blob)
+    _, version, header_size, total, crc, count, _, _, _ = hdr
+    if version != VERSION:
+        raise DumpError(f"unsupported version {version}")
+    if total > len(blob) or header_size > total:
+        raise DumpError(f"truncated dump: {len(blob)} of {total} bytes")
+    body = bytearray(blob[:total])
+    struct.pack_into("<I", body, 16, 0)
+    if zlib.crc32(bytes(body)) != crc:
+        raise DumpError(f"crc mismatch (stored {crc:08x}, actual {zlib.crc32(bytes(body)):08x})")
+
+    sections = []
+    off = header_size
+    for _ in range(count):
+        if off + SECTION.size > total:
+            raise DumpError("section table runs past the end")
+        stype, size = SECTION.unpack_from(blob, off)
+        off += SECTION.size
+        if off + size > total:
+            raise DumpError(f"section {stype} runs past the end")
+        sections.append((stype, blob[off:off + size]))
+        off += (size + 7) & ~7
+    return hdr, sections
+
+
+def load_symbols(kernel: str) -> list[tuple[int, str]]:
+    nm = os.environ.get("NM", "nm")
+    out = subprocess.run([nm, "-n", "--defined-only", kernel],
+                         check=True, capture_output=True, text=True).stdout
+    syms = []
+    for line in out.splitlines():
+        parts = line.split()
+        if len(parts) == 3 and parts[1] in "TtWw":
+            syms.append((int(parts[0], 16), parts[2]))
+    return syms
+
+
+def symbolize(syms: list[tuple[int, str]], addr: int) -> str:
+    lo, hi = 0, len(syms)
+    while lo < hi:
+        mid = (lo + hi) // 2
+        if syms[mid][0] <= addr:
+            lo = mid + 1
+        else:
+            hi = mid
+    if lo == 0:
+        return ""
+    base, name = syms[lo - 1]
+    return f" {name}+0x{addr - base:x}"
+
+
+def report(hdr: tuple, sections: list[tuple[int, bytes]], syms, out) -> None:
+    _, version, _, total, crc, count, uptime_ns, cpu, flags = hdr
+    w = out.write
+    w(f"RodNIX crash dump v{version}: {total} bytes, {count} sections, crc {crc:08x}\n")
+    w(f"cpu={cpu} uptime={uptime_ns // 1000000000}.{uptime_ns % 1000000000 // 1000000:03d}s "
+      f"regs={'exception frame' if flags & F_FRAME else 'panic() caller'}\n")
+
+    for stype, data in sections:
+        if stype == SEC_MESSAGE:
+            w(f"\nMessage: {data.decode('utf-8', 'replace')}\n")
+        elif stype == SEC_REGS and len(data) >= REGS.size:
+            regs = dict(zip(REG_NAMES, REGS.unpack_from(data)))
+            w("\nRegisters:\n")
+            names = REG_NAMES if flags & F_FRAME else \
+                ("rip", "rsp", "rbp", "rflags", "cs", "ss", "cr0", "cr2", "cr3", "cr4")
+            for i in range(0, len(names), 4):
+                w("  " + "  ".join(f"{n.upper():>8}={regs[n]:016x}" for n in names[i:i + 4]) + "\n")
+            if syms:
+                w(f"  RIP is{symbolize(syms, regs['rip']) or ' ?'}\n")
+        elif stype == SEC_BACKTRACE:
+            w("\nBacktrace:\n")
+            for i in range(len(data) // 8):
+                pc = struct.unpack_from("<Q", data, i * 8)[0]
+                w(f"  #{i:<2} {pc:016x}{symbolize(syms, pc) if syms else ''}\n")
+        elif stype == SEC_TASK and len(data) >= TASK.size:
+            task_id, parent, thread_id, uid, state, abi, prio, _, cwd = TASK.unpack_from(data)
+            cwd = cwd.split(b"\0", 1)[0].decode("utf-8", "replace")
+            w("\nCurrent:\n")
+            if task_id:
+                w(f"  task={task_id} parent={parent} uid={uid} "
+                  f"abi={'linux' if abi == 1 else 'native'} "
+                  f"cwd={cwd}\n")
+            if thread_id:
+                sname = THREAD_STATES[state] if state < len(THREAD_STATES) else str(state)
+                w(f"  thread={thread_id} state={sname} priority={prio}\n")
+            if not task_id and not thread_id:
+                w("  (no task)\n")
+        elif stype == SEC_TRACE:
+            n = len(data) // TRACE_REC.size
+            w(f"\nTrace ({n} recent events):\n")
+            for i in range(n):
+                seq, cat, ev, rcpu, _, tick, a0, a1 = TRACE_REC.unpack_from(data, i * TRACE_REC.size)
+                w(f"  seq={seq} tick={tick} cpu={rcpu} {TRACE_CATS.get(cat, cat)}/{ev} "
+                  f"a0={a0:#x} a1={a1:#x}\n")
+        elif stype == SEC_LOG:
+            w(f"\nLog tail ({len(data)} bytes):\n")
+            text = data.decode("utf-8", "replace").replace("\r", "")
+            for line in text.split("\n"):
+                w(f"  | {line}\n")
+        else:
+            w(f"\nSection {stype}: {len(data)} bytes (unknown)\n")
+
+
+def main() -> int:
+    ap = argparse.ArgumentParser(description=__doc__,
+                                 formatter_class=argparse.RawDescriptionHelpFormatter)
+    src = ap.add_mutually_exclusive_group(required=True)
+    src.add_argument("--serial", metavar="LOG", help="serial log (e.g. CI boot.log)")
+    src.add_argument("--disk", metavar="IMG", help="raw disk image")
+    src.add_argument("--raw", metavar="BIN", help="raw dump blob saved with -o")
+    ap.add_argument("--lba", type=int, help="dump start sector on --disk (default: MBR type 0xDA)")
+    ap.add_argument("--kernel", metavar="ELF", help="kernel image for symbolization")
+    ap.add_argument("-o", "--output", metavar="BIN", help="also save the raw dump blob")
+    args = ap.parse_args()
+
+    try:
+        if args.serial:
+            blob = from_serial(args.serial)
+        elif args.disk:
+            blob = from_disk(args.disk, args.lba)
+        else:
+            with open(args.raw, "rb") as f:
+                blob = f.read()
+        if not blob:
+            print("crashdump: no dump found", file=sys.stderr)
+            return 1
+        hdr, sections = parse(blob)
+    except DumpError as exc:
+        print(f"crashdump: {exc}", file=sys.stderr)
+        return 2
+
+    if args.output:
+        with open(args.output, "wb") as f:
+            f.write(blob[:hdr[3]])
+    syms = load_symbols(args.kernel) if args.kernel else []
+    report(hdr, sections, syms, sys.stdout)
+    return 0
+
+
+if __name__ == "__main__":
+    sys.exit(main())
@@ -0,0 +1,111 @@
+#!/usr/bin/env python3
+
+"""
+Generate the embedded kernel symbol table (kernel/common/ksyms.h) from
+`nm -n -S --defined-only` output of a linked kernel.
+
+The kernel is linked twice: first with an empty table (--empty), then with
+the table generated from the first image. The table lives in the .ksyms
+section at the very end of the image, so its size does not move any code;
+--verify checks that the final image really has the same text symbols.
+"""
+
+import argparse
+from pathlib import Path
+import sys
+
+KERNEL_VIRT_BASE = 0xFFFFFFFF80000000
+TEXT_TYPES = set("TtWw")
+
+
+def parse_nm(lines):
+    syms = []
+    end = 0
+    for raw in lines:
+        parts = raw.split()
+        if len(parts) == 4:
+            addr_s, size_s, kind, name = parts
+            size = int(size_s, 16)
+        elif len(parts) == 3:
+            addr_s, kind, name = parts
+            size = 0
+        else:
+            continue
+        if kind not in TEXT_TYPES:
+            continue
+        addr = int(addr_s, 16)
+        if addr < KERNEL_VIRT_BASE:
+            continue
+        # Several names on one address: keep the first, prefer real names
+        if syms and syms[-1][0] == addr:
+            if syms[-1][1].startswith(".") and not name.startswith("."):
+                syms[-1] = (addr, name)
+            end = max(end, addr + size)
+            continue
+        syms.append((addr, name))
+        end = max(end, addr + size)
+    syms.sort(key=lambda it: it[0])
+    if syms:
+        end = max(end, syms[-1][0] + 1)
+    return syms, end
+
+
+def c_escape(name):
+    return name.replace("\\", "\\\\").replace('"', '\\"')
+
+
+def render(syms, end):
+    out = []
+    out.append("/* Generated by scripts/mkksyms.py. Do not edit. */")
+    out.append("")
+    out.append('#include "ksyms.h"')
+    out.append("")
+    out.append("#define KSYMS_SECTION __attribute__((section(\".ksyms\"), used))")
+    out.append("")
+    out.append(f"const uint32_t ksyms_count KSYMS_SECTION = {len(syms)};")
+    out.append("")
+    out.append("/* Sorted by address; the extra last entry marks the end of text */")
+    out.append(f"const ksym_entry_t ksyms_table[{len(syms) + 1}] KSYMS_SECTION = {{")
+    name_off = 0
+    for addr, name in syms:
+        out.append(f"    {{ 0x{addr - KERNEL_VIRT_BASE:08x}u, {name_off}u }}, /* {name} */")
+        name_off += len(name.encode()) + 1
+    end_off = (end - KERNEL_VIRT_BASE) if syms else 0
+    out.append(f"    {{ 0x{end_off:08x}u, {name_off}u }}")
+    out.append("};")
+    out.append("")
+    out.append("const char ksyms_names[] KSYMS_SECTION =")
+    if syms:
+        for _, name in syms:
+            out.append(f'    "{c_escape(name)}\\0"')
+    out.append('    "";')
+    return "\n".join(out) + "\n"
+
+
+def main():
+    ap = argparse.ArgumentParser(description=__doc__)
+    ap.add_argument("-o", "--output", required=True, help="generated C file")
+    ap.add_argument("--empty", action="store_true", help="emit an empty table (first link pass)")
+    ap.add_argument("--verify", action="store_true",
+                    help="compare stdin symbols with an existing --output instead of writing it")
+    args = ap.parse_args()
+
+    if args.empty:
+        syms, end = [], 0
+    else:
+        syms, end = parse_nm(sys.stdin.read().splitlines())
+        for addr, _ in syms:
+            if addr - KERNEL_VIRT_BASE > 0xFFFFFFFF:
+                raise SystemExit(f"mkksyms: symbol at {addr:#x} does not fit 32-bit offset")
+
+    text = render(syms, end)
+    out = Path(args.output)
+    if args.verify:
+        if not out.exists() or out.read_text(encoding="utf-8") != text:
+            raise SystemExit(f"mkksyms: text layout moved between link passes ({out})")
+        return
+    out.write_text(text, encoding="utf-8")
+
+
+if __name__ == "__main__":
+    main()
@@ -249,6 +249,21 @@ def render_mman(vals: dict[str, int]) -> str:
         f"#define MS_ASYNC      {fmt_hex(vals['MS_ASYNC'])}",
         f"#define MS_INVALIDATE {fmt_hex(vals['MS_INVALIDATE'])}",
         "",
+        "#define MAP_FAILED ((void*)-1)",
+        "",
+        f"#define MADV_NORMAL     {vals['MADV_NORMAL']}",
+        f"#define MADV_RANDOM     {vals['MADV_RANDOM']}",
+        f"#define MADV_SEQUENTIAL {vals['MADV_SEQUENTIAL']}",
+        f"#define MADV_WILLNEED   {vals['MADV_WILLNEED']}",
+        f"#define MADV_DONTNEED   {vals['MADV_DONTNEED']}",
+        f"#define MADV_FREE       {vals['MADV_FREE']}",
+        "",
+        f"#define MINCORE_INCORE {fmt_hex(vals['MINCORE_INCORE'])}",
+        "",
+        "/* mremap() is not in FreeBSD: flags follow Linux */",
+        "#define MREMAP_MAYMOVE 0x0001",
+        "#define MREMAP_FIXED   0x0002",
+        "",
         "#endif /* _RODNIX_USERLAND_SYS_MMAN_H */",
         "",
     ]
@@ -282,6 +297,8 @@ def main() -> int:
         "PROT_NONE", "PROT_READ", "PROT_WRITE", "PROT_EXEC",
         "MAP_SHARED", "MAP_PRIVATE", "MAP_FIXED", "MAP_ANON",
         "MS_SYNC", "MS_ASYNC", "MS_INVALIDATE",
+        "MADV_NORMAL", "MADV_RANDOM", "MADV_SEQUENTIAL", "MADV_WILLNEED", "MADV_DONTNEED",
+        "MADV_FREE", "MINCORE_INCORE",
     ]
 
     errno_vals = get_vals(errno_names, upstream_errno)
@@ -0,0 +1,155 @@
+/*
+ * aslrtest.c
+ * Address-space randomization: layouts of fresh execs with ASLR forced
+ * on and off through procctl(PROC_ASLR_CTL).
+ */
+
+#include <stdint.h>
+#include <sys/mman.h>
+#include <sys/procctl.h>
+#include "unistd.h"
+
+#define FD_STDOUT 1
+#define PAGE 4096u
+#define RUNS 4
+
+typedef struct {
+    uint64_t stack;
+    uint64_t mmap;
+    uint64_t brk;
+} layout_t;
+
+static long write_str(const char* s)
+{
+    uint64_t len = 0;
+    while (s[len]) {
+        len++;
+    }
+    return write(FD_STDOUT, s, (size_t)len);
+}
+
+static int fail(const char* what)
+{
+    (void)write_str("aslrtest: FAIL ");
+    (void)write_str(what);
+    (void)write_str("\n");
+    return 1;
+}
+
+/* Probe mode: report this image's layout on stdout */
+static int probe(void)
+{
+    int local = 0;
+    layout_t l;
+    l.stack = (uint64_t)(uintptr_t)&local & ~(uint64_t)(PAGE - 1u);
+    void* p = mmap(0, PAGE, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANON, -1, 0);
+    if (p == MAP_FAILED) {
+        return 1;
+    }
+    l.mmap = (uint64_t)(uintptr_t)p;
+    l.brk = (uint64_t)(uintptr_t)sbrk(0);
+    return write(FD_STDOUT, &l, sizeof(l)) == (long)sizeof(l) ? 0 : 1;
+}
+
+/* Exec a probe child and read back its layout */
+static int sample(layout_t* out)
+{
+    int fds[2];
+    if (pipe(fds) != 0) {
+        return -1;
+    }
+    pid_t pid = fork();
+    if (pid < 0) {
+        return -1;
+    }
+    if (pid == 0) {
+        (void)close(fds[0]);
+        if (dup2(fds[1], FD_STDOUT) < 0) {
+            _exit(1);
+        }
+        char* av[3];
+        av[0] = (char*)"aslrtest";
+        av[1] = (char*)"-p";
+        av[2] = 0;
+        (void)execve("/bin/aslrtest", av, (char* const*)0);
+        _exit(1);
+    }
+    (void)close(fds[1]);
+    long n = read(fds[0], out, sizeof(*out));
+    (void)close(fds[0]);
+    int status = -1;
+    if (waitpid(pid, &status, 0) != pid || status != 0 || n != (long)sizeof(*out)) {
+        return -1;
+    }
+    return 0;
+}
+
+static int set_aslr(int mode)
+{
+    return procctl(P_PID, 0, PROC_ASLR_CTL, &mode);
+}
+
+/* Field @field differs between the first of @n samples and any other */
+static int varies(const layout_t* l, int n, int field)
+{
+    for (int i = 1; i < n; i++) {
+        const uint64_t* a = (const uint64_t*)&l[0];
+        const uint64_t* b = (const uint64_t*)&l[i];
+        if (a[field] != b[field]) {
+            return 1;
+        }
+    }
+    return 0;
+}
+
+int main(int argc, char** argv)
+{
+    if (argc >= 2 && argv[1] && argv[1][0] == '-' && argv[1][1] == 'p') {
+        return probe();
+    }
+
+    int status = 0;
+    if (procctl(P_PID, 0, PROC_ASLR_STATUS, &status) != 0) {
+        return fail("procctl(PROC_ASLR_STATUS)");
+    }
+    if (set_aslr(42) == 0 || errno != EINVAL) {
+        return fail("bad PROC_ASLR_CTL value accepted");
+    }
+
+    layout_t l[RUNS];
+    if (set_aslr(PROC_ASLR_FORCE_ENABLE) != 0) {
+        return fail("PROC_ASLR_FORCE_ENABLE");
+    }
+    for (int i = 0; i < RUNS; i++) {
+        if (sample(&l[i]) != 0) {
+            return fail("probe child");
+        }
+    }
+    if (!varies(l, RUNS, 0) || !varies(l, RUNS, 1) || !varies(l, RUNS, 2)) {
+        return fail("layout did not change with ASLR forced on");
+    }
+    (void)write_str("aslrtest: randomized ok\n");
+
+    if (set_aslr(PROC_ASLR_FORCE_DISABLE) != 0) {
+        return fail("PROC_ASLR_FORCE_DISABLE");
+    }
+    for (int i = 0; i < 2; i++) {
+        if (sample(&l[i]) != 0) {
+            return fail("probe child");
+        }
+    }
+    if (varies(l, 2, 0) || varies(l, 2, 1) || varies(l, 2, 2)) {
+        return fail("layout changed with ASLR forced off");
+    }
+    if (procctl(P_PID, 0, PROC_ASLR_STATUS, &status) != 0 ||
+        (status & ~PROC_ASLR_ACTIVE) != PROC_ASLR_FORCE_DISABLE) {
+        return fail("PROC_ASLR_STATUS after FORCE_DISABLE");
+    }
+    (void)write_str("aslrtest: fixed ok\n");
+
+    if (set_aslr(PROC_ASLR_NOFORCE) != 0) {
+        return fail("PROC_ASLR_NOFORCE");
+    }
+    (void)write_str("aslrtest: PASS\n");
+    return 0;
+}
@@ -0,0 +1,128 @@
+/*
+ * kenv.c
+ * Print kernel boot parameters (read-only view of the command line registry).
+ */
+
+#include <stdint.h>
+#include "posix_syscall.h"
+#include "bootparam.h"
+
+#define FD_STDOUT 1
+#define KENV_MAX  64
+
+static long write_buf(const char* s, uint64_t len)
+{
+    return posix_write(FD_STDOUT, s, len);
+}
+
+static long write_str(const char* s)
+{
+    uint64_t len = 0;
+    while (s[len]) {
+        len++;
+    }
+    return write_buf(s, len);
+}
+
+static int streq(const char* a, const char* b)
+{
+    uint64_t i = 0;
+    if (!a || !b) {
+        return 0;
+    }
+    while (a[i] && b[i]) {
+        if (a[i] != b[i]) {
+            return 0;
+        }
+        i++;
+    }
+    return a[i] == b[i];
+}
+
+static const char* type_name(uint32_t type)
+{
+    switch (type) {
+        case RODNIX_BOOTPARAM_BOOL:
+            return "bool";
+        case RODNIX_BOOTPARAM_INT:
+            return "int";
+        case RODNIX_BOOTPARAM_STRING:
+            return "string";
+        default:
+            return "?";
+    }
+}
+
+static void usage(void)
+{
+    (void)write_str("usage:\n");
+    (void)write_str("  kenv          list name=value\n");
+    (void)write_str("  kenv -v       also type, default and description\n");
+    (void)write_str("  kenv <name>   print one value\n");
+}
+
+int main(int argc, char** argv)
+{
+    static rodnix_bootparam_info_t params[KENV_MAX];
+    uint32_t total = 0;
+    int verbose = 0;
+    const char* want = 0;
+
+    if (argc > 2) {
+        usage();
+        return 1;
+    }
+    if (argc == 2 && argv[1]) {
+        if (streq(argv[1], "-h")) {
+            usage();
+            return 0;
+        }
+        if (streq(argv[1], "-v")) {
+            verbose = 1;
+        } else {
+            want = argv[1];
+        }
+    }
+
+    long n = posix_bootparams(params, KENV_MAX, &total);
+    if (n < 0) {
+        (void)write_str("kenv: bootparams failed\n");
+        return 1;
+    }
+
+    for (uint32_t i = 0; i < (uint32_t)n; i++) {
+        if (want) {
+            if (streq(params[i].name, want)) {
+                (void)write_str(params[i].value);
+                (void)write_str("\n");
+                return 0;
+            }
+            continue;
+        }
+        (void)write_str(params[i].name);
+        (void)write_str("=");
+        (void)write_str(params[i].value);
+        if (verbose) {
+            (void)write_str("  [");
+            (void)write_str(type_name(params[i].type));
+            (void)write_str(", default ");
+            (void)write_str(params[i].defval[0] ? params[i].defval : "\"\"");
+            if (params[i].flags & RODNIX_BOOTPARAM_SET) {
+                (void)write_str(", set");
+            }
+            (void)write_str("] ");
+            (void)write_str(params[i].desc);
+        }
+        (void)write_str("\n");
+    }
+    if (want) {
+        (void)write_str("kenv: ");
+        (void)write_str(want);
+        (void)write_str(": no such parameter\n");
+        return 1;
+    }
+    if (total > (uint32_t)n) {
+        (void)write_str("kenv: list truncated\n");
+    }
+    return 0;
+}
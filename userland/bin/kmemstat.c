@@ -0,0 +1,145 @@
+/*
+ * kmemstat.c
+ * Kernel object cache (slab allocator) statistics.
+ */
+
+#include <stdint.h>
+#include "posix_syscall.h"
+#include "kmeminfo.h"
+
+#define FD_STDOUT     1
+#define KMEMSTAT_MAX  96
+
+static long write_buf(const char* s, uint64_t len)
+{
+    return posix_write(FD_STDOUT, s, len);
+}
+
+static long write_str(const char* s)
+{
+    uint64_t len = 0;
+    while (s[len]) {
+        len++;
+    }
+    return write_buf(s, len);
+}
+
+static uint64_t u64_to_dec(uint64_t v, char* out)
+{
+    char tmp[24];
+    uint64_t n = 0;
+    uint64_t i = 0;
+    do {
+        tmp[n++] = (char)('0' + (v % 10u));
+        v /= 10u;
+    } while (v > 0 && n < sizeof(tmp));
+    while (n > 0) {
+        out[i++] = tmp[--n];
+    }
+    out[i] = '\0';
+    return i;
+}
+
+/* Right-aligned number in a column of @width */
+static void write_col_u64(uint64_t v, uint64_t width)
+{
+    char buf[24];
+    uint64_t len = u64_to_dec(v, buf);
+    while (len < width) {
+        (void)write_buf(" ", 1);
+        width--;
+    }
+    (void)write_buf(buf, len);
+}
+
+/* Left-aligned string in a column of @width */
+static void write_col_str(const char* s, uint64_t width)
+{
+    uint64_t len = 0;
+    while (s[len]) {
+        len++;
+    }
+    (void)write_buf(s, len);
+    while (len < width) {
+        (void)write_buf(" ", 1);
+        len++;
+    }
+}
+
+static int streq(const char* a, const char* b)
+{
+    uint64_t i = 0;
+    if (!a || !b) {
+        return 0;
+    }
+    while (a[i] && b[i]) {
+        if (a[i] != b[i]) {
+            return 0;
+        }
+        i++;
+    }
+    return a[i] == b[i];
+}
+
+static void usage(void)
+{
+    (void)write_str("usage:\n");
+    (void)write_str("  kmemstat       list all kernel object caches\n");
+    (void)write_str("  kmemstat -a    only caches with objects in use\n");
+}
+
+int main(int argc, char** argv)
+{
+    static rodnix_kmem_cache_info_t caches[KMEMSTAT_MAX];
+    uint32_t total = 0;
+    int active_only = 0;
+    uint64_t slab_pages = 0;
+
+    if (argc > 2) {
+        usage();
+        return 1;
+    }
+    if (argc == 2 && argv[1]) {
+        if (streq(argv[1], "-a")) {
+            active_only = 1;
+        } else {
+            usage();
+            return streq(argv[1], "-h") ? 0 : 1;
+        }
+    }
+
+    long n = posix_kmemstat(caches, KMEMSTAT_MAX, &total);
+    if (n < 0) {
+        (void)write_str("kmemstat: kmemstat failed\n");
+        return 1;
+    }
+
+    (void)write_str("NAME                      SIZE    INUSE   CACHED    TOTAL  SLABS PG       ALLOCS        FREES  FAIL\n");
+    for (uint32_t i = 0; i < (uint32_t)n; i++) {
+        const rodnix_kmem_cache_info_t* c = &caches[i];
+        slab_pages += (uint64_t)c->slabs * c->slab_pages;
+        if (active_only && c->objs_inuse == 0) {
+            continue;
+        }
+        write_col_str(c->name, 24);
+        write_col_u64(c->obj_size, 6);
+        write_col_u64(c->objs_inuse, 9);
+        write_col_u64(c->objs_cached, 9);
+        write_col_u64(c->objs_total, 9);
+        write_col_u64(c->slabs, 7);
+        write_col_u64(c->slab_pages, 3);
+        write_col_u64(c->allocs, 13);
+        write_col_u64(c->frees, 13);
+        write_col_u64(c->fails, 6);
+        (void)write_str("\n");
+    }
+    (void)write_str("slab pages: ");
+    write_col_u64(slab_pages, 0);
+    (void)write_str(" (");
+    write_col_u64(slab_pages * 4u, 0);
+    (void)write_str(" KiB)\n");
+    if (total > (uint32_t)n) {
+        (void)write_str("kmemstat: list truncated\n");
+    }
+    return 0;
+}
@@ -0,0 +1,83 @@
+/*
+ * oomtest.c
+ * A runaway child eats memory until the OOM killer takes it; the parent
+ * (and the rest of the system) must live on.
+ */
+
+#include <stdint.h>
+#include <signal.h>
+#include "unistd.h"
+
+#define FD_STDOUT 1
+#define PAGE 4096u
+#define CHUNK (1024u * 1024u)
+
+static long write_str(const char* s)
+{
+    uint64_t len = 0;
+    while (s[len]) {
+        len++;
+    }
+    return write(FD_STDOUT, s, (size_t)len);
+}
+
+static int fail(const char* what)
+{
+    (void)write_str("oomtest: FAIL ");
+    (void)write_str(what);
+    (void)write_str("\n");
+    return 1;
+}
+
+static void touch(volatile uint8_t* p, uint32_t len)
+{
+    for (uint32_t off = 0; off < len; off += PAGE) {
+        p[off] = 1;
+    }
+}
+
+static void runaway(void)
+{
+    for (;;) {
+        void* p = sbrk(CHUNK);
+        if (p == (void*)-1) {
+            p = mmap(0, CHUNK, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANON, -1, 0);
+            if (p == MAP_FAILED) {
+                _exit(2); /* Out of address space before memory ran out */
+            }
+        }
+        touch((volatile uint8_t*)p, CHUNK);
+    }
+}
+
+int main(void)
+{
+    pid_t pid = fork();
+    if (pid < 0) {
+        return fail("fork");
+    }
+    if (pid == 0) {
+        runaway();
+    }
+    int status = -1;
+    if (waitpid(pid, &status, 0) != pid) {
+        return fail("waitpid");
+    }
+    if (status == 2) {
+        return fail("child ran out of address space first (too much RAM/swap)");
+    }
+    if (status != 128 + SIGKILL) {
+        return fail("child did not die by the OOM killer");
+    }
+    (void)write_str("oomtest: runaway child killed\n");
+
+    /* The memory came back: the survivor can allocate again */
+    volatile uint8_t* p = (volatile uint8_t*)mmap(0, CHUNK, PROT_READ | PROT_WRITE,
+                                                  MAP_PRIVATE | MAP_ANON, -1, 0);
+    if ((void*)p == MAP_FAILED) {
+        return fail("mmap after OOM");
+    }
+    touch(p, CHUNK);
+    (void)write_str("oomtest: PASS\n");
+    return 0;
+}
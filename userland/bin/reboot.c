@@ -0,0 +1,27 @@
+/*
+ * reboot.c
+ * Reboot the machine via reboot(2).
+ * Mounted filesystems are synced first unless -n is given.
+ */
+
+#include <errno.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/reboot.h>
+
+int main(int argc, char** argv)
+{
+    int howto = RB_AUTOBOOT;
+    for (int i = 1; i < argc; i++) {
+        if (argv[i] && strcmp(argv[i], "-n") == 0) {
+            howto |= RB_NOSYNC;
+        } else {
+            fprintf(stderr, "usage: reboot [-n]\n");
+            return 1;
+        }
+    }
+
+    (void)reboot(howto);
+    fprintf(stderr, "reboot: %s\n", strerror(errno));
+    return 1;
+}
@@ -0,0 +1,152 @@
+/*
+ * rlimittest.c
+ * getrlimit/setrlimit: RLIMIT_NOFILE, RLIMIT_AS, RLIMIT_DATA and RLIMIT_CPU.
+ */
+
+#include <stdint.h>
+#include <signal.h>
+#include <sys/resource.h>
+#include "unistd.h"
+
+#define FD_STDOUT 1
+#define MIB (1024u * 1024u)
+
+static long write_str(const char* s)
+{
+    uint64_t len = 0;
+    while (s[len]) {
+        len++;
+    }
+    return write(FD_STDOUT, s, (size_t)len);
+}
+
+static int fail(const char* what)
+{
+    (void)write_str("rlimittest: FAIL ");
+    (void)write_str(what);
+    (void)write_str("\n");
+    return 1;
+}
+
+static int set_limit(int resource, rlim_t cur, rlim_t max)
+{
+    struct rlimit rl;
+    rl.rlim_cur = cur;
+    rl.rlim_max = max;
+    return setrlimit(resource, &rl);
+}
+
+/* Run @fn in a child; @return its wait status */
+static int run_child(int (*fn)(void))
+{
+    pid_t pid = fork();
+    if (pid < 0) {
+        return -1;
+    }
+    if (pid == 0) {
+        _exit(fn());
+    }
+    int status = -1;
+    if (waitpid(pid, &status, 0) != pid) {
+        return -1;
+    }
+    return status;
+}
+
+static int test_nofile(void)
+{
+    struct rlimit rl;
+    if (getrlimit(RLIMIT_NOFILE, &rl) != 0 || rl.rlim_cur == 0 || rl.rlim_cur > rl.rlim_max) {
+        return fail("getrlimit(RLIMIT_NOFILE)");
+    }
+    if (set_limit(RLIMIT_NOFILE, rl.rlim_max + 1, rl.rlim_max) == 0 || errno != EINVAL) {
+        return fail("rlim_cur above rlim_max accepted");
+    }
+    if (set_limit(RLIMIT_NOFILE, 4, rl.rlim_max) != 0) {
+        return fail("setrlimit(RLIMIT_NOFILE)");
+    }
+    int fd = dup(0); /* fds 0-2 are taken: this one is 3 */
+    if (fd != 3) {
+        return fail("dup below RLIMIT_NOFILE");
+    }
+    if (dup(0) >= 0 || dup2(0, 5) >= 0) {
+        return fail("fd allocated past RLIMIT_NOFILE");
+    }
+    (void)close(fd);
+    if (set_limit(RLIMIT_NOFILE, rl.rlim_cur, rl.rlim_max) != 0) {
+        return fail("restore RLIMIT_NOFILE");
+    }
+    (void)write_str("rlimittest: nofile ok\n");
+    return 0;
+}
+
+static int child_as(void)
+{
+    if (set_limit(RLIMIT_AS, 64u * MIB, RLIM_INFINITY) != 0) {
+        return 2;
+    }
+    void* big = mmap(0, 128u * MIB, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANON, -1, 0);
+    if (big != MAP_FAILED) {
+        return 3;
+    }
+    void* small = mmap(0, MIB, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANON, -1, 0);
+    return small == MAP_FAILED ? 4 : 0;
+}
+
+static int child_data(void)
+{
+    if (set_limit(RLIMIT_DATA, 64u * 1024u, RLIM_INFINITY) != 0) {
+        return 2;
+    }
+    if (sbrk(128 * 1024) != (void*)-1) {
+        return 3;
+    }
+    return sbrk(16 * 1024) == (void*)-1 ? 4 : 0;
+}
+
+static int child_cpu(void)
+{
+    if (set_limit(RLIMIT_CPU, 1, 2) != 0) {
+        return 2;
+    }
+    for (;;) {
+        (void)getpid(); /* Limits are checked on the way out of a system call */
+    }
+}
+
+static int child_cpu_hard(void)
+{
+    struct sigaction sa;
+    sa.sa_handler = SIG_IGN;
+    sa.sa_flags = 0;
+    sa.sa_restorer = 0;
+    sa.sa_mask = 0;
+    if (sigaction(SIGXCPU, &sa, 0) != 0) {
+        return 2;
+    }
+    return child_cpu();
+}
+
+int main(void)
+{
+    if (test_nofile() != 0) {
+        return 1;
+    }
+    if (run_child(child_as) != 0) {
+        return fail("RLIMIT_AS");
+    }
+    (void)write_str("rlimittest: as ok\n");
+    if (run_child(child_data) != 0) {
+        return fail("RLIMIT_DATA");
+    }
+    (void)write_str("rlimittest: data ok\n");
+    if (run_child(child_cpu) != 128 + SIGXCPU) {
+        return fail("RLIMIT_CPU soft limit did not raise SIGXCPU");
+    }
+    if (run_child(child_cpu_hard) != 128 + SIGKILL) {
+        return fail("RLIMIT_CPU hard limit did not kill");
+    }
+    (void)write_str("rlimittest: cpu ok\n");
+    (void)write_str("rlimittest: PASS\n");
+    return 0;
+}
@@ -0,0 +1,140 @@
+/*
+ * shmtest.c
+ * MAP_SHARED anonymous memory across fork and POSIX shm_open objects.
+ */
+
+#include <stdint.h>
+#include "unistd.h"
+
+#define FD_STDOUT 1
+#define PAGE 4096u
+#define SHM_NAME "/shmtest"
+
+static long write_str(const char* s)
+{
+    uint64_t len = 0;
+    while (s[len]) {
+        len++;
+    }
+    return write(FD_STDOUT, s, (size_t)len);
+}
+
+static int fail(const char* what)
+{
+    (void)write_str("shmtest: FAIL ");
+    (void)write_str(what);
+    (void)write_str("\n");
+    return 1;
+}
+
+static int wait_child(pid_t pid)
+{
+    int status = -1;
+    return waitpid(pid, &status, 0) == pid && status == 0;
+}
+
+static int test_anon(void)
+{
+    volatile uint32_t* shared = (volatile uint32_t*)mmap(0, 2u * PAGE, PROT_READ | PROT_WRITE,
+                                                         MAP_SHARED | MAP_ANON, -1, 0);
+    volatile uint32_t* priv = (volatile uint32_t*)mmap(0, PAGE, PROT_READ | PROT_WRITE,
+                                                       MAP_PRIVATE | MAP_ANON, -1, 0);
+    if ((void*)shared == MAP_FAILED || (void*)priv == MAP_FAILED) {
+        return fail("anon mmap");
+    }
+    shared[0] = 1;
+    priv[0] = 1;
+
+    pid_t pid = fork();
+    if (pid < 0) {
+        return fail("fork");
+    }
+    if (pid == 0) {
+        shared[0] = 42;
+        shared[PAGE / sizeof(uint32_t)] = 43; /* First touched after fork */
+        priv[0] = 44;
+        _exit(0);
+    }
+    if (!wait_child(pid)) {
+        return fail("anon child");
+    }
+    if (shared[0] != 42 || shared[PAGE / sizeof(uint32_t)] != 43) {
+        return fail("MAP_SHARED|MAP_ANON not shared with the child");
+    }
+    if (priv[0] != 1) {
+        return fail("MAP_PRIVATE|MAP_ANON leaked the child's write");
+    }
+    (void)munmap((void*)shared, 2u * PAGE);
+    (void)munmap((void*)priv, PAGE);
+    (void)write_str("shmtest: anon shared across fork ok\n");
+    return 0;
+}
+
+static int test_shm_open(void)
+{
+    (void)shm_unlink(SHM_NAME);
+    int fd = shm_open(SHM_NAME, O_RDWR | O_CREAT | O_EXCL, 0600);
+    if (fd < 0) {
+        return fail("shm_open create");
+    }
+    if (shm_open(SHM_NAME, O_RDWR | O_CREAT | O_EXCL, 0600) >= 0 || errno != EEXIST) {
+        return fail("shm_open O_EXCL on an existing object");
+    }
+    if (ftruncate(fd, 2 * PAGE) != 0) {
+        return fail("ftruncate");
+    }
+    volatile uint32_t* p = (volatile uint32_t*)mmap(0, 2u * PAGE, PROT_READ | PROT_WRITE,
+                                                    MAP_SHARED, fd, 0);
+    if ((void*)p == MAP_FAILED) {
+        return fail("mmap shm");
+    }
+    (void)close(fd);
+
+    pid_t pid = fork();
+    if (pid < 0) {
+        return fail("fork");
+    }
+    if (pid == 0) {
+        /* Open by name and map it again, as an unrelated process would */
+        int cfd = shm_open(SHM_NAME, O_RDWR, 0);
+        if (cfd < 0) {
+            _exit(1);
+        }
+        volatile uint32_t* c = (volatile uint32_t*)mmap(0, 2u * PAGE, PROT_READ | PROT_WRITE,
+                                                        MAP_SHARED, cfd, 0);
+        if ((void*)c == MAP_FAILED) {
+            _exit(1);
+        }
+        c[PAGE / sizeof(uint32_t)] = 0x5a5a;
+        _exit(0);
+    }
+    if (!wait_child(pid)) {
+        return fail("shm child");
+    }
+    if (p[PAGE / sizeof(uint32_t)] != 0x5a5a) {
+        return fail("shm object not shared");
+    }
+
+    if (shm_unlink(SHM_NAME) != 0) {
+        return fail("shm_unlink");
+    }
+    if (shm_open(SHM_NAME, O_RDWR, 0) >= 0 || errno != ENOENT) {
+        return fail("shm_open after shm_unlink");
+    }
+    p[0] = 7; /* The mapping outlives the name */
+    if (p[0] != 7 || p[PAGE / sizeof(uint32_t)] != 0x5a5a) {
+        return fail("mapping after shm_unlink");
+    }
+    (void)munmap((void*)p, 2u * PAGE);
+    (void)write_str("shmtest: shm_open ok\n");
+    return 0;
+}
+
+int main(void)
+{
+    if (test_anon() != 0 || test_shm_open() != 0) {
+        return 1;
+    }
+    (void)write_str("shmtest: PASS\n");
+    return 0;
+}
@@ -0,0 +1,46 @@
+/*
+ * swapoff.c
+ * Read a swap device back into memory and stop using it.
+ */
+
+#include <stdint.h>
+#include "posix_syscall.h"
+
+#define FD_STDOUT 1
+
+static long write_str(const char* s)
+{
+    uint64_t len = 0;
+    while (s[len]) {
+        len++;
+    }
+    return posix_write(FD_STDOUT, s, len);
+}
+
+static const char* swap_error(long rc)
+{
+    switch (rc) {
+    case -3: return "not enough memory to hold its pages";
+    case -4: return "not a swap device";
+    case -5: return "already being turned off";
+    default: return "failed";
+    }
+}
+
+int main(int argc, char** argv)
+{
+    if (argc != 2 || !argv[1] || argv[1][0] == '-') {
+        (void)write_str("usage: swapoff PATH\n");
+        return 1;
+    }
+    long rc = posix_swapoff(argv[1]);
+    if (rc < 0) {
+        (void)write_str("swapoff: ");
+        (void)write_str(argv[1]);
+        (void)write_str(": ");
+        (void)write_str(swap_error(rc));
+        (void)write_str("\n");
+        return 1;
+    }
+    return 0;
+}
@@ -0,0 +1,148 @@
+/*
+ * swapon.c
+ * Start swapping to a block device or file; without arguments list swap.
+ */
+
+#include <stdint.h>
+#include "posix_syscall.h"
+#include "swapinfo.h"
+
+#define FD_STDOUT   1
+#define SWAPON_MAX  8
+
+static long write_buf(const char* s, uint64_t len)
+{
+    return posix_write(FD_STDOUT, s, len);
+}
+
+static long write_str(const char* s)
+{
+    uint64_t len = 0;
+    while (s[len]) {
+        len++;
+    }
+    return write_buf(s, len);
+}
+
+static uint64_t u64_to_dec(uint64_t v, char* out)
+{
+    char tmp[24];
+    uint64_t n = 0;
+    uint64_t i = 0;
+    do {
+        tmp[n++] = (char)('0' + (v % 10u));
+        v /= 10u;
+    } while (v > 0 && n < sizeof(tmp));
+    while (n > 0) {
+        out[i++] = tmp[--n];
+    }
+    out[i] = '\0';
+    return i;
+}
+
+/* Right-aligned number in a column of @width */
+static void write_col_u64(uint64_t v, uint64_t width)
+{
+    char buf[24];
+    uint64_t len = u64_to_dec(v, buf);
+    while (len < width) {
+        (void)write_buf(" ", 1);
+        width--;
+    }
+    (void)write_buf(buf, len);
+}
+
+/* Left-aligned string in a column of @width */
+static void write_col_str(const char* s, uint64_t width)
+{
+    uint64_t len = 0;
+    while (s[len]) {
+        len++;
+    }
+    (void)write_buf(s, len);
+    while (len < width) {
+        (void)write_buf(" ", 1);
+        len++;
+    }
+}
+
+static int streq(const char* a, const char* b)
+{
+    uint64_t i = 0;
+    if (!a || !b) {
+        return 0;
+    }
+    while (a[i] && b[i]) {
+        if (a[i] != b[i]) {
+            return 0;
+        }
+        i++;
+    }
+    return a[i] == b[i];
+}
+
+static const char* swap_error(long rc)
+{
+    switch (rc) {
+    case -3: return "out of memory";
+    case -4: return "no such file or device";
+    case -5: return "busy (already swapping there, or holds a mounted filesystem)";
+    case -7: return "not a disk-backed file or writable block device";
+    default: return "failed";
+    }
+}
+
+static void usage(void)
+{
+    (void)write_str("usage:\n");
+    (void)write_str("  swapon [-s]   list swap devices\n");
+    (void)write_str("  swapon PATH   swap to a block device (/dev/diskN) or a pre-written file\n");
+}
+
+static int list_swap(void)
+{
+    static rodnix_swap_info_t devs[SWAPON_MAX];
+    uint32_t total = 0;
+    long n = posix_swapinfo(devs, SWAPON_MAX, &total);
+    if (n < 0) {
+        (void)write_str("swapon: swapinfo failed\n");
+        return 1;
+    }
+    (void)write_str("PATH                             TYPE    SIZE(KiB)    USED(KiB)\n");
+    for (uint32_t i = 0; i < (uint32_t)n; i++) {
+        const rodnix_swap_info_t* d = &devs[i];
+        write_col_str(d->path, 32);
+        (void)write_str((d->flags & RODNIX_SWAP_FILE) ? " file  " : " device");
+        write_col_u64(d->pages * 4u, 13);
+        write_col_u64(d->used * 4u, 13);
+        if (d->flags & RODNIX_SWAP_DRAINING) {
+            (void)write_str("  draining");
+        }
+        (void)write_str("\n");
+    }
+    if (total > (uint32_t)n) {
+        (void)write_str("swapon: list truncated\n");
+    }
+    return 0;
+}
+
+int main(int argc, char** argv)
+{
+    if (argc == 1 || (argc == 2 && argv[1] && streq(argv[1], "-s"))) {
+        return list_swap();
+    }
+    if (argc != 2 || !argv[1] || argv[1][0] == '-') {
+        usage();
+        return (argc == 2 && argv[1] && streq(argv[1], "-h")) ? 0 : 1;
+    }
+    long rc = posix_swapon(argv[1]);
+    if (rc < 0) {
+        (void)write_str("swapon: ");
+        (void)write_str(argv[1]);
+        (void)write_str(": ");
+        (void)write_str(swap_error(rc));
+        (void)write_str("\n");
+        return 1;
+    }
+    return 0;
+}
@@ -131,6 +131,12 @@ int main(void)
     (void)write_str(s.uptime_source);
     (void)write_str("\nUptime (us): ");
     write_u64(s.uptime_us);
+    (void)write_str("\nClocksource: ");
+    (void)write_str(s.clocksource);
+    (void)write_str(" (");
+    write_u64(s.clocksource_hz);
+    (void)write_str(" Hz)\nTimer: ");
+    (void)write_str(s.timer_mode);
     (void)write_str("\n\nCPU:\n  vendor: ");
     (void)write_str(s.cpu_vendor);
     (void)write_str("\n  model: ");
@@ -182,6 +188,22 @@ int main(void)
     write_u64(s.oom_vmm);
     (void)write_str("/");
     write_u64(s.oom_heap);
+    for (uint32_t z = 0; z < s.pmm_zone_count && z < RODNIX_PMM_ZONE_MAX; z++) {
+        const rodnix_pmm_zone_info_t* zi = &s.pmm_zones[z];
+        (void)write_str("\n  zone ");
+        (void)write_str(zi->name);
+        (void)write_str(" total/free/used: ");
+        write_u64(zi->total_pages);
+        (void)write_str("/");
+        write_u64(zi->free_pages);
+        (void)write_str("/");
+        write_u64(zi->used_pages);
+        (void)write_str("\n    free blocks by order:");
+        for (uint32_t o = 0; o < s.pmm_order_count && o < RODNIX_PMM_ORDER_MAX; o++) {
+            (void)write_str(" ");
+            write_u64(zi->free_blocks[o]);
+        }
+    }
 
     (void)write_str("\n\nInterrupts:\n  apic: ");
     write_u64((uint64_t)s.apic_available);
@@ -0,0 +1,236 @@
+/*
+ * vmadvtest.c
+ * madvise, mlock/munlock, mincore and mremap on anonymous mappings.
+ */
+
+#include <stdint.h>
+#include <sys/mman.h>
+#include "unistd.h"
+
+#define FD_STDOUT 1
+#define PAGE 4096u
+#define NPAGES 4u
+
+static long write_str(const char* s)
+{
+    uint64_t len = 0;
+    while (s[len]) {
+        len++;
+    }
+    return write(FD_STDOUT, s, (size_t)len);
+}
+
+static int fail(const char* what)
+{
+    (void)write_str("vmadvtest: FAIL ");
+    (void)write_str(what);
+    (void)write_str("\n");
+    return 1;
+}
+
+static unsigned char* map_anon(size_t len)
+{
+    void* p = mmap(0, len, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANON, -1, 0);
+    return (p == MAP_FAILED) ? 0 : (unsigned char*)p;
+}
+
+static void fill(unsigned char* p, uint32_t pages, unsigned char seed)
+{
+    for (uint32_t i = 0; i < pages; i++) {
+        p[i * PAGE] = (unsigned char)(seed + i);
+        p[i * PAGE + PAGE - 1u] = (unsigned char)(seed + i);
+    }
+}
+
+static int check(const unsigned char* p, uint32_t pages, unsigned char seed)
+{
+    for (uint32_t i = 0; i < pages; i++) {
+        if (p[i * PAGE] != (unsigned char)(seed + i) ||
+            p[i * PAGE + PAGE - 1u] != (unsigned char)(seed + i)) {
+            return 0;
+        }
+    }
+    return 1;
+}
+
+static int zeroed(const unsigned char* p, uint32_t pages)
+{
+    for (uint32_t i = 0; i < pages; i++) {
+        if (p[i * PAGE] != 0 || p[i * PAGE + PAGE - 1u] != 0) {
+            return 0;
+        }
+    }
+    return 1;
+}
+
+/* Pages of [p, p + pages * PAGE) that mincore reports in memory, -1 on error */
+static int incore(unsigned char* p, uint32_t pages)
+{
+    unsigned char vec[NPAGES * 2u];
+    if (pages > sizeof(vec) || mincore(p, (size_t)pages * PAGE, vec) != 0) {
+        return -1;
+    }
+    int n = 0;
+    for (uint32_t i = 0; i < pages; i++) {
+        if (vec[i] & MINCORE_INCORE) {
+            n++;
+        }
+    }
+    return n;
+}
+
+static int test_dontneed(void)
+{
+    unsigned char* p = map_anon(NPAGES * PAGE);
+    if (!p) {
+        return fail("mmap");
+    }
+    if (incore(p, NPAGES) != 0) {
+        return fail("untouched pages reported resident");
+    }
+    fill(p, NPAGES, 0x10);
+    if (incore(p, NPAGES) != (int)NPAGES) {
+        return fail("touched pages not resident");
+    }
+    if (madvise(p, NPAGES * PAGE, MADV_WILLNEED) != 0 ||
+        madvise(p, NPAGES * PAGE, MADV_SEQUENTIAL) != 0) {
+        return fail("madvise(WILLNEED/SEQUENTIAL)");
+    }
+    if (madvise(p + PAGE, PAGE, MADV_DONTNEED) != 0) {
+        return fail("madvise(DONTNEED)");
+    }
+    if (incore(p, NPAGES) != (int)NPAGES - 1) {
+        return fail("DONTNEED page still resident");
+    }
+    if (!zeroed(p + PAGE, 1) || !check(p, 1, 0x10) || !check(p + 2u * PAGE, 2, 0x12)) {
+        return fail("DONTNEED contents");
+    }
+    if (madvise(p, NPAGES * PAGE, MADV_FREE) != 0 || !zeroed(p, NPAGES)) {
+        return fail("madvise(FREE)");
+    }
+    if (madvise(p + 1, PAGE, MADV_DONTNEED) == 0 || errno != EINVAL) {
+        return fail("unaligned madvise accepted");
+    }
+    if (madvise(p, PAGE, 1000) == 0 || errno != EINVAL) {
+        return fail("unknown advice accepted");
+    }
+    (void)munmap(p, NPAGES * PAGE);
+    if (madvise(p, PAGE, MADV_DONTNEED) == 0 || errno != ENOMEM) {
+        return fail("madvise on unmapped range");
+    }
+    (void)write_str("vmadvtest: madvise ok\n");
+    return 0;
+}
+
+/* DONTNEED in a fork child must not reach the parent's copy-on-write pages */
+static int test_dontneed_fork(void)
+{
+    unsigned char* p = map_anon(NPAGES * PAGE);
+    if (!p) {
+        return fail("mmap");
+    }
+    fill(p, NPAGES, 0x30);
+    pid_t pid = fork();
+    if (pid < 0) {
+        return fail("fork");
+    }
+    if (pid == 0) {
+        if (madvise(p, NPAGES * PAGE, MADV_DONTNEED) != 0 || !zeroed(p, NPAGES)) {
+            _exit(1);
+        }
+        fill(p, NPAGES, 0x50);
+        _exit(check(p, NPAGES, 0x50) ? 0 : 2);
+    }
+    int status = -1;
+    if (waitpid(pid, &status, 0) != pid || status != 0) {
+        return fail("DONTNEED in a fork child");
+    }
+    if (!check(p, NPAGES, 0x30)) {
+        return fail("parent pages changed by the child");
+    }
+    (void)munmap(p, NPAGES * PAGE);
+    (void)write_str("vmadvtest: fork ok\n");
+    return 0;
+}
+
+static int test_mlock(void)
+{
+    unsigned char* p = map_anon(NPAGES * PAGE);
+    if (!p) {
+        return fail("mmap");
+    }
+    if (mlock(p + PAGE, 2u * PAGE) != 0) {
+        return fail("mlock");
+    }
+    if (incore(p, NPAGES) != 2) {
+        return fail("mlock did not fault the range in");
+    }
+    if (madvise(p, NPAGES * PAGE, MADV_DONTNEED) == 0 || errno != EINVAL) {
+        return fail("DONTNEED on a locked range");
+    }
+    if (munlock(p, NPAGES * PAGE) != 0) {
+        return fail("munlock");
+    }
+    if (madvise(p, NPAGES * PAGE, MADV_DONTNEED) != 0 || incore(p, NPAGES) != 0) {
+        return fail("DONTNEED after munlock");
+    }
+    (void)munmap(p, NPAGES * PAGE);
+    if (mlock(p, PAGE) == 0 || errno != ENOMEM) {
+        return fail("mlock on unmapped range");
+    }
+    (void)write_str("vmadvtest: mlock ok\n");
+    return 0;
+}
+
+static int test_mremap(void)
+{
+    unsigned char* p = map_anon(2u * PAGE);
+    if (!p) {
+        return fail("mmap");
+    }
+    fill(p, 2, 0x70);
+
+    /* The second page of the mapping blocks growth of the first in place */
+    if (mremap(p, PAGE, 2u * PAGE, 0, 0) != MAP_FAILED || errno != ENOMEM) {
+        return fail("blocked in-place growth succeeded");
+    }
+    unsigned char* q = (unsigned char*)mremap(p, PAGE, 3u * PAGE, MREMAP_MAYMOVE, 0);
+    if (q == MAP_FAILED || q == p) {
+        return fail("mremap(MAYMOVE)");
+    }
+    if (!check(q, 1, 0x70) || !zeroed(q + PAGE, 2) || !check(p + PAGE, 1, 0x71)) {
+        return fail("moved contents");
+    }
+    if (incore(p, 1) != -1 || errno != ENOMEM) {
+        return fail("old range still mapped after move");
+    }
+
+    /* Shrink, then grow back in place: the dropped tail must read as zeroes */
+    fill(q, 3, 0x90);
+    if (mremap(q, 3u * PAGE, PAGE, 0, 0) != (void*)q) {
+        return fail("mremap shrink");
+    }
+    if (mremap(q, PAGE, 3u * PAGE, 0, 0) != (void*)q) {
+        return fail("mremap in-place growth");
+    }
+    if (!check(q, 1, 0x90) || !zeroed(q + PAGE, 2)) {
+        return fail("in-place growth contents");
+    }
+    if (mremap(p, 2u * PAGE, 4u * PAGE, MREMAP_MAYMOVE, 0) != MAP_FAILED || errno != EFAULT) {
+        return fail("mremap across a hole");
+    }
+    (void)munmap(q, 3u * PAGE);
+    (void)munmap(p + PAGE, PAGE);
+    (void)write_str("vmadvtest: mremap ok\n");
+    return 0;
+}
+
+int main(void)
+{
+    if (test_dontneed() != 0 || test_dontneed_fork() != 0 || test_mlock() != 0 ||
+        test_mremap() != 0) {
+        return 1;
+    }
+    (void)write_str("vmadvtest: PASS\n");
+    return 0;
+}
@@ -0,0 +1,23 @@
+#ifndef _RODNIX_USERLAND_BOOTPARAM_H
+#define _RODNIX_USERLAND_BOOTPARAM_H
+
+#include <stdint.h>
+
+/* rodnix_bootparam_info.type */
+#define RODNIX_BOOTPARAM_BOOL   1
+#define RODNIX_BOOTPARAM_INT    2
+#define RODNIX_BOOTPARAM_STRING 3
+
+/* rodnix_bootparam_info.flags */
+#define RODNIX_BOOTPARAM_SET    0x1u
+
+typedef struct rodnix_bootparam_info {
+    char name[32];
+    char value[64];
+    char defval[64];
+    char desc[80];
+    uint32_t type;
+    uint32_t flags;
+} rodnix_bootparam_info_t;
+
+#endif /* _RODNIX_USERLAND_BOOTPARAM_H */
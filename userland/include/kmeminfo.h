@@ -0,0 +1,20 @@
+#ifndef _RODNIX_USERLAND_KMEMINFO_H
+#define _RODNIX_USERLAND_KMEMINFO_H
+
+#include <stdint.h>
+
+typedef struct rodnix_kmem_cache_info {
+    char name[24];
+    uint32_t obj_size;
+    uint32_t slab_pages;
+    uint32_t objs_per_slab;
+    uint32_t slabs;
+    uint64_t objs_total;
+    uint64_t objs_inuse;
+    uint64_t objs_cached;
+    uint64_t allocs;
+    uint64_t frees;
+    uint64_t fails;
+} rodnix_kmem_cache_info_t;
+
+#endif /* _RODNIX_USERLAND_KMEMINFO_H */
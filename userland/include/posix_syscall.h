@@ -12,6 +12,9 @@
 #include "scstat.h"
 #include "diskinfo.h"
 #include "kmodinfo.h"
+#include "bootparam.h"
+#include "kmeminfo.h"
+#include "swapinfo.h"
 
 #ifndef RDNX_STDIN_INT80_READ_WORKAROUND
 #define RDNX_STDIN_INT80_READ_WORKAROUND 1
@@ -330,6 +333,36 @@ static inline long posix_brk(void* new_break)
     return rdnx_syscall1(POSIX_SYS_BRK, (long)(uintptr_t)new_break);
 }
 
+static inline long posix_madvise(void* addr, uint64_t len, int advice)
+{
+    return rdnx_syscall3(POSIX_SYS_MADVISE, (long)(uintptr_t)addr, (long)len, advice);
+}
+
+static inline long posix_mlock(const void* addr, uint64_t len)
+{
+    return rdnx_syscall2(POSIX_SYS_MLOCK, (long)(uintptr_t)addr, (long)len);
+}
+
+static inline long posix_munlock(const void* addr, uint64_t len)
+{
+    return rdnx_syscall2(POSIX_SYS_MUNLOCK, (long)(uintptr_t)addr, (long)len);
+}
+
+static inline long posix_mincore(void* addr, uint64_t len, unsigned char* vec)
+{
+    return rdnx_syscall3(POSIX_SYS_MINCORE, (long)(uintptr_t)addr, (long)len, (long)(uintptr_t)vec);
+}
+
+static inline long posix_mremap(void* old_addr, uint64_t old_len, uint64_t new_len, int flags, void* new_addr)
+{
+    return rdnx_syscall5(POSIX_SYS_MREMAP,
+                         (long)(uintptr_t)old_addr,
+                         (long)old_len,
+                         (long)new_len,
+                         (long)flags,
+                         (long)(uintptr_t)new_addr);
+}
+
 static inline long posix_fork(void)
 {
     return rdnx_syscall0(POSIX_SYS_FORK);
@@ -421,6 +454,65 @@ static inline long posix_kmodls(rodnix_kmod_info_t* entries, uint64_t max_entrie
                          (long)(uintptr_t)out_total);
 }
 
+static inline long posix_bootparams(rodnix_bootparam_info_t* entries, uint64_t max_entries, uint32_t* out_total)
+{
+    return rdnx_syscall3(POSIX_SYS_BOOTPARAMS,
+                         (long)(uintptr_t)entries,
+                         (long)max_entries,
+                         (long)(uintptr_t)out_total);
+}
+
+static inline long posix_kmemstat(rodnix_kmem_cache_info_t* entries, uint64_t max_entries, uint32_t* out_total)
+{
+    return rdnx_syscall3(POSIX_SYS_KMEMSTAT,
+                         (long)(uintptr_t)entries,
+                         (long)max_entries,
+                         (long)(uintptr_t)out_total);
+}
+
+static inline long posix_swapon(const char* path)
+{
+    return rdnx_syscall1(POSIX_SYS_SWAPON, (long)(uintptr_t)path);
+}
+
+static inline long posix_swapoff(const char* path)
+{
+    return rdnx_syscall1(POSIX_SYS_SWAPOFF, (long)(uintptr_t)path);
+}
+
+static inline long posix_swapinfo(rodnix_swap_info_t* entries, uint64_t max_entries, uint32_t* out_total)
+{
+    return rdnx_syscall3(POSIX_SYS_SWAPINFO,
+                         (long)(uintptr_t)entries,
+                         (long)max_entries,
+                         (long)(uintptr_t)out_total);
+}
+
+static inline long posix_shm_open(const char* name, int flags, int mode)
+{
+    return rdnx_syscall3(POSIX_SYS_SHM_OPEN, (long)(uintptr_t)name, flags, mode);
+}
+
+static inline long posix_shm_unlink(const char* name)
+{
+    return rdnx_syscall1(POSIX_SYS_SHM_UNLINK, (long)(uintptr_t)name);
+}
+
+static inline long posix_getrlimit(int resource, void* rlim)
+{
+    return rdnx_syscall2(POSIX_SYS_GETRLIMIT, resource, (long)(uintptr_t)rlim);
+}
+
+static inline long posix_setrlimit(int resource, const void* rlim)
+{
+    return rdnx_syscall2(POSIX_SYS_SETRLIMIT, resource, (long)(uintptr_t)rlim);
+}
+
+static inline long posix_procctl(int idtype, long id, int cmd, void* data)
+{
+    return rdnx_syscall4(POSIX_SYS_PROCCTL, idtype, id, cmd, (long)(uintptr_t)data);
+}
+
 static inline long posix_kmodload(const char* path)
 {
     return rdnx_syscall1(POSIX_SYS_KMODLOAD, (long)(uintptr_t)path);
@@ -431,4 +523,9 @@ static inline long posix_kmodunload(const char* name)
     return rdnx_syscall1(POSIX_SYS_KMODUNLOAD, (long)(uintptr_t)name);
 }
 
+static inline long posix_reboot(int howto)
+{
+    return rdnx_syscall1(POSIX_SYS_REBOOT, (long)howto);
+}
+
 #endif /* _RODNIX_USERLAND_POSIX_SYSCALL_H */
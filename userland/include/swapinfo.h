@@ -0,0 +1,18 @@
+#ifndef _RODNIX_USERLAND_SWAPINFO_H
+#define _RODNIX_USERLAND_SWAPINFO_H
+
+#include <stdint.h>
+
+/* rodnix_swap_info.flags */
+#define RODNIX_SWAP_FILE     0x1u
+#define RODNIX_SWAP_DRAINING 0x2u
+
+typedef struct rodnix_swap_info {
+    char path[64];
+    uint64_t pages;
+    uint64_t used;
+    uint32_t flags;
+    uint32_t reserved;
+} rodnix_swap_info_t;
+
+#endif /* _RODNIX_USERLAND_SWAPINFO_H */
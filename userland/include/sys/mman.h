@@ -22,4 +22,17 @@
 
 #define MAP_FAILED ((void*)-1)
 
+#define MADV_NORMAL     0
+#define MADV_RANDOM     1
+#define MADV_SEQUENTIAL 2
+#define MADV_WILLNEED   3
+#define MADV_DONTNEED   4
+#define MADV_FREE       5
+
+#define MINCORE_INCORE 0x0001
+
+/* mremap() is not in FreeBSD: flags follow Linux */
+#define MREMAP_MAYMOVE 0x0001
+#define MREMAP_FIXED   0x0002
+
 #endif /* _RODNIX_USERLAND_SYS_MMAN_H */
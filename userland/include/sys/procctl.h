@@ -0,0 +1,33 @@
+#ifndef _RODNIX_USERLAND_SYS_PROCCTL_H
+#define _RODNIX_USERLAND_SYS_PROCCTL_H
+
+#include <stdint.h>
+#include <errno.h>
+#include "posix_syscall.h"
+
+/* Only P_PID and the ASLR commands are implemented; numbers follow FreeBSD */
+#define P_PID 0
+
+#define PROC_ASLR_CTL    13
+#define PROC_ASLR_STATUS 14
+
+#define PROC_ASLR_FORCE_ENABLE  1
+#define PROC_ASLR_FORCE_DISABLE 2
+#define PROC_ASLR_NOFORCE       3
+#define PROC_ASLR_ACTIVE        0x80000000
+
+static inline int procctl(int idtype, long id, int cmd, void* data)
+{
+    long r = posix_procctl(idtype, id, cmd, data);
+    if (r < 0) {
+        switch ((int)r) {
+            case -4: errno = ESRCH; break;
+            case -6: errno = EPERM; break;
+            default: errno = EINVAL; break;
+        }
+        return -1;
+    }
+    return 0;
+}
+
+#endif /* _RODNIX_USERLAND_SYS_PROCCTL_H */
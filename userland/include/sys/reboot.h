@@ -0,0 +1,20 @@
+#ifndef _RODNIX_USERLAND_SYS_REBOOT_H
+#define _RODNIX_USERLAND_SYS_REBOOT_H
+
+#include <unistd.h>
+
+/* FreeBSD howto values */
+#define RB_AUTOBOOT 0x0000
+#define RB_NOSYNC   0x0004
+#define RB_HALT     0x0008
+#define RB_POWEROFF 0x4000
+
+/* Returns only on failure */
+static inline int reboot(int howto)
+{
+    long r = posix_reboot(howto);
+    errno = rdnx_errno_from_status(r);
+    return -1;
+}
+
+#endif /* _RODNIX_USERLAND_SYS_REBOOT_H */
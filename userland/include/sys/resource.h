@@ -0,0 +1,60 @@
+#ifndef _RODNIX_USERLAND_SYS_RESOURCE_H
+#define _RODNIX_USERLAND_SYS_RESOURCE_H
+
+#include <stdint.h>
+#include <errno.h>
+#include "posix_syscall.h"
+
+/* Resource numbers follow Linux; both ABIs share them */
+#define RLIMIT_CPU     0
+#define RLIMIT_FSIZE   1
+#define RLIMIT_DATA    2
+#define RLIMIT_STACK   3
+#define RLIMIT_CORE    4
+#define RLIMIT_RSS     5
+#define RLIMIT_NPROC   6
+#define RLIMIT_NOFILE  7
+#define RLIMIT_MEMLOCK 8
+#define RLIMIT_AS      9
+#define RLIMIT_NLIMITS 16
+
+#define RLIM_INFINITY  (~(rlim_t)0)
+
+typedef uint64_t rlim_t;
+
+struct rlimit {
+    rlim_t rlim_cur;
+    rlim_t rlim_max;
+};
+
+static inline int rdnx_rlimit_errno(long r)
+{
+    switch ((int)r) {
+        case -3: return ENOMEM;
+        case -4: return ESRCH;
+        case -6: return EPERM;
+        default: return EINVAL;
+    }
+}
+
+static inline int getrlimit(int resource, struct rlimit* rlim)
+{
+    long r = posix_getrlimit(resource, rlim);
+    if (r < 0) {
+        errno = rdnx_rlimit_errno(r);
+        return -1;
+    }
+    return 0;
+}
+
+static inline int setrlimit(int resource, const struct rlimit* rlim)
+{
+    long r = posix_setrlimit(resource, rlim);
+    if (r < 0) {
+        errno = rdnx_rlimit_errno(r);
+        return -1;
+    }
+    return 0;
+}
+
+#endif /* _RODNIX_USERLAND_SYS_RESOURCE_H */
@@ -13,6 +13,7 @@ typedef void (*sighandler_t)(int);
 #define SIGKILL   9
 #define SIGALRM   14
 #define SIGTERM   15
+#define SIGXCPU   24
 
 #define SIG_DFL ((sighandler_t)0)
 #define SIG_IGN ((sighandler_t)1)
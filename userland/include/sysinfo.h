@@ -3,6 +3,17 @@
 
 #include <stdint.h>
 
+#define RODNIX_PMM_ZONE_MAX  4
+#define RODNIX_PMM_ORDER_MAX 16
+
+typedef struct rodnix_pmm_zone_info {
+    char name[8];
+    uint64_t total_pages;
+    uint64_t free_pages;
+    uint64_t used_pages;
+    uint32_t free_blocks[RODNIX_PMM_ORDER_MAX]; /* Free buddy blocks of 2^i pages */
+} rodnix_pmm_zone_info_t;
+
 typedef struct rodnix_sysinfo {
     char sysname[32];
     char release[32];
@@ -51,6 +62,15 @@ typedef struct rodnix_sysinfo {
 
     uint64_t syscall_int80_count;
     uint64_t syscall_fast_count;
+
+    uint64_t uptime_ns;
+    uint64_t clocksource_hz;
+    char clocksource[16];
+    char timer_mode[16];
+
+    uint32_t pmm_zone_count;
+    uint32_t pmm_order_count;
+    rodnix_pmm_zone_info_t pmm_zones[RODNIX_PMM_ZONE_MAX];
 } rodnix_sysinfo_t;
 
 #endif /* _RODNIX_USERLAND_SYSINFO_H */
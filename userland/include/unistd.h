@@ -42,7 +42,8 @@ static inline int rdnx_open_flags_from_posix(int flags)
         VFS_OPEN_READ   = 1 << 0,
         VFS_OPEN_WRITE  = 1 << 1,
         VFS_OPEN_CREATE = 1 << 2,
-        VFS_OPEN_TRUNC  = 1 << 3
+        VFS_OPEN_TRUNC  = 1 << 3,
+        VFS_OPEN_EXCL   = 1 << 4
     };
 
     int out = 0;
@@ -60,6 +61,9 @@ static inline int rdnx_open_flags_from_posix(int flags)
     if (flags & O_TRUNC) {
         out |= VFS_OPEN_TRUNC;
     }
+    if (flags & O_EXCL) {
+        out |= VFS_OPEN_EXCL;
+    }
     return out;
 }
 
@@ -374,6 +378,81 @@ static inline int munmap(void* addr, size_t len)
     return 0;
 }
 
+static inline int madvise(void* addr, size_t len, int advice)
+{
+    long r = posix_madvise(addr, (uint64_t)len, advice);
+    if (r < 0) {
+        errno = rdnx_errno_from_status(r);
+        return -1;
+    }
+    return 0;
+}
+
+static inline int mlock(const void* addr, size_t len)
+{
+    long r = posix_mlock(addr, (uint64_t)len);
+    if (r < 0) {
+        errno = rdnx_errno_from_status(r);
+        return -1;
+    }
+    return 0;
+}
+
+static inline int munlock(const void* addr, size_t len)
+{
+    long r = posix_munlock(addr, (uint64_t)len);
+    if (r < 0) {
+        errno = rdnx_errno_from_status(r);
+        return -1;
+    }
+    return 0;
+}
+
+/* vec[i] & MINCORE_INCORE: page i of the range is in memory */
+static inline int mincore(void* addr, size_t len, unsigned char* vec)
+{
+    long r = posix_mincore(addr, (uint64_t)len, vec);
+    if (r < 0) {
+        errno = rdnx_errno_from_status(r);
+        return -1;
+    }
+    return 0;
+}
+
+/* new_addr is used with MREMAP_FIXED only */
+static inline void* mremap(void* old_addr, size_t old_len, size_t new_len, int flags, void* new_addr)
+{
+    long r = posix_mremap(old_addr, (uint64_t)old_len, (uint64_t)new_len, flags, new_addr);
+    if (r < 0) {
+        errno = (r == -4) ? EFAULT : rdnx_errno_from_status(r);
+        return MAP_FAILED;
+    }
+    return (void*)(uintptr_t)r;
+}
+
+/* Shared memory object names look like "/name" (up to 30 characters) */
+static inline int shm_open(const char* name, int flags, mode_t mode)
+{
+    long r = posix_shm_open(name, rdnx_open_flags_from_posix(flags), (int)mode);
+    if (r < 0) {
+        errno = ((flags & (O_CREAT | O_EXCL)) == (O_CREAT | O_EXCL) && r == -5)
+                    ? EEXIST
+                    : rdnx_errno_from_status(r);
+        return -1;
+    }
+    return (int)r;
+}
+
+static inline int shm_unlink(const char* name)
+{
+    long r = posix_shm_unlink(name);
+    if (r < 0) {
+        errno = rdnx_errno_from_status(r);
+        return -1;
+    }
+    return 0;
+}
+
 static inline int brk(void* addr)
 {
     long r = posix_brk(addr);